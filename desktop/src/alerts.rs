@@ -0,0 +1,556 @@
+//! 告警管理
+//!
+//! 告警定义保存在配置目录的 `alerts.json`，旧版 `set_price_alert` 写出的价格告警在打开时转换为当前格式，
+//! 触发记录逐行追加到数据目录的 `alert_history.jsonl`。调度器刷新行情和实时行情推送时检查告警，
+//! 触发后自动暂停一段时间，避免价格在目标附近徘徊时反复提醒。
+//! 均线/RSI 穿越、放量和 52 周新高新低基于本地缓存的日线，短窗口涨跌幅基于内存中保留的近期行情
+
+use crate::AppState;
+use alpha_core::errors::{AlphaError, AlphaResult};
+use alpha_core::models::{Alert, AlertCondition, AlertContext, MarketData};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use uuid::Uuid;
+
+/// 告警触发时发送给前端的事件，载荷为 [`AlertTrigger`]
+pub const ALERT_TRIGGERED_EVENT: &str = "alert-triggered";
+
+/// 触发后自动暂停的时长 (分钟)
+const TRIGGER_COOLDOWN_MINUTES: i64 = 60;
+/// 求值时读取的日线数量，覆盖 52 周
+pub const DAILY_HISTORY_BARS: usize = 300;
+/// 近期行情最长保留时间 (分钟)，也是涨跌幅窗口的上限
+const MAX_RECENT_MINUTES: u32 = 24 * 60;
+/// 每个代码最多保留的近期行情条数
+const MAX_RECENT_QUOTES: usize = 10_000;
+
+/// 告警当前状态
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertStatus {
+    Active,
+    Disabled,
+    Snoozed,
+    Expired,
+}
+
+impl AlertStatus {
+    pub fn of(alert: &Alert, now: &DateTime<Utc>) -> Self {
+        if alert.is_expired(now) {
+            Self::Expired
+        } else if !alert.active {
+            Self::Disabled
+        } else if alert.snoozed_until.is_some_and(|until| until > *now) {
+            Self::Snoozed
+        } else {
+            Self::Active
+        }
+    }
+}
+
+/// 告警及其状态，供界面展示
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertSummary {
+    #[serde(flatten)]
+    pub alert: Alert,
+    pub status: AlertStatus,
+}
+
+/// 编辑告警时可修改的字段 (整体替换)
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlertEdit {
+    pub symbol: String,
+    pub condition: AlertCondition,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// 一次告警触发记录
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AlertTrigger {
+    pub alert_id: Uuid,
+    pub symbol: String,
+    pub condition: AlertCondition,
+    /// 触发时的价格
+    pub price: f64,
+    pub message: Option<String>,
+    pub triggered_at: DateTime<Utc>,
+}
+
+/// 旧版 `set_price_alert` 写出的告警，以 `<代码>_<时间戳>` 为键
+#[derive(Debug, Deserialize)]
+struct LegacyAlert {
+    symbol: String,
+    target_price: f64,
+    /// "above" 或 "below"
+    alert_type: String,
+    created_at: DateTime<Utc>,
+    active: bool,
+}
+
+impl LegacyAlert {
+    fn into_alert(self) -> Option<Alert> {
+        let condition = match self.alert_type.as_str() {
+            "above" => AlertCondition::PriceAbove { target: self.target_price },
+            "below" => AlertCondition::PriceBelow { target: self.target_price },
+            _ => return None,
+        };
+        let mut alert = Alert::with_id(Uuid::new_v4(), self.symbol, condition, self.created_at);
+        alert.active = self.active;
+        Some(alert)
+    }
+}
+
+/// 旧版告警文件转换为当前格式，跳过无法识别的告警类型
+fn migrate(legacy: HashMap<String, LegacyAlert>) -> BTreeMap<Uuid, Alert> {
+    legacy.into_iter()
+        .filter_map(|(key, legacy)| {
+            let alert = legacy.into_alert();
+            if alert.is_none() {
+                tracing::warn!("Dropping legacy alert {} with unknown type", key);
+            }
+            alert
+        })
+        .map(|alert| (alert.id, alert))
+        .collect()
+}
+
+#[derive(Debug)]
+pub struct AlertStore {
+    path: PathBuf,
+    history_path: PathBuf,
+    alerts: Mutex<BTreeMap<Uuid, Alert>>,
+    /// 有涨跌幅告警的代码的近期行情，按时间升序
+    recent: Mutex<HashMap<String, VecDeque<MarketData>>>,
+}
+
+impl AlertStore {
+    /// 读取告警定义，文件不存在时为空；旧版格式转换后写回，无法解析的文件改名备份
+    pub fn open(path: PathBuf, history_path: PathBuf) -> AlphaResult<Self> {
+        let mut migrated = false;
+        let alerts = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|e| {
+                AlphaError::StorageError(format!("Failed to read {}", path.display())).caused_by(e)
+            })?;
+            match serde_json::from_str(&content) {
+                Ok(alerts) => alerts,
+                Err(e) => match serde_json::from_str(&content).map(migrate) {
+                    Ok(alerts) => {
+                        tracing::info!("Migrated {} legacy alerts in {}", alerts.len(), path.display());
+                        migrated = true;
+                        alerts
+                    }
+                    // 无法解析时备份原文件后从空白开始，不影响应用启动
+                    Err(_) => {
+                        let backup = path.with_extension(format!("json.corrupt-{}", Utc::now().format("%Y%m%d%H%M%S")));
+                        fs::rename(&path, &backup).map_err(|e| {
+                            AlphaError::StorageError(format!("Failed to back up {}", path.display())).caused_by(e)
+                        })?;
+                        tracing::warn!("Malformed alert file {} moved to {}: {}", path.display(), backup.display(), e);
+                        BTreeMap::new()
+                    }
+                },
+            }
+        } else {
+            BTreeMap::new()
+        };
+
+        let store = Self { path, history_path, alerts: Mutex::new(alerts), recent: Mutex::new(HashMap::new()) };
+        if migrated {
+            store.save(&store.lock())?;
+        }
+        Ok(store)
+    }
+
+    /// 全部告警，按创建时间升序
+    pub fn list(&self, now: &DateTime<Utc>) -> Vec<AlertSummary> {
+        let mut alerts: Vec<AlertSummary> = self.lock().values()
+            .map(|alert| AlertSummary { status: AlertStatus::of(alert, now), alert: alert.clone() })
+            .collect();
+        alerts.sort_by_key(|summary| summary.alert.created_at);
+        alerts
+    }
+
+    pub fn create(&self, alert: Alert) -> AlphaResult<Alert> {
+        validate(&alert.symbol, &alert.condition, alert.expires_at)?;
+        self.modify(|alerts| {
+            alerts.insert(alert.id, alert.clone());
+            Ok(alert)
+        })
+    }
+
+    /// 修改告警定义，条件变化时清除上次触发后的暂停
+    pub fn edit(&self, id: Uuid, edit: AlertEdit) -> AlphaResult<Alert> {
+        validate(&edit.symbol, &edit.condition, edit.expires_at)?;
+        self.update(id, |alert| {
+            if alert.condition != edit.condition || alert.symbol != edit.symbol {
+                alert.snoozed_until = None;
+            }
+            alert.symbol = edit.symbol;
+            alert.condition = edit.condition;
+            alert.message = edit.message;
+            alert.expires_at = edit.expires_at;
+        })
+    }
+
+    pub fn set_active(&self, id: Uuid, active: bool) -> AlphaResult<Alert> {
+        self.update(id, |alert| alert.active = active)
+    }
+
+    /// 暂停提醒至 `until`，None 表示立即恢复
+    pub fn snooze(&self, id: Uuid, until: Option<DateTime<Utc>>) -> AlphaResult<Alert> {
+        self.update(id, |alert| alert.snoozed_until = until)
+    }
+
+    /// 删除告警，不存在时返回 false (触发记录保留)
+    pub fn remove(&self, id: Uuid) -> AlphaResult<bool> {
+        self.modify(|alerts| Ok(alerts.remove(&id).is_some()))
+    }
+
+    /// 用最新行情检查全部告警，记录并返回本次触发的告警
+    ///
+    /// `daily` 按代码读取日线，只在有告警需要时调用
+    pub fn evaluate(
+        &self,
+        quotes: &[MarketData],
+        now: DateTime<Utc>,
+        daily: impl Fn(&str) -> Vec<MarketData>,
+    ) -> AlphaResult<Vec<AlertTrigger>> {
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        for buffer in recent.values_mut() {
+            buffer.make_contiguous();
+        }
+        let mut history: HashMap<&str, Vec<MarketData>> = HashMap::new();
+
+        let triggers = self.modify(|alerts| {
+            let mut triggers = Vec::new();
+            for alert in alerts.values_mut().filter(|alert| alert.is_armed(&now)) {
+                if alert.condition.needs_daily() && !history.contains_key(alert.symbol.as_str()) {
+                    if let Some(quote) = quotes.iter().find(|quote| quote.symbol == alert.symbol) {
+                        history.insert(&quote.symbol, daily(&quote.symbol));
+                    }
+                }
+                let context = AlertContext {
+                    daily: history.get(alert.symbol.as_str()).map_or(&[], Vec::as_slice),
+                    recent: recent.get(&alert.symbol).map_or(&[], |buffer| buffer.as_slices().0),
+                };
+                let Some(quote) = quotes.iter().find(|quote| alert.check(quote, &context)) else {
+                    continue;
+                };
+                alert.snoozed_until = Some(now + Duration::minutes(TRIGGER_COOLDOWN_MINUTES));
+                triggers.push(AlertTrigger {
+                    alert_id: alert.id,
+                    symbol: alert.symbol.clone(),
+                    condition: alert.condition.clone(),
+                    price: quote.price,
+                    message: alert.message.clone(),
+                    triggered_at: now,
+                });
+            }
+            Ok(triggers)
+        })?;
+        self.record_recent(&mut recent, quotes);
+        drop(recent);
+
+        if !triggers.is_empty() {
+            self.append_history(&triggers)?;
+        }
+        Ok(triggers)
+    }
+
+    /// 最近的触发记录，按时间倒序，最多 `limit` 条；跳过无法解析的行
+    pub fn history(&self, limit: usize) -> AlphaResult<Vec<AlertTrigger>> {
+        if !self.history_path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.history_path).map_err(|e| {
+            AlphaError::StorageError(format!("Failed to read {}", self.history_path.display())).caused_by(e)
+        })?;
+        Ok(content.lines()
+            .rev()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .take(limit)
+            .collect())
+    }
+
+    /// 保留有涨跌幅告警的代码的行情，按最长窗口裁剪
+    fn record_recent(&self, recent: &mut HashMap<String, VecDeque<MarketData>>, quotes: &[MarketData]) {
+        let mut windows: HashMap<&str, u32> = HashMap::new();
+        let alerts = self.lock();
+        for alert in alerts.values().filter(|alert| alert.active) {
+            if let Some(minutes) = alert.condition.recent_window_minutes() {
+                let window = windows.entry(alert.symbol.as_str()).or_default();
+                *window = (*window).max(minutes.min(MAX_RECENT_MINUTES));
+            }
+        }
+
+        recent.retain(|symbol, _| windows.contains_key(symbol.as_str()));
+        for quote in quotes {
+            let Some(&minutes) = windows.get(quote.symbol.as_str()) else {
+                continue;
+            };
+            let buffer = recent.entry(quote.symbol.clone()).or_default();
+            if buffer.back().is_some_and(|last| last.timestamp > quote.timestamp) {
+                continue;
+            }
+            buffer.push_back(quote.clone());
+            let start = quote.timestamp - Duration::minutes(i64::from(minutes));
+            while buffer.front().is_some_and(|first| first.timestamp < start) || buffer.len() > MAX_RECENT_QUOTES {
+                buffer.pop_front();
+            }
+        }
+    }
+
+    fn append_history(&self, triggers: &[AlertTrigger]) -> AlphaResult<()> {
+        let mut lines = String::new();
+        for trigger in triggers {
+            let line = serde_json::to_string(trigger)
+                .map_err(|e| AlphaError::SerializationError("Failed to serialize alert trigger".to_string()).caused_by(e))?;
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.history_path)
+            .and_then(|mut file| file.write_all(lines.as_bytes()))
+            .map_err(|e| AlphaError::StorageError(format!("Failed to write {}", self.history_path.display())).caused_by(e))
+    }
+
+    fn update(&self, id: Uuid, f: impl FnOnce(&mut Alert)) -> AlphaResult<Alert> {
+        self.modify(|alerts| {
+            let alert = alerts.get_mut(&id)
+                .ok_or_else(|| AlphaError::not_found(format!("Alert {}", id)))?;
+            f(alert);
+            Ok(alert.clone())
+        })
+    }
+
+    /// 在副本上修改并写盘，成功后才替换内存中的告警
+    fn modify<T>(&self, f: impl FnOnce(&mut BTreeMap<Uuid, Alert>) -> AlphaResult<T>) -> AlphaResult<T> {
+        let mut alerts = self.lock();
+        let mut updated = alerts.clone();
+        let result = f(&mut updated)?;
+        if updated != *alerts {
+            self.save(&updated)?;
+            *alerts = updated;
+        }
+        Ok(result)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<Uuid, Alert>> {
+        self.alerts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 先写临时文件再替换，避免写入中断损坏已有告警
+    fn save(&self, alerts: &BTreeMap<Uuid, Alert>) -> AlphaResult<()> {
+        let content = serde_json::to_string_pretty(alerts)
+            .map_err(|e| AlphaError::SerializationError("Failed to serialize alerts".to_string()).caused_by(e))?;
+        let temp = self.path.with_extension("json.tmp");
+        fs::write(&temp, content)
+            .and_then(|_| fs::rename(&temp, &self.path))
+            .map_err(|e| AlphaError::StorageError(format!("Failed to write {}", self.path.display())).caused_by(e))
+    }
+}
+
+fn validate(symbol: &str, condition: &AlertCondition, expires_at: Option<DateTime<Utc>>) -> AlphaResult<()> {
+    if symbol.trim().is_empty() {
+        return Err(AlphaError::invalid_input("Symbol cannot be empty"));
+    }
+    let valid = match condition {
+        AlertCondition::PriceAbove { target } | AlertCondition::PriceBelow { target } => {
+            target.is_finite() && *target > 0.0
+        }
+        AlertCondition::PercentChange { percent, window_minutes } => {
+            percent.is_finite() && *percent != 0.0 && (1..=MAX_RECENT_MINUTES).contains(window_minutes)
+        }
+        AlertCondition::PriceCrossesAverage { period, .. } => (2..DAILY_HISTORY_BARS).contains(period),
+        AlertCondition::RsiCrosses { period, level, .. } => {
+            (2..DAILY_HISTORY_BARS).contains(period) && *level > 0.0 && *level < 100.0
+        }
+        AlertCondition::VolumeSpike { multiple, lookback } => {
+            multiple.is_finite() && *multiple > 1.0 && (1..DAILY_HISTORY_BARS).contains(lookback)
+        }
+        AlertCondition::High52Week | AlertCondition::Low52Week => true,
+    };
+    if !valid {
+        return Err(AlphaError::invalid_input(format!("Invalid alert condition: {:?}", condition)));
+    }
+    if expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+        return Err(AlphaError::invalid_input("Expiry must be in the future"));
+    }
+    Ok(())
+}
+
+/// 用刷新或推送的行情检查告警并通知前端，托盘中暂停告警时跳过
+pub fn check_quotes(app: &AppHandle, quotes: &[MarketData]) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    if state.alerts_paused.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let daily = |symbol: &str| {
+        state.cache.load(symbol, Duration::days(1), DAILY_HISTORY_BARS).unwrap_or_else(|e| {
+            tracing::warn!("Failed to load daily candles for {}: {}", symbol, e.report());
+            Vec::new()
+        })
+    };
+    match state.alerts.evaluate(quotes, Utc::now(), daily) {
+        Ok(triggers) => {
+            for trigger in triggers {
+                if let Err(e) = app.emit_all(ALERT_TRIGGERED_EVENT, &trigger) {
+                    tracing::warn!("Failed to emit {}: {}", ALERT_TRIGGERED_EVENT, e);
+                }
+            }
+        }
+        Err(e) => tracing::warn!("Failed to evaluate alerts: {}", e.report()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(name: &str) -> (AlertStore, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("alpha-alerts-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let store = AlertStore::open(dir.join("alerts.json"), dir.join("alert_history.jsonl")).unwrap();
+        (store, dir)
+    }
+
+    fn quote(symbol: &str, price: f64) -> MarketData {
+        MarketData::new(symbol.to_string(), price, 100)
+    }
+
+    fn no_daily(_: &str) -> Vec<MarketData> {
+        Vec::new()
+    }
+
+    #[test]
+    fn test_lifecycle_and_reload() {
+        let (store, dir) = store("lifecycle");
+        let alert = store.create(Alert::new("AAPL".to_string(), AlertCondition::PriceAbove { target: 200.0 })).unwrap();
+        assert!(store.create(Alert::new("AAPL".to_string(), AlertCondition::PriceBelow { target: -1.0 })).is_err());
+
+        let edit = AlertEdit {
+            symbol: "AAPL".to_string(),
+            condition: AlertCondition::PriceBelow { target: 150.0 },
+            message: Some("buy the dip".to_string()),
+            expires_at: Some(Utc::now() + Duration::days(1)),
+        };
+        store.edit(alert.id, edit).unwrap();
+        store.set_active(alert.id, false).unwrap();
+        assert!(store.set_active(Uuid::new_v4(), true).is_err());
+
+        let reloaded = AlertStore::open(dir.join("alerts.json"), dir.join("alert_history.jsonl")).unwrap();
+        let alerts = reloaded.list(&Utc::now());
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].status, AlertStatus::Disabled);
+        assert_eq!(alerts[0].alert.message.as_deref(), Some("buy the dip"));
+        assert_eq!(AlertStatus::of(&alerts[0].alert, &(Utc::now() + Duration::days(2))), AlertStatus::Expired);
+
+        assert!(reloaded.remove(alert.id).unwrap());
+        assert!(!reloaded.remove(alert.id).unwrap());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_migrates_legacy_file() {
+        let dir = std::env::temp_dir().join(format!("alpha-alerts-legacy-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("alerts.json");
+        fs::write(&path, r#"{
+            "AAPL_1700000000": {"symbol": "AAPL", "target_price": 200.0, "alert_type": "above", "created_at": "2023-11-14T22:13:20Z", "active": true},
+            "MSFT_1700000100": {"symbol": "MSFT", "target_price": 300.0, "alert_type": "below", "created_at": "2023-11-14T22:15:00Z", "active": false}
+        }"#).unwrap();
+
+        let store = AlertStore::open(path.clone(), dir.join("alert_history.jsonl")).unwrap();
+        let alerts = store.list(&Utc::now());
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0].alert.condition, AlertCondition::PriceAbove { target: 200.0 });
+        assert_eq!(alerts[0].alert.created_at.timestamp(), 1_700_000_000);
+        assert_eq!(alerts[1].alert.condition, AlertCondition::PriceBelow { target: 300.0 });
+        assert_eq!(alerts[1].status, AlertStatus::Disabled);
+
+        // 转换结果已写回，再次打开时按当前格式读取
+        assert!(fs::read_to_string(&path).unwrap().contains("\"condition\""));
+        let reloaded = AlertStore::open(path.clone(), dir.join("alert_history.jsonl")).unwrap();
+        assert_eq!(reloaded.list(&Utc::now()).len(), 2);
+
+        // 无法解析的文件备份后从空白开始
+        fs::write(&path, "{\"AAPL\": 1").unwrap();
+        let store = AlertStore::open(path.clone(), dir.join("alert_history.jsonl")).unwrap();
+        assert!(store.list(&Utc::now()).is_empty());
+        assert!(!path.exists());
+        let backups: Vec<_> = fs::read_dir(&dir).unwrap()
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name.starts_with("alerts.json.corrupt-"))
+            .collect();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(fs::read_to_string(dir.join(&backups[0])).unwrap(), "{\"AAPL\": 1");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_evaluate_snoozes_and_records_history() {
+        let (store, dir) = store("evaluate");
+        let alert = store.create(Alert::new("AAPL".to_string(), AlertCondition::PriceAbove { target: 200.0 })).unwrap();
+        let now = Utc::now();
+
+        assert!(store.evaluate(&[quote("AAPL", 199.0)], now, no_daily).unwrap().is_empty());
+        let triggers = store.evaluate(&[quote("MSFT", 300.0), quote("AAPL", 201.0)], now, no_daily).unwrap();
+        assert_eq!(triggers.len(), 1);
+        assert_eq!(triggers[0].price, 201.0);
+
+        // 冷却期内不重复触发，手动恢复后再次触发
+        assert!(store.evaluate(&[quote("AAPL", 202.0)], now, no_daily).unwrap().is_empty());
+        assert_eq!(store.list(&now)[0].status, AlertStatus::Snoozed);
+        store.snooze(alert.id, None).unwrap();
+        assert_eq!(store.evaluate(&[quote("AAPL", 203.0)], now, no_daily).unwrap().len(), 1);
+
+        let history = store.history(10).unwrap();
+        assert_eq!(history.iter().map(|t| t.price).collect::<Vec<_>>(), vec![203.0, 201.0]);
+        assert_eq!(store.history(1).unwrap().len(), 1);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_history_conditions_use_daily_and_recent_quotes() {
+        let (store, dir) = store("history");
+        let now = Utc::now();
+        let at = |minutes: i64, price: f64| MarketData { timestamp: now - Duration::minutes(minutes), ..quote("AAPL", price) };
+        store.create(Alert::new("AAPL".to_string(), AlertCondition::PercentChange { percent: 5.0, window_minutes: 30 })).unwrap();
+        store.create(Alert::new("MSFT".to_string(), AlertCondition::High52Week)).unwrap();
+        assert!(store.create(Alert::new("AAPL".to_string(), AlertCondition::VolumeSpike { multiple: 0.5, lookback: 20 })).is_err());
+
+        // 近期行情逐步积累，窗口内涨幅达到 5% 时触发
+        assert!(store.evaluate(&[at(20, 100.0)], now, no_daily).unwrap().is_empty());
+        assert!(store.evaluate(&[at(10, 103.0)], now, no_daily).unwrap().is_empty());
+        assert_eq!(store.evaluate(&[at(0, 105.0)], now, no_daily).unwrap().len(), 1);
+
+        let loaded = std::cell::Cell::new(0);
+        let daily = |symbol: &str| {
+            loaded.set(loaded.get() + 1);
+            vec![MarketData { timestamp: now - Duration::days(1), ..quote(symbol, 300.0) }]
+        };
+        assert!(store.evaluate(&[quote("MSFT", 299.0)], now, daily).unwrap().is_empty());
+        assert_eq!(store.evaluate(&[quote("MSFT", 301.0), quote("AAPL", 1.0)], now, daily).unwrap().len(), 1);
+        assert_eq!(loaded.get(), 2);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
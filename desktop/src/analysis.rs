@@ -0,0 +1,183 @@
+//! 自选列表批量分析
+//!
+//! 同时分析列表中的多个代码 (限制并发数避免数据源限流)，每完成一个代码回调一次进度，
+//! 全部完成后按信号方向和置信度排序，供总览页一次性展示
+
+use crate::cache::{self, CandleCache};
+use crate::DEFAULT_CANDLE_LIMIT;
+use alpha_core::analytics::AnalysisEngine;
+use alpha_core::models::{AnalysisResult, SignalType};
+use alpha_providers::DataProvider;
+use chrono::{DateTime, Duration, Utc};
+use futures_util::stream::{self, StreamExt};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// 每个代码分析完成后发送的事件，载荷为 [`AnalysisProgress`]
+pub const ANALYSIS_PROGRESS_EVENT: &str = "analysis-progress";
+
+/// 默认同时分析的代码数
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// 单个代码的分析进度
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalysisProgress {
+    pub watchlist: String,
+    pub symbol: String,
+    /// 已完成的代码数 (含失败)
+    pub completed: usize,
+    pub total: usize,
+    pub result: Option<AnalysisResult>,
+    pub error: Option<String>,
+}
+
+/// 排名中的一项
+#[derive(Debug, Clone, Serialize)]
+pub struct RankedSymbol {
+    pub symbol: String,
+    pub recommendation: SignalType,
+    pub confidence: f64,
+    /// 排序得分：买入为正、卖出为负，绝对值为置信度
+    pub score: f64,
+    pub volatility: f64,
+}
+
+/// 批量分析结果
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchlistSummary {
+    pub watchlist: String,
+    /// 按得分降序
+    pub ranked: Vec<RankedSymbol>,
+    /// 分析失败的代码及原因
+    pub errors: BTreeMap<String, String>,
+    pub analyzed_at: DateTime<Utc>,
+}
+
+/// 排序得分
+pub fn score(result: &AnalysisResult) -> f64 {
+    match result.recommendation {
+        SignalType::Buy => result.confidence,
+        SignalType::Sell => -result.confidence,
+        SignalType::Hold | SignalType::None => 0.0,
+    }
+}
+
+/// 分析一个代码，K 线优先取缓存
+async fn analyze_one(
+    engine: &AnalysisEngine,
+    provider: &dyn DataProvider,
+    cache: &CandleCache,
+    symbol: &str,
+    timeframe: Duration,
+) -> Result<AnalysisResult, String> {
+    let data = cache::cached_candles(cache, provider, symbol, timeframe, DEFAULT_CANDLE_LIMIT)
+        .await
+        .map_err(|e| e.report())?;
+    engine.analyze_symbol(&data, None).await.map_err(|e| e.report())
+}
+
+/// 以最多 `concurrency` 个并发分析全部代码，每完成一个调用一次 `on_progress`；单个代码失败不影响其他代码
+#[allow(clippy::too_many_arguments)]
+pub async fn analyze_watchlist(
+    engine: &AnalysisEngine,
+    provider: &dyn DataProvider,
+    cache: &CandleCache,
+    watchlist: &str,
+    symbols: &[String],
+    timeframe: Duration,
+    concurrency: usize,
+    mut on_progress: impl FnMut(&AnalysisProgress),
+) -> WatchlistSummary {
+    let mut summary = WatchlistSummary {
+        watchlist: watchlist.to_string(),
+        ranked: Vec::new(),
+        errors: BTreeMap::new(),
+        analyzed_at: Utc::now(),
+    };
+
+    let mut results = stream::iter(symbols)
+        .map(|symbol| async move { (symbol, analyze_one(engine, provider, cache, symbol, timeframe).await) })
+        .buffer_unordered(concurrency.max(1));
+
+    let mut completed = 0;
+    while let Some((symbol, result)) = results.next().await {
+        completed += 1;
+        let progress = AnalysisProgress {
+            watchlist: watchlist.to_string(),
+            symbol: symbol.clone(),
+            completed,
+            total: symbols.len(),
+            result: result.as_ref().ok().cloned(),
+            error: result.as_ref().err().cloned(),
+        };
+        on_progress(&progress);
+
+        match result {
+            Ok(result) => summary.ranked.push(RankedSymbol {
+                symbol: symbol.clone(),
+                recommendation: result.recommendation.clone(),
+                confidence: result.confidence,
+                score: score(&result),
+                volatility: result.risk_metrics.volatility,
+            }),
+            Err(error) => {
+                summary.errors.insert(symbol.clone(), error);
+            }
+        }
+    }
+
+    summary.ranked.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.symbol.cmp(&b.symbol)));
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alpha_core::errors::{AlphaError, AlphaResult};
+    use alpha_core::models::MarketData;
+    use alpha_providers::{SimulatedProvider, SymbolMatch};
+
+    /// 对 `BAD` 报错的数据源
+    struct Partial;
+
+    #[async_trait::async_trait]
+    impl DataProvider for Partial {
+        fn name(&self) -> &str {
+            "partial"
+        }
+
+        async fn candles(&self, symbol: &str, timeframe: Duration, limit: usize) -> AlphaResult<Vec<MarketData>> {
+            if symbol == "BAD" {
+                return Err(AlphaError::not_found("BAD"));
+            }
+            SimulatedProvider.candles(symbol, timeframe, limit).await
+        }
+
+        async fn quote(&self, symbol: &str) -> AlphaResult<MarketData> {
+            SimulatedProvider.quote(symbol).await
+        }
+
+        async fn search(&self, query: &str) -> AlphaResult<Vec<SymbolMatch>> {
+            SimulatedProvider.search(query).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reports_progress_and_ranks() {
+        let cache = CandleCache::open_in_memory().unwrap();
+        let symbols: Vec<String> = ["AAPL", "BAD", "MSFT", "NVDA"].iter().map(|s| s.to_string()).collect();
+        let mut progress = Vec::new();
+
+        let summary = analyze_watchlist(
+            &AnalysisEngine::new(), &Partial, &cache, "tech", &symbols, Duration::days(1), 2,
+            |p| progress.push((p.completed, p.total, p.error.is_some())),
+        ).await;
+
+        assert_eq!(progress.iter().map(|p| p.0).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert!(progress.iter().all(|p| p.1 == 4));
+        assert_eq!(progress.iter().filter(|p| p.2).count(), 1);
+        assert_eq!(summary.errors.keys().collect::<Vec<_>>(), vec!["BAD"]);
+        assert_eq!(summary.ranked.len(), 3);
+        assert!(summary.ranked.windows(2).all(|w| w[0].score >= w[1].score));
+    }
+}
@@ -0,0 +1,291 @@
+//! 应用数据备份与恢复
+//!
+//! 将设置 (含自选股与刷新任务)、告警、图表窗口、用户脚本、持仓流水、告警记录和本地行情缓存打包为一个 zip，
+//! 附带 `manifest.json` 记录备份格式版本和每个文件的摘要。恢复时先全部校验再写入，
+//! 任一文件损坏都不会改动本地数据
+
+use crate::cache::CandleCache;
+use alpha_core::errors::{AlphaError, AlphaResult};
+use alpha_core::utils::checksum::{Checksum, ChecksumAlgorithm};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// 当前备份格式版本
+pub const BACKUP_SCHEMA_VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const CACHE_ENTRY: &str = "data/market_data.db";
+const CACHE_FILE: &str = "market_data.db";
+
+/// 备份的 JSON 文件：(归档内名称, 是否在配置目录, 文件名)
+const FILES: &[(&str, bool, &str)] = &[
+    ("config/config.json", true, "config.json"),
+    ("config/alerts.json", true, "alerts.json"),
+    ("config/chart_windows.json", true, "chart_windows.json"),
+    ("config/scripts.json", true, "scripts.json"),
+    ("config/workspaces.json", true, "workspaces.json"),
+    ("data/portfolio.json", false, "portfolio.json"),
+    ("data/alert_history.jsonl", false, "alert_history.jsonl"),
+];
+
+/// 备份清单
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackupManifest {
+    pub schema_version: u32,
+    /// 生成备份的应用版本
+    pub app_version: String,
+    pub created_at: DateTime<Utc>,
+    pub entries: Vec<BackupEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackupEntry {
+    /// 归档内名称
+    pub name: String,
+    pub size: u64,
+    pub checksum: Checksum,
+}
+
+/// 备份涉及的本地目录
+#[derive(Debug, Clone)]
+pub struct BackupDirs {
+    pub config_dir: PathBuf,
+    pub data_dir: PathBuf,
+}
+
+impl BackupDirs {
+    fn path(&self, in_config: bool, file: &str) -> PathBuf {
+        if in_config { self.config_dir.join(file) } else { self.data_dir.join(file) }
+    }
+}
+
+/// 生成备份到 `dest`，本地不存在的文件跳过
+pub fn export_backup(cache: &CandleCache, dirs: &BackupDirs, dest: &Path) -> AlphaResult<BackupManifest> {
+    let mut contents = Vec::new();
+    for &(name, in_config, file) in FILES {
+        let path = dirs.path(in_config, file);
+        if path.exists() {
+            contents.push((name, read(&path)?));
+        }
+    }
+
+    // 缓存正在使用，通过 SQLite 生成一致的快照而不是直接复制文件
+    let snapshot = dirs.data_dir.join(format!("backup-{}.db", Uuid::new_v4().simple()));
+    let database = cache.snapshot(&snapshot).and_then(|_| read(&snapshot));
+    fs::remove_file(&snapshot).ok();
+    contents.push((CACHE_ENTRY, database?));
+
+    let manifest = BackupManifest {
+        schema_version: BACKUP_SCHEMA_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: Utc::now(),
+        entries: contents.iter()
+            .map(|(name, bytes)| BackupEntry {
+                name: name.to_string(),
+                size: bytes.len() as u64,
+                checksum: Checksum::of_bytes(ChecksumAlgorithm::Sha256, bytes),
+            })
+            .collect(),
+    };
+    let manifest_json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| AlphaError::SerializationError("Failed to serialize backup manifest".to_string()).caused_by(e))?;
+
+    // 先写临时文件，完整写入后再改名
+    let temp = dest.with_extension("zip.tmp");
+    let result = File::create(&temp)
+        .map_err(|e| storage_error("create", &temp, e))
+        .and_then(|file| {
+            let mut writer = ZipWriter::new(file);
+            let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+            for (name, bytes) in contents.iter().map(|(name, bytes)| (*name, bytes)).chain([(MANIFEST, &manifest_json)]) {
+                writer.start_file(name, options).map_err(zip_error)?;
+                writer.write_all(bytes).map_err(|e| storage_error("write", &temp, e))?;
+            }
+            writer.finish().map_err(zip_error)?;
+            fs::rename(&temp, dest).map_err(|e| storage_error("write", dest, e))
+        });
+    if result.is_err() {
+        fs::remove_file(&temp).ok();
+    }
+    result.map(|_| manifest)
+}
+
+/// 从备份恢复：校验全部文件后替换缓存内容并覆盖 JSON 文件，备份中没有的文件从本地删除
+///
+/// 内存中的状态不会更新，调用方恢复后需要重启应用
+pub fn import_backup(cache: &CandleCache, dirs: &BackupDirs, archive: &Path) -> AlphaResult<BackupManifest> {
+    let mut zip = open_archive(archive)?;
+    let manifest = manifest(&mut zip)?;
+    if manifest.schema_version > BACKUP_SCHEMA_VERSION {
+        return Err(AlphaError::invalid_input(format!(
+            "Backup schema version {} is newer than supported version {}",
+            manifest.schema_version, BACKUP_SCHEMA_VERSION
+        )));
+    }
+
+    let mut contents = Vec::new();
+    for entry in &manifest.entries {
+        let bytes = read_entry(&mut zip, &entry.name)?;
+        if bytes.len() as u64 != entry.size {
+            return Err(AlphaError::DataCorrupted(format!("{} is truncated", entry.name)));
+        }
+        entry.checksum.verify_bytes(&bytes).map_err(|e| e.context(entry.name.clone()))?;
+        if entry.name != CACHE_ENTRY {
+            if !FILES.iter().any(|(name, _, _)| *name == entry.name) {
+                tracing::warn!("Skipping unknown backup entry {}", entry.name);
+                continue;
+            }
+            // JSON Lines 逐行校验
+            let valid = if entry.name.ends_with(".jsonl") {
+                bytes.split(|b| *b == b'\n')
+                    .filter(|line| !line.is_empty())
+                    .all(|line| serde_json::from_slice::<serde_json::Value>(line).is_ok())
+            } else {
+                serde_json::from_slice::<serde_json::Value>(&bytes).is_ok()
+            };
+            if !valid {
+                return Err(AlphaError::DataCorrupted(format!("{} is not valid JSON", entry.name)));
+            }
+        }
+        contents.push((entry.name.as_str(), bytes));
+    }
+
+    if let Some((_, database)) = contents.iter().find(|(name, _)| *name == CACHE_ENTRY) {
+        let staged = dirs.data_dir.join(format!("restore-{}.db", Uuid::new_v4().simple()));
+        let result = fs::write(&staged, database)
+            .map_err(|e| storage_error("write", &staged, e))
+            .and_then(|_| cache.replace_from(&staged));
+        fs::remove_file(&staged).ok();
+        result?;
+    }
+
+    for &(name, in_config, file) in FILES {
+        let path = dirs.path(in_config, file);
+        match contents.iter().find(|(entry, _)| *entry == name) {
+            Some((_, bytes)) => write_atomic(&path, bytes)?,
+            None if path.exists() => fs::remove_file(&path).map_err(|e| storage_error("remove", &path, e))?,
+            None => {}
+        }
+    }
+    Ok(manifest)
+}
+
+/// 默认备份文件名
+pub fn backup_file_name(now: &DateTime<Utc>) -> String {
+    format!("alpha-backup-{}.zip", now.format("%Y%m%d_%H%M%S"))
+}
+
+fn open_archive(path: &Path) -> AlphaResult<ZipArchive<File>> {
+    let file = File::open(path).map_err(|e| storage_error("open", path, e))?;
+    ZipArchive::new(file).map_err(zip_error)
+}
+
+fn manifest(zip: &mut ZipArchive<File>) -> AlphaResult<BackupManifest> {
+    let bytes = read_entry(zip, MANIFEST)?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| AlphaError::DataCorrupted("Malformed backup manifest".to_string()).caused_by(e))
+}
+
+fn read_entry(zip: &mut ZipArchive<File>, name: &str) -> AlphaResult<Vec<u8>> {
+    let mut entry = zip.by_name(name)
+        .map_err(|e| AlphaError::DataCorrupted(format!("Backup is missing {}", name)).caused_by(e))?;
+    let mut bytes = Vec::new();
+    entry.read_to_end(&mut bytes)
+        .map_err(|e| AlphaError::DataCorrupted(format!("Failed to extract {}", name)).caused_by(e))?;
+    Ok(bytes)
+}
+
+fn read(path: &Path) -> AlphaResult<Vec<u8>> {
+    fs::read(path).map_err(|e| storage_error("read", path, e))
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> AlphaResult<()> {
+    let temp = path.with_extension("restore.tmp");
+    fs::write(&temp, bytes)
+        .and_then(|_| fs::rename(&temp, path))
+        .map_err(|e| storage_error("write", path, e))
+}
+
+fn storage_error(action: &str, path: &Path, err: std::io::Error) -> AlphaError {
+    AlphaError::StorageError(format!("Failed to {} {}", action, path.display())).caused_by(err)
+}
+
+fn zip_error(err: zip::result::ZipError) -> AlphaError {
+    AlphaError::DataCorrupted("Invalid backup archive".to_string()).caused_by(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alpha_core::models::MarketData;
+    use chrono::Duration;
+
+    fn dirs(name: &str) -> BackupDirs {
+        let root = std::env::temp_dir().join(format!("alpha-backup-{}-{}", name, std::process::id()));
+        let dirs = BackupDirs { config_dir: root.join("config"), data_dir: root.join("data") };
+        fs::create_dir_all(&dirs.config_dir).unwrap();
+        fs::create_dir_all(&dirs.data_dir).unwrap();
+        dirs
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dirs = dirs("round-trip");
+        let cache = CandleCache::open(&dirs.data_dir.join(CACHE_FILE)).unwrap();
+        cache.store("AAPL", Duration::days(1), &[MarketData::new("AAPL".to_string(), 190.0, 10)]).unwrap();
+        fs::write(dirs.config_dir.join("config.json"), r#"{"theme": "dark"}"#).unwrap();
+        fs::write(dirs.data_dir.join("portfolio.json"), "[]").unwrap();
+
+        let archive = dirs.data_dir.join(backup_file_name(&Utc::now()));
+        let manifest = export_backup(&cache, &dirs, &archive).unwrap();
+        assert_eq!(manifest.entries.len(), 3);
+        assert_eq!(super::manifest(&mut open_archive(&archive).unwrap()).unwrap(), manifest);
+
+        // 备份后的改动在恢复后消失
+        cache.store("MSFT", Duration::days(1), &[MarketData::new("MSFT".to_string(), 410.0, 10)]).unwrap();
+        fs::write(dirs.config_dir.join("config.json"), r#"{"theme": "light"}"#).unwrap();
+        fs::write(dirs.config_dir.join("alerts.json"), "{}").unwrap();
+
+        import_backup(&cache, &dirs, &archive).unwrap();
+        assert_eq!(fs::read_to_string(dirs.config_dir.join("config.json")).unwrap(), r#"{"theme": "dark"}"#);
+        assert!(!dirs.config_dir.join("alerts.json").exists());
+        assert!(cache.load("MSFT", Duration::days(1), 10).unwrap().is_empty());
+        assert_eq!(cache.load("AAPL", Duration::days(1), 10).unwrap().len(), 1);
+
+        fs::remove_dir_all(dirs.config_dir.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn test_rejects_corrupted_backup() {
+        let dirs = dirs("corrupted");
+        let cache = CandleCache::open_in_memory().unwrap();
+        fs::write(dirs.config_dir.join("config.json"), r#"{"theme": "dark"}"#).unwrap();
+        let archive = dirs.data_dir.join("backup.zip");
+        let mut manifest = export_backup(&cache, &dirs, &archive).unwrap();
+
+        // 清单摘要与内容不符时不改动本地文件
+        manifest.entries[0].checksum = Checksum::of_bytes(ChecksumAlgorithm::Sha256, b"tampered");
+        let tampered = dirs.data_dir.join("tampered.zip");
+        let mut source = open_archive(&archive).unwrap();
+        let mut writer = ZipWriter::new(File::create(&tampered).unwrap());
+        for name in ["config/config.json", CACHE_ENTRY] {
+            writer.start_file(name, SimpleFileOptions::default()).unwrap();
+            writer.write_all(&read_entry(&mut source, name).unwrap()).unwrap();
+        }
+        writer.start_file(MANIFEST, SimpleFileOptions::default()).unwrap();
+        writer.write_all(&serde_json::to_vec(&manifest).unwrap()).unwrap();
+        writer.finish().unwrap();
+
+        fs::write(dirs.config_dir.join("config.json"), r#"{"theme": "light"}"#).unwrap();
+        assert!(import_backup(&cache, &dirs, &tampered).is_err());
+        assert_eq!(fs::read_to_string(dirs.config_dir.join("config.json")).unwrap(), r#"{"theme": "light"}"#);
+
+        fs::remove_dir_all(dirs.config_dir.parent().unwrap()).ok();
+    }
+}
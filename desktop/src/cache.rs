@@ -0,0 +1,484 @@
+//! 本地 K 线缓存
+//!
+//! 按 代码/周期 将下载过的 K 线存入 SQLite：离线时直接用缓存分析，在线时只向数据源补齐最新一根之后缺失的部分。
+//! 定期整理缓存：删除损坏和重叠的 K 线，按保留设置删除最旧的数据后压缩文件
+
+use alpha_core::errors::{AlphaError, AlphaResult};
+use alpha_core::models::MarketData;
+use alpha_providers::DataProvider;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS candles (
+    symbol    TEXT    NOT NULL,
+    timeframe INTEGER NOT NULL,
+    timestamp INTEGER NOT NULL,
+    open      REAL,
+    high      REAL,
+    low       REAL,
+    close     REAL    NOT NULL,
+    volume    INTEGER NOT NULL,
+    PRIMARY KEY (symbol, timeframe, timestamp)
+) WITHOUT ROWID;
+
+CREATE TABLE IF NOT EXISTS cache_meta (
+    key   TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+";
+
+/// 上次整理时间 (RFC 3339) 在 cache_meta 中的键
+const LAST_COMPACTED_KEY: &str = "last_compacted";
+/// 超过大小上限时每个 代码/周期 至少保留的根数
+const MIN_RETAINED_BARS: usize = 200;
+
+/// 价格列损坏：非数值、非正数或无穷大
+fn corrupt_price(column: &str, nullable: bool) -> String {
+    let invalid = format!("typeof({c}) NOT IN ('real', 'integer') OR {c} <= 0 OR {c} >= 9e999", c = column);
+    if nullable { format!("({c} IS NOT NULL AND ({invalid}))", c = column) } else { format!("({})", invalid) }
+}
+
+/// 缓存维护设置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CacheSettings {
+    /// 每个 代码/周期 最多保留的 K 线根数，超出时删除最旧的
+    pub max_bars_per_series: usize,
+    /// 缓存文件大小上限 (MB)
+    pub max_size_mb: u64,
+    /// 自动整理间隔 (天)，为 0 时只手动整理
+    pub compact_interval_days: u32,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            max_bars_per_series: 20_000,
+            max_size_mb: 1024,
+            compact_interval_days: 7,
+        }
+    }
+}
+
+/// 一次整理的结果
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CompactionReport {
+    /// 删除的损坏 K 线
+    pub corrupt: usize,
+    /// 删除的重叠 K 线
+    pub duplicates: usize,
+    /// 按保留设置删除的旧 K 线
+    pub expired: usize,
+    /// 整理前后的文件大小 (字节)
+    pub size_before: u64,
+    pub size_after: u64,
+    pub compacted_at: DateTime<Utc>,
+}
+
+/// SQLite K 线缓存，周期以秒存储，时间戳以毫秒存储
+#[derive(Debug)]
+pub struct CandleCache {
+    conn: Mutex<Connection>,
+}
+
+impl CandleCache {
+    pub fn open(path: &Path) -> AlphaResult<Self> {
+        let conn = Connection::open(path).map_err(|e| {
+            AlphaError::StorageError(format!("Failed to open candle cache {}", path.display())).caused_by(e)
+        })?;
+        Self::with_connection(conn)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> AlphaResult<Self> {
+        Self::with_connection(Connection::open_in_memory().map_err(storage_error)?)
+    }
+
+    fn with_connection(conn: Connection) -> AlphaResult<Self> {
+        conn.execute_batch(SCHEMA).map_err(storage_error)?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        // 写入在事务中完成，持锁线程 panic 不会留下半写状态
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 写入 K 线，同一时间戳的旧数据被覆盖 (缓存时最新一根可能尚未收盘)
+    pub fn store(&self, symbol: &str, timeframe: Duration, candles: &[MarketData]) -> AlphaResult<usize> {
+        let mut conn = self.connection();
+        let tx = conn.transaction().map_err(storage_error)?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR REPLACE INTO candles (symbol, timeframe, timestamp, open, high, low, close, volume)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            ).map_err(storage_error)?;
+
+            for candle in candles {
+                stmt.execute(params![
+                    symbol,
+                    timeframe.num_seconds(),
+                    candle.timestamp.timestamp_millis(),
+                    candle.open,
+                    candle.high,
+                    candle.low,
+                    candle.price,
+                    candle.volume as i64,
+                ]).map_err(storage_error)?;
+            }
+        }
+        tx.commit().map_err(storage_error)?;
+        Ok(candles.len())
+    }
+
+    /// 最近 `limit` 根 K 线，按时间升序
+    pub fn load(&self, symbol: &str, timeframe: Duration, limit: usize) -> AlphaResult<Vec<MarketData>> {
+        let conn = self.connection();
+        let mut stmt = conn.prepare_cached(
+            "SELECT timestamp, open, high, low, close, volume FROM candles
+             WHERE symbol = ?1 AND timeframe = ?2
+             ORDER BY timestamp DESC LIMIT ?3",
+        ).map_err(storage_error)?;
+
+        let rows = stmt.query_map(params![symbol, timeframe.num_seconds(), limit as i64], |row| {
+            Ok(MarketData {
+                symbol: symbol.to_string(),
+                timestamp: millis_to_datetime(row.get(0)?),
+                price: row.get(4)?,
+                volume: row.get::<_, i64>(5)?.max(0) as u64,
+                bid: None,
+                ask: None,
+                open: row.get(1)?,
+                high: row.get(2)?,
+                low: row.get(3)?,
+            })
+        }).map_err(storage_error)?;
+
+        let mut candles = rows.collect::<Result<Vec<_>, _>>().map_err(storage_error)?;
+        candles.reverse();
+        Ok(candles)
+    }
+
+    /// 将缓存完整复制到新文件 `path` (VACUUM INTO，得到一致的快照)
+    pub fn snapshot(&self, path: &Path) -> AlphaResult<()> {
+        let path = path.to_str()
+            .ok_or_else(|| AlphaError::invalid_input(format!("Non UTF-8 path {}", path.display())))?;
+        self.connection().execute("VACUUM INTO ?1", params![path]).map_err(storage_error)?;
+        Ok(())
+    }
+
+    /// 用另一个缓存文件中的全部 K 线替换当前内容，返回写入的行数
+    pub fn replace_from(&self, path: &Path) -> AlphaResult<usize> {
+        let path = path.to_str()
+            .ok_or_else(|| AlphaError::invalid_input(format!("Non UTF-8 path {}", path.display())))?;
+        let mut conn = self.connection();
+        conn.execute("ATTACH DATABASE ?1 AS restore", params![path]).map_err(storage_error)?;
+
+        let result = conn.transaction().and_then(|tx| {
+            tx.execute("DELETE FROM candles", [])?;
+            let rows = tx.execute(
+                "INSERT INTO candles (symbol, timeframe, timestamp, open, high, low, close, volume)
+                 SELECT symbol, timeframe, timestamp, open, high, low, close, volume FROM restore.candles",
+                [],
+            )?;
+            tx.commit()?;
+            Ok(rows)
+        });
+        conn.execute("DETACH DATABASE restore", []).map_err(storage_error)?;
+        result.map_err(storage_error)
+    }
+
+    /// 整理缓存
+    ///
+    /// 依次删除损坏的 K 线、同一序列中间隔不到半个周期的重叠 K 线 (保留较晚的一根)、
+    /// 超过每序列根数上限的旧 K 线；仍超过大小上限时逐步缩短最长的序列，最后 VACUUM 压缩文件
+    pub fn compact(&self, settings: &CacheSettings, now: DateTime<Utc>) -> AlphaResult<CompactionReport> {
+        let mut conn = self.connection();
+        let size_before = pages(&conn, "page_count")? * pages(&conn, "page_size")?;
+
+        let tx = conn.transaction().map_err(storage_error)?;
+        let corrupt = tx.execute(&format!(
+            "DELETE FROM candles WHERE timeframe <= 0 OR timestamp <= 0
+                 OR typeof(volume) != 'integer' OR volume < 0
+                 OR {} OR {} OR {} OR {}
+                 OR (high IS NOT NULL AND low IS NOT NULL AND high < low)",
+            corrupt_price("close", false), corrupt_price("open", true), corrupt_price("high", true), corrupt_price("low", true),
+        ), []).map_err(storage_error)?;
+        let duplicates = tx.execute(
+            "DELETE FROM candles AS c WHERE EXISTS (
+                 SELECT 1 FROM candles AS d
+                 WHERE d.symbol = c.symbol AND d.timeframe = c.timeframe
+                   AND d.timestamp > c.timestamp AND d.timestamp < c.timestamp + c.timeframe * 500
+             )",
+            [],
+        ).map_err(storage_error)?;
+        let mut expired = trim_series(&tx, settings.max_bars_per_series)?;
+
+        // 空闲页不计入大小，不必每轮 VACUUM
+        let max_bytes = settings.max_size_mb.saturating_mul(1024 * 1024);
+        let mut longest: usize = tx.query_row(
+            "SELECT COALESCE(MAX(n), 0) FROM (SELECT COUNT(*) AS n FROM candles GROUP BY symbol, timeframe)",
+            [],
+            |row| row.get::<_, i64>(0),
+        ).map_err(storage_error)? as usize;
+        loop {
+            let used = (pages(&tx, "page_count")? - pages(&tx, "freelist_count")?) * pages(&tx, "page_size")?;
+            if used <= max_bytes || longest <= MIN_RETAINED_BARS {
+                break;
+            }
+            let ratio = max_bytes as f64 / used as f64 * 0.9;
+            longest = ((longest as f64 * ratio) as usize).max(MIN_RETAINED_BARS);
+            expired += trim_series(&tx, longest)?;
+        }
+
+        tx.execute(
+            "INSERT OR REPLACE INTO cache_meta (key, value) VALUES (?1, ?2)",
+            params![LAST_COMPACTED_KEY, now.to_rfc3339()],
+        ).map_err(storage_error)?;
+        tx.commit().map_err(storage_error)?;
+        conn.execute_batch("VACUUM; PRAGMA optimize;").map_err(storage_error)?;
+
+        let report = CompactionReport {
+            corrupt,
+            duplicates,
+            expired,
+            size_before,
+            size_after: pages(&conn, "page_count")? * pages(&conn, "page_size")?,
+            compacted_at: now,
+        };
+        tracing::info!("Compacted candle cache: {:?}", report);
+        Ok(report)
+    }
+
+    /// 距上次整理超过设置的间隔时整理，未到期或已关闭自动整理时返回 None
+    pub fn compact_if_due(&self, settings: &CacheSettings, now: DateTime<Utc>) -> AlphaResult<Option<CompactionReport>> {
+        if settings.compact_interval_days == 0 {
+            return Ok(None);
+        }
+        if let Some(last) = self.last_compacted()? {
+            if now - last < Duration::days(i64::from(settings.compact_interval_days)) {
+                return Ok(None);
+            }
+        }
+        self.compact(settings, now).map(Some)
+    }
+
+    /// 上次整理时间，从未整理过时为 None
+    pub fn last_compacted(&self) -> AlphaResult<Option<DateTime<Utc>>> {
+        let value: Option<String> = self.connection()
+            .query_row("SELECT value FROM cache_meta WHERE key = ?1", params![LAST_COMPACTED_KEY], |row| row.get(0))
+            .optional()
+            .map_err(storage_error)?;
+        // 无法解析的时间视为从未整理
+        Ok(value.and_then(|value| DateTime::parse_from_rfc3339(&value).ok()).map(|time| time.with_timezone(&Utc)))
+    }
+}
+
+/// 每个 代码/周期 只保留最新的 `limit` 根，返回删除的行数
+fn trim_series(conn: &Connection, limit: usize) -> AlphaResult<usize> {
+    conn.execute(
+        "DELETE FROM candles WHERE (symbol, timeframe, timestamp) IN (
+             SELECT symbol, timeframe, timestamp FROM (
+                 SELECT symbol, timeframe, timestamp,
+                        ROW_NUMBER() OVER (PARTITION BY symbol, timeframe ORDER BY timestamp DESC) AS recency
+                 FROM candles
+             ) WHERE recency > ?1
+         )",
+        params![limit as i64],
+    ).map_err(storage_error)
+}
+
+/// 读取页数相关的 PRAGMA
+fn pages(conn: &Connection, pragma: &str) -> AlphaResult<u64> {
+    conn.query_row(&format!("PRAGMA {}", pragma), [], |row| row.get::<_, i64>(0))
+        .map(|value| value.max(0) as u64)
+        .map_err(storage_error)
+}
+
+/// 读取 K 线，优先使用缓存
+///
+/// 缓存不足 `limit` 根时请求全部，否则只请求自缓存最新一根以来的根数；数据源不可用时退回缓存以支持离线分析
+pub async fn cached_candles(
+    cache: &CandleCache,
+    provider: &dyn DataProvider,
+    symbol: &str,
+    timeframe: Duration,
+    limit: usize,
+) -> AlphaResult<Vec<MarketData>> {
+    let cached = cache.load(symbol, timeframe, limit)?;
+    let missing = missing_count(&cached, timeframe, limit, Utc::now());
+
+    match provider.candles(symbol, timeframe, missing).await {
+        Ok(fresh) => {
+            cache.store(symbol, timeframe, &fresh)?;
+            cache.load(symbol, timeframe, limit)
+        }
+        Err(err) if !cached.is_empty() => {
+            tracing::warn!("Serving {} cached candles for {}: {}", cached.len(), symbol, err.report());
+            Ok(cached)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// 需要向数据源请求的根数，至少 1 根以刷新可能未收盘的最新 K 线
+fn missing_count(cached: &[MarketData], timeframe: Duration, limit: usize, now: DateTime<Utc>) -> usize {
+    let latest = match cached.last() {
+        Some(latest) if cached.len() >= limit => latest,
+        _ => return limit,
+    };
+
+    let step = timeframe.num_seconds().max(1);
+    let elapsed = (now - latest.timestamp).num_seconds().max(0);
+    usize::try_from(elapsed / step).unwrap_or(usize::MAX).saturating_add(1).min(limit)
+}
+
+/// 行情所在日之前最后一根日线的收盘价
+pub fn previous_close(daily: &[MarketData], quote: &MarketData) -> Option<f64> {
+    daily.iter()
+        .rev()
+        .find(|c| c.timestamp.date_naive() < quote.timestamp.date_naive())
+        .map(|c| c.price)
+}
+
+fn millis_to_datetime(millis: i64) -> DateTime<Utc> {
+    Utc.timestamp_millis_opt(millis).single().unwrap_or_default()
+}
+
+fn storage_error(err: rusqlite::Error) -> AlphaError {
+    AlphaError::StorageError("Candle cache query failed".to_string()).caused_by(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alpha_providers::{SymbolMatch, SimulatedProvider};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 记录请求根数的数据源，`online` 为 false 时模拟断网
+    struct Recording {
+        inner: SimulatedProvider,
+        requested: AtomicUsize,
+        online: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl DataProvider for Recording {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        async fn candles(&self, symbol: &str, timeframe: Duration, limit: usize) -> AlphaResult<Vec<MarketData>> {
+            if !self.online {
+                return Err(AlphaError::network("offline"));
+            }
+            self.requested.store(limit, Ordering::SeqCst);
+            self.inner.candles(symbol, timeframe, limit).await
+        }
+
+        async fn quote(&self, symbol: &str) -> AlphaResult<MarketData> {
+            self.inner.quote(symbol).await
+        }
+
+        async fn search(&self, query: &str) -> AlphaResult<Vec<SymbolMatch>> {
+            self.inner.search(query).await
+        }
+    }
+
+    fn provider(online: bool) -> Recording {
+        Recording { inner: SimulatedProvider, requested: AtomicUsize::new(0), online }
+    }
+
+    #[tokio::test]
+    async fn test_incremental_fetch_and_offline_fallback() {
+        let cache = CandleCache::open_in_memory().unwrap();
+        let step = Duration::minutes(1);
+
+        let online = provider(true);
+        let first = cached_candles(&cache, &online, "AAPL", step, 50).await.unwrap();
+        assert_eq!(first.len(), 50);
+        assert_eq!(online.requested.load(Ordering::SeqCst), 50);
+
+        // 缓存已满，只补齐最新部分
+        let second = cached_candles(&cache, &online, "AAPL", step, 50).await.unwrap();
+        assert_eq!(second.len(), 50);
+        assert!(online.requested.load(Ordering::SeqCst) < 50);
+
+        let offline = cached_candles(&cache, &provider(false), "AAPL", step, 50).await.unwrap();
+        assert_eq!(offline.len(), 50);
+        assert!(offline.windows(2).all(|w| w[0].timestamp < w[1].timestamp));
+
+        // 其他周期没有缓存，断网时报错
+        assert!(cached_candles(&cache, &provider(false), "AAPL", Duration::hours(1), 50).await.is_err());
+    }
+
+    #[test]
+    fn test_compact() {
+        let cache = CandleCache::open_in_memory().unwrap();
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 14, 30, 0).unwrap();
+        let day = Duration::days(1);
+        let bar = |timestamp: DateTime<Utc>, price: f64| MarketData {
+            timestamp,
+            high: Some(price + 1.0),
+            low: Some(price - 1.0),
+            ..MarketData::new("AAPL".to_string(), price, 100)
+        };
+
+        let mut bars: Vec<MarketData> = (0..30).map(|i| bar(start + day * i, 100.0 + i as f64)).collect();
+        // 夏令时切换前后重复下载的同一天
+        bars.push(bar(start + day * 3 - Duration::hours(1), 50.0));
+        bars.push(bar(start + day * 4 + Duration::hours(1), 50.0));
+        // 损坏的行
+        bars.push(MarketData { high: Some(1.0), low: Some(2.0), ..bar(start - day * 2, 1.5) });
+        bars.push(bar(start - day, 0.0));
+        cache.store("AAPL", day, &bars).unwrap();
+        cache.connection().execute(
+            "INSERT INTO candles VALUES ('AAPL', 86400, ?1, NULL, NULL, NULL, 'n/a', 0)",
+            params![(start - day * 3).timestamp_millis()],
+        ).unwrap();
+
+        let settings = CacheSettings { max_bars_per_series: 25, ..CacheSettings::default() };
+        let now = start + day * 40;
+        assert_eq!(cache.last_compacted().unwrap(), None);
+        let report = cache.compact(&settings, now).unwrap();
+        assert_eq!((report.corrupt, report.duplicates, report.expired), (3, 2, 5));
+        assert_eq!(cache.last_compacted().unwrap(), Some(now));
+
+        let kept = cache.load("AAPL", day, 100).unwrap();
+        assert_eq!(kept.len(), 25);
+        assert_eq!(kept[0].timestamp, start + day * 5);
+        assert!(kept.windows(2).all(|w| w[1].timestamp - w[0].timestamp == day));
+
+        // 间隔未到不再整理
+        assert!(cache.compact_if_due(&settings, now + day).unwrap().is_none());
+        assert!(cache.compact_if_due(&settings, now + day * 7).unwrap().is_some());
+        let manual = CacheSettings { compact_interval_days: 0, ..settings };
+        assert!(cache.compact_if_due(&manual, now + day * 30).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_missing_count() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 2, 10, 0, 0).unwrap();
+        let candle = |minutes_ago: i64| MarketData {
+            symbol: "AAPL".to_string(),
+            timestamp: now - Duration::minutes(minutes_ago),
+            price: 1.0,
+            volume: 0,
+            bid: None,
+            ask: None,
+            open: None,
+            high: None,
+            low: None,
+        };
+
+        let full: Vec<_> = (0..10).rev().map(|i| candle(i + 5)).collect();
+        assert_eq!(missing_count(&full, Duration::minutes(1), 10, now), 6);
+        assert_eq!(missing_count(&full[..5], Duration::minutes(1), 10, now), 10);
+        assert_eq!(missing_count(&full, Duration::minutes(1), 3, now), 3);
+        assert_eq!(missing_count(&[], Duration::minutes(1), 10, now), 10);
+    }
+}
@@ -0,0 +1,241 @@
+//! 图表数据
+//!
+//! 按周期和时间范围返回 K 线及指标叠加线，前端直接绘制。缓存和数据源只保存常用基础周期，
+//! 其他周期由能整除它的最大基础周期聚合而来 (如 4h 由 1h 聚合)；指标在范围之前多取的预热数据上计算，
+//! 输出与 K 线逐根对齐，预热期内的值为 null
+
+use crate::cache::{self, CandleCache};
+use alpha_core::errors::{AlphaError, AlphaResult};
+use alpha_core::indicators::registry::{IndicatorInput, IndicatorParams, IndicatorRegistry};
+use alpha_core::indicators::TechnicalIndicators;
+use alpha_core::models::MarketData;
+use alpha_core::utils::series::aggregate_candles;
+use alpha_providers::DataProvider;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 缓存和数据源使用的基础周期 (秒)，从大到小
+const BASE_TIMEFRAMES: &[i64] = &[86_400, 3_600, 900, 300, 60];
+/// 单次请求的基础周期 K 线上限
+pub const MAX_BASE_BARS: usize = 5000;
+/// 未指定起始时间时返回的 K 线数量
+const DEFAULT_BARS: i32 = 200;
+
+/// 指标叠加请求，参数缺省时取注册的默认值
+#[derive(Debug, Clone, Deserialize)]
+pub struct OverlayRequest {
+    /// 注册表中的指标名称，如 `sma`、`bollinger`
+    pub name: String,
+    #[serde(default)]
+    pub params: IndicatorParams,
+}
+
+/// 一根 K 线，时间为区间起点的 Unix 秒
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ChartCandle {
+    pub time: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: u64,
+}
+
+impl From<&MarketData> for ChartCandle {
+    fn from(bar: &MarketData) -> Self {
+        Self {
+            time: bar.timestamp.timestamp(),
+            open: bar.open.unwrap_or(bar.price),
+            high: bar.high.unwrap_or(bar.price),
+            low: bar.low.unwrap_or(bar.price),
+            close: bar.price,
+            volume: bar.volume,
+        }
+    }
+}
+
+/// 指标的一条输出线，与 `candles` 等长
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct OverlayLine {
+    pub name: String,
+    pub values: Vec<Option<f64>>,
+}
+
+/// 一个指标叠加
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ChartOverlay {
+    /// 如 `sma(50)`
+    pub label: String,
+    pub lines: Vec<OverlayLine>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ChartData {
+    pub symbol: String,
+    /// 周期 (秒)
+    pub timeframe: i64,
+    pub candles: Vec<ChartCandle>,
+    pub overlays: Vec<ChartOverlay>,
+}
+
+/// 能整除 `timeframe` 的最大基础周期，没有时直接使用 `timeframe`
+pub fn base_timeframe(timeframe: Duration) -> Duration {
+    let secs = timeframe.num_seconds();
+    BASE_TIMEFRAMES.iter()
+        .find(|&&base| base <= secs && secs % base == 0)
+        .map_or(timeframe, |&base| Duration::seconds(base))
+}
+
+/// 读取 `[from, to]` 内的 K 线和指标
+///
+/// `from` 缺省时返回 `to` (缺省为当前时间) 之前最近的 200 根
+#[allow(clippy::too_many_arguments)]
+pub async fn chart_data(
+    cache: &CandleCache,
+    provider: &dyn DataProvider,
+    symbol: &str,
+    timeframe: Duration,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    overlays: &[OverlayRequest],
+    now: DateTime<Utc>,
+) -> AlphaResult<ChartData> {
+    if timeframe <= Duration::zero() {
+        return Err(AlphaError::invalid_input("Timeframe must be positive"));
+    }
+    let to = to.unwrap_or(now).min(now);
+    let from = from.unwrap_or(to - timeframe * DEFAULT_BARS);
+    if from >= to {
+        return Err(AlphaError::invalid_input("Chart range start must be before its end"));
+    }
+
+    let registry = IndicatorRegistry::new();
+    let warmup = overlays.iter()
+        .map(|overlay| warmup_bars(&registry, overlay))
+        .collect::<AlphaResult<Vec<_>>>()?
+        .into_iter()
+        .max()
+        .unwrap_or(0);
+
+    // 数据源按根数取最近的数据，从范围起点 (含预热) 算到当前时间
+    let base = base_timeframe(timeframe);
+    let start = from - timeframe * warmup as i32;
+    let needed = ((now - start).num_seconds() / base.num_seconds()).max(0) as usize + 1;
+    if needed > MAX_BASE_BARS {
+        return Err(AlphaError::invalid_input(format!(
+            "Range needs {} {}s bars, at most {} are allowed", needed, base.num_seconds(), MAX_BASE_BARS
+        )));
+    }
+    let bars = cache::cached_candles(cache, provider, symbol, base, needed).await?;
+    let bars = if base == timeframe { bars } else { aggregate_candles(&bars, timeframe)? };
+
+    // 预热数据参与计算，输出时截掉
+    let bars: Vec<MarketData> = bars.into_iter().filter(|bar| bar.timestamp <= to).collect();
+    let first = bars.iter().position(|bar| bar.timestamp >= from).unwrap_or(bars.len());
+    let overlays = overlays.iter()
+        .map(|overlay| calculate_overlay(&registry, overlay, &bars, first))
+        .collect::<AlphaResult<Vec<_>>>()?;
+
+    Ok(ChartData {
+        symbol: symbol.to_string(),
+        timeframe: timeframe.num_seconds(),
+        candles: bars[first..].iter().map(ChartCandle::from).collect(),
+        overlays,
+    })
+}
+
+/// 指标需要的预热根数，取最大的参数值；同时检查指标和参数名
+fn warmup_bars(registry: &IndicatorRegistry, overlay: &OverlayRequest) -> AlphaResult<usize> {
+    let spec = registry.get(&overlay.name)
+        .ok_or_else(|| AlphaError::not_found(format!("Unknown indicator: {}", overlay.name)))?;
+    if let Some(unknown) = overlay.params.keys().find(|key| !spec.params.iter().any(|(param, _)| param == key)) {
+        return Err(AlphaError::invalid_input(format!("Unknown parameter for {}: {}", spec.name, unknown)));
+    }
+    let warmup = spec.params.iter()
+        .map(|(param, default)| overlay.params.get(*param).copied().unwrap_or(*default))
+        .fold(0.0, f64::max);
+    if !warmup.is_finite() || warmup > MAX_BASE_BARS as f64 {
+        return Err(AlphaError::invalid_input(format!("Parameters of {} are out of range", spec.name)));
+    }
+    Ok(warmup.ceil() as usize)
+}
+
+fn calculate_overlay(
+    registry: &IndicatorRegistry,
+    overlay: &OverlayRequest,
+    bars: &[MarketData],
+    first: usize,
+) -> AlphaResult<ChartOverlay> {
+    let close: Vec<f64> = bars.iter().map(|bar| bar.price).collect();
+    let high: Vec<f64> = bars.iter().map(|bar| bar.high.unwrap_or(bar.price)).collect();
+    let low: Vec<f64> = bars.iter().map(|bar| bar.low.unwrap_or(bar.price)).collect();
+    let volume: Vec<f64> = bars.iter().map(|bar| bar.volume as f64).collect();
+    let input = IndicatorInput { high: Some(&high), low: Some(&low), close: &close, volume: Some(&volume) };
+
+    let warmup = warmup_bars(registry, overlay)?;
+    let columns = registry.calculate(&TechnicalIndicators::new(), &overlay.name, &input, &overlay.params)?;
+    let lines = columns.into_iter()
+        .map(|(name, values)| OverlayLine {
+            name,
+            values: values.iter()
+                .enumerate()
+                .skip(first)
+                .map(|(i, &value)| Some(value).filter(|v| i + 1 >= warmup && v.is_finite()))
+                .collect(),
+        })
+        .collect();
+
+    // 标签按注册顺序列出全部参数 (含默认值)
+    let spec = registry.get(&overlay.name)
+        .ok_or_else(|| AlphaError::not_found(format!("Unknown indicator: {}", overlay.name)))?;
+    let args: Vec<String> = spec.params.iter()
+        .map(|(param, default)| overlay.params.get(*param).unwrap_or(default).to_string())
+        .collect();
+    Ok(ChartOverlay { label: format!("{}({})", spec.name, args.join(",")), lines })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alpha_providers::SimulatedProvider;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_base_timeframe() {
+        assert_eq!(base_timeframe(Duration::hours(4)), Duration::hours(1));
+        assert_eq!(base_timeframe(Duration::weeks(1)), Duration::days(1));
+        assert_eq!(base_timeframe(Duration::minutes(10)), Duration::minutes(5));
+        assert_eq!(base_timeframe(Duration::minutes(7)), Duration::minutes(1));
+        assert_eq!(base_timeframe(Duration::seconds(30)), Duration::seconds(30));
+    }
+
+    #[tokio::test]
+    async fn test_resamples_and_aligns_overlays() {
+        let cache = CandleCache::open_in_memory().unwrap();
+        let now = Utc::now();
+        let overlays = vec![
+            OverlayRequest { name: "sma".to_string(), params: BTreeMap::from([("period".to_string(), 5.0)]) },
+            OverlayRequest { name: "bollinger".to_string(), params: BTreeMap::new() },
+        ];
+
+        let data = chart_data(&cache, &SimulatedProvider, "AAPL", Duration::hours(4), Some(now - Duration::days(5)), None, &overlays, now)
+            .await
+            .unwrap();
+        assert_eq!(data.timeframe, 4 * 3600);
+        assert!(data.candles.len() >= 29 && data.candles.len() <= 31, "{}", data.candles.len());
+        assert!(data.candles.windows(2).all(|w| w[1].time - w[0].time == 4 * 3600));
+        assert_eq!(data.overlays[0].label, "sma(5)");
+        assert_eq!(data.overlays[1].label, "bollinger(20,2)");
+        assert_eq!(data.overlays[1].lines.len(), 3);
+        // 范围之前有预热数据，指标从第一根起就有值
+        assert!(data.overlays.iter().flat_map(|o| &o.lines).all(|line| {
+            line.values.len() == data.candles.len() && line.values.iter().all(Option::is_some)
+        }));
+
+        let unknown = [OverlayRequest { name: "foo".to_string(), params: BTreeMap::new() }];
+        assert!(chart_data(&cache, &SimulatedProvider, "AAPL", Duration::days(1), None, None, &unknown, now).await.is_err());
+        assert!(chart_data(&cache, &SimulatedProvider, "AAPL", Duration::minutes(1), Some(now - Duration::days(30)), None, &[], now)
+            .await
+            .is_err());
+    }
+}
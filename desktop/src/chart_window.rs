@@ -0,0 +1,275 @@
+//! 独立图表窗口
+//!
+//! 每个标的最多一个弹出窗口，窗口的代码、周期和指标保存在配置目录，下次启动时恢复；
+//! 调度器刷新得到的行情经广播通道分发，每个窗口只接收当前标的的行情
+
+use crate::AppState;
+use alpha_core::errors::{AlphaError, AlphaResult};
+use alpha_core::models::MarketData;
+use alpha_core::utils::series::parse_timeframe;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, WindowBuilder, WindowEvent, WindowUrl};
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+/// 发送给图表窗口的实时行情事件，载荷为 [`MarketData`]
+pub const QUOTE_EVENT: &str = "quote";
+
+const LABEL_PREFIX: &str = "chart-";
+/// 图表页面的前端路由，页面通过 `get_chart_window_state` 读取自身状态
+const CHART_ROUTE: &str = "index.html#/chart";
+/// 广播通道容量，窗口处理过慢时丢弃最旧的行情
+const QUOTE_CHANNEL_CAPACITY: usize = 256;
+
+/// 图表窗口状态
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChartWindowState {
+    pub symbol: String,
+    /// K 线周期，如 `1d`
+    pub timeframe: String,
+    #[serde(default)]
+    pub indicators: Vec<String>,
+}
+
+impl ChartWindowState {
+    pub fn validate(&self) -> AlphaResult<()> {
+        if self.symbol.trim().is_empty() {
+            return Err(AlphaError::invalid_input("Symbol cannot be empty"));
+        }
+        parse_timeframe(&self.timeframe)?;
+        Ok(())
+    }
+}
+
+/// 已打开的图表窗口 (按窗口 label) 及行情广播
+#[derive(Debug)]
+pub struct ChartWindows {
+    path: PathBuf,
+    windows: Mutex<BTreeMap<String, ChartWindowState>>,
+    quotes: broadcast::Sender<MarketData>,
+}
+
+impl ChartWindows {
+    /// 读取保存的窗口状态，文件不存在时为空
+    pub fn open(path: PathBuf) -> AlphaResult<Self> {
+        let windows = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|e| {
+                AlphaError::StorageError(format!("Failed to read {}", path.display())).caused_by(e)
+            })?;
+            serde_json::from_str(&content).map_err(|e| {
+                AlphaError::DataCorrupted(format!("Malformed chart window file {}", path.display())).caused_by(e)
+            })?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            path,
+            windows: Mutex::new(windows),
+            quotes: broadcast::channel(QUOTE_CHANNEL_CAPACITY).0,
+        })
+    }
+
+    pub fn states(&self) -> BTreeMap<String, ChartWindowState> {
+        self.lock().clone()
+    }
+
+    pub fn get(&self, label: &str) -> Option<ChartWindowState> {
+        self.lock().get(label).cloned()
+    }
+
+    /// 该标的已有窗口的 label
+    pub fn find(&self, symbol: &str) -> Option<String> {
+        self.lock().iter()
+            .find(|(_, state)| state.symbol.eq_ignore_ascii_case(symbol))
+            .map(|(label, _)| label.clone())
+    }
+
+    /// 校验并保存窗口状态，写盘成功后才生效
+    pub fn upsert(&self, label: &str, state: ChartWindowState) -> AlphaResult<()> {
+        state.validate()?;
+        let mut windows = self.lock();
+        let mut updated = windows.clone();
+        updated.insert(label.to_string(), state);
+        self.save(&updated)?;
+        *windows = updated;
+        Ok(())
+    }
+
+    /// 删除窗口状态，不存在时返回 false
+    pub fn remove(&self, label: &str) -> AlphaResult<bool> {
+        let mut windows = self.lock();
+        let mut updated = windows.clone();
+        if updated.remove(label).is_none() {
+            return Ok(false);
+        }
+        self.save(&updated)?;
+        *windows = updated;
+        Ok(true)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<MarketData> {
+        self.quotes.subscribe()
+    }
+
+    /// 向所有窗口广播行情，没有窗口订阅时直接丢弃
+    pub fn broadcast(&self, quotes: &[MarketData]) {
+        for quote in quotes {
+            if self.quotes.send(quote.clone()).is_err() {
+                break;
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, ChartWindowState>> {
+        self.windows.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 先写临时文件再替换，避免写入中断损坏已有状态
+    fn save(&self, windows: &BTreeMap<String, ChartWindowState>) -> AlphaResult<()> {
+        let content = serde_json::to_string_pretty(windows)
+            .map_err(|e| AlphaError::SerializationError("Failed to serialize chart windows".to_string()).caused_by(e))?;
+        let temp = self.path.with_extension("json.tmp");
+        fs::write(&temp, content)
+            .and_then(|_| fs::rename(&temp, &self.path))
+            .map_err(|e| AlphaError::StorageError(format!("Failed to write {}", self.path.display())).caused_by(e))
+    }
+}
+
+/// 打开标的的图表窗口，已打开时更新状态并聚焦，返回窗口 label
+pub fn show(app: &AppHandle, windows: &ChartWindows, state: ChartWindowState) -> AlphaResult<String> {
+    if let Some(label) = windows.find(&state.symbol) {
+        if let Some(window) = app.get_window(&label) {
+            windows.upsert(&label, state)?;
+            window.unminimize()
+                .and_then(|_| window.show())
+                .and_then(|_| window.set_focus())
+                .map_err(|e| AlphaError::internal(format!("Failed to focus window {}: {}", label, e)))?;
+            return Ok(label);
+        }
+        // 状态残留但窗口已不存在 (如上次异常退出)，复用 label 重新创建
+        windows.upsert(&label, state.clone())?;
+        build(app, &label, &state)?;
+        return Ok(label);
+    }
+
+    let label = format!("{}{}", LABEL_PREFIX, Uuid::new_v4().simple());
+    windows.upsert(&label, state.clone())?;
+    if let Err(e) = build(app, &label, &state) {
+        windows.remove(&label)?;
+        return Err(e);
+    }
+    Ok(label)
+}
+
+/// 重新打开上次退出时仍打开的窗口，单个窗口失败只记录日志
+pub fn restore(app: &AppHandle, windows: &ChartWindows) {
+    for (label, state) in windows.states() {
+        if app.get_window(&label).is_none() {
+            if let Err(e) = build(app, &label, &state) {
+                tracing::warn!("Failed to restore chart window {}: {}", label, e.report());
+            }
+        }
+    }
+}
+
+fn build(app: &AppHandle, label: &str, state: &ChartWindowState) -> AlphaResult<()> {
+    let window = WindowBuilder::new(app, label, WindowUrl::App(CHART_ROUTE.into()))
+        .title(format!("{} - Alpha Finance", state.symbol))
+        .inner_size(960.0, 640.0)
+        .min_inner_size(480.0, 320.0)
+        .build()
+        .map_err(|e| AlphaError::internal(format!("Failed to create window {}: {}", label, e)))?;
+
+    // 用户关闭的窗口不再恢复；应用退出时窗口直接销毁，状态保留
+    let handle = app.clone();
+    let closed = label.to_string();
+    window.on_window_event(move |event| {
+        if let WindowEvent::CloseRequested { .. } = event {
+            if let Some(state) = handle.try_state::<AppState>() {
+                if let Err(e) = state.chart_windows.remove(&closed) {
+                    tracing::warn!("Failed to forget chart window {}: {}", closed, e.report());
+                }
+            }
+        }
+    });
+
+    tauri::async_runtime::spawn(forward_quotes(app.clone(), label.to_string()));
+    Ok(())
+}
+
+/// 将广播的行情转发给窗口，只转发窗口当前标的；窗口关闭后退出
+async fn forward_quotes(app: AppHandle, label: String) {
+    let Some(mut quotes) = app.try_state::<AppState>().map(|state| state.chart_windows.subscribe()) else {
+        return;
+    };
+
+    loop {
+        let quote = match quotes.recv().await {
+            Ok(quote) => quote,
+            Err(RecvError::Lagged(skipped)) => {
+                tracing::debug!("Chart window {} skipped {} quotes", label, skipped);
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+
+        let Some(state) = app.try_state::<AppState>().and_then(|state| state.chart_windows.get(&label)) else {
+            return;
+        };
+        let Some(window) = app.get_window(&label) else {
+            return;
+        };
+        if quote.symbol == state.symbol {
+            if let Err(e) = window.emit(QUOTE_EVENT, &quote) {
+                tracing::warn!("Failed to emit {} to {}: {}", QUOTE_EVENT, label, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chart(symbol: &str) -> ChartWindowState {
+        ChartWindowState { symbol: symbol.to_string(), timeframe: "1h".to_string(), indicators: vec!["sma".to_string()] }
+    }
+
+    #[test]
+    fn test_persist_and_restore_states() {
+        let dir = std::env::temp_dir().join(format!("alpha-charts-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("chart_windows.json");
+
+        let windows = ChartWindows::open(path.clone()).unwrap();
+        windows.upsert("chart-a", chart("AAPL")).unwrap();
+        windows.upsert("chart-b", chart("MSFT")).unwrap();
+        assert!(windows.upsert("chart-c", ChartWindowState { timeframe: "7x".to_string(), ..chart("TSLA") }).is_err());
+
+        let reloaded = ChartWindows::open(path).unwrap();
+        assert_eq!(reloaded.states().len(), 2);
+        assert_eq!(reloaded.find("aapl").as_deref(), Some("chart-a"));
+        assert!(reloaded.remove("chart-a").unwrap());
+        assert!(!reloaded.remove("chart-a").unwrap());
+        assert_eq!(reloaded.find("AAPL"), None);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_broadcast_reaches_all_subscribers() {
+        let windows = ChartWindows::open(std::env::temp_dir().join("alpha-charts-unused.json")).unwrap();
+        // 无订阅者时不报错
+        windows.broadcast(&[MarketData::new("AAPL".to_string(), 1.0, 1)]);
+
+        let (mut first, mut second) = (windows.subscribe(), windows.subscribe());
+        windows.broadcast(&[MarketData::new("AAPL".to_string(), 190.0, 10)]);
+        assert_eq!(first.try_recv().unwrap().price, 190.0);
+        assert_eq!(second.try_recv().unwrap().symbol, "AAPL");
+    }
+}
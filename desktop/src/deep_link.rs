@@ -0,0 +1,197 @@
+//! `alpha://` 深度链接
+//!
+//! 邮件、通知或其他应用中的链接 (如 `alpha://analyze/AAPL?tf=1d`) 打开对应页面或图表窗口。
+//! 链接来自外部，只用于导航，不触发任何修改数据的操作；应用初始化完成前收到的链接先排队，初始化后再处理
+//!
+//! 支持的链接：
+//! - `alpha://analyze/<代码>?tf=<周期>`：主窗口打开分析页
+//! - `alpha://chart/<代码>?tf=<周期>&indicators=sma,rsi`：打开独立图表窗口
+//! - `alpha://alerts`、`alpha://portfolio`、`alpha://settings`：主窗口打开对应页面
+
+use crate::chart_window::{self, ChartWindowState};
+use crate::tray;
+use crate::AppState;
+use alpha_core::errors::{AlphaError, AlphaResult};
+use alpha_core::utils::series::parse_timeframe;
+use percent_encoding::percent_decode_str;
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use url::Url;
+
+/// 注册的 URL scheme
+pub const SCHEME: &str = "alpha";
+/// 需要主窗口切换页面时发送给前端，载荷为 [`DeepLink`]
+pub const DEEP_LINK_EVENT: &str = "deep-link";
+
+/// 链接未指定周期时使用的周期
+const DEFAULT_TIMEFRAME: &str = "1d";
+/// 代码长度上限
+const MAX_SYMBOL_LEN: usize = 32;
+
+/// 解析后的深度链接
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(tag = "view", rename_all = "snake_case")]
+pub enum DeepLink {
+    Analyze { symbol: String, timeframe: String },
+    Chart { symbol: String, timeframe: String, indicators: Vec<String> },
+    Alerts,
+    Portfolio,
+    Settings,
+}
+
+impl DeepLink {
+    pub fn parse(link: &str) -> AlphaResult<Self> {
+        let url = Url::parse(link.trim())
+            .map_err(|e| AlphaError::invalid_input(format!("Malformed link {}", link)).caused_by(e))?;
+        if url.scheme() != SCHEME {
+            return Err(AlphaError::invalid_input(format!("Unsupported link scheme: {}", url.scheme())));
+        }
+        let view = url.host_str()
+            .ok_or_else(|| AlphaError::invalid_input(format!("Link has no view: {}", link)))?
+            .to_ascii_lowercase();
+        let path: Vec<&str> = url.path_segments()
+            .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default();
+        let query = |key: &str| url.query_pairs().find(|(k, _)| k == key).map(|(_, v)| v.into_owned());
+
+        match (view.as_str(), path.as_slice()) {
+            ("analyze", [symbol]) => Ok(Self::Analyze { symbol: symbol_of(symbol)?, timeframe: timeframe_of(query("tf"))? }),
+            ("chart", [symbol]) => Ok(Self::Chart {
+                symbol: symbol_of(symbol)?,
+                timeframe: timeframe_of(query("tf"))?,
+                indicators: query("indicators")
+                    .map(|list| list.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_lowercase).collect())
+                    .unwrap_or_default(),
+            }),
+            ("alerts", []) => Ok(Self::Alerts),
+            ("portfolio", []) => Ok(Self::Portfolio),
+            ("settings", []) => Ok(Self::Settings),
+            _ => Err(AlphaError::invalid_input(format!("Unsupported link: {}", link))),
+        }
+    }
+}
+
+/// 是否为本应用的链接，用于从命令行参数中找出冷启动时传入的链接
+pub fn is_deep_link(arg: &str) -> bool {
+    arg.get(..SCHEME.len()).is_some_and(|scheme| scheme.eq_ignore_ascii_case(SCHEME))
+        && arg[SCHEME.len()..].starts_with("://")
+}
+
+/// 代码只允许字母数字和 `.-^=`，统一转为大写
+fn symbol_of(segment: &str) -> AlphaResult<String> {
+    let symbol = percent_decode_str(segment).decode_utf8_lossy().trim().to_ascii_uppercase();
+    let valid = !symbol.is_empty()
+        && symbol.len() <= MAX_SYMBOL_LEN
+        && symbol.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '^' | '='));
+    if !valid {
+        return Err(AlphaError::invalid_input(format!("Invalid symbol in link: {}", segment)));
+    }
+    Ok(symbol)
+}
+
+fn timeframe_of(value: Option<String>) -> AlphaResult<String> {
+    let timeframe = value.unwrap_or_else(|| DEFAULT_TIMEFRAME.to_string());
+    parse_timeframe(&timeframe)?;
+    Ok(timeframe)
+}
+
+/// 应用初始化完成前收到的链接
+#[derive(Debug, Default)]
+pub struct PendingLinks(Mutex<Vec<DeepLink>>);
+
+/// 处理收到的链接，无效链接只记录日志
+pub fn handle(app: &AppHandle, link: &str) {
+    let link = match DeepLink::parse(link) {
+        Ok(link) => link,
+        Err(e) => {
+            tracing::warn!("Ignoring deep link: {}", e.report());
+            return;
+        }
+    };
+
+    // 持锁检查应用状态，避免与 dispatch_pending 交错导致链接滞留在队列中
+    if let Some(pending) = app.try_state::<PendingLinks>() {
+        let mut queue = pending.0.lock().unwrap_or_else(|e| e.into_inner());
+        if app.try_state::<AppState>().is_none() {
+            queue.push(link);
+            return;
+        }
+    }
+    dispatch(app, link);
+}
+
+/// 应用初始化完成后处理排队的链接
+pub fn dispatch_pending(app: &AppHandle) {
+    let links = match app.try_state::<PendingLinks>() {
+        Some(pending) => std::mem::take(&mut *pending.0.lock().unwrap_or_else(|e| e.into_inner())),
+        None => return,
+    };
+    for link in links {
+        dispatch(app, link);
+    }
+}
+
+fn dispatch(app: &AppHandle, link: DeepLink) {
+    tracing::info!("Opening deep link {:?}", link);
+    if let DeepLink::Chart { symbol, timeframe, indicators } = link {
+        let Some(state) = app.try_state::<AppState>() else {
+            return;
+        };
+        let chart = ChartWindowState { symbol: symbol.clone(), timeframe, indicators };
+        if let Err(e) = chart_window::show(app, &state.chart_windows, chart) {
+            tracing::warn!("Failed to open chart window for {}: {}", symbol, e.report());
+        }
+        return;
+    }
+
+    tray::show_main_window(app);
+    if let Err(e) = app.emit_all(DEEP_LINK_EVENT, &link) {
+        tracing::warn!("Failed to emit {}: {}", DEEP_LINK_EVENT, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_links() {
+        assert_eq!(
+            DeepLink::parse("alpha://analyze/aapl?tf=1h").unwrap(),
+            DeepLink::Analyze { symbol: "AAPL".to_string(), timeframe: "1h".to_string() }
+        );
+        assert_eq!(
+            DeepLink::parse("alpha://Chart/%5EGSPC/?indicators=SMA,%20rsi,").unwrap(),
+            DeepLink::Chart {
+                symbol: "^GSPC".to_string(),
+                timeframe: "1d".to_string(),
+                indicators: vec!["sma".to_string(), "rsi".to_string()],
+            }
+        );
+        assert_eq!(DeepLink::parse("alpha://portfolio").unwrap(), DeepLink::Portfolio);
+        assert_eq!(DeepLink::parse("alpha://alerts/").unwrap(), DeepLink::Alerts);
+
+        for link in [
+            "https://analyze/AAPL",
+            "alpha:analyze/AAPL",
+            "alpha://analyze",
+            "alpha://analyze/AAPL/extra",
+            "alpha://analyze/AAPL?tf=7x",
+            "alpha://analyze/%3Cscript%3E",
+            "alpha://delete/AAPL",
+        ] {
+            assert!(DeepLink::parse(link).is_err(), "{}", link);
+        }
+    }
+
+    #[test]
+    fn test_is_deep_link() {
+        assert!(is_deep_link("alpha://analyze/AAPL"));
+        assert!(is_deep_link("ALPHA://settings"));
+        assert!(!is_deep_link("alpha"));
+        assert!(!is_deep_link("--alpha://x"));
+        assert!(!is_deep_link("/usr/bin/alpha-desktop"));
+        assert!(!is_deep_link("行情://x"));
+    }
+}
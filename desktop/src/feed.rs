@@ -0,0 +1,289 @@
+//! real-time-feed 服务的 WebSocket 客户端
+//!
+//! 由 Rust 端维护与实时行情服务的连接：连接后发送自选股订阅，断线按指数退避重连，
+//! 收到的逐笔行情通过 `tick` 事件转发给前端，广播给图表窗口并检查告警，前端无需自行维护 socket
+
+use crate::AppState;
+use alpha_core::errors::{AlphaError, AlphaResult};
+use alpha_core::models::MarketData;
+use alpha_core::utils::retry::RetryPolicy;
+use chrono::{DateTime, Utc};
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Manager};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+/// 逐笔行情事件，载荷为 [`FeedTick`]
+pub const TICK_EVENT: &str = "tick";
+/// 连接状态变化事件，载荷为 [`FeedStatus`]
+pub const FEED_STATUS_EVENT: &str = "feed-status";
+
+/// 最长重连等待时间，首次等待 1 秒，之后每次翻倍 (见 [`RetryPolicy::reconnect`])
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// 实时行情，与 real-time-feed 推送的消息一致
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FeedTick {
+    pub symbol: String,
+    pub price: f64,
+    pub volume: u64,
+    pub change: f64,
+    pub change_percent: f64,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl FeedTick {
+    pub fn to_market_data(&self) -> MarketData {
+        MarketData {
+            symbol: self.symbol.clone(),
+            timestamp: self.timestamp,
+            price: self.price,
+            volume: self.volume,
+            bid: None,
+            ask: None,
+            open: None,
+            high: None,
+            low: None,
+        }
+    }
+}
+
+/// 发送给服务端的订阅消息
+#[derive(Debug, Clone, Serialize)]
+struct SubscribeMessage<'a> {
+    symbols: Vec<&'a str>,
+    action: &'static str,
+}
+
+/// 连接状态
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum FeedStatus {
+    Stopped,
+    Connecting { attempt: u32 },
+    Connected,
+    Disconnected { error: String, retry_in_secs: u64 },
+}
+
+#[derive(Debug)]
+pub struct FeedClient {
+    url: Mutex<String>,
+    /// 已订阅的代码，重连后整体重新订阅
+    symbols: Mutex<BTreeSet<String>>,
+    /// 当前连接的发送端，未连接时为 None
+    sink: tokio::sync::Mutex<Option<SplitSink<Socket, Message>>>,
+    status: Mutex<FeedStatus>,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl FeedClient {
+    pub fn new(url: impl Into<String>, symbols: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            url: Mutex::new(url.into()),
+            symbols: Mutex::new(symbols.into_iter().collect()),
+            sink: tokio::sync::Mutex::new(None),
+            status: Mutex::new(FeedStatus::Stopped),
+            task: Mutex::new(None),
+        }
+    }
+
+    pub fn status(&self) -> FeedStatus {
+        self.status.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn symbols(&self) -> Vec<String> {
+        self.lock_symbols().iter().cloned().collect()
+    }
+
+    /// 增加订阅，已连接时立即通知服务端
+    pub async fn subscribe(&self, symbols: &[String]) -> AlphaResult<()> {
+        self.lock_symbols().extend(symbols.iter().cloned());
+        self.send("subscribe", symbols).await
+    }
+
+    /// 取消订阅，已连接时立即通知服务端
+    pub async fn unsubscribe(&self, symbols: &[String]) -> AlphaResult<()> {
+        self.lock_symbols().retain(|symbol| !symbols.contains(symbol));
+        self.send("unsubscribe", symbols).await
+    }
+
+    /// 启动后台连接，已有连接先停止
+    pub fn start(&self, app: AppHandle) {
+        let task = tauri::async_runtime::spawn(run(app));
+        if let Some(previous) = self.lock_task().replace(task) {
+            previous.abort();
+        }
+    }
+
+    /// 更换服务地址，正在运行时立即按新地址重连
+    pub fn set_url(&self, app: AppHandle, url: impl Into<String>) {
+        *self.url.lock().unwrap_or_else(|e| e.into_inner()) = url.into();
+        if self.lock_task().is_some() {
+            self.start(app);
+        }
+    }
+
+    pub fn stop(&self) {
+        if let Some(task) = self.lock_task().take() {
+            task.abort();
+        }
+        *self.status.lock().unwrap_or_else(|e| e.into_inner()) = FeedStatus::Stopped;
+    }
+
+    /// 建立连接并发送当前全部订阅，返回接收端
+    async fn connect(&self) -> AlphaResult<SplitStream<Socket>> {
+        let url = self.url.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let (socket, _) = connect_async(url.as_str()).await
+            .map_err(|e| AlphaError::network(format!("Failed to connect to {}", url)).caused_by(e))?;
+        let (sink, stream) = socket.split();
+        *self.sink.lock().await = Some(sink);
+
+        let symbols = self.symbols();
+        if !symbols.is_empty() {
+            self.send("subscribe", &symbols).await?;
+        }
+        Ok(stream)
+    }
+
+    /// 未连接时只更新本地订阅，连接后统一发送
+    async fn send(&self, action: &'static str, symbols: &[String]) -> AlphaResult<()> {
+        let mut sink = self.sink.lock().await;
+        let Some(writer) = sink.as_mut() else {
+            return Ok(());
+        };
+
+        let message = SubscribeMessage { symbols: symbols.iter().map(String::as_str).collect(), action };
+        let text = serde_json::to_string(&message)
+            .map_err(|e| AlphaError::SerializationError("Failed to serialize subscription".to_string()).caused_by(e))?;
+        if let Err(e) = writer.send(Message::Text(text)).await {
+            *sink = None;
+            return Err(AlphaError::network("Failed to send subscription").caused_by(e));
+        }
+        Ok(())
+    }
+
+    /// 读取消息直到连接关闭，只回调已订阅代码的行情；无法解析的消息跳过
+    async fn receive(&self, mut stream: SplitStream<Socket>, mut on_tick: impl FnMut(FeedTick)) -> AlphaResult<()> {
+        let result = loop {
+            match stream.next().await {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<FeedTick>(&text) {
+                    Ok(tick) if self.lock_symbols().contains(&tick.symbol) => on_tick(tick),
+                    Ok(_) => {}
+                    Err(e) => tracing::debug!("Ignoring feed message {}: {}", text, e),
+                },
+                Some(Ok(Message::Close(_))) | None => break Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => break Err(AlphaError::network("Feed connection failed").caused_by(e)),
+            }
+        };
+        *self.sink.lock().await = None;
+        result
+    }
+
+    fn set_status(&self, app: &AppHandle, status: FeedStatus) {
+        *self.status.lock().unwrap_or_else(|e| e.into_inner()) = status.clone();
+        if let Err(e) = app.emit_all(FEED_STATUS_EVENT, &status) {
+            tracing::warn!("Failed to emit {}: {}", FEED_STATUS_EVENT, e);
+        }
+    }
+
+    fn lock_symbols(&self) -> std::sync::MutexGuard<'_, BTreeSet<String>> {
+        self.symbols.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock_task(&self) -> std::sync::MutexGuard<'_, Option<JoinHandle<()>>> {
+        self.task.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// 连接循环：断线后按退避时间重连，连接成功后重置退避
+async fn run(app: AppHandle) {
+    let state = app.state::<AppState>();
+    let feed = &state.feed;
+    let mut backoff = RetryPolicy::reconnect(MAX_RETRY_DELAY).backoffs();
+
+    loop {
+        feed.set_status(&app, FeedStatus::Connecting { attempt: backoff.retries() });
+        let result = match feed.connect().await {
+            Ok(stream) => {
+                backoff.reset();
+                feed.set_status(&app, FeedStatus::Connected);
+                feed.receive(stream, |tick| forward_tick(&app, &state, tick)).await
+            }
+            Err(e) => Err(e),
+        };
+
+        let error = match result {
+            Ok(()) => "Connection closed by server".to_string(),
+            Err(e) => e.report(),
+        };
+        let delay = backoff.next_delay();
+        tracing::debug!("Feed disconnected ({}), retrying in {:?}", error, delay);
+        let retry_in_secs = delay.as_secs() + u64::from(delay.subsec_nanos() > 0);
+        feed.set_status(&app, FeedStatus::Disconnected { error, retry_in_secs });
+        tokio::time::sleep(delay).await;
+    }
+}
+
+fn forward_tick(app: &AppHandle, state: &AppState, tick: FeedTick) {
+    let quote = [tick.to_market_data()];
+    state.chart_windows.broadcast(&quote);
+    crate::alerts::check_quotes(app, &quote);
+    if let Err(e) = app.emit_all(TICK_EVENT, &tick) {
+        tracing::warn!("Failed to emit {}: {}", TICK_EVENT, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn tick(symbol: &str, price: f64) -> String {
+        let tick = FeedTick {
+            symbol: symbol.to_string(),
+            price,
+            volume: 10,
+            change: 0.0,
+            change_percent: 0.0,
+            timestamp: Utc::now(),
+        };
+        serde_json::to_string(&tick).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_subscribes_and_filters_ticks() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let subscription = socket.next().await.unwrap().unwrap().into_text().unwrap();
+            for text in [tick("AAPL", 190.0), tick("MSFT", 410.0), "heartbeat".to_string(), tick("AAPL", 191.0)] {
+                socket.send(Message::Text(text)).await.unwrap();
+            }
+            socket.close(None).await.unwrap();
+            subscription
+        });
+
+        let feed = FeedClient::new(url, ["AAPL".to_string()]);
+        let stream = feed.connect().await.unwrap();
+        let mut ticks = Vec::new();
+        feed.receive(stream, |tick| ticks.push(tick)).await.unwrap();
+
+        assert_eq!(ticks.iter().map(|t| t.price).collect::<Vec<_>>(), vec![190.0, 191.0]);
+        assert!(server.await.unwrap().contains("\"AAPL\""));
+        // 断开后订阅只更新本地
+        feed.subscribe(&["MSFT".to_string()]).await.unwrap();
+        assert_eq!(feed.symbols(), vec!["AAPL", "MSFT"]);
+    }
+}
@@ -0,0 +1,219 @@
+//! 导入外部历史行情
+//!
+//! 面向券商、数据站导出的文件：CSV 按列映射解析，JSON 支持本应用导出的校验数据集、行情数组和任意对象数组
+//! (对象键视为表头)。每行经 `utils::validation` 校验后按代码写入本地缓存
+
+use crate::cache::CandleCache;
+use alpha_core::errors::{AlphaError, AlphaResult};
+use alpha_core::models::MarketData;
+use alpha_core::utils::checksum::ChecksummedDataset;
+use alpha_core::utils::csv::{parse_csv, parse_records, CsvMapping};
+use alpha_core::utils::validation;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// 疑似坏点的 MAD 阈值
+const OUTLIER_THRESHOLD: f64 = 3.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    Csv,
+    Json,
+}
+
+impl ImportFormat {
+    /// 优先使用显式指定的格式，否则按扩展名判断 (未知扩展名按 CSV 处理)
+    pub fn detect(path: &Path, format: Option<&str>) -> AlphaResult<Self> {
+        let format = match format {
+            Some(format) => format.to_ascii_lowercase(),
+            None => path.extension()
+                .and_then(|ext| ext.to_str())
+                .map(str::to_ascii_lowercase)
+                .unwrap_or_default(),
+        };
+        match format.as_str() {
+            "json" => Ok(Self::Json),
+            "csv" | "txt" | "" => Ok(Self::Csv),
+            other => Err(AlphaError::invalid_input(format!("Unsupported import format: {}", other))),
+        }
+    }
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReport {
+    /// 写入缓存的行数
+    pub imported: usize,
+    /// 校验未通过而跳过的行数
+    pub skipped: usize,
+    pub symbols: Vec<String>,
+    pub first: Option<DateTime<Utc>>,
+    pub last: Option<DateTime<Utc>>,
+    /// 不影响导入的提示，如疑似坏点
+    pub warnings: Vec<String>,
+}
+
+/// 读取文件、校验并按代码写入缓存
+pub fn import_file(
+    cache: &CandleCache,
+    path: &Path,
+    format: ImportFormat,
+    mapping: &CsvMapping,
+    timeframe: Duration,
+) -> AlphaResult<ImportReport> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| AlphaError::StorageError(format!("Failed to read {}", path.display())).caused_by(e))?;
+    let data = match format {
+        ImportFormat::Csv => parse_csv(&bytes, mapping)?,
+        ImportFormat::Json => parse_json(&bytes, mapping)?,
+    };
+    let (data, skipped) = validate_rows(data, mapping.skip_invalid)?;
+    if data.is_empty() {
+        return Err(AlphaError::invalid_input("No valid rows to import"));
+    }
+
+    let mut by_symbol: BTreeMap<String, Vec<MarketData>> = BTreeMap::new();
+    for row in data {
+        by_symbol.entry(row.symbol.clone()).or_default().push(row);
+    }
+
+    let mut report = ImportReport {
+        imported: 0,
+        skipped,
+        symbols: by_symbol.keys().cloned().collect(),
+        first: None,
+        last: None,
+        warnings: Vec::new(),
+    };
+    for (symbol, rows) in &mut by_symbol {
+        rows.sort_by_key(|d| d.timestamp);
+        let prices: Vec<f64> = rows.iter().map(|d| d.price).collect();
+        for index in validation::detect_price_outliers(&prices, OUTLIER_THRESHOLD) {
+            report.warnings.push(format!("{} at {}: suspected bad quote {}", symbol, rows[index].timestamp, rows[index].price));
+        }
+
+        cache.store(symbol, timeframe, rows)
+            .map_err(|e| e.context(format!("Storing {}", symbol)))?;
+        report.imported += rows.len();
+        let (first, last) = (rows[0].timestamp, rows[rows.len() - 1].timestamp);
+        report.first = Some(report.first.map_or(first, |t| t.min(first)));
+        report.last = Some(report.last.map_or(last, |t| t.max(last)));
+    }
+    Ok(report)
+}
+
+/// 解析 JSON：校验数据集 (校验摘要)、行情数组，或按 `mapping` 解析的对象数组
+pub fn parse_json(bytes: &[u8], mapping: &CsvMapping) -> AlphaResult<Vec<MarketData>> {
+    let value: Value = serde_json::from_slice(bytes)
+        .map_err(|e| AlphaError::invalid_input("File is not valid JSON").caused_by(e))?;
+
+    if value.get("checksum").is_some() {
+        let dataset: ChecksummedDataset = serde_json::from_value(value)
+            .map_err(|e| AlphaError::DataCorrupted("Malformed checksummed dataset".to_string()).caused_by(e))?;
+        return dataset.into_verified();
+    }
+
+    let Value::Array(items) = value else {
+        return Err(AlphaError::invalid_input("JSON must be an array of records"));
+    };
+    if let Ok(data) = serde_json::from_value::<Vec<MarketData>>(Value::Array(items.clone())) {
+        return Ok(data);
+    }
+
+    // 表头取所有对象键的并集，缺失的键视为空字段
+    let mut header: Vec<String> = Vec::new();
+    for item in &items {
+        let Value::Object(object) = item else {
+            return Err(AlphaError::invalid_input("JSON array must contain only objects"));
+        };
+        for key in object.keys() {
+            if !header.contains(key) {
+                header.push(key.clone());
+            }
+        }
+    }
+    let records = items.iter().map(|item| {
+        header.iter()
+            .map(|key| match item.get(key) {
+                None | Some(Value::Null) => String::new(),
+                Some(Value::String(s)) => s.clone(),
+                Some(other) => other.to_string(),
+            })
+            .collect()
+    });
+    parse_records(&header, records, mapping)
+}
+
+/// 逐行校验，返回通过的行和跳过的行数；`skip_invalid` 为 false 时遇到无效行即报错
+pub fn validate_rows(data: Vec<MarketData>, skip_invalid: bool) -> AlphaResult<(Vec<MarketData>, usize)> {
+    let mut valid = Vec::with_capacity(data.len());
+    let mut skipped = 0;
+    for row in data {
+        let result = validation::validate_market_data(&row)
+            .and_then(|_| validation::validate_price_range(row.price, &row.symbol));
+        match result {
+            Ok(()) => valid.push(row),
+            Err(_) if skip_invalid => skipped += 1,
+            Err(e) => return Err(e.context(format!("{} at {}", row.symbol, row.timestamp))),
+        }
+    }
+    Ok((valid, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str, content: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("alpha-import-{}-{}", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_import_csv_with_timezone() {
+        let cache = CandleCache::open_in_memory().unwrap();
+        let path = temp_file("broker.csv", "日期,开盘,最高,最低,收盘,成交量\n\
+            2024-01-03 09:30:00,10.1,10.5,10.0,10.4,1000\n\
+            2024-01-02 09:30:00,10.0,10.2,9.9,10.1,800\n\
+            2024-01-04 09:30:00,10.4,10.6,10.3,-1,900\n");
+        let mapping = CsvMapping {
+            symbol: Some("600519.SS".to_string()),
+            timezone: Some("Asia/Shanghai".to_string()),
+            ..CsvMapping::default()
+        };
+
+        assert!(import_file(&cache, &path, ImportFormat::Csv, &mapping, Duration::days(1)).is_err());
+
+        let mapping = CsvMapping { skip_invalid: true, ..mapping };
+        let report = import_file(&cache, &path, ImportFormat::Csv, &mapping, Duration::days(1)).unwrap();
+        assert_eq!((report.imported, report.skipped), (2, 1));
+        assert_eq!(report.symbols, vec!["600519.SS"]);
+        assert_eq!(report.first.unwrap().to_rfc3339(), "2024-01-02T01:30:00+00:00");
+
+        let cached = cache.load("600519.SS", Duration::days(1), 10).unwrap();
+        assert_eq!(cached.len(), 2);
+        assert_eq!(cached[1].price, 10.4);
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_parse_json_objects() {
+        let json = r#"[
+            {"ticker": "AAPL", "date": "2024-01-02", "close": 185.6, "volume": 1000},
+            {"ticker": "AAPL", "date": "2024-01-03", "close": "184.2"}
+        ]"#;
+        let data = parse_json(json.as_bytes(), &CsvMapping::default()).unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[1].price, 184.2);
+        assert_eq!(data[1].volume, 0);
+
+        assert!(parse_json(b"{\"rows\": []}", &CsvMapping::default()).is_err());
+        assert_eq!(ImportFormat::detect(Path::new("a.JSON"), None).unwrap(), ImportFormat::Json);
+        assert!(ImportFormat::detect(Path::new("a.json"), Some("xlsx")).is_err());
+    }
+}
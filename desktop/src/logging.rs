@@ -0,0 +1,189 @@
+//! 日志输出
+//!
+//! 日志同时写入终端和数据目录下的 `logs/alpha.log`。文件超过大小上限时轮转为 `alpha.log.1`、`alpha.log.2` …，
+//! 只保留固定数量的旧文件；日志级别可在运行时调整，方便用户反馈问题时临时打开调试日志
+
+use alpha_core::errors::{AlphaError, AlphaResult};
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+
+/// 当前日志文件名
+pub const LOG_FILE_NAME: &str = "alpha.log";
+/// 单个日志文件大小上限
+const MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+/// 保留的旧日志文件数
+const MAX_ROTATED_FILES: usize = 4;
+/// 未设置 RUST_LOG 时的默认级别
+const DEFAULT_LEVEL: &str = "info";
+
+/// 按大小轮转的日志文件
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    pub fn open(path: PathBuf, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, max_bytes, max_files, file, size })
+    }
+
+    /// 第 `index` 个旧文件，数字越大越旧
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_files == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            fs::remove_file(self.rotated_path(self.max_files)).ok();
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+            self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // 空文件直接写入，避免单条超长日志反复轮转
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// 当前日志配置，返回给界面
+#[derive(Debug, Clone, Serialize)]
+pub struct LogInfo {
+    /// 过滤规则，如 `info` 或 `alpha_desktop=debug,info`
+    pub level: String,
+    pub directory: PathBuf,
+}
+
+/// 已安装的日志系统，用于运行时调整级别
+#[derive(Debug)]
+pub struct Logging {
+    filter: reload::Handle<EnvFilter, Registry>,
+    level: Mutex<String>,
+    directory: PathBuf,
+}
+
+impl Logging {
+    pub fn info(&self) -> LogInfo {
+        LogInfo {
+            level: self.level.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+            directory: self.directory.clone(),
+        }
+    }
+
+    /// 调整日志级别，支持 EnvFilter 的过滤规则语法
+    pub fn set_level(&self, level: &str) -> AlphaResult<LogInfo> {
+        let filter = parse_filter(level)?;
+        self.filter.reload(filter)
+            .map_err(|e| AlphaError::internal("Failed to reload log filter").caused_by(e))?;
+        *self.level.lock().unwrap_or_else(|e| e.into_inner()) = level.trim().to_string();
+        tracing::info!("Log level set to {}", level.trim());
+        Ok(self.info())
+    }
+}
+
+fn parse_filter(level: &str) -> AlphaResult<EnvFilter> {
+    let level = level.trim();
+    if level.is_empty() {
+        return Err(AlphaError::invalid_input("Log level must not be empty"));
+    }
+    EnvFilter::try_new(level)
+        .map_err(|e| AlphaError::invalid_input(format!("Invalid log level {}", level)).caused_by(e))
+}
+
+/// 安装全局日志，文件写入 `directory`
+///
+/// 初始级别取 RUST_LOG，未设置或无效时为 info
+pub fn init(directory: &Path) -> AlphaResult<Logging> {
+    fs::create_dir_all(directory).map_err(|e| {
+        AlphaError::StorageError(format!("Failed to create log directory {}", directory.display())).caused_by(e)
+    })?;
+    let path = directory.join(LOG_FILE_NAME);
+    let file = RotatingFile::open(path.clone(), MAX_FILE_BYTES, MAX_ROTATED_FILES).map_err(|e| {
+        AlphaError::StorageError(format!("Failed to open log file {}", path.display())).caused_by(e)
+    })?;
+
+    let level = std::env::var("RUST_LOG").ok()
+        .filter(|level| parse_filter(level).is_ok())
+        .unwrap_or_else(|| DEFAULT_LEVEL.to_string());
+    let (filter, handle) = reload::Layer::new(parse_filter(&level)?);
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .with(fmt::layer().with_ansi(false).with_writer(Mutex::new(file)))
+        .try_init()
+        .map_err(|e| AlphaError::internal("Failed to install logger").caused_by(e))?;
+
+    Ok(Logging { filter: handle, level: Mutex::new(level), directory: directory.to_path_buf() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotates_and_caps_files() {
+        let dir = std::env::temp_dir().join(format!("alpha-logs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut file = RotatingFile::open(dir.join(LOG_FILE_NAME), 16, 2).unwrap();
+
+        for line in ["first line\n", "second line\n", "third line\n", "fourth line\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(dir.join(LOG_FILE_NAME)).unwrap(), "fourth line\n");
+        assert_eq!(fs::read_to_string(dir.join("alpha.log.1")).unwrap(), "third line\n");
+        assert_eq!(fs::read_to_string(dir.join("alpha.log.2")).unwrap(), "second line\n");
+        assert!(!dir.join("alpha.log.3").exists());
+
+        // 重新打开时沿用已有大小
+        let reopened = RotatingFile::open(dir.join(LOG_FILE_NAME), 16, 2).unwrap();
+        assert_eq!(reopened.size, 12);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        assert!(parse_filter("debug").is_ok());
+        assert!(parse_filter("alpha_desktop=trace,info").is_ok());
+        assert!(parse_filter("  ").is_err());
+        assert!(parse_filter("alpha_desktop=loud").is_err());
+    }
+}
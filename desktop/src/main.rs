@@ -5,37 +5,103 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use alpha_core::{models::*, analytics::AnalysisEngine};
+use alpha_core::utils::series::parse_timeframe;
+use alpha_core::utils::checksum::{self, ChecksumAlgorithm, ChecksummedDataset};
+use alpha_core::errors::AlphaResult;
+use alpha_core::screener::{ScreenCriteria, ScreenResult};
+use alpha_core::portfolio::{self, PortfolioValuation, PriceSnapshot, TradeSide, Transaction};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use alpha_providers::{DataProvider, ProviderChain, ProviderSettings, SymbolMatch};
 use tauri::{Manager, State};
 
+mod alerts;
+mod analysis;
+mod backup;
+mod cache;
+mod chart_data;
+mod chart_window;
+mod deep_link;
+mod feed;
+mod import;
+mod logging;
+mod parquet_io;
+mod portfolio;
+mod scheduler;
+mod screener;
+mod scripting;
+mod secrets;
+mod settings;
+mod tray;
+mod updater;
+mod workspace;
+
+use alpha_core::utils::csv::CsvMapping;
+use alerts::{AlertEdit, AlertStore, AlertSummary, AlertTrigger};
+use analysis::WatchlistSummary;
+use backup::{BackupDirs, BackupManifest};
+use cache::{CandleCache, CompactionReport};
+use chart_data::{ChartData, OverlayRequest};
+use chart_window::{ChartWindowState, ChartWindows};
+use deep_link::PendingLinks;
+use feed::{FeedClient, FeedStatus};
+use import::{ImportFormat, ImportReport};
+use logging::{LogInfo, Logging};
+use portfolio::PortfolioStore;
+use scheduler::Scheduler;
+use scripting::{ScriptEdit, ScriptOutput, ScriptRunner, ScriptStore, UserScript};
+use secrets::{Keyring, SecretStore};
+use settings::{Settings, SettingsError, SettingsStore};
+use std::sync::atomic::AtomicBool;
+use std::sync::RwLock;
+use tray::TrayQuotes;
+use updater::{UpdateChannel, UpdateInfo};
+use workspace::{Workspace, WorkspaceList, WorkspaceSnapshot, WorkspaceStore};
+
+/// 默认请求的 K 线数量
+const DEFAULT_CANDLE_LIMIT: usize = 200;
+
+/// 检查缓存是否需要自动整理的间隔
+const CACHE_MAINTENANCE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// 设置更新后发送给所有窗口的事件，载荷为新的 [`Settings`]
+const SETTINGS_CHANGED_EVENT: &str = "settings-changed";
+
 /// 应用状态
 #[derive(Debug)]
 struct AppState {
     analysis_engine: AnalysisEngine,
+    /// 数据源回退链，更新 API Key 后整体替换
+    provider: RwLock<ProviderChain>,
+    /// 当前设置 (数据源不含 API Key)
+    settings: SettingsStore,
+    cache: CandleCache,
+    portfolio: PortfolioStore,
+    alerts: AlertStore,
+    /// 用户自定义指标和信号脚本
+    scripts: ScriptStore,
+    /// 弹出的图表窗口及其行情广播
+    chart_windows: ChartWindows,
+    workspaces: WorkspaceStore,
+    /// real-time-feed 实时行情连接
+    feed: FeedClient,
+    scheduler: Scheduler,
+    tray_quotes: TrayQuotes,
+    /// 托盘菜单中暂停告警
+    alerts_paused: AtomicBool,
     config_dir: PathBuf,
     data_dir: PathBuf,
 }
 
-/// 配置结构
-#[derive(Debug, Serialize, Deserialize)]
-struct AppConfig {
-    api_url: String,
-    symbols: Vec<String>,
-    theme: String,
-    auto_update: bool,
-}
+impl AppState {
+    fn provider(&self) -> ProviderChain {
+        self.provider.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
 
-impl Default for AppConfig {
-    fn default() -> Self {
-        Self {
-            api_url: "http://localhost:8080".to_string(),
-            symbols: vec!["AAPL".to_string(), "GOOGL".to_string(), "MSFT".to_string()],
-            theme: "light".to_string(),
-            auto_update: true,
-        }
+    fn backup_dirs(&self) -> BackupDirs {
+        BackupDirs { config_dir: self.config_dir.clone(), data_dir: self.data_dir.clone() }
     }
 }
 
@@ -51,15 +117,43 @@ struct AnalyzeRequest {
 #[derive(Debug, Deserialize)]
 struct ExportRequest {
     symbols: Vec<String>,
-    format: String, // "csv", "json", "excel"
+    format: String, // "csv", "json", "parquet"
     date_range: Option<DateRange>,
 }
 
+/// 记录交易请求
+#[derive(Debug, Deserialize)]
+struct TransactionRequest {
+    symbol: String,
+    side: TradeSide,
+    quantity: f64,
+    price: f64,
+    #[serde(default)]
+    fee: f64,
+    /// 成交时间，缺省为当前时间
+    timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    note: Option<String>,
+}
+
+/// 导入外部行情文件请求
+#[derive(Debug, Deserialize)]
+struct ImportRequest {
+    path: PathBuf,
+    /// "csv" 或 "json"，缺省按扩展名判断
+    format: Option<String>,
+    #[serde(default)]
+    mapping: CsvMapping,
+    /// 不带时区的时间所在时区，覆盖 `mapping.timezone`
+    timezone: Option<String>,
+    /// 写入缓存的 K 线周期，如 `1d`
+    timeframe: String,
+}
+
 /// Tauri 命令实现
 
 /// 初始化应用
 #[tauri::command]
-async fn initialize_app(app_handle: tauri::AppHandle) -> Result<AppConfig, String> {
+async fn initialize_app(app_handle: tauri::AppHandle) -> Result<Settings, String> {
     // 获取应用目录
     let app_dir = app_handle.path_resolver().app_config_dir()
         .ok_or("无法获取配置目录")?;
@@ -70,107 +164,374 @@ async fn initialize_app(app_handle: tauri::AppHandle) -> Result<AppConfig, Strin
     fs::create_dir_all(&app_dir).map_err(|e| format!("创建配置目录失败: {}", e))?;
     fs::create_dir_all(&data_dir).map_err(|e| format!("创建数据目录失败: {}", e))?;
 
-    // 读取或创建配置文件
-    let config_path = app_dir.join("config.json");
-    let config = if config_path.exists() {
-        let content = fs::read_to_string(&config_path)
-            .map_err(|e| format!("读取配置文件失败: {}", e))?;
-        serde_json::from_str(&content)
-            .map_err(|e| format!("解析配置文件失败: {}", e))?
-    } else {
-        let config = AppConfig::default();
-        let content = serde_json::to_string_pretty(&config)
-            .map_err(|e| format!("序列化配置失败: {}", e))?;
-        fs::write(&config_path, content)
-            .map_err(|e| format!("写入配置文件失败: {}", e))?;
-        config
-    };
+    // 读取或创建配置文件，旧版本按版本迁移
+    let settings = SettingsStore::open(app_dir.join("config.json"))
+        .map_err(|e| format!("读取配置文件失败: {}", e.report()))?;
+
+    // 旧版配置中的明文 API Key 迁移到系统钥匙串
+    let mut providers = settings.get().providers;
+    match secrets::migrate_api_keys(&mut providers, &Keyring) {
+        Ok(true) => {
+            settings.modify(|s| s.providers = providers)
+                .map_err(|e| format!("写入配置文件失败: {}", e.report()))?;
+        }
+        Ok(false) => {}
+        Err(e) => tracing::warn!("API Key 迁移失败，暂时保留在配置文件中: {}", e.report()),
+    }
+    let config = settings.get();
+
+    let provider = build_provider(&config.providers)?;
+
+    let cache = CandleCache::open(&data_dir.join("market_data.db"))
+        .map_err(|e| format!("打开本地行情缓存失败: {}", e.report()))?;
+    let portfolio = PortfolioStore::open(data_dir.join("portfolio.json"))
+        .map_err(|e| format!("读取持仓记录失败: {}", e.report()))?;
+    let alerts = AlertStore::open(app_dir.join("alerts.json"), data_dir.join("alert_history.jsonl"))
+        .map_err(|e| format!("读取告警配置失败: {}", e.report()))?;
+    let scripts = ScriptStore::open(app_dir.join("scripts.json"))
+        .map_err(|e| format!("读取用户脚本失败: {}", e.report()))?;
+    let chart_windows = ChartWindows::open(app_dir.join("chart_windows.json"))
+        .map_err(|e| format!("读取图表窗口状态失败: {}", e.report()))?;
+    let workspaces = WorkspaceStore::open(app_dir.join("workspaces.json"))
+        .map_err(|e| format!("读取工作区失败: {}", e.report()))?;
 
     // 初始化应用状态
     let state = AppState {
         analysis_engine: AnalysisEngine::new(),
+        provider: RwLock::new(provider),
+        settings,
+        cache,
+        portfolio,
+        alerts,
+        scripts,
+        chart_windows,
+        workspaces,
+        feed: FeedClient::new(config.feed_url.clone().unwrap_or_default(), config.symbols.clone()),
+        scheduler: Scheduler::new(config.refresh_jobs.clone(), config.symbols.clone()),
+        tray_quotes: TrayQuotes::default(),
+        alerts_paused: AtomicBool::new(false),
         config_dir: app_dir,
         data_dir,
     };
 
     app_handle.manage(state);
+    chart_window::restore(&app_handle, &app_handle.state::<AppState>().chart_windows);
+    // 标签页和面板布局由前端通过 get_workspace 读取，这里只恢复主窗口位置
+    let geometry = app_handle.state::<AppState>().workspaces.active().and_then(|w| w.window);
+    if let (Some(window), Some(geometry)) = (app_handle.get_window("main"), geometry) {
+        workspace::restore_geometry(&window, &geometry);
+    }
+    deep_link::dispatch_pending(&app_handle);
+
+    if config.auto_update {
+        app_handle.state::<AppState>().scheduler.start(app_handle.clone());
+    }
+    if config.feed_url.is_some() {
+        app_handle.state::<AppState>().feed.start(app_handle.clone());
+    }
+    spawn_cache_maintenance(app_handle);
 
     Ok(config)
 }
 
+/// 后台定期按设置的间隔整理缓存，每次检查时读取最新设置
+fn spawn_cache_maintenance(app_handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            // 启动后先等待一段时间，避免与启动时的数据加载争用缓存
+            tokio::time::sleep(CACHE_MAINTENANCE_INTERVAL).await;
+            let handle = app_handle.clone();
+            let result = tauri::async_runtime::spawn_blocking(move || {
+                let state = handle.state::<AppState>();
+                state.cache.compact_if_due(&state.settings.get().cache, chrono::Utc::now())
+            }).await;
+            match result {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => tracing::warn!("自动整理缓存失败: {}", e.report()),
+                Err(e) => tracing::warn!("自动整理缓存任务异常退出: {}", e),
+            }
+        }
+    });
+}
+
 /// 分析股票数据
 #[tauri::command]
 async fn analyze_symbol(
     request: AnalyzeRequest,
     state: State<'_, AppState>,
 ) -> Result<AnalysisResult, String> {
-    // 这里应该从 API 或本地缓存获取数据
-    let market_data = fetch_market_data(&request.symbol).await
-        .map_err(|e| format!("获取市场数据失败: {}", e))?;
+    let timeframe = parse_timeframe(&request.timeframe).map_err(|e| format!("无效的时间周期: {}", e))?;
+    let market_data = cache::cached_candles(&state.cache, &state.provider(), &request.symbol, timeframe, DEFAULT_CANDLE_LIMIT)
+        .await
+        .map_err(|e| format!("获取市场数据失败: {}", e.report()))?;
 
     if market_data.is_empty() {
         return Err("没有找到市场数据".to_string());
     }
 
     // 执行分析
-    let analysis_result = state.analysis_engine
+    let mut analysis_result = state.analysis_engine
         .analyze_symbol(&market_data, None)
         .await
-        .map_err(|e| format!("分析失败: {}", e))?;
+        .map_err(|e| format!("分析失败: {}", e.report()))?;
+
+    // 启用的用户脚本追加指标和信号，单个脚本失败不影响分析结果
+    let runner = ScriptRunner::default();
+    for script in state.scripts.enabled() {
+        match runner.run(&script, &market_data) {
+            Ok(ScriptOutput::Indicator(indicator)) => analysis_result.indicators.push(indicator),
+            Ok(ScriptOutput::Signal(signal)) => analysis_result.signals.extend(signal),
+            Err(e) => tracing::warn!("Script {} failed on {}: {}", script.name, request.symbol, e.report()),
+        }
+    }
 
     Ok(analysis_result)
 }
 
+/// 并发分析自选列表中的全部代码，每完成一个发送 `analysis-progress` 事件，返回按信号排序的汇总
+///
+/// `watchlist` 为刷新任务中的列表名称，为空时分析配置中的 `symbols`
+#[tauri::command]
+async fn analyze_watchlist(
+    watchlist: Option<String>,
+    timeframe: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<WatchlistSummary, String> {
+    let timeframe = parse_timeframe(timeframe.as_deref().unwrap_or("1d")).map_err(|e| format!("无效的时间周期: {}", e))?;
+    let (name, symbols) = watchlist_symbols(&state.settings.get(), watchlist)?;
+
+    let summary = analysis::analyze_watchlist(
+        &state.analysis_engine,
+        &state.provider(),
+        &state.cache,
+        &name,
+        &symbols,
+        timeframe,
+        analysis::DEFAULT_CONCURRENCY,
+        |progress| {
+            if let Err(e) = app_handle.emit_all(analysis::ANALYSIS_PROGRESS_EVENT, progress) {
+                tracing::warn!("Failed to emit {}: {}", analysis::ANALYSIS_PROGRESS_EVENT, e);
+            }
+        },
+    ).await;
+    Ok(summary)
+}
+
+/// 选股请求
+#[derive(Debug, Deserialize)]
+struct ScreenRequest {
+    criteria: ScreenCriteria,
+    /// 扫描的代码，为空时使用 `watchlist` 对应的自选列表
+    #[serde(default)]
+    symbols: Vec<String>,
+    #[serde(default)]
+    watchlist: Option<String>,
+    #[serde(default)]
+    timeframe: Option<String>,
+    /// 先向数据源补齐缓存，默认只读本地缓存
+    #[serde(default)]
+    refresh: bool,
+}
+
+/// 按条件 (指标阈值、价格/成交量) 扫描代码，返回命中的代码、得分及条件中各项的取值
+#[tauri::command]
+async fn screen_symbols(request: ScreenRequest, state: State<'_, AppState>) -> Result<ScreenResult, String> {
+    let timeframe = parse_timeframe(request.timeframe.as_deref().unwrap_or("1d")).map_err(|e| format!("无效的时间周期: {}", e))?;
+    let symbols = if request.symbols.is_empty() {
+        watchlist_symbols(&state.settings.get(), request.watchlist)?.1
+    } else {
+        request.symbols
+    };
+
+    let provider = state.provider();
+    let provider = request.refresh.then_some(&provider as &dyn DataProvider);
+    screener::screen_symbols(&state.cache, provider, &symbols, timeframe, request.criteria)
+        .await
+        .map_err(|e| format!("选股失败: {}", e.report()))
+}
+
+/// 全部用户脚本
+#[tauri::command]
+async fn list_scripts(state: State<'_, AppState>) -> Result<Vec<UserScript>, String> {
+    Ok(state.scripts.list())
+}
+
+/// 新建 (未指定 id) 或修改用户脚本，保存前校验语法并试运行
+#[tauri::command]
+async fn save_script(id: Option<String>, script: ScriptEdit, state: State<'_, AppState>) -> Result<UserScript, String> {
+    let id = match id {
+        Some(id) => parse_script_id(&id)?,
+        None => uuid::Uuid::new_v4(),
+    };
+    state.scripts.save(&ScriptRunner::default(), script.into_script(id))
+        .map_err(|e| format!("保存脚本失败: {}", e.report()))
+}
+
+/// 删除用户脚本，不存在时返回 false
+#[tauri::command]
+async fn delete_script(id: String, state: State<'_, AppState>) -> Result<bool, String> {
+    state.scripts.remove(parse_script_id(&id)?)
+        .map_err(|e| format!("删除脚本失败: {}", e.report()))
+}
+
+/// 校验脚本而不保存，返回错误说明
+#[tauri::command]
+async fn validate_script(script: ScriptEdit) -> Result<(), String> {
+    ScriptRunner::default().validate(&script.into_script(uuid::Uuid::nil()))
+        .map_err(|e| e.report())
+}
+
+/// 在标的的 K 线上运行已保存的脚本
+#[tauri::command]
+async fn run_script(
+    id: String,
+    symbol: String,
+    timeframe: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ScriptOutput, String> {
+    let script = state.scripts.get(parse_script_id(&id)?).map_err(|e| format!("读取脚本失败: {}", e.report()))?;
+    let timeframe = parse_timeframe(timeframe.as_deref().unwrap_or("1d")).map_err(|e| format!("无效的时间周期: {}", e))?;
+    let market_data = cache::cached_candles(&state.cache, &state.provider(), &symbol, timeframe, DEFAULT_CANDLE_LIMIT)
+        .await
+        .map_err(|e| format!("获取市场数据失败: {}", e.report()))?;
+
+    ScriptRunner::default().run(&script, &market_data)
+        .map_err(|e| format!("运行脚本 {} 失败: {}", script.name, e.report()))
+}
+
+fn parse_script_id(id: &str) -> Result<uuid::Uuid, String> {
+    id.parse().map_err(|_| format!("无效的脚本 ID: {}", id))
+}
+
+/// 分析已导出的数据文件 (校验完整性后再分析)
+#[tauri::command]
+async fn analyze_file(
+    path: PathBuf,
+    state: State<'_, AppState>,
+) -> Result<AnalysisResult, String> {
+    let market_data = if is_parquet(&path) {
+        load_parquet(&path, None)
+    } else {
+        load_exported_json(&path)
+    }
+    .map_err(|e| format!("读取数据文件失败: {}", e))?;
+
+    if market_data.is_empty() {
+        return Err("没有找到市场数据".to_string());
+    }
+
+    state.analysis_engine
+        .analyze_symbol(&market_data, None)
+        .await
+        .map_err(|e| format!("分析失败: {}", e.report()))
+}
+
 /// 获取实时行情
 #[tauri::command]
-async fn get_real_time_quotes(symbols: Vec<String>) -> Result<Vec<MarketData>, String> {
+async fn get_real_time_quotes(
+    symbols: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<MarketData>, String> {
     let mut quotes = Vec::new();
 
     for symbol in symbols {
-        let quote = fetch_single_quote(&symbol).await
-            .map_err(|e| format!("获取 {} 行情失败: {}", symbol, e))?;
+        let quote = state.provider().quote(&symbol).await
+            .map_err(|e| format!("获取 {} 行情失败: {}", symbol, e.report()))?;
         quotes.push(quote);
     }
 
     Ok(quotes)
 }
 
+/// 按代码或名称搜索标的
+#[tauri::command]
+async fn search_symbols(query: String, state: State<'_, AppState>) -> Result<Vec<SymbolMatch>, String> {
+    state.provider().search(&query).await
+        .map_err(|e| format!("搜索 {} 失败: {}", query, e.report()))
+}
+
 /// 设置价格告警
 #[tauri::command]
 async fn set_price_alert(
     symbol: String,
     target_price: f64,
     alert_type: String, // "above" or "below"
+    message: Option<String>,
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
     state: State<'_, AppState>,
 ) -> Result<String, String> {
-    // 保存告警配置到本地文件
-    let alerts_path = state.config_dir.join("alerts.json");
-
-    let mut alerts: HashMap<String, AlertConfig> = if alerts_path.exists() {
-        let content = fs::read_to_string(&alerts_path)
-            .map_err(|e| format!("读取告警配置失败: {}", e))?;
-        serde_json::from_str(&content)
-            .map_err(|e| format!("解析告警配置失败: {}", e))?
-    } else {
-        HashMap::new()
+    let condition = match alert_type.as_str() {
+        "above" => AlertCondition::PriceAbove { target: target_price },
+        "below" => AlertCondition::PriceBelow { target: target_price },
+        other => return Err(format!("不支持的告警类型: {}", other)),
     };
 
-    let alert_id = format!("{}_{}", symbol, chrono::Utc::now().timestamp());
+    let mut alert = Alert::new(symbol, condition);
+    alert.message = message;
+    alert.expires_at = expires_at;
+    let alert = state.alerts.create(alert)
+        .map_err(|e| format!("保存告警配置失败: {}", e.report()))?;
 
-    alerts.insert(alert_id.clone(), AlertConfig {
-        symbol,
-        target_price,
-        alert_type,
-        created_at: chrono::Utc::now(),
-        active: true,
-    });
+    Ok(alert.id.to_string())
+}
 
-    let content = serde_json::to_string_pretty(&alerts)
-        .map_err(|e| format!("序列化告警配置失败: {}", e))?;
-    fs::write(alerts_path, content)
-        .map_err(|e| format!("保存告警配置失败: {}", e))?;
+/// 创建任意条件的告警 (涨跌幅、均线/RSI 穿越、放量、52 周新高新低等)，返回告警 ID
+#[tauri::command]
+async fn create_alert(alert: AlertEdit, state: State<'_, AppState>) -> Result<String, String> {
+    let mut created = Alert::new(alert.symbol, alert.condition);
+    created.message = alert.message;
+    created.expires_at = alert.expires_at;
+    let created = state.alerts.create(created)
+        .map_err(|e| format!("保存告警配置失败: {}", e.report()))?;
 
-    Ok(alert_id)
+    Ok(created.id.to_string())
+}
+
+/// 列出全部告警及其状态
+#[tauri::command]
+async fn list_alerts(state: State<'_, AppState>) -> Result<Vec<AlertSummary>, String> {
+    Ok(state.alerts.list(&chrono::Utc::now()))
+}
+
+/// 修改告警的标的、条件、说明和过期时间
+#[tauri::command]
+async fn update_alert(id: String, edit: AlertEdit, state: State<'_, AppState>) -> Result<Alert, String> {
+    state.alerts.edit(parse_alert_id(&id)?, edit)
+        .map_err(|e| format!("修改告警失败: {}", e.report()))
+}
+
+/// 删除告警，不存在时返回 false
+#[tauri::command]
+async fn delete_alert(id: String, state: State<'_, AppState>) -> Result<bool, String> {
+    state.alerts.remove(parse_alert_id(&id)?)
+        .map_err(|e| format!("删除告警失败: {}", e.report()))
+}
+
+/// 启用或停用告警
+#[tauri::command]
+async fn set_alert_enabled(id: String, enabled: bool, state: State<'_, AppState>) -> Result<Alert, String> {
+    state.alerts.set_active(parse_alert_id(&id)?, enabled)
+        .map_err(|e| format!("更新告警失败: {}", e.report()))
+}
+
+/// 暂停告警若干分钟，0 表示立即恢复
+#[tauri::command]
+async fn snooze_alert(id: String, minutes: u32, state: State<'_, AppState>) -> Result<Alert, String> {
+    let until = (minutes > 0).then(|| chrono::Utc::now() + chrono::Duration::minutes(i64::from(minutes)));
+    state.alerts.snooze(parse_alert_id(&id)?, until)
+        .map_err(|e| format!("暂停告警失败: {}", e.report()))
+}
+
+fn parse_alert_id(id: &str) -> Result<uuid::Uuid, String> {
+    id.parse().map_err(|_| format!("无效的告警 ID: {}", id))
+}
+
+/// 最近的告警触发记录，按时间倒序
+#[tauri::command]
+async fn get_alert_history(limit: Option<usize>, state: State<'_, AppState>) -> Result<Vec<AlertTrigger>, String> {
+    state.alerts.history(limit.unwrap_or(100))
+        .map_err(|e| format!("读取告警记录失败: {}", e.report()))
 }
 
 /// 导出数据
@@ -186,12 +547,14 @@ async fn export_data(
     // 为每个符号生成文件
     let mut exported_files = Vec::new();
     for symbol in &request.symbols {
-        let market_data = fetch_market_data(symbol).await
-            .map_err(|e| format!("获取 {} 数据失败: {}", symbol, e))?;
+        let market_data = cache::cached_candles(&state.cache, &state.provider(), symbol, chrono::Duration::days(1), DEFAULT_CANDLE_LIMIT)
+            .await
+            .map_err(|e| format!("获取 {} 数据失败: {}", symbol, e.report()))?;
 
         let filename = match request.format.as_str() {
             "csv" => export_to_csv(&market_data, &export_dir, symbol)?,
             "json" => export_to_json(&market_data, &export_dir, symbol)?,
+            "parquet" => export_to_parquet(&market_data, &export_dir, symbol)?,
             _ => return Err("不支持的导出格式".to_string()),
         };
 
@@ -201,6 +564,404 @@ async fn export_data(
     Ok(format!("成功导出 {} 个文件", exported_files.len()))
 }
 
+/// 记录一笔交易
+#[tauri::command]
+async fn record_transaction(
+    request: TransactionRequest,
+    state: State<'_, AppState>,
+) -> Result<Transaction, String> {
+    let mut transaction = Transaction::new(request.symbol, request.side, request.quantity, request.price);
+    transaction.fee = request.fee;
+    transaction.note = request.note;
+    if let Some(timestamp) = request.timestamp {
+        transaction.timestamp = timestamp;
+    }
+
+    state.portfolio.record(transaction.clone())
+        .map_err(|e| format!("记录交易失败: {}", e.report()))?;
+    Ok(transaction)
+}
+
+/// 获取全部交易记录
+#[tauri::command]
+async fn list_transactions(state: State<'_, AppState>) -> Result<Vec<Transaction>, String> {
+    Ok(state.portfolio.transactions())
+}
+
+/// 删除交易记录
+#[tauri::command]
+async fn delete_transaction(id: String, state: State<'_, AppState>) -> Result<bool, String> {
+    let id = id.parse().map_err(|_| format!("无效的交易 ID: {}", id))?;
+    state.portfolio.remove(id)
+        .map_err(|e| format!("删除交易失败: {}", e.report()))
+}
+
+/// 获取持仓市值与盈亏
+#[tauri::command]
+async fn get_portfolio(state: State<'_, AppState>) -> Result<PortfolioValuation, String> {
+    portfolio_valuation(&state).await
+}
+
+/// 导出持仓估值 (csv) 或交易记录与估值 (json)
+#[tauri::command]
+async fn export_portfolio(format: String, state: State<'_, AppState>) -> Result<String, String> {
+    let valuation = portfolio_valuation(&state).await?;
+    let export_dir = state.data_dir.join("exports");
+    fs::create_dir_all(&export_dir)
+        .map_err(|e| format!("创建导出目录失败: {}", e))?;
+
+    let filename = match format.as_str() {
+        "csv" => export_portfolio_csv(&valuation, &export_dir),
+        "json" => export_portfolio_json(&state.portfolio.transactions(), &valuation, &export_dir),
+        _ => return Err("不支持的导出格式".to_string()),
+    };
+    filename.map_err(|e| format!("导出持仓失败: {}", e))
+}
+
+/// 导入 Parquet 行情文件到本地缓存，返回导入的记录数
+///
+/// 文件可包含多个标的；不含 symbol 列时使用 `symbol` 参数
+#[tauri::command]
+async fn import_parquet(
+    path: PathBuf,
+    timeframe: String,
+    symbol: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let timeframe = parse_timeframe(&timeframe).map_err(|e| format!("无效的时间周期: {}", e))?;
+    let market_data = load_parquet(&path, symbol.as_deref())
+        .map_err(|e| format!("读取数据文件失败: {}", e))?;
+
+    let mut by_symbol: HashMap<&str, Vec<MarketData>> = HashMap::new();
+    for data in &market_data {
+        by_symbol.entry(data.symbol.as_str()).or_default().push(data.clone());
+    }
+    for (symbol, data) in &by_symbol {
+        state.cache.store(symbol, timeframe, data)
+            .map_err(|e| format!("写入 {} 缓存失败: {}", symbol, e.report()))?;
+    }
+
+    Ok(market_data.len())
+}
+
+/// 导入券商或数据站导出的 CSV/JSON 行情，校验后写入本地缓存
+#[tauri::command]
+async fn import_data(request: ImportRequest, state: State<'_, AppState>) -> Result<ImportReport, String> {
+    let timeframe = parse_timeframe(&request.timeframe).map_err(|e| format!("无效的时间周期: {}", e))?;
+    let format = ImportFormat::detect(&request.path, request.format.as_deref())
+        .map_err(|e| format!("无法识别文件格式: {}", e.report()))?;
+    let mut mapping = request.mapping;
+    if request.timezone.is_some() {
+        mapping.timezone = request.timezone;
+    }
+
+    import::import_file(&state.cache, &request.path, format, &mapping, timeframe)
+        .map_err(|e| format!("导入 {} 失败: {}", request.path.display(), e.report()))
+}
+
+/// 备份设置、自选股、告警、图表窗口、持仓和本地行情缓存，未指定路径时写入数据目录下的 backups，返回备份文件路径
+#[tauri::command]
+async fn export_backup(path: Option<PathBuf>, state: State<'_, AppState>) -> Result<PathBuf, String> {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            let backup_dir = state.data_dir.join("backups");
+            fs::create_dir_all(&backup_dir).map_err(|e| format!("创建备份目录失败: {}", e))?;
+            backup_dir.join(backup::backup_file_name(&chrono::Utc::now()))
+        }
+    };
+
+    backup::export_backup(&state.cache, &state.backup_dirs(), &path)
+        .map_err(|e| format!("生成备份失败: {}", e.report()))?;
+    Ok(path)
+}
+
+/// 立即整理本地行情缓存：删除损坏和重叠的 K 线，按保留设置清理后压缩文件
+#[tauri::command]
+async fn compact_cache(state: State<'_, AppState>) -> Result<CompactionReport, String> {
+    state.cache.compact(&state.settings.get().cache, chrono::Utc::now())
+        .map_err(|e| format!("整理缓存失败: {}", e.report()))
+}
+
+/// 从备份恢复全部本地数据，完成后重启应用以重新加载
+#[tauri::command]
+async fn import_backup(
+    path: PathBuf,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<BackupManifest, String> {
+    // 恢复期间停止会写入缓存和告警记录的后台任务
+    let scheduler_running = state.scheduler.is_running();
+    state.scheduler.stop();
+    state.feed.stop();
+
+    match backup::import_backup(&state.cache, &state.backup_dirs(), &path) {
+        Ok(manifest) => {
+            tracing::info!("Restored backup {} created at {}", path.display(), manifest.created_at);
+            app_handle.restart();
+            Ok(manifest)
+        }
+        Err(e) => {
+            let config = state.settings.get();
+            if scheduler_running {
+                state.scheduler.start(app_handle.clone());
+            }
+            if config.feed_url.is_some() {
+                state.feed.start(app_handle);
+            }
+            Err(format!("恢复备份失败: {}", e.report()))
+        }
+    }
+}
+
+/// 图表数据：`[from, to]` 内按周期重采样的 K 线及指标叠加线，未指定范围时返回最近 200 根
+#[tauri::command]
+async fn get_chart_data(
+    symbol: String,
+    timeframe: String,
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
+    indicators: Option<Vec<OverlayRequest>>,
+    state: State<'_, AppState>,
+) -> Result<ChartData, String> {
+    let timeframe = parse_timeframe(&timeframe).map_err(|e| format!("无效的时间周期: {}", e))?;
+    chart_data::chart_data(
+        &state.cache,
+        &state.provider(),
+        &symbol,
+        timeframe,
+        from,
+        to,
+        &indicators.unwrap_or_default(),
+        chrono::Utc::now(),
+    )
+    .await
+    .map_err(|e| format!("获取 {} 图表数据失败: {}", symbol, e.report()))
+}
+
+/// 在独立窗口中打开标的图表，已打开时聚焦该窗口，返回窗口 label
+#[tauri::command]
+async fn open_chart_window(
+    symbol: String,
+    timeframe: Option<String>,
+    indicators: Option<Vec<String>>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let chart = ChartWindowState {
+        symbol: symbol.clone(),
+        timeframe: timeframe.unwrap_or_else(|| "1d".to_string()),
+        indicators: indicators.unwrap_or_default(),
+    };
+    chart_window::show(&app_handle, &state.chart_windows, chart)
+        .map_err(|e| format!("打开 {} 图表窗口失败: {}", symbol, e.report()))
+}
+
+/// 图表窗口读取自身状态，非图表窗口返回 None
+#[tauri::command]
+async fn get_chart_window_state(window: tauri::Window, state: State<'_, AppState>) -> Result<Option<ChartWindowState>, String> {
+    Ok(state.chart_windows.get(window.label()))
+}
+
+/// 图表窗口切换标的、周期或指标后保存状态
+#[tauri::command]
+async fn save_chart_window_state(
+    chart: ChartWindowState,
+    window: tauri::Window,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    if state.chart_windows.get(window.label()).is_none() {
+        return Err("当前窗口不是图表窗口".to_string());
+    }
+    let title = format!("{} - Alpha Finance", chart.symbol);
+    state.chart_windows.upsert(window.label(), chart)
+        .map_err(|e| format!("保存图表窗口状态失败: {}", e.report()))?;
+    if let Err(e) = window.set_title(&title) {
+        tracing::warn!("更新窗口标题失败: {}", e);
+    }
+    Ok(())
+}
+
+/// 列出已打开的图表窗口 (label 到状态)
+#[tauri::command]
+async fn list_chart_windows(state: State<'_, AppState>) -> Result<BTreeMap<String, ChartWindowState>, String> {
+    Ok(state.chart_windows.states())
+}
+
+/// 列出已保存的工作区
+#[tauri::command]
+async fn list_workspaces(state: State<'_, AppState>) -> Result<WorkspaceList, String> {
+    Ok(state.workspaces.list())
+}
+
+/// 读取工作区，未指定名称时读取当前工作区；启动时前端据此恢复标签页和布局
+#[tauri::command]
+async fn get_workspace(name: Option<String>, state: State<'_, AppState>) -> Result<Option<Workspace>, String> {
+    match name {
+        Some(name) => state.workspaces.get(&name).map(Some).map_err(|e| format!("读取工作区失败: {}", e.report())),
+        None => Ok(state.workspaces.active()),
+    }
+}
+
+/// 保存当前界面为工作区 (同名覆盖) 并设为当前工作区，窗口位置和图表窗口由后端读取
+#[tauri::command]
+async fn save_workspace(
+    name: String,
+    snapshot: WorkspaceSnapshot,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Workspace, String> {
+    let workspace = Workspace {
+        window: app_handle.get_window("main").and_then(|window| workspace::capture_geometry(&window)),
+        charts: state.chart_windows.states().into_values().collect(),
+        ..Workspace::new(name, snapshot)
+    };
+    state.workspaces.save(workspace)
+        .map_err(|e| format!("保存工作区失败: {}", e.report()))
+}
+
+/// 切换工作区：恢复主窗口位置和图表窗口，返回的标签页和布局由前端恢复
+#[tauri::command]
+async fn switch_workspace(
+    name: String,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Workspace, String> {
+    let workspace = state.workspaces.set_active(&name)
+        .map_err(|e| format!("切换工作区失败: {}", e.report()))?;
+    workspace::apply_charts(&app_handle, &state.chart_windows, &workspace.charts);
+    if let (Some(window), Some(geometry)) = (app_handle.get_window("main"), workspace.window.as_ref()) {
+        workspace::restore_geometry(&window, geometry);
+    }
+    Ok(workspace)
+}
+
+/// 删除工作区，不存在时返回 false
+#[tauri::command]
+async fn delete_workspace(name: String, state: State<'_, AppState>) -> Result<bool, String> {
+    state.workspaces.remove(&name)
+        .map_err(|e| format!("删除工作区失败: {}", e.report()))
+}
+
+/// 订阅实时行情，行情通过 `tick` 事件推送
+#[tauri::command]
+async fn subscribe_ticks(symbols: Vec<String>, state: State<'_, AppState>) -> Result<(), String> {
+    state.feed.subscribe(&symbols).await
+        .map_err(|e| format!("订阅实时行情失败: {}", e.report()))
+}
+
+/// 取消订阅实时行情
+#[tauri::command]
+async fn unsubscribe_ticks(symbols: Vec<String>, state: State<'_, AppState>) -> Result<(), String> {
+    state.feed.unsubscribe(&symbols).await
+        .map_err(|e| format!("取消订阅实时行情失败: {}", e.report()))
+}
+
+/// 实时行情连接状态及已订阅代码
+#[tauri::command]
+async fn get_feed_status(state: State<'_, AppState>) -> Result<(FeedStatus, Vec<String>), String> {
+    Ok((state.feed.status(), state.feed.symbols()))
+}
+
+/// 检查更新，指定通道时先切换并保存到配置
+#[tauri::command]
+async fn check_for_updates(
+    channel: Option<UpdateChannel>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<UpdateInfo, String> {
+    let mut settings = state.settings.get();
+    if let Some(channel) = channel.filter(|channel| *channel != settings.updates.channel) {
+        settings = state.settings.modify(|s| s.updates.channel = channel)
+            .map_err(|e| format!("保存更新通道失败: {}", e.report()))?;
+    }
+    updater::check(&app_handle, &settings.updates).await
+        .map_err(|e| format!("检查更新失败: {}", e.report()))
+}
+
+/// 下载并安装当前通道的新版本，成功后应用自动重启；没有新版本时返回 false
+#[tauri::command]
+async fn apply_update(app_handle: tauri::AppHandle, state: State<'_, AppState>) -> Result<bool, String> {
+    updater::apply(&app_handle, &state.settings.get().updates).await
+        .map_err(|e| format!("安装更新失败: {}", e.report()))
+}
+
+/// 读取当前设置
+#[tauri::command]
+async fn get_settings(state: State<'_, AppState>) -> Result<Settings, String> {
+    Ok(state.settings.get())
+}
+
+/// 校验并保存设置，立即应用到数据源、定时刷新和实时行情，并通知所有窗口
+#[tauri::command]
+async fn update_settings(
+    settings: Settings,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Settings, SettingsError> {
+    let registry = alpha_providers::default_registry();
+    let previous = state.settings.update(settings, registry.names())?;
+    let current = state.settings.get();
+
+    if current.providers != previous.providers {
+        let chain = build_provider(&current.providers).map_err(|message| SettingsError::Failed { message })?;
+        *state.provider.write().unwrap_or_else(|e| e.into_inner()) = chain;
+    }
+
+    if current.refresh_jobs != previous.refresh_jobs || current.symbols != previous.symbols {
+        state.scheduler.reconfigure(app_handle.clone(), current.refresh_jobs.clone(), current.symbols.clone());
+    }
+    match (current.auto_update, state.scheduler.is_running()) {
+        (true, false) => state.scheduler.start(app_handle.clone()),
+        (false, true) => state.scheduler.stop(),
+        _ => {}
+    }
+
+    if current.feed_url != previous.feed_url {
+        match &current.feed_url {
+            Some(url) => {
+                state.feed.set_url(app_handle.clone(), url.clone());
+                if previous.feed_url.is_none() {
+                    state.feed.start(app_handle.clone());
+                }
+            }
+            None => state.feed.stop(),
+        }
+    }
+
+    if let Err(e) = app_handle.emit_all(SETTINGS_CHANGED_EVENT, &current) {
+        tracing::warn!("发送 {} 事件失败: {}", SETTINGS_CHANGED_EVENT, e);
+    }
+    Ok(current)
+}
+
+/// 保存数据源 API Key 到系统钥匙串 (空字符串表示删除)，并立即重建数据源
+#[tauri::command]
+async fn set_api_key(
+    provider: String,
+    api_key: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let api_key = api_key.trim();
+    let result = if api_key.is_empty() {
+        Keyring.delete(&provider).map(|_| ())
+    } else {
+        Keyring.set(&provider, api_key)
+    };
+    result.map_err(|e| format!("保存 {} 的 API Key 失败: {}", provider, e.report()))?;
+
+    let chain = build_provider(&state.settings.get().providers)?;
+    *state.provider.write().unwrap_or_else(|e| e.into_inner()) = chain;
+    Ok(())
+}
+
+/// 读取钥匙串中的数据源 API Key
+#[tauri::command]
+async fn get_api_key(provider: String) -> Result<Option<String>, String> {
+    Keyring.get(&provider)
+        .map_err(|e| format!("读取 {} 的 API Key 失败: {}", provider, e.report()))
+}
+
 /// 获取应用信息
 #[tauri::command]
 async fn get_app_info() -> Result<AppInfo, String> {
@@ -212,17 +973,20 @@ async fn get_app_info() -> Result<AppInfo, String> {
     })
 }
 
-// 辅助结构和函数
+/// 获取日志级别和日志目录
+#[tauri::command]
+async fn get_log_info(logging: State<'_, Logging>) -> Result<LogInfo, String> {
+    Ok(logging.info())
+}
 
-#[derive(Debug, Serialize, Deserialize)]
-struct AlertConfig {
-    symbol: String,
-    target_price: f64,
-    alert_type: String,
-    created_at: chrono::DateTime<chrono::Utc>,
-    active: bool,
+/// 运行时调整日志级别，如 `debug` 或 `alpha_desktop=trace,info`
+#[tauri::command]
+async fn set_log_level(level: String, logging: State<'_, Logging>) -> Result<LogInfo, String> {
+    logging.set_level(&level).map_err(|e| format!("设置日志级别失败: {}", e.report()))
 }
 
+// 辅助结构和函数
+
 #[derive(Debug, Serialize)]
 struct AppInfo {
     name: String,
@@ -231,50 +995,97 @@ struct AppInfo {
     arch: String,
 }
 
-/// 模拟获取市场数据
-async fn fetch_market_data(symbol: &str) -> Result<Vec<MarketData>, anyhow::Error> {
-    // 这里应该实现真实的数据获取逻辑
-    let mut data = Vec::new();
-    let base_price = 100.0 + (symbol.len() as f64 * 10.0);
-
-    for i in 0..100 {
-        let price = base_price + (i as f64 * 0.5) + (rand::random::<f64>() - 0.5) * 2.0;
-        let volume = 1000 + rand::random::<u64>() % 90000;
-
-        let market_data = MarketData {
-            symbol: symbol.to_string(),
-            timestamp: chrono::Utc::now() - chrono::Duration::minutes((100 - i) as i64),
-            price,
-            volume,
-            bid: Some(price - 0.01),
-            ask: Some(price + 0.01),
-            open: Some(price - 0.1),
-            high: Some(price + 0.2),
-            low: Some(price - 0.3),
-        };
+/// 自选列表名称及其代码；`watchlist` 为刷新任务中的列表名称，为空时取配置中的 `symbols`
+fn watchlist_symbols(config: &Settings, watchlist: Option<String>) -> Result<(String, Vec<String>), String> {
+    let Some(name) = watchlist else {
+        return Ok(("default".to_string(), config.symbols.clone()));
+    };
+    let job = config.refresh_jobs.iter()
+        .find(|job| job.watchlist == name)
+        .ok_or_else(|| format!("自选列表不存在: {}", name))?;
+    let symbols = if job.symbols.is_empty() { config.symbols.clone() } else { job.symbols.clone() };
+    Ok((name, symbols))
+}
+
+/// 按配置组装数据源回退链，API Key 从系统钥匙串读取
+fn build_provider(providers: &[ProviderSettings]) -> Result<ProviderChain, String> {
+    alpha_providers::default_registry()
+        .chain(&secrets::with_api_keys(providers, &Keyring))
+        .map_err(|e| format!("初始化数据源失败: {}", e.report()))
+}
+
+/// 按最新行情为当前持仓估值
+async fn portfolio_valuation(state: &AppState) -> Result<PortfolioValuation, String> {
+    let holdings = state.portfolio.holdings()
+        .map_err(|e| format!("计算持仓失败: {}", e.report()))?;
 
-        data.push(market_data);
+    let mut prices = std::collections::BTreeMap::new();
+    for holding in holdings.iter().filter(|h| h.is_open()) {
+        let snapshot = price_snapshot(state, &holding.symbol).await
+            .map_err(|e| format!("获取 {} 行情失败: {}", holding.symbol, e.report()))?;
+        prices.insert(holding.symbol.clone(), snapshot);
     }
 
-    Ok(data)
+    portfolio::value_holdings(&holdings, &prices)
+        .map_err(|e| format!("计算持仓盈亏失败: {}", e.report()))
 }
 
-/// 模拟获取单个行情
-async fn fetch_single_quote(symbol: &str) -> Result<MarketData, anyhow::Error> {
-    let base_price = 100.0 + (symbol.len() as f64 * 10.0);
-    let price = base_price + (rand::random::<f64>() - 0.5) * 10.0;
+/// 最新价与前收盘价，前收盘取最新价所在日之前的最后一根日线；实时行情不可用时以缓存日线收盘价估值
+async fn price_snapshot(state: &AppState, symbol: &str) -> AlphaResult<PriceSnapshot> {
+    let provider = state.provider();
+    let daily = cache::cached_candles(&state.cache, &provider, symbol, chrono::Duration::days(1), 5).await;
+    let quote = match provider.quote(symbol).await {
+        Ok(quote) => quote,
+        Err(err) => daily.as_ref().ok().and_then(|d| d.last().cloned()).ok_or(err)?,
+    };
 
-    Ok(MarketData {
-        symbol: symbol.to_string(),
-        timestamp: chrono::Utc::now(),
-        price,
-        volume: 1000 + rand::random::<u64>() % 90000,
-        bid: Some(price - 0.01),
-        ask: Some(price + 0.01),
-        open: Some(price - 0.1),
-        high: Some(price + 0.2),
-        low: Some(price - 0.3),
-    })
+    let previous_close = cache::previous_close(&daily.unwrap_or_default(), &quote);
+    Ok(PriceSnapshot { price: quote.price, previous_close })
+}
+
+/// 导出持仓估值到 CSV
+fn export_portfolio_csv(valuation: &PortfolioValuation, export_dir: &Path) -> Result<String, anyhow::Error> {
+    let filename = format!("portfolio_{}.csv", chrono::Utc::now().format("%Y%m%d_%H%M%S"));
+    let filepath = export_dir.join(&filename);
+
+    let mut wtr = csv::Writer::from_path(&filepath)?;
+    wtr.write_record(&[
+        "symbol", "quantity", "average_cost", "price", "market_value",
+        "cost_basis", "unrealized_pnl", "realized_pnl", "daily_change",
+    ])?;
+    for position in &valuation.positions {
+        wtr.write_record(&[
+            &position.symbol,
+            &position.quantity.to_string(),
+            &position.average_cost.to_string(),
+            &position.price.map(|v| v.to_string()).unwrap_or_default(),
+            &position.market_value.to_string(),
+            &position.cost_basis.to_string(),
+            &position.unrealized_pnl.to_string(),
+            &position.realized_pnl.to_string(),
+            &position.daily_change.to_string(),
+        ])?;
+    }
+    wtr.flush()?;
+    drop(wtr);
+
+    checksum::write_sidecar(ChecksumAlgorithm::Sha256, &filepath)?;
+    Ok(filename)
+}
+
+/// 导出交易记录与估值到 JSON
+fn export_portfolio_json(
+    transactions: &[Transaction],
+    valuation: &PortfolioValuation,
+    export_dir: &Path,
+) -> Result<String, anyhow::Error> {
+    let filename = format!("portfolio_{}.json", chrono::Utc::now().format("%Y%m%d_%H%M%S"));
+    let content = serde_json::to_string_pretty(&serde_json::json!({
+        "transactions": transactions,
+        "valuation": valuation,
+    }))?;
+    fs::write(export_dir.join(&filename), content)?;
+    Ok(filename)
 }
 
 /// 导出到 CSV
@@ -301,6 +1112,10 @@ fn export_to_csv(data: &[MarketData], export_dir: &PathBuf, symbol: &str) -> Res
     }
 
     wtr.flush()?;
+    drop(wtr);
+
+    // CSV 无法内嵌摘要，写入旁路文件供导入时校验
+    checksum::write_sidecar(ChecksumAlgorithm::Sha256, &filepath)?;
     Ok(filename)
 }
 
@@ -309,28 +1124,124 @@ fn export_to_json(data: &[MarketData], export_dir: &PathBuf, symbol: &str) -> Re
     let filename = format!("{}_{}.json", symbol, chrono::Utc::now().format("%Y%m%d_%H%M%S"));
     let filepath = export_dir.join(&filename);
 
-    let content = serde_json::to_string_pretty(data)?;
+    let dataset = ChecksummedDataset::new(ChecksumAlgorithm::Sha256, data.to_vec());
+    let content = serde_json::to_string_pretty(&dataset)?;
     fs::write(filepath, content)?;
 
     Ok(filename)
 }
 
+/// 导出到 Parquet，列布局与 data-engine 一致
+fn export_to_parquet(data: &[MarketData], export_dir: &PathBuf, symbol: &str) -> Result<String, anyhow::Error> {
+    let filename = format!("{}_{}.parquet", symbol, chrono::Utc::now().format("%Y%m%d_%H%M%S"));
+    let filepath = export_dir.join(&filename);
+
+    parquet_io::write_market_data(&filepath, data)?;
+    checksum::write_sidecar(ChecksumAlgorithm::Sha256, &filepath)?;
+    Ok(filename)
+}
+
+fn is_parquet(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("parquet"))
+}
+
+/// 读取 Parquet 文件，存在摘要旁路文件时先校验
+fn load_parquet(path: &Path, symbol: Option<&str>) -> Result<Vec<MarketData>, anyhow::Error> {
+    if !checksum::verify_sidecar(path)? {
+        tracing::warn!("No checksum found for {}, skipping integrity check", path.display());
+    }
+    Ok(parquet_io::read_market_data(path, symbol)?)
+}
+
+/// 读取导出的 JSON 数据集，校验摘要后返回行情数据
+fn load_exported_json(path: &Path) -> Result<Vec<MarketData>, anyhow::Error> {
+    let content = fs::read_to_string(path)?;
+    let dataset: ChecksummedDataset = serde_json::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("文件已截断或格式错误: {}", e))?;
+
+    Ok(dataset.into_verified()?)
+}
+
 fn main() {
-    // 初始化日志
-    tracing_subscriber::fmt::init();
+    // 已有实例运行时把链接转交给它并退出
+    tauri_plugin_deep_link::prepare("com.alpha.finance");
 
     tauri::Builder::default()
         .setup(|app| {
-            // 这里可以进行应用初始化
+            // 初始化日志，文件写入数据目录下的 logs
+            let log_dir = app.path_resolver().app_data_dir()
+                .ok_or("无法获取数据目录")?
+                .join("logs");
+            app.manage(logging::init(&log_dir)?);
+
+            // 注册 alpha:// 链接，注册失败不影响启动
+            app.manage(PendingLinks::default());
+            let handle = app.handle();
+            if let Err(e) = tauri_plugin_deep_link::register(deep_link::SCHEME, move |link| deep_link::handle(&handle, &link)) {
+                tracing::warn!("Failed to register {}:// links: {}", deep_link::SCHEME, e);
+            }
+            // Windows 和 Linux 冷启动时链接在命令行参数中
+            if let Some(link) = std::env::args().skip(1).find(|arg| deep_link::is_deep_link(arg)) {
+                deep_link::handle(&app.handle(), &link);
+            }
             Ok(())
         })
+        .system_tray(tray::system_tray())
+        .on_system_tray_event(tray::handle_event)
         .invoke_handler(tauri::generate_handler![
             initialize_app,
             analyze_symbol,
+            analyze_file,
+            analyze_watchlist,
+            screen_symbols,
+            list_scripts,
+            save_script,
+            delete_script,
+            validate_script,
+            run_script,
             get_real_time_quotes,
+            search_symbols,
             set_price_alert,
+            create_alert,
+            list_alerts,
+            update_alert,
+            delete_alert,
+            set_alert_enabled,
+            snooze_alert,
+            get_alert_history,
             export_data,
+            import_parquet,
+            import_data,
+            export_backup,
+            import_backup,
+            compact_cache,
+            get_chart_data,
+            open_chart_window,
+            get_chart_window_state,
+            save_chart_window_state,
+            list_chart_windows,
+            list_workspaces,
+            get_workspace,
+            save_workspace,
+            switch_workspace,
+            delete_workspace,
+            subscribe_ticks,
+            unsubscribe_ticks,
+            get_feed_status,
+            check_for_updates,
+            apply_update,
+            get_settings,
+            update_settings,
+            set_api_key,
+            get_api_key,
+            record_transaction,
+            list_transactions,
+            delete_transaction,
+            get_portfolio,
+            export_portfolio,
             get_app_info,
+            get_log_info,
+            set_log_level,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -340,18 +1251,33 @@ fn main() {
 mod tests {
     use super::*;
 
+    fn simulated_provider() -> ProviderChain {
+        alpha_providers::default_registry()
+            .chain(&[ProviderSettings::new("simulated")])
+            .unwrap()
+    }
+
     #[tokio::test]
     async fn test_fetch_market_data() {
-        let data = fetch_market_data("AAPL").await.unwrap();
+        let data = simulated_provider().candles("AAPL", chrono::Duration::minutes(1), 100).await.unwrap();
         assert!(!data.is_empty());
         assert_eq!(data[0].symbol, "AAPL");
     }
 
-    #[test]
-    fn test_app_config_serialization() {
-        let config = AppConfig::default();
-        let json = serde_json::to_string(&config).unwrap();
-        let deserialized: AppConfig = serde_json::from_str(&json).unwrap();
-        assert_eq!(config.api_url, deserialized.api_url);
+    #[tokio::test]
+    async fn test_json_export_checksum() {
+        let dir = std::env::temp_dir().join(format!("alpha-export-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let data = simulated_provider().candles("AAPL", chrono::Duration::minutes(1), 100).await.unwrap();
+
+        let filename = export_to_json(&data, &dir, "AAPL").unwrap();
+        let path = dir.join(filename);
+        assert_eq!(load_exported_json(&path).unwrap(), data);
+
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(&path, &content[..content.len() / 2]).unwrap();
+        assert!(load_exported_json(&path).is_err());
+
+        fs::remove_dir_all(&dir).ok();
     }
 }
\ No newline at end of file
@@ -0,0 +1,204 @@
+//! Parquet 行情文件读写
+//!
+//! 列布局与 data-engine 的 `stock_quotes` 表一致，导出文件可直接注册为 DataFusion 表；
+//! 读取时按列名查找并转换类型，兼容其他工具写出的整数价格、秒级时间戳等变体
+
+use alpha_core::errors::{AlphaError, AlphaResult};
+use alpha_core::models::MarketData;
+use arrow::array::{Array, ArrayRef, AsArray, Float64Array, StringArray, TimestampMillisecondArray, UInt64Array};
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Field, Float64Type, Schema, SchemaRef, TimeUnit, TimestampMillisecondType, UInt64Type};
+use arrow::record_batch::RecordBatch;
+use chrono::{TimeZone, Utc};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+/// 每个行组的最大行数
+const ROW_GROUP_SIZE: usize = 64 * 1024;
+
+fn timestamp_type() -> DataType {
+    DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()))
+}
+
+/// 行情数据的 Arrow Schema
+pub fn market_data_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("timestamp", timestamp_type(), false),
+        Field::new("price", DataType::Float64, false),
+        Field::new("volume", DataType::UInt64, false),
+        Field::new("open", DataType::Float64, true),
+        Field::new("high", DataType::Float64, true),
+        Field::new("low", DataType::Float64, true),
+    ]))
+}
+
+/// 写入 Parquet 文件
+pub fn write_market_data(path: &Path, data: &[MarketData]) -> AlphaResult<()> {
+    let schema = market_data_schema();
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(data.iter().map(|d| d.symbol.as_str()))),
+        Arc::new(
+            TimestampMillisecondArray::from_iter_values(data.iter().map(|d| d.timestamp.timestamp_millis()))
+                .with_timezone("UTC"),
+        ),
+        Arc::new(Float64Array::from_iter_values(data.iter().map(|d| d.price))),
+        Arc::new(UInt64Array::from_iter_values(data.iter().map(|d| d.volume))),
+        Arc::new(data.iter().map(|d| d.open).collect::<Float64Array>()),
+        Arc::new(data.iter().map(|d| d.high).collect::<Float64Array>()),
+        Arc::new(data.iter().map(|d| d.low).collect::<Float64Array>()),
+    ];
+    let batch = RecordBatch::try_new(schema.clone(), columns).map_err(arrow_error)?;
+
+    let file = File::create(path)
+        .map_err(|e| AlphaError::StorageError(format!("Failed to create {}", path.display())).caused_by(e))?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_max_row_group_size(ROW_GROUP_SIZE)
+        .build();
+    let mut writer = ArrowWriter::try_new(file, schema, Some(properties)).map_err(parquet_error)?;
+    writer.write(&batch).map_err(parquet_error)?;
+    writer.close().map_err(parquet_error)?;
+    Ok(())
+}
+
+/// 读取 Parquet 文件，文件不含 `symbol` 列时使用 `symbol` 参数
+pub fn read_market_data(path: &Path, symbol: Option<&str>) -> AlphaResult<Vec<MarketData>> {
+    let file = File::open(path)
+        .map_err(|e| AlphaError::StorageError(format!("Failed to open {}", path.display())).caused_by(e))?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .and_then(|builder| builder.build())
+        .map_err(parquet_error)?;
+
+    let mut data = Vec::new();
+    for batch in reader {
+        let batch = batch.map_err(arrow_error)?;
+        read_batch(&batch, symbol, &mut data)?;
+    }
+    Ok(data)
+}
+
+fn read_batch(batch: &RecordBatch, symbol: Option<&str>, out: &mut Vec<MarketData>) -> AlphaResult<()> {
+    // optional_column 已将各列转换为目标类型，以下 as_* 不会失败
+    let symbols = optional_column(batch, "symbol", &DataType::Utf8)?;
+    let symbols = symbols.as_ref().map(|a| a.as_string::<i32>());
+    if symbols.is_none() && symbol.is_none() {
+        return Err(AlphaError::invalid_input("Parquet file has no symbol column and no symbol was given"));
+    }
+
+    let timestamps = required_column(batch, "timestamp", &DataType::Timestamp(TimeUnit::Millisecond, None))?;
+    let timestamps = timestamps.as_primitive::<TimestampMillisecondType>();
+    let prices = required_column(batch, "price", &DataType::Float64)?;
+    let prices = prices.as_primitive::<Float64Type>();
+    let volumes = optional_column(batch, "volume", &DataType::UInt64)?;
+    let volumes = volumes.as_ref().map(|a| a.as_primitive::<UInt64Type>());
+    let [open, high, low] = ["open", "high", "low"].map(|name| optional_column(batch, name, &DataType::Float64));
+    let (open, high, low) = (open?, high?, low?);
+    let float = |array: &Option<ArrayRef>, row: usize| {
+        array.as_ref()
+            .map(|a| a.as_primitive::<Float64Type>())
+            .filter(|a| a.is_valid(row))
+            .map(|a| a.value(row))
+    };
+
+    for row in 0..batch.num_rows() {
+        if timestamps.is_null(row) || prices.is_null(row) {
+            return Err(AlphaError::invalid_input(format!("Row {} is missing timestamp or price", row)));
+        }
+        let symbol = match symbols {
+            Some(symbols) if symbols.is_valid(row) => symbols.value(row),
+            _ => symbol.ok_or_else(|| AlphaError::invalid_input(format!("Row {} is missing symbol", row)))?,
+        };
+        let timestamp = Utc.timestamp_millis_opt(timestamps.value(row)).single()
+            .ok_or_else(|| AlphaError::invalid_input(format!("Row {} has an out-of-range timestamp", row)))?;
+
+        out.push(MarketData {
+            symbol: symbol.to_string(),
+            timestamp,
+            price: prices.value(row),
+            volume: volumes.filter(|v| v.is_valid(row)).map(|v| v.value(row)).unwrap_or(0),
+            bid: None,
+            ask: None,
+            open: float(&open, row),
+            high: float(&high, row),
+            low: float(&low, row),
+        });
+    }
+    Ok(())
+}
+
+fn required_column(batch: &RecordBatch, name: &str, data_type: &DataType) -> AlphaResult<ArrayRef> {
+    optional_column(batch, name, data_type)?
+        .ok_or_else(|| AlphaError::invalid_input(format!("Parquet file has no {} column", name)))
+}
+
+/// 按名称取列并转换为目标类型，列不存在时返回 None
+fn optional_column(batch: &RecordBatch, name: &str, data_type: &DataType) -> AlphaResult<Option<ArrayRef>> {
+    batch.column_by_name(name)
+        .map(|column| {
+            cast(column, data_type).map_err(|e| {
+                AlphaError::invalid_input(format!("Column {} cannot be read as {}", name, data_type)).caused_by(e)
+            })
+        })
+        .transpose()
+}
+
+fn arrow_error(err: arrow::error::ArrowError) -> AlphaError {
+    AlphaError::DataCorrupted("Invalid Arrow data".to_string()).caused_by(err)
+}
+
+fn parquet_error(err: parquet::errors::ParquetError) -> AlphaError {
+    AlphaError::DataCorrupted("Invalid Parquet file".to_string()).caused_by(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alpha_core::simulate::MarketSimulator;
+    use chrono::Duration;
+
+    #[test]
+    fn test_round_trip() {
+        let path = std::env::temp_dir().join(format!("alpha-parquet-{}.parquet", std::process::id()));
+        let start = Utc.with_ymd_and_hms(2025, 1, 2, 0, 0, 0).unwrap();
+        let mut data = MarketSimulator::gbm(100.0, 0.05, 0.3, 7).unwrap()
+            .generate_bars("AAPL", start, Duration::minutes(1), 500);
+        data[3].open = None;
+
+        write_market_data(&path, &data).unwrap();
+        assert_eq!(read_market_data(&path, None).unwrap(), data);
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_reads_foreign_layout() {
+        let path = std::env::temp_dir().join(format!("alpha-parquet-foreign-{}.parquet", std::process::id()));
+        // 其他工具常见的布局：无代码列、秒级时间戳、整数价格
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("timestamp", DataType::Timestamp(TimeUnit::Second, None), false),
+            Field::new("price", DataType::Int64, false),
+        ]));
+        let batch = RecordBatch::try_new(schema.clone(), vec![
+            Arc::new(arrow::array::TimestampSecondArray::from(vec![1_700_000_000, 1_700_000_060])),
+            Arc::new(arrow::array::Int64Array::from(vec![101, 102])),
+        ]).unwrap();
+        let mut writer = ArrowWriter::try_new(File::create(&path).unwrap(), schema, None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        assert!(read_market_data(&path, None).is_err());
+        let data = read_market_data(&path, Some("BTCUSDT")).unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[1].price, 102.0);
+        assert_eq!(data[1].timestamp.timestamp(), 1_700_000_060);
+        assert_eq!(data[0].open, None);
+
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,112 @@
+//! 本地持仓流水
+//!
+//! 交易流水以 JSON 保存在数据目录，持仓成本与盈亏每次由流水重新推算，不单独存储
+
+use alpha_core::errors::{AlphaError, AlphaResult};
+use alpha_core::portfolio::{self, Holding, Transaction};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub struct PortfolioStore {
+    path: PathBuf,
+    transactions: Mutex<Vec<Transaction>>,
+}
+
+impl PortfolioStore {
+    /// 打开流水文件，文件不存在时为空组合
+    pub fn open(path: PathBuf) -> AlphaResult<Self> {
+        let transactions = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|e| {
+                AlphaError::StorageError(format!("Failed to read {}", path.display())).caused_by(e)
+            })?;
+            serde_json::from_str(&content).map_err(|e| {
+                AlphaError::DataCorrupted(format!("Malformed portfolio file {}", path.display())).caused_by(e)
+            })?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self { path, transactions: Mutex::new(transactions) })
+    }
+
+    /// 全部流水，按成交时间升序
+    pub fn transactions(&self) -> Vec<Transaction> {
+        let mut transactions = self.lock().clone();
+        transactions.sort_by_key(|t| t.timestamp);
+        transactions
+    }
+
+    pub fn holdings(&self) -> AlphaResult<Vec<Holding>> {
+        portfolio::holdings(&self.lock())
+    }
+
+    /// 校验并追加一笔流水，写盘成功后才生效
+    pub fn record(&self, transaction: Transaction) -> AlphaResult<()> {
+        transaction.validate()?;
+        let mut transactions = self.lock();
+        let mut updated = transactions.clone();
+        updated.push(transaction);
+        self.save(&updated)?;
+        *transactions = updated;
+        Ok(())
+    }
+
+    /// 删除流水，不存在时返回 false
+    pub fn remove(&self, id: Uuid) -> AlphaResult<bool> {
+        let mut transactions = self.lock();
+        let mut updated = transactions.clone();
+        updated.retain(|t| t.id != id);
+        if updated.len() == transactions.len() {
+            return Ok(false);
+        }
+        self.save(&updated)?;
+        *transactions = updated;
+        Ok(true)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Transaction>> {
+        self.transactions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 先写临时文件再替换，避免写入中断损坏已有流水
+    fn save(&self, transactions: &[Transaction]) -> AlphaResult<()> {
+        let content = serde_json::to_string_pretty(transactions)
+            .map_err(|e| AlphaError::SerializationError("Failed to serialize transactions".to_string()).caused_by(e))?;
+        let temp = self.path.with_extension("json.tmp");
+        fs::write(&temp, content)
+            .and_then(|_| fs::rename(&temp, &self.path))
+            .map_err(|e| AlphaError::StorageError(format!("Failed to write {}", self.path.display())).caused_by(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alpha_core::portfolio::TradeSide;
+
+    #[test]
+    fn test_record_and_reload() {
+        let dir = std::env::temp_dir().join(format!("alpha-portfolio-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("portfolio.json");
+
+        let store = PortfolioStore::open(path.clone()).unwrap();
+        let buy = Transaction::new("AAPL".to_string(), TradeSide::Buy, 10.0, 150.0);
+        let id = buy.id;
+        store.record(buy).unwrap();
+        store.record(Transaction::new("AAPL".to_string(), TradeSide::Sell, 4.0, 160.0)).unwrap();
+        assert!(store.record(Transaction::new("AAPL".to_string(), TradeSide::Buy, -1.0, 1.0)).is_err());
+
+        let reloaded = PortfolioStore::open(path).unwrap();
+        assert_eq!(reloaded.transactions().len(), 2);
+        assert_eq!(reloaded.holdings().unwrap()[0].quantity, 6.0);
+
+        assert!(reloaded.remove(id).unwrap());
+        assert!(!reloaded.remove(id).unwrap());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
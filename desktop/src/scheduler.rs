@@ -0,0 +1,272 @@
+//! 定时刷新行情
+//!
+//! 每个刷新任务对应一个自选列表的代码和刷新间隔：周期拉取最新行情 (可选同时补齐缓存 K 线)，
+//! 完成后向前端发送 `data-updated` 事件。默认只刷新所属市场处于交易时段 (含盘前盘后) 的代码
+
+use crate::cache::{self, CandleCache};
+use crate::{AppState, DEFAULT_CANDLE_LIMIT};
+use alpha_core::models::{Exchange, MarketData};
+use alpha_core::utils::series::parse_timeframe;
+use alpha_core::utils::symbol::{AssetClass, Symbol};
+use alpha_core::utils::time::{session_at, MarketSession};
+use alpha_providers::DataProvider;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Manager};
+
+/// 刷新完成后发送给前端的事件名
+pub const DATA_UPDATED_EVENT: &str = "data-updated";
+
+/// 最短刷新间隔 (秒)，避免配置错误导致数据源限流
+pub const MIN_INTERVAL_SECS: u64 = 5;
+
+/// 刷新任务配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RefreshJob {
+    /// 自选列表名称，随事件一起发送
+    pub watchlist: String,
+    /// 要刷新的代码，为空时使用配置中的 `symbols`
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    /// 刷新间隔 (秒)
+    pub interval_secs: u64,
+    /// 同时刷新缓存的 K 线周期，如 `1d`，为空时只刷新行情
+    #[serde(default)]
+    pub timeframe: Option<String>,
+    /// 只在市场交易时段刷新
+    #[serde(default = "default_market_hours_only")]
+    pub market_hours_only: bool,
+}
+
+fn default_market_hours_only() -> bool {
+    true
+}
+
+impl RefreshJob {
+    pub fn new(watchlist: impl Into<String>, interval_secs: u64) -> Self {
+        Self {
+            watchlist: watchlist.into(),
+            symbols: Vec::new(),
+            interval_secs,
+            timeframe: None,
+            market_hours_only: true,
+        }
+    }
+
+    /// 本次需要刷新的代码
+    pub fn due_symbols(&self, default_symbols: &[String], now: &DateTime<Utc>) -> Vec<String> {
+        let symbols = if self.symbols.is_empty() { default_symbols } else { &self.symbols };
+        symbols.iter()
+            .filter(|symbol| !self.market_hours_only || is_market_open(symbol, now))
+            .cloned()
+            .collect()
+    }
+}
+
+/// 一次刷新的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct DataUpdated {
+    pub watchlist: String,
+    pub quotes: Vec<MarketData>,
+    /// 已更新缓存 K 线的代码
+    pub candles: Vec<String>,
+    /// 刷新失败的代码及原因
+    pub errors: BTreeMap<String, String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// 代码所属市场此刻是否在交易时段；加密货币全天交易，未标明交易所的股票按纽交所判断，无法识别的代码视为开市
+pub fn is_market_open(symbol: &str, now: &DateTime<Utc>) -> bool {
+    match Symbol::parse(symbol) {
+        Ok(symbol) if symbol.asset_class == AssetClass::Crypto => true,
+        Ok(symbol) => session_at(symbol.exchange.unwrap_or(Exchange::Nyse), now) != MarketSession::Closed,
+        Err(_) => true,
+    }
+}
+
+/// 刷新一组代码，单个代码失败不影响其他代码
+pub async fn refresh(
+    provider: &dyn DataProvider,
+    cache: &CandleCache,
+    job: &RefreshJob,
+    symbols: &[String],
+) -> DataUpdated {
+    let mut update = DataUpdated {
+        watchlist: job.watchlist.clone(),
+        quotes: Vec::new(),
+        candles: Vec::new(),
+        errors: BTreeMap::new(),
+        timestamp: Utc::now(),
+    };
+    let timeframe = job.timeframe.as_deref().map(parse_timeframe).transpose();
+
+    for symbol in symbols {
+        match provider.quote(symbol).await {
+            Ok(quote) => update.quotes.push(quote),
+            Err(e) => {
+                update.errors.insert(symbol.clone(), e.report());
+                continue;
+            }
+        }
+
+        let result = match &timeframe {
+            Ok(Some(timeframe)) => cache::cached_candles(cache, provider, symbol, *timeframe, DEFAULT_CANDLE_LIMIT)
+                .await
+                .map(|_| update.candles.push(symbol.clone())),
+            Ok(None) => Ok(()),
+            Err(e) => Err(e.clone()),
+        };
+        if let Err(e) = result {
+            update.errors.insert(symbol.clone(), e.report());
+        }
+    }
+
+    update
+}
+
+/// 刷新任务调度器，每个任务一个后台循环
+#[derive(Debug)]
+pub struct Scheduler {
+    config: Mutex<SchedulerConfig>,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+}
+
+#[derive(Debug, Clone)]
+struct SchedulerConfig {
+    jobs: Vec<RefreshJob>,
+    /// 任务未指定代码时使用的默认代码
+    default_symbols: Vec<String>,
+}
+
+impl Scheduler {
+    pub fn new(jobs: Vec<RefreshJob>, default_symbols: Vec<String>) -> Self {
+        Self {
+            config: Mutex::new(SchedulerConfig { jobs, default_symbols }),
+            tasks: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 替换任务配置，正在运行时按新配置重启
+    pub fn reconfigure(&self, app: AppHandle, jobs: Vec<RefreshJob>, default_symbols: Vec<String>) {
+        *self.config.lock().unwrap_or_else(|e| e.into_inner()) = SchedulerConfig { jobs, default_symbols };
+        if self.is_running() {
+            self.start(app);
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.lock().is_empty()
+    }
+
+    /// 启动全部任务，已有任务先停止
+    pub fn start(&self, app: AppHandle) {
+        let config = self.config();
+        let tasks = config.jobs
+            .into_iter()
+            .map(|job| {
+                let app = app.clone();
+                let default_symbols = config.default_symbols.clone();
+                tauri::async_runtime::spawn(async move {
+                    let period = Duration::from_secs(job.interval_secs.max(MIN_INTERVAL_SECS));
+                    let mut ticker = tokio::time::interval(period);
+                    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+                    loop {
+                        ticker.tick().await;
+                        let symbols = job.due_symbols(&default_symbols, &Utc::now());
+                        if !symbols.is_empty() {
+                            publish(&app, &job, &symbols).await;
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let previous = std::mem::replace(&mut *self.lock(), tasks);
+        previous.into_iter().for_each(|task| task.abort());
+    }
+
+    /// 停止全部任务
+    pub fn stop(&self) {
+        self.lock().drain(..).for_each(|task| task.abort());
+    }
+
+    /// 立即刷新全部任务的代码 (不考虑交易时段)，不影响定时任务
+    pub fn refresh_now(&self, app: AppHandle) {
+        let SchedulerConfig { jobs, default_symbols } = self.config();
+        tauri::async_runtime::spawn(async move {
+            for job in &jobs {
+                let symbols = if job.symbols.is_empty() { &default_symbols } else { &job.symbols };
+                publish(&app, job, symbols).await;
+            }
+        });
+    }
+
+    fn config(&self) -> SchedulerConfig {
+        self.config.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<JoinHandle<()>>> {
+        self.tasks.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// 刷新并通知前端、托盘与图表窗口，同时检查告警
+async fn publish(app: &AppHandle, job: &RefreshJob, symbols: &[String]) {
+    let state = app.state::<AppState>();
+    let update = refresh(&state.provider(), &state.cache, job, symbols).await;
+
+    crate::tray::record_quotes(app, &update.quotes);
+    crate::alerts::check_quotes(app, &update.quotes);
+    state.chart_windows.broadcast(&update.quotes);
+    if let Err(e) = app.emit_all(DATA_UPDATED_EVENT, &update) {
+        tracing::warn!("Failed to emit {} for {}: {}", DATA_UPDATED_EVENT, job.watchlist, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alpha_providers::SimulatedProvider;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_due_symbols_follow_market_hours() {
+        let defaults = vec!["AAPL".to_string(), "BTC-USDT".to_string(), "0700.HK".to_string()];
+        let job = RefreshJob::new("default", 60);
+
+        // 周六：只有加密货币在交易
+        let saturday = Utc.with_ymd_and_hms(2025, 3, 8, 15, 0, 0).unwrap();
+        assert_eq!(job.due_symbols(&defaults, &saturday), vec!["BTC-USDT"]);
+
+        // 周三 15:00 UTC：美股开盘，港股已收市
+        let wednesday = Utc.with_ymd_and_hms(2025, 3, 5, 15, 0, 0).unwrap();
+        assert_eq!(job.due_symbols(&defaults, &wednesday), vec!["AAPL", "BTC-USDT"]);
+
+        let always = RefreshJob { market_hours_only: false, symbols: vec!["MSFT".to_string()], ..job };
+        assert_eq!(always.due_symbols(&defaults, &saturday), vec!["MSFT"]);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_collects_quotes_and_candles() {
+        let cache = CandleCache::open_in_memory().unwrap();
+        let job = RefreshJob { timeframe: Some("1h".to_string()), ..RefreshJob::new("tech", 60) };
+        let symbols = vec!["AAPL".to_string(), "MSFT".to_string()];
+
+        let update = refresh(&SimulatedProvider, &cache, &job, &symbols).await;
+        assert_eq!(update.watchlist, "tech");
+        assert_eq!(update.quotes.len(), 2);
+        assert_eq!(update.candles, symbols);
+        assert!(update.errors.is_empty());
+        assert!(!cache.load("MSFT", chrono::Duration::hours(1), 10).unwrap().is_empty());
+
+        let invalid = RefreshJob { timeframe: Some("7x".to_string()), ..job };
+        let update = refresh(&SimulatedProvider, &cache, &invalid, &symbols).await;
+        assert_eq!(update.quotes.len(), 2);
+        assert_eq!(update.errors.len(), 2);
+    }
+}
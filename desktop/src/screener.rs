@@ -0,0 +1,81 @@
+//! 基于本地缓存的条件选股
+//!
+//! 条件解析与求值由 core 的 [`Screener`] 完成，K 线取自本地缓存；
+//! 需要时先向数据源补齐，缓存中没有数据的代码列入 `skipped`
+
+use crate::cache::{self, CandleCache};
+use crate::DEFAULT_CANDLE_LIMIT;
+use alpha_core::errors::AlphaResult;
+use alpha_core::indicators::registry::IndicatorRegistry;
+use alpha_core::indicators::TechnicalIndicators;
+use alpha_core::screener::{ScreenCriteria, ScreenResult, Screener};
+use alpha_providers::DataProvider;
+use chrono::Duration;
+use std::collections::BTreeMap;
+
+/// 按条件扫描 `symbols`
+///
+/// `provider` 为 None 时只读缓存，否则先补齐缓存 (数据源失败时退回缓存)；条件无效时直接返回错误
+pub async fn screen_symbols(
+    cache: &CandleCache,
+    provider: Option<&dyn DataProvider>,
+    symbols: &[String],
+    timeframe: Duration,
+    criteria: ScreenCriteria,
+) -> AlphaResult<ScreenResult> {
+    let registry = IndicatorRegistry::new();
+    let indicators = TechnicalIndicators::new();
+    let screener = Screener::new(&registry, &indicators, criteria)?;
+
+    let mut universe = BTreeMap::new();
+    for symbol in symbols {
+        let data = match provider {
+            Some(provider) => cache::cached_candles(cache, provider, symbol, timeframe, DEFAULT_CANDLE_LIMIT).await,
+            None => cache.load(symbol, timeframe, DEFAULT_CANDLE_LIMIT),
+        };
+        // 读取失败与没有数据一样，交给 Screener 记为 skipped
+        let data = data.unwrap_or_else(|e| {
+            tracing::debug!("No candles for {}: {}", symbol, e.report());
+            Vec::new()
+        });
+        universe.insert(symbol.clone(), data);
+    }
+
+    Ok(screener.scan(&universe))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alpha_core::models::MarketData;
+    use chrono::{TimeZone, Utc};
+
+    fn candles(symbol: &str, prices: impl Iterator<Item = f64>) -> Vec<MarketData> {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        prices.enumerate()
+            .map(|(i, price)| MarketData {
+                timestamp: start + Duration::days(i as i64),
+                ..MarketData::new(symbol.to_string(), price, 1000 + i as u64)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_screens_cached_universe() {
+        let cache = CandleCache::open_in_memory().unwrap();
+        let day = Duration::days(1);
+        cache.store("UP", day, &candles("UP", (0..60).map(|i| 100.0 + i as f64))).unwrap();
+        cache.store("DOWN", day, &candles("DOWN", (0..60).map(|i| 200.0 - i as f64))).unwrap();
+        let symbols = vec!["DOWN".to_string(), "MISSING".to_string(), "UP".to_string()];
+
+        let criteria = ScreenCriteria::new("price > SMA(20) AND volume > 1000");
+        let result = screen_symbols(&cache, None, &symbols, day, criteria).await.unwrap();
+        assert_eq!(result.scanned, 3);
+        assert_eq!(result.skipped, vec!["MISSING".to_string()]);
+        assert_eq!(result.matches.len(), 1);
+        assert_eq!(result.matches[0].symbol, "UP");
+        assert_eq!(result.matches[0].values["volume"], 1059.0);
+
+        assert!(screen_symbols(&cache, None, &symbols, day, ScreenCriteria::new("price >")).await.is_err());
+    }
+}
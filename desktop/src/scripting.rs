@@ -0,0 +1,437 @@
+//! 用户脚本 (Rhai)
+//!
+//! 用户可以用 Rhai 编写自定义指标和信号规则。脚本可读取行情数组 `open` `high` `low` `close` `volume`、
+//! 参数表 `params`，并调用内置的 `sma` `ema` `rsi`：
+//! - 指标脚本返回与 `close` 等长的数组，结果为 core 的 [`IndicatorResult`]
+//! - 信号脚本返回 `#{direction: "buy", strength: 0.8, rationale: "..."}`，无信号时返回 `()`，结果为 core 的 [`Signal`]
+//!
+//! 脚本不能导入模块、调用 `eval` 或访问文件，运算次数、调用深度和数据大小都有上限
+
+use alpha_core::errors::{AlphaError, AlphaResult};
+use alpha_core::indicators::TechnicalIndicators;
+use alpha_core::models::{IndicatorResult, MarketData, Signal, SignalHorizon, SignalType};
+use alpha_core::simulate::MarketSimulator;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// 脚本源码长度上限
+pub const MAX_SOURCE_LEN: usize = 64 * 1024;
+/// 校验时试运行的 K 线数量
+const SAMPLE_BARS: usize = 250;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptKind {
+    Indicator,
+    Signal,
+}
+
+/// 保存的用户脚本
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UserScript {
+    pub id: Uuid,
+    /// 名称，作为指标名或信号来源
+    pub name: String,
+    pub kind: ScriptKind,
+    pub source: String,
+    #[serde(default)]
+    pub description: String,
+    /// 传给脚本的 `params`
+    #[serde(default)]
+    pub params: BTreeMap<String, f64>,
+    /// 启用后随分析一起运行
+    #[serde(default)]
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 新建或修改脚本的内容
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScriptEdit {
+    pub name: String,
+    pub kind: ScriptKind,
+    pub source: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub params: BTreeMap<String, f64>,
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+impl ScriptEdit {
+    /// 保存时沿用已有脚本的创建时间
+    pub fn into_script(self, id: Uuid) -> UserScript {
+        let now = Utc::now();
+        UserScript {
+            id,
+            name: self.name.trim().to_string(),
+            kind: self.kind,
+            source: self.source,
+            description: self.description,
+            params: self.params,
+            enabled: self.enabled,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// 脚本运行结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "lowercase")]
+pub enum ScriptOutput {
+    Indicator(IndicatorResult),
+    Signal(Option<Signal>),
+}
+
+/// 脚本资源限制
+#[derive(Debug, Clone, Copy)]
+pub struct ScriptLimits {
+    /// 单次运行的运算次数上限
+    pub max_operations: u64,
+    pub max_call_levels: usize,
+    pub max_expr_depth: usize,
+    pub max_array_size: usize,
+    pub max_map_size: usize,
+    pub max_string_size: usize,
+}
+
+impl Default for ScriptLimits {
+    fn default() -> Self {
+        Self {
+            max_operations: 1_000_000,
+            max_call_levels: 32,
+            max_expr_depth: 64,
+            max_array_size: 100_000,
+            max_map_size: 256,
+            max_string_size: 4096,
+        }
+    }
+}
+
+/// 脚本编译与运行
+#[derive(Debug, Clone, Default)]
+pub struct ScriptRunner {
+    limits: ScriptLimits,
+}
+
+impl ScriptRunner {
+    pub fn new(limits: ScriptLimits) -> Self {
+        Self { limits }
+    }
+
+    /// 受限的脚本引擎，只注册内置指标函数
+    fn engine(&self) -> Engine {
+        let mut engine = Engine::new();
+        engine.set_max_operations(self.limits.max_operations)
+            .set_max_call_levels(self.limits.max_call_levels)
+            .set_max_expr_depths(self.limits.max_expr_depth, self.limits.max_expr_depth)
+            .set_max_array_size(self.limits.max_array_size)
+            .set_max_map_size(self.limits.max_map_size)
+            .set_max_string_size(self.limits.max_string_size)
+            .set_module_resolver(DummyModuleResolver::new());
+        engine.disable_symbol("eval");
+        engine.on_print(|text| tracing::debug!("script: {}", text));
+        engine.on_debug(|text, _, pos| tracing::debug!("script {:?}: {}", pos, text));
+
+        engine.register_fn("sma", |values: Array, period: i64| {
+            series_fn(values, period, |indicators, values, period| indicators.calculate_sma(values, period))
+        });
+        engine.register_fn("ema", |values: Array, period: i64| {
+            series_fn(values, period, |indicators, values, period| indicators.calculate_ema(values, period))
+        });
+        engine.register_fn("rsi", |values: Array, period: i64| {
+            series_fn(values, period, |indicators, values, period| indicators.calculate_rsi(values, period))
+        });
+        engine
+    }
+
+    /// 编译脚本，返回语法错误
+    pub fn compile(&self, source: &str) -> AlphaResult<AST> {
+        if source.len() > MAX_SOURCE_LEN {
+            return Err(AlphaError::invalid_input(format!("Script exceeds {} bytes", MAX_SOURCE_LEN)));
+        }
+        self.engine().compile(source)
+            .map_err(|e| AlphaError::invalid_input(format!("Syntax error: {}", e)))
+    }
+
+    /// 在 `data` 上运行脚本
+    pub fn run(&self, script: &UserScript, data: &[MarketData]) -> AlphaResult<ScriptOutput> {
+        let ast = self.compile(&script.source)?;
+        let mut scope = Scope::new();
+        let column = |f: fn(&MarketData) -> f64| -> Array { data.iter().map(|d| Dynamic::from_float(f(d))).collect() };
+        scope.push_constant("open", column(|d| d.open.unwrap_or(d.price)));
+        scope.push_constant("high", column(|d| d.high.unwrap_or(d.price)));
+        scope.push_constant("low", column(|d| d.low.unwrap_or(d.price)));
+        scope.push_constant("close", column(|d| d.price));
+        scope.push_constant("volume", column(|d| d.volume as f64));
+        let params: Map = script.params.iter()
+            .map(|(name, value)| (name.as_str().into(), Dynamic::from_float(*value)))
+            .collect();
+        scope.push_constant("params", params);
+
+        let value = self.engine().eval_ast_with_scope::<Dynamic>(&mut scope, &ast)
+            .map_err(|e| AlphaError::CalculationError(format!("Script {} failed: {}", script.name, e)))?;
+
+        match script.kind {
+            ScriptKind::Indicator => {
+                let values = value.into_array()
+                    .map_err(|_| AlphaError::invalid_input("Indicator script must return an array"))
+                    .and_then(|values| to_floats(values).map_err(AlphaError::invalid_input))?;
+                if values.len() != data.len() {
+                    return Err(AlphaError::invalid_input(format!(
+                        "Indicator script returned {} values for {} bars", values.len(), data.len()
+                    )));
+                }
+                Ok(ScriptOutput::Indicator(IndicatorResult {
+                    name: script.name.clone(),
+                    timestamps: data.iter().map(|d| d.timestamp).collect(),
+                    values,
+                    signals: Vec::new(),
+                    warmup: 0,
+                }))
+            }
+            ScriptKind::Signal if value.is_unit() => Ok(ScriptOutput::Signal(None)),
+            ScriptKind::Signal => {
+                let map = value.try_cast::<Map>()
+                    .ok_or_else(|| AlphaError::invalid_input("Signal script must return a map or ()"))?;
+                to_signal(&script.name, map).map(|signal| ScriptOutput::Signal(Some(signal)))
+            }
+        }
+    }
+
+    /// 检查名称和语法，并在模拟行情上试运行以确认返回值类型
+    pub fn validate(&self, script: &UserScript) -> AlphaResult<()> {
+        if script.name.trim().is_empty() {
+            return Err(AlphaError::invalid_input("Script name cannot be empty"));
+        }
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let sample = MarketSimulator::gbm(100.0, 0.05, 0.3, 42)?
+            .generate_bars("SAMPLE", start, Duration::days(1), SAMPLE_BARS);
+        self.run(script, &sample).map(|_| ())
+    }
+}
+
+/// 对数组计算内置指标
+fn series_fn(
+    values: Array,
+    period: i64,
+    f: impl Fn(&TechnicalIndicators, &[f64], usize) -> Vec<f64>,
+) -> Result<Array, Box<EvalAltResult>> {
+    if period < 1 {
+        return Err(format!("Period must be positive, got {}", period).into());
+    }
+    let values = to_floats(values)?;
+    let result = f(&TechnicalIndicators::new(), &values, period as usize);
+    Ok(result.into_iter().map(Dynamic::from_float).collect())
+}
+
+fn to_floats(values: Array) -> Result<Vec<f64>, String> {
+    values.into_iter()
+        .enumerate()
+        .map(|(i, value)| {
+            value.as_float()
+                .or_else(|_| value.as_int().map(|v| v as f64))
+                .map_err(|kind| format!("Element {} is {}, expected a number", i, kind))
+        })
+        .collect()
+}
+
+fn to_signal(name: &str, map: Map) -> AlphaResult<Signal> {
+    let text = |key: &str| map.get(key).and_then(|v| v.clone().into_string().ok());
+    let direction = match text("direction").as_deref() {
+        Some("buy") => SignalType::Buy,
+        Some("sell") => SignalType::Sell,
+        Some("hold") => SignalType::Hold,
+        other => return Err(AlphaError::invalid_input(format!("Invalid signal direction: {:?}", other))),
+    };
+    let strength = match map.get("strength") {
+        Some(value) => value.as_float().or_else(|_| value.as_int().map(|v| v as f64))
+            .map_err(|_| AlphaError::invalid_input("Signal strength must be a number"))?,
+        None => 0.5,
+    };
+    let horizon = match text("horizon").as_deref() {
+        None | Some("short_term") => SignalHorizon::ShortTerm,
+        Some("intraday") => SignalHorizon::Intraday,
+        Some("medium_term") => SignalHorizon::MediumTerm,
+        Some("long_term") => SignalHorizon::LongTerm,
+        Some(other) => return Err(AlphaError::invalid_input(format!("Invalid signal horizon: {}", other))),
+    };
+
+    Ok(Signal::new(direction, strength, name, text("rationale").unwrap_or_default(), horizon))
+}
+
+/// 用户脚本存储，以 JSON 保存在配置目录
+#[derive(Debug)]
+pub struct ScriptStore {
+    path: PathBuf,
+    scripts: Mutex<BTreeMap<Uuid, UserScript>>,
+}
+
+impl ScriptStore {
+    /// 读取脚本，文件不存在时为空
+    pub fn open(path: PathBuf) -> AlphaResult<Self> {
+        let scripts = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|e| {
+                AlphaError::StorageError(format!("Failed to read {}", path.display())).caused_by(e)
+            })?;
+            serde_json::from_str(&content).map_err(|e| {
+                AlphaError::DataCorrupted(format!("Malformed script file {}", path.display())).caused_by(e)
+            })?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self { path, scripts: Mutex::new(scripts) })
+    }
+
+    /// 全部脚本，按名称排序
+    pub fn list(&self) -> Vec<UserScript> {
+        let mut scripts: Vec<UserScript> = self.lock().values().cloned().collect();
+        scripts.sort_by(|a, b| a.name.cmp(&b.name));
+        scripts
+    }
+
+    pub fn get(&self, id: Uuid) -> AlphaResult<UserScript> {
+        self.lock().get(&id).cloned().ok_or_else(|| AlphaError::not_found(format!("Script {}", id)))
+    }
+
+    /// 启用的脚本
+    pub fn enabled(&self) -> Vec<UserScript> {
+        self.list().into_iter().filter(|script| script.enabled).collect()
+    }
+
+    /// 校验后新建或覆盖脚本 (按 id)，名称不能与其他脚本重复
+    pub fn save(&self, runner: &ScriptRunner, mut script: UserScript) -> AlphaResult<UserScript> {
+        runner.validate(&script)?;
+        self.modify(|scripts| {
+            if scripts.values().any(|other| other.id != script.id && other.name == script.name) {
+                return Err(AlphaError::invalid_input(format!("Script {} already exists", script.name)));
+            }
+            if let Some(existing) = scripts.get(&script.id) {
+                script.created_at = existing.created_at;
+            }
+            script.updated_at = Utc::now();
+            scripts.insert(script.id, script.clone());
+            Ok(script)
+        })
+    }
+
+    /// 删除脚本，不存在时返回 false
+    pub fn remove(&self, id: Uuid) -> AlphaResult<bool> {
+        self.modify(|scripts| Ok(scripts.remove(&id).is_some()))
+    }
+
+    /// 在副本上修改并写盘，成功后才替换内存中的脚本
+    fn modify<T>(&self, f: impl FnOnce(&mut BTreeMap<Uuid, UserScript>) -> AlphaResult<T>) -> AlphaResult<T> {
+        let mut scripts = self.lock();
+        let mut updated = scripts.clone();
+        let result = f(&mut updated)?;
+        if updated != *scripts {
+            self.save_file(&updated)?;
+            *scripts = updated;
+        }
+        Ok(result)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<Uuid, UserScript>> {
+        self.scripts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn save_file(&self, scripts: &BTreeMap<Uuid, UserScript>) -> AlphaResult<()> {
+        let content = serde_json::to_string_pretty(scripts)
+            .map_err(|e| AlphaError::SerializationError("Failed to serialize scripts".to_string()).caused_by(e))?;
+        let temp = self.path.with_extension("json.tmp");
+        fs::write(&temp, content)
+            .and_then(|_| fs::rename(&temp, &self.path))
+            .map_err(|e| AlphaError::StorageError(format!("Failed to write {}", self.path.display())).caused_by(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(name: &str, kind: ScriptKind, source: &str) -> UserScript {
+        UserScript {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            kind,
+            source: source.to_string(),
+            description: String::new(),
+            params: BTreeMap::from([("period".to_string(), 10.0)]),
+            enabled: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_runs_indicator_and_signal_scripts() {
+        let runner = ScriptRunner::default();
+        let data: Vec<MarketData> = (0..30).map(|i| MarketData::new("AAPL".to_string(), 100.0 + i as f64, 10)).collect();
+
+        let spread = script("spread", ScriptKind::Indicator, r#"
+            let fast = sma(close, params.period.to_int());
+            let out = [];
+            for i in 0..close.len() { out.push(close[i] - fast[i]); }
+            out
+        "#);
+        let ScriptOutput::Indicator(result) = runner.run(&spread, &data).unwrap() else { panic!("expected indicator") };
+        assert_eq!(result.name, "spread");
+        assert_eq!(result.values.len(), 30);
+        assert!((result.values[29] - 4.5).abs() < 1e-9);
+
+        let trend = script("trend", ScriptKind::Signal, r#"
+            if close[-1] > sma(close, 20)[-1] { #{direction: "buy", strength: 2, rationale: "above sma"} } else { () }
+        "#);
+        let ScriptOutput::Signal(Some(signal)) = runner.run(&trend, &data).unwrap() else { panic!("expected signal") };
+        assert_eq!(signal.direction, SignalType::Buy);
+        assert_eq!(signal.strength, 1.0);
+        assert_eq!(signal.source_indicator, "trend");
+        assert!(runner.validate(&trend).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_invalid_and_runaway_scripts() {
+        let runner = ScriptRunner::new(ScriptLimits { max_operations: 10_000, ..ScriptLimits::default() });
+        let cases = [
+            (ScriptKind::Indicator, "let x = ;"),
+            (ScriptKind::Indicator, "[1, 2, 3]"),
+            (ScriptKind::Indicator, "loop { }"),
+            (ScriptKind::Indicator, r#"eval("close")"#),
+            (ScriptKind::Indicator, r#"import "os" as os; close"#),
+            (ScriptKind::Signal, r#"#{direction: "up"}"#),
+            (ScriptKind::Signal, "42"),
+        ];
+        for (kind, source) in cases {
+            assert!(runner.validate(&script("bad", kind, source)).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_store_validates_and_persists() {
+        let path = std::env::temp_dir().join(format!("alpha-scripts-{}.json", std::process::id()));
+        let runner = ScriptRunner::default();
+        let store = ScriptStore::open(path.clone()).unwrap();
+
+        let saved = store.save(&runner, script("copy", ScriptKind::Indicator, "close")).unwrap();
+        assert!(store.save(&runner, script("copy", ScriptKind::Indicator, "close")).is_err());
+        assert!(store.save(&runner, script("broken", ScriptKind::Indicator, "close +")).is_err());
+
+        let reopened = ScriptStore::open(path.clone()).unwrap();
+        assert_eq!(reopened.list(), vec![saved.clone()]);
+        assert!(reopened.remove(saved.id).unwrap());
+        assert!(ScriptStore::open(path.clone()).unwrap().list().is_empty());
+        fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,150 @@
+//! 数据源 API Key 的安全存储
+//!
+//! API Key 保存在系统钥匙串 (macOS 钥匙串、Windows 凭据管理器、Linux Secret Service)，配置文件只保留数据源名称；
+//! 旧版配置中的明文 Key 在启动时迁移到钥匙串并从配置文件中移除
+
+use alpha_core::errors::{AlphaError, AlphaResult};
+use alpha_providers::ProviderSettings;
+
+/// 钥匙串中的服务名，与应用 identifier 一致
+const SERVICE: &str = "com.alpha.finance";
+
+/// 按数据源名称存取 API Key
+pub trait SecretStore: Send + Sync + std::fmt::Debug {
+    fn get(&self, provider: &str) -> AlphaResult<Option<String>>;
+
+    fn set(&self, provider: &str, api_key: &str) -> AlphaResult<()>;
+
+    /// 删除 Key，不存在时返回 false
+    fn delete(&self, provider: &str) -> AlphaResult<bool>;
+}
+
+/// 系统钥匙串
+#[derive(Debug, Default)]
+pub struct Keyring;
+
+impl Keyring {
+    fn entry(provider: &str) -> AlphaResult<keyring::Entry> {
+        keyring::Entry::new(SERVICE, provider).map_err(keyring_error)
+    }
+}
+
+impl SecretStore for Keyring {
+    fn get(&self, provider: &str) -> AlphaResult<Option<String>> {
+        match Self::entry(provider)?.get_password() {
+            Ok(api_key) => Ok(Some(api_key)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(keyring_error(e)),
+        }
+    }
+
+    fn set(&self, provider: &str, api_key: &str) -> AlphaResult<()> {
+        Self::entry(provider)?.set_password(api_key).map_err(keyring_error)
+    }
+
+    fn delete(&self, provider: &str) -> AlphaResult<bool> {
+        match Self::entry(provider)?.delete_password() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(e) => Err(keyring_error(e)),
+        }
+    }
+}
+
+fn keyring_error(err: keyring::Error) -> AlphaError {
+    AlphaError::StorageError("OS keychain is unavailable".to_string()).caused_by(err)
+}
+
+/// 将配置中的明文 Key 移入钥匙串，返回配置是否有改动 (需要写回配置文件)
+///
+/// 任一 Key 写入失败时立即返回错误，已迁移的 Key 从配置中移除，未迁移的保持原样
+pub fn migrate_api_keys(providers: &mut [ProviderSettings], store: &dyn SecretStore) -> AlphaResult<bool> {
+    let mut changed = false;
+    for settings in providers.iter_mut() {
+        let Some(api_key) = settings.api_key.take() else {
+            continue;
+        };
+        if !api_key.is_empty() {
+            if let Err(e) = store.set(&settings.name, &api_key) {
+                settings.api_key = Some(api_key);
+                return Err(e.context(format!("Migrating api_key of {}", settings.name)));
+            }
+        }
+        changed = true;
+    }
+    Ok(changed)
+}
+
+/// 为缺少 Key 的数据源从钥匙串补全，钥匙串不可用时保持原样
+pub fn with_api_keys(providers: &[ProviderSettings], store: &dyn SecretStore) -> Vec<ProviderSettings> {
+    providers.iter()
+        .map(|settings| {
+            let mut settings = settings.clone();
+            if settings.api_key.is_none() {
+                settings.api_key = store.get(&settings.name).unwrap_or_else(|e| {
+                    tracing::warn!("Failed to read api_key of {}: {}", settings.name, e.report());
+                    None
+                });
+            }
+            settings
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[derive(Debug, Default)]
+    struct MemoryStore {
+        keys: Mutex<HashMap<String, String>>,
+        fail: bool,
+    }
+
+    impl SecretStore for MemoryStore {
+        fn get(&self, provider: &str) -> AlphaResult<Option<String>> {
+            Ok(self.keys.lock().unwrap().get(provider).cloned())
+        }
+
+        fn set(&self, provider: &str, api_key: &str) -> AlphaResult<()> {
+            if self.fail {
+                return Err(AlphaError::StorageError("locked".to_string()));
+            }
+            self.keys.lock().unwrap().insert(provider.to_string(), api_key.to_string());
+            Ok(())
+        }
+
+        fn delete(&self, provider: &str) -> AlphaResult<bool> {
+            Ok(self.keys.lock().unwrap().remove(provider).is_some())
+        }
+    }
+
+    fn keyed(name: &str, api_key: &str) -> ProviderSettings {
+        ProviderSettings { api_key: Some(api_key.to_string()), ..ProviderSettings::new(name) }
+    }
+
+    #[test]
+    fn test_migrate_and_resolve() {
+        let store = MemoryStore::default();
+        let mut providers = vec![ProviderSettings::new("yahoo"), keyed("alpha_vantage", "secret")];
+
+        assert!(migrate_api_keys(&mut providers, &store).unwrap());
+        assert!(providers.iter().all(|p| p.api_key.is_none()));
+        assert!(!migrate_api_keys(&mut providers, &store).unwrap());
+
+        let resolved = with_api_keys(&providers, &store);
+        assert_eq!(resolved[0].api_key, None);
+        assert_eq!(resolved[1].api_key.as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn test_failed_migration_keeps_key() {
+        let store = MemoryStore { fail: true, ..MemoryStore::default() };
+        let mut providers = vec![keyed("alpha_vantage", "secret")];
+
+        assert!(migrate_api_keys(&mut providers, &store).is_err());
+        assert_eq!(providers[0].api_key.as_deref(), Some("secret"));
+    }
+}
@@ -0,0 +1,329 @@
+//! 应用设置
+//!
+//! 设置以带版本号的 JSON 保存在配置目录的 `config.json`；读取时按版本迁移旧文件，
+//! 更新时先整体校验，校验失败返回逐字段的错误，通过后写盘并由调用方立即应用，无需重启
+
+use crate::cache::CacheSettings;
+use crate::chart_data::MAX_BASE_BARS;
+use crate::scheduler::{RefreshJob, MIN_INTERVAL_SECS};
+use crate::updater::UpdateSettings;
+use alpha_core::errors::{AlphaError, AlphaResult};
+use alpha_core::utils::series::parse_timeframe;
+use alpha_core::utils::symbol::Symbol;
+use alpha_providers::ProviderSettings;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+/// 当前设置文件版本
+pub const SETTINGS_VERSION: u32 = 1;
+
+const THEMES: &[&str] = &["light", "dark", "system"];
+/// 缓存文件大小上限的最小值 (MB)
+const MIN_CACHE_SIZE_MB: u64 = 16;
+
+/// 应用设置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Settings {
+    /// 设置文件版本，旧版文件没有该字段
+    #[serde(default)]
+    pub version: u32,
+    pub api_url: String,
+    pub symbols: Vec<String>,
+    /// `light`、`dark` 或 `system`
+    pub theme: String,
+    /// 是否按 `refresh_jobs` 定时刷新行情
+    pub auto_update: bool,
+    /// 行情数据源，按顺序回退；旧版配置文件缺省时只使用 Yahoo Finance
+    #[serde(default = "default_providers")]
+    pub providers: Vec<ProviderSettings>,
+    /// 定时刷新任务，旧版配置文件缺省时每分钟刷新一次 `symbols`
+    #[serde(default = "default_refresh_jobs")]
+    pub refresh_jobs: Vec<RefreshJob>,
+    /// real-time-feed 的 WebSocket 地址，为 null 时不连接
+    #[serde(default = "default_feed_url")]
+    pub feed_url: Option<String>,
+    /// 应用更新通道与清单地址
+    #[serde(default)]
+    pub updates: UpdateSettings,
+    /// 本地行情缓存的保留和整理设置
+    #[serde(default)]
+    pub cache: CacheSettings,
+}
+
+pub fn default_providers() -> Vec<ProviderSettings> {
+    vec![ProviderSettings::new("yahoo")]
+}
+
+pub fn default_refresh_jobs() -> Vec<RefreshJob> {
+    vec![RefreshJob::new("default", 60)]
+}
+
+fn default_feed_url() -> Option<String> {
+    Some("ws://localhost:8082/ws".to_string())
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            api_url: "http://localhost:8080".to_string(),
+            symbols: vec!["AAPL".to_string(), "GOOGL".to_string(), "MSFT".to_string()],
+            theme: "light".to_string(),
+            auto_update: true,
+            providers: default_providers(),
+            refresh_jobs: default_refresh_jobs(),
+            feed_url: default_feed_url(),
+            updates: UpdateSettings::default(),
+            cache: CacheSettings::default(),
+        }
+    }
+}
+
+/// 单个字段的校验错误，`field` 为 JSON 路径，如 `refresh_jobs[0].interval_secs`
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { field: field.into(), message: message.into() }
+    }
+}
+
+/// 更新设置失败的原因，序列化后返回给界面
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SettingsError {
+    /// 校验未通过，设置未保存
+    Invalid { errors: Vec<FieldError> },
+    /// 保存或应用失败
+    Failed { message: String },
+}
+
+impl From<AlphaError> for SettingsError {
+    fn from(err: AlphaError) -> Self {
+        Self::Failed { message: err.report() }
+    }
+}
+
+impl Settings {
+    /// 校验全部字段，`providers` 为可用的数据源名称
+    pub fn validate<'a>(&self, providers: impl IntoIterator<Item = &'a str>) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        if !has_scheme(&self.api_url, &["http://", "https://"]) {
+            errors.push(FieldError::new("api_url", "Must be an http(s) URL"));
+        }
+        let mut seen = HashSet::new();
+        for (i, symbol) in self.symbols.iter().enumerate() {
+            if Symbol::parse(symbol).is_err() {
+                errors.push(FieldError::new(format!("symbols[{}]", i), format!("Invalid symbol: {}", symbol)));
+            } else if !seen.insert(symbol.to_ascii_uppercase()) {
+                errors.push(FieldError::new(format!("symbols[{}]", i), format!("Duplicate symbol: {}", symbol)));
+            }
+        }
+        if !THEMES.contains(&self.theme.as_str()) {
+            errors.push(FieldError::new("theme", format!("Must be one of {}", THEMES.join(", "))));
+        }
+
+        let known: HashSet<&str> = providers.into_iter().collect();
+        if self.providers.is_empty() {
+            errors.push(FieldError::new("providers", "At least one provider is required"));
+        }
+        for (i, provider) in self.providers.iter().enumerate() {
+            if !known.contains(provider.name.as_str()) {
+                errors.push(FieldError::new(format!("providers[{}].name", i), format!("Unknown provider: {}", provider.name)));
+            }
+        }
+
+        for (i, job) in self.refresh_jobs.iter().enumerate() {
+            if job.watchlist.trim().is_empty() {
+                errors.push(FieldError::new(format!("refresh_jobs[{}].watchlist", i), "Cannot be empty"));
+            }
+            if job.interval_secs < MIN_INTERVAL_SECS {
+                errors.push(FieldError::new(
+                    format!("refresh_jobs[{}].interval_secs", i),
+                    format!("Must be at least {} seconds", MIN_INTERVAL_SECS),
+                ));
+            }
+            if let Some(Err(e)) = job.timeframe.as_deref().map(parse_timeframe) {
+                errors.push(FieldError::new(format!("refresh_jobs[{}].timeframe", i), e.report()));
+            }
+        }
+
+        if let Some(url) = &self.feed_url {
+            if !has_scheme(url, &["ws://", "wss://"]) {
+                errors.push(FieldError::new("feed_url", "Must be a ws(s) URL"));
+            }
+        }
+        if !has_scheme(&self.updates.endpoint, &["https://"]) {
+            errors.push(FieldError::new("updates.endpoint", "Must be an https URL"));
+        }
+        // 图表单次最多读取 MAX_BASE_BARS 根，保留更少会导致每次都重新下载
+        if self.cache.max_bars_per_series < MAX_BASE_BARS {
+            errors.push(FieldError::new("cache.max_bars_per_series", format!("Must be at least {}", MAX_BASE_BARS)));
+        }
+        if self.cache.max_size_mb < MIN_CACHE_SIZE_MB {
+            errors.push(FieldError::new("cache.max_size_mb", format!("Must be at least {} MB", MIN_CACHE_SIZE_MB)));
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+}
+
+fn has_scheme(url: &str, schemes: &[&str]) -> bool {
+    schemes.iter().any(|scheme| url.len() > scheme.len() && url[..scheme.len()].eq_ignore_ascii_case(scheme))
+}
+
+/// 按版本逐级迁移设置 JSON
+fn migrate(mut value: Value) -> AlphaResult<Value> {
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > u64::from(SETTINGS_VERSION) {
+        return Err(AlphaError::ConfigurationError(format!(
+            "Settings version {} is newer than supported version {}",
+            version, SETTINGS_VERSION
+        )));
+    }
+    // 0 -> 1：新增字段均有默认值，只需写入版本号
+    if let Value::Object(object) = &mut value {
+        object.insert("version".to_string(), Value::from(SETTINGS_VERSION));
+    }
+    Ok(value)
+}
+
+/// 设置文件及当前生效的设置
+#[derive(Debug)]
+pub struct SettingsStore {
+    path: PathBuf,
+    current: RwLock<Settings>,
+}
+
+impl SettingsStore {
+    /// 读取设置文件，不存在时写入默认设置，旧版本迁移后写回
+    pub fn open(path: PathBuf) -> AlphaResult<Self> {
+        if !path.exists() {
+            let store = Self { path, current: RwLock::new(Settings::default()) };
+            store.save(&Settings::default())?;
+            return Ok(store);
+        }
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| AlphaError::StorageError(format!("Failed to read {}", path.display())).caused_by(e))?;
+        let value: Value = serde_json::from_str(&content)
+            .map_err(|e| AlphaError::ConfigurationError(format!("Malformed settings file {}", path.display())).caused_by(e))?;
+        let outdated = value.get("version").and_then(Value::as_u64) != Some(u64::from(SETTINGS_VERSION));
+        let settings: Settings = serde_json::from_value(migrate(value)?)
+            .map_err(|e| AlphaError::ConfigurationError(format!("Malformed settings file {}", path.display())).caused_by(e))?;
+
+        let store = Self { path, current: RwLock::new(settings.clone()) };
+        if outdated {
+            store.save(&settings)?;
+        }
+        Ok(store)
+    }
+
+    pub fn get(&self) -> Settings {
+        self.current.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 校验并替换全部设置，返回替换前的设置供调用方比较变化
+    pub fn update<'a>(
+        &self,
+        mut settings: Settings,
+        providers: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Settings, SettingsError> {
+        settings.version = SETTINGS_VERSION;
+        settings.validate(providers).map_err(|errors| SettingsError::Invalid { errors })?;
+        self.replace(settings).map_err(SettingsError::from)
+    }
+
+    /// 内部修改 (不校验)，如迁移 API Key、切换更新通道，返回修改后的设置
+    pub fn modify(&self, f: impl FnOnce(&mut Settings)) -> AlphaResult<Settings> {
+        let mut settings = self.get();
+        f(&mut settings);
+        self.replace(settings.clone())?;
+        Ok(settings)
+    }
+
+    /// 写盘成功后才替换内存中的设置
+    fn replace(&self, settings: Settings) -> AlphaResult<Settings> {
+        let mut current = self.current.write().unwrap_or_else(|e| e.into_inner());
+        self.save(&settings)?;
+        Ok(std::mem::replace(&mut *current, settings))
+    }
+
+    /// 先写临时文件再替换，避免写入中断损坏已有设置
+    fn save(&self, settings: &Settings) -> AlphaResult<()> {
+        let content = serde_json::to_string_pretty(settings)
+            .map_err(|e| AlphaError::SerializationError("Failed to serialize settings".to_string()).caused_by(e))?;
+        let temp = self.path.with_extension("json.tmp");
+        fs::write(&temp, content)
+            .and_then(|_| fs::rename(&temp, &self.path))
+            .map_err(|e| AlphaError::StorageError(format!("Failed to write {}", self.path.display())).caused_by(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROVIDERS: [&str; 2] = ["yahoo", "simulated"];
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("alpha-settings-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("config.json")
+    }
+
+    #[test]
+    fn test_migrates_legacy_file() {
+        let path = temp_path("legacy");
+        // 旧版配置文件没有版本号和 providers 字段
+        fs::write(&path, r#"{"api_url":"http://localhost:8080","symbols":[],"theme":"dark","auto_update":false}"#).unwrap();
+
+        let store = SettingsStore::open(path.clone()).unwrap();
+        let settings = store.get();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.providers, default_providers());
+        assert_eq!(settings.refresh_jobs, default_refresh_jobs());
+        assert!(fs::read_to_string(&path).unwrap().contains("\"version\": 1"));
+
+        fs::write(&path, r#"{"version": 99, "api_url": "", "symbols": [], "theme": "", "auto_update": false}"#).unwrap();
+        assert!(SettingsStore::open(path.clone()).is_err());
+
+        fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn test_update_validates_fields() {
+        let path = temp_path("update");
+        let store = SettingsStore::open(path.clone()).unwrap();
+
+        let mut invalid = store.get();
+        invalid.theme = "neon".to_string();
+        invalid.symbols.push("AAPL".to_string());
+        invalid.refresh_jobs[0].interval_secs = 1;
+        invalid.providers.push(ProviderSettings::new("bloomberg"));
+        invalid.cache.max_size_mb = 1;
+        let SettingsError::Invalid { errors } = store.update(invalid, PROVIDERS).unwrap_err() else {
+            panic!("expected validation errors");
+        };
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["symbols[3]", "theme", "providers[1].name", "refresh_jobs[0].interval_secs", "cache.max_size_mb"]);
+        assert_eq!(store.get(), Settings::default());
+
+        let mut valid = store.get();
+        valid.theme = "dark".to_string();
+        let previous = store.update(valid, PROVIDERS).unwrap();
+        assert_eq!(previous.theme, "light");
+        assert_eq!(SettingsStore::open(path.clone()).unwrap().get().theme, "dark");
+
+        fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+}
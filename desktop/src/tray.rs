@@ -0,0 +1,183 @@
+//! 系统托盘
+//!
+//! 托盘菜单列出自选股中涨跌幅最大的几只，并提供打开主窗口、立即刷新和暂停告警等快捷操作；
+//! 调度器每次刷新行情后调用 [`record_quotes`] 重建菜单
+
+use crate::cache;
+use crate::AppState;
+use alpha_core::models::MarketData;
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use tauri::{AppHandle, CustomMenuItem, Manager, SystemTray, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem};
+
+/// 菜单中显示的标的数量
+const TOP_MOVERS: usize = 5;
+
+const QUOTE_PREFIX: &str = "quote:";
+const OPEN: &str = "open";
+const REFRESH: &str = "refresh";
+const PAUSE_ALERTS: &str = "pause_alerts";
+const QUIT: &str = "quit";
+
+/// 点击托盘中的标的时发送给前端，载荷为代码
+pub const OPEN_SYMBOL_EVENT: &str = "open-symbol";
+/// 告警暂停状态变化时发送给前端，载荷为是否暂停
+pub const ALERTS_PAUSED_EVENT: &str = "alerts-paused";
+
+/// 托盘中的一条行情
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mover {
+    pub symbol: String,
+    pub price: f64,
+    /// 相对前收盘的涨跌幅 (小数)，本地没有前收盘时为 None
+    pub change: Option<f64>,
+}
+
+impl Mover {
+    fn label(&self) -> String {
+        match self.change {
+            Some(change) => format!("{}  {:.2}  {:+.2}%", self.symbol, self.price, change * 100.0),
+            None => format!("{}  {:.2}", self.symbol, self.price),
+        }
+    }
+}
+
+/// 各标的最近一次刷新的行情
+#[derive(Debug, Default)]
+pub struct TrayQuotes {
+    quotes: Mutex<BTreeMap<String, Mover>>,
+}
+
+impl TrayQuotes {
+    pub fn update(&self, movers: impl IntoIterator<Item = Mover>) {
+        let mut quotes = self.quotes.lock().unwrap_or_else(|e| e.into_inner());
+        quotes.extend(movers.into_iter().map(|m| (m.symbol.clone(), m)));
+    }
+
+    /// 按涨跌幅绝对值降序的前 `n` 个，没有涨跌幅的排在最后
+    pub fn top(&self, n: usize) -> Vec<Mover> {
+        let quotes = self.quotes.lock().unwrap_or_else(|e| e.into_inner());
+        let mut movers: Vec<Mover> = quotes.values().cloned().collect();
+        movers.sort_by(|a, b| {
+            let magnitude = |m: &Mover| m.change.map(f64::abs).unwrap_or(-1.0);
+            magnitude(b).total_cmp(&magnitude(a))
+        });
+        movers.truncate(n);
+        movers
+    }
+}
+
+pub fn system_tray() -> SystemTray {
+    SystemTray::new().with_menu(menu(&[], false))
+}
+
+fn menu(movers: &[Mover], alerts_paused: bool) -> SystemTrayMenu {
+    let mut menu = SystemTrayMenu::new();
+    if movers.is_empty() {
+        menu = menu.add_item(CustomMenuItem::new("no_quotes", "暂无行情").disabled());
+    }
+    for mover in movers {
+        menu = menu.add_item(CustomMenuItem::new(format!("{}{}", QUOTE_PREFIX, mover.symbol), mover.label()));
+    }
+
+    menu.add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new(OPEN, "打开 Alpha Finance"))
+        .add_item(CustomMenuItem::new(REFRESH, "立即刷新"))
+        .add_item(CustomMenuItem::new(PAUSE_ALERTS, if alerts_paused { "恢复告警" } else { "暂停告警" }))
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new(QUIT, "退出"))
+}
+
+/// 记录刷新得到的行情并重建托盘菜单
+pub fn record_quotes(app: &AppHandle, quotes: &[MarketData]) {
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+
+    let movers = quotes.iter().map(|quote| {
+        // 只读本地缓存的日线，不为托盘额外请求数据源
+        let daily = state.cache.load(&quote.symbol, chrono::Duration::days(1), 5).unwrap_or_default();
+        Mover {
+            symbol: quote.symbol.clone(),
+            price: quote.price,
+            change: cache::previous_close(&daily, quote)
+                .filter(|close| *close > 0.0)
+                .map(|close| quote.price / close - 1.0),
+        }
+    });
+    state.tray_quotes.update(movers);
+    rebuild_menu(app, &state);
+}
+
+fn rebuild_menu(app: &AppHandle, state: &AppState) {
+    let menu = menu(&state.tray_quotes.top(TOP_MOVERS), state.alerts_paused.load(Ordering::Relaxed));
+    if let Err(e) = app.tray_handle().set_menu(menu) {
+        tracing::warn!("Failed to update tray menu: {}", e);
+    }
+}
+
+/// 托盘事件处理，应用尚未初始化时只响应打开和退出
+pub fn handle_event(app: &AppHandle, event: SystemTrayEvent) {
+    let id = match event {
+        SystemTrayEvent::LeftClick { .. } => return show_main_window(app),
+        SystemTrayEvent::MenuItemClick { id, .. } => id,
+        _ => return,
+    };
+
+    match id.as_str() {
+        OPEN => show_main_window(app),
+        QUIT => app.exit(0),
+        REFRESH => {
+            if let Some(state) = app.try_state::<AppState>() {
+                state.scheduler.refresh_now(app.clone());
+            }
+        }
+        PAUSE_ALERTS => {
+            if let Some(state) = app.try_state::<AppState>() {
+                let paused = !state.alerts_paused.fetch_xor(true, Ordering::Relaxed);
+                if let Err(e) = app.emit_all(ALERTS_PAUSED_EVENT, paused) {
+                    tracing::warn!("Failed to emit {}: {}", ALERTS_PAUSED_EVENT, e);
+                }
+                rebuild_menu(app, &state);
+            }
+        }
+        other => {
+            if let Some(symbol) = other.strip_prefix(QUOTE_PREFIX) {
+                show_main_window(app);
+                if let Err(e) = app.emit_all(OPEN_SYMBOL_EVENT, symbol.to_string()) {
+                    tracing::warn!("Failed to emit {}: {}", OPEN_SYMBOL_EVENT, e);
+                }
+            }
+        }
+    }
+}
+
+/// 显示并聚焦主窗口
+pub fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_window("main") {
+        let result = window.unminimize()
+            .and_then(|_| window.show())
+            .and_then(|_| window.set_focus());
+        if let Err(e) = result {
+            tracing::warn!("Failed to show main window: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_movers() {
+        let quotes = TrayQuotes::default();
+        let mover = |symbol: &str, change: Option<f64>| Mover { symbol: symbol.to_string(), price: 10.0, change };
+        quotes.update([mover("AAPL", Some(0.01)), mover("TSLA", Some(-0.05)), mover("NEW", None)]);
+        quotes.update([mover("AAPL", Some(0.02))]);
+
+        let top: Vec<String> = quotes.top(5).into_iter().map(|m| m.symbol).collect();
+        assert_eq!(top, vec!["TSLA", "AAPL", "NEW"]);
+        assert_eq!(quotes.top(1)[0].label(), "TSLA  10.00  -5.00%");
+    }
+}
@@ -0,0 +1,128 @@
+//! 应用内更新
+//!
+//! 基于 Tauri updater，按配置中的更新通道 (stable/beta) 选择清单地址。
+//! 发布流程为每个通道维护一个滚动 release，清单地址中的 `{channel}` 替换为通道名。
+//! `tauri.conf.json` 中提交发布签名公钥并启用 updater 之前，检查和安装更新都返回错误
+
+use alpha_core::errors::{AlphaError, AlphaResult};
+use serde::{Deserialize, Serialize};
+use tauri::updater::UpdateResponse;
+use tauri::{AppHandle, Wry};
+
+/// 默认清单地址模板
+const DEFAULT_ENDPOINT: &str = "https://github.com/cuihairu/alpha/releases/download/updater-{channel}/latest.json";
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+impl UpdateChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Stable => "stable",
+            Self::Beta => "beta",
+        }
+    }
+}
+
+/// 更新配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct UpdateSettings {
+    pub channel: UpdateChannel,
+    /// 清单地址模板，`{channel}` 替换为通道名
+    pub endpoint: String,
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        Self {
+            channel: UpdateChannel::Stable,
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+        }
+    }
+}
+
+impl UpdateSettings {
+    pub fn endpoint_url(&self) -> String {
+        self.endpoint.replace("{channel}", self.channel.as_str())
+    }
+}
+
+/// 检查结果，返回给界面
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateInfo {
+    pub available: bool,
+    pub channel: UpdateChannel,
+    pub current_version: String,
+    /// 最新版本，没有更新时与当前版本相同
+    pub latest_version: String,
+    /// 发布时间
+    pub date: Option<String>,
+    /// 更新说明 (Markdown)
+    pub changelog: Option<String>,
+}
+
+async fn fetch(app: &AppHandle, settings: &UpdateSettings) -> AlphaResult<UpdateResponse<Wry>> {
+    // 清单地址可由用户修改，只能依靠签名校验保证安装包可信；未配置发布公钥的构建不启用更新
+    let config = app.config();
+    if !config.tauri.updater.active || config.tauri.updater.pubkey.trim().is_empty() {
+        return Err(AlphaError::ConfigurationError("Updater is disabled in this build".to_string()));
+    }
+
+    tauri::updater::builder(app.clone())
+        .endpoints(&[settings.endpoint_url()])
+        .check()
+        .await
+        .map_err(|e| AlphaError::network(format!("Failed to check {} channel", settings.channel.as_str())).caused_by(e))
+}
+
+/// 检查所选通道是否有新版本
+pub async fn check(app: &AppHandle, settings: &UpdateSettings) -> AlphaResult<UpdateInfo> {
+    let update = fetch(app, settings).await?;
+
+    Ok(UpdateInfo {
+        available: update.is_update_available(),
+        channel: settings.channel,
+        current_version: update.current_version().to_string(),
+        latest_version: update.latest_version().to_string(),
+        date: update.date().map(|date| date.to_string()),
+        changelog: update.body().cloned(),
+    })
+}
+
+/// 下载并安装所选通道的新版本，安装后重启应用；没有新版本时返回 false
+pub async fn apply(app: &AppHandle, settings: &UpdateSettings) -> AlphaResult<bool> {
+    let update = fetch(app, settings).await?;
+    if !update.is_update_available() {
+        return Ok(false);
+    }
+
+    let version = update.latest_version().to_string();
+    update.download_and_install().await
+        .map_err(|e| AlphaError::internal(format!("Failed to install {}", version)).caused_by(e))?;
+    tracing::info!("Installed update {}, restarting", version);
+    app.restart();
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_endpoint() {
+        let settings: UpdateSettings = serde_json::from_str(r#"{"channel": "beta"}"#).unwrap();
+        assert_eq!(settings.channel, UpdateChannel::Beta);
+        assert_eq!(
+            settings.endpoint_url(),
+            "https://github.com/cuihairu/alpha/releases/download/updater-beta/latest.json"
+        );
+        assert_eq!(UpdateSettings::default().endpoint_url().matches("stable").count(), 1);
+        assert!(serde_json::from_str::<UpdateChannel>(r#""nightly""#).is_err());
+    }
+}
@@ -0,0 +1,355 @@
+//! 工作区
+//!
+//! 工作区保存主窗口的标签页、面板布局、窗口位置和打开的图表窗口，可保存多个并随时切换；
+//! 启动时恢复上次使用的工作区。面板布局由前端定义，后端原样保存
+
+use crate::chart_window::{self, ChartWindowState, ChartWindows};
+use alpha_core::errors::{AlphaError, AlphaResult};
+use alpha_core::utils::series::parse_timeframe;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize, Window};
+
+/// 工作区名称长度上限
+const MAX_NAME_LEN: usize = 64;
+/// 单个工作区的标签页上限
+const MAX_TABS: usize = 50;
+/// 窗口至少有这么多像素落在某个显示器内才恢复位置，避免显示器拔掉后窗口跑到屏幕外
+const MIN_VISIBLE_PX: i64 = 100;
+
+/// 主窗口中的一个标签页
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WorkspaceTab {
+    pub symbol: String,
+    /// K 线周期，如 `1d`
+    pub timeframe: String,
+    #[serde(default)]
+    pub indicators: Vec<String>,
+}
+
+/// 窗口位置和大小 (物理像素)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub maximized: bool,
+}
+
+impl WindowGeometry {
+    /// 与 `monitors` (x, y, 宽, 高) 中任一显示器的重叠部分是否足够大
+    pub fn is_visible_on(&self, monitors: &[(i32, i32, u32, u32)]) -> bool {
+        monitors.iter().any(|&(x, y, width, height)| {
+            let overlap = |start: i32, len: u32, other: i32, other_len: u32| {
+                let end = (start as i64 + len as i64).min(other as i64 + other_len as i64);
+                end - (start as i64).max(other as i64)
+            };
+            overlap(self.x, self.width, x, width) >= MIN_VISIBLE_PX && overlap(self.y, self.height, y, height) >= MIN_VISIBLE_PX
+        })
+    }
+}
+
+/// 前端提交的工作区内容，窗口位置和图表窗口由后端读取
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WorkspaceSnapshot {
+    #[serde(default)]
+    pub tabs: Vec<WorkspaceTab>,
+    #[serde(default)]
+    pub active_tab: Option<usize>,
+    #[serde(default)]
+    pub layout: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Workspace {
+    pub name: String,
+    pub tabs: Vec<WorkspaceTab>,
+    /// 当前标签页在 `tabs` 中的下标
+    pub active_tab: Option<usize>,
+    /// 前端面板布局 (分栏比例、停靠位置等)
+    #[serde(default)]
+    pub layout: serde_json::Value,
+    /// 主窗口位置
+    #[serde(default)]
+    pub window: Option<WindowGeometry>,
+    /// 打开的图表窗口
+    #[serde(default)]
+    pub charts: Vec<ChartWindowState>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Workspace {
+    pub fn new(name: String, snapshot: WorkspaceSnapshot) -> Self {
+        Self {
+            name,
+            tabs: snapshot.tabs,
+            active_tab: snapshot.active_tab,
+            layout: snapshot.layout,
+            window: None,
+            charts: Vec::new(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    pub fn validate(&self) -> AlphaResult<()> {
+        let name = self.name.trim();
+        if name.is_empty() || name.len() > MAX_NAME_LEN || name != self.name {
+            return Err(AlphaError::invalid_input(format!(
+                "Workspace name must be 1-{} characters without surrounding spaces", MAX_NAME_LEN
+            )));
+        }
+        if self.tabs.len() > MAX_TABS {
+            return Err(AlphaError::invalid_input(format!("A workspace can have at most {} tabs", MAX_TABS)));
+        }
+        for tab in &self.tabs {
+            if tab.symbol.trim().is_empty() {
+                return Err(AlphaError::invalid_input("Tab symbol cannot be empty"));
+            }
+            parse_timeframe(&tab.timeframe)?;
+        }
+        if self.active_tab.is_some_and(|index| index >= self.tabs.len()) {
+            return Err(AlphaError::invalid_input("Active tab is out of range"));
+        }
+        self.charts.iter().try_for_each(ChartWindowState::validate)
+    }
+}
+
+/// 工作区列表中的一项
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceSummary {
+    pub name: String,
+    pub tabs: usize,
+    pub charts: usize,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceList {
+    pub active: Option<String>,
+    pub workspaces: Vec<WorkspaceSummary>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+struct WorkspaceFile {
+    active: Option<String>,
+    workspaces: BTreeMap<String, Workspace>,
+}
+
+/// 已保存的工作区，存储在配置目录的 `workspaces.json`
+#[derive(Debug)]
+pub struct WorkspaceStore {
+    path: PathBuf,
+    file: Mutex<WorkspaceFile>,
+}
+
+impl WorkspaceStore {
+    /// 读取工作区，文件不存在时为空
+    pub fn open(path: PathBuf) -> AlphaResult<Self> {
+        let file = if path.exists() {
+            let content = fs::read_to_string(&path).map_err(|e| {
+                AlphaError::StorageError(format!("Failed to read {}", path.display())).caused_by(e)
+            })?;
+            serde_json::from_str(&content).map_err(|e| {
+                AlphaError::DataCorrupted(format!("Malformed workspace file {}", path.display())).caused_by(e)
+            })?
+        } else {
+            WorkspaceFile::default()
+        };
+
+        Ok(Self { path, file: Mutex::new(file) })
+    }
+
+    pub fn list(&self) -> WorkspaceList {
+        let file = self.lock();
+        WorkspaceList {
+            active: file.active.clone(),
+            workspaces: file.workspaces.values()
+                .map(|workspace| WorkspaceSummary {
+                    name: workspace.name.clone(),
+                    tabs: workspace.tabs.len(),
+                    charts: workspace.charts.len(),
+                    updated_at: workspace.updated_at,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> AlphaResult<Workspace> {
+        self.lock().workspaces.get(name).cloned()
+            .ok_or_else(|| AlphaError::not_found(format!("Workspace {}", name)))
+    }
+
+    /// 当前工作区，从未保存过时为 None
+    pub fn active(&self) -> Option<Workspace> {
+        let file = self.lock();
+        file.active.as_ref().and_then(|name| file.workspaces.get(name)).cloned()
+    }
+
+    /// 校验后新建或覆盖工作区，并设为当前工作区
+    pub fn save(&self, mut workspace: Workspace) -> AlphaResult<Workspace> {
+        workspace.validate()?;
+        workspace.updated_at = Utc::now();
+        self.modify(|file| {
+            file.active = Some(workspace.name.clone());
+            file.workspaces.insert(workspace.name.clone(), workspace.clone());
+            Ok(workspace)
+        })
+    }
+
+    /// 切换当前工作区
+    pub fn set_active(&self, name: &str) -> AlphaResult<Workspace> {
+        self.modify(|file| {
+            let workspace = file.workspaces.get(name).cloned()
+                .ok_or_else(|| AlphaError::not_found(format!("Workspace {}", name)))?;
+            file.active = Some(name.to_string());
+            Ok(workspace)
+        })
+    }
+
+    /// 删除工作区，不存在时返回 false；删除当前工作区后没有当前工作区
+    pub fn remove(&self, name: &str) -> AlphaResult<bool> {
+        self.modify(|file| {
+            if file.active.as_deref() == Some(name) {
+                file.active = None;
+            }
+            Ok(file.workspaces.remove(name).is_some())
+        })
+    }
+
+    /// 在副本上修改并写盘，成功后才替换内存中的工作区
+    fn modify<T>(&self, f: impl FnOnce(&mut WorkspaceFile) -> AlphaResult<T>) -> AlphaResult<T> {
+        let mut file = self.lock();
+        let mut updated = file.clone();
+        let result = f(&mut updated)?;
+        if updated != *file {
+            self.save_file(&updated)?;
+            *file = updated;
+        }
+        Ok(result)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, WorkspaceFile> {
+        self.file.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn save_file(&self, file: &WorkspaceFile) -> AlphaResult<()> {
+        let content = serde_json::to_string_pretty(file)
+            .map_err(|e| AlphaError::SerializationError("Failed to serialize workspaces".to_string()).caused_by(e))?;
+        let temp = self.path.with_extension("json.tmp");
+        fs::write(&temp, content)
+            .and_then(|_| fs::rename(&temp, &self.path))
+            .map_err(|e| AlphaError::StorageError(format!("Failed to write {}", self.path.display())).caused_by(e))
+    }
+}
+
+/// 读取窗口当前位置，窗口不可用时为 None
+pub fn capture_geometry(window: &Window) -> Option<WindowGeometry> {
+    let position = window.outer_position().ok()?;
+    let size = window.inner_size().ok()?;
+    Some(WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        maximized: window.is_maximized().unwrap_or(false),
+    })
+}
+
+/// 恢复窗口位置，不在任何显示器上时保持系统默认位置
+pub fn restore_geometry(window: &Window, geometry: &WindowGeometry) {
+    let monitors: Vec<(i32, i32, u32, u32)> = window.available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(|monitor| (monitor.position().x, monitor.position().y, monitor.size().width, monitor.size().height))
+        .collect();
+    if !geometry.is_visible_on(&monitors) {
+        tracing::debug!("Saved window position {:?} is off screen, keeping default", geometry);
+        return;
+    }
+
+    let result = window.set_size(PhysicalSize::new(geometry.width, geometry.height))
+        .and_then(|_| window.set_position(PhysicalPosition::new(geometry.x, geometry.y)))
+        .and_then(|_| if geometry.maximized { window.maximize() } else { Ok(()) });
+    if let Err(e) = result {
+        tracing::warn!("Failed to restore window position: {}", e);
+    }
+}
+
+/// 让打开的图表窗口与工作区一致：关闭多余的窗口，打开缺少的窗口
+pub fn apply_charts(app: &AppHandle, windows: &ChartWindows, charts: &[ChartWindowState]) {
+    for (label, state) in windows.states() {
+        if charts.iter().any(|chart| chart.symbol.eq_ignore_ascii_case(&state.symbol)) {
+            continue;
+        }
+        if let Some(window) = app.get_window(&label) {
+            if let Err(e) = window.close() {
+                tracing::warn!("Failed to close chart window {}: {}", label, e);
+            }
+        }
+        if let Err(e) = windows.remove(&label) {
+            tracing::warn!("Failed to forget chart window {}: {}", label, e.report());
+        }
+    }
+
+    for chart in charts {
+        if let Err(e) = chart_window::show(app, windows, chart.clone()) {
+            tracing::warn!("Failed to open chart window for {}: {}", chart.symbol, e.report());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(name: &str, symbols: &[&str]) -> Workspace {
+        let tabs = symbols.iter()
+            .map(|symbol| WorkspaceTab { symbol: symbol.to_string(), timeframe: "1d".to_string(), indicators: vec![] })
+            .collect();
+        Workspace::new(name.to_string(), WorkspaceSnapshot { tabs, active_tab: Some(0), layout: serde_json::json!({ "split": 0.3 }) })
+    }
+
+    #[test]
+    fn test_save_switch_and_delete() {
+        let dir = std::env::temp_dir().join(format!("alpha-workspaces-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("workspaces.json");
+
+        let store = WorkspaceStore::open(path.clone()).unwrap();
+        assert!(store.active().is_none());
+        store.save(workspace("Trading", &["AAPL", "MSFT"])).unwrap();
+        store.save(workspace("Research", &["NVDA"])).unwrap();
+        assert!(store.save(workspace(" Bad ", &[])).is_err());
+        assert!(store.save(Workspace { active_tab: Some(5), ..workspace("Bad", &["AAPL"]) }).is_err());
+
+        let reloaded = WorkspaceStore::open(path).unwrap();
+        let list = reloaded.list();
+        assert_eq!(list.active.as_deref(), Some("Research"));
+        assert_eq!(list.workspaces.iter().map(|w| (w.name.as_str(), w.tabs)).collect::<Vec<_>>(), vec![("Research", 1), ("Trading", 2)]);
+
+        assert_eq!(reloaded.set_active("Trading").unwrap().layout["split"], 0.3);
+        assert_eq!(reloaded.active().unwrap().tabs[1].symbol, "MSFT");
+        assert!(reloaded.set_active("Missing").is_err());
+        assert!(reloaded.remove("Trading").unwrap());
+        assert!(!reloaded.remove("Trading").unwrap());
+        assert!(reloaded.active().is_none());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_geometry_visibility() {
+        let monitors = [(0, 0, 1920, 1080), (1920, 0, 2560, 1440)];
+        let geometry = |x, y| WindowGeometry { x, y, width: 1200, height: 800, maximized: false };
+        assert!(geometry(100, 100).is_visible_on(&monitors));
+        assert!(geometry(3000, 200).is_visible_on(&monitors));
+        assert!(!geometry(-1150, 0).is_visible_on(&monitors));
+        assert!(!geometry(5000, 0).is_visible_on(&monitors));
+        assert!(!geometry(100, 100).is_visible_on(&[]));
+    }
+}
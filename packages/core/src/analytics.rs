@@ -1,9 +1,11 @@
 //! 分析引擎模块
 
 use crate::models::*;
-use crate::errors::AlphaResult;
+use crate::errors::{AlphaError, AlphaResult};
 use crate::indicators::TechnicalIndicators;
-use chrono::Utc;
+use crate::prelude::*;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
 
 /// 市场数据分析引擎
 #[derive(Debug, Clone)]
@@ -55,6 +57,7 @@ impl AnalysisEngine {
             timestamps: timestamps.clone(),
             values: sma_short,
             signals: Vec::new(),
+            warmup: 19,
         });
 
         indicators.push(IndicatorResult {
@@ -62,6 +65,7 @@ impl AnalysisEngine {
             timestamps: timestamps.clone(),
             values: sma_long,
             signals: Vec::new(),
+            warmup: 49,
         });
 
         // 计算 MACD
@@ -71,21 +75,30 @@ impl AnalysisEngine {
             timestamps: timestamps.clone(),
             values: macd_line,
             signals: Vec::new(),
+            warmup: 0,
         });
 
         // 计算风险指标
         let risk_metrics = self.calculate_risk_metrics(&prices);
 
         // 生成推荐信号
-        let recommendation = self.generate_recommendation(&indicators, &risk_metrics);
+        let signals = self.generate_signals(&indicators, &risk_metrics);
+        let recommendation = Self::aggregate_signals(&signals);
         let confidence = self.calculate_confidence(&indicators, &risk_metrics);
 
+        // no_std 环境下没有系统时钟，以最新一条数据的时间作为分析时间
+        #[cfg(feature = "std")]
+        let analyzed_at = chrono::Utc::now();
+        #[cfg(not(feature = "std"))]
+        let analyzed_at = data[data.len() - 1].timestamp;
+
         Ok(AnalysisResult {
             symbol: symbol.clone(),
-            analyzed_at: Utc::now(),
+            analyzed_at,
             indicators,
             risk_metrics,
             recommendation,
+            signals,
             confidence,
         })
     }
@@ -144,33 +157,46 @@ impl AnalysisEngine {
         }
     }
 
-    /// 生成推荐信号
-    fn generate_recommendation(&self, indicators: &[IndicatorResult], risk_metrics: &RiskMetrics) -> SignalType {
-        let mut buy_signals = 0;
-        let mut sell_signals = 0;
+    /// 生成各指标的交易信号
+    fn generate_signals(&self, indicators: &[IndicatorResult], risk_metrics: &RiskMetrics) -> Vec<Signal> {
+        let mut signals = Vec::new();
 
         for indicator in indicators {
-            if indicator.values.is_empty() {
-                continue;
-            }
-
-            let latest_value = indicator.values[indicator.values.len() - 1];
+            let latest_value = match indicator.values.last() {
+                Some(&value) => value,
+                None => continue,
+            };
 
             match indicator.name.as_str() {
                 "RSI(14)" => {
                     if latest_value < 30.0 {
-                        buy_signals += 1;
+                        signals.push(Signal::new(
+                            SignalType::Buy,
+                            (30.0 - latest_value) / 30.0,
+                            &indicator.name,
+                            format!("RSI {:.2} 低于超卖线 30", latest_value),
+                            SignalHorizon::ShortTerm,
+                        ));
                     } else if latest_value > 70.0 {
-                        sell_signals += 1;
+                        signals.push(Signal::new(
+                            SignalType::Sell,
+                            (latest_value - 70.0) / 30.0,
+                            &indicator.name,
+                            format!("RSI {:.2} 高于超买线 70", latest_value),
+                            SignalHorizon::ShortTerm,
+                        ));
                     }
                 }
                 "MACD" => {
-                    if let (Some(macd), Some(signal)) = (indicator.values.last(), indicator.values.get(indicator.values.len().saturating_sub(9))) {
-                        if macd > signal {
-                            buy_signals += 1;
+                    if let Some(&previous) = indicator.values.get(indicator.values.len().saturating_sub(9)) {
+                        let delta = latest_value - previous;
+                        let strength = (delta.abs() / previous.abs().max(1e-9)).min(1.0);
+                        let (direction, rationale) = if delta > 0.0 {
+                            (SignalType::Buy, format!("MACD 较 9 周期前上升 {:.4}", delta))
                         } else {
-                            sell_signals += 1;
-                        }
+                            (SignalType::Sell, format!("MACD 较 9 周期前下降 {:.4}", -delta))
+                        };
+                        signals.push(Signal::new(direction, strength, &indicator.name, rationale, SignalHorizon::MediumTerm));
                     }
                 }
                 _ => {}
@@ -180,17 +206,33 @@ impl AnalysisEngine {
         // 考虑风险指标
         if risk_metrics.volatility > 0.5 {
             // 高波动率，降低买入信号权重
-            buy_signals /= 2;
+            for signal in signals.iter_mut().filter(|s| s.direction == SignalType::Buy) {
+                signal.strength /= 2.0;
+            }
         }
 
         if risk_metrics.max_drawdown > 0.2 {
             // 大幅回撤，增加卖出信号
-            sell_signals += 1;
+            signals.push(Signal::new(
+                SignalType::Sell,
+                risk_metrics.max_drawdown,
+                "MaxDrawdown",
+                format!("最大回撤 {:.2}% 超过 20%", risk_metrics.max_drawdown * 100.0),
+                SignalHorizon::MediumTerm,
+            ));
         }
 
-        if buy_signals > sell_signals {
+        signals.sort_by(|a, b| b.strength.total_cmp(&a.strength));
+        signals
+    }
+
+    /// 按信号强度汇总推荐方向
+    fn aggregate_signals(signals: &[Signal]) -> SignalType {
+        let score: f64 = signals.iter().map(Signal::score).sum();
+
+        if score > 0.0 {
             SignalType::Buy
-        } else if sell_signals > buy_signals {
+        } else if score < 0.0 {
             SignalType::Sell
         } else {
             SignalType::Hold
@@ -248,6 +290,18 @@ mod tests {
         assert_eq!(analysis.symbol, "AAPL");
         assert!(!analysis.indicators.is_empty());
         assert!(matches!(analysis.recommendation, SignalType::Buy | SignalType::Sell | SignalType::Hold));
+        assert!(analysis.signals.windows(2).all(|w| w[0].strength >= w[1].strength));
+    }
+
+    #[test]
+    fn test_aggregate_signals() {
+        let signals = vec![
+            Signal::new(SignalType::Buy, 0.3, "RSI(14)", "oversold", SignalHorizon::ShortTerm),
+            Signal::new(SignalType::Sell, 0.8, "MaxDrawdown", "drawdown", SignalHorizon::MediumTerm),
+        ];
+
+        assert_eq!(AnalysisEngine::aggregate_signals(&signals), SignalType::Sell);
+        assert_eq!(AnalysisEngine::aggregate_signals(&[]), SignalType::Hold);
     }
 
     #[test]
@@ -1,7 +1,12 @@
 //! 统一错误定义
 
+use crate::prelude::*;
+use alloc::sync::Arc;
 use thiserror::Error;
 
+/// 外部底层错误 (使用 Arc 以保持 `AlphaError: Clone`)
+pub type ErrorSource = Arc<dyn core::error::Error + Send + Sync + 'static>;
+
 /// Alpha Finance 统一错误类型
 #[derive(Debug, Clone, Error)]
 pub enum AlphaError {
@@ -49,6 +54,28 @@ pub enum AlphaError {
 
     #[error("Serialization error: {0}")]
     SerializationError(String),
+
+    #[error("Data corrupted: {0}")]
+    DataCorrupted(String),
+
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
+    /// 在已有错误外层附加的上下文说明
+    #[error("{context}")]
+    Context {
+        context: String,
+        #[source]
+        source: Arc<AlphaError>,
+    },
+
+    /// 保留外部底层原因的错误，`Display` 与被包装的错误一致
+    #[error("{error}")]
+    Caused {
+        error: Arc<AlphaError>,
+        #[source]
+        source: ErrorSource,
+    },
 }
 
 /// 统一结果类型
@@ -74,18 +101,153 @@ impl AlphaError {
     pub fn internal(msg: impl Into<String>) -> Self {
         Self::InternalError(msg.into())
     }
+
+    /// 在当前错误外层附加上下文
+    pub fn context(self, context: impl Into<String>) -> Self {
+        Self::Context {
+            context: context.into(),
+            source: Arc::new(self),
+        }
+    }
+
+    /// 记录导致当前错误的外部底层原因
+    pub fn caused_by<E>(self, source: E) -> Self
+    where
+        E: core::error::Error + Send + Sync + 'static,
+    {
+        Self::Caused {
+            error: Arc::new(self),
+            source: Arc::new(source),
+        }
+    }
+
+    /// 去除上下文包装后的原始错误，用于按错误类别分支处理
+    pub fn root(&self) -> &AlphaError {
+        match self {
+            Self::Context { source, .. } => source.root(),
+            Self::Caused { error, .. } => error.root(),
+            other => other,
+        }
+    }
+
+    /// 是否为可重试的暂时性错误 (网络故障、限流、服务暂不可用)，按原始错误类别判断
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self.root(),
+            Self::NetworkError(_) | Self::RateLimited(_) | Self::ServiceUnavailable(_)
+        )
+    }
+
+    /// 稳定的错误码 (如 `INVALID_INPUT`)，按原始错误类别给出，供跨语言绑定按类别分支
+    pub fn code(&self) -> &'static str {
+        match self.root() {
+            Self::InvalidInput(_) => "INVALID_INPUT",
+            Self::DataNotFound(_) => "DATA_NOT_FOUND",
+            Self::CalculationError(_) => "CALCULATION_ERROR",
+            Self::NetworkError(_) => "NETWORK_ERROR",
+            Self::StorageError(_) => "STORAGE_ERROR",
+            Self::ConfigurationError(_) => "CONFIGURATION_ERROR",
+            Self::AuthenticationError(_) => "AUTHENTICATION_ERROR",
+            Self::PermissionDenied(_) => "PERMISSION_DENIED",
+            Self::RateLimited(_) => "RATE_LIMITED",
+            Self::ServiceUnavailable(_) => "SERVICE_UNAVAILABLE",
+            Self::InternalError(_) => "INTERNAL_ERROR",
+            Self::PlatformError(_) => "PLATFORM_ERROR",
+            Self::WasmError(_) => "WASM_ERROR",
+            Self::JniError(_) => "JNI_ERROR",
+            Self::SerializationError(_) => "SERIALIZATION_ERROR",
+            Self::DataCorrupted(_) => "DATA_CORRUPTED",
+            Self::Cancelled(_) => "CANCELLED",
+            Self::Context { .. } | Self::Caused { .. } => "INTERNAL_ERROR",
+        }
+    }
+
+    /// 错误大类：`input` / `data` / `calculation` / `network` / `auth` / `config` / `platform` / `cancelled` / `internal`
+    pub fn category(&self) -> &'static str {
+        match self.root() {
+            Self::InvalidInput(_) => "input",
+            Self::DataNotFound(_) | Self::StorageError(_) | Self::SerializationError(_) | Self::DataCorrupted(_) => "data",
+            Self::CalculationError(_) => "calculation",
+            Self::NetworkError(_) | Self::RateLimited(_) | Self::ServiceUnavailable(_) => "network",
+            Self::AuthenticationError(_) | Self::PermissionDenied(_) => "auth",
+            Self::ConfigurationError(_) => "config",
+            Self::PlatformError(_) | Self::WasmError(_) | Self::JniError(_) => "platform",
+            Self::Cancelled(_) => "cancelled",
+            Self::InternalError(_) | Self::Context { .. } | Self::Caused { .. } => "internal",
+        }
+    }
+
+    /// 从外到内遍历错误链 (含外部底层原因)
+    pub fn chain(&self) -> impl Iterator<Item = &(dyn core::error::Error + 'static)> {
+        let mut next: Option<&(dyn core::error::Error + 'static)> = Some(self);
+        core::iter::from_fn(move || {
+            let current = next?;
+            next = current.source();
+            Some(current)
+        })
+    }
+
+    /// 完整错误链的单行描述 (`外层: 内层: 原因`)，用于日志和错误对话框
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        for (i, err) in self.chain().enumerate() {
+            let message = err.to_string();
+            // Caused 与被包装错误的 Display 相同，跳过重复项
+            if report.ends_with(&message) {
+                continue;
+            }
+            if i > 0 {
+                report.push_str(": ");
+            }
+            report.push_str(&message);
+        }
+        report
+    }
+}
+
+/// 为 `Result` 附加错误上下文
+pub trait ErrorContext<T> {
+    /// 出错时附加上下文说明
+    fn context(self, context: impl Into<String>) -> AlphaResult<T>;
+
+    /// 出错时延迟生成上下文说明
+    fn with_context<C, F>(self, f: F) -> AlphaResult<T>
+    where
+        C: Into<String>,
+        F: FnOnce() -> C;
+}
+
+impl<T, E> ErrorContext<T> for Result<T, E>
+where
+    E: Into<AlphaError>,
+{
+    fn context(self, context: impl Into<String>) -> AlphaResult<T> {
+        self.map_err(|e| e.into().context(context))
+    }
+
+    fn with_context<C, F>(self, f: F) -> AlphaResult<T>
+    where
+        C: Into<String>,
+        F: FnOnce() -> C,
+    {
+        self.map_err(|e| e.into().context(f()))
+    }
 }
 
 // 为常见外部错误类型实现转换
 impl From<serde_json::Error> for AlphaError {
     fn from(err: serde_json::Error) -> Self {
-        Self::SerializationError(err.to_string())
+        Self::SerializationError(err.to_string()).caused_by(err)
     }
 }
 
 impl From<chrono::ParseError> for AlphaError {
     fn from(err: chrono::ParseError) -> Self {
-        Self::InvalidInput(format!("Date parsing error: {}", err))
+        let error = Self::InvalidInput(format!("Date parsing error: {}", err));
+        // chrono 仅在 std 下为 ParseError 实现 Error trait
+        #[cfg(feature = "std")]
+        let error = error.caused_by(err);
+        error
     }
 }
 
@@ -101,4 +263,39 @@ impl From<jni::errors::Error> for AlphaError {
     fn from(err: jni::errors::Error) -> Self {
         Self::JniError(format!("JNI error: {}", err))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_chain() {
+        let result: AlphaResult<()> = Err(AlphaError::network("connection refused"));
+        let err = result
+            .context("fetch AAPL quotes")
+            .with_context(|| "refresh watchlist")
+            .unwrap_err();
+
+        assert_eq!(err.to_string(), "refresh watchlist");
+        assert!(matches!(err.root(), AlphaError::NetworkError(_)));
+        assert_eq!(err.chain().count(), 3);
+        assert_eq!(err.report(), "refresh watchlist: fetch AAPL quotes: Network error: connection refused");
+        assert!(err.is_retryable());
+        assert!(!AlphaError::invalid_input("bad symbol").context("fetch").is_retryable());
+        assert_eq!((err.code(), err.category()), ("NETWORK_ERROR", "network"));
+
+        let cloned = err.clone();
+        assert_eq!(cloned.report(), err.report());
+    }
+
+    #[test]
+    fn test_external_source_preserved() {
+        let err: AlphaError = serde_json::from_str::<u32>("not json").unwrap_err().into();
+
+        assert!(matches!(err.root(), AlphaError::SerializationError(_)));
+        assert!(err.to_string().starts_with("Serialization error"));
+        assert!(core::error::Error::source(&err).is_some());
+        assert_eq!(err.chain().count(), 2);
+    }
+}
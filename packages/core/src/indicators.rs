@@ -4,8 +4,20 @@
 
 use crate::models::{IndicatorResult, SignalType, MarketData};
 use crate::errors::AlphaError;
+use crate::prelude::*;
+// no_std 下 f64 没有 sqrt/powi/round 等固有方法，由 libm 实现的 Float trait 提供
+#[cfg(not(feature = "std"))]
 use num_traits::Float;
 
+/// 增量 (逐笔) 指标计算
+pub mod streaming;
+
+/// K 线形态识别
+pub mod patterns;
+
+/// 按名称调用的指标注册表
+pub mod registry;
+
 /// 技术指标计算器
 #[derive(Debug, Clone)]
 pub struct TechnicalIndicators {
@@ -13,6 +25,9 @@ pub struct TechnicalIndicators {
 }
 
 impl TechnicalIndicators {
+    /// 作为精度传入时不做任何取整，保留完整的 f64 结果
+    pub const FULL_PRECISION: usize = usize::MAX;
+
     /// 创建新的技术指标计算器
     pub fn new() -> Self {
         Self { precision: 4 }
@@ -25,16 +40,23 @@ impl TechnicalIndicators {
 
     /// 计算简单移动平均线 (SMA)
     pub fn calculate_sma(&self, prices: &[f64], period: usize) -> Vec<f64> {
+        let mut sma = Vec::new();
+        self.calculate_sma_into(prices, period, &mut sma);
+        sma
+    }
+
+    /// 计算 SMA 并写入 `sma`，复用其已分配的容量
+    pub fn calculate_sma_into(&self, prices: &[f64], period: usize, sma: &mut Vec<f64>) {
+        reset(sma, prices.len());
         if prices.len() < period {
-            return vec![0.0; prices.len()];
+            return;
         }
 
-        let mut sma = vec![0.0; prices.len()];
         let mut sum = 0.0;
 
         // 计算第一个平均值
-        for i in 0..period {
-            sum += prices[i];
+        for price in &prices[..period] {
+            sum += price;
         }
         sma[period - 1] = (sum / period as f64).round_to(self.precision);
 
@@ -43,17 +65,22 @@ impl TechnicalIndicators {
             sum = sum - prices[i - period] + prices[i];
             sma[i] = (sum / period as f64).round_to(self.precision);
         }
-
-        sma
     }
 
     /// 计算指数移动平均线 (EMA)
     pub fn calculate_ema(&self, prices: &[f64], period: usize) -> Vec<f64> {
+        let mut ema = Vec::new();
+        self.calculate_ema_into(prices, period, &mut ema);
+        ema
+    }
+
+    /// 计算 EMA 并写入 `ema`，复用其已分配的容量
+    pub fn calculate_ema_into(&self, prices: &[f64], period: usize, ema: &mut Vec<f64>) {
+        reset(ema, prices.len());
         if prices.is_empty() {
-            return vec![];
+            return;
         }
 
-        let mut ema = vec![0.0; prices.len()];
         let multiplier = 2.0 / (period + 1) as f64;
 
         // 第一个 EMA 值使用第一个价格
@@ -63,17 +90,22 @@ impl TechnicalIndicators {
         for i in 1..prices.len() {
             ema[i] = ((prices[i] - ema[i - 1]) * multiplier + ema[i - 1]).round_to(self.precision);
         }
-
-        ema
     }
 
     /// 计算相对强弱指标 (RSI)
     pub fn calculate_rsi(&self, prices: &[f64], period: usize) -> Vec<f64> {
+        let mut rsi = Vec::new();
+        self.calculate_rsi_into(prices, period, &mut rsi);
+        rsi
+    }
+
+    /// 计算 RSI 并写入 `rsi`，复用其已分配的容量
+    pub fn calculate_rsi_into(&self, prices: &[f64], period: usize, rsi: &mut Vec<f64>) {
+        reset(rsi, prices.len());
         if prices.len() < period + 1 {
-            return vec![0.0; prices.len()];
+            return;
         }
 
-        let mut rsi = vec![0.0; prices.len()];
         let mut gains = 0.0;
         let mut losses = 0.0;
 
@@ -109,18 +141,31 @@ impl TechnicalIndicators {
                 avg_loss = (avg_loss * (period - 1) as f64 + loss) / period as f64;
             }
         }
-
-        rsi
     }
 
     /// 计算布林带 (Bollinger Bands)
     pub fn calculate_bollinger_bands(&self, prices: &[f64], period: usize, std_dev: f64) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
-        let sma = self.calculate_sma(prices, period);
-        let mut upper_band = vec![0.0; prices.len()];
-        let mut lower_band = vec![0.0; prices.len()];
+        let (mut upper, mut middle, mut lower) = (Vec::new(), Vec::new(), Vec::new());
+        self.calculate_bollinger_bands_into(prices, period, std_dev, &mut upper, &mut middle, &mut lower);
+        (upper, middle, lower)
+    }
+
+    /// 计算布林带并写入上中下轨，复用其已分配的容量
+    pub fn calculate_bollinger_bands_into(
+        &self,
+        prices: &[f64],
+        period: usize,
+        std_dev: f64,
+        upper_band: &mut Vec<f64>,
+        sma: &mut Vec<f64>,
+        lower_band: &mut Vec<f64>,
+    ) {
+        self.calculate_sma_into(prices, period, sma);
+        reset(upper_band, prices.len());
+        reset(lower_band, prices.len());
 
         for i in period - 1..prices.len() {
-            let slice = &prices[i - period + 1..=i];
+            let slice = &prices[i + 1 - period..=i];
             let mean = sma[i];
             let variance = slice.iter()
                 .map(|&price| (price - mean).powi(2))
@@ -130,28 +175,151 @@ impl TechnicalIndicators {
             upper_band[i] = (mean + std_dev * std_deviation).round_to(self.precision);
             lower_band[i] = (mean - std_dev * std_deviation).round_to(self.precision);
         }
-
-        (upper_band, sma, lower_band)
     }
 
     /// 计算移动平均收敛散度 (MACD)
     pub fn calculate_macd(&self, prices: &[f64], fast_period: usize, slow_period: usize, signal_period: usize) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
-        let ema_fast = self.calculate_ema(prices, fast_period);
-        let ema_slow = self.calculate_ema(prices, slow_period);
+        let (mut macd_line, mut signal_line, mut histogram) = (Vec::new(), Vec::new(), Vec::new());
+        self.calculate_macd_into(prices, fast_period, slow_period, signal_period, &mut macd_line, &mut signal_line, &mut histogram);
+        (macd_line, signal_line, histogram)
+    }
 
-        let mut macd_line = vec![0.0; prices.len()];
-        for i in 0..prices.len() {
-            macd_line[i] = (ema_fast[i] - ema_slow[i]).round_to(self.precision);
+    /// 计算 MACD 并写入三条线，复用其已分配的容量 (计算过程不再额外分配)
+    #[allow(clippy::too_many_arguments)]
+    pub fn calculate_macd_into(
+        &self,
+        prices: &[f64],
+        fast_period: usize,
+        slow_period: usize,
+        signal_period: usize,
+        macd_line: &mut Vec<f64>,
+        signal_line: &mut Vec<f64>,
+        histogram: &mut Vec<f64>,
+    ) {
+        // 快线暂存于 macd_line，慢线暂存于 histogram
+        self.calculate_ema_into(prices, fast_period, macd_line);
+        self.calculate_ema_into(prices, slow_period, histogram);
+
+        for (macd, &slow) in macd_line.iter_mut().zip(histogram.iter()) {
+            *macd = (*macd - slow).round_to(self.precision);
         }
 
-        let signal_line = self.calculate_ema(&macd_line, signal_period);
-        let mut histogram = vec![0.0; prices.len()];
+        self.calculate_ema_into(macd_line, signal_period, signal_line);
 
-        for i in 0..prices.len() {
-            histogram[i] = ((macd_line[i] - signal_line[i]) * 1000.0).round_to(self.precision); // 放大显示
+        for ((hist, &macd), &signal) in histogram.iter_mut().zip(macd_line.iter()).zip(signal_line.iter()) {
+            *hist = ((macd - signal) * 1000.0).round_to(self.precision); // 放大显示
         }
+    }
 
-        (macd_line, signal_line, histogram)
+    /// 计算平均真实波幅 (ATR，Wilder 平滑)
+    ///
+    /// 以下 OHLCV 指标的各输入序列按最短长度对齐，预热期内的值为 0
+    pub fn calculate_atr(&self, high: &[f64], low: &[f64], close: &[f64], period: usize) -> Vec<f64> {
+        let len = high.len().min(low.len()).min(close.len());
+        let mut atr = vec![0.0; len];
+        if period == 0 || len < period {
+            return atr;
+        }
+
+        let true_range = |i: usize| {
+            let range = high[i] - low[i];
+            if i == 0 {
+                range
+            } else {
+                range.max((high[i] - close[i - 1]).abs()).max((low[i] - close[i - 1]).abs())
+            }
+        };
+
+        let mut current = (0..period).map(true_range).sum::<f64>() / period as f64;
+        atr[period - 1] = current.round_to(self.precision);
+        for (i, value) in atr.iter_mut().enumerate().skip(period) {
+            current = (current * (period - 1) as f64 + true_range(i)) / period as f64;
+            *value = current.round_to(self.precision);
+        }
+
+        atr
+    }
+
+    /// 计算随机指标 (KD)，返回 (%K, %D)，%D 为 %K 的简单移动平均
+    pub fn calculate_stochastic(&self, high: &[f64], low: &[f64], close: &[f64], k_period: usize, d_period: usize) -> (Vec<f64>, Vec<f64>) {
+        let len = high.len().min(low.len()).min(close.len());
+        let mut k = vec![0.0; len];
+        let mut d = vec![0.0; len];
+        if k_period == 0 || d_period == 0 || len < k_period {
+            return (k, d);
+        }
+
+        for i in k_period - 1..len {
+            let window = i + 1 - k_period..=i;
+            let highest = high[window.clone()].iter().copied().fold(f64::MIN, f64::max);
+            let lowest = low[window].iter().copied().fold(f64::MAX, f64::min);
+            // 区间无波动时取中值
+            k[i] = if highest > lowest {
+                (100.0 * (close[i] - lowest) / (highest - lowest)).round_to(self.precision)
+            } else {
+                50.0
+            };
+        }
+
+        let first_d = k_period + d_period - 2;
+        for i in first_d..len {
+            d[i] = (k[i + 1 - d_period..=i].iter().sum::<f64>() / d_period as f64).round_to(self.precision);
+        }
+
+        (k, d)
+    }
+
+    /// 计算累计成交量加权平均价 (VWAP)，以典型价 (H+L+C)/3 加权
+    pub fn calculate_vwap(&self, high: &[f64], low: &[f64], close: &[f64], volume: &[f64]) -> Vec<f64> {
+        let len = high.len().min(low.len()).min(close.len()).min(volume.len());
+        let mut cumulative_value = 0.0;
+        let mut cumulative_volume = 0.0;
+
+        (0..len)
+            .map(|i| {
+                let typical = (high[i] + low[i] + close[i]) / 3.0;
+                cumulative_value += typical * volume[i];
+                cumulative_volume += volume[i];
+                // 尚无成交时以典型价代替
+                let vwap = if cumulative_volume > 0.0 { cumulative_value / cumulative_volume } else { typical };
+                vwap.round_to(self.precision)
+            })
+            .collect()
+    }
+
+    /// 计算资金流量指标 (MFI)
+    pub fn calculate_mfi(&self, high: &[f64], low: &[f64], close: &[f64], volume: &[f64], period: usize) -> Vec<f64> {
+        let len = high.len().min(low.len()).min(close.len()).min(volume.len());
+        let mut mfi = vec![0.0; len];
+        if period == 0 || len < period + 1 {
+            return mfi;
+        }
+
+        let typical: Vec<f64> = (0..len).map(|i| (high[i] + low[i] + close[i]) / 3.0).collect();
+        // 第 i 个资金流对应 i-1 到 i 的变化，正负由典型价涨跌决定
+        let flow = |i: usize| {
+            let money = typical[i] * volume[i];
+            if typical[i] > typical[i - 1] {
+                (money, 0.0)
+            } else if typical[i] < typical[i - 1] {
+                (0.0, money)
+            } else {
+                (0.0, 0.0)
+            }
+        };
+
+        for (i, value) in mfi.iter_mut().enumerate().skip(period) {
+            let (positive, negative) = (i + 1 - period..=i)
+                .map(flow)
+                .fold((0.0, 0.0), |(p, n), (fp, fn_)| (p + fp, n + fn_));
+            *value = if negative == 0.0 {
+                100.0
+            } else {
+                (100.0 - 100.0 / (1.0 + positive / negative)).round_to(self.precision)
+            };
+        }
+
+        mfi
     }
 
     /// 从市场数据计算技术指标
@@ -178,17 +346,28 @@ impl TechnicalIndicators {
             timestamps,
             values: rsi_values,
             signals,
+            warmup: 14,
         })
     }
 }
 
 /// 浮点数精度处理辅助 trait
+/// 清空并填充为指定长度的 0.0，保留已分配的容量
+fn reset(values: &mut Vec<f64>, len: usize) {
+    values.clear();
+    values.resize(len, 0.0);
+}
+
 trait RoundTo {
     fn round_to(self, precision: usize) -> Self;
 }
 
 impl RoundTo for f64 {
     fn round_to(self, precision: usize) -> Self {
+        // f64 只有约 15 位有效数字，更高精度的取整没有意义
+        if precision > 15 {
+            return self;
+        }
         let multiplier = 10_f64.powi(precision as i32);
         (self * multiplier).round() / multiplier
     }
@@ -226,6 +405,62 @@ mod tests {
         assert!(rsi[14] >= 0.0 && rsi[14] <= 100.0);
     }
 
+    #[test]
+    fn test_into_variants_reuse_buffers() {
+        let indicators = TechnicalIndicators::new();
+        let prices: Vec<f64> = (0..60).map(|i| 100.0 + (i as f64 * 0.5).sin() * 3.0).collect();
+
+        let mut out = Vec::with_capacity(prices.len());
+        let capacity = out.capacity();
+        indicators.calculate_rsi_into(&prices, 14, &mut out);
+        assert_eq!(out, indicators.calculate_rsi(&prices, 14));
+
+        // 上一次的结果会被完全覆盖，且不重新分配
+        indicators.calculate_sma_into(&prices[..10], 20, &mut out);
+        assert_eq!(out, vec![0.0; 10]);
+        assert_eq!(out.capacity(), capacity);
+
+        let (mut macd, mut signal, mut histogram) = (Vec::new(), Vec::new(), Vec::new());
+        indicators.calculate_macd_into(&prices, 12, 26, 9, &mut macd, &mut signal, &mut histogram);
+        assert_eq!((macd, signal, histogram), indicators.calculate_macd(&prices, 12, 26, 9));
+    }
+
+    #[test]
+    fn test_full_precision_skips_rounding() {
+        let prices = [1.0, 2.0, 2.0];
+        let rounded = TechnicalIndicators::new().calculate_sma(&prices, 3);
+        let full = TechnicalIndicators::with_precision(TechnicalIndicators::FULL_PRECISION).calculate_sma(&prices, 3);
+
+        assert_eq!(rounded[2], 1.6667);
+        assert_eq!(full[2], 5.0 / 3.0);
+    }
+
+    #[test]
+    fn test_ohlcv_indicators() {
+        let indicators = TechnicalIndicators::new();
+        let high = [10.0, 11.0, 12.0, 11.5, 13.0, 14.0];
+        let low = [9.0, 9.5, 10.5, 10.0, 11.0, 12.5];
+        let close = [9.5, 10.5, 11.5, 10.5, 12.5, 13.5];
+        let volume = [100.0, 200.0, 150.0, 300.0, 250.0, 100.0];
+
+        let atr = indicators.calculate_atr(&high, &low, &close, 3);
+        assert_eq!(atr[..2], [0.0, 0.0]);
+        assert_eq!(atr[2], 1.3333); // (1.0 + 1.5 + 1.5) / 3
+
+        let (k, d) = indicators.calculate_stochastic(&high, &low, &close, 3, 2);
+        assert_eq!(k[2], 83.3333); // (11.5 - 9.0) / (12.0 - 9.0)
+        assert_eq!(d[3], ((k[2] + k[3]) / 2.0).round_to(4));
+        assert_eq!(d[2], 0.0);
+
+        let vwap = indicators.calculate_vwap(&high, &low, &close, &volume);
+        assert_eq!(vwap[0], 9.5);
+        assert!(vwap.iter().zip(&low).zip(&high).all(|((&v, &l), &h)| v >= l.min(9.0) && v <= h));
+
+        let mfi = indicators.calculate_mfi(&high, &low, &close, &volume, 3);
+        assert_eq!(mfi[2], 0.0);
+        assert!(mfi[3] > 0.0 && mfi[3] < 100.0);
+    }
+
     #[test]
     fn test_market_data_indicators() {
         let indicators = TechnicalIndicators::new();
@@ -0,0 +1,261 @@
+//! K 线形态识别
+//!
+//! 基于实体与上下影线比例的经典单根/多根 K 线形态，缺少开高低价的数据点不参与识别
+
+use crate::models::{MarketData, SignalType};
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 判断前期趋势时回看的 K 线数量
+const TREND_LOOKBACK: usize = 3;
+
+/// 支持的 K 线形态
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum CandlePattern {
+    /// 十字星：实体极小，多空僵持
+    Doji,
+    /// 锤子线：下跌后长下影、小实体
+    Hammer,
+    /// 射击之星：上涨后长上影、小实体
+    ShootingStar,
+    /// 看涨吞没
+    BullishEngulfing,
+    /// 看跌吞没
+    BearishEngulfing,
+    /// 启明星 (三根)
+    MorningStar,
+    /// 黄昏星 (三根)
+    EveningStar,
+}
+
+impl CandlePattern {
+    pub const ALL: [CandlePattern; 7] = [
+        CandlePattern::Doji,
+        CandlePattern::Hammer,
+        CandlePattern::ShootingStar,
+        CandlePattern::BullishEngulfing,
+        CandlePattern::BearishEngulfing,
+        CandlePattern::MorningStar,
+        CandlePattern::EveningStar,
+    ];
+
+    /// 形态由几根 K 线组成
+    pub fn candles(&self) -> usize {
+        match self {
+            CandlePattern::Doji | CandlePattern::Hammer | CandlePattern::ShootingStar => 1,
+            CandlePattern::BullishEngulfing | CandlePattern::BearishEngulfing => 2,
+            CandlePattern::MorningStar | CandlePattern::EveningStar => 3,
+        }
+    }
+
+    /// 形态隐含的方向
+    pub fn direction(&self) -> SignalType {
+        match self {
+            CandlePattern::Doji => SignalType::Hold,
+            CandlePattern::Hammer | CandlePattern::BullishEngulfing | CandlePattern::MorningStar => SignalType::Buy,
+            CandlePattern::ShootingStar | CandlePattern::BearishEngulfing | CandlePattern::EveningStar => SignalType::Sell,
+        }
+    }
+}
+
+/// 一次形态命中，`index` 与 `timestamp` 指形态的最后一根 K 线
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PatternHit {
+    pub pattern: CandlePattern,
+    pub index: usize,
+    pub timestamp: DateTime<Utc>,
+    pub direction: SignalType,
+    /// 0.0 - 1.0
+    pub confidence: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Candle {
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+}
+
+impl Candle {
+    fn from_market_data(data: &MarketData) -> Option<Self> {
+        let candle = Candle {
+            open: data.open?,
+            high: data.high?,
+            low: data.low?,
+            close: data.price,
+        };
+        (candle.range() > 0.0).then_some(candle)
+    }
+
+    fn range(&self) -> f64 {
+        self.high - self.low
+    }
+
+    fn body(&self) -> f64 {
+        (self.close - self.open).abs()
+    }
+
+    fn upper_shadow(&self) -> f64 {
+        self.high - self.open.max(self.close)
+    }
+
+    fn lower_shadow(&self) -> f64 {
+        self.open.min(self.close) - self.low
+    }
+
+    fn is_bullish(&self) -> bool {
+        self.close > self.open
+    }
+
+    fn is_bearish(&self) -> bool {
+        self.close < self.open
+    }
+
+    fn midpoint(&self) -> f64 {
+        (self.open + self.close) / 2.0
+    }
+}
+
+/// 识别指定形态，`patterns` 为空时识别全部形态；结果按 K 线顺序排列
+pub fn detect_patterns(data: &[MarketData], patterns: &[CandlePattern]) -> Vec<PatternHit> {
+    let patterns = if patterns.is_empty() { &CandlePattern::ALL[..] } else { patterns };
+    let candles: Vec<Option<Candle>> = data.iter().map(Candle::from_market_data).collect();
+    let mut hits = Vec::new();
+
+    for (index, point) in data.iter().enumerate() {
+        for &pattern in patterns {
+            let needed = pattern.candles();
+            if index + 1 < needed {
+                continue;
+            }
+            let window: Option<Vec<Candle>> = candles[index + 1 - needed..=index].iter().copied().collect();
+            let Some(window) = window else {
+                continue;
+            };
+
+            if let Some(confidence) = match_pattern(pattern, &window, trend(data, index + 1 - needed)) {
+                hits.push(PatternHit {
+                    pattern,
+                    index,
+                    timestamp: point.timestamp,
+                    direction: pattern.direction(),
+                    confidence: confidence.clamp(0.0, 1.0),
+                });
+            }
+        }
+    }
+
+    hits
+}
+
+/// 形态开始前的价格变化，正为上涨
+fn trend(data: &[MarketData], start: usize) -> f64 {
+    if start == 0 {
+        return 0.0;
+    }
+    let from = start.saturating_sub(TREND_LOOKBACK + 1);
+    data[start - 1].price - data[from].price
+}
+
+fn match_pattern(pattern: CandlePattern, window: &[Candle], trend: f64) -> Option<f64> {
+    match pattern {
+        CandlePattern::Doji => {
+            let c = window[0];
+            let ratio = c.body() / c.range();
+            (ratio <= 0.1).then_some(1.0 - ratio * 5.0)
+        }
+        CandlePattern::Hammer => {
+            let c = window[0];
+            let small_body = c.body() > 0.1 * c.range() && c.body() <= 0.35 * c.range();
+            (trend < 0.0 && small_body && c.lower_shadow() >= 2.0 * c.body() && c.upper_shadow() <= 0.1 * c.range())
+                .then(|| 0.5 + 0.5 * c.lower_shadow() / c.range())
+        }
+        CandlePattern::ShootingStar => {
+            let c = window[0];
+            let small_body = c.body() > 0.1 * c.range() && c.body() <= 0.35 * c.range();
+            (trend > 0.0 && small_body && c.upper_shadow() >= 2.0 * c.body() && c.lower_shadow() <= 0.1 * c.range())
+                .then(|| 0.5 + 0.5 * c.upper_shadow() / c.range())
+        }
+        CandlePattern::BullishEngulfing => {
+            let (prev, curr) = (window[0], window[1]);
+            (prev.is_bearish() && curr.is_bullish()
+                && curr.open <= prev.close && curr.close >= prev.open && curr.body() > prev.body())
+                .then(|| 0.5 + 0.5 * (1.0 - prev.body() / curr.body()))
+        }
+        CandlePattern::BearishEngulfing => {
+            let (prev, curr) = (window[0], window[1]);
+            (prev.is_bullish() && curr.is_bearish()
+                && curr.open >= prev.close && curr.close <= prev.open && curr.body() > prev.body())
+                .then(|| 0.5 + 0.5 * (1.0 - prev.body() / curr.body()))
+        }
+        CandlePattern::MorningStar => {
+            let (first, star, last) = (window[0], window[1], window[2]);
+            (first.is_bearish() && first.body() >= 0.5 * first.range()
+                && star.body() <= 0.3 * first.body()
+                && last.is_bullish() && last.close > first.midpoint())
+                .then(|| 0.5 + 0.5 * ((last.close - first.close) / first.body()).min(1.0))
+        }
+        CandlePattern::EveningStar => {
+            let (first, star, last) = (window[0], window[1], window[2]);
+            (first.is_bullish() && first.body() >= 0.5 * first.range()
+                && star.body() <= 0.3 * first.body()
+                && last.is_bearish() && last.close < first.midpoint())
+                .then(|| 0.5 + 0.5 * ((first.close - last.close) / first.body()).min(1.0))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn bars(ohlc: &[(f64, f64, f64, f64)]) -> Vec<MarketData> {
+        let start = Utc.with_ymd_and_hms(2025, 1, 2, 0, 0, 0).unwrap();
+        ohlc.iter()
+            .enumerate()
+            .map(|(i, &(open, high, low, close))| {
+                MarketData::with_ohlcv("TEST".to_string(), start + Duration::days(i as i64), open, high, low, close, 1000)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_reversal_patterns() {
+        let data = bars(&[
+            (20.0, 20.5, 18.5, 19.0),
+            (19.0, 19.2, 17.5, 18.0),
+            (18.0, 18.2, 16.5, 17.0),
+            // 下跌后的锤子线
+            (16.5, 16.6, 14.0, 16.0),
+            // 看涨吞没前一根阴线
+            (16.0, 16.2, 15.0, 15.2),
+            (15.0, 17.5, 14.9, 17.2),
+        ]);
+
+        let hits = detect_patterns(&data, &[]);
+        let found: Vec<(CandlePattern, usize)> = hits.iter().map(|h| (h.pattern, h.index)).collect();
+
+        assert!(found.contains(&(CandlePattern::Hammer, 3)));
+        assert!(found.contains(&(CandlePattern::BullishEngulfing, 5)));
+        assert!(hits.iter().all(|h| (0.0..=1.0).contains(&h.confidence)));
+
+        let only_doji = detect_patterns(&data, &[CandlePattern::Doji]);
+        assert!(only_doji.iter().all(|h| h.pattern == CandlePattern::Doji));
+    }
+
+    #[test]
+    fn test_morning_star_and_missing_ohlc() {
+        let data = bars(&[(20.0, 20.2, 16.8, 17.0), (16.8, 17.0, 16.4, 16.7), (16.8, 19.5, 16.7, 19.2)]);
+        let hits = detect_patterns(&data, &[CandlePattern::MorningStar]);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].direction, SignalType::Buy);
+
+        let mut partial = data.clone();
+        partial[1].open = None;
+        assert!(detect_patterns(&partial, &[CandlePattern::MorningStar]).is_empty());
+    }
+}
@@ -0,0 +1,301 @@
+//! 按名称调用的指标注册表
+//!
+//! 各平台绑定 (WASM、移动端) 通过名称 + 参数表调用指标，新增指标只需在此注册，
+//! 不必为每个指标单独增加导出函数
+
+use super::TechnicalIndicators;
+use crate::errors::{AlphaError, AlphaResult};
+use crate::prelude::*;
+use alloc::collections::BTreeMap;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+use serde::Serialize;
+
+/// 指标参数，缺省的参数取注册时的默认值
+pub type IndicatorParams = BTreeMap<String, f64>;
+
+/// 指标输出，按注册顺序排列的 (列名, 数值) 列表
+pub type IndicatorOutput = Vec<(String, Vec<f64>)>;
+
+/// 指标输入，收盘价必填，其余列仅 OHLCV 指标需要
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IndicatorInput<'a> {
+    pub high: Option<&'a [f64]>,
+    pub low: Option<&'a [f64]>,
+    pub close: &'a [f64],
+    pub volume: Option<&'a [f64]>,
+}
+
+impl<'a> IndicatorInput<'a> {
+    /// 仅含收盘价的输入
+    pub fn close(close: &'a [f64]) -> Self {
+        Self { close, ..Self::default() }
+    }
+
+    fn column(&self, name: &str, column: Option<&'a [f64]>) -> AlphaResult<&'a [f64]> {
+        let column = column.ok_or_else(|| AlphaError::invalid_input(format!("Missing input column: {}", name)))?;
+        if column.len() != self.close.len() {
+            return Err(AlphaError::invalid_input(format!("Column {} length does not match close", name)));
+        }
+        Ok(column)
+    }
+
+    fn high(&self) -> AlphaResult<&'a [f64]> {
+        self.column("high", self.high)
+    }
+
+    fn low(&self) -> AlphaResult<&'a [f64]> {
+        self.column("low", self.low)
+    }
+
+    fn volume(&self) -> AlphaResult<&'a [f64]> {
+        self.column("volume", self.volume)
+    }
+}
+
+type Calculator = fn(&TechnicalIndicators, &IndicatorInput, &ResolvedParams) -> AlphaResult<IndicatorOutput>;
+
+/// 注册的指标描述
+#[derive(Debug, Clone, Serialize)]
+pub struct IndicatorSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// 参数名及默认值
+    pub params: &'static [(&'static str, f64)],
+    /// 输出列名
+    pub outputs: &'static [&'static str],
+    /// 是否需要高低价/成交量列
+    pub requires_ohlcv: bool,
+    #[serde(skip)]
+    calculate: Calculator,
+}
+
+/// 合并默认值后的参数
+struct ResolvedParams(BTreeMap<&'static str, f64>);
+
+impl ResolvedParams {
+    fn value(&self, name: &str) -> f64 {
+        self.0.get(name).copied().unwrap_or_default()
+    }
+
+    /// 周期类参数，需为正整数
+    fn period(&self, name: &str) -> AlphaResult<usize> {
+        let value = self.value(name);
+        if value < 1.0 || value.fract() != 0.0 {
+            return Err(AlphaError::invalid_input(format!("Parameter {} must be a positive integer, got {}", name, value)));
+        }
+        Ok(value as usize)
+    }
+}
+
+/// 指标注册表
+#[derive(Debug, Clone)]
+pub struct IndicatorRegistry {
+    specs: BTreeMap<&'static str, IndicatorSpec>,
+}
+
+impl IndicatorRegistry {
+    /// 空注册表
+    pub fn empty() -> Self {
+        Self { specs: BTreeMap::new() }
+    }
+
+    /// 包含全部内置指标的注册表
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        for spec in builtin() {
+            registry.register(spec);
+        }
+        registry
+    }
+
+    /// 注册指标，同名指标会被覆盖
+    pub fn register(&mut self, spec: IndicatorSpec) {
+        self.specs.insert(spec.name, spec);
+    }
+
+    /// 按名称查找 (不区分大小写)
+    pub fn get(&self, name: &str) -> Option<&IndicatorSpec> {
+        self.specs.get(name.to_ascii_lowercase().as_str())
+    }
+
+    /// 全部已注册指标
+    pub fn specs(&self) -> impl Iterator<Item = &IndicatorSpec> {
+        self.specs.values()
+    }
+
+    /// 按名称计算指标，未知参数视为错误以便尽早发现拼写问题
+    pub fn calculate(
+        &self,
+        indicators: &TechnicalIndicators,
+        name: &str,
+        input: &IndicatorInput,
+        params: &IndicatorParams,
+    ) -> AlphaResult<IndicatorOutput> {
+        let spec = self.get(name)
+            .ok_or_else(|| AlphaError::not_found(format!("Unknown indicator: {}", name)))?;
+
+        let mut resolved: BTreeMap<&'static str, f64> = spec.params.iter().copied().collect();
+        for (key, &value) in params {
+            let (known, _) = spec.params.iter()
+                .find(|(param, _)| param == key)
+                .ok_or_else(|| AlphaError::invalid_input(format!("Unknown parameter for {}: {}", spec.name, key)))?;
+            if !value.is_finite() {
+                return Err(AlphaError::invalid_input(format!("Parameter {} must be finite", key)));
+            }
+            resolved.insert(known, value);
+        }
+
+        (spec.calculate)(indicators, input, &ResolvedParams(resolved))
+    }
+}
+
+impl Default for IndicatorRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn output(columns: &[&str], values: Vec<Vec<f64>>) -> IndicatorOutput {
+    columns.iter().map(|c| c.to_string()).zip(values).collect()
+}
+
+fn builtin() -> Vec<IndicatorSpec> {
+    vec![
+        IndicatorSpec {
+            name: "sma",
+            description: "Simple moving average",
+            params: &[("period", 20.0)],
+            outputs: &["value"],
+            requires_ohlcv: false,
+            calculate: |ti, input, p| Ok(output(&["value"], vec![ti.calculate_sma(input.close, p.period("period")?)])),
+        },
+        IndicatorSpec {
+            name: "ema",
+            description: "Exponential moving average",
+            params: &[("period", 20.0)],
+            outputs: &["value"],
+            requires_ohlcv: false,
+            calculate: |ti, input, p| Ok(output(&["value"], vec![ti.calculate_ema(input.close, p.period("period")?)])),
+        },
+        IndicatorSpec {
+            name: "rsi",
+            description: "Relative strength index",
+            params: &[("period", 14.0)],
+            outputs: &["value"],
+            requires_ohlcv: false,
+            calculate: |ti, input, p| Ok(output(&["value"], vec![ti.calculate_rsi(input.close, p.period("period")?)])),
+        },
+        IndicatorSpec {
+            name: "bollinger",
+            description: "Bollinger bands",
+            params: &[("period", 20.0), ("std_dev", 2.0)],
+            outputs: &["upper", "middle", "lower"],
+            requires_ohlcv: false,
+            calculate: |ti, input, p| {
+                let (upper, middle, lower) = ti.calculate_bollinger_bands(input.close, p.period("period")?, p.value("std_dev"));
+                Ok(output(&["upper", "middle", "lower"], vec![upper, middle, lower]))
+            },
+        },
+        IndicatorSpec {
+            name: "macd",
+            description: "Moving average convergence divergence",
+            params: &[("fast", 12.0), ("slow", 26.0), ("signal", 9.0)],
+            outputs: &["macd", "signal", "histogram"],
+            requires_ohlcv: false,
+            calculate: |ti, input, p| {
+                let (macd, signal, histogram) = ti.calculate_macd(input.close, p.period("fast")?, p.period("slow")?, p.period("signal")?);
+                Ok(output(&["macd", "signal", "histogram"], vec![macd, signal, histogram]))
+            },
+        },
+        IndicatorSpec {
+            name: "atr",
+            description: "Average true range",
+            params: &[("period", 14.0)],
+            outputs: &["value"],
+            requires_ohlcv: true,
+            calculate: |ti, input, p| {
+                let atr = ti.calculate_atr(input.high()?, input.low()?, input.close, p.period("period")?);
+                Ok(output(&["value"], vec![atr]))
+            },
+        },
+        IndicatorSpec {
+            name: "stochastic",
+            description: "Stochastic oscillator",
+            params: &[("k_period", 14.0), ("d_period", 3.0)],
+            outputs: &["k", "d"],
+            requires_ohlcv: true,
+            calculate: |ti, input, p| {
+                let (k, d) = ti.calculate_stochastic(input.high()?, input.low()?, input.close, p.period("k_period")?, p.period("d_period")?);
+                Ok(output(&["k", "d"], vec![k, d]))
+            },
+        },
+        IndicatorSpec {
+            name: "vwap",
+            description: "Cumulative volume weighted average price",
+            params: &[],
+            outputs: &["value"],
+            requires_ohlcv: true,
+            calculate: |ti, input, _| {
+                let vwap = ti.calculate_vwap(input.high()?, input.low()?, input.close, input.volume()?);
+                Ok(output(&["value"], vec![vwap]))
+            },
+        },
+        IndicatorSpec {
+            name: "mfi",
+            description: "Money flow index",
+            params: &[("period", 14.0)],
+            outputs: &["value"],
+            requires_ohlcv: true,
+            calculate: |ti, input, p| {
+                let mfi = ti.calculate_mfi(input.high()?, input.low()?, input.close, input.volume()?, p.period("period")?);
+                Ok(output(&["value"], vec![mfi]))
+            },
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dispatch_matches_direct_calls() {
+        let registry = IndicatorRegistry::new();
+        let indicators = TechnicalIndicators::new();
+        let close: Vec<f64> = (0..40).map(|i| 100.0 + (i as f64 * 0.3).sin() * 4.0).collect();
+        let input = IndicatorInput::close(&close);
+
+        let mut params = IndicatorParams::new();
+        params.insert("period".to_string(), 10.0);
+        let rsi = registry.calculate(&indicators, "RSI", &input, &params).unwrap();
+        assert_eq!(rsi, vec![("value".to_string(), indicators.calculate_rsi(&close, 10))]);
+
+        let macd = registry.calculate(&indicators, "macd", &input, &IndicatorParams::new()).unwrap();
+        assert_eq!(macd.len(), 3);
+        assert_eq!(macd[0].1, indicators.calculate_macd(&close, 12, 26, 9).0);
+    }
+
+    #[test]
+    fn test_rejects_bad_requests() {
+        let registry = IndicatorRegistry::new();
+        let indicators = TechnicalIndicators::new();
+        let close = [1.0, 2.0, 3.0];
+        let input = IndicatorInput::close(&close);
+
+        let mut typo = IndicatorParams::new();
+        typo.insert("peroid".to_string(), 5.0);
+        assert!(registry.calculate(&indicators, "sma", &input, &typo).is_err());
+
+        let mut fractional = IndicatorParams::new();
+        fractional.insert("period".to_string(), 2.5);
+        assert!(registry.calculate(&indicators, "sma", &input, &fractional).is_err());
+
+        assert!(matches!(
+            registry.calculate(&indicators, "unknown", &input, &IndicatorParams::new()),
+            Err(AlphaError::DataNotFound(_))
+        ));
+        // 缺少高低价列
+        assert!(registry.calculate(&indicators, "atr", &input, &IndicatorParams::new()).is_err());
+    }
+}
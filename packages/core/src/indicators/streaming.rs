@@ -0,0 +1,314 @@
+//! 增量 (逐笔) 指标计算
+//!
+//! 每推入一个价格只做 O(1) 更新 (布林带为 O(period))，结果与 [`TechnicalIndicators`] 的批量计算逐点一致，
+//! 预热期内的值以 `None` 表示
+
+use super::{RoundTo, TechnicalIndicators};
+use crate::errors::{AlphaError, AlphaResult};
+use alloc::collections::VecDeque;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+use serde::{Deserialize, Serialize};
+
+/// 增量指标参数 (反序列化时缺省字段取默认值)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct StreamConfig {
+    pub rsi_period: usize,
+    pub ema_period: usize,
+    pub macd_fast: usize,
+    pub macd_slow: usize,
+    pub macd_signal: usize,
+    pub bollinger_period: usize,
+    pub bollinger_std_dev: f64,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            rsi_period: 14,
+            ema_period: 20,
+            macd_fast: 12,
+            macd_slow: 26,
+            macd_signal: 9,
+            bollinger_period: 20,
+            bollinger_std_dev: 2.0,
+        }
+    }
+}
+
+impl StreamConfig {
+    pub fn validate(&self) -> AlphaResult<()> {
+        let periods = [
+            self.rsi_period,
+            self.ema_period,
+            self.macd_fast,
+            self.macd_slow,
+            self.macd_signal,
+            self.bollinger_period,
+        ];
+        if periods.contains(&0) {
+            return Err(AlphaError::invalid_input("Indicator periods must be positive"));
+        }
+        if !self.bollinger_std_dev.is_finite() || self.bollinger_std_dev < 0.0 {
+            return Err(AlphaError::invalid_input("Bollinger std dev must be non-negative"));
+        }
+        Ok(())
+    }
+}
+
+/// 最新一笔的指标值
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct IndicatorSnapshot {
+    pub price: f64,
+    pub rsi: Option<f64>,
+    pub ema: Option<f64>,
+    pub macd: Option<f64>,
+    pub macd_signal: Option<f64>,
+    pub macd_histogram: Option<f64>,
+    pub bollinger_upper: Option<f64>,
+    pub bollinger_middle: Option<f64>,
+    pub bollinger_lower: Option<f64>,
+}
+
+/// 与 `calculate_ema` 一致的递推 EMA (首值为首个输入，之后每步按精度取整)
+#[derive(Debug, Clone)]
+struct Ema {
+    multiplier: f64,
+    value: Option<f64>,
+}
+
+impl Ema {
+    fn new(period: usize) -> Self {
+        Self {
+            multiplier: 2.0 / (period + 1) as f64,
+            value: None,
+        }
+    }
+
+    fn update(&mut self, input: f64, precision: usize) -> f64 {
+        let next = match self.value {
+            Some(prev) => ((input - prev) * self.multiplier + prev).round_to(precision),
+            None => input,
+        };
+        self.value = Some(next);
+        next
+    }
+}
+
+/// 增量技术指标计算器
+#[derive(Debug, Clone)]
+pub struct StreamingIndicators {
+    config: StreamConfig,
+    precision: usize,
+    count: usize,
+    last_price: Option<f64>,
+
+    // RSI (Wilder 平滑)
+    gain_sum: f64,
+    loss_sum: f64,
+    avg_gain: f64,
+    avg_loss: f64,
+
+    ema: Ema,
+    macd_fast: Ema,
+    macd_slow: Ema,
+    macd_signal: Ema,
+
+    // 布林带滑动窗口
+    window: VecDeque<f64>,
+    window_sum: f64,
+
+    latest: Option<IndicatorSnapshot>,
+}
+
+impl StreamingIndicators {
+    pub fn new(config: StreamConfig) -> AlphaResult<Self> {
+        Self::with_precision(config, TechnicalIndicators::new().precision)
+    }
+
+    pub fn with_precision(config: StreamConfig, precision: usize) -> AlphaResult<Self> {
+        config.validate()?;
+
+        Ok(Self {
+            precision,
+            count: 0,
+            last_price: None,
+            gain_sum: 0.0,
+            loss_sum: 0.0,
+            avg_gain: 0.0,
+            avg_loss: 0.0,
+            ema: Ema::new(config.ema_period),
+            macd_fast: Ema::new(config.macd_fast),
+            macd_slow: Ema::new(config.macd_slow),
+            macd_signal: Ema::new(config.macd_signal),
+            window: VecDeque::with_capacity(config.bollinger_period),
+            window_sum: 0.0,
+            latest: None,
+            config,
+        })
+    }
+
+    pub fn config(&self) -> &StreamConfig {
+        &self.config
+    }
+
+    /// 已推入的价格数量
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// 最近一次推入后的指标值
+    pub fn latest(&self) -> Option<IndicatorSnapshot> {
+        self.latest
+    }
+
+    /// 推入新价格并返回最新指标值
+    pub fn push(&mut self, price: f64) -> IndicatorSnapshot {
+        let precision = self.precision;
+        let rsi = self.update_rsi(price);
+
+        let ema = self.ema.update(price, precision);
+
+        let fast = self.macd_fast.update(price, precision);
+        let slow = self.macd_slow.update(price, precision);
+        let macd = (fast - slow).round_to(precision);
+        let signal = self.macd_signal.update(macd, precision);
+        let histogram = ((macd - signal) * 1000.0).round_to(precision);
+
+        let (upper, middle, lower) = self.update_bollinger(price);
+
+        self.count += 1;
+        self.last_price = Some(price);
+
+        let snapshot = IndicatorSnapshot {
+            price,
+            rsi,
+            ema: Some(ema),
+            macd: Some(macd),
+            macd_signal: Some(signal),
+            macd_histogram: Some(histogram),
+            bollinger_upper: upper,
+            bollinger_middle: middle,
+            bollinger_lower: lower,
+        };
+        self.latest = Some(snapshot);
+        snapshot
+    }
+
+    /// 清空状态，重新开始预热
+    pub fn reset(&mut self) {
+        // 参数在构造时已校验，重建不会失败
+        if let Ok(fresh) = Self::with_precision(self.config, self.precision) {
+            *self = fresh;
+        }
+    }
+
+    fn update_rsi(&mut self, price: f64) -> Option<f64> {
+        let period = self.config.rsi_period;
+        let change = price - self.last_price?;
+        let gain = if change > 0.0 { change } else { 0.0 };
+        let loss = if change < 0.0 { -change } else { 0.0 };
+
+        // 当前为第 count 个涨跌幅 (从 1 开始)，累计满 period 个后才有首个 RSI
+        let changes = self.count;
+        if changes <= period {
+            self.gain_sum += gain;
+            self.loss_sum += loss;
+            if changes < period {
+                return None;
+            }
+            self.avg_gain = self.gain_sum / period as f64;
+            self.avg_loss = self.loss_sum / period as f64;
+        } else {
+            self.avg_gain = (self.avg_gain * (period - 1) as f64 + gain) / period as f64;
+            self.avg_loss = (self.avg_loss * (period - 1) as f64 + loss) / period as f64;
+        }
+
+        Some(if self.avg_loss == 0.0 {
+            100.0
+        } else {
+            let rs = self.avg_gain / self.avg_loss;
+            (100.0 - (100.0 / (1.0 + rs))).round_to(self.precision)
+        })
+    }
+
+    fn update_bollinger(&mut self, price: f64) -> (Option<f64>, Option<f64>, Option<f64>) {
+        let period = self.config.bollinger_period;
+
+        if self.window.len() == period {
+            let oldest = self.window.pop_front().unwrap_or_default();
+            self.window_sum = self.window_sum - oldest + price;
+        } else {
+            self.window_sum += price;
+        }
+        self.window.push_back(price);
+
+        if self.window.len() < period {
+            return (None, None, None);
+        }
+
+        let mean = (self.window_sum / period as f64).round_to(self.precision);
+        let variance = self.window.iter()
+            .map(|&p| (p - mean).powi(2))
+            .sum::<f64>() / period as f64;
+        let std_deviation = variance.sqrt();
+        let k = self.config.bollinger_std_dev;
+
+        (
+            Some((mean + k * std_deviation).round_to(self.precision)),
+            Some(mean),
+            Some((mean - k * std_deviation).round_to(self.precision)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_matches_batch_calculation() {
+        let prices: Vec<f64> = (0..120)
+            .map(|i| 100.0 + (i as f64 * 0.37).sin() * 5.0 + i as f64 * 0.05)
+            .collect();
+        let config = StreamConfig::default();
+        let batch = TechnicalIndicators::new();
+
+        let rsi = batch.calculate_rsi(&prices, config.rsi_period);
+        let ema = batch.calculate_ema(&prices, config.ema_period);
+        let (macd, signal, histogram) = batch.calculate_macd(&prices, config.macd_fast, config.macd_slow, config.macd_signal);
+        let (upper, middle, lower) = batch.calculate_bollinger_bands(&prices, config.bollinger_period, config.bollinger_std_dev);
+
+        let mut stream = StreamingIndicators::new(config).unwrap();
+        for (i, &price) in prices.iter().enumerate() {
+            let snap = stream.push(price);
+
+            assert_eq!(snap.rsi, (i >= config.rsi_period).then_some(rsi[i]), "rsi at {}", i);
+            assert_eq!(snap.ema, Some(ema[i]));
+            assert_eq!(snap.macd, Some(macd[i]));
+            assert_eq!(snap.macd_signal, Some(signal[i]));
+            assert_eq!(snap.macd_histogram, Some(histogram[i]));
+
+            let ready = i + 1 >= config.bollinger_period;
+            assert_eq!(snap.bollinger_upper, ready.then_some(upper[i]));
+            assert_eq!(snap.bollinger_middle, ready.then_some(middle[i]));
+            assert_eq!(snap.bollinger_lower, ready.then_some(lower[i]));
+        }
+
+        assert_eq!(stream.len(), prices.len());
+        stream.reset();
+        assert!(stream.is_empty() && stream.latest().is_none());
+    }
+
+    #[test]
+    fn test_rejects_zero_period() {
+        let config = StreamConfig { rsi_period: 0, ..StreamConfig::default() };
+        assert!(StreamingIndicators::new(config).is_err());
+    }
+}
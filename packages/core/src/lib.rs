@@ -4,17 +4,33 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
+/// 各模块共用的 alloc 类型导入，保证 std 与 no_std 构建使用同一套路径
+pub(crate) mod prelude {
+    pub use alloc::boxed::Box;
+    pub use alloc::format;
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec;
+    pub use alloc::vec::Vec;
+}
+
 pub mod models;
 pub mod indicators;
 pub mod analytics;
 pub mod utils;
 pub mod errors;
+pub mod simulate;
+pub mod portfolio;
+pub mod provider;
+pub mod screener;
 
 // 重新导出主要类型
 pub use models::*;
 pub use indicators::TechnicalIndicators;
 pub use analytics::AnalysisEngine;
 pub use errors::*;
+pub use simulate::MarketSimulator;
 
 #[cfg(test)]
 mod tests {
@@ -1,11 +1,14 @@
 //! 跨平台数据模型定义
 
 use chrono::{DateTime, Utc};
+use crate::prelude::*;
+use alloc::collections::{BTreeMap, BTreeSet};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 /// 市场数据基础结构
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct MarketData {
     /// 股票代码
     pub symbol: String,
@@ -28,6 +31,7 @@ pub struct MarketData {
 }
 
 impl MarketData {
+    #[cfg(feature = "std")]
     pub fn new(symbol: String, price: f64, volume: u64) -> Self {
         Self {
             symbol,
@@ -65,6 +69,47 @@ impl MarketData {
     }
 }
 
+/// 逐笔成交
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Trade {
+    pub symbol: String,
+    /// 成交时间
+    pub timestamp: DateTime<Utc>,
+    pub price: f64,
+    /// 成交数量，加密货币等可为小数
+    pub size: f64,
+    /// 上游的成交编号
+    pub trade_id: Option<String>,
+}
+
+/// 最优买卖报价
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Quote {
+    pub symbol: String,
+    pub timestamp: DateTime<Utc>,
+    pub bid: f64,
+    pub bid_size: f64,
+    pub ask: f64,
+    pub ask_size: f64,
+}
+
+impl Quote {
+    /// 中间价
+    pub fn mid(&self) -> f64 {
+        (self.bid + self.ask) / 2.0
+    }
+
+    /// 买卖价差
+    pub fn spread(&self) -> f64 {
+        self.ask - self.bid
+    }
+
+    /// 双边价格为正且未倒挂
+    pub fn is_valid(&self) -> bool {
+        self.bid > 0.0 && self.ask >= self.bid && self.ask.is_finite()
+    }
+}
+
 /// 技术指标结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndicatorResult {
@@ -76,6 +121,156 @@ pub struct IndicatorResult {
     pub values: Vec<f64>,
     /// 信号序列
     pub signals: Vec<SignalType>,
+    /// 预热期长度，前 `warmup` 个值是填充的 0.0 而不是有效数据
+    #[serde(default)]
+    pub warmup: usize,
+}
+
+impl IndicatorResult {
+    /// 转换为按时间戳对齐的序列，预热期和缺少对应值的时间点记为缺失
+    pub fn to_series(&self) -> IndicatorSeries {
+        IndicatorSeries::from_aligned(self.name.clone(), &self.timestamps, &self.values, self.warmup)
+    }
+}
+
+/// 带时间戳的指标值，`value` 为 `None` 表示该时间点无有效数据
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct IndicatorPoint {
+    /// 时间戳
+    pub timestamp: DateTime<Utc>,
+    /// 指标值
+    pub value: Option<f64>,
+}
+
+/// 时间戳对齐的指标序列，支持缺失数据
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IndicatorSeries {
+    /// 指标名称
+    pub name: String,
+    /// 数据点 (按时间升序)
+    pub points: Vec<IndicatorPoint>,
+}
+
+impl IndicatorSeries {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            points: Vec::new(),
+        }
+    }
+
+    /// 由等长的时间戳和指标值构造序列，前 `warmup` 个点 (指标预热期) 记为缺失
+    pub fn from_aligned(
+        name: impl Into<String>,
+        timestamps: &[DateTime<Utc>],
+        values: &[f64],
+        warmup: usize,
+    ) -> Self {
+        let points = timestamps.iter()
+            .enumerate()
+            .map(|(i, &timestamp)| IndicatorPoint {
+                timestamp,
+                value: if i < warmup {
+                    None
+                } else {
+                    values.get(i).copied().filter(|v| v.is_finite())
+                },
+            })
+            .collect();
+
+        Self {
+            name: name.into(),
+            points,
+        }
+    }
+
+    /// 追加数据点
+    pub fn push(&mut self, timestamp: DateTime<Utc>, value: Option<f64>) {
+        self.points.push(IndicatorPoint { timestamp, value });
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// 有效 (非缺失) 数据点数量
+    pub fn valid_count(&self) -> usize {
+        self.points.iter().filter(|p| p.value.is_some()).count()
+    }
+
+    /// 查找指定时间点的值
+    pub fn value_at(&self, timestamp: &DateTime<Utc>) -> Option<f64> {
+        self.points.iter()
+            .find(|p| p.timestamp == *timestamp)
+            .and_then(|p| p.value)
+    }
+
+    /// 最近一个有效数据点
+    pub fn latest(&self) -> Option<IndicatorPoint> {
+        self.points.iter().rev().find(|p| p.value.is_some()).copied()
+    }
+
+    /// 按给定时间索引对齐，索引中不存在于序列的时间点记为缺失
+    pub fn align_to(&self, index: &[DateTime<Utc>]) -> Vec<Option<f64>> {
+        let lookup: BTreeMap<DateTime<Utc>, Option<f64>> = self.points.iter()
+            .map(|p| (p.timestamp, p.value))
+            .collect();
+
+        index.iter()
+            .map(|ts| lookup.get(ts).copied().flatten())
+            .collect()
+    }
+
+    /// 将多个序列按时间戳并集进行外连接
+    pub fn join(series: &[IndicatorSeries]) -> JoinedSeries {
+        let timestamps: Vec<DateTime<Utc>> = series.iter()
+            .flat_map(|s| s.points.iter().map(|p| p.timestamp))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        let columns = series.iter()
+            .map(|s| (s.name.clone(), s.align_to(&timestamps)))
+            .collect();
+
+        JoinedSeries { timestamps, columns }
+    }
+}
+
+/// 多个指标序列在公共时间索引上的连接结果
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JoinedSeries {
+    /// 公共时间索引 (升序)
+    pub timestamps: Vec<DateTime<Utc>>,
+    /// 各指标列 (名称, 与时间索引对齐的值)
+    pub columns: Vec<(String, Vec<Option<f64>>)>,
+}
+
+impl JoinedSeries {
+    /// 按名称获取列
+    pub fn column(&self, name: &str) -> Option<&[Option<f64>]> {
+        self.columns.iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, values)| values.as_slice())
+    }
+
+    /// 仅保留所有列均有值的时间点 (内连接)
+    pub fn dropna(&self) -> JoinedSeries {
+        let keep: Vec<usize> = (0..self.timestamps.len())
+            .filter(|&i| self.columns.iter().all(|(_, values)| values[i].is_some()))
+            .collect();
+
+        JoinedSeries {
+            timestamps: keep.iter().map(|&i| self.timestamps[i]).collect(),
+            columns: self.columns.iter()
+                .map(|(name, values)| (name.clone(), keep.iter().map(|&i| values[i]).collect()))
+                .collect(),
+        }
+    }
 }
 
 /// 交易信号类型
@@ -91,6 +286,61 @@ pub enum SignalType {
     None,
 }
 
+/// 信号适用周期
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum SignalHorizon {
+    /// 日内
+    Intraday,
+    /// 短期 (数日至数周)
+    ShortTerm,
+    /// 中期 (数周至数月)
+    MediumTerm,
+    /// 长期
+    LongTerm,
+}
+
+/// 带强度和依据的交易信号
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Signal {
+    /// 信号方向
+    pub direction: SignalType,
+    /// 信号强度 (0.0 - 1.0)
+    pub strength: f64,
+    /// 产生信号的指标名称
+    pub source_indicator: String,
+    /// 信号依据说明
+    pub rationale: String,
+    /// 适用周期
+    pub horizon: SignalHorizon,
+}
+
+impl Signal {
+    pub fn new(
+        direction: SignalType,
+        strength: f64,
+        source_indicator: impl Into<String>,
+        rationale: impl Into<String>,
+        horizon: SignalHorizon,
+    ) -> Self {
+        Self {
+            direction,
+            strength: strength.clamp(0.0, 1.0),
+            source_indicator: source_indicator.into(),
+            rationale: rationale.into(),
+            horizon,
+        }
+    }
+
+    /// 带方向的得分：买入为正，卖出为负，其余为 0
+    pub fn score(&self) -> f64 {
+        match self.direction {
+            SignalType::Buy => self.strength,
+            SignalType::Sell => -self.strength,
+            SignalType::Hold | SignalType::None => 0.0,
+        }
+    }
+}
+
 /// 分析结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnalysisResult {
@@ -104,6 +354,8 @@ pub struct AnalysisResult {
     pub risk_metrics: RiskMetrics,
     /// 推荐信号
     pub recommendation: SignalType,
+    /// 构成推荐的各项信号 (按强度降序)
+    pub signals: Vec<Signal>,
     /// 置信度
     pub confidence: f64,
 }
@@ -144,16 +396,16 @@ pub struct TradingStrategy {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StrategyParameters {
     /// 参数映射
-    pub params: std::collections::HashMap<String, f64>,
+    pub params: BTreeMap<String, f64>,
     /// 参数描述
-    pub descriptions: std::collections::HashMap<String, String>,
+    pub descriptions: BTreeMap<String, String>,
 }
 
 impl StrategyParameters {
     pub fn new() -> Self {
         Self {
-            params: std::collections::HashMap::new(),
-            descriptions: std::collections::HashMap::new(),
+            params: BTreeMap::new(),
+            descriptions: BTreeMap::new(),
         }
     }
 
@@ -199,4 +451,506 @@ pub enum DataSource {
     File,
     /// API 数据源
     API(String),
-}
\ No newline at end of file
+}
+/// 交易所
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Exchange {
+    /// 纽约证券交易所
+    Nyse,
+    /// 纳斯达克
+    Nasdaq,
+    /// 伦敦证券交易所
+    Lse,
+    /// 香港交易所
+    Hkex,
+    /// 上海证券交易所
+    Sse,
+    /// 深圳证券交易所
+    Szse,
+}
+
+impl Exchange {
+    /// 全部支持的交易所
+    pub const ALL: [Exchange; 6] = [
+        Exchange::Nyse,
+        Exchange::Nasdaq,
+        Exchange::Lse,
+        Exchange::Hkex,
+        Exchange::Sse,
+        Exchange::Szse,
+    ];
+
+    /// 交易所代码
+    pub fn code(&self) -> &'static str {
+        match self {
+            Exchange::Nyse => "NYSE",
+            Exchange::Nasdaq => "NASDAQ",
+            Exchange::Lse => "LSE",
+            Exchange::Hkex => "HKEX",
+            Exchange::Sse => "SSE",
+            Exchange::Szse => "SZSE",
+        }
+    }
+
+    /// 由交易所代码解析 (不区分大小写)
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|e| e.code().eq_ignore_ascii_case(code))
+    }
+
+    /// 行情代码后缀 (如 `0700.HK` 中的 `HK`)，美股不带后缀
+    pub fn suffix(&self) -> Option<&'static str> {
+        match self {
+            Exchange::Nyse | Exchange::Nasdaq => None,
+            Exchange::Lse => Some("L"),
+            Exchange::Hkex => Some("HK"),
+            Exchange::Sse => Some("SS"),
+            Exchange::Szse => Some("SZ"),
+        }
+    }
+
+    /// 由行情代码后缀解析 (不区分大小写，兼容 `SH`、`LN` 等常见别名)
+    pub fn from_suffix(suffix: &str) -> Option<Self> {
+        match suffix.to_ascii_uppercase().as_str() {
+            "L" | "LN" | "LON" => Some(Exchange::Lse),
+            "HK" | "HKG" => Some(Exchange::Hkex),
+            "SS" | "SH" | "SHA" => Some(Exchange::Sse),
+            "SZ" | "SHE" => Some(Exchange::Szse),
+            _ => None,
+        }
+    }
+}
+
+/// 自选股列表
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Watchlist {
+    /// 列表 ID
+    pub id: Uuid,
+    /// 列表名称
+    pub name: String,
+    /// 股票代码列表 (保持用户添加顺序)
+    pub symbols: Vec<String>,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+    /// 更新时间
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Watchlist {
+    #[cfg(feature = "std")]
+    pub fn new(name: String) -> Self {
+        Self::with_id(Uuid::new_v4(), name, Utc::now())
+    }
+
+    /// 使用指定 ID 和创建时间构造 (no_std 环境下无系统时钟和随机数)
+    pub fn with_id(id: Uuid, name: String, created_at: DateTime<Utc>) -> Self {
+        Self {
+            id,
+            name,
+            symbols: Vec::new(),
+            created_at,
+            updated_at: created_at,
+        }
+    }
+
+    /// 添加股票代码，已存在时返回 false
+    pub fn add_symbol(&mut self, symbol: impl Into<String>) -> bool {
+        let symbol = symbol.into();
+        if self.contains(&symbol) {
+            return false;
+        }
+        self.symbols.push(symbol);
+        self.touch();
+        true
+    }
+
+    /// 移除股票代码，不存在时返回 false
+    pub fn remove_symbol(&mut self, symbol: &str) -> bool {
+        let before = self.symbols.len();
+        self.symbols.retain(|s| s != symbol);
+        let removed = self.symbols.len() != before;
+        if removed {
+            self.touch();
+        }
+        removed
+    }
+
+    pub fn contains(&self, symbol: &str) -> bool {
+        self.symbols.iter().any(|s| s == symbol)
+    }
+
+    fn touch(&mut self) {
+        #[cfg(feature = "std")]
+        {
+            self.updated_at = Utc::now();
+        }
+    }
+}
+
+/// 均线类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MovingAverage {
+    Sma,
+    Ema,
+}
+
+/// 穿越方向
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CrossDirection {
+    /// 由下向上穿越
+    Above,
+    /// 由上向下穿越
+    Below,
+}
+
+impl CrossDirection {
+    /// 从 (前值, 前基准) 到 (现值, 现基准) 是否按该方向穿越
+    fn crossed(self, previous: f64, previous_base: f64, current: f64, current_base: f64) -> bool {
+        match self {
+            CrossDirection::Above => previous <= previous_base && current > current_base,
+            CrossDirection::Below => previous >= previous_base && current < current_base,
+        }
+    }
+}
+
+/// 告警触发条件
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// 价格高于目标价
+    PriceAbove { target: f64 },
+    /// 价格低于目标价
+    PriceBelow { target: f64 },
+    /// `window_minutes` 分钟内涨跌幅达到 `percent` (%)，正数为涨幅，负数为跌幅
+    PercentChange { percent: f64, window_minutes: u32 },
+    /// 价格穿越均线，如价格上穿 SMA(50)
+    PriceCrossesAverage { average: MovingAverage, period: usize, direction: CrossDirection },
+    /// RSI 穿越阈值，如 RSI(14) 上穿 70
+    RsiCrosses { period: usize, level: f64, direction: CrossDirection },
+    /// 当日成交量达到前 `lookback` 个交易日均量的 `multiple` 倍
+    VolumeSpike { multiple: f64, lookback: usize },
+    /// 创 52 周新高
+    High52Week,
+    /// 创 52 周新低
+    Low52Week,
+}
+
+/// 条件求值所需的历史数据，均按时间升序
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AlertContext<'a> {
+    /// 日线，与行情同一天的 K 线 (未收盘) 会被忽略
+    pub daily: &'a [MarketData],
+    /// 近期行情 (逐笔或定时刷新的报价)，用于短窗口涨跌幅
+    pub recent: &'a [MarketData],
+}
+
+impl AlertContext<'_> {
+    /// 行情日之前的日线
+    fn prior_daily(&self, data: &MarketData) -> &[MarketData] {
+        let today = data.timestamp.date_naive();
+        let end = self.daily.iter().rposition(|bar| bar.timestamp.date_naive() < today).map_or(0, |i| i + 1);
+        &self.daily[..end]
+    }
+
+    /// 行情日之前的日线收盘价，末尾追加当前价格
+    fn closes(&self, data: &MarketData) -> Vec<f64> {
+        self.prior_daily(data).iter().map(|bar| bar.price).chain(core::iter::once(data.price)).collect()
+    }
+}
+
+impl AlertCondition {
+    /// 是否需要日线历史
+    pub fn needs_daily(&self) -> bool {
+        !matches!(self, AlertCondition::PriceAbove { .. } | AlertCondition::PriceBelow { .. } | AlertCondition::PercentChange { .. })
+    }
+
+    /// 需要保留的近期行情时长 (分钟)
+    pub fn recent_window_minutes(&self) -> Option<u32> {
+        match self {
+            AlertCondition::PercentChange { window_minutes, .. } => Some(*window_minutes),
+            _ => None,
+        }
+    }
+
+    /// 判断行情是否满足条件，历史数据不足时视为不满足
+    pub fn is_met(&self, data: &MarketData, context: &AlertContext) -> bool {
+        match self {
+            AlertCondition::PriceAbove { target } => data.price >= *target,
+            AlertCondition::PriceBelow { target } => data.price <= *target,
+            AlertCondition::PercentChange { percent, window_minutes } => {
+                let start = data.timestamp - chrono::Duration::minutes(i64::from(*window_minutes));
+                let Some(base) = context.recent.iter()
+                    .find(|quote| quote.timestamp >= start && quote.timestamp <= data.timestamp && quote.price > 0.0)
+                else {
+                    return false;
+                };
+                let change = (data.price - base.price) / base.price * 100.0;
+                if *percent >= 0.0 { change >= *percent } else { change <= *percent }
+            }
+            AlertCondition::PriceCrossesAverage { average, period, direction } => {
+                let closes = context.closes(data);
+                if *period == 0 || closes.len() < period + 1 {
+                    return false;
+                }
+                let indicators = crate::indicators::TechnicalIndicators::new();
+                let line = match average {
+                    MovingAverage::Sma => indicators.calculate_sma(&closes, *period),
+                    MovingAverage::Ema => indicators.calculate_ema(&closes, *period),
+                };
+                let n = closes.len();
+                direction.crossed(closes[n - 2], line[n - 2], closes[n - 1], line[n - 1])
+            }
+            AlertCondition::RsiCrosses { period, level, direction } => {
+                let closes = context.closes(data);
+                if *period == 0 || closes.len() < period + 2 {
+                    return false;
+                }
+                let rsi = crate::indicators::TechnicalIndicators::new().calculate_rsi(&closes, *period);
+                let n = closes.len();
+                direction.crossed(rsi[n - 2], *level, rsi[n - 1], *level)
+            }
+            AlertCondition::VolumeSpike { multiple, lookback } => {
+                let prior = context.prior_daily(data);
+                if *lookback == 0 || prior.len() < *lookback {
+                    return false;
+                }
+                let average = prior[prior.len() - lookback..].iter().map(|bar| bar.volume as f64).sum::<f64>() / *lookback as f64;
+                average > 0.0 && data.volume as f64 >= average * multiple
+            }
+            AlertCondition::High52Week | AlertCondition::Low52Week => {
+                let start = data.timestamp - chrono::Duration::weeks(52);
+                let mut year = context.prior_daily(data).iter().filter(|bar| bar.timestamp >= start).peekable();
+                if year.peek().is_none() {
+                    return false;
+                }
+                if matches!(self, AlertCondition::High52Week) {
+                    let high = year.map(|bar| bar.high.unwrap_or(bar.price)).fold(f64::MIN, f64::max);
+                    data.price > high
+                } else {
+                    let low = year.map(|bar| bar.low.unwrap_or(bar.price)).fold(f64::MAX, f64::min);
+                    data.price < low
+                }
+            }
+        }
+    }
+}
+
+/// 告警定义
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Alert {
+    /// 告警 ID
+    pub id: Uuid,
+    /// 股票代码
+    pub symbol: String,
+    /// 触发条件
+    pub condition: AlertCondition,
+    /// 附加说明
+    pub message: Option<String>,
+    /// 是否启用
+    pub active: bool,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+    /// 最近一次触发时间
+    pub triggered_at: Option<DateTime<Utc>>,
+    /// 过期时间，过期后不再触发
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 暂停提醒至该时间
+    #[serde(default)]
+    pub snoozed_until: Option<DateTime<Utc>>,
+}
+
+impl Alert {
+    #[cfg(feature = "std")]
+    pub fn new(symbol: String, condition: AlertCondition) -> Self {
+        Self::with_id(Uuid::new_v4(), symbol, condition, Utc::now())
+    }
+
+    /// 使用指定 ID 和创建时间构造 (no_std 环境下无系统时钟和随机数)
+    pub fn with_id(id: Uuid, symbol: String, condition: AlertCondition, created_at: DateTime<Utc>) -> Self {
+        Self {
+            id,
+            symbol,
+            condition,
+            message: None,
+            active: true,
+            created_at,
+            triggered_at: None,
+            expires_at: None,
+            snoozed_until: None,
+        }
+    }
+
+    pub fn is_expired(&self, now: &DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= *now)
+    }
+
+    /// 在 `now` 时刻是否会触发：已启用、未过期且不在暂停期内
+    pub fn is_armed(&self, now: &DateTime<Utc>) -> bool {
+        self.active && !self.is_expired(now) && self.snoozed_until.map_or(true, |until| until <= *now)
+    }
+
+    /// 检查告警 (以行情时间判断过期与暂停)，满足条件时记录触发时间并返回 true
+    pub fn check(&mut self, data: &MarketData, context: &AlertContext) -> bool {
+        if !self.is_armed(&data.timestamp) || data.symbol != self.symbol || !self.condition.is_met(data, context) {
+            return false;
+        }
+        self.triggered_at = Some(data.timestamp);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        let quote = Quote {
+            symbol: "AAPL".to_string(),
+            timestamp: DateTime::from_timestamp(0, 0).unwrap(),
+            bid: 99.5,
+            bid_size: 100.0,
+            ask: 100.5,
+            ask_size: 200.0,
+        };
+        assert_eq!(quote.mid(), 100.0);
+        assert_eq!(quote.spread(), 1.0);
+        assert!(quote.is_valid());
+        assert!(!Quote { ask: 99.0, ..quote.clone() }.is_valid());
+        assert!(!Quote { bid: 0.0, ..quote }.is_valid());
+    }
+
+    #[test]
+    fn test_watchlist_symbols() {
+        let mut watchlist = Watchlist::new("Tech".to_string());
+
+        assert!(watchlist.add_symbol("AAPL"));
+        assert!(!watchlist.add_symbol("AAPL"));
+        assert!(watchlist.contains("AAPL"));
+        assert!(watchlist.remove_symbol("AAPL"));
+        assert!(!watchlist.remove_symbol("AAPL"));
+    }
+
+    #[test]
+    fn test_indicator_series_join() {
+        let t0 = Utc::now();
+        let ts: Vec<_> = (0..4).map(|i| t0 + chrono::Duration::minutes(i)).collect();
+
+        let sma = IndicatorSeries::from_aligned("SMA(2)", &ts, &[0.0, 1.5, 2.5, 3.5], 1);
+        let mut rsi = IndicatorSeries::new("RSI(14)");
+        rsi.push(ts[1], Some(55.0));
+        rsi.push(ts[3], None);
+
+        assert_eq!(sma.valid_count(), 3);
+        assert_eq!(rsi.latest().map(|p| p.timestamp), Some(ts[1]));
+
+        let joined = IndicatorSeries::join(&[sma, rsi]);
+        assert_eq!(joined.timestamps, ts);
+        assert_eq!(joined.column("RSI(14)").unwrap(), &[None, Some(55.0), None, None]);
+
+        let complete = joined.dropna();
+        assert_eq!(complete.timestamps, vec![ts[1]]);
+        assert_eq!(complete.column("SMA(2)").unwrap(), &[Some(1.5)]);
+    }
+
+    #[test]
+    fn test_indicator_result_warmup() {
+        let t0 = Utc::now();
+        let timestamps: Vec<_> = (0..5).map(|i| t0 + chrono::Duration::minutes(i)).collect();
+        let values = crate::indicators::TechnicalIndicators::new().calculate_sma(&[1.0, 2.0, 3.0, 4.0, 5.0], 3);
+        assert_eq!(values[..2], [0.0, 0.0]);
+
+        let result = IndicatorResult { name: "SMA(3)".to_string(), timestamps, values, signals: Vec::new(), warmup: 2 };
+        let series = result.to_series();
+        // 预热期填充的 0.0 不是有效数据
+        assert_eq!(series.points.iter().map(|p| p.value).collect::<Vec<_>>(), vec![None, None, Some(2.0), Some(3.0), Some(4.0)]);
+        assert_eq!(series.valid_count(), 3);
+
+        // 旧版本序列化的结果没有 warmup 字段
+        let legacy: IndicatorResult = serde_json::from_str(r#"{"name": "RSI(14)", "timestamps": [], "values": [], "signals": []}"#).unwrap();
+        assert_eq!(legacy.warmup, 0);
+    }
+
+    #[test]
+    fn test_alert_check() {
+        let mut alert = Alert::new("AAPL".to_string(), AlertCondition::PriceAbove { target: 150.0 });
+
+        assert!(!alert.check(&MarketData::new("AAPL".to_string(), 149.0, 100), &AlertContext::default()));
+        assert!(!alert.check(&MarketData::new("MSFT".to_string(), 151.0, 100), &AlertContext::default()));
+        assert!(alert.check(&MarketData::new("AAPL".to_string(), 151.0, 100), &AlertContext::default()));
+        assert!(alert.triggered_at.is_some());
+
+        let json = serde_json::to_string(&alert).unwrap();
+        assert!(json.contains("\"type\":\"price_above\""));
+
+        let quote = MarketData::new("AAPL".to_string(), 151.0, 100);
+        alert.snoozed_until = Some(quote.timestamp + chrono::Duration::minutes(5));
+        assert!(!alert.check(&quote, &AlertContext::default()));
+        alert.snoozed_until = None;
+        alert.expires_at = Some(quote.timestamp);
+        assert!(alert.is_expired(&quote.timestamp));
+        assert!(!alert.check(&quote, &AlertContext::default()));
+    }
+
+    fn daily(prices: &[f64]) -> Vec<MarketData> {
+        let start = Utc::now() - chrono::Duration::days(prices.len() as i64);
+        prices.iter()
+            .enumerate()
+            .map(|(i, &price)| MarketData {
+                timestamp: start + chrono::Duration::days(i as i64),
+                ..MarketData::new("AAPL".to_string(), price, 1000)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_history_conditions() {
+        let quote = |price: f64, volume: u64| MarketData::new("AAPL".to_string(), price, volume);
+
+        // 前 10 天 100，最后一天 99：价格由均线下方上穿
+        let mut prices = vec![100.0; 10];
+        prices.push(99.0);
+        let bars = daily(&prices);
+        let context = AlertContext { daily: &bars, recent: &[] };
+        let cross = AlertCondition::PriceCrossesAverage { average: MovingAverage::Sma, period: 5, direction: CrossDirection::Above };
+        assert!(cross.is_met(&quote(101.0, 0), &context));
+        assert!(!cross.is_met(&quote(99.0, 0), &context));
+        assert!(!cross.is_met(&quote(101.0, 0), &AlertContext::default()));
+
+        let rising = daily(&(0..20).map(|i| 100.0 - i as f64 * 0.5).collect::<Vec<_>>());
+        let rsi = AlertCondition::RsiCrosses { period: 14, level: 30.0, direction: CrossDirection::Above };
+        assert!(rsi.is_met(&quote(120.0, 0), &AlertContext { daily: &rising, recent: &[] }));
+
+        let spike = AlertCondition::VolumeSpike { multiple: 3.0, lookback: 5 };
+        assert!(spike.is_met(&quote(100.0, 3000), &context));
+        assert!(!spike.is_met(&quote(100.0, 2999), &context));
+
+        assert!(AlertCondition::High52Week.is_met(&quote(100.5, 0), &context));
+        assert!(!AlertCondition::High52Week.is_met(&quote(100.0, 0), &context));
+        assert!(AlertCondition::Low52Week.is_met(&quote(98.0, 0), &context));
+        // 当天未收盘的日线不参与比较
+        let today = [MarketData { high: Some(150.0), ..quote(100.0, 0) }];
+        assert!(AlertCondition::High52Week.is_met(&quote(101.0, 0), &AlertContext { daily: &[bars.clone(), today.to_vec()].concat(), recent: &[] }));
+    }
+
+    #[test]
+    fn test_percent_change_window() {
+        let now = Utc::now();
+        let at = |minutes: i64, price: f64| MarketData { timestamp: now - chrono::Duration::minutes(minutes), ..MarketData::new("AAPL".to_string(), price, 0) };
+        let recent = [at(90, 80.0), at(30, 100.0), at(10, 103.0)];
+        let context = AlertContext { daily: &[], recent: &recent };
+
+        let rise = AlertCondition::PercentChange { percent: 5.0, window_minutes: 60 };
+        assert!(rise.is_met(&at(0, 105.0), &context));
+        assert!(!rise.is_met(&at(0, 104.0), &context));
+        let fall = AlertCondition::PercentChange { percent: -5.0, window_minutes: 60 };
+        assert!(fall.is_met(&at(0, 95.0), &context));
+        assert!(!rise.is_met(&at(0, 105.0), &AlertContext::default()));
+
+        let json = serde_json::to_string(&AlertCondition::RsiCrosses { period: 14, level: 70.0, direction: CrossDirection::Above }).unwrap();
+        assert_eq!(json, r#"{"type":"rsi_crosses","period":14,"level":70.0,"direction":"above"}"#);
+    }
+}
@@ -0,0 +1,242 @@
+//! 组合风险分析
+//!
+//! 按各标的共同的时间点对齐收益率，以当前市值权重计算组合波动率、历史 VaR / CVaR、
+//! 最大回撤及各资产的风险贡献 (方差分解，贡献之和为 1)
+
+use crate::errors::{AlphaError, AlphaResult};
+use crate::models::MarketData;
+use crate::prelude::*;
+use crate::simulate::TRADING_DAYS_PER_YEAR;
+use alloc::collections::{BTreeMap, BTreeSet};
+use chrono::{DateTime, Utc};
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+use serde::{Deserialize, Serialize};
+
+/// 交易流水、持仓成本与盈亏估值
+pub mod ledger;
+
+pub use ledger::{holdings, value_holdings, Holding, PortfolioValuation, PriceSnapshot, TradeSide, Transaction};
+
+/// 持仓
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Position {
+    pub symbol: String,
+    /// 持有数量，负数表示空头
+    pub quantity: f64,
+}
+
+/// 组合风险参数
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PortfolioRiskConfig {
+    /// VaR / CVaR 置信度
+    pub confidence: f64,
+    /// 年化使用的每年周期数
+    pub periods_per_year: f64,
+}
+
+impl Default for PortfolioRiskConfig {
+    fn default() -> Self {
+        Self {
+            confidence: 0.95,
+            periods_per_year: TRADING_DAYS_PER_YEAR,
+        }
+    }
+}
+
+/// 单个资产的风险贡献
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AssetContribution {
+    pub symbol: String,
+    pub market_value: f64,
+    /// 市值权重
+    pub weight: f64,
+    /// 年化波动率
+    pub volatility: f64,
+    /// 占组合方差的比例
+    pub risk_contribution: f64,
+}
+
+/// 组合风险指标，VaR / CVaR 为单周期损失比例 (正数)，`*_value` 为对应的金额
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PortfolioRisk {
+    pub total_value: f64,
+    pub volatility: f64,
+    pub value_at_risk: f64,
+    pub value_at_risk_value: f64,
+    pub conditional_var: f64,
+    pub conditional_var_value: f64,
+    pub max_drawdown: f64,
+    /// 参与计算的收益率样本数
+    pub observations: usize,
+    pub contributions: Vec<AssetContribution>,
+}
+
+/// 计算组合风险，`histories` 需包含每个持仓标的的行情
+pub fn analyze_portfolio(
+    positions: &[Position],
+    histories: &BTreeMap<String, Vec<MarketData>>,
+    config: &PortfolioRiskConfig,
+) -> AlphaResult<PortfolioRisk> {
+    if positions.is_empty() {
+        return Err(AlphaError::invalid_input("Portfolio has no positions"));
+    }
+    if !(config.confidence > 0.0 && config.confidence < 1.0) || config.periods_per_year <= 0.0 {
+        return Err(AlphaError::invalid_input("Invalid portfolio risk config"));
+    }
+
+    // 各标的按时间索引的价格
+    let series: Vec<BTreeMap<DateTime<Utc>, f64>> = positions.iter()
+        .map(|position| {
+            let history = histories.get(&position.symbol)
+                .filter(|h| !h.is_empty())
+                .ok_or_else(|| AlphaError::not_found(format!("No price history for {}", position.symbol)))?;
+            Ok(history.iter()
+                .filter(|d| d.price.is_finite() && d.price > 0.0)
+                .map(|d| (d.timestamp, d.price))
+                .collect())
+        })
+        .collect::<AlphaResult<_>>()?;
+
+    let mut common: BTreeSet<DateTime<Utc>> = series[0].keys().copied().collect();
+    for prices in &series[1..] {
+        common.retain(|ts| prices.contains_key(ts));
+    }
+    if common.len() < 3 {
+        return Err(AlphaError::invalid_input("Need at least 3 common timestamps across all histories"));
+    }
+
+    // 按最新价计算市值权重
+    let market_values: Vec<f64> = positions.iter()
+        .zip(&series)
+        .map(|(position, prices)| position.quantity * prices.values().next_back().copied().unwrap_or_default())
+        .collect();
+    let total_value: f64 = market_values.iter().sum();
+    if total_value.abs() < f64::EPSILON {
+        return Err(AlphaError::invalid_input("Portfolio net market value is zero"));
+    }
+    let weights: Vec<f64> = market_values.iter().map(|v| v / total_value).collect();
+
+    let returns: Vec<Vec<f64>> = series.iter()
+        .map(|prices| {
+            let aligned: Vec<f64> = common.iter().map(|ts| prices[ts]).collect();
+            aligned.windows(2).map(|w| w[1] / w[0] - 1.0).collect()
+        })
+        .collect();
+    let observations = returns[0].len();
+
+    let portfolio_returns: Vec<f64> = (0..observations)
+        .map(|t| weights.iter().zip(&returns).map(|(w, r)| w * r[t]).sum())
+        .collect();
+
+    // 协方差矩阵与方差分解
+    let means: Vec<f64> = returns.iter().map(|r| r.iter().sum::<f64>() / observations as f64).collect();
+    let covariance = |i: usize, j: usize| {
+        (0..observations)
+            .map(|t| (returns[i][t] - means[i]) * (returns[j][t] - means[j]))
+            .sum::<f64>() / (observations - 1) as f64
+    };
+    let n = positions.len();
+    let marginal: Vec<f64> = (0..n)
+        .map(|i| (0..n).map(|j| covariance(i, j) * weights[j]).sum())
+        .collect();
+    let variance: f64 = weights.iter().zip(&marginal).map(|(w, m)| w * m).sum();
+    let annualize = config.periods_per_year.sqrt();
+
+    let contributions = positions.iter()
+        .enumerate()
+        .map(|(i, position)| AssetContribution {
+            symbol: position.symbol.clone(),
+            market_value: market_values[i],
+            weight: weights[i],
+            volatility: covariance(i, i).max(0.0).sqrt() * annualize,
+            risk_contribution: if variance > 0.0 { weights[i] * marginal[i] / variance } else { 0.0 },
+        })
+        .collect();
+
+    let (value_at_risk, conditional_var) = historical_var(&portfolio_returns, config.confidence);
+
+    Ok(PortfolioRisk {
+        total_value,
+        volatility: variance.max(0.0).sqrt() * annualize,
+        value_at_risk,
+        value_at_risk_value: value_at_risk * total_value.abs(),
+        conditional_var,
+        conditional_var_value: conditional_var * total_value.abs(),
+        max_drawdown: max_drawdown(&portfolio_returns),
+        observations,
+        contributions,
+    })
+}
+
+/// 历史模拟法 VaR 与 CVaR (尾部平均损失)，损失以正数表示
+fn historical_var(returns: &[f64], confidence: f64) -> (f64, f64) {
+    let mut sorted = returns.to_vec();
+    sorted.sort_by(f64::total_cmp);
+
+    let tail = (((1.0 - confidence) * sorted.len() as f64).floor() as usize).min(sorted.len() - 1);
+    let var = -sorted[tail];
+    let cvar = -sorted[..=tail].iter().sum::<f64>() / (tail + 1) as f64;
+    (var.max(0.0), cvar.max(0.0))
+}
+
+/// 由收益率序列复利得到净值后的最大回撤
+fn max_drawdown(returns: &[f64]) -> f64 {
+    let mut value = 1.0;
+    let mut peak = 1.0;
+    let mut max_drawdown = 0.0_f64;
+    for r in returns {
+        value *= 1.0 + r;
+        peak = f64::max(peak, value);
+        max_drawdown = max_drawdown.max((peak - value) / peak);
+    }
+    max_drawdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn history(symbol: &str, prices: &[f64]) -> Vec<MarketData> {
+        let start = Utc.with_ymd_and_hms(2025, 1, 2, 0, 0, 0).unwrap();
+        prices.iter()
+            .enumerate()
+            .map(|(i, &price)| {
+                let mut data = MarketData::new(symbol.to_string(), price, 1000);
+                data.timestamp = start + Duration::days(i as i64);
+                data
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_portfolio_risk() {
+        let mut histories = BTreeMap::new();
+        histories.insert("AAA".to_string(), history("AAA", &[100.0, 102.0, 99.0, 103.0, 101.0, 104.0]));
+        histories.insert("BBB".to_string(), history("BBB", &[50.0, 50.5, 50.2, 50.8, 50.4, 51.0]));
+        let positions = vec![
+            Position { symbol: "AAA".to_string(), quantity: 10.0 },
+            Position { symbol: "BBB".to_string(), quantity: 20.0 },
+        ];
+
+        let risk = analyze_portfolio(&positions, &histories, &PortfolioRiskConfig::default()).unwrap();
+
+        assert_eq!(risk.total_value, 10.0 * 104.0 + 20.0 * 51.0);
+        assert_eq!(risk.observations, 5);
+        let total: f64 = risk.contributions.iter().map(|c| c.risk_contribution).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        // 高波动资产贡献更多风险
+        assert!(risk.contributions[0].risk_contribution > risk.contributions[1].risk_contribution);
+        assert!(risk.value_at_risk > 0.0 && risk.conditional_var >= risk.value_at_risk);
+        assert!(risk.max_drawdown > 0.0);
+    }
+
+    #[test]
+    fn test_missing_history() {
+        let positions = vec![Position { symbol: "AAA".to_string(), quantity: 1.0 }];
+        let err = analyze_portfolio(&positions, &BTreeMap::new(), &PortfolioRiskConfig::default()).unwrap_err();
+        assert!(matches!(err, AlphaError::DataNotFound(_)));
+    }
+}
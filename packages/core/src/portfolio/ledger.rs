@@ -0,0 +1,312 @@
+//! 交易流水与持仓盈亏
+//!
+//! 按移动加权平均成本法由交易流水推算持仓：加仓摊薄成本，减仓 (含买入平空) 按平均成本结转已实现盈亏，
+//! 手续费计入已实现盈亏
+
+use super::Position;
+use crate::errors::{AlphaError, AlphaResult};
+use crate::prelude::*;
+use alloc::collections::BTreeMap;
+use chrono::{DateTime, Utc};
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 数量绝对值低于此值视为已平仓，避免浮点残差留下零头持仓
+const QUANTITY_EPSILON: f64 = 1e-9;
+
+/// 交易方向
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+/// 成交记录
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Transaction {
+    pub id: Uuid,
+    pub symbol: String,
+    pub side: TradeSide,
+    /// 成交数量，始终为正数
+    pub quantity: f64,
+    pub price: f64,
+    #[serde(default)]
+    pub fee: f64,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl Transaction {
+    #[cfg(feature = "std")]
+    pub fn new(symbol: String, side: TradeSide, quantity: f64, price: f64) -> Self {
+        Self::with_id(Uuid::new_v4(), symbol, side, quantity, price, Utc::now())
+    }
+
+    /// 使用指定 ID 和成交时间构造 (no_std 环境下无系统时钟和随机数)
+    pub fn with_id(id: Uuid, symbol: String, side: TradeSide, quantity: f64, price: f64, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            id,
+            symbol,
+            side,
+            quantity,
+            price,
+            fee: 0.0,
+            timestamp,
+            note: None,
+        }
+    }
+
+    /// 带方向的数量，卖出为负
+    pub fn signed_quantity(&self) -> f64 {
+        match self.side {
+            TradeSide::Buy => self.quantity,
+            TradeSide::Sell => -self.quantity,
+        }
+    }
+
+    pub fn validate(&self) -> AlphaResult<()> {
+        if self.symbol.trim().is_empty() {
+            return Err(AlphaError::invalid_input("Transaction symbol is empty"));
+        }
+        if !(self.quantity.is_finite() && self.quantity > 0.0) {
+            return Err(AlphaError::invalid_input(format!("Invalid quantity for {}: {}", self.symbol, self.quantity)));
+        }
+        if !(self.price.is_finite() && self.price >= 0.0) {
+            return Err(AlphaError::invalid_input(format!("Invalid price for {}: {}", self.symbol, self.price)));
+        }
+        if !(self.fee.is_finite() && self.fee >= 0.0) {
+            return Err(AlphaError::invalid_input(format!("Invalid fee for {}: {}", self.symbol, self.fee)));
+        }
+        Ok(())
+    }
+}
+
+/// 由流水推算的单个标的持仓
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Holding {
+    pub symbol: String,
+    /// 持有数量，负数表示空头，已平仓为 0
+    pub quantity: f64,
+    /// 移动加权平均成本
+    pub average_cost: f64,
+    /// 已实现盈亏 (已扣除手续费)
+    pub realized_pnl: f64,
+    /// 累计手续费
+    pub fees: f64,
+}
+
+impl Holding {
+    fn new(symbol: String) -> Self {
+        Self {
+            symbol,
+            quantity: 0.0,
+            average_cost: 0.0,
+            realized_pnl: 0.0,
+            fees: 0.0,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.quantity != 0.0
+    }
+
+    pub fn position(&self) -> Position {
+        Position {
+            symbol: self.symbol.clone(),
+            quantity: self.quantity,
+        }
+    }
+
+    fn apply(&mut self, transaction: &Transaction) {
+        let delta = transaction.signed_quantity();
+        let price = transaction.price;
+
+        if self.quantity == 0.0 || self.quantity.signum() == delta.signum() {
+            let total = self.quantity.abs() + delta.abs();
+            self.average_cost = (self.quantity.abs() * self.average_cost + delta.abs() * price) / total;
+        } else {
+            let closed = delta.abs().min(self.quantity.abs());
+            self.realized_pnl += closed * (price - self.average_cost) * self.quantity.signum();
+            // 反手时剩余部分按成交价建仓
+            if delta.abs() > self.quantity.abs() {
+                self.average_cost = price;
+            }
+        }
+
+        self.quantity += delta;
+        if self.quantity.abs() < QUANTITY_EPSILON {
+            self.quantity = 0.0;
+            self.average_cost = 0.0;
+        }
+        self.realized_pnl -= transaction.fee;
+        self.fees += transaction.fee;
+    }
+}
+
+/// 按成交时间重放流水得到各标的持仓 (含已平仓标的)，按代码排序
+pub fn holdings(transactions: &[Transaction]) -> AlphaResult<Vec<Holding>> {
+    let mut ordered: Vec<&Transaction> = transactions.iter().collect();
+    ordered.sort_by_key(|t| t.timestamp);
+
+    let mut holdings: BTreeMap<&str, Holding> = BTreeMap::new();
+    for transaction in ordered {
+        transaction.validate()?;
+        holdings.entry(transaction.symbol.as_str())
+            .or_insert_with(|| Holding::new(transaction.symbol.clone()))
+            .apply(transaction);
+    }
+    Ok(holdings.into_values().collect())
+}
+
+/// 估值所需的价格
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct PriceSnapshot {
+    /// 最新价
+    pub price: f64,
+    /// 前收盘价，缺失时当日变动记为 0
+    pub previous_close: Option<f64>,
+}
+
+/// 单个标的的估值与盈亏
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PositionValuation {
+    pub symbol: String,
+    pub quantity: f64,
+    pub average_cost: f64,
+    /// 最新价，已平仓标的为 None
+    pub price: Option<f64>,
+    pub market_value: f64,
+    pub cost_basis: f64,
+    pub unrealized_pnl: f64,
+    pub realized_pnl: f64,
+    /// 相对前收盘的市值变动
+    pub daily_change: f64,
+}
+
+/// 组合估值汇总，百分比以小数表示
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PortfolioValuation {
+    pub positions: Vec<PositionValuation>,
+    pub market_value: f64,
+    pub cost_basis: f64,
+    pub unrealized_pnl: f64,
+    /// 未实现盈亏占成本的比例
+    pub unrealized_pnl_percent: f64,
+    pub realized_pnl: f64,
+    pub daily_change: f64,
+    /// 相对前收盘市值的变动比例
+    pub daily_change_percent: f64,
+}
+
+/// 按最新价估值，`prices` 需包含每个未平仓标的
+pub fn value_holdings(holdings: &[Holding], prices: &BTreeMap<String, PriceSnapshot>) -> AlphaResult<PortfolioValuation> {
+    let positions = holdings.iter()
+        .map(|holding| {
+            if !holding.is_open() {
+                return Ok(PositionValuation {
+                    symbol: holding.symbol.clone(),
+                    quantity: 0.0,
+                    average_cost: 0.0,
+                    price: None,
+                    market_value: 0.0,
+                    cost_basis: 0.0,
+                    unrealized_pnl: 0.0,
+                    realized_pnl: holding.realized_pnl,
+                    daily_change: 0.0,
+                });
+            }
+
+            let snapshot = prices.get(&holding.symbol)
+                .ok_or_else(|| AlphaError::not_found(format!("No price for {}", holding.symbol)))?;
+            let market_value = holding.quantity * snapshot.price;
+            let cost_basis = holding.quantity * holding.average_cost;
+            Ok(PositionValuation {
+                symbol: holding.symbol.clone(),
+                quantity: holding.quantity,
+                average_cost: holding.average_cost,
+                price: Some(snapshot.price),
+                market_value,
+                cost_basis,
+                unrealized_pnl: market_value - cost_basis,
+                realized_pnl: holding.realized_pnl,
+                daily_change: snapshot.previous_close
+                    .map(|close| holding.quantity * (snapshot.price - close))
+                    .unwrap_or(0.0),
+            })
+        })
+        .collect::<AlphaResult<Vec<_>>>()?;
+
+    let sum = |field: fn(&PositionValuation) -> f64| positions.iter().map(field).sum::<f64>();
+    let market_value = sum(|p| p.market_value);
+    let cost_basis = sum(|p| p.cost_basis);
+    let unrealized_pnl = sum(|p| p.unrealized_pnl);
+    let daily_change = sum(|p| p.daily_change);
+    let ratio = |value: f64, base: f64| if base.abs() > f64::EPSILON { value / base.abs() } else { 0.0 };
+
+    Ok(PortfolioValuation {
+        market_value,
+        cost_basis,
+        unrealized_pnl,
+        unrealized_pnl_percent: ratio(unrealized_pnl, cost_basis),
+        realized_pnl: sum(|p| p.realized_pnl),
+        daily_change,
+        daily_change_percent: ratio(daily_change, market_value - daily_change),
+        positions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn trade(day: i64, symbol: &str, side: TradeSide, quantity: f64, price: f64) -> Transaction {
+        let timestamp = Utc.with_ymd_and_hms(2025, 1, 2, 0, 0, 0).unwrap() + Duration::days(day);
+        Transaction::with_id(Uuid::from_u128(day as u128), symbol.to_string(), side, quantity, price, timestamp)
+    }
+
+    #[test]
+    fn test_average_cost_and_realized_pnl() {
+        let mut sell = trade(3, "AAA", TradeSide::Sell, 15.0, 130.0);
+        sell.fee = 5.0;
+        let transactions = vec![
+            sell,
+            trade(1, "AAA", TradeSide::Buy, 10.0, 100.0),
+            trade(2, "AAA", TradeSide::Buy, 10.0, 120.0),
+            // 空头：卖出开仓后部分买入平仓
+            trade(1, "BBB", TradeSide::Sell, 4.0, 50.0),
+            trade(2, "BBB", TradeSide::Buy, 4.0, 40.0),
+        ];
+
+        let holdings = holdings(&transactions).unwrap();
+        assert_eq!(holdings[0].quantity, 5.0);
+        assert_eq!(holdings[0].average_cost, 110.0);
+        assert_eq!(holdings[0].realized_pnl, 15.0 * 20.0 - 5.0);
+        assert!(!holdings[1].is_open());
+        assert_eq!(holdings[1].realized_pnl, 40.0);
+
+        let mut prices = BTreeMap::new();
+        prices.insert("AAA".to_string(), PriceSnapshot { price: 140.0, previous_close: Some(135.0) });
+        let valuation = value_holdings(&holdings, &prices).unwrap();
+        assert_eq!(valuation.market_value, 700.0);
+        assert_eq!(valuation.unrealized_pnl, 150.0);
+        assert_eq!(valuation.realized_pnl, 335.0);
+        assert_eq!(valuation.daily_change, 25.0);
+        assert!((valuation.daily_change_percent - 25.0 / 675.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_invalid_transactions_and_missing_prices() {
+        let err = holdings(&[trade(1, "AAA", TradeSide::Buy, 0.0, 100.0)]).unwrap_err();
+        assert!(matches!(err, AlphaError::InvalidInput(_)));
+
+        let holdings = holdings(&[trade(1, "AAA", TradeSide::Sell, 2.0, 100.0)]).unwrap();
+        assert_eq!(holdings[0].quantity, -2.0);
+        let err = value_holdings(&holdings, &BTreeMap::new()).unwrap_err();
+        assert!(matches!(err, AlphaError::DataNotFound(_)));
+    }
+}
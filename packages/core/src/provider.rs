@@ -0,0 +1,242 @@
+//! 行情数据源抽象
+//!
+//! 桌面端、采集服务与网关通过同一个 [`DataProvider`] 接口获取行情，具体实现 (HTTP 客户端等)
+//! 在启动时注册到 [`ProviderRegistry`]，再按配置顺序组装为带回退的 [`ProviderChain`]
+
+use crate::errors::{AlphaError, AlphaResult};
+use crate::models::MarketData;
+use crate::prelude::*;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use async_trait::async_trait;
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+/// 代码搜索结果
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SymbolMatch {
+    pub symbol: String,
+    pub name: String,
+    pub exchange: Option<String>,
+    /// 数据源给出的品种类型，如 `EQUITY`、`ETF`、`SPOT`
+    pub asset_type: Option<String>,
+}
+
+/// 行情数据源
+#[async_trait]
+pub trait DataProvider: Send + Sync {
+    /// 注册名，如 `yahoo`
+    fn name(&self) -> &str;
+
+    /// 最近 `limit` 根 K 线，按时间升序
+    async fn candles(&self, symbol: &str, timeframe: Duration, limit: usize) -> AlphaResult<Vec<MarketData>>;
+
+    /// 最新行情
+    async fn quote(&self, symbol: &str) -> AlphaResult<MarketData>;
+
+    /// 按代码或名称搜索
+    async fn search(&self, query: &str) -> AlphaResult<Vec<SymbolMatch>>;
+}
+
+/// 单个数据源的配置，`name` 对应注册名
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProviderSettings {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// 覆盖默认接口地址 (代理或测试环境)
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+impl ProviderSettings {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            api_key: None,
+            base_url: None,
+        }
+    }
+
+    /// 非空的 API Key，缺失时返回配置错误
+    pub fn require_api_key(&self) -> AlphaResult<&str> {
+        self.api_key.as_deref()
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| AlphaError::ConfigurationError(format!("Provider {} requires an api_key", self.name)))
+    }
+}
+
+/// 由配置构造数据源
+pub type ProviderFactory = fn(&ProviderSettings) -> AlphaResult<Arc<dyn DataProvider>>;
+
+/// 数据源注册表
+#[derive(Debug, Clone, Default)]
+pub struct ProviderRegistry {
+    factories: BTreeMap<String, ProviderFactory>,
+}
+
+impl ProviderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册数据源，同名数据源会被覆盖
+    pub fn register(&mut self, name: impl Into<String>, factory: ProviderFactory) {
+        self.factories.insert(name.into(), factory);
+    }
+
+    /// 已注册的数据源名称
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(String::as_str)
+    }
+
+    pub fn build(&self, settings: &ProviderSettings) -> AlphaResult<Arc<dyn DataProvider>> {
+        let factory = self.factories.get(&settings.name)
+            .ok_or_else(|| AlphaError::ConfigurationError(format!("Unknown data provider: {}", settings.name)))?;
+        factory(settings)
+    }
+
+    /// 按配置顺序组装回退链
+    pub fn chain(&self, settings: &[ProviderSettings]) -> AlphaResult<ProviderChain> {
+        let providers = settings.iter()
+            .map(|s| self.build(s))
+            .collect::<AlphaResult<Vec<_>>>()?;
+        ProviderChain::new(providers)
+    }
+}
+
+/// 按顺序尝试多个数据源，前一个失败时回退到下一个
+#[derive(Clone)]
+pub struct ProviderChain {
+    providers: Vec<Arc<dyn DataProvider>>,
+}
+
+impl ProviderChain {
+    pub fn new(providers: Vec<Arc<dyn DataProvider>>) -> AlphaResult<Self> {
+        if providers.is_empty() {
+            return Err(AlphaError::ConfigurationError("No data provider configured".to_string()));
+        }
+        Ok(Self { providers })
+    }
+
+    pub fn providers(&self) -> &[Arc<dyn DataProvider>] {
+        &self.providers
+    }
+}
+
+impl core::fmt::Debug for ProviderChain {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.providers.iter().map(|p| p.name())).finish()
+    }
+}
+
+/// 依次调用各数据源，全部失败时返回首个数据源的错误 (通常是主数据源，最能说明问题)
+macro_rules! fallback {
+    ($chain:expr, $what:expr, |$provider:ident| $call:expr) => {{
+        let mut first_error = None;
+        for $provider in &$chain.providers {
+            match $call.await {
+                Ok(value) => return Ok(value),
+                Err(err) => {
+                    first_error.get_or_insert(err.context(format!("{} via {}", $what, $provider.name())));
+                }
+            }
+        }
+        Err(first_error.unwrap_or_else(|| AlphaError::ConfigurationError("No data provider configured".to_string())))
+    }};
+}
+
+#[async_trait]
+impl DataProvider for ProviderChain {
+    fn name(&self) -> &str {
+        "chain"
+    }
+
+    async fn candles(&self, symbol: &str, timeframe: Duration, limit: usize) -> AlphaResult<Vec<MarketData>> {
+        fallback!(self, format!("Fetching candles for {}", symbol), |p| p.candles(symbol, timeframe, limit))
+    }
+
+    async fn quote(&self, symbol: &str) -> AlphaResult<MarketData> {
+        fallback!(self, format!("Fetching quote for {}", symbol), |p| p.quote(symbol))
+    }
+
+    async fn search(&self, query: &str) -> AlphaResult<Vec<SymbolMatch>> {
+        fallback!(self, format!("Searching {}", query), |p| p.search(query))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    struct Fixed {
+        name: &'static str,
+        price: Option<f64>,
+    }
+
+    #[async_trait]
+    impl DataProvider for Fixed {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn candles(&self, symbol: &str, _timeframe: Duration, _limit: usize) -> AlphaResult<Vec<MarketData>> {
+            Ok(vec![self.quote(symbol).await?])
+        }
+
+        async fn quote(&self, symbol: &str) -> AlphaResult<MarketData> {
+            let price = self.price.ok_or_else(|| AlphaError::network(format!("{} is down", self.name)))?;
+            Ok(MarketData {
+                symbol: symbol.to_string(),
+                timestamp: Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap(),
+                price,
+                volume: 0,
+                bid: None,
+                ask: None,
+                open: None,
+                high: None,
+                low: None,
+            })
+        }
+
+        async fn search(&self, _query: &str) -> AlphaResult<Vec<SymbolMatch>> {
+            Err(AlphaError::not_found("search not supported"))
+        }
+    }
+
+    fn registry() -> ProviderRegistry {
+        let mut registry = ProviderRegistry::new();
+        registry.register("down", |_| Ok(Arc::new(Fixed { name: "down", price: None })));
+        registry.register("up", |_| Ok(Arc::new(Fixed { name: "up", price: Some(10.0) })));
+        registry.register("keyed", |settings| {
+            settings.require_api_key()?;
+            Ok(Arc::new(Fixed { name: "keyed", price: Some(20.0) }))
+        });
+        registry
+    }
+
+    #[test]
+    fn test_chain_falls_back() {
+        let chain = registry().chain(&[ProviderSettings::new("down"), ProviderSettings::new("up")]).unwrap();
+        assert_eq!(tokio_test::block_on(chain.quote("AAPL")).unwrap().price, 10.0);
+
+        // 全部失败时保留主数据源的错误类别
+        let err = tokio_test::block_on(chain.search("AAPL")).unwrap_err();
+        assert!(matches!(err.root(), AlphaError::DataNotFound(_)));
+        assert!(err.to_string().contains("via down"));
+    }
+
+    #[test]
+    fn test_registry_config_errors() {
+        let registry = registry();
+        assert!(matches!(registry.chain(&[]), Err(AlphaError::ConfigurationError(_))));
+        assert!(matches!(registry.build(&ProviderSettings::new("missing")), Err(AlphaError::ConfigurationError(_))));
+        assert!(registry.build(&ProviderSettings::new("keyed")).is_err());
+
+        let settings = ProviderSettings { api_key: Some("secret".into()), ..ProviderSettings::new("keyed") };
+        assert_eq!(registry.build(&settings).unwrap().name(), "keyed");
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec!["down", "keyed", "up"]);
+    }
+}
@@ -0,0 +1,574 @@
+//! 多标的条件选股
+//!
+//! 条件以表达式描述，如 `RSI(14) < 30 AND price > SMA(200)`，在每个标的的最新一根数据上求值。
+//! 指标通过 [`IndicatorRegistry`] 按名称调用，括号内的参数按注册顺序对应，
+//! 多输出指标用 `.列名` 选择输出 (如 `MACD(12,26,9).histogram`，缺省取第一列)
+
+use crate::errors::{AlphaError, AlphaResult};
+use crate::indicators::registry::{IndicatorInput, IndicatorParams, IndicatorRegistry};
+use crate::indicators::TechnicalIndicators;
+use crate::models::MarketData;
+use crate::prelude::*;
+use alloc::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+
+/// 选股条件
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScreenCriteria {
+    /// 条件表达式，支持 `AND` / `OR` / `NOT`、括号及 `<` `<=` `>` `>=` `==` `!=`
+    pub condition: String,
+    /// 排序依据的操作数 (如 `RSI(14)`)；缺省时按各比较条件超出阈值的相对幅度之和排序
+    #[serde(default)]
+    pub rank_by: Option<String>,
+    /// 按得分升序排列 (默认降序)
+    #[serde(default)]
+    pub ascending: bool,
+    /// 最多返回的标的数量
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl ScreenCriteria {
+    pub fn new(condition: impl Into<String>) -> Self {
+        Self {
+            condition: condition.into(),
+            rank_by: None,
+            ascending: false,
+            limit: None,
+        }
+    }
+}
+
+/// 命中的标的
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScreenMatch {
+    pub symbol: String,
+    pub score: f64,
+    /// 条件中各操作数的最新值，键为规范化后的表达式 (如 `rsi(14)`)
+    pub values: BTreeMap<String, f64>,
+}
+
+/// 选股结果
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScreenResult {
+    pub matches: Vec<ScreenMatch>,
+    /// 参与扫描的标的数量
+    pub scanned: usize,
+    /// 数据不足或计算失败而未参与比较的标的
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Price,
+    Open,
+    High,
+    Low,
+    Volume,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Number(f64),
+    Field(Field),
+    Indicator {
+        name: String,
+        args: Vec<f64>,
+        output: Option<String>,
+    },
+}
+
+impl Operand {
+    fn label(&self) -> String {
+        match self {
+            Operand::Number(v) => format!("{}", v),
+            Operand::Field(field) => match field {
+                Field::Price => "price",
+                Field::Open => "open",
+                Field::High => "high",
+                Field::Low => "low",
+                Field::Volume => "volume",
+            }
+            .to_string(),
+            Operand::Indicator { name, args, output } => {
+                let args: Vec<String> = args.iter().map(|a| format!("{}", a)).collect();
+                match output {
+                    Some(output) => format!("{}({}).{}", name, args.join(","), output),
+                    None => format!("{}({})", name, args.join(",")),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl CompareOp {
+    fn apply(self, left: f64, right: f64) -> bool {
+        match self {
+            CompareOp::Lt => left < right,
+            CompareOp::Le => left <= right,
+            CompareOp::Gt => left > right,
+            CompareOp::Ge => left >= right,
+            CompareOp::Eq => (left - right).abs() < f64::EPSILON,
+            CompareOp::Ne => (left - right).abs() >= f64::EPSILON,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Compare(Operand, CompareOp, Operand),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+impl Expr {
+    fn operands<'a>(&'a self, out: &mut Vec<&'a Operand>) {
+        match self {
+            Expr::Compare(left, _, right) => {
+                out.push(left);
+                out.push(right);
+            }
+            Expr::And(a, b) | Expr::Or(a, b) => {
+                a.operands(out);
+                b.operands(out);
+            }
+            Expr::Not(inner) => inner.operands(out),
+        }
+    }
+
+    /// 求值，同时累计成立的比较条件的相对幅度
+    fn eval(&self, values: &BTreeMap<String, f64>, margin: &mut f64) -> bool {
+        match self {
+            Expr::Compare(left, op, right) => {
+                let (l, r) = (values[&left.label()], values[&right.label()]);
+                let holds = op.apply(l, r);
+                if holds {
+                    *margin += (l - r).abs() / r.abs().max(f64::EPSILON);
+                }
+                holds
+            }
+            // 两侧都求值以便得分包含全部成立的条件
+            Expr::And(a, b) => a.eval(values, margin) & b.eval(values, margin),
+            Expr::Or(a, b) => a.eval(values, margin) | b.eval(values, margin),
+            Expr::Not(inner) => !inner.eval(values, &mut 0.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    LParen,
+    RParen,
+    Comma,
+    Dot,
+    Op(CompareOp),
+}
+
+fn tokenize(input: &str) -> AlphaResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = input.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' => { tokens.push(Token::LParen); i += 1; }
+            ')' => { tokens.push(Token::RParen); i += 1; }
+            ',' => { tokens.push(Token::Comma); i += 1; }
+            '.' if !chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()) => { tokens.push(Token::Dot); i += 1; }
+            '<' | '>' | '=' | '!' => {
+                let double = chars.get(i + 1) == Some(&'=');
+                let op = match (c, double) {
+                    ('<', false) => CompareOp::Lt,
+                    ('<', true) => CompareOp::Le,
+                    ('>', false) => CompareOp::Gt,
+                    ('>', true) => CompareOp::Ge,
+                    ('=', true) => CompareOp::Eq,
+                    ('!', true) => CompareOp::Ne,
+                    _ => return Err(AlphaError::invalid_input(format!("Unexpected '{}' at {}", c, i))),
+                };
+                tokens.push(Token::Op(op));
+                i += if double { 2 } else { 1 };
+            }
+            c if c.is_ascii_digit() || c == '.' || c == '-' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let value = text.parse::<f64>()
+                    .map_err(|_| AlphaError::invalid_input(format!("Invalid number: {}", text)))?;
+                tokens.push(Token::Number(value));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            _ => return Err(AlphaError::invalid_input(format!("Unexpected '{}' at {}", c, i))),
+        }
+    }
+    Ok(tokens)
+}
+
+/// 递归下降解析：or := and (OR and)*，and := unary (AND unary)*，unary := NOT unary | '(' or ')' | 比较
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    registry: &'a IndicatorRegistry,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn keyword(&mut self, word: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(id)) if id.eq_ignore_ascii_case(word)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> AlphaResult<()> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            other => Err(AlphaError::invalid_input(format!("Expected {:?}, found {:?}", expected, other))),
+        }
+    }
+
+    fn or(&mut self) -> AlphaResult<Expr> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> AlphaResult<Expr> {
+        let mut expr = self.unary()?;
+        while self.keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> AlphaResult<Expr> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let expr = self.or()?;
+            self.expect(Token::RParen)?;
+            return Ok(expr);
+        }
+
+        let left = self.operand()?;
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            other => return Err(AlphaError::invalid_input(format!("Expected comparison operator, found {:?}", other))),
+        };
+        let right = self.operand()?;
+        Ok(Expr::Compare(left, op, right))
+    }
+
+    fn operand(&mut self) -> AlphaResult<Operand> {
+        let ident = match self.next() {
+            Some(Token::Number(value)) => return Ok(Operand::Number(value)),
+            Some(Token::Ident(ident)) => ident.to_ascii_lowercase(),
+            other => return Err(AlphaError::invalid_input(format!("Expected operand, found {:?}", other))),
+        };
+
+        let field = match ident.as_str() {
+            "price" | "close" => Some(Field::Price),
+            "open" => Some(Field::Open),
+            "high" => Some(Field::High),
+            "low" => Some(Field::Low),
+            "volume" => Some(Field::Volume),
+            _ => None,
+        };
+        if let Some(field) = field {
+            if self.peek() != Some(&Token::LParen) {
+                return Ok(Operand::Field(field));
+            }
+        }
+
+        let spec = self.registry.get(&ident)
+            .ok_or_else(|| AlphaError::not_found(format!("Unknown indicator: {}", ident)))?;
+
+        let mut args = Vec::new();
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            while self.peek() != Some(&Token::RParen) {
+                if !args.is_empty() {
+                    self.expect(Token::Comma)?;
+                }
+                match self.next() {
+                    Some(Token::Number(value)) => args.push(value),
+                    other => return Err(AlphaError::invalid_input(format!("Expected number argument, found {:?}", other))),
+                }
+            }
+            self.pos += 1;
+        }
+        if args.len() > spec.params.len() {
+            return Err(AlphaError::invalid_input(format!(
+                "{} takes at most {} arguments", spec.name, spec.params.len()
+            )));
+        }
+
+        let output = if self.peek() == Some(&Token::Dot) {
+            self.pos += 1;
+            match self.next() {
+                Some(Token::Ident(output)) if spec.outputs.contains(&output.to_ascii_lowercase().as_str()) => {
+                    Some(output.to_ascii_lowercase())
+                }
+                other => return Err(AlphaError::invalid_input(format!("Unknown output of {}: {:?}", spec.name, other))),
+            }
+        } else {
+            None
+        };
+
+        Ok(Operand::Indicator { name: spec.name.to_string(), args, output })
+    }
+}
+
+/// 已解析的选股条件，可对多个标的重复求值
+#[derive(Debug, Clone)]
+pub struct Screener<'a> {
+    registry: &'a IndicatorRegistry,
+    indicators: &'a TechnicalIndicators,
+    expr: Expr,
+    rank_by: Option<Operand>,
+    criteria: ScreenCriteria,
+}
+
+impl<'a> Screener<'a> {
+    pub fn new(registry: &'a IndicatorRegistry, indicators: &'a TechnicalIndicators, criteria: ScreenCriteria) -> AlphaResult<Self> {
+        let parse = |text: &str| -> AlphaResult<Parser<'a>> {
+            Ok(Parser { tokens: tokenize(text)?, pos: 0, registry })
+        };
+
+        let mut parser = parse(&criteria.condition)?;
+        let expr = parser.or()?;
+        if parser.pos < parser.tokens.len() {
+            return Err(AlphaError::invalid_input(format!("Unexpected trailing input in condition: {:?}", parser.tokens[parser.pos])));
+        }
+
+        let rank_by = match &criteria.rank_by {
+            Some(text) => {
+                let mut parser = parse(text)?;
+                let operand = parser.operand()?;
+                if parser.pos < parser.tokens.len() {
+                    return Err(AlphaError::invalid_input(format!("Invalid rank_by: {}", text)));
+                }
+                Some(operand)
+            }
+            None => None,
+        };
+
+        Ok(Self { registry, indicators, expr, rank_by, criteria })
+    }
+
+    /// 扫描全部标的，返回按得分排序的命中结果
+    pub fn scan<'d>(&self, universe: impl IntoIterator<Item = (&'d String, &'d Vec<MarketData>)>) -> ScreenResult {
+        let mut matches = Vec::new();
+        let mut skipped = Vec::new();
+        let mut scanned = 0;
+
+        for (symbol, data) in universe {
+            scanned += 1;
+            match self.evaluate(data) {
+                Ok(Some((score, values))) => matches.push(ScreenMatch { symbol: symbol.clone(), score, values }),
+                Ok(None) => {}
+                Err(_) => skipped.push(symbol.clone()),
+            }
+        }
+
+        matches.sort_by(|a, b| {
+            let order = a.score.partial_cmp(&b.score).unwrap_or(core::cmp::Ordering::Equal);
+            if self.criteria.ascending { order } else { order.reverse() }
+        });
+        if let Some(limit) = self.criteria.limit {
+            matches.truncate(limit);
+        }
+
+        ScreenResult { matches, scanned, skipped }
+    }
+
+    /// 对单个标的求值，命中时返回 (得分, 操作数取值)
+    pub fn evaluate(&self, data: &[MarketData]) -> AlphaResult<Option<(f64, BTreeMap<String, f64>)>> {
+        let last = data.last().ok_or_else(|| AlphaError::invalid_input("No market data"))?;
+
+        let mut operands = Vec::new();
+        self.expr.operands(&mut operands);
+        operands.extend(self.rank_by.as_ref());
+
+        let close: Vec<f64> = data.iter().map(|d| d.price).collect();
+        let high: Vec<f64> = data.iter().map(|d| d.high.unwrap_or(d.price)).collect();
+        let low: Vec<f64> = data.iter().map(|d| d.low.unwrap_or(d.price)).collect();
+        let volume: Vec<f64> = data.iter().map(|d| d.volume as f64).collect();
+        let input = IndicatorInput { high: Some(&high), low: Some(&low), close: &close, volume: Some(&volume) };
+
+        let mut values = BTreeMap::new();
+        for operand in operands {
+            let label = operand.label();
+            if values.contains_key(&label) {
+                continue;
+            }
+            let value = match operand {
+                Operand::Number(v) => *v,
+                Operand::Field(Field::Price) => last.price,
+                Operand::Field(Field::Open) => last.open.unwrap_or(last.price),
+                Operand::Field(Field::High) => last.high.unwrap_or(last.price),
+                Operand::Field(Field::Low) => last.low.unwrap_or(last.price),
+                Operand::Field(Field::Volume) => last.volume as f64,
+                Operand::Indicator { name, args, output } => self.latest_indicator(name, args, output.as_deref(), &input)?,
+            };
+            values.insert(label, value);
+        }
+
+        let mut margin = 0.0;
+        if !self.expr.eval(&values, &mut margin) {
+            return Ok(None);
+        }
+        let score = match &self.rank_by {
+            Some(operand) => values[&operand.label()],
+            None => margin,
+        };
+        values.retain(|key, _| key.parse::<f64>().is_err());
+        Ok(Some((score, values)))
+    }
+
+    /// 指标最新值；数据长度不足最大周期参数时视为数据不足
+    fn latest_indicator(&self, name: &str, args: &[f64], output: Option<&str>, input: &IndicatorInput) -> AlphaResult<f64> {
+        let spec = self.registry.get(name)
+            .ok_or_else(|| AlphaError::not_found(format!("Unknown indicator: {}", name)))?;
+
+        let mut params = IndicatorParams::new();
+        for ((param, _), &value) in spec.params.iter().zip(args) {
+            params.insert(param.to_string(), value);
+        }
+        let warmup = spec.params.iter()
+            .map(|(param, default)| params.get(*param).copied().unwrap_or(*default))
+            .fold(0.0, f64::max);
+        if (input.close.len() as f64) < warmup {
+            return Err(AlphaError::invalid_input(format!("Not enough data for {}", name)));
+        }
+
+        let columns = self.registry.calculate(self.indicators, name, input, &params)?;
+        let (_, series) = match output {
+            Some(output) => columns.iter().find(|(column, _)| column == output),
+            None => columns.first(),
+        }
+        .ok_or_else(|| AlphaError::internal(format!("{} produced no output", name)))?;
+
+        series.last()
+            .copied()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| AlphaError::CalculationError(format!("{} has no value", name)))
+    }
+}
+
+/// 解析条件并扫描全部标的
+pub fn screen(
+    registry: &IndicatorRegistry,
+    indicators: &TechnicalIndicators,
+    universe: &BTreeMap<String, Vec<MarketData>>,
+    criteria: &ScreenCriteria,
+) -> AlphaResult<ScreenResult> {
+    Ok(Screener::new(registry, indicators, criteria.clone())?.scan(universe))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+
+    fn series(symbol: &str, prices: impl Iterator<Item = f64>) -> Vec<MarketData> {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        prices.enumerate()
+            .map(|(i, price)| MarketData {
+                symbol: symbol.to_string(),
+                timestamp: start + Duration::days(i as i64),
+                price,
+                volume: 1000,
+                bid: None,
+                ask: None,
+                open: None,
+                high: None,
+                low: None,
+            })
+            .collect()
+    }
+
+    fn universe() -> BTreeMap<String, Vec<MarketData>> {
+        let mut universe = BTreeMap::new();
+        // 长期上涨后急跌：价格仍在长均线上方且 RSI 超卖
+        let dip = (0..60).map(|i| if i < 50 { 100.0 + i as f64 } else { 150.0 - (i - 49) as f64 * 2.0 });
+        universe.insert("DIP".to_string(), series("DIP", dip));
+        universe.insert("UP".to_string(), series("UP", (0..60).map(|i| 100.0 + i as f64)));
+        universe.insert("DOWN".to_string(), series("DOWN", (0..60).map(|i| 200.0 - i as f64)));
+        universe.insert("SHORT".to_string(), series("SHORT", (0..5).map(|i| 10.0 + i as f64)));
+        universe
+    }
+
+    #[test]
+    fn test_screen_conditions() {
+        let registry = IndicatorRegistry::new();
+        let indicators = TechnicalIndicators::new();
+        let universe = universe();
+
+        let criteria = ScreenCriteria::new("RSI(14) < 35 AND price > SMA(60)");
+        let result = screen(&registry, &indicators, &universe, &criteria).unwrap();
+        assert_eq!(result.scanned, 4);
+        assert_eq!(result.skipped, vec!["SHORT".to_string()]);
+        let symbols: Vec<&str> = result.matches.iter().map(|m| m.symbol.as_str()).collect();
+        assert_eq!(symbols, vec!["DIP"]);
+        assert!(result.matches[0].values.contains_key("rsi(14)"));
+
+        let criteria = ScreenCriteria {
+            rank_by: Some("rsi(14)".into()),
+            ascending: true,
+            ..ScreenCriteria::new("NOT (price < sma(20)) OR rsi(14) <= 35")
+        };
+        let result = screen(&registry, &indicators, &universe, &criteria).unwrap();
+        let symbols: Vec<&str> = result.matches.iter().map(|m| m.symbol.as_str()).collect();
+        assert_eq!(symbols, vec!["DOWN", "DIP", "UP"]);
+    }
+
+    #[test]
+    fn test_rejects_invalid_conditions() {
+        let registry = IndicatorRegistry::new();
+        let indicators = TechnicalIndicators::new();
+        for condition in ["RSI(14) <", "FOO(3) > 1", "price > 1 extra", "MACD.bogus > 0", "SMA(1,2) > 0", "(price > 1"] {
+            assert!(Screener::new(&registry, &indicators, ScreenCriteria::new(condition)).is_err(), "{}", condition);
+        }
+        assert!(Screener::new(&registry, &indicators, ScreenCriteria::new("macd(12,26,9).histogram >= -0.5")).is_ok());
+    }
+}
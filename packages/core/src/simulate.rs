@@ -0,0 +1,451 @@
+//! 合成行情数据生成模块
+//!
+//! 提供可复现 (固定种子) 的价格过程模拟，供演示、测试和蒙特卡洛模拟共用
+
+use crate::errors::{AlphaError, AlphaResult};
+use crate::models::MarketData;
+use crate::prelude::*;
+use chrono::{DateTime, Duration, Utc};
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+use serde::{Deserialize, Serialize};
+
+/// 一年的交易日数，用于把日频步长换算为年化参数
+pub const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+
+/// 确定性伪随机数生成器 (xoshiro256**，SplitMix64 播种)
+///
+/// 不依赖系统熵源，相同种子在所有平台上产生相同序列
+#[derive(Debug, Clone)]
+pub struct SimRng {
+    state: [u64; 4],
+    spare_normal: Option<f64>,
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        let mut sm = seed;
+        let mut next = || {
+            sm = sm.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = sm;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        };
+
+        Self {
+            state: [next(), next(), next(), next()],
+            spare_normal: None,
+        }
+    }
+
+    /// 下一个 64 位随机整数
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+
+        result
+    }
+
+    /// `[0, 1)` 区间均匀分布
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// `[low, high)` 区间均匀分布
+    pub fn uniform(&mut self, low: f64, high: f64) -> f64 {
+        low + (high - low) * self.next_f64()
+    }
+
+    /// 标准正态分布 (Box-Muller 变换，成对生成并缓存第二个值)
+    pub fn standard_normal(&mut self) -> f64 {
+        if let Some(z) = self.spare_normal.take() {
+            return z;
+        }
+
+        // 1 - u 取值 (0, 1]，避免 ln(0)
+        let u1 = 1.0 - self.next_f64();
+        let u2 = self.next_f64();
+        let radius = (-2.0 * u1.ln()).sqrt();
+        let theta = 2.0 * core::f64::consts::PI * u2;
+
+        self.spare_normal = Some(radius * theta.sin());
+        radius * theta.cos()
+    }
+
+    /// 正态分布
+    pub fn normal(&mut self, mean: f64, std_dev: f64) -> f64 {
+        mean + std_dev * self.standard_normal()
+    }
+
+    /// 泊松分布 (Knuth 算法，适用于单步跳跃次数这类小均值场景)
+    pub fn poisson(&mut self, lambda: f64) -> u32 {
+        if lambda <= 0.0 {
+            return 0;
+        }
+
+        let limit = (-lambda).exp();
+        let mut count = 0;
+        let mut product = self.next_f64();
+        while product > limit {
+            count += 1;
+            product *= self.next_f64();
+        }
+        count
+    }
+}
+
+/// 价格随机过程 (参数均为年化)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PriceProcess {
+    /// 几何布朗运动
+    Gbm {
+        /// 漂移率
+        drift: f64,
+        /// 波动率
+        volatility: f64,
+    },
+    /// Ornstein-Uhlenbeck 均值回归过程 (作用于价格本身)
+    OrnsteinUhlenbeck {
+        /// 长期均值
+        mean: f64,
+        /// 回归速度
+        reversion: f64,
+        /// 波动率 (价格单位)
+        volatility: f64,
+    },
+    /// Merton 跳跃扩散过程：几何布朗运动叠加对数正态跳跃
+    JumpDiffusion {
+        /// 漂移率
+        drift: f64,
+        /// 波动率
+        volatility: f64,
+        /// 每年平均跳跃次数
+        jump_intensity: f64,
+        /// 对数跳跃幅度均值
+        jump_mean: f64,
+        /// 对数跳跃幅度标准差
+        jump_std: f64,
+    },
+}
+
+impl PriceProcess {
+    /// 校验参数合法性
+    pub fn validate(&self) -> AlphaResult<()> {
+        let valid = match *self {
+            PriceProcess::Gbm { drift, volatility } => drift.is_finite() && volatility.is_finite() && volatility >= 0.0,
+            PriceProcess::OrnsteinUhlenbeck { mean, reversion, volatility } => {
+                mean.is_finite() && reversion.is_finite() && reversion >= 0.0 && volatility.is_finite() && volatility >= 0.0
+            }
+            PriceProcess::JumpDiffusion { drift, volatility, jump_intensity, jump_mean, jump_std } => {
+                drift.is_finite()
+                    && volatility.is_finite() && volatility >= 0.0
+                    && jump_intensity.is_finite() && jump_intensity >= 0.0
+                    && jump_mean.is_finite()
+                    && jump_std.is_finite() && jump_std >= 0.0
+            }
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(AlphaError::invalid_input(format!("Invalid price process parameters: {:?}", self)))
+        }
+    }
+
+    /// 从 `price` 出发推进一个时间步 `dt` (年)
+    pub fn step(&self, price: f64, dt: f64, rng: &mut SimRng) -> f64 {
+        match *self {
+            PriceProcess::Gbm { drift, volatility } => {
+                let z = rng.standard_normal();
+                price * ((drift - 0.5 * volatility * volatility) * dt + volatility * dt.sqrt() * z).exp()
+            }
+            PriceProcess::OrnsteinUhlenbeck { mean, reversion, volatility } => {
+                // 精确离散化，避免回归速度较大时 Euler 格式发散
+                let decay = (-reversion * dt).exp();
+                let std_dev = if reversion > 0.0 {
+                    volatility * ((1.0 - decay * decay) / (2.0 * reversion)).sqrt()
+                } else {
+                    volatility * dt.sqrt()
+                };
+                mean + (price - mean) * decay + std_dev * rng.standard_normal()
+            }
+            PriceProcess::JumpDiffusion { drift, volatility, jump_intensity, jump_mean, jump_std } => {
+                // 漂移补偿跳跃的期望贡献，使整体期望收益率仍为 drift
+                let kappa = (jump_mean + 0.5 * jump_std * jump_std).exp() - 1.0;
+                let diffusion = (drift - jump_intensity * kappa - 0.5 * volatility * volatility) * dt
+                    + volatility * dt.sqrt() * rng.standard_normal();
+
+                let jumps = rng.poisson(jump_intensity * dt);
+                let jump: f64 = (0..jumps).map(|_| rng.normal(jump_mean, jump_std)).sum();
+
+                price * (diffusion + jump).exp()
+            }
+        }
+    }
+}
+
+/// 模拟配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SimulationConfig {
+    /// 价格过程
+    pub process: PriceProcess,
+    /// 初始价格
+    pub initial_price: f64,
+    /// 每步时长 (年)，默认一个交易日
+    pub dt: f64,
+    /// 随机种子
+    pub seed: u64,
+    /// 价格下限，防止均值回归等过程产生非正价格
+    pub min_price: f64,
+}
+
+impl SimulationConfig {
+    pub fn new(process: PriceProcess, initial_price: f64, seed: u64) -> Self {
+        Self {
+            process,
+            initial_price,
+            dt: 1.0 / TRADING_DAYS_PER_YEAR,
+            seed,
+            min_price: 0.01,
+        }
+    }
+
+    /// 设置步长 (年)
+    pub fn with_dt(mut self, dt: f64) -> Self {
+        self.dt = dt;
+        self
+    }
+
+    /// 设置价格下限
+    pub fn with_min_price(mut self, min_price: f64) -> Self {
+        self.min_price = min_price;
+        self
+    }
+
+    pub fn validate(&self) -> AlphaResult<()> {
+        self.process.validate()?;
+
+        if !(self.initial_price.is_finite() && self.initial_price > 0.0) {
+            return Err(AlphaError::invalid_input("Initial price must be positive"));
+        }
+        if !(self.dt.is_finite() && self.dt > 0.0) {
+            return Err(AlphaError::invalid_input("Time step must be positive"));
+        }
+        if !(self.min_price.is_finite() && self.min_price >= 0.0) {
+            return Err(AlphaError::invalid_input("Minimum price cannot be negative"));
+        }
+
+        Ok(())
+    }
+}
+
+/// 行情模拟器
+#[derive(Debug, Clone)]
+pub struct MarketSimulator {
+    config: SimulationConfig,
+    rng: SimRng,
+    price: f64,
+}
+
+impl MarketSimulator {
+    pub fn new(config: SimulationConfig) -> AlphaResult<Self> {
+        config.validate()?;
+
+        Ok(Self {
+            rng: SimRng::new(config.seed),
+            price: config.initial_price,
+            config,
+        })
+    }
+
+    /// 几何布朗运动模拟器
+    pub fn gbm(initial_price: f64, drift: f64, volatility: f64, seed: u64) -> AlphaResult<Self> {
+        Self::new(SimulationConfig::new(PriceProcess::Gbm { drift, volatility }, initial_price, seed))
+    }
+
+    pub fn config(&self) -> &SimulationConfig {
+        &self.config
+    }
+
+    /// 当前价格
+    pub fn price(&self) -> f64 {
+        self.price
+    }
+
+    /// 恢复到初始价格和初始种子，之后会重新产生同一条路径
+    pub fn reset(&mut self) {
+        self.rng = SimRng::new(self.config.seed);
+        self.price = self.config.initial_price;
+    }
+
+    /// 推进一步并返回新价格
+    pub fn next_price(&mut self) -> f64 {
+        self.price = self.config.process
+            .step(self.price, self.config.dt, &mut self.rng)
+            .max(self.config.min_price);
+        self.price
+    }
+
+    /// 生成 `steps` 个后续价格 (不含初始价格)
+    pub fn generate_prices(&mut self, steps: usize) -> Vec<f64> {
+        (0..steps).map(|_| self.next_price()).collect()
+    }
+
+    /// 生成 `paths` 条独立路径，每条路径使用由主种子派生的子种子
+    pub fn generate_paths(config: &SimulationConfig, paths: usize, steps: usize) -> AlphaResult<Vec<Vec<f64>>> {
+        config.validate()?;
+
+        let mut seeder = SimRng::new(config.seed);
+        (0..paths)
+            .map(|_| {
+                let path_config = SimulationConfig { seed: seeder.next_u64(), ..config.clone() };
+                Ok(MarketSimulator::new(path_config)?.generate_prices(steps))
+            })
+            .collect()
+    }
+
+    /// 生成一笔行情快照，带买卖价和成交量
+    pub fn next_tick(&mut self, symbol: &str, timestamp: DateTime<Utc>) -> MarketData {
+        let price = self.next_price();
+        let spread = price * 0.0001;
+
+        MarketData {
+            symbol: symbol.to_string(),
+            timestamp,
+            price,
+            volume: self.next_volume(),
+            bid: Some(price - spread),
+            ask: Some(price + spread),
+            open: None,
+            high: None,
+            low: None,
+        }
+    }
+
+    /// 从 `start` 开始按 `interval` 间隔生成 `count` 根 K 线
+    ///
+    /// 每根 K 线内部细分为若干子步模拟，开盘价为上一根收盘价，最高/最低价取自子步路径
+    pub fn generate_bars(
+        &mut self,
+        symbol: &str,
+        start: DateTime<Utc>,
+        interval: Duration,
+        count: usize,
+    ) -> Vec<MarketData> {
+        const SUB_STEPS: usize = 8;
+
+        let dt = self.config.dt;
+        let sub_dt = dt / SUB_STEPS as f64;
+        let mut bars = Vec::with_capacity(count);
+
+        for i in 0..count {
+            let open = self.price;
+            let (mut high, mut low) = (open, open);
+
+            self.config.dt = sub_dt;
+            for _ in 0..SUB_STEPS {
+                let price = self.next_price();
+                high = high.max(price);
+                low = low.min(price);
+            }
+            self.config.dt = dt;
+
+            bars.push(MarketData::with_ohlcv(
+                symbol.to_string(),
+                start + interval * i as i32,
+                open,
+                high,
+                low,
+                self.price,
+                self.next_volume(),
+            ));
+        }
+
+        bars
+    }
+
+    /// 对数正态分布的成交量，中位数约 10,000
+    fn next_volume(&mut self) -> u64 {
+        (10_000.0 * self.rng.normal(0.0, 0.5).exp()) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_is_deterministic() {
+        let mut a = MarketSimulator::gbm(100.0, 0.05, 0.2, 42).unwrap();
+        let mut b = MarketSimulator::gbm(100.0, 0.05, 0.2, 42).unwrap();
+        assert_eq!(a.generate_prices(100), b.generate_prices(100));
+
+        let mut c = MarketSimulator::gbm(100.0, 0.05, 0.2, 43).unwrap();
+        a.reset();
+        assert_ne!(a.generate_prices(100), c.generate_prices(100));
+    }
+
+    #[test]
+    fn test_normal_moments() {
+        let mut rng = SimRng::new(7);
+        let samples: Vec<f64> = (0..20_000).map(|_| rng.standard_normal()).collect();
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        let var = samples.iter().map(|z| (z - mean).powi(2)).sum::<f64>() / samples.len() as f64;
+
+        assert!(mean.abs() < 0.05);
+        assert!((var - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_ou_reverts_to_mean() {
+        let process = PriceProcess::OrnsteinUhlenbeck { mean: 50.0, reversion: 20.0, volatility: 1.0 };
+        let mut sim = MarketSimulator::new(SimulationConfig::new(process, 100.0, 1)).unwrap();
+        let prices = sim.generate_prices(500);
+        let tail = &prices[400..];
+
+        assert!((tail.iter().sum::<f64>() / tail.len() as f64 - 50.0).abs() < 2.0);
+    }
+
+    #[test]
+    fn test_jump_diffusion_and_bars() {
+        let process = PriceProcess::JumpDiffusion {
+            drift: 0.05,
+            volatility: 0.2,
+            jump_intensity: 10.0,
+            jump_mean: -0.05,
+            jump_std: 0.1,
+        };
+        let mut sim = MarketSimulator::new(SimulationConfig::new(process, 100.0, 9)).unwrap();
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let bars = sim.generate_bars("SIM", start, Duration::days(1), 50);
+
+        assert_eq!(bars.len(), 50);
+        assert_eq!(bars[1].timestamp - bars[0].timestamp, Duration::days(1));
+        assert_eq!(bars[1].open, Some(bars[0].price));
+        assert!(bars.iter().all(|b| b.low.unwrap() <= b.price && b.price <= b.high.unwrap()));
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(MarketSimulator::gbm(-1.0, 0.05, 0.2, 1).is_err());
+        assert!(MarketSimulator::gbm(100.0, 0.05, -0.2, 1).is_err());
+
+        let paths = MarketSimulator::generate_paths(
+            &SimulationConfig::new(PriceProcess::Gbm { drift: 0.0, volatility: 0.3 }, 10.0, 5),
+            3,
+            20,
+        ).unwrap();
+        assert_eq!(paths.len(), 3);
+        assert_ne!(paths[0], paths[1]);
+    }
+}
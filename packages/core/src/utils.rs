@@ -1,47 +1,35 @@
 //! 工具函数模块
 
-use crate::errors::AlphaResult;
-use chrono::{DateTime, Utc, Duration};
+use crate::errors::{AlphaError, AlphaResult};
+use crate::prelude::*;
 
 /// 时间工具函数
-pub mod time {
-    use super::*;
-
-    /// 获取当前时间戳 (毫秒)
-    pub fn current_timestamp_ms() -> i64 {
-        Utc::now().timestamp_millis()
-    }
+pub mod time;
 
-    /// 时间戳转换为 DateTime
-    pub fn timestamp_to_datetime(timestamp: i64) -> AlphaResult<DateTime<Utc>> {
-        DateTime::from_timestamp_millis(timestamp)
-            .ok_or_else(|| crate::errors::AlphaError::invalid_input("Invalid timestamp"))
-    }
+/// 时间序列缺失值填充与重采样
+pub mod series;
 
-    /// 获取交易时间范围 (9:30-16:00)
-    pub fn is_trading_time(dt: &DateTime<Utc>) -> bool {
-        // 简化的美股交易时间判断
-        let hour = dt.hour() % 24; // UTC 转换简化处理
-        let minute = dt.minute();
+/// 失败重试与指数退避
+pub mod retry;
 
-        (hour == 14 && minute >= 30) || (hour > 14 && hour < 21) || (hour == 21 && minute == 0)
-    }
+/// 有界批量缓冲
+pub mod buffer;
 
-    /// 获取下一个交易日
-    pub fn next_trading_day(dt: &DateTime<Utc>) -> DateTime<Utc> {
-        let mut next_day = *dt + Duration::days(1);
+/// 证券代码解析与规范化
+pub mod symbol;
 
-        // 简化处理：跳过周末
-        while next_day.weekday().num_days_from_monday() >= 5 {
-            next_day = next_day + Duration::days(1);
-        }
+/// 数据完整性校验
+pub mod checksum;
 
-        next_day
-    }
-}
+/// CSV K 线文件解析
+pub mod csv;
 
 /// 数值工具函数
 pub mod numeric {
+    use super::*;
+    #[cfg(not(feature = "std"))]
+    use num_traits::Float;
+
     /// 保留指定位数的小数
     pub fn round_to(value: f64, precision: usize) -> f64 {
         let multiplier = 10_f64.powi(precision as i32);
@@ -83,10 +71,90 @@ pub mod numeric {
 
         result
     }
+
+    /// 计算分位数 (线性插值，`q` 取值 0.0 - 1.0)，忽略非有限值
+    pub fn percentile(values: &[f64], q: f64) -> Option<f64> {
+        let mut sorted: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+        if sorted.is_empty() || !(0.0..=1.0).contains(&q) {
+            return None;
+        }
+        sorted.sort_by(|a, b| a.total_cmp(b));
+
+        let rank = q * (sorted.len() - 1) as f64;
+        let lower = rank.floor() as usize;
+        let upper = rank.ceil() as usize;
+        Some(sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64))
+    }
+
+    /// 计算中位数
+    pub fn median(values: &[f64]) -> Option<f64> {
+        percentile(values, 0.5)
+    }
+
+    /// 将数值限制在 `[min, max]` 区间内，边界为 NaN 或 `min > max` 时返回错误
+    pub fn clamp_values(values: &[f64], min: f64, max: f64) -> AlphaResult<Vec<f64>> {
+        if min.is_nan() || max.is_nan() || min > max {
+            return Err(AlphaError::invalid_input(format!("Invalid clamp bounds [{}, {}]", min, max)));
+        }
+        Ok(values.iter().map(|v| v.clamp(min, max)).collect())
+    }
+
+    /// 缩尾处理：低于 `lower` 分位数或高于 `upper` 分位数的值替换为对应分位数
+    pub fn winsorize(values: &[f64], lower: f64, upper: f64) -> Vec<f64> {
+        match (percentile(values, lower), percentile(values, upper)) {
+            (Some(low), Some(high)) => clamp_values(values, low, high).unwrap_or_else(|_| values.to_vec()),
+            _ => values.to_vec(),
+        }
+    }
+
+    /// 中位数绝对偏差 (MAD)
+    pub fn median_absolute_deviation(values: &[f64]) -> Option<f64> {
+        let med = median(values)?;
+        let deviations: Vec<f64> = values.iter()
+            .filter(|v| v.is_finite())
+            .map(|v| (v - med).abs())
+            .collect();
+        median(&deviations)
+    }
+
+    /// 基于 MAD 的修正 Z 分数判定异常值 (常用阈值 3.5)，非有限值一律视为异常
+    pub fn mad_outliers(values: &[f64], threshold: f64) -> Vec<bool> {
+        let (med, mad) = match (median(values), median_absolute_deviation(values)) {
+            (Some(med), Some(mad)) => (med, mad),
+            _ => return values.iter().map(|v| !v.is_finite()).collect(),
+        };
+
+        values.iter()
+            .map(|v| {
+                if !v.is_finite() {
+                    return true;
+                }
+                if mad < f64::EPSILON {
+                    return (v - med).abs() > f64::EPSILON;
+                }
+                0.6745 * (v - med).abs() / mad > threshold
+            })
+            .collect()
+    }
+
+    /// MAD 过滤：将异常值替换为中位数
+    pub fn mad_filter(values: &[f64], threshold: f64) -> Vec<f64> {
+        let med = match median(values) {
+            Some(med) => med,
+            None => return values.to_vec(),
+        };
+
+        values.iter()
+            .zip(mad_outliers(values, threshold))
+            .map(|(&v, outlier)| if outlier { med } else { v })
+            .collect()
+    }
 }
 
 /// 字符串工具函数
 pub mod string {
+    use super::*;
+
     /// 安全截断字符串
     pub fn safe_truncate(s: &str, max_len: usize) -> String {
         if s.len() <= max_len {
@@ -101,16 +169,15 @@ pub mod string {
         s.chars().filter(|c| !c.is_whitespace()).collect()
     }
 
-    /// 检查是否为有效的股票代码
+    /// 检查是否为有效的证券代码 (支持 `AAPL`、`0700.HK`、`BRK.B`、`BTC-USDT` 等写法)
     pub fn is_valid_symbol(symbol: &str) -> bool {
-        !symbol.is_empty()
-            && symbol.len() <= 10
-            && symbol.chars().all(|c| c.is_alphanumeric() || c == '.')
+        super::symbol::Symbol::parse(symbol).is_ok()
     }
 }
 
 /// 数据验证工具
 pub mod validation {
+    use super::*;
     use crate::models::MarketData;
 
     /// 验证市场数据有效性
@@ -123,6 +190,7 @@ pub mod validation {
             return Err(crate::errors::AlphaError::invalid_input("Price must be positive"));
         }
 
+        #[cfg(feature = "std")]
         if data.timestamp > chrono::Utc::now() {
             return Err(crate::errors::AlphaError::invalid_input("Timestamp cannot be in the future"));
         }
@@ -143,11 +211,41 @@ pub mod validation {
 
         Ok(())
     }
+
+    /// 标记疑似错误报价的下标
+    ///
+    /// 对收益率做 MAD 异常检测，仅当某点的涨跌与随后的回落方向相反且均为异常时判定为尖刺 (坏点)，
+    /// 避免把真实的趋势跳空误判；最后一个点只检查进入该点的收益率
+    pub fn detect_price_outliers(prices: &[f64], threshold: f64) -> Vec<usize> {
+        if prices.len() < 3 {
+            return Vec::new();
+        }
+
+        let returns: Vec<f64> = prices.windows(2)
+            .map(|w| super::numeric::safe_divide(w[1] - w[0], w[0], f64::NAN))
+            .collect();
+        let outliers = super::numeric::mad_outliers(&returns, threshold);
+
+        (1..prices.len())
+            .filter(|&i| {
+                let into = i - 1;
+                if !outliers[into] {
+                    return false;
+                }
+                match returns.get(i) {
+                    Some(&out) => outliers[i] && (returns[into] * out < 0.0 || !out.is_finite()),
+                    None => true,
+                }
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::MarketData;
+    use chrono::{Duration, Utc};
 
     #[test]
     fn test_numeric_utils() {
@@ -157,11 +255,44 @@ mod tests {
         assert_eq!(numeric::safe_divide(10.0, 0.0, -1.0), -1.0);
     }
 
+    #[test]
+    fn test_outlier_utils() {
+        let values = [10.0, 11.0, 9.0, 10.5, 500.0, 10.2, f64::NAN];
+
+        assert_eq!(numeric::median(&[3.0, 1.0, 2.0]), Some(2.0));
+        assert_eq!(numeric::percentile(&[1.0, 2.0, 3.0, 4.0], 0.25), Some(1.75));
+        assert_eq!(numeric::clamp_values(&[-1.0, 5.0], 0.0, 1.0).unwrap(), vec![0.0, 1.0]);
+        // 非法边界返回错误而不是 panic
+        assert!(matches!(numeric::clamp_values(&[1.0], 1.0, 0.0), Err(AlphaError::InvalidInput(_))));
+        assert!(numeric::clamp_values(&[1.0], f64::NAN, 1.0).is_err());
+        assert!(numeric::clamp_values(&[1.0], 0.0, f64::NAN).is_err());
+        assert_eq!(numeric::winsorize(&[1.0, 2.0, 3.0, 4.0, 100.0], 0.0, 0.75), vec![1.0, 2.0, 3.0, 4.0, 4.0]);
+
+        let flags = numeric::mad_outliers(&values, 3.5);
+        assert_eq!(flags, vec![false, false, false, false, true, false, true]);
+
+        let cleaned = numeric::mad_filter(&values, 3.5);
+        assert_eq!(cleaned[4], 10.35);
+        assert!(cleaned.iter().all(|v| v.is_finite()));
+    }
+
+    #[test]
+    fn test_detect_price_outliers() {
+        let prices = [100.0, 100.5, 101.0, 1010.0, 101.5, 102.0, 102.3, 101.8];
+        assert_eq!(validation::detect_price_outliers(&prices, 3.5), vec![3]);
+
+        // 持续跳空不是坏点
+        let gap = [100.0, 100.5, 101.0, 101.2, 150.0, 150.5, 151.0, 150.8];
+        assert!(validation::detect_price_outliers(&gap, 3.5).is_empty());
+    }
+
     #[test]
     fn test_string_utils() {
         assert_eq!(string::safe_truncate("hello world", 5), "he...");
         assert_eq!(string::trim_whitespace(" hello  world "), "helloworld");
         assert!(string::is_valid_symbol("AAPL"));
+        assert!(string::is_valid_symbol("0700.HK"));
+        assert!(string::is_valid_symbol("BTC-USDT"));
         assert!(!string::is_valid_symbol(""));
         assert!(!string::is_valid_symbol("TOO_LONG_SYMBOL_12345"));
     }
@@ -178,8 +309,6 @@ mod tests {
 
     #[test]
     fn test_validation() {
-        use chrono::Utc;
-
         let valid_data = MarketData {
             symbol: "AAPL".to_string(),
             timestamp: Utc::now(),
@@ -0,0 +1,84 @@
+//! 有界批量缓冲
+//!
+//! 攒批写入下游 (归档、入库) 时暂存待写的数据，下游不可用期间积压超过容量则丢弃最早的，
+//! 写入失败的一批放回队首留待下次重试
+
+use crate::prelude::*;
+use alloc::collections::VecDeque;
+
+/// 等待写入的数据，超过容量时丢弃最早的
+#[derive(Debug, Clone)]
+pub struct BatchBuffer<T> {
+    items: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T> BatchBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self { items: VecDeque::new(), capacity }
+    }
+
+    /// 放入一条，返回因此丢弃的条数
+    pub fn push(&mut self, item: T) -> usize {
+        self.items.push_back(item);
+        self.trim()
+    }
+
+    /// 取出最早的至多 `size` 条
+    pub fn take(&mut self, size: usize) -> Vec<T> {
+        let size = size.min(self.items.len());
+        self.items.drain(..size).collect()
+    }
+
+    /// 写入失败时把取出的一批放回队首，保持原有顺序，下次 [`take`](Self::take) 时重新取出重试；
+    /// 放回后超过容量则丢弃最早的，返回因此丢弃的条数
+    pub fn restore(&mut self, items: Vec<T>) -> usize {
+        for item in items.into_iter().rev() {
+            self.items.push_front(item);
+        }
+        self.trim()
+    }
+
+    /// 最早的一条
+    pub fn front(&self) -> Option<&T> {
+        self.items.front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    fn trim(&mut self) -> usize {
+        let excess = self.items.len().saturating_sub(self.capacity);
+        self.items.drain(..excess);
+        excess
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_buffer() {
+        let mut buffer = BatchBuffer::new(3);
+        assert_eq!(buffer.push(1) + buffer.push(2) + buffer.push(3), 0);
+        // 超过容量时丢弃最早的
+        assert_eq!(buffer.push(4), 1);
+        assert_eq!(buffer.front(), Some(&2));
+
+        let batch = buffer.take(2);
+        assert_eq!(batch, vec![2, 3]);
+        buffer.push(5);
+        // 放回队首后仍按原有顺序，超出容量时同样丢弃最早的
+        assert_eq!(buffer.restore(batch), 1);
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.take(10), vec![3, 4, 5]);
+        assert!(buffer.is_empty());
+        assert!(buffer.take(10).is_empty());
+    }
+}
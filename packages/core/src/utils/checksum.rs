@@ -0,0 +1,449 @@
+//! 数据完整性校验
+//!
+//! 对 K 线序列按固定的二进制编码计算摘要，与序列化格式 (JSON / CSV) 无关，
+//! 写入导出文件和缓存后可在分析前发现截断或损坏的数据。
+//! 价格按浮点数的二进制位计算摘要，JSON 解析须开启 `serde_json/float_roundtrip` (工作区已开启)，否则往返后末位可能不同
+
+use crate::errors::{AlphaError, AlphaResult};
+use crate::models::{AnalysisResult, MarketData, SignalType};
+use crate::prelude::*;
+use core::fmt;
+use core::str::FromStr;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use xxhash_rust::xxh3::Xxh3;
+
+/// 摘要算法
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecksumAlgorithm {
+    /// XXH3 64 位，速度快，适合本地缓存
+    Xxh3,
+    /// SHA-256，适合对外导出的文件
+    Sha256,
+}
+
+impl ChecksumAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Xxh3 => "xxh3",
+            ChecksumAlgorithm::Sha256 => "sha256",
+        }
+    }
+}
+
+/// 分析结果指纹默认的取整小数位数
+pub const FINGERPRINT_DECIMALS: u32 = 8;
+
+/// 增量计算摘要
+#[derive(Clone)]
+pub struct ChecksumHasher {
+    inner: HasherInner,
+}
+
+#[derive(Clone)]
+enum HasherInner {
+    Xxh3(Box<Xxh3>),
+    Sha256(Sha256),
+}
+
+impl ChecksumHasher {
+    pub fn new(algorithm: ChecksumAlgorithm) -> Self {
+        let inner = match algorithm {
+            ChecksumAlgorithm::Xxh3 => HasherInner::Xxh3(Box::new(Xxh3::new())),
+            ChecksumAlgorithm::Sha256 => HasherInner::Sha256(Sha256::new()),
+        };
+        Self { inner }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        match &mut self.inner {
+            HasherInner::Xxh3(h) => h.update(bytes),
+            HasherInner::Sha256(h) => h.update(bytes),
+        }
+    }
+
+    /// 写入一条行情记录的规范编码 (小端定长字段，变长字段带长度前缀)
+    pub fn update_market_data(&mut self, data: &MarketData) {
+        self.update(&(data.symbol.len() as u64).to_le_bytes());
+        self.update(data.symbol.as_bytes());
+        self.update(&data.timestamp.timestamp_millis().to_le_bytes());
+        self.update(&data.price.to_bits().to_le_bytes());
+        self.update(&data.volume.to_le_bytes());
+        for field in [data.bid, data.ask, data.open, data.high, data.low] {
+            match field {
+                Some(v) => {
+                    self.update(&[1]);
+                    self.update(&v.to_bits().to_le_bytes());
+                }
+                None => self.update(&[0]),
+            }
+        }
+    }
+
+    /// 写入取整后的数值：按 `decimals` 放大取整为整数，消除不同平台浮点末位差异；
+    /// 非有限值与 `None` 各自写入固定标记
+    pub fn update_rounded(&mut self, value: Option<f64>, decimals: u32) {
+        match value {
+            Some(v) if v.is_finite() => {
+                self.update(&[1]);
+                let scaled = (v * 10_f64.powi(decimals as i32)).round() as i64;
+                self.update(&scaled.to_le_bytes());
+            }
+            Some(_) => self.update(&[2]),
+            None => self.update(&[0]),
+        }
+    }
+
+    fn update_str(&mut self, s: &str) {
+        self.update(&(s.len() as u64).to_le_bytes());
+        self.update(s.as_bytes());
+    }
+
+    fn update_signal_type(&mut self, signal: &SignalType) {
+        self.update(&[match signal {
+            SignalType::Buy => 1,
+            SignalType::Sell => 2,
+            SignalType::Hold => 3,
+            SignalType::None => 0,
+        }]);
+    }
+
+    pub fn finish(self) -> Checksum {
+        match self.inner {
+            HasherInner::Xxh3(h) => Checksum {
+                algorithm: ChecksumAlgorithm::Xxh3,
+                digest: format!("{:016x}", h.digest()),
+            },
+            HasherInner::Sha256(h) => Checksum {
+                algorithm: ChecksumAlgorithm::Sha256,
+                digest: h.finalize().iter().map(|b| format!("{:02x}", b)).collect(),
+            },
+        }
+    }
+}
+
+/// 摘要值，文本形式为 `算法:十六进制摘要`，如 `xxh3:9f86d081884c7d65`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Checksum {
+    /// 摘要算法
+    pub algorithm: ChecksumAlgorithm,
+    /// 小写十六进制摘要
+    pub digest: String,
+}
+
+impl Checksum {
+    /// 计算字节数据的摘要
+    pub fn of_bytes(algorithm: ChecksumAlgorithm, bytes: &[u8]) -> Self {
+        let mut hasher = ChecksumHasher::new(algorithm);
+        hasher.update(bytes);
+        hasher.finish()
+    }
+
+    /// 计算行情序列的摘要 (包含记录数，截断后必然不一致)
+    pub fn of_market_data(algorithm: ChecksumAlgorithm, data: &[MarketData]) -> Self {
+        let mut hasher = ChecksumHasher::new(algorithm);
+        hasher.update(&(data.len() as u64).to_le_bytes());
+        for item in data {
+            hasher.update_market_data(item);
+        }
+        hasher.finish()
+    }
+
+    /// 分析结果指纹，用于比对各平台对同一输入的计算结果
+    ///
+    /// 覆盖标的、指标序列、风险指标、信号 (方向、强度、来源) 和推荐，不含分析时间和说明文本
+    pub fn of_analysis(algorithm: ChecksumAlgorithm, result: &AnalysisResult, decimals: u32) -> Self {
+        let mut hasher = ChecksumHasher::new(algorithm);
+        hasher.update_str(&result.symbol);
+
+        hasher.update(&(result.indicators.len() as u64).to_le_bytes());
+        for indicator in &result.indicators {
+            hasher.update_str(&indicator.name);
+            hasher.update(&(indicator.values.len() as u64).to_le_bytes());
+            for (i, value) in indicator.values.iter().enumerate() {
+                let timestamp = indicator.timestamps.get(i).map_or(i64::MIN, |t| t.timestamp_millis());
+                hasher.update(&timestamp.to_le_bytes());
+                hasher.update_rounded(Some(*value), decimals);
+            }
+            hasher.update(&(indicator.signals.len() as u64).to_le_bytes());
+            for signal in &indicator.signals {
+                hasher.update_signal_type(signal);
+            }
+        }
+
+        let risk = &result.risk_metrics;
+        hasher.update_rounded(Some(risk.volatility), decimals);
+        hasher.update_rounded(risk.sharpe_ratio, decimals);
+        hasher.update_rounded(Some(risk.max_drawdown), decimals);
+        hasher.update_rounded(risk.beta, decimals);
+
+        hasher.update(&(result.signals.len() as u64).to_le_bytes());
+        for signal in &result.signals {
+            hasher.update_signal_type(&signal.direction);
+            hasher.update_rounded(Some(signal.strength), decimals);
+            hasher.update_str(&signal.source_indicator);
+        }
+
+        hasher.update_signal_type(&result.recommendation);
+        hasher.update_rounded(Some(result.confidence), decimals);
+        hasher.finish()
+    }
+
+    /// 校验字节数据
+    pub fn verify_bytes(&self, bytes: &[u8]) -> AlphaResult<()> {
+        self.expect(&Self::of_bytes(self.algorithm, bytes))
+    }
+
+    /// 校验行情序列
+    pub fn verify_market_data(&self, data: &[MarketData]) -> AlphaResult<()> {
+        self.expect(&Self::of_market_data(self.algorithm, data))
+    }
+
+    fn expect(&self, actual: &Checksum) -> AlphaResult<()> {
+        if self.digest.eq_ignore_ascii_case(&actual.digest) {
+            Ok(())
+        } else {
+            Err(AlphaError::DataCorrupted(format!("checksum mismatch: expected {}, got {}", self, actual)))
+        }
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm.name(), self.digest)
+    }
+}
+
+impl FromStr for Checksum {
+    type Err = AlphaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, digest) = s.trim().split_once(':')
+            .ok_or_else(|| AlphaError::invalid_input(format!("Invalid checksum: '{}'", s)))?;
+        let (algorithm, len) = match name.to_ascii_lowercase().as_str() {
+            "xxh3" => (ChecksumAlgorithm::Xxh3, 16),
+            "sha256" => (ChecksumAlgorithm::Sha256, 64),
+            _ => return Err(AlphaError::invalid_input(format!("Unknown checksum algorithm: '{}'", name))),
+        };
+        if digest.len() != len || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(AlphaError::invalid_input(format!("Invalid {} digest: '{}'", name, digest)));
+        }
+
+        Ok(Self {
+            algorithm,
+            digest: digest.to_ascii_lowercase(),
+        })
+    }
+}
+
+impl Serialize for Checksum {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Checksum {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// 摘要旁路文件路径 (`data.csv` -> `data.csv.checksum`)
+#[cfg(feature = "std")]
+pub fn sidecar_path(path: &std::path::Path) -> std::path::PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".checksum");
+    std::path::PathBuf::from(name)
+}
+
+/// 流式计算文件摘要
+#[cfg(feature = "std")]
+pub fn checksum_file(algorithm: ChecksumAlgorithm, path: &std::path::Path) -> AlphaResult<Checksum> {
+    use std::io::Read;
+
+    let io_error = |err: std::io::Error| {
+        AlphaError::StorageError(format!("failed to read {}", path.display())).caused_by(err)
+    };
+    let mut file = std::fs::File::open(path).map_err(io_error)?;
+    let mut hasher = ChecksumHasher::new(algorithm);
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).map_err(io_error)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finish())
+}
+
+/// 计算文件摘要并写入旁路文件
+#[cfg(feature = "std")]
+pub fn write_sidecar(algorithm: ChecksumAlgorithm, path: &std::path::Path) -> AlphaResult<Checksum> {
+    let checksum = checksum_file(algorithm, path)?;
+    let sidecar = sidecar_path(path);
+    std::fs::write(&sidecar, format!("{}\n", checksum)).map_err(|err| {
+        AlphaError::StorageError(format!("failed to write {}", sidecar.display())).caused_by(err)
+    })?;
+    Ok(checksum)
+}
+
+/// 按旁路文件校验文件完整性，没有旁路文件时返回 `Ok(false)`
+#[cfg(feature = "std")]
+pub fn verify_sidecar(path: &std::path::Path) -> AlphaResult<bool> {
+    let sidecar = sidecar_path(path);
+    let expected: Checksum = match std::fs::read_to_string(&sidecar) {
+        Ok(content) => content.parse()?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => {
+            return Err(AlphaError::StorageError(format!("failed to read {}", sidecar.display())).caused_by(err));
+        }
+    };
+
+    let actual = checksum_file(expected.algorithm, path)?;
+    expected.expect(&actual).map_err(|e| e.context(format!("verify {}", path.display())))?;
+    Ok(true)
+}
+
+/// 带摘要的行情数据集，用于导出文件和缓存
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChecksummedDataset {
+    /// 数据摘要
+    pub checksum: Checksum,
+    /// 记录数
+    pub record_count: usize,
+    /// 行情数据
+    pub data: Vec<MarketData>,
+}
+
+impl ChecksummedDataset {
+    pub fn new(algorithm: ChecksumAlgorithm, data: Vec<MarketData>) -> Self {
+        Self {
+            checksum: Checksum::of_market_data(algorithm, &data),
+            record_count: data.len(),
+            data,
+        }
+    }
+
+    /// 校验记录数和摘要
+    pub fn verify(&self) -> AlphaResult<()> {
+        if self.record_count != self.data.len() {
+            return Err(AlphaError::DataCorrupted(format!(
+                "expected {} records, found {}",
+                self.record_count,
+                self.data.len()
+            )));
+        }
+        self.checksum.verify_market_data(&self.data)
+    }
+
+    /// 校验通过后取出数据
+    pub fn into_verified(self) -> AlphaResult<Vec<MarketData>> {
+        self.verify()?;
+        Ok(self.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration};
+
+    fn candles(n: usize) -> Vec<MarketData> {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        (0..n)
+            .map(|i| {
+                let p = 100.0 + i as f64;
+                MarketData::with_ohlcv("AAPL".to_string(), start + Duration::minutes(i as i64), p, p + 1.0, p - 1.0, p, 1000)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_known_digests() {
+        assert_eq!(
+            Checksum::of_bytes(ChecksumAlgorithm::Sha256, b"abc").digest,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(Checksum::of_bytes(ChecksumAlgorithm::Xxh3, b"").digest, "2d06800538d394c2");
+    }
+
+    #[test]
+    fn test_detects_truncation_and_corruption() {
+        let data = candles(10);
+        for algorithm in [ChecksumAlgorithm::Xxh3, ChecksumAlgorithm::Sha256] {
+            let checksum = Checksum::of_market_data(algorithm, &data);
+            assert!(checksum.verify_market_data(&data).is_ok());
+            assert!(checksum.verify_market_data(&data[..9]).is_err());
+
+            let mut corrupted = data.clone();
+            corrupted[3].price += 0.01;
+            assert!(matches!(checksum.verify_market_data(&corrupted), Err(AlphaError::DataCorrupted(_))));
+        }
+    }
+
+    #[test]
+    fn test_dataset_roundtrip() {
+        let dataset = ChecksummedDataset::new(ChecksumAlgorithm::Sha256, candles(5));
+        let json = serde_json::to_string(&dataset).unwrap();
+        assert!(json.contains("\"checksum\":\"sha256:"));
+
+        let restored: ChecksummedDataset = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.into_verified().unwrap().len(), 5);
+
+        // 非整数价格 JSON 往返后逐位一致
+        let mut data = candles(3);
+        for (item, price) in data.iter_mut().zip([187.23999786376953, 117.54621790330661, 0.1 + 0.2]) {
+            item.price = price;
+            item.high = Some(price * 1.01);
+        }
+        let json = serde_json::to_string(&ChecksummedDataset::new(ChecksumAlgorithm::Sha256, data)).unwrap();
+        let restored: ChecksummedDataset = serde_json::from_str(&json).unwrap();
+        assert!(restored.verify().is_ok());
+
+        let mut truncated = dataset.clone();
+        truncated.data.pop();
+        assert!(truncated.verify().is_err());
+
+        assert!("md5:abcd".parse::<Checksum>().is_err());
+        assert!("xxh3:zz".parse::<Checksum>().is_err());
+    }
+
+    #[test]
+    fn test_analysis_fingerprint() {
+        let engine = crate::analytics::AnalysisEngine::new();
+        let data = candles(60);
+        let result = tokio_test::block_on(engine.analyze_symbol(&data, None)).unwrap();
+        let fingerprint = Checksum::of_analysis(ChecksumAlgorithm::Sha256, &result, FINGERPRINT_DECIMALS);
+
+        // 分析时间不参与，末位浮点噪声被取整消除
+        let mut later = result.clone();
+        later.analyzed_at += Duration::hours(1);
+        later.risk_metrics.volatility += 1e-12;
+        assert_eq!(Checksum::of_analysis(ChecksumAlgorithm::Sha256, &later, FINGERPRINT_DECIMALS), fingerprint);
+
+        let mut changed = result.clone();
+        changed.indicators[0].values[30] += 0.001;
+        assert_ne!(Checksum::of_analysis(ChecksumAlgorithm::Sha256, &changed, FINGERPRINT_DECIMALS), fingerprint);
+    }
+
+    #[test]
+    fn test_sidecar_file() {
+        let path = std::env::temp_dir().join(format!("alpha-checksum-{}.csv", std::process::id()));
+        std::fs::write(&path, "symbol,price\nAAPL,150.0\n").unwrap();
+
+        assert!(!verify_sidecar(&path).unwrap());
+        write_sidecar(ChecksumAlgorithm::Sha256, &path).unwrap();
+        assert!(verify_sidecar(&path).unwrap());
+
+        std::fs::write(&path, "symbol,price\nAAPL,15").unwrap();
+        assert!(verify_sidecar(&path).is_err());
+
+        std::fs::remove_file(sidecar_path(&path)).ok();
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,371 @@
+//! CSV K 线文件解析
+//!
+//! 面向用户上传的行情文件 (各券商/数据站导出格式不一)，通过列映射与日期格式配置转换为 [`MarketData`]。
+//! 只支持 RFC 4180 的常见子集：双引号包裹字段、`""` 转义、CRLF/LF 换行
+
+use crate::errors::{AlphaError, AlphaResult};
+use crate::models::MarketData;
+use crate::prelude::*;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/// 列引用：按表头名称 (不区分大小写) 或从 0 开始的列号
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ColumnRef {
+    Index(usize),
+    Name(String),
+}
+
+/// 列映射，未指定的列按常见表头名自动识别
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct CsvColumns {
+    pub timestamp: Option<ColumnRef>,
+    pub open: Option<ColumnRef>,
+    pub high: Option<ColumnRef>,
+    pub low: Option<ColumnRef>,
+    pub close: Option<ColumnRef>,
+    pub volume: Option<ColumnRef>,
+    pub symbol: Option<ColumnRef>,
+}
+
+/// CSV 解析配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct CsvMapping {
+    pub delimiter: char,
+    pub has_header: bool,
+    /// 文件不含代码列时使用的股票代码
+    pub symbol: Option<String>,
+    pub columns: CsvColumns,
+    /// chrono strftime 格式，如 `%Y/%m/%d`；为空时自动识别 RFC 3339、常见日期格式及 Unix 秒/毫秒
+    pub date_format: Option<String>,
+    /// 不带时区的时间所在时区 (IANA 名称，如 `Asia/Shanghai`)，默认 UTC
+    pub timezone: Option<String>,
+    /// 跳过无法解析的行而不是报错
+    pub skip_invalid: bool,
+}
+
+impl Default for CsvMapping {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            has_header: true,
+            symbol: None,
+            columns: CsvColumns::default(),
+            date_format: None,
+            timezone: None,
+            skip_invalid: false,
+        }
+    }
+}
+
+const TIMESTAMP_ALIASES: &[&str] = &["timestamp", "date", "datetime", "time", "trade_date", "日期", "时间"];
+const OPEN_ALIASES: &[&str] = &["open", "开盘", "开盘价"];
+const HIGH_ALIASES: &[&str] = &["high", "最高", "最高价"];
+const LOW_ALIASES: &[&str] = &["low", "最低", "最低价"];
+const CLOSE_ALIASES: &[&str] = &["close", "adj close", "price", "收盘", "收盘价"];
+const VOLUME_ALIASES: &[&str] = &["volume", "vol", "成交量"];
+const SYMBOL_ALIASES: &[&str] = &["symbol", "ticker", "code", "代码"];
+
+const AUTO_DATE_FORMATS: &[&str] = &["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y/%m/%d %H:%M:%S", "%Y-%m-%d %H:%M"];
+const AUTO_DAY_FORMATS: &[&str] = &["%Y-%m-%d", "%Y/%m/%d", "%Y%m%d"];
+
+struct ResolvedColumns {
+    timestamp: usize,
+    open: Option<usize>,
+    high: Option<usize>,
+    low: Option<usize>,
+    close: usize,
+    volume: Option<usize>,
+    symbol: Option<usize>,
+}
+
+/// 解析 CSV 字节为按时间升序排列的行情序列
+pub fn parse_csv(bytes: &[u8], mapping: &CsvMapping) -> AlphaResult<Vec<MarketData>> {
+    let text = core::str::from_utf8(bytes)
+        .map_err(|e| AlphaError::invalid_input(format!("CSV is not valid UTF-8: {}", e)))?;
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+
+    let mut lines = text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    let header = if mapping.has_header {
+        let (_, line) = lines.next().ok_or_else(|| AlphaError::invalid_input("CSV is empty"))?;
+        Some(split_record(line, mapping.delimiter))
+    } else {
+        None
+    };
+
+    let rows = lines.map(|(index, line)| (index + 1, split_record(line, mapping.delimiter)));
+    parse_table(header.as_deref(), rows, mapping, "line")
+}
+
+/// 解析已拆分为字段的记录 (如按表头展开的 JSON 对象数组)，列映射与时间解析规则与 CSV 相同
+pub fn parse_records(
+    header: &[String],
+    records: impl IntoIterator<Item = Vec<String>>,
+    mapping: &CsvMapping,
+) -> AlphaResult<Vec<MarketData>> {
+    let rows = records.into_iter().enumerate().map(|(index, fields)| (index + 1, fields));
+    parse_table(Some(header), rows, mapping, "record")
+}
+
+/// `rows` 为 (位置, 字段)，位置用于错误上下文，`unit` 为位置的名称
+fn parse_table(
+    header: Option<&[String]>,
+    rows: impl Iterator<Item = (usize, Vec<String>)>,
+    mapping: &CsvMapping,
+    unit: &str,
+) -> AlphaResult<Vec<MarketData>> {
+    let timezone = match &mapping.timezone {
+        Some(name) => Some(name.parse::<Tz>()
+            .map_err(|_| AlphaError::invalid_input(format!("Unknown timezone: {}", name)))?),
+        None => None,
+    };
+    let columns = resolve_columns(&mapping.columns, header)?;
+    if columns.symbol.is_none() && mapping.symbol.is_none() {
+        return Err(AlphaError::invalid_input("CSV has no symbol column and no symbol was configured"));
+    }
+
+    let mut data = Vec::new();
+    for (position, fields) in rows {
+        match parse_row(&fields, &columns, mapping, timezone) {
+            Ok(row) => data.push(row),
+            Err(_) if mapping.skip_invalid => continue,
+            Err(e) => return Err(e.context(format!("{} {}", unit, position))),
+        }
+    }
+
+    if data.is_empty() {
+        return Err(AlphaError::invalid_input("CSV contains no data rows"));
+    }
+    // 部分数据源按时间倒序导出
+    data.sort_by_key(|d| d.timestamp);
+    Ok(data)
+}
+
+fn parse_row(fields: &[String], columns: &ResolvedColumns, mapping: &CsvMapping, timezone: Option<Tz>) -> AlphaResult<MarketData> {
+    let field = |index: usize| -> AlphaResult<&str> {
+        fields.get(index)
+            .map(|s| s.trim())
+            .ok_or_else(|| AlphaError::invalid_input(format!("Missing column {}", index + 1)))
+    };
+    let number = |index: Option<usize>| -> AlphaResult<Option<f64>> {
+        let Some(index) = index else { return Ok(None) };
+        let raw = field(index)?;
+        if raw.is_empty() {
+            return Ok(None);
+        }
+        raw.parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .map(Some)
+            .ok_or_else(|| AlphaError::invalid_input(format!("Invalid number: {}", raw)))
+    };
+
+    let timestamp = parse_timestamp(field(columns.timestamp)?, mapping.date_format.as_deref(), timezone)?;
+    let price = number(Some(columns.close))?
+        .ok_or_else(|| AlphaError::invalid_input("Close price is empty"))?;
+    let symbol = match columns.symbol {
+        Some(index) => field(index)?.to_string(),
+        None => mapping.symbol.clone().unwrap_or_default(),
+    };
+
+    Ok(MarketData {
+        symbol,
+        timestamp,
+        price,
+        volume: number(columns.volume)?.unwrap_or(0.0).max(0.0) as u64,
+        bid: None,
+        ask: None,
+        open: number(columns.open)?,
+        high: number(columns.high)?,
+        low: number(columns.low)?,
+    })
+}
+
+fn parse_timestamp(raw: &str, format: Option<&str>, timezone: Option<Tz>) -> AlphaResult<DateTime<Utc>> {
+    let invalid = || AlphaError::invalid_input(format!("Invalid timestamp: {}", raw));
+    let localize = |naive: NaiveDateTime| -> Option<DateTime<Utc>> {
+        match timezone {
+            Some(tz) => tz.from_local_datetime(&naive).earliest().map(|dt| dt.with_timezone(&Utc)),
+            None => Some(Utc.from_utc_datetime(&naive)),
+        }
+    };
+
+    if let Some(format) = format {
+        if let Ok(dt) = DateTime::parse_from_str(raw, format) {
+            return Ok(dt.with_timezone(&Utc));
+        }
+        return NaiveDateTime::parse_from_str(raw, format)
+            .ok()
+            .or_else(|| NaiveDate::parse_from_str(raw, format).ok().and_then(|d| d.and_hms_opt(0, 0, 0)))
+            .and_then(localize)
+            .ok_or_else(invalid);
+    }
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    // 纯数字且非 YYYYMMDD：Unix 时间戳，超过 1e11 视为毫秒
+    if raw.len() != 8 {
+        if let Ok(ts) = raw.parse::<i64>() {
+            let dt = if ts.abs() >= 100_000_000_000 {
+                DateTime::from_timestamp_millis(ts)
+            } else {
+                DateTime::from_timestamp(ts, 0)
+            };
+            return dt.ok_or_else(invalid);
+        }
+    }
+
+    AUTO_DATE_FORMATS.iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(raw, f).ok())
+        .or_else(|| {
+            AUTO_DAY_FORMATS.iter()
+                .find_map(|f| NaiveDate::parse_from_str(raw, f).ok())
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+        .and_then(localize)
+        .ok_or_else(invalid)
+}
+
+fn resolve_columns(columns: &CsvColumns, header: Option<&[String]>) -> AlphaResult<ResolvedColumns> {
+    // 无表头且未配置任何列时，按 date, open, high, low, close, volume 的顺序读取
+    let positional = *columns == CsvColumns::default();
+    let resolve = |column: &Option<ColumnRef>, aliases: &[&str], default: Option<usize>| -> AlphaResult<Option<usize>> {
+        match (column, header) {
+            (Some(ColumnRef::Index(index)), _) => Ok(Some(*index)),
+            (Some(ColumnRef::Name(name)), Some(header)) => find_column(header, &[name.as_str()])
+                .map(Some)
+                .ok_or_else(|| AlphaError::invalid_input(format!("Column not found in CSV header: {}", name))),
+            (Some(ColumnRef::Name(name)), None) => Err(AlphaError::invalid_input(format!(
+                "Columns must be mapped by index when the CSV has no header: {}", name
+            ))),
+            (None, Some(header)) => Ok(find_column(header, aliases)),
+            (None, None) => Ok(default.filter(|_| positional)),
+        }
+    };
+
+    let timestamp = resolve(&columns.timestamp, TIMESTAMP_ALIASES, Some(0))?
+        .ok_or_else(|| AlphaError::invalid_input("Cannot detect timestamp column, set columns.timestamp"))?;
+    let close = resolve(&columns.close, CLOSE_ALIASES, Some(4))?
+        .ok_or_else(|| AlphaError::invalid_input("Cannot detect close column, set columns.close"))?;
+
+    Ok(ResolvedColumns {
+        timestamp,
+        open: resolve(&columns.open, OPEN_ALIASES, Some(1))?,
+        high: resolve(&columns.high, HIGH_ALIASES, Some(2))?,
+        low: resolve(&columns.low, LOW_ALIASES, Some(3))?,
+        close,
+        volume: resolve(&columns.volume, VOLUME_ALIASES, Some(5))?,
+        symbol: resolve(&columns.symbol, SYMBOL_ALIASES, None)?,
+    })
+}
+
+fn find_column(header: &[String], names: &[&str]) -> Option<usize> {
+    names.iter().find_map(|name| {
+        header.iter().position(|h| h.trim().eq_ignore_ascii_case(name))
+    })
+}
+
+/// 按分隔符拆分一行，处理双引号包裹与 `""` 转义 (不支持跨行字段)
+fn split_record(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => fields.push(core::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_with_header_aliases() {
+        let csv = "\u{feff}Date,Open,High,Low,Close,Adj Close,Volume\n\
+                   2024-01-03,10.5,11,10,10.8,10.8,\"1,200\"\n\
+                   2024-01-02,10,10.6,9.9,10.5,10.5,1000\n";
+        let mapping = CsvMapping { symbol: Some("AAPL".into()), ..CsvMapping::default() };
+
+        // 带千分位的成交量不是合法数值
+        assert!(parse_csv(csv.as_bytes(), &mapping).is_err());
+
+        let mapping = CsvMapping { skip_invalid: true, ..mapping };
+        let data = parse_csv(csv.as_bytes(), &mapping).unwrap();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].price, 10.5);
+        assert_eq!(data[0].high, Some(10.6));
+        assert_eq!(data[0].volume, 1000);
+        assert_eq!(data[0].timestamp.to_rfc3339(), "2024-01-02T00:00:00+00:00");
+    }
+
+    #[test]
+    fn test_parse_with_explicit_mapping() {
+        let csv = "20240102 09:30;600519;1700.5;300\n20240102 09:31;600519;1701.0;200\n";
+        let mapping = CsvMapping {
+            delimiter: ';',
+            has_header: false,
+            columns: CsvColumns {
+                timestamp: Some(ColumnRef::Index(0)),
+                symbol: Some(ColumnRef::Index(1)),
+                close: Some(ColumnRef::Index(2)),
+                volume: Some(ColumnRef::Index(3)),
+                open: None,
+                high: None,
+                low: None,
+            },
+            date_format: Some("%Y%m%d %H:%M".into()),
+            timezone: Some("Asia/Shanghai".into()),
+            ..CsvMapping::default()
+        };
+
+        let data = parse_csv(csv.as_bytes(), &mapping).unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].symbol, "600519");
+        assert_eq!(data[1].price, 1701.0);
+        assert_eq!(data[0].open, None);
+        assert_eq!(data[0].timestamp.to_rfc3339(), "2024-01-02T01:30:00+00:00");
+    }
+
+    #[test]
+    fn test_requires_symbol() {
+        assert!(parse_csv(b"date,close\n2024-01-02,1\n", &CsvMapping::default()).is_err());
+        let data = parse_csv(b"date,close,symbol\n1704153600,1,X\n", &CsvMapping::default()).unwrap();
+        assert_eq!(data[0].timestamp.to_rfc3339(), "2024-01-02T00:00:00+00:00");
+    }
+
+    #[test]
+    fn test_parse_records() {
+        let header = vec!["ticker".to_string(), "date".to_string(), "close".to_string()];
+        let records = vec![
+            vec!["AAPL".to_string(), "2024-01-03".to_string(), "185.6".to_string()],
+            vec!["AAPL".to_string(), "2024-01-02".to_string(), "184.2".to_string()],
+        ];
+        let data = parse_records(&header, records.clone(), &CsvMapping::default()).unwrap();
+        assert_eq!(data[0].price, 184.2);
+
+        let mut broken = records;
+        broken[1][2] = "n/a".to_string();
+        let err = parse_records(&header, broken, &CsvMapping::default()).unwrap_err();
+        assert!(err.report().contains("record 2"));
+    }
+}
@@ -0,0 +1,305 @@
+//! 失败重试与指数退避
+//!
+//! 与运行时无关：`retry_with_sleep` 由调用方注入休眠函数，开启 `tokio` 特性后可直接使用 `retry`。
+//! 没有固定尝试次数的循环 (如断线重连) 使用 [`Backoff`] 取得每次的等待时间
+
+use crate::errors::{AlphaError, AlphaResult};
+use crate::prelude::*;
+use crate::simulate::SimRng;
+use core::future::Future;
+use core::time::Duration;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+use serde::{Deserialize, Serialize};
+
+/// 重试策略
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetryPolicy {
+    /// 最大尝试次数 (含首次调用)
+    pub max_attempts: u32,
+    /// 首次重试前的等待时间
+    pub initial_delay: Duration,
+    /// 单次等待上限
+    pub max_delay: Duration,
+    /// 每次重试等待时间的增长倍数
+    pub multiplier: f64,
+    /// 随机抖动比例 (0.0 - 1.0)，实际等待时间在 `[delay * (1 - jitter), delay]` 内均匀分布
+    pub jitter: f64,
+    /// 抖动随机种子
+    pub seed: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: 0.5,
+            seed: default_seed(),
+        }
+    }
+}
+
+/// 默认种子：std 下取当前时间，避免多个客户端同步重试
+fn default_seed() -> u64 {
+    #[cfg(feature = "std")]
+    {
+        chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        0
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32, initial_delay: Duration) -> Self {
+        Self {
+            max_attempts,
+            initial_delay,
+            ..Self::default()
+        }
+    }
+
+    /// 不重试，仅调用一次
+    pub fn none() -> Self {
+        Self::new(1, Duration::ZERO)
+    }
+
+    /// 断线重连：不限次数，1 秒起翻倍至 `max_delay`，带默认抖动避免多个客户端同时重连
+    pub fn reconnect(max_delay: Duration) -> Self {
+        Self::new(u32::MAX, Duration::from_secs(1)).with_max_delay(max_delay)
+    }
+
+    /// 设置单次等待上限
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// 设置增长倍数
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// 设置抖动比例
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// 设置抖动随机种子
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// 第 `retry` 次重试 (从 0 开始) 前的基础等待时间，不含抖动
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = self.multiplier.max(1.0).powi(retry.min(i32::MAX as u32) as i32);
+        let secs = (self.initial_delay.as_secs_f64() * factor).min(self.max_delay.as_secs_f64());
+        Duration::from_secs_f64(secs.max(0.0))
+    }
+
+    /// 按策略生成带抖动的等待时间序列
+    pub fn backoffs(&self) -> Backoff {
+        Backoff::new(self.clone())
+    }
+}
+
+/// 连续失败时带抖动的等待时间序列，用于重试和断线重连；恢复正常后调用 `reset` 从首次等待时间重新开始
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    policy: RetryPolicy,
+    rng: SimRng,
+    retry: u32,
+}
+
+impl Backoff {
+    pub fn new(policy: RetryPolicy) -> Self {
+        let rng = SimRng::new(policy.seed);
+        Self { policy, rng, retry: 0 }
+    }
+
+    /// 已连续等待的次数
+    pub fn retries(&self) -> u32 {
+        self.retry
+    }
+
+    /// 下一次等待时间：`backoff(retries)` 按抖动比例随机缩短
+    pub fn next_delay(&mut self) -> Duration {
+        let base = self.policy.backoff(self.retry);
+        self.retry = self.retry.saturating_add(1);
+        let jitter = self.policy.jitter.clamp(0.0, 1.0);
+        base.mul_f64(1.0 - jitter * self.rng.next_f64())
+    }
+
+    pub fn reset(&mut self) {
+        self.retry = 0;
+    }
+}
+
+/// 按策略重试，仅对 `AlphaError::is_retryable` 的错误重试
+pub async fn retry_with_sleep<T, F, Fut, S, SFut>(policy: &RetryPolicy, sleep: S, op: F) -> AlphaResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = AlphaResult<T>>,
+    S: FnMut(Duration) -> SFut,
+    SFut: Future<Output = ()>,
+{
+    retry_if_with_sleep(policy, AlphaError::is_retryable, sleep, op).await
+}
+
+/// 按策略重试，由 `should_retry` 决定哪些错误需要重试
+///
+/// 用尽尝试次数后返回最后一次的错误，并附加尝试次数上下文
+pub async fn retry_if_with_sleep<T, F, Fut, P, S, SFut>(
+    policy: &RetryPolicy,
+    mut should_retry: P,
+    mut sleep: S,
+    mut op: F,
+) -> AlphaResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = AlphaResult<T>>,
+    P: FnMut(&AlphaError) -> bool,
+    S: FnMut(Duration) -> SFut,
+    SFut: Future<Output = ()>,
+{
+    let max_attempts = policy.max_attempts.max(1);
+    let mut backoff = policy.backoffs();
+    let mut attempt = 1;
+
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(err) if !should_retry(&err) => return Err(err),
+            Err(err) if attempt >= max_attempts => {
+                return Err(if max_attempts > 1 {
+                    err.context(format!("gave up after {} attempts", attempt))
+                } else {
+                    err
+                });
+            }
+            Err(_) => {
+                sleep(backoff.next_delay()).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// 使用 tokio 定时器按策略重试
+#[cfg(feature = "tokio")]
+pub async fn retry<T, F, Fut>(policy: &RetryPolicy, op: F) -> AlphaResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = AlphaResult<T>>,
+{
+    retry_with_sleep(policy, tokio::time::sleep, op).await
+}
+
+/// 使用 tokio 定时器按策略重试，由 `should_retry` 决定哪些错误需要重试
+#[cfg(feature = "tokio")]
+pub async fn retry_if<T, F, Fut, P>(policy: &RetryPolicy, should_retry: P, op: F) -> AlphaResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = AlphaResult<T>>,
+    P: FnMut(&AlphaError) -> bool,
+{
+    retry_if_with_sleep(policy, should_retry, tokio::time::sleep, op).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::{Cell, RefCell};
+
+    fn no_sleep(delays: &RefCell<Vec<Duration>>) -> impl FnMut(Duration) -> core::future::Ready<()> + '_ {
+        move |d| {
+            delays.borrow_mut().push(d);
+            core::future::ready(())
+        }
+    }
+
+    #[test]
+    fn test_backoff_growth_and_cap() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(100)).with_max_delay(Duration::from_millis(300));
+
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(300));
+        assert_eq!(policy.backoff(40), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_backoff_sequence() {
+        let policy = RetryPolicy::new(0, Duration::from_millis(100)).with_max_delay(Duration::from_millis(400)).with_jitter(0.5);
+        let mut backoff = policy.backoffs();
+        for expected in [100, 200, 400, 400] {
+            let delay = backoff.next_delay();
+            let base = Duration::from_millis(expected);
+            assert!(delay >= base / 2 && delay <= base, "{:?} not within jitter of {:?}", delay, base);
+        }
+        assert_eq!(backoff.retries(), 4);
+
+        backoff.reset();
+        assert!(backoff.next_delay() <= Duration::from_millis(100));
+        assert_eq!(policy.with_jitter(0.0).backoffs().next_delay(), Duration::from_millis(100));
+
+        let reconnect = RetryPolicy::reconnect(Duration::from_secs(60));
+        assert_eq!((reconnect.backoff(0), reconnect.backoff(10)), (Duration::from_secs(1), Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_retries_transient_errors_until_success() {
+        let calls = Cell::new(0);
+        let delays = RefCell::new(Vec::new());
+        let policy = RetryPolicy::new(4, Duration::from_millis(100)).with_jitter(0.5).with_seed(1);
+
+        let result = tokio_test::block_on(retry_with_sleep(&policy, no_sleep(&delays), || {
+            calls.set(calls.get() + 1);
+            let n = calls.get();
+            async move {
+                if n < 3 {
+                    Err(AlphaError::network("timeout"))
+                } else {
+                    Ok(n)
+                }
+            }
+        }));
+
+        assert_eq!(result.unwrap(), 3);
+        let delays = delays.into_inner();
+        assert_eq!(delays.len(), 2);
+        assert!(delays[0] >= Duration::from_millis(50) && delays[0] <= Duration::from_millis(100));
+        assert!(delays[1] >= Duration::from_millis(100) && delays[1] <= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_stops_on_permanent_error_and_exhaustion() {
+        let calls = Cell::new(0);
+        let delays = RefCell::new(Vec::new());
+        let policy = RetryPolicy::new(3, Duration::from_millis(10));
+
+        let result: AlphaResult<()> = tokio_test::block_on(retry_with_sleep(&policy, no_sleep(&delays), || {
+            calls.set(calls.get() + 1);
+            async { Err(AlphaError::invalid_input("bad symbol")) }
+        }));
+        assert!(matches!(result.unwrap_err(), AlphaError::InvalidInput(_)));
+        assert_eq!(calls.get(), 1);
+
+        calls.set(0);
+        let result: AlphaResult<()> = tokio_test::block_on(retry_with_sleep(&policy, no_sleep(&delays), || {
+            calls.set(calls.get() + 1);
+            async { Err(AlphaError::RateLimited("429".to_string())) }
+        }));
+        let err = result.unwrap_err();
+        assert_eq!(calls.get(), 3);
+        assert!(matches!(err.root(), AlphaError::RateLimited(_)));
+        assert_eq!(err.to_string(), "gave up after 3 attempts");
+    }
+}
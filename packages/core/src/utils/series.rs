@@ -0,0 +1,303 @@
+//! 时间序列缺失值填充与重采样
+//!
+//! 不规则的逐笔数据需要先对齐到固定周期并补齐缺口，才能输入按固定周期计算的技术指标
+
+use crate::errors::{AlphaError, AlphaResult};
+use crate::models::{IndicatorPoint, MarketData};
+use crate::prelude::*;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 缺失值填充方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FillMethod {
+    /// 保留缺失
+    None,
+    /// 使用前一个有效值
+    Forward,
+    /// 使用后一个有效值
+    Backward,
+    /// 按时间线性插值
+    Linear,
+}
+
+/// 前向填充：缺失值取前一个有效值，序列开头的缺失保持不变
+pub fn forward_fill(values: &[Option<f64>]) -> Vec<Option<f64>> {
+    let mut last = None;
+    values.iter()
+        .map(|v| {
+            if v.is_some() {
+                last = *v;
+            }
+            last
+        })
+        .collect()
+}
+
+/// 后向填充：缺失值取后一个有效值，序列末尾的缺失保持不变
+pub fn backward_fill(values: &[Option<f64>]) -> Vec<Option<f64>> {
+    let mut next = None;
+    let mut filled: Vec<Option<f64>> = values.iter()
+        .rev()
+        .map(|v| {
+            if v.is_some() {
+                next = *v;
+            }
+            next
+        })
+        .collect();
+    filled.reverse();
+    filled
+}
+
+/// 按时间线性插值：两个有效值之间的缺失点按时间距离加权，首尾缺失保持不变
+pub fn interpolate_linear(points: &[IndicatorPoint]) -> Vec<IndicatorPoint> {
+    let mut result = points.to_vec();
+    let mut prev: Option<usize> = None;
+
+    for i in 0..points.len() {
+        if points[i].value.is_none() {
+            continue;
+        }
+
+        if let Some(p) = prev {
+            if i - p > 1 {
+                let (t0, v0) = (points[p].timestamp, points[p].value.unwrap_or_default());
+                let (t1, v1) = (points[i].timestamp, points[i].value.unwrap_or_default());
+                let span = (t1 - t0).num_milliseconds() as f64;
+
+                for point in &mut result[p + 1..i] {
+                    let ratio = if span > 0.0 {
+                        (point.timestamp - t0).num_milliseconds() as f64 / span
+                    } else {
+                        0.0
+                    };
+                    point.value = Some(v0 + (v1 - v0) * ratio);
+                }
+            }
+        }
+
+        prev = Some(i);
+    }
+
+    result
+}
+
+/// 按指定方式填充序列中的缺失值
+pub fn fill(points: &[IndicatorPoint], method: FillMethod) -> Vec<IndicatorPoint> {
+    let apply = |filled: Vec<Option<f64>>| {
+        points.iter()
+            .zip(filled)
+            .map(|(p, value)| IndicatorPoint { timestamp: p.timestamp, value })
+            .collect()
+    };
+
+    match method {
+        FillMethod::None => points.to_vec(),
+        FillMethod::Forward => apply(forward_fill(&values_of(points))),
+        FillMethod::Backward => apply(backward_fill(&values_of(points))),
+        FillMethod::Linear => interpolate_linear(points),
+    }
+}
+
+/// 单次重采样最多产生的区间数，避免跨度很长的稀疏数据配合很小的周期分配过多内存
+pub const MAX_RESAMPLE_BUCKETS: usize = 1_000_000;
+
+/// 重采样到固定周期：每个 `[t, t + step)` 区间取最后一个有效观测值，空区间按 `method` 填充
+///
+/// 区间起点按 `step` 对齐到 Unix 纪元，输入需按时间升序排列；区间数超过 [`MAX_RESAMPLE_BUCKETS`] 时返回错误
+pub fn resample(points: &[IndicatorPoint], step: Duration, method: FillMethod) -> AlphaResult<Vec<IndicatorPoint>> {
+    let step_ms = step.num_milliseconds();
+    if step_ms <= 0 {
+        return Err(AlphaError::invalid_input("Resample step must be positive"));
+    }
+    if points.windows(2).any(|w| w[0].timestamp > w[1].timestamp) {
+        return Err(AlphaError::invalid_input("Series must be sorted by timestamp"));
+    }
+
+    let (first, last) = match (points.first(), points.last()) {
+        (Some(first), Some(last)) => (first.timestamp, last.timestamp),
+        _ => return Ok(Vec::new()),
+    };
+
+    let start = bucket_start(first, step_ms);
+    let spans = (bucket_start(last, step_ms) - start).num_milliseconds() / step_ms;
+    let bucket_count = usize::try_from(spans)
+        .ok()
+        .filter(|&spans| spans < MAX_RESAMPLE_BUCKETS)
+        .map(|spans| spans + 1)
+        .ok_or_else(|| AlphaError::invalid_input(format!("Resampling would produce more than {} buckets", MAX_RESAMPLE_BUCKETS)))?;
+
+    let mut buckets: Vec<IndicatorPoint> = (0..bucket_count)
+        .map(|i| IndicatorPoint {
+            timestamp: start + Duration::milliseconds(step_ms * i as i64),
+            value: None,
+        })
+        .collect();
+
+    for point in points {
+        if let Some(value) = point.value {
+            let index = ((point.timestamp - start).num_milliseconds() / step_ms) as usize;
+            buckets[index].value = Some(value);
+        }
+    }
+
+    Ok(fill(&buckets, method))
+}
+
+/// 解析 K 线周期，如 `30s`、`1m`、`5m`、`1h`、`1d`
+pub fn parse_timeframe(timeframe: &str) -> AlphaResult<Duration> {
+    let timeframe = timeframe.trim();
+    let split = timeframe.find(|c: char| !c.is_ascii_digit()).unwrap_or(timeframe.len());
+    let (count, unit) = timeframe.split_at(split);
+    let count: i64 = count.parse()
+        .ok()
+        .filter(|&n| n > 0)
+        .ok_or_else(|| AlphaError::invalid_input(format!("Invalid timeframe: {}", timeframe)))?;
+
+    // 周期来自用户输入，超出 Duration 范围时返回错误而不是 panic
+    let duration = match unit {
+        "s" => Duration::try_seconds(count),
+        "m" => Duration::try_minutes(count),
+        "h" => Duration::try_hours(count),
+        "d" => Duration::try_days(count),
+        "w" => Duration::try_weeks(count),
+        _ => None,
+    };
+    duration.ok_or_else(|| AlphaError::invalid_input(format!("Invalid timeframe: {}", timeframe)))
+}
+
+/// 将逐笔数据聚合为固定周期 K 线，区间划分与 [`resample`] 一致，无成交的区间不生成 K 线
+///
+/// 输入本身带开高低价时 (如由 1m 聚合为 5m) 一并参与计算，需按时间升序排列
+pub fn aggregate_candles(ticks: &[MarketData], step: Duration) -> AlphaResult<Vec<MarketData>> {
+    let step_ms = step.num_milliseconds();
+    if step_ms <= 0 {
+        return Err(AlphaError::invalid_input("Candle timeframe must be positive"));
+    }
+    if ticks.windows(2).any(|w| w[0].timestamp > w[1].timestamp) {
+        return Err(AlphaError::invalid_input("Ticks must be sorted by timestamp"));
+    }
+
+    let mut candles: Vec<MarketData> = Vec::new();
+    for tick in ticks.iter().filter(|t| t.price.is_finite()) {
+        let start = bucket_start(tick.timestamp, step_ms);
+        let high = tick.high.unwrap_or(tick.price);
+        let low = tick.low.unwrap_or(tick.price);
+
+        match candles.last_mut() {
+            Some(candle) if candle.timestamp == start => {
+                candle.high = candle.high.map(|h| h.max(high));
+                candle.low = candle.low.map(|l| l.min(low));
+                candle.price = tick.price;
+                candle.volume = candle.volume.saturating_add(tick.volume);
+            }
+            _ => candles.push(MarketData::with_ohlcv(
+                tick.symbol.clone(),
+                start,
+                tick.open.unwrap_or(tick.price),
+                high,
+                low,
+                tick.price,
+                tick.volume,
+            )),
+        }
+    }
+
+    Ok(candles)
+}
+
+/// 将市场数据转换为价格序列
+pub fn prices_of(data: &[MarketData]) -> Vec<IndicatorPoint> {
+    data.iter()
+        .map(|d| IndicatorPoint {
+            timestamp: d.timestamp,
+            value: Some(d.price).filter(|p| p.is_finite()),
+        })
+        .collect()
+}
+
+fn values_of(points: &[IndicatorPoint]) -> Vec<Option<f64>> {
+    points.iter().map(|p| p.value).collect()
+}
+
+fn bucket_start(timestamp: DateTime<Utc>, step_ms: i64) -> DateTime<Utc> {
+    let ms = timestamp.timestamp_millis();
+    let floored = ms - ms.rem_euclid(step_ms);
+    DateTime::from_timestamp_millis(floored).unwrap_or(timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn point(minute: i64, value: Option<f64>) -> IndicatorPoint {
+        IndicatorPoint {
+            timestamp: Utc.with_ymd_and_hms(2025, 1, 2, 9, 30, 0).unwrap() + Duration::minutes(minute),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_forward_and_backward_fill() {
+        let values = [None, Some(1.0), None, Some(3.0), None];
+
+        assert_eq!(forward_fill(&values), vec![None, Some(1.0), Some(1.0), Some(3.0), Some(3.0)]);
+        assert_eq!(backward_fill(&values), vec![Some(1.0), Some(1.0), Some(3.0), Some(3.0), None]);
+    }
+
+    #[test]
+    fn test_interpolate_linear_uses_time_distance() {
+        let points = [point(0, Some(10.0)), point(1, None), point(4, Some(20.0)), point(5, None)];
+        let filled = interpolate_linear(&points);
+
+        assert_eq!(filled[1].value, Some(12.5));
+        assert_eq!(filled[3].value, None);
+    }
+
+    #[test]
+    fn test_resample_with_fill() {
+        let points = [point(0, Some(1.0)), point(0, Some(2.0)), point(3, Some(5.0))];
+        let resampled = resample(&points, Duration::minutes(1), FillMethod::Forward).unwrap();
+
+        assert_eq!(resampled.len(), 4);
+        assert_eq!(resampled.iter().map(|p| p.value).collect::<Vec<_>>(), vec![Some(2.0), Some(2.0), Some(2.0), Some(5.0)]);
+        assert_eq!(resampled[1].timestamp, point(1, None).timestamp);
+
+        assert!(resample(&points, Duration::zero(), FillMethod::None).is_err());
+        assert!(resample(&[point(1, Some(1.0)), point(0, Some(1.0))], Duration::minutes(1), FillMethod::None).is_err());
+
+        // 跨度过长时拒绝，而不是分配海量区间
+        let sparse = [point(0, Some(1.0)), point(60 * 24 * 365 * 100, Some(2.0))];
+        let err = resample(&sparse, Duration::milliseconds(1), FillMethod::None).unwrap_err();
+        assert!(matches!(err, AlphaError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn test_aggregate_candles() {
+        let tick = |second: i64, price: f64, volume: u64| {
+            let mut data = MarketData::new("AAPL".to_string(), price, volume);
+            data.timestamp = point(0, None).timestamp + Duration::seconds(second);
+            data
+        };
+        let ticks = [tick(0, 10.0, 1), tick(20, 12.0, 2), tick(50, 9.0, 3), tick(130, 11.0, 4)];
+        let candles = aggregate_candles(&ticks, parse_timeframe("1m").unwrap()).unwrap();
+
+        assert_eq!(candles.len(), 2);
+        assert_eq!((candles[0].open, candles[0].high, candles[0].low), (Some(10.0), Some(12.0), Some(9.0)));
+        assert_eq!((candles[0].price, candles[0].volume), (9.0, 6));
+        assert_eq!(candles[1].timestamp, point(2, None).timestamp);
+
+        // 再聚合到更大周期与直接聚合一致
+        let five = aggregate_candles(&candles, Duration::minutes(5)).unwrap();
+        assert_eq!(five, aggregate_candles(&ticks, Duration::minutes(5)).unwrap());
+
+        assert_eq!(parse_timeframe("15m").unwrap(), Duration::minutes(15));
+        assert!(parse_timeframe("0m").is_err() && parse_timeframe("5x").is_err() && parse_timeframe("m").is_err());
+        // 超出 Duration 范围的数量
+        assert!(matches!(parse_timeframe("99999999999999w"), Err(AlphaError::InvalidInput(_))));
+        assert!(parse_timeframe("9223372036854775807s").is_err());
+    }
+}
@@ -0,0 +1,222 @@
+//! 证券代码解析与规范化
+//!
+//! 不同数据源对同一标的的写法不一 (`BRK.B` / `BRK-B`、`700.HK` / `0700.HK`、`BTC/USDT` / `BTC-USDT`)，
+//! 统一解析为 [`Symbol`] 后再以规范形式作为内部主键
+
+use crate::errors::{AlphaError, AlphaResult};
+use crate::models::Exchange;
+use crate::prelude::*;
+use core::fmt;
+use core::str::FromStr;
+use serde::{Deserialize, Serialize};
+
+/// 常见加密货币计价币种，用于区分 `BTC-USDT` 与 `BRK-B`
+const CRYPTO_QUOTES: [&str; 10] = ["USDT", "USDC", "BUSD", "FDUSD", "USD", "EUR", "BTC", "ETH", "BNB", "DAI"];
+
+/// 资产类别
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetClass {
+    /// 股票 / ETF
+    Equity,
+    /// 加密货币交易对
+    Crypto,
+}
+
+/// 解析后的证券代码
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Symbol {
+    /// 证券代码 (加密货币为基础币种)
+    pub ticker: String,
+    /// 计价币种 (仅加密货币)
+    pub quote: Option<String>,
+    /// 所属交易所，美股未指明时为 `None`
+    pub exchange: Option<Exchange>,
+    /// 资产类别
+    pub asset_class: AssetClass,
+}
+
+impl Symbol {
+    /// 解析任意常见写法的代码
+    pub fn parse(input: &str) -> AlphaResult<Self> {
+        let raw = input.trim().to_ascii_uppercase();
+        if raw.is_empty() || raw.len() > 24 {
+            return Err(AlphaError::invalid_input(format!("Invalid symbol length: '{}'", input)));
+        }
+
+        let symbol = match raw.split_once(':') {
+            // 交易所前缀写法，如 NASDAQ:AAPL、HKEX:700
+            Some((code, ticker)) => {
+                let exchange = Exchange::from_code(code)
+                    .ok_or_else(|| AlphaError::invalid_input(format!("Unknown exchange prefix: '{}'", code)))?;
+                Self::equity(ticker, Some(exchange))
+            }
+            None => Self::parse_unprefixed(&raw),
+        };
+
+        let symbol = symbol.map_err(|e| e.context(format!("parse symbol '{}'", input)))?;
+        symbol.validate()?;
+        Ok(symbol)
+    }
+
+    fn parse_unprefixed(raw: &str) -> AlphaResult<Self> {
+        if let Some(pos) = raw.rfind(['-', '/', '_']) {
+            let (base, quote) = (&raw[..pos], &raw[pos + 1..]);
+            // 单字母后缀为美股股票类别 (BRK-B、BRK/B)
+            if quote.len() == 1 {
+                return Self::equity(&format!("{}.{}", base, quote), None);
+            }
+            if raw.as_bytes()[pos] == b'/' || CRYPTO_QUOTES.contains(&quote) {
+                return Ok(Self::crypto(base, quote));
+            }
+            return Err(AlphaError::invalid_input(format!("Unknown quote currency '{}'", quote)));
+        }
+
+        if let Some((ticker, suffix)) = raw.rsplit_once('.') {
+            if let Some(exchange) = Exchange::from_suffix(suffix) {
+                return Self::equity(ticker, Some(exchange));
+            }
+            // 单字母后缀为股票类别 (BRK.B)
+            if suffix.len() == 1 {
+                return Self::equity(raw, None);
+            }
+            return Err(AlphaError::invalid_input(format!("Unknown exchange suffix '{}'", suffix)));
+        }
+
+        // 六位纯数字为 A 股代码，按首位判断上交所 / 深交所
+        if raw.len() == 6 && raw.bytes().all(|b| b.is_ascii_digit()) {
+            let exchange = if matches!(raw.as_bytes()[0], b'5' | b'6' | b'9') {
+                Exchange::Sse
+            } else {
+                Exchange::Szse
+            };
+            return Self::equity(raw, Some(exchange));
+        }
+
+        Self::equity(raw, None)
+    }
+
+    fn equity(ticker: &str, exchange: Option<Exchange>) -> AlphaResult<Self> {
+        let ticker = match exchange {
+            // 港股统一补足四位 (700 -> 0700)
+            Some(Exchange::Hkex) if ticker.bytes().all(|b| b.is_ascii_digit()) && !ticker.is_empty() => {
+                let trimmed = ticker.trim_start_matches('0');
+                format!("{:0>4}", if trimmed.is_empty() { "0" } else { trimmed })
+            }
+            _ => ticker.replace(['-', '/'], "."),
+        };
+
+        Ok(Self {
+            ticker,
+            quote: None,
+            exchange,
+            asset_class: AssetClass::Equity,
+        })
+    }
+
+    fn crypto(base: &str, quote: &str) -> Self {
+        Self {
+            ticker: base.to_string(),
+            quote: Some(quote.to_string()),
+            exchange: None,
+            asset_class: AssetClass::Crypto,
+        }
+    }
+
+    /// 按资产类别和交易所校验代码格式
+    pub fn validate(&self) -> AlphaResult<()> {
+        let ticker = self.ticker.as_str();
+        let is_digits = |s: &str, min: usize, max: usize| {
+            (min..=max).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_digit())
+        };
+        let is_alnum = |s: &str, min: usize, max: usize| {
+            (min..=max).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_alphanumeric())
+        };
+        let is_share_class = |s: &str, root_max: usize| match s.split_once('.') {
+            Some((root, class)) => is_alnum(root, 1, root_max) && is_alnum(class, 1, 2),
+            None => is_alnum(s, 1, root_max),
+        };
+
+        let valid = match (self.asset_class, self.exchange) {
+            (AssetClass::Crypto, _) => {
+                is_alnum(ticker, 2, 10) && self.quote.as_deref().is_some_and(|q| is_alnum(q, 2, 6))
+            }
+            (AssetClass::Equity, Some(Exchange::Hkex)) => is_digits(ticker, 4, 5),
+            (AssetClass::Equity, Some(Exchange::Sse | Exchange::Szse)) => is_digits(ticker, 6, 6),
+            (AssetClass::Equity, Some(Exchange::Lse)) => is_share_class(ticker, 5),
+            (AssetClass::Equity, _) => {
+                is_share_class(ticker, 5) && ticker.bytes().next().is_some_and(|b| b.is_ascii_alphabetic())
+            }
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(AlphaError::invalid_input(format!("Invalid {:?} symbol: '{}'", self.asset_class, self.canonical())))
+        }
+    }
+
+    /// 规范形式：美股 `BRK.B`，其他市场 `0700.HK`，加密货币 `BTC-USDT`
+    pub fn canonical(&self) -> String {
+        match (&self.quote, self.exchange.and_then(|e| e.suffix())) {
+            (Some(quote), _) => format!("{}-{}", self.ticker, quote),
+            (None, Some(suffix)) => format!("{}.{}", self.ticker, suffix),
+            (None, None) => self.ticker.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.canonical())
+    }
+}
+
+impl FromStr for Symbol {
+    type Err = AlphaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// 将代码规范化为内部统一形式
+pub fn normalize_symbol(input: &str) -> AlphaResult<String> {
+    Symbol::parse(input).map(|s| s.canonical())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_equities() {
+        assert_eq!(normalize_symbol("aapl").unwrap(), "AAPL");
+        assert_eq!(normalize_symbol("BRK-B").unwrap(), "BRK.B");
+        assert_eq!(normalize_symbol("brk.b").unwrap(), "BRK.B");
+        assert_eq!(normalize_symbol("700.HK").unwrap(), "0700.HK");
+        assert_eq!(normalize_symbol("HKEX:00700").unwrap(), "0700.HK");
+        assert_eq!(normalize_symbol("600519.SH").unwrap(), "600519.SS");
+        assert_eq!(normalize_symbol("000001").unwrap(), "000001.SZ");
+        assert_eq!(normalize_symbol("VOD.LN").unwrap(), "VOD.L");
+        assert_eq!(normalize_symbol("NASDAQ:MSFT").unwrap(), "MSFT");
+    }
+
+    #[test]
+    fn test_normalize_crypto() {
+        let symbol: Symbol = "btc/usdt".parse().unwrap();
+        assert_eq!(symbol.asset_class, AssetClass::Crypto);
+        assert_eq!(symbol.to_string(), "BTC-USDT");
+        assert_eq!(normalize_symbol("ETH_BTC").unwrap(), "ETH-BTC");
+    }
+
+    #[test]
+    fn test_rejects_invalid_symbols() {
+        assert!(normalize_symbol("").is_err());
+        assert!(normalize_symbol("TOO_LONG_SYMBOL_12345").is_err());
+        assert!(normalize_symbol("AAPL.XX").is_err());
+        assert!(normalize_symbol("ABC.HK").is_err());
+        assert!(normalize_symbol("12345.SS").is_err());
+        assert!(normalize_symbol("FOO:AAPL").is_err());
+    }
+}
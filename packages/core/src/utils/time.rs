@@ -0,0 +1,48 @@
+//! 时间工具函数
+
+pub mod calendar;
+pub mod session;
+
+use crate::errors::AlphaResult;
+use crate::models::Exchange;
+use chrono::{DateTime, Utc};
+
+pub use calendar::TradingCalendar;
+#[cfg(feature = "std")]
+pub use session::current_session;
+pub use session::{session_at, MarketSession};
+
+/// 获取当前时间戳 (毫秒)
+#[cfg(feature = "std")]
+pub fn current_timestamp_ms() -> i64 {
+    Utc::now().timestamp_millis()
+}
+
+/// 时间戳转换为 DateTime
+pub fn timestamp_to_datetime(timestamp: i64) -> AlphaResult<DateTime<Utc>> {
+    DateTime::from_timestamp_millis(timestamp)
+        .ok_or_else(|| crate::errors::AlphaError::invalid_input("Invalid timestamp"))
+}
+
+/// 判断指定时刻是否处于交易所常规交易时段 (按交易所当地时间，自动处理夏令时、午休和节假日)
+pub fn is_trading_time(exchange: Exchange, dt: &DateTime<Utc>) -> bool {
+    session_at(exchange, dt) == MarketSession::Regular
+}
+
+/// 获取下一个交易日 (按纽交所日历跳过周末和节假日，保留原时刻)
+pub fn next_trading_day(dt: &DateTime<Utc>) -> DateTime<Utc> {
+    let calendar = TradingCalendar::new(Exchange::Nyse);
+    let next_date = calendar.next_trading_day(dt.date_naive());
+
+    next_date.and_time(dt.time()).and_utc()
+}
+
+/// 判断指定交易所在某日是否开市
+pub fn is_trading_day(exchange: Exchange, date: chrono::NaiveDate) -> bool {
+    TradingCalendar::new(exchange).is_trading_day(date)
+}
+
+/// 统计指定交易所在 `[start, end)` 区间内的交易日数量
+pub fn trading_days_between(exchange: Exchange, start: chrono::NaiveDate, end: chrono::NaiveDate) -> usize {
+    TradingCalendar::new(exchange).trading_days_between(start, end)
+}
@@ -0,0 +1,218 @@
+//! 交易所交易日历
+//!
+//! 节假日和半日市数据以静态表维护 (按日期升序)，超出数据覆盖年份时仅按周末判断
+
+use crate::models::Exchange;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+/// 日期表项 (年, 月, 日)
+type DateEntry = (i32, u32, u32);
+
+/// 节假日数据覆盖的年份范围
+pub const FIRST_COVERED_YEAR: i32 = 2024;
+pub const LAST_COVERED_YEAR: i32 = 2026;
+
+/// 美股 (NYSE / NASDAQ) 全日休市
+const US_HOLIDAYS: &[DateEntry] = &[
+    (2024, 1, 1), (2024, 1, 15), (2024, 2, 19), (2024, 3, 29), (2024, 5, 27),
+    (2024, 6, 19), (2024, 7, 4), (2024, 9, 2), (2024, 11, 28), (2024, 12, 25),
+    (2025, 1, 1), (2025, 1, 9), (2025, 1, 20), (2025, 2, 17), (2025, 4, 18),
+    (2025, 5, 26), (2025, 6, 19), (2025, 7, 4), (2025, 9, 1), (2025, 11, 27),
+    (2025, 12, 25),
+    (2026, 1, 1), (2026, 1, 19), (2026, 2, 16), (2026, 4, 3), (2026, 5, 25),
+    (2026, 6, 19), (2026, 7, 3), (2026, 9, 7), (2026, 11, 26), (2026, 12, 25),
+];
+
+/// 美股半日市 (13:00 收市)
+const US_HALF_DAYS: &[DateEntry] = &[
+    (2024, 7, 3), (2024, 11, 29), (2024, 12, 24),
+    (2025, 7, 3), (2025, 11, 28), (2025, 12, 24),
+    (2026, 11, 27), (2026, 12, 24),
+];
+
+/// 伦敦证券交易所全日休市
+const LSE_HOLIDAYS: &[DateEntry] = &[
+    (2024, 1, 1), (2024, 3, 29), (2024, 4, 1), (2024, 5, 6), (2024, 5, 27),
+    (2024, 8, 26), (2024, 12, 25), (2024, 12, 26),
+    (2025, 1, 1), (2025, 4, 18), (2025, 4, 21), (2025, 5, 5), (2025, 5, 26),
+    (2025, 8, 25), (2025, 12, 25), (2025, 12, 26),
+    (2026, 1, 1), (2026, 4, 3), (2026, 4, 6), (2026, 5, 4), (2026, 5, 25),
+    (2026, 8, 31), (2026, 12, 25), (2026, 12, 28),
+];
+
+/// 伦敦证券交易所半日市 (12:30 收市)
+const LSE_HALF_DAYS: &[DateEntry] = &[
+    (2024, 12, 24), (2024, 12, 31),
+    (2025, 12, 24), (2025, 12, 31),
+    (2026, 12, 24), (2026, 12, 31),
+];
+
+/// 香港交易所全日休市
+const HKEX_HOLIDAYS: &[DateEntry] = &[
+    (2024, 1, 1), (2024, 2, 12), (2024, 2, 13), (2024, 3, 29), (2024, 4, 1),
+    (2024, 4, 4), (2024, 5, 1), (2024, 5, 15), (2024, 6, 10), (2024, 7, 1),
+    (2024, 9, 18), (2024, 10, 1), (2024, 10, 11), (2024, 12, 25), (2024, 12, 26),
+    (2025, 1, 1), (2025, 1, 29), (2025, 1, 30), (2025, 1, 31), (2025, 4, 4),
+    (2025, 4, 18), (2025, 4, 21), (2025, 5, 1), (2025, 5, 5), (2025, 7, 1),
+    (2025, 10, 1), (2025, 10, 7), (2025, 10, 29), (2025, 12, 25), (2025, 12, 26),
+    (2026, 1, 1), (2026, 2, 17), (2026, 2, 18), (2026, 2, 19), (2026, 4, 3),
+    (2026, 4, 6), (2026, 4, 7), (2026, 5, 1), (2026, 5, 25), (2026, 6, 19),
+    (2026, 7, 1), (2026, 10, 1), (2026, 10, 19), (2026, 12, 25),
+];
+
+/// 香港交易所半日市 (仅上午交易)
+const HKEX_HALF_DAYS: &[DateEntry] = &[
+    (2024, 2, 9), (2024, 12, 24), (2024, 12, 31),
+    (2025, 1, 28), (2025, 12, 24), (2025, 12, 31),
+    (2026, 2, 16), (2026, 12, 24), (2026, 12, 31),
+];
+
+/// 沪深交易所全日休市 (调休补班的周末同样不开市，按周末规则处理)
+const CN_HOLIDAYS: &[DateEntry] = &[
+    (2024, 1, 1), (2024, 2, 9), (2024, 2, 12), (2024, 2, 13), (2024, 2, 14),
+    (2024, 2, 15), (2024, 2, 16), (2024, 4, 4), (2024, 4, 5), (2024, 5, 1),
+    (2024, 5, 2), (2024, 5, 3), (2024, 6, 10), (2024, 9, 16), (2024, 9, 17),
+    (2024, 10, 1), (2024, 10, 2), (2024, 10, 3), (2024, 10, 4), (2024, 10, 7),
+    (2025, 1, 1), (2025, 1, 28), (2025, 1, 29), (2025, 1, 30), (2025, 1, 31),
+    (2025, 2, 3), (2025, 2, 4), (2025, 4, 4), (2025, 5, 1), (2025, 5, 2),
+    (2025, 5, 5), (2025, 6, 2), (2025, 10, 1), (2025, 10, 2), (2025, 10, 3),
+    (2025, 10, 6), (2025, 10, 7), (2025, 10, 8),
+    (2026, 1, 1), (2026, 1, 2), (2026, 2, 16), (2026, 2, 17), (2026, 2, 18),
+    (2026, 2, 19), (2026, 2, 20), (2026, 2, 23), (2026, 4, 6), (2026, 5, 1),
+    (2026, 5, 4), (2026, 5, 5), (2026, 6, 19), (2026, 9, 25), (2026, 10, 1),
+    (2026, 10, 2), (2026, 10, 5), (2026, 10, 6), (2026, 10, 7),
+];
+
+/// 沪深交易所没有半日市
+const CN_HALF_DAYS: &[DateEntry] = &[];
+
+/// 交易所交易日历
+#[derive(Debug, Clone, Copy)]
+pub struct TradingCalendar {
+    exchange: Exchange,
+    holidays: &'static [DateEntry],
+    half_days: &'static [DateEntry],
+}
+
+impl TradingCalendar {
+    /// 创建指定交易所的日历
+    pub fn new(exchange: Exchange) -> Self {
+        let (holidays, half_days) = match exchange {
+            Exchange::Nyse | Exchange::Nasdaq => (US_HOLIDAYS, US_HALF_DAYS),
+            Exchange::Lse => (LSE_HOLIDAYS, LSE_HALF_DAYS),
+            Exchange::Hkex => (HKEX_HOLIDAYS, HKEX_HALF_DAYS),
+            Exchange::Sse | Exchange::Szse => (CN_HOLIDAYS, CN_HALF_DAYS),
+        };
+
+        Self {
+            exchange,
+            holidays,
+            half_days,
+        }
+    }
+
+    pub fn exchange(&self) -> Exchange {
+        self.exchange
+    }
+
+    /// 节假日数据是否覆盖该日期所在年份
+    pub fn covers(&self, date: NaiveDate) -> bool {
+        (FIRST_COVERED_YEAR..=LAST_COVERED_YEAR).contains(&date.year())
+    }
+
+    /// 是否为周末
+    pub fn is_weekend(date: NaiveDate) -> bool {
+        matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+    }
+
+    /// 是否为交易所节假日 (不含周末)
+    pub fn is_holiday(&self, date: NaiveDate) -> bool {
+        contains(self.holidays, date)
+    }
+
+    /// 是否为半日市
+    pub fn is_half_day(&self, date: NaiveDate) -> bool {
+        self.is_trading_day(date) && contains(self.half_days, date)
+    }
+
+    /// 是否为交易日
+    pub fn is_trading_day(&self, date: NaiveDate) -> bool {
+        !Self::is_weekend(date) && !self.is_holiday(date)
+    }
+
+    /// 下一个交易日 (不含当日)
+    pub fn next_trading_day(&self, date: NaiveDate) -> NaiveDate {
+        let mut next = date + Duration::days(1);
+        while !self.is_trading_day(next) {
+            next += Duration::days(1);
+        }
+        next
+    }
+
+    /// 上一个交易日 (不含当日)
+    pub fn previous_trading_day(&self, date: NaiveDate) -> NaiveDate {
+        let mut previous = date - Duration::days(1);
+        while !self.is_trading_day(previous) {
+            previous -= Duration::days(1);
+        }
+        previous
+    }
+
+    /// 统计 `[start, end)` 区间内的交易日数量，`end <= start` 时为 0
+    pub fn trading_days_between(&self, start: NaiveDate, end: NaiveDate) -> usize {
+        start.iter_days()
+            .take_while(|d| *d < end)
+            .filter(|d| self.is_trading_day(*d))
+            .count()
+    }
+}
+
+fn contains(table: &[DateEntry], date: NaiveDate) -> bool {
+    table.binary_search(&(date.year(), date.month(), date.day())).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_tables_sorted() {
+        for table in [US_HOLIDAYS, US_HALF_DAYS, LSE_HOLIDAYS, LSE_HALF_DAYS, HKEX_HOLIDAYS, HKEX_HALF_DAYS, CN_HOLIDAYS] {
+            assert!(table.windows(2).all(|w| w[0] < w[1]));
+            assert!(table.iter().all(|&(y, m, d)| NaiveDate::from_ymd_opt(y, m, d).is_some()));
+        }
+    }
+
+    #[test]
+    fn test_us_calendar() {
+        let nyse = TradingCalendar::new(Exchange::Nyse);
+
+        assert!(!nyse.is_trading_day(date(2025, 7, 4)));
+        assert!(nyse.is_half_day(date(2025, 7, 3)));
+        assert!(!nyse.is_trading_day(date(2025, 7, 5)));
+        // 周四感恩节 -> 周五半日市
+        assert_eq!(nyse.next_trading_day(date(2025, 11, 26)), date(2025, 11, 28));
+        assert_eq!(nyse.previous_trading_day(date(2025, 12, 26)), date(2025, 12, 24));
+    }
+
+    #[test]
+    fn test_cn_calendar() {
+        let sse = TradingCalendar::new(Exchange::Sse);
+
+        // 2025 国庆中秋假期: 10月1日至8日休市
+        assert_eq!(sse.next_trading_day(date(2025, 9, 30)), date(2025, 10, 9));
+        assert_eq!(sse.trading_days_between(date(2025, 9, 29), date(2025, 10, 13)), 4);
+        assert!(!sse.is_half_day(date(2025, 12, 31)));
+    }
+
+    #[test]
+    fn test_trading_days_between_empty_range() {
+        let lse = TradingCalendar::new(Exchange::Lse);
+        assert_eq!(lse.trading_days_between(date(2025, 1, 10), date(2025, 1, 10)), 0);
+        assert_eq!(lse.trading_days_between(date(2025, 1, 10), date(2025, 1, 1)), 0);
+    }
+}
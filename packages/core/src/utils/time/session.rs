@@ -0,0 +1,203 @@
+//! 交易时段判断
+//!
+//! 所有判断都先把 UTC 时间换算为交易所当地时间 (由 IANA 时区处理夏令时)，再对照当地的交易时段
+
+use super::calendar::TradingCalendar;
+use crate::models::Exchange;
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/// 交易时段
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketSession {
+    /// 盘前 (含集合竞价)
+    PreMarket,
+    /// 常规交易时段
+    Regular,
+    /// 盘后
+    AfterHours,
+    /// 休市 (含午休、周末和节假日)
+    Closed,
+}
+
+/// 交易所当地时间的时段定义
+#[derive(Debug, Clone, Copy)]
+pub struct SessionHours {
+    /// 交易所时区
+    pub timezone: Tz,
+    /// 盘前开始时间
+    pub pre_market_open: Option<NaiveTime>,
+    /// 常规时段开盘
+    pub open: NaiveTime,
+    /// 常规时段收盘
+    pub close: NaiveTime,
+    /// 盘后结束时间
+    pub after_hours_close: Option<NaiveTime>,
+    /// 午休 (开始, 结束)
+    pub lunch_break: Option<(NaiveTime, NaiveTime)>,
+    /// 半日市收盘时间
+    pub half_day_close: NaiveTime,
+}
+
+fn hm(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).expect("valid session time")
+}
+
+impl SessionHours {
+    /// 获取交易所的时段定义
+    pub fn for_exchange(exchange: Exchange) -> Self {
+        match exchange {
+            Exchange::Nyse | Exchange::Nasdaq => Self {
+                timezone: chrono_tz::America::New_York,
+                pre_market_open: Some(hm(4, 0)),
+                open: hm(9, 30),
+                close: hm(16, 0),
+                after_hours_close: Some(hm(20, 0)),
+                lunch_break: None,
+                half_day_close: hm(13, 0),
+            },
+            Exchange::Lse => Self {
+                timezone: chrono_tz::Europe::London,
+                pre_market_open: Some(hm(7, 50)),
+                open: hm(8, 0),
+                close: hm(16, 30),
+                after_hours_close: Some(hm(16, 35)),
+                lunch_break: None,
+                half_day_close: hm(12, 30),
+            },
+            Exchange::Hkex => Self {
+                timezone: chrono_tz::Asia::Hong_Kong,
+                pre_market_open: Some(hm(9, 0)),
+                open: hm(9, 30),
+                close: hm(16, 0),
+                after_hours_close: Some(hm(16, 10)),
+                lunch_break: Some((hm(12, 0), hm(13, 0))),
+                half_day_close: hm(12, 0),
+            },
+            Exchange::Sse | Exchange::Szse => Self {
+                timezone: chrono_tz::Asia::Shanghai,
+                pre_market_open: Some(hm(9, 15)),
+                open: hm(9, 30),
+                close: hm(15, 0),
+                after_hours_close: None,
+                lunch_break: Some((hm(11, 30), hm(13, 0))),
+                half_day_close: hm(15, 0),
+            },
+        }
+    }
+}
+
+/// 交易所所在时区
+pub fn exchange_timezone(exchange: Exchange) -> Tz {
+    SessionHours::for_exchange(exchange).timezone
+}
+
+/// 判断指定 UTC 时刻所处的交易时段
+pub fn session_at(exchange: Exchange, dt: &DateTime<Utc>) -> MarketSession {
+    let hours = SessionHours::for_exchange(exchange);
+    let calendar = TradingCalendar::new(exchange);
+
+    let local = dt.with_timezone(&hours.timezone);
+    let date = local.date_naive();
+    let time = local.time();
+
+    if !calendar.is_trading_day(date) {
+        return MarketSession::Closed;
+    }
+
+    // 半日市提前收盘，盘后时段相应前移
+    let close = if calendar.is_half_day(date) { hours.half_day_close } else { hours.close };
+    let after_hours_close = hours.after_hours_close
+        .map(|after| close + (after - hours.close));
+
+    if time >= hours.open && time < close {
+        let in_lunch = hours.lunch_break
+            .map(|(start, end)| time >= start && time < end)
+            .unwrap_or(false);
+        return if in_lunch { MarketSession::Closed } else { MarketSession::Regular };
+    }
+
+    if let Some(pre_open) = hours.pre_market_open {
+        if time >= pre_open && time < hours.open {
+            return MarketSession::PreMarket;
+        }
+    }
+
+    if let Some(after_close) = after_hours_close {
+        if time >= close && time < after_close {
+            return MarketSession::AfterHours;
+        }
+    }
+
+    MarketSession::Closed
+}
+
+/// 当前所处的交易时段
+#[cfg(feature = "std")]
+pub fn current_session(exchange: Exchange) -> MarketSession {
+    session_at(exchange, &Utc::now())
+}
+
+/// 下一次常规时段开盘的 UTC 时间 (逐日查找，最多向后 14 天)
+pub fn next_regular_open(exchange: Exchange, dt: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let hours = SessionHours::for_exchange(exchange);
+    let calendar = TradingCalendar::new(exchange);
+    let local_date = dt.with_timezone(&hours.timezone).date_naive();
+
+    (0..14)
+        .map(|offset| local_date + Duration::days(offset))
+        .filter(|date| calendar.is_trading_day(*date))
+        .filter_map(|date| {
+            date.and_time(hours.open)
+                .and_local_timezone(hours.timezone)
+                .earliest()
+                .map(|open| open.with_timezone(&Utc))
+        })
+        .find(|open| open > dt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn test_us_sessions_across_dst() {
+        // 冬令时 (EST, UTC-5): 14:30 UTC 开盘
+        assert_eq!(session_at(Exchange::Nyse, &utc(2025, 1, 15, 14, 29)), MarketSession::PreMarket);
+        assert_eq!(session_at(Exchange::Nyse, &utc(2025, 1, 15, 14, 30)), MarketSession::Regular);
+        // 夏令时 (EDT, UTC-4): 13:30 UTC 开盘
+        assert_eq!(session_at(Exchange::Nyse, &utc(2025, 7, 15, 13, 30)), MarketSession::Regular);
+        assert_eq!(session_at(Exchange::Nyse, &utc(2025, 7, 15, 20, 30)), MarketSession::AfterHours);
+        assert_eq!(session_at(Exchange::Nyse, &utc(2025, 7, 16, 0, 30)), MarketSession::Closed);
+    }
+
+    #[test]
+    fn test_half_day_and_holiday() {
+        // 2025-11-28 半日市 13:00 EST 收盘
+        assert_eq!(session_at(Exchange::Nasdaq, &utc(2025, 11, 28, 17, 30)), MarketSession::Regular);
+        assert_eq!(session_at(Exchange::Nasdaq, &utc(2025, 11, 28, 18, 30)), MarketSession::AfterHours);
+        assert_eq!(session_at(Exchange::Nasdaq, &utc(2025, 11, 27, 15, 0)), MarketSession::Closed);
+    }
+
+    #[test]
+    fn test_lunch_break() {
+        // 上交所 11:30-13:00 午休 (UTC+8)
+        assert_eq!(session_at(Exchange::Sse, &utc(2025, 3, 3, 2, 0)), MarketSession::Regular);
+        assert_eq!(session_at(Exchange::Sse, &utc(2025, 3, 3, 4, 0)), MarketSession::Closed);
+        assert_eq!(session_at(Exchange::Sse, &utc(2025, 3, 3, 1, 20)), MarketSession::PreMarket);
+    }
+
+    #[test]
+    fn test_next_regular_open() {
+        // 周五收盘后 -> 下周一 08:00 London (冬令时即 08:00 UTC)
+        let open = next_regular_open(Exchange::Lse, &utc(2025, 1, 17, 17, 0)).unwrap();
+        assert_eq!(open, utc(2025, 1, 20, 8, 0));
+    }
+}
@@ -0,0 +1,200 @@
+//! Alpha Vantage 时间序列接口，需要 API Key
+//!
+//! 限流与参数错误以 HTTP 200 返回，需从响应体中的 `Note` / `Information` / `Error Message` 字段识别
+
+use crate::http::{candle, interval_name, latest_candles, number, HttpClient};
+use alpha_core::errors::{AlphaError, AlphaResult};
+use alpha_core::models::{Exchange, MarketData};
+use alpha_core::provider::{DataProvider, ProviderSettings, SymbolMatch};
+use alpha_core::utils::time::session::exchange_timezone;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde_json::Value;
+
+const PROVIDER: &str = "Alpha Vantage";
+const BASE_URL: &str = "https://www.alphavantage.co";
+/// `outputsize=compact` 返回的数据点数
+const COMPACT_SIZE: usize = 100;
+
+const INTRADAY_INTERVALS: &[(i64, &str)] = &[
+    (60, "1min"),
+    (300, "5min"),
+    (900, "15min"),
+    (1800, "30min"),
+    (3600, "60min"),
+];
+
+/// Alpha Vantage 数据源
+#[derive(Debug, Clone)]
+pub struct AlphaVantageProvider {
+    http: HttpClient,
+    api_key: String,
+}
+
+impl AlphaVantageProvider {
+    pub fn new(settings: &ProviderSettings) -> AlphaResult<Self> {
+        Ok(Self {
+            api_key: settings.require_api_key()?.to_string(),
+            http: HttpClient::new(PROVIDER, BASE_URL, settings)?,
+        })
+    }
+
+    async fn query(&self, mut query: Vec<(&str, String)>) -> AlphaResult<Value> {
+        query.push(("apikey", self.api_key.clone()));
+        let body = self.http.get_json("/query", &query).await?;
+        check_body(&body)?;
+        Ok(body)
+    }
+}
+
+#[async_trait]
+impl DataProvider for AlphaVantageProvider {
+    fn name(&self) -> &str {
+        "alpha_vantage"
+    }
+
+    async fn candles(&self, symbol: &str, timeframe: Duration, limit: usize) -> AlphaResult<Vec<MarketData>> {
+        let mut query = vec![
+            ("symbol", symbol.to_string()),
+            ("outputsize", if limit <= COMPACT_SIZE { "compact" } else { "full" }.to_string()),
+        ];
+        if timeframe == Duration::days(1) {
+            query.push(("function", "TIME_SERIES_DAILY".to_string()));
+        } else if timeframe == Duration::weeks(1) {
+            query.push(("function", "TIME_SERIES_WEEKLY".to_string()));
+        } else {
+            query.push(("function", "TIME_SERIES_INTRADAY".to_string()));
+            query.push(("interval", interval_name(PROVIDER, timeframe, INTRADAY_INTERVALS)?.to_string()));
+        }
+
+        let body = self.query(query).await?;
+        latest_candles(self.http.provider(), symbol, parse_series(symbol, &body)?, limit)
+    }
+
+    async fn quote(&self, symbol: &str) -> AlphaResult<MarketData> {
+        let body = self.query(vec![
+            ("function", "GLOBAL_QUOTE".to_string()),
+            ("symbol", symbol.to_string()),
+        ]).await?;
+        parse_quote(symbol, &body)
+    }
+
+    async fn search(&self, query: &str) -> AlphaResult<Vec<SymbolMatch>> {
+        let body = self.query(vec![
+            ("function", "SYMBOL_SEARCH".to_string()),
+            ("keywords", query.to_string()),
+        ]).await?;
+        Ok(parse_search(&body))
+    }
+}
+
+fn check_body(body: &Value) -> AlphaResult<()> {
+    if let Some(message) = body["Error Message"].as_str() {
+        return Err(AlphaError::invalid_input(format!("{}: {}", PROVIDER, message)));
+    }
+    if let Some(message) = body["Note"].as_str().or_else(|| body["Information"].as_str()) {
+        let message = format!("{}: {}", PROVIDER, message);
+        return Err(if message.contains("premium") && !message.contains("rate limit") {
+            AlphaError::PermissionDenied(message)
+        } else {
+            AlphaError::RateLimited(message)
+        });
+    }
+    Ok(())
+}
+
+/// 时间为美东时间，日线只有日期
+fn parse_time(raw: &str) -> Option<DateTime<Utc>> {
+    let naive = NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .or_else(|| NaiveDate::parse_from_str(raw, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))?;
+    exchange_timezone(Exchange::Nyse)
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// 序列字段名随周期变化 (`Time Series (5min)`、`Time Series (Daily)`、`Weekly Time Series`)
+pub(crate) fn parse_series(symbol: &str, body: &Value) -> AlphaResult<Vec<MarketData>> {
+    check_body(body)?;
+    let series = body.as_object()
+        .and_then(|object| object.iter().find(|(key, _)| key.contains("Time Series")))
+        .and_then(|(_, series)| series.as_object())
+        .ok_or_else(|| AlphaError::not_found(format!("{} has no data for {}", PROVIDER, symbol)))?;
+
+    series.iter()
+        .map(|(time, bar)| {
+            let field = |name: &str| number(&bar[name]);
+            match (parse_time(time), field("1. open"), field("2. high"), field("3. low"), field("4. close")) {
+                (Some(ts), Some(open), Some(high), Some(low), Some(close)) => {
+                    Ok(candle(symbol, ts, open, high, low, close, field("5. volume").unwrap_or(0.0)))
+                }
+                _ => Err(AlphaError::SerializationError(format!("Malformed {} bar at {}", PROVIDER, time))),
+            }
+        })
+        .collect()
+}
+
+/// `GLOBAL_QUOTE` 只给出最新交易日，时间戳取请求时刻
+pub(crate) fn parse_quote(symbol: &str, body: &Value) -> AlphaResult<MarketData> {
+    check_body(body)?;
+    let quote = &body["Global Quote"];
+    let price = number(&quote["05. price"])
+        .ok_or_else(|| AlphaError::not_found(format!("{} has no latest price for {}", PROVIDER, symbol)))?;
+
+    Ok(MarketData {
+        symbol: symbol.to_string(),
+        timestamp: Utc::now(),
+        price,
+        volume: number(&quote["06. volume"]).unwrap_or(0.0).max(0.0) as u64,
+        bid: None,
+        ask: None,
+        open: number(&quote["02. open"]),
+        high: number(&quote["03. high"]),
+        low: number(&quote["04. low"]),
+    })
+}
+
+pub(crate) fn parse_search(body: &Value) -> Vec<SymbolMatch> {
+    body["bestMatches"].as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            Some(SymbolMatch {
+                symbol: item["1. symbol"].as_str()?.to_string(),
+                name: item["2. name"].as_str().unwrap_or_default().to_string(),
+                exchange: item["4. region"].as_str().map(str::to_string),
+                asset_type: item["3. type"].as_str().map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_series_and_errors() {
+        let body = json!({
+            "Meta Data": {"2. Symbol": "IBM", "6. Time Zone": "US/Eastern"},
+            "Time Series (5min)": {
+                "2024-01-02 16:00:00": {"1. open": "161.1", "2. high": "161.5", "3. low": "161.0", "4. close": "161.4", "5. volume": "2000"},
+                "2024-01-02 15:55:00": {"1. open": "161.0", "2. high": "161.2", "3. low": "160.9", "4. close": "161.1", "5. volume": "1500"}
+            }
+        });
+        let mut candles = parse_series("IBM", &body).unwrap();
+        candles.sort_by_key(|c| c.timestamp);
+        assert_eq!(candles.len(), 2);
+        assert_eq!(candles[1].timestamp.to_rfc3339(), "2024-01-02T21:00:00+00:00");
+
+        let limited = json!({"Note": "Thank you for using Alpha Vantage! Our standard API rate limit is 25 requests per day."});
+        assert!(matches!(parse_series("IBM", &limited), Err(AlphaError::RateLimited(_))));
+        let invalid = json!({"Error Message": "Invalid API call."});
+        assert!(matches!(parse_quote("IBM", &invalid), Err(AlphaError::InvalidInput(_))));
+
+        let search = json!({"bestMatches": [{"1. symbol": "IBM", "2. name": "International Business Machines Corp", "3. type": "Equity", "4. region": "United States"}]});
+        assert_eq!(parse_search(&search)[0].asset_type.as_deref(), Some("Equity"));
+    }
+}
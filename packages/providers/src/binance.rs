@@ -0,0 +1,168 @@
+//! Binance 现货行情接口，公开行情无需 API Key
+
+use crate::http::{candle, interval_name, latest_candles, number, HttpClient};
+use alpha_core::errors::{AlphaError, AlphaResult};
+use alpha_core::models::MarketData;
+use alpha_core::provider::{DataProvider, ProviderSettings, SymbolMatch};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde_json::Value;
+
+const PROVIDER: &str = "Binance";
+const BASE_URL: &str = "https://api.binance.com";
+/// 单次请求 K 线数量上限
+const MAX_LIMIT: usize = 1000;
+const SEARCH_LIMIT: usize = 20;
+
+const INTERVALS: &[(i64, &str)] = &[
+    (60, "1m"),
+    (180, "3m"),
+    (300, "5m"),
+    (900, "15m"),
+    (1800, "30m"),
+    (3600, "1h"),
+    (7200, "2h"),
+    (14400, "4h"),
+    (21600, "6h"),
+    (28800, "8h"),
+    (43200, "12h"),
+    (86400, "1d"),
+    (259200, "3d"),
+    (604800, "1w"),
+];
+
+/// Binance 现货数据源
+#[derive(Debug, Clone)]
+pub struct BinanceProvider {
+    http: HttpClient,
+}
+
+impl BinanceProvider {
+    pub fn new(settings: &ProviderSettings) -> AlphaResult<Self> {
+        Ok(Self { http: HttpClient::new(PROVIDER, BASE_URL, settings)? })
+    }
+}
+
+#[async_trait]
+impl DataProvider for BinanceProvider {
+    fn name(&self) -> &str {
+        "binance"
+    }
+
+    async fn candles(&self, symbol: &str, timeframe: Duration, limit: usize) -> AlphaResult<Vec<MarketData>> {
+        let interval = interval_name(PROVIDER, timeframe, INTERVALS)?;
+        let body = self.http.get_json("/api/v3/klines", &[
+            ("symbol", pair(symbol)),
+            ("interval", interval.to_string()),
+            ("limit", limit.min(MAX_LIMIT).to_string()),
+        ]).await?;
+
+        latest_candles(self.http.provider(), symbol, parse_klines(symbol, &body)?, limit)
+    }
+
+    async fn quote(&self, symbol: &str) -> AlphaResult<MarketData> {
+        let body = self.http.get_json("/api/v3/ticker/24hr", &[("symbol", pair(symbol))]).await?;
+        parse_ticker(symbol, &body)
+    }
+
+    /// 现货接口没有搜索功能，在全部交易对的最新价列表中按代码匹配
+    async fn search(&self, query: &str) -> AlphaResult<Vec<SymbolMatch>> {
+        let body = self.http.get_json("/api/v3/ticker/price", &[]).await?;
+        Ok(parse_search(&body, query))
+    }
+}
+
+/// 交易对统一为 Binance 格式，如 `btc-usdt`、`BTC/USDT` -> `BTCUSDT`
+fn pair(symbol: &str) -> String {
+    symbol.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_ascii_uppercase()
+}
+
+/// K 线为数组：[开盘时间, 开, 高, 低, 收, 成交量, 收盘时间, ...]，价格与成交量为字符串
+pub(crate) fn parse_klines(symbol: &str, body: &Value) -> AlphaResult<Vec<MarketData>> {
+    let rows = body.as_array()
+        .ok_or_else(|| AlphaError::SerializationError(format!("{} klines response is not an array", PROVIDER)))?;
+
+    rows.iter()
+        .map(|row| {
+            let field = |i: usize| number(&row[i]);
+            let timestamp = row[0].as_i64().and_then(DateTime::from_timestamp_millis);
+            match (timestamp, field(1), field(2), field(3), field(4), field(5)) {
+                (Some(ts), Some(open), Some(high), Some(low), Some(close), Some(volume)) => {
+                    Ok(candle(symbol, ts, open, high, low, close, volume))
+                }
+                _ => Err(AlphaError::SerializationError(format!("Malformed {} kline: {}", PROVIDER, row))),
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn parse_ticker(symbol: &str, body: &Value) -> AlphaResult<MarketData> {
+    let price = number(&body["lastPrice"])
+        .ok_or_else(|| AlphaError::not_found(format!("{} has no latest price for {}", PROVIDER, symbol)))?;
+    let timestamp = body["closeTime"].as_i64()
+        .and_then(DateTime::from_timestamp_millis)
+        .unwrap_or_else(Utc::now);
+
+    Ok(MarketData {
+        symbol: symbol.to_string(),
+        timestamp,
+        price,
+        volume: number(&body["volume"]).unwrap_or(0.0).max(0.0) as u64,
+        bid: number(&body["bidPrice"]),
+        ask: number(&body["askPrice"]),
+        open: number(&body["openPrice"]),
+        high: number(&body["highPrice"]),
+        low: number(&body["lowPrice"]),
+    })
+}
+
+/// 代码以查询串开头的交易对排在前面
+pub(crate) fn parse_search(body: &Value, query: &str) -> Vec<SymbolMatch> {
+    let needle = pair(query);
+    let mut matches: Vec<&str> = body.as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|ticker| ticker["symbol"].as_str())
+        .filter(|symbol| !needle.is_empty() && symbol.contains(needle.as_str()))
+        .collect();
+    matches.sort_by_key(|symbol| (!symbol.starts_with(needle.as_str()), symbol.len(), *symbol));
+
+    matches.into_iter()
+        .take(SEARCH_LIMIT)
+        .map(|symbol| SymbolMatch {
+            symbol: symbol.to_string(),
+            name: symbol.to_string(),
+            exchange: Some(PROVIDER.to_string()),
+            asset_type: Some("SPOT".to_string()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_klines_and_ticker() {
+        let body = json!([
+            [1704153600000_i64, "42283.58", "42554.57", "42261.02", "42475.23", "1271.68108", 1704157199999_i64, "0", 0, "0", "0", "0"]
+        ]);
+        let candles = parse_klines("BTC-USDT", &body).unwrap();
+        assert_eq!(candles[0].price, 42475.23);
+        assert_eq!(candles[0].volume, 1271);
+        assert_eq!(pair("btc/usdt"), "BTCUSDT");
+
+        let ticker = json!({"lastPrice": "42475.23", "bidPrice": "42475.22", "askPrice": "42475.24", "volume": "100.5", "closeTime": 1704157199999_i64});
+        let quote = parse_ticker("BTCUSDT", &ticker).unwrap();
+        assert_eq!(quote.bid, Some(42475.22));
+        assert!(parse_klines("BTCUSDT", &json!({"code": -1121, "msg": "Invalid symbol."})).is_err());
+
+        let prices = json!([{"symbol": "WBTCBTC"}, {"symbol": "BTCUSDT"}, {"symbol": "ETHUSDT"}]);
+        let found: Vec<String> = parse_search(&prices, "btc").into_iter().map(|m| m.symbol).collect();
+        assert_eq!(found, vec!["BTCUSDT", "WBTCBTC"]);
+    }
+}
@@ -0,0 +1,197 @@
+//! 各 HTTP 数据源共用的请求与解析辅助
+//!
+//! HTTP 与业务错误统一映射为 [`AlphaError`]。网络错误和 5xx 先按 [`RetryPolicy`] 重试一次再交给回退链，
+//! 限流 (429) 直接返回，由回退链换用下一个数据源
+
+use alpha_core::errors::{AlphaError, AlphaResult};
+use alpha_core::models::MarketData;
+use alpha_core::provider::ProviderSettings;
+use alpha_core::utils::retry::{retry_if, RetryPolicy};
+use chrono::{DateTime, Duration, Utc};
+use reqwest::StatusCode;
+
+/// 单个请求的重试：共 2 次，间隔 300ms 左右，避免明显拖慢回退
+fn request_retry() -> RetryPolicy {
+    RetryPolicy::new(2, std::time::Duration::from_millis(300))
+}
+
+/// 绑定了数据源名称与接口地址的 HTTP 客户端
+#[derive(Debug, Clone)]
+pub(crate) struct HttpClient {
+    provider: &'static str,
+    base_url: String,
+    client: reqwest::Client,
+    retry: RetryPolicy,
+}
+
+impl HttpClient {
+    pub(crate) fn new(provider: &'static str, default_base_url: &str, settings: &ProviderSettings) -> AlphaResult<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("alpha-providers/", env!("CARGO_PKG_VERSION")))
+            .timeout(std::time::Duration::from_secs(15))
+            .build()
+            .map_err(|e| AlphaError::internal("Failed to create HTTP client").caused_by(e))?;
+
+        let base_url = settings.base_url.as_deref()
+            .unwrap_or(default_base_url)
+            .trim_end_matches('/')
+            .to_string();
+
+        Ok(Self { provider, base_url, client, retry: request_retry() })
+    }
+
+    pub(crate) fn provider(&self) -> &'static str {
+        self.provider
+    }
+
+    /// 发送 GET 请求并解析 JSON，非 2xx 响应按状态码映射错误，可重试的错误按策略重试
+    pub(crate) async fn get_json(&self, path: &str, query: &[(&str, String)]) -> AlphaResult<serde_json::Value> {
+        let url = format!("{}{}", self.base_url, path);
+        let should_retry = |err: &AlphaError| err.is_retryable() && !matches!(err.root(), AlphaError::RateLimited(_));
+        retry_if(&self.retry, should_retry, || self.get_json_once(&url, query)).await
+    }
+
+    async fn get_json_once(&self, url: &str, query: &[(&str, String)]) -> AlphaResult<serde_json::Value> {
+        let response = self.client.get(url)
+            .query(query)
+            .send()
+            .await
+            .map_err(|e| request_error(self.provider, e))?;
+
+        let status = response.status();
+        let body = response.text().await.map_err(|e| request_error(self.provider, e))?;
+        if !status.is_success() {
+            return Err(status_error(self.provider, status, &body));
+        }
+
+        serde_json::from_str(&body).map_err(|e| {
+            AlphaError::SerializationError(format!("Malformed response from {}", self.provider)).caused_by(e)
+        })
+    }
+}
+
+/// 按 HTTP 状态码映射错误类别
+pub(crate) fn status_error(provider: &str, status: StatusCode, body: &str) -> AlphaError {
+    let detail: String = body.chars().take(200).collect();
+    let message = format!("{} returned {}: {}", provider, status, detail);
+
+    match status {
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => AlphaError::invalid_input(message),
+        StatusCode::UNAUTHORIZED => AlphaError::AuthenticationError(message),
+        StatusCode::FORBIDDEN => AlphaError::PermissionDenied(message),
+        StatusCode::NOT_FOUND => AlphaError::not_found(message),
+        // Binance 在持续超限后返回 418 (IP 被临时封禁)
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::IM_A_TEAPOT => AlphaError::RateLimited(message),
+        s if s.is_server_error() => AlphaError::ServiceUnavailable(message),
+        _ => AlphaError::network(message),
+    }
+}
+
+fn request_error(provider: &str, err: reqwest::Error) -> AlphaError {
+    let error = if err.is_timeout() {
+        AlphaError::network(format!("{} request timed out", provider))
+    } else {
+        AlphaError::network(format!("{} request failed", provider))
+    };
+    error.caused_by(err)
+}
+
+/// 将周期映射为数据源的 interval 参数，`intervals` 为 (秒数, 参数值) 列表
+pub(crate) fn interval_name(provider: &str, step: Duration, intervals: &[(i64, &'static str)]) -> AlphaResult<&'static str> {
+    intervals.iter()
+        .find(|(seconds, _)| *seconds == step.num_seconds())
+        .map(|(_, name)| *name)
+        .ok_or_else(|| {
+            let supported: Vec<&str> = intervals.iter().map(|(_, name)| *name).collect();
+            AlphaError::invalid_input(format!("{} does not support this timeframe, expected one of: {}", provider, supported.join(", ")))
+        })
+}
+
+/// 解析字符串或数字形式的数值字段
+pub(crate) fn number(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+    .filter(|v: &f64| v.is_finite())
+}
+
+pub(crate) fn candle(symbol: &str, timestamp: DateTime<Utc>, open: f64, high: f64, low: f64, close: f64, volume: f64) -> MarketData {
+    MarketData {
+        symbol: symbol.to_string(),
+        timestamp,
+        price: close,
+        volume: volume.max(0.0) as u64,
+        bid: None,
+        ask: None,
+        open: Some(open),
+        high: Some(high),
+        low: Some(low),
+    }
+}
+
+/// 按时间升序排列并保留最近 `limit` 根，空结果视为数据不存在
+pub(crate) fn latest_candles(provider: &str, symbol: &str, mut candles: Vec<MarketData>, limit: usize) -> AlphaResult<Vec<MarketData>> {
+    if candles.is_empty() {
+        return Err(AlphaError::not_found(format!("{} has no candles for {}", provider, symbol)));
+    }
+    candles.sort_by_key(|c| c.timestamp);
+    if candles.len() > limit {
+        candles.drain(..candles.len() - limit);
+    }
+    Ok(candles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 依次返回给定状态码的 HTTP 服务，返回地址和已收到的请求数
+    async fn serve(statuses: Vec<u16>) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        use std::sync::atomic::Ordering;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        let requests = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = requests.clone();
+        tokio::spawn(async move {
+            for status in statuses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                counter.fetch_add(1, Ordering::SeqCst);
+                let response = format!("HTTP/1.1 {} X\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{{}}", status);
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (address, requests)
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors() {
+        use std::sync::atomic::Ordering;
+
+        let (address, requests) = serve(vec![503, 200]).await;
+        let settings = ProviderSettings { base_url: Some(address), ..ProviderSettings::new("test") };
+        let client = HttpClient::new("Test", "", &settings).unwrap();
+        assert_eq!(client.get_json("/", &[]).await.unwrap(), serde_json::json!({}));
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // 限流不重试，直接交给回退链
+        let (address, requests) = serve(vec![429, 200]).await;
+        let settings = ProviderSettings { base_url: Some(address), ..ProviderSettings::new("test") };
+        let client = HttpClient::new("Test", "", &settings).unwrap();
+        assert!(matches!(client.get_json("/", &[]).await, Err(AlphaError::RateLimited(_))));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_status_error_mapping() {
+        let err = status_error("Binance", StatusCode::TOO_MANY_REQUESTS, "");
+        assert!(matches!(err, AlphaError::RateLimited(_)) && err.is_retryable());
+        assert!(matches!(status_error("Yahoo", StatusCode::NOT_FOUND, ""), AlphaError::DataNotFound(_)));
+        assert!(matches!(status_error("Yahoo", StatusCode::BAD_GATEWAY, ""), AlphaError::ServiceUnavailable(_)));
+    }
+}
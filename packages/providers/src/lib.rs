@@ -0,0 +1,58 @@
+//! Alpha Finance 行情数据源实现
+//!
+//! 实现 alpha-core 的 [`DataProvider`]，供桌面端、采集服务与网关共用。
+//! 启动时通过 [`register_builtin`] 注册，再由配置中的数据源列表组装回退链
+
+use alpha_core::provider::ProviderRegistry;
+use std::sync::Arc;
+
+pub use alpha_core::provider::{DataProvider, ProviderChain, ProviderSettings, SymbolMatch};
+
+mod http;
+
+pub mod alpha_vantage;
+pub mod binance;
+pub mod simulated;
+pub mod yahoo;
+
+pub use alpha_vantage::AlphaVantageProvider;
+pub use binance::BinanceProvider;
+pub use simulated::SimulatedProvider;
+pub use yahoo::YahooProvider;
+
+/// 注册全部内置数据源：`yahoo`、`alpha_vantage` (需 api_key)、`binance`、`simulated`
+pub fn register_builtin(registry: &mut ProviderRegistry) {
+    registry.register("yahoo", |s| Ok(Arc::new(YahooProvider::new(s)?)));
+    registry.register("alpha_vantage", |s| Ok(Arc::new(AlphaVantageProvider::new(s)?)));
+    registry.register("binance", |s| Ok(Arc::new(BinanceProvider::new(s)?)));
+    registry.register("simulated", |s| Ok(Arc::new(SimulatedProvider::new(s)?)));
+}
+
+/// 包含全部内置数据源的注册表
+pub fn default_registry() -> ProviderRegistry {
+    let mut registry = ProviderRegistry::new();
+    register_builtin(&mut registry);
+    registry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alpha_core::errors::AlphaError;
+    use chrono::Duration;
+
+    #[test]
+    fn test_builtin_chain() {
+        let registry = default_registry();
+        assert!(matches!(
+            registry.build(&ProviderSettings::new("alpha_vantage")),
+            Err(AlphaError::ConfigurationError(_))
+        ));
+
+        let chain = registry.chain(&[ProviderSettings::new("simulated")]).unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let candles = runtime.block_on(chain.candles("AAPL", Duration::minutes(5), 50)).unwrap();
+        assert_eq!(candles.len(), 50);
+        assert_eq!(candles[1].timestamp - candles[0].timestamp, Duration::minutes(5));
+    }
+}
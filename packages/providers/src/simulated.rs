@@ -0,0 +1,52 @@
+//! 本地随机游走数据源，用于离线演示和测试
+
+use alpha_core::errors::AlphaResult;
+use alpha_core::models::MarketData;
+use alpha_core::provider::{DataProvider, ProviderSettings, SymbolMatch};
+use alpha_core::simulate::MarketSimulator;
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+
+/// 模拟数据源，初始价格按代码区分，种子取当前时间使每次请求结果不同
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedProvider;
+
+impl SimulatedProvider {
+    pub fn new(_settings: &ProviderSettings) -> AlphaResult<Self> {
+        Ok(Self)
+    }
+
+    fn simulator(symbol: &str) -> AlphaResult<MarketSimulator> {
+        let base_price = 100.0 + (symbol.len() as f64 * 10.0);
+        let seed = Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64;
+
+        MarketSimulator::gbm(base_price, 0.05, 0.3, seed)
+    }
+}
+
+#[async_trait]
+impl DataProvider for SimulatedProvider {
+    fn name(&self) -> &str {
+        "simulated"
+    }
+
+    async fn candles(&self, symbol: &str, timeframe: Duration, limit: usize) -> AlphaResult<Vec<MarketData>> {
+        let start = Utc::now() - timeframe * limit as i32;
+        Ok(Self::simulator(symbol)?.generate_bars(symbol, start, timeframe, limit))
+    }
+
+    async fn quote(&self, symbol: &str) -> AlphaResult<MarketData> {
+        Ok(Self::simulator(symbol)?.next_tick(symbol, Utc::now()))
+    }
+
+    /// 任意代码均可模拟，直接回显查询
+    async fn search(&self, query: &str) -> AlphaResult<Vec<SymbolMatch>> {
+        let symbol = query.trim().to_ascii_uppercase();
+        Ok(vec![SymbolMatch {
+            name: format!("{} (simulated)", symbol),
+            symbol,
+            exchange: None,
+            asset_type: None,
+        }])
+    }
+}
@@ -0,0 +1,201 @@
+//! Yahoo Finance 图表接口 (`/v8/finance/chart`)，无需 API Key
+
+use crate::http::{candle, interval_name, latest_candles, number, HttpClient};
+use alpha_core::errors::{AlphaError, AlphaResult};
+use alpha_core::models::MarketData;
+use alpha_core::provider::{DataProvider, ProviderSettings, SymbolMatch};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde_json::Value;
+
+const PROVIDER: &str = "Yahoo Finance";
+const BASE_URL: &str = "https://query1.finance.yahoo.com";
+const SEARCH_LIMIT: usize = 10;
+
+const INTERVALS: &[(i64, &str)] = &[
+    (60, "1m"),
+    (120, "2m"),
+    (300, "5m"),
+    (900, "15m"),
+    (1800, "30m"),
+    (3600, "60m"),
+    (5400, "90m"),
+    (86400, "1d"),
+    (432000, "5d"),
+    (604800, "1wk"),
+];
+
+/// Yahoo Finance 数据源
+#[derive(Debug, Clone)]
+pub struct YahooProvider {
+    http: HttpClient,
+}
+
+impl YahooProvider {
+    pub fn new(settings: &ProviderSettings) -> AlphaResult<Self> {
+        Ok(Self { http: HttpClient::new(PROVIDER, BASE_URL, settings)? })
+    }
+
+    async fn chart(&self, symbol: &str, query: &[(&str, String)]) -> AlphaResult<Value> {
+        self.http.get_json(&format!("/v8/finance/chart/{}", symbol), query).await
+    }
+}
+
+#[async_trait]
+impl DataProvider for YahooProvider {
+    fn name(&self) -> &str {
+        "yahoo"
+    }
+
+    async fn candles(&self, symbol: &str, timeframe: Duration, limit: usize) -> AlphaResult<Vec<MarketData>> {
+        let interval = interval_name(PROVIDER, timeframe, INTERVALS)?;
+        let end = Utc::now();
+        let start = end - lookback(timeframe, limit);
+
+        let body = self.chart(symbol, &[
+            ("interval", interval.to_string()),
+            ("period1", start.timestamp().to_string()),
+            ("period2", end.timestamp().to_string()),
+        ]).await?;
+        latest_candles(self.http.provider(), symbol, parse_chart(symbol, &body)?, limit)
+    }
+
+    async fn quote(&self, symbol: &str) -> AlphaResult<MarketData> {
+        let body = self.chart(symbol, &[("interval", "1m".to_string()), ("range", "1d".to_string())]).await?;
+        parse_quote(symbol, &body)
+    }
+
+    async fn search(&self, query: &str) -> AlphaResult<Vec<SymbolMatch>> {
+        let body = self.http.get_json("/v1/finance/search", &[
+            ("q", query.to_string()),
+            ("quotesCount", SEARCH_LIMIT.to_string()),
+            ("newsCount", "0".to_string()),
+        ]).await?;
+        Ok(parse_search(&body))
+    }
+}
+
+/// 请求的时间跨度需覆盖休市时段；分钟线只能取最近 7 天，其余日内周期最近 60 天
+fn lookback(step: Duration, limit: usize) -> Duration {
+    let span = step * (limit as i32) * 2 + Duration::days(4);
+    if step < Duration::minutes(2) {
+        span.min(Duration::days(7))
+    } else if step < Duration::days(1) {
+        span.min(Duration::days(59))
+    } else {
+        span
+    }
+}
+
+fn chart_result<'a>(symbol: &str, body: &'a Value) -> AlphaResult<&'a Value> {
+    let chart = &body["chart"];
+    if let Some(error) = chart["error"].as_object() {
+        let code = error.get("code").and_then(Value::as_str).unwrap_or_default();
+        let description = error.get("description").and_then(Value::as_str).unwrap_or_default();
+        let message = format!("{} {}: {}", PROVIDER, symbol, description);
+        return Err(if code == "Not Found" { AlphaError::not_found(message) } else { AlphaError::invalid_input(message) });
+    }
+
+    chart["result"].get(0)
+        .ok_or_else(|| AlphaError::not_found(format!("{} has no data for {}", PROVIDER, symbol)))
+}
+
+/// 解析 K 线，开高低收任一缺失 (停牌或尚未成交的区间) 的点被跳过
+pub(crate) fn parse_chart(symbol: &str, body: &Value) -> AlphaResult<Vec<MarketData>> {
+    let result = chart_result(symbol, body)?;
+    let quote = &result["indicators"]["quote"][0];
+    let Some(timestamps) = result["timestamp"].as_array() else {
+        return Ok(Vec::new());
+    };
+
+    Ok(timestamps.iter()
+        .enumerate()
+        .filter_map(|(i, ts)| {
+            let timestamp = DateTime::from_timestamp(ts.as_i64()?, 0)?;
+            let field = |name: &str| number(&quote[name][i]);
+            Some(candle(
+                symbol,
+                timestamp,
+                field("open")?,
+                field("high")?,
+                field("low")?,
+                field("close")?,
+                field("volume").unwrap_or(0.0),
+            ))
+        })
+        .collect())
+}
+
+/// 最新行情取自图表元数据中的 `regularMarket*` 字段
+pub(crate) fn parse_quote(symbol: &str, body: &Value) -> AlphaResult<MarketData> {
+    let meta = &chart_result(symbol, body)?["meta"];
+    let price = number(&meta["regularMarketPrice"])
+        .ok_or_else(|| AlphaError::not_found(format!("{} has no latest price for {}", PROVIDER, symbol)))?;
+    let timestamp = meta["regularMarketTime"].as_i64()
+        .and_then(|ts| DateTime::from_timestamp(ts, 0))
+        .unwrap_or_else(Utc::now);
+
+    Ok(MarketData {
+        symbol: symbol.to_string(),
+        timestamp,
+        price,
+        volume: number(&meta["regularMarketVolume"]).unwrap_or(0.0).max(0.0) as u64,
+        bid: None,
+        ask: None,
+        open: None,
+        high: number(&meta["regularMarketDayHigh"]),
+        low: number(&meta["regularMarketDayLow"]),
+    })
+}
+
+pub(crate) fn parse_search(body: &Value) -> Vec<SymbolMatch> {
+    body["quotes"].as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|quote| {
+            let symbol = quote["symbol"].as_str()?;
+            let name = quote["longname"].as_str()
+                .or_else(|| quote["shortname"].as_str())
+                .unwrap_or(symbol);
+            Some(SymbolMatch {
+                symbol: symbol.to_string(),
+                name: name.to_string(),
+                exchange: quote["exchange"].as_str().map(str::to_string),
+                asset_type: quote["quoteType"].as_str().map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_chart() {
+        let body = json!({"chart": {"result": [{
+            "meta": {"regularMarketPrice": 187.5, "regularMarketTime": 1704224400, "regularMarketVolume": 1200},
+            "timestamp": [1704205800, 1704205860, 1704205920],
+            "indicators": {"quote": [{
+                "open": [187.1, null, 187.3],
+                "high": [187.4, null, 187.6],
+                "low": [186.9, null, 187.2],
+                "close": [187.2, null, 187.5],
+                "volume": [1000, null, 800]
+            }]}
+        }], "error": null}});
+
+        let candles = parse_chart("AAPL", &body).unwrap();
+        assert_eq!(candles.len(), 2);
+        assert_eq!(candles[1].price, 187.5);
+        assert_eq!(candles[1].high, Some(187.6));
+        assert_eq!(parse_quote("AAPL", &body).unwrap().volume, 1200);
+
+        let missing = json!({"chart": {"result": null, "error": {"code": "Not Found", "description": "No data found, symbol may be delisted"}}});
+        assert!(matches!(parse_chart("XXXX", &missing), Err(AlphaError::DataNotFound(_))));
+
+        let search = json!({"quotes": [{"symbol": "AAPL", "shortname": "Apple Inc.", "exchange": "NMS", "quoteType": "EQUITY"}]});
+        assert_eq!(parse_search(&search)[0].name, "Apple Inc.");
+    }
+}
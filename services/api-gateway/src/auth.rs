@@ -0,0 +1,357 @@
+//! JWT 认证
+//!
+//! 配置 `--jwks-url` 后，受保护的路由要求 `Authorization: Bearer <token>` (WebSocket 握手可以改用 `access_token` 查询参数)：
+//! - 签名用 JWKS 中 `kid` 对应的公钥校验，JWKS 缓存 `--jwks-cache-secs` 秒，遇到未知的 `kid` 时提前刷新 (至多每 30 秒一次)
+//! - 签名算法取自公钥的 `alg` (未声明时按密钥类型)，令牌头部声明的算法与之不符时拒绝
+//! - 校验 `exp`，配置了 `--jwt-issuer`、`--jwt-audience` 时同时校验 `iss`、`aud`
+//! - 通过后 [`Claims`] 放入请求扩展，处理函数可以用 `Extension<Claims>` 读取
+//!
+//! 路由要求的角色取自 `roles` 声明，满足其一即可。未认证返回 401，角色不足返回 403，
+//! 响应体与其他错误一致 (`ApiResponse::error`)。未配置 `--jwks-url` 时不做认证
+
+use crate::ApiResponse;
+use axum::extract::{Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Json, Response};
+use axum::Router;
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// 遇到未知 `kid` 时两次刷新 JWKS 的最短间隔
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 认证配置
+#[derive(Debug, Clone)]
+pub struct AuthSettings {
+    pub jwks_url: String,
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    pub jwks_ttl: Duration,
+}
+
+/// 通过校验的令牌声明
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub exp: u64,
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+impl Claims {
+    pub fn has_any_role(&self, roles: &[&str]) -> bool {
+        roles.is_empty() || self.roles.iter().any(|role| roles.contains(&role.as_str()))
+    }
+}
+
+/// 认证失败的原因
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("Missing bearer token")]
+    MissingToken,
+    #[error("Invalid token: {0}")]
+    InvalidToken(String),
+    #[error("Signing keys unavailable: {0}")]
+    KeysUnavailable(String),
+    #[error("Requires one of the roles {0:?}")]
+    Forbidden(&'static [&'static str]),
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Self::MissingToken | Self::InvalidToken(_) => StatusCode::UNAUTHORIZED,
+            Self::KeysUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+        };
+        let mut response = (status, Json(ApiResponse::<()>::error(self.to_string()))).into_response();
+        if status == StatusCode::UNAUTHORIZED {
+            let challenge = match self {
+                Self::InvalidToken(_) => "Bearer error=\"invalid_token\"",
+                _ => "Bearer",
+            };
+            response.headers_mut().insert(header::WWW_AUTHENTICATE, header::HeaderValue::from_static(challenge));
+        }
+        response
+    }
+}
+
+struct CachedKeys {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+/// 校验令牌，缓存 JWKS
+pub struct Authenticator {
+    settings: AuthSettings,
+    client: reqwest::Client,
+    keys: RwLock<Option<CachedKeys>>,
+}
+
+impl Authenticator {
+    pub fn new(settings: AuthSettings) -> Self {
+        Self { settings, client: reqwest::Client::new(), keys: RwLock::new(None) }
+    }
+
+    pub async fn verify(&self, token: &str) -> Result<Claims, AuthError> {
+        let header = jsonwebtoken::decode_header(token).map_err(|e| AuthError::InvalidToken(e.to_string()))?;
+        let (key, algorithms) = self.key(header.kid.as_deref()).await?;
+        if !algorithms.contains(&header.alg) {
+            return Err(AuthError::InvalidToken(format!("Algorithm {:?} does not match signing key {:?}", header.alg, header.kid)));
+        }
+
+        let mut validation = Validation::new(header.alg);
+        let mut required = vec!["exp"];
+        if let Some(issuer) = &self.settings.issuer {
+            validation.set_issuer(&[issuer]);
+            required.push("iss");
+        }
+        match &self.settings.audience {
+            Some(audience) => {
+                validation.set_audience(&[audience]);
+                required.push("aud");
+            }
+            None => validation.validate_aud = false,
+        }
+        validation.set_required_spec_claims(&required);
+        jsonwebtoken::decode::<Claims>(token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| AuthError::InvalidToken(e.to_string()))
+    }
+
+    /// `kid` 对应的公钥及其允许的算法，令牌没有 `kid` 时 JWKS 须只有一个公钥
+    async fn key(&self, kid: Option<&str>) -> Result<(DecodingKey, Vec<Algorithm>), AuthError> {
+        {
+            let cached = self.keys.read().await;
+            if let Some(cached) = cached.as_ref().filter(|cached| cached.fetched_at.elapsed() < self.settings.jwks_ttl) {
+                if let Some(key) = find(&cached.keys, kid) {
+                    return decoding_key(key);
+                }
+            }
+        }
+
+        let mut cached = self.keys.write().await;
+        // 过期或未知的 kid 时刷新，刷新失败时继续使用过期的公钥
+        let refresh = match cached.as_ref() {
+            Some(cached) => {
+                let age = cached.fetched_at.elapsed();
+                age >= self.settings.jwks_ttl || (find(&cached.keys, kid).is_none() && age >= MIN_REFRESH_INTERVAL)
+            }
+            None => true,
+        };
+        if refresh {
+            match self.fetch().await {
+                Ok(keys) => *cached = Some(CachedKeys { keys, fetched_at: Instant::now() }),
+                Err(e) if cached.is_some() => tracing::warn!("Failed to refresh JWKS, using cached keys: {}", e),
+                Err(e) => return Err(AuthError::KeysUnavailable(e)),
+            }
+        }
+        let keys = &cached.as_ref().expect("JWKS cached").keys;
+        let key = find(keys, kid).ok_or_else(|| AuthError::InvalidToken(format!("Unknown signing key {:?}", kid)))?;
+        decoding_key(key)
+    }
+
+    async fn fetch(&self) -> Result<JwkSet, String> {
+        tracing::debug!("Fetching JWKS from {}", self.settings.jwks_url);
+        self.client
+            .get(&self.settings.jwks_url)
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+fn find<'a>(keys: &'a JwkSet, kid: Option<&str>) -> Option<&'a Jwk> {
+    match kid {
+        Some(kid) => keys.find(kid),
+        None if keys.keys.len() == 1 => keys.keys.first(),
+        None => None,
+    }
+}
+
+fn decoding_key(jwk: &Jwk) -> Result<(DecodingKey, Vec<Algorithm>), AuthError> {
+    let key = DecodingKey::from_jwk(jwk).map_err(|e| AuthError::InvalidToken(e.to_string()))?;
+    Ok((key, allowed_algorithms(jwk)))
+}
+
+/// 公钥允许的签名算法：声明了 `alg` 时只允许该算法 (加密算法不可用于签名)，否则按密钥类型
+fn allowed_algorithms(jwk: &Jwk) -> Vec<Algorithm> {
+    if let Some(algorithm) = jwk.common.key_algorithm {
+        return Algorithm::from_str(&algorithm.to_string()).into_iter().collect();
+    }
+    match &jwk.algorithm {
+        AlgorithmParameters::OctetKey(_) => vec![Algorithm::HS256, Algorithm::HS384, Algorithm::HS512],
+        AlgorithmParameters::RSA(_) => vec![
+            Algorithm::RS256,
+            Algorithm::RS384,
+            Algorithm::RS512,
+            Algorithm::PS256,
+            Algorithm::PS384,
+            Algorithm::PS512,
+        ],
+        AlgorithmParameters::EllipticCurve(params) => match params.curve {
+            EllipticCurve::P256 => vec![Algorithm::ES256],
+            EllipticCurve::P384 => vec![Algorithm::ES384],
+            _ => Vec::new(),
+        },
+        AlgorithmParameters::OctetKeyPair(_) => vec![Algorithm::EdDSA],
+    }
+}
+
+/// 取 `Authorization` 头中的令牌，WebSocket 握手没有该头时取 `access_token` 查询参数
+fn bearer_token(request: &Request) -> Option<String> {
+    let authorization = request.headers().get(header::AUTHORIZATION).and_then(|value| value.to_str().ok());
+    if let Some(authorization) = authorization {
+        let (scheme, token) = authorization.split_once(' ')?;
+        return scheme.eq_ignore_ascii_case("bearer").then(|| token.trim().to_string());
+    }
+    request.headers().get(header::UPGRADE)?;
+    request.uri().query()?.split('&').find_map(|pair| pair.strip_prefix("access_token=")).map(str::to_string)
+}
+
+/// 认证中间件，通过后把 [`Claims`] 放入请求扩展
+pub async fn authenticate(State(auth): State<Arc<Authenticator>>, mut request: Request, next: Next) -> Response {
+    let Some(token) = bearer_token(&request) else {
+        return AuthError::MissingToken.into_response();
+    };
+    match auth.verify(&token).await {
+        Ok(claims) => {
+            request.extensions_mut().insert(claims);
+            next.run(request).await
+        }
+        Err(e) => {
+            tracing::debug!("Rejected request to {}: {}", request.uri().path(), e);
+            e.into_response()
+        }
+    }
+}
+
+/// 角色中间件，须在 [`authenticate`] 之后执行
+pub async fn require_roles(State(roles): State<&'static [&'static str]>, request: Request, next: Next) -> Response {
+    match request.extensions().get::<Claims>() {
+        Some(claims) if claims.has_any_role(roles) => next.run(request).await,
+        Some(_) => AuthError::Forbidden(roles).into_response(),
+        None => AuthError::MissingToken.into_response(),
+    }
+}
+
+/// 为路由加上认证，`roles` 为空时只要求认证；未配置认证时原样返回
+pub fn protect<S>(router: Router<S>, auth: Option<Arc<Authenticator>>, roles: &'static [&'static str]) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    match auth {
+        Some(auth) => router
+            .route_layer(axum::middleware::from_fn_with_state(roles, require_roles))
+            .route_layer(axum::middleware::from_fn_with_state(auth, authenticate)),
+        None => router,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{EncodingKey, Header};
+    use tower::ServiceExt;
+
+    const SECRET: &[u8] = b"alpha-gateway-test-secret";
+
+    fn token(kid: &str, roles: &[&str], audience: &str, exp: u64) -> String {
+        signed(Algorithm::HS256, kid, roles, audience, exp)
+    }
+
+    fn signed(algorithm: Algorithm, kid: &str, roles: &[&str], audience: &str, exp: u64) -> String {
+        let header = Header { kid: Some(kid.to_string()), ..Header::new(algorithm) };
+        let claims = serde_json::json!({"sub": "alice", "exp": exp, "roles": roles, "iss": "https://auth.alpha.finance", "aud": audience});
+        jsonwebtoken::encode(&header, &claims, &EncodingKey::from_secret(SECRET)).unwrap()
+    }
+
+    async fn authenticator() -> Arc<Authenticator> {
+        let jwks = serde_json::json!({"keys": [{"kty": "oct", "kid": "k1", "alg": "HS256", "k": "YWxwaGEtZ2F0ZXdheS10ZXN0LXNlY3JldA"}]});
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let jwks_url = format!("http://{}/.well-known/jwks.json", listener.local_addr().unwrap());
+        let app = Router::new().route("/.well-known/jwks.json", axum::routing::get(move || async move { Json(jwks) }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        Arc::new(Authenticator::new(AuthSettings {
+            jwks_url,
+            issuer: Some("https://auth.alpha.finance".to_string()),
+            audience: Some("alpha-api".to_string()),
+            jwks_ttl: Duration::from_secs(300),
+        }))
+    }
+
+    fn expires() -> u64 {
+        chrono::Utc::now().timestamp() as u64 + 600
+    }
+
+    #[tokio::test]
+    async fn test_verify() {
+        let auth = authenticator().await;
+        let claims = auth.verify(&token("k1", &["analyst"], "alpha-api", expires())).await.unwrap();
+        assert_eq!((claims.sub.as_str(), claims.roles), ("alice", vec!["analyst".to_string()]));
+
+        assert!(matches!(auth.verify(&token("k1", &[], "other", expires())).await, Err(AuthError::InvalidToken(_))));
+        assert!(matches!(auth.verify(&token("k1", &[], "alpha-api", 1_000)).await, Err(AuthError::InvalidToken(_))));
+        assert!(matches!(auth.verify(&token("k2", &[], "alpha-api", expires())).await, Err(AuthError::InvalidToken(_))));
+        assert!(matches!(auth.verify("not-a-token").await, Err(AuthError::InvalidToken(_))));
+    }
+
+    #[tokio::test]
+    async fn test_verify_rejects_other_algorithm() {
+        let auth = authenticator().await;
+        // 同一密钥按 HS384 签名同样有效，但公钥声明的是 HS256
+        let token = signed(Algorithm::HS384, "k1", &["admin"], "alpha-api", expires());
+        let err = auth.verify(&token).await.unwrap_err();
+        assert!(matches!(&err, AuthError::InvalidToken(message) if message.contains("HS384")), "{}", err);
+    }
+
+    #[test]
+    fn test_allowed_algorithms() {
+        let jwk = |value: serde_json::Value| serde_json::from_value::<Jwk>(value).unwrap();
+        let oct = jwk(serde_json::json!({"kty": "oct", "k": "c2VjcmV0"}));
+        assert_eq!(allowed_algorithms(&oct), vec![Algorithm::HS256, Algorithm::HS384, Algorithm::HS512]);
+        let pinned = jwk(serde_json::json!({"kty": "oct", "alg": "HS512", "k": "c2VjcmV0"}));
+        assert_eq!(allowed_algorithms(&pinned), vec![Algorithm::HS512]);
+        let encryption = jwk(serde_json::json!({"kty": "RSA", "alg": "RSA-OAEP", "n": "AQAB", "e": "AQAB"}));
+        assert!(allowed_algorithms(&encryption).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_protect() {
+        let auth = authenticator().await;
+        let handler = |axum::Extension(claims): axum::Extension<Claims>| async move { claims.sub };
+        let app = protect(Router::new().route("/admin", axum::routing::get(handler)), Some(auth), &["admin"]);
+        let request = |token: Option<String>| {
+            let mut request = axum::http::Request::get("/admin");
+            if let Some(token) = token {
+                request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            request.body(axum::body::Body::empty()).unwrap()
+        };
+
+        let response = app.clone().oneshot(request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+        let response = app.clone().oneshot(request(Some(token("k1", &["analyst"], "alpha-api", expires())))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["success"], false);
+
+        let response = app.oneshot(request(Some(token("k1", &["admin"], "alpha-api", expires())))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"alice");
+    }
+}
@@ -0,0 +1,166 @@
+//! 熔断
+//!
+//! 每个上游服务一个熔断器：连续 `--breaker-failure-threshold` 个请求失败 (连接失败、超时或 5xx) 后打开，
+//! 打开期间直接返回 503 而不再请求上游；`--breaker-open-secs` 后半开，放行一个探测请求，成功则关闭，失败则再次打开。
+//! 熔断器状态在 `/health` 中返回
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 熔断配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakerSettings {
+    pub failure_threshold: u32,
+    pub open_duration: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: BreakerState,
+    failures: u32,
+    opened_at: Option<Instant>,
+    /// 半开时是否已有探测请求
+    probing: bool,
+}
+
+/// 一个服务的熔断器
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    settings: BreakerSettings,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(settings: BreakerSettings) -> Self {
+        Self {
+            settings,
+            inner: Mutex::new(Inner { state: BreakerState::Closed, failures: 0, opened_at: None, probing: false }),
+        }
+    }
+
+    /// 请求是否可以发往上游，被拒绝时返回距离半开的时间
+    pub fn acquire(&self, now: Instant) -> Result<Permit<'_>, Duration> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.state == BreakerState::Open {
+            let elapsed = inner.opened_at.map_or(Duration::MAX, |opened_at| now.saturating_duration_since(opened_at));
+            if elapsed < self.settings.open_duration {
+                return Err(self.settings.open_duration - elapsed);
+            }
+            inner.state = BreakerState::HalfOpen;
+        }
+        if inner.state == BreakerState::HalfOpen {
+            if inner.probing {
+                return Err(Duration::from_secs(1));
+            }
+            inner.probing = true;
+        }
+        Ok(Permit { breaker: self, recorded: false })
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.inner.lock().unwrap().state
+    }
+
+    fn record(&self, success: bool, now: Instant) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.probing = false;
+        if success {
+            if inner.state != BreakerState::Closed {
+                tracing::info!("Circuit closed after a successful probe");
+            }
+            *inner = Inner { state: BreakerState::Closed, failures: 0, opened_at: None, probing: false };
+            return;
+        }
+        inner.failures += 1;
+        if inner.state == BreakerState::HalfOpen || inner.failures >= self.settings.failure_threshold {
+            if inner.state != BreakerState::Open {
+                tracing::warn!("Circuit opened after {} consecutive failures", inner.failures);
+            }
+            inner.state = BreakerState::Open;
+            inner.opened_at = Some(now);
+        }
+    }
+}
+
+/// 放行的请求，须记录结果；未记录就释放时 (如客户端断开) 不计入结果
+#[derive(Debug)]
+pub struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    recorded: bool,
+}
+
+impl Permit<'_> {
+    pub fn success(mut self) {
+        self.recorded = true;
+        self.breaker.record(true, Instant::now());
+    }
+
+    pub fn failure(mut self) {
+        self.recorded = true;
+        self.breaker.record(false, Instant::now());
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if !self.recorded {
+            self.breaker.inner.lock().unwrap().probing = false;
+        }
+    }
+}
+
+/// 各服务的熔断器
+#[derive(Debug)]
+pub struct Breakers {
+    breakers: BTreeMap<String, CircuitBreaker>,
+}
+
+impl Breakers {
+    pub fn new<'a>(services: impl IntoIterator<Item = &'a str>, settings: BreakerSettings) -> Self {
+        Self { breakers: services.into_iter().map(|name| (name.to_string(), CircuitBreaker::new(settings))).collect() }
+    }
+
+    pub fn get(&self, service: &str) -> Option<&CircuitBreaker> {
+        self.breakers.get(service)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_breaker() {
+        let breaker = CircuitBreaker::new(BreakerSettings { failure_threshold: 2, open_duration: Duration::from_secs(30) });
+        breaker.acquire(Instant::now()).unwrap().failure();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        breaker.acquire(Instant::now()).unwrap().failure();
+        assert_eq!(breaker.state(), BreakerState::Open);
+        let start = Instant::now();
+        let retry_after = breaker.acquire(start + Duration::from_secs(10)).unwrap_err();
+        assert!(retry_after > Duration::from_secs(19) && retry_after <= Duration::from_secs(20));
+
+        // 半开时只放行一个探测请求，失败后再次打开
+        let probe = breaker.acquire(start + Duration::from_secs(30)).unwrap();
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert!(breaker.acquire(start + Duration::from_secs(30)).is_err());
+        probe.failure();
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        // 未记录结果的探测请求不占用名额
+        let later = Instant::now() + Duration::from_secs(60);
+        drop(breaker.acquire(later).unwrap());
+        breaker.acquire(later).unwrap().success();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+}
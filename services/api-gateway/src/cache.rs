@@ -0,0 +1,333 @@
+//! 响应缓存
+//!
+//! 缓存 `--cache-route <路径前缀>=<秒>[:<范围>]` 匹配的 GET 请求的 200 响应，挡住看板定时刷新对上游的重复查询。
+//! 缓存键为路径、排序后的查询参数和认证范围：`public` 所有人共享，`roles` 按令牌的角色共享，`user` (默认) 按令牌的 `sub`。
+//!
+//! 上游响应 `Cache-Control` 含 `no-store` 或 `no-cache` 时不缓存，`max-age` 短于配置时按 `max-age` 缓存，`private` 只在 `user` 范围缓存；
+//! 请求带 `Cache-Control: no-cache` 时跳过缓存重新请求上游，`no-store` 时不读也不写缓存。
+//! 缓存的响应带 `ETag` (上游没有时按内容生成)、`Age` 和 `X-Cache: HIT/MISS`，`If-None-Match` 匹配时返回 304
+
+use crate::auth::Claims;
+use anyhow::{bail, Context};
+use axum::body::{Body, Bytes, HttpBody};
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 超过该大小的响应不缓存
+const MAX_ENTRY_BYTES: u64 = 1 << 20;
+
+/// 缓存键的认证范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Public,
+    Roles,
+    User,
+}
+
+impl FromStr for Scope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "public" => Ok(Self::Public),
+            "roles" => Ok(Self::Roles),
+            "user" => Ok(Self::User),
+            _ => bail!("Unknown cache scope: {} (expected public, roles or user)", s),
+        }
+    }
+}
+
+/// 一条缓存规则
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheRule {
+    pub prefix: String,
+    pub ttl: Duration,
+    pub scope: Scope,
+}
+
+impl CacheRule {
+    /// 解析 `<路径前缀>=<秒>[:<范围>]`，如 `/market/quote/=5:public`
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let (prefix, rest) = spec.split_once('=').with_context(|| format!("Invalid cache route {}, expected <prefix>=<secs>[:<scope>]", spec))?;
+        let (ttl, scope) = match rest.split_once(':') {
+            Some((ttl, scope)) => (ttl, scope.parse()?),
+            None => (rest, Scope::User),
+        };
+        let ttl = ttl.trim().parse::<u64>().with_context(|| format!("Invalid cache TTL in {}", spec))?;
+        if !prefix.starts_with('/') || ttl == 0 {
+            bail!("Invalid cache route {}, prefix must start with / and TTL must be positive", spec);
+        }
+        Ok(Self { prefix: prefix.to_string(), ttl: Duration::from_secs(ttl), scope })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    headers: HeaderMap,
+    body: Bytes,
+    etag: HeaderValue,
+    scope: Scope,
+    stored: Instant,
+    expires: Instant,
+}
+
+impl Entry {
+    fn respond(&self, request_headers: &HeaderMap, now: Instant, hit: bool) -> Response {
+        let mut headers = self.headers.clone();
+        headers.insert(header::ETAG, self.etag.clone());
+        headers.insert(header::AGE, now.saturating_duration_since(self.stored).as_secs().into());
+        headers.insert("x-cache", HeaderValue::from_static(if hit { "HIT" } else { "MISS" }));
+        if !headers.contains_key(header::CACHE_CONTROL) {
+            let visibility = if self.scope == Scope::Public { "public" } else { "private" };
+            let max_age = self.expires.saturating_duration_since(now).as_secs();
+            if let Ok(value) = HeaderValue::from_str(&format!("{}, max-age={}", visibility, max_age)) {
+                headers.insert(header::CACHE_CONTROL, value);
+            }
+        }
+        if etag_matches(request_headers, &self.etag) {
+            headers.remove(header::CONTENT_LENGTH);
+            headers.remove(header::CONTENT_TYPE);
+            return (StatusCode::NOT_MODIFIED, headers).into_response();
+        }
+        (StatusCode::OK, headers, Body::from(self.body.clone())).into_response()
+    }
+}
+
+/// 内存中的响应缓存
+#[derive(Debug)]
+pub struct ResponseCache {
+    rules: Vec<CacheRule>,
+    max_entries: usize,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl ResponseCache {
+    pub fn new(rules: Vec<CacheRule>, max_entries: usize) -> Self {
+        Self { rules, max_entries: max_entries.max(1), entries: Mutex::new(HashMap::new()) }
+    }
+
+    /// 路径匹配的最长前缀的规则
+    fn rule(&self, path: &str) -> Option<&CacheRule> {
+        self.rules.iter().filter(|rule| path.starts_with(&rule.prefix)).max_by_key(|rule| rule.prefix.len())
+    }
+
+    fn get(&self, key: &str, now: Instant) -> Option<Entry> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(entry) if entry.expires > now => Some(entry.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// 缓存满时先清理过期的条目，仍然满时淘汰最早过期的条目
+    fn insert(&self, key: String, entry: Entry, now: Instant) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            entries.retain(|_, entry| entry.expires > now);
+            if entries.len() >= self.max_entries {
+                if let Some(oldest) = entries.iter().min_by_key(|(_, entry)| entry.expires).map(|(key, _)| key.clone()) {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(key, entry);
+    }
+}
+
+/// 缓存中间件，须放在认证之后以便按令牌区分缓存
+pub async fn cache(State(cache): State<Arc<ResponseCache>>, request: Request, next: Next) -> Response {
+    if request.method() != Method::GET {
+        return next.run(request).await;
+    }
+    let Some(rule) = cache.rule(request.uri().path()).cloned() else {
+        return next.run(request).await;
+    };
+    let directives = cache_control(request.headers());
+    if directives.iter().any(|directive| directive == "no-store") {
+        return next.run(request).await;
+    }
+    let key = cache_key(&request, rule.scope);
+    let request_headers = request.headers().clone();
+    if !directives.iter().any(|directive| directive == "no-cache") {
+        if let Some(entry) = cache.get(&key, Instant::now()) {
+            return entry.respond(&request_headers, Instant::now(), true);
+        }
+    }
+
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+    let Some(ttl) = response_ttl(response.headers(), &rule) else {
+        return response;
+    };
+    let size = response
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok()?.parse::<u64>().ok())
+        .or_else(|| response.body().size_hint().exact());
+    match size {
+        Some(size) if size <= MAX_ENTRY_BYTES => {}
+        _ => return response,
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match axum::body::to_bytes(body, MAX_ENTRY_BYTES as usize).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::warn!("Failed to read response for {}: {}", key, e);
+            return StatusCode::BAD_GATEWAY.into_response();
+        }
+    };
+    let etag = parts.headers.get(header::ETAG).cloned().unwrap_or_else(|| content_etag(&body));
+    let now = Instant::now();
+    let entry = Entry { headers: parts.headers, body, etag, scope: rule.scope, stored: now, expires: now + ttl };
+    let response = entry.respond(&request_headers, now, false);
+    cache.insert(key, entry, now);
+    response
+}
+
+/// 路径、排序后的查询参数和认证范围
+fn cache_key(request: &Request, scope: Scope) -> String {
+    let mut params: Vec<&str> = request.uri().query().unwrap_or_default().split('&').filter(|param| !param.is_empty()).collect();
+    params.sort_unstable();
+    let claims = request.extensions().get::<Claims>();
+    let scope = match (scope, claims) {
+        (Scope::Public, _) | (_, None) => String::new(),
+        (Scope::Roles, Some(claims)) => {
+            let mut roles = claims.roles.clone();
+            roles.sort_unstable();
+            format!("roles:{}", roles.join(","))
+        }
+        (Scope::User, Some(claims)) => format!("user:{}", claims.sub),
+    };
+    format!("{}?{}#{}", request.uri().path(), params.join("&"), scope)
+}
+
+/// 按上游的 `Cache-Control` 决定缓存时间，不可缓存时返回 `None`
+fn response_ttl(headers: &HeaderMap, rule: &CacheRule) -> Option<Duration> {
+    let mut ttl = rule.ttl;
+    for directive in cache_control(headers) {
+        match directive.as_str() {
+            "no-store" | "no-cache" => return None,
+            "private" if rule.scope != Scope::User => return None,
+            _ => {}
+        }
+        if let Some(max_age) = directive.strip_prefix("max-age=").and_then(|secs| secs.trim_matches('"').parse().ok()) {
+            ttl = ttl.min(Duration::from_secs(max_age));
+        }
+    }
+    (!ttl.is_zero()).then_some(ttl)
+}
+
+fn cache_control(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|directive| directive.trim().to_ascii_lowercase())
+        .filter(|directive| !directive.is_empty())
+        .collect()
+}
+
+fn content_etag(body: &Bytes) -> HeaderValue {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    HeaderValue::from_str(&format!("W/\"{:016x}\"", hasher.finish())).expect("valid etag")
+}
+
+/// `If-None-Match` 按弱比较匹配
+fn etag_matches(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let Ok(etag) = etag.to_str() else {
+        return false;
+    };
+    let etag = etag.trim_start_matches("W/");
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderName;
+    use axum::routing::get;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_parse_rule() {
+        let rule = CacheRule::parse("/market/quote/=5:public").unwrap();
+        assert_eq!(rule, CacheRule { prefix: "/market/quote/".to_string(), ttl: Duration::from_secs(5), scope: Scope::Public });
+        assert_eq!(CacheRule::parse("/api/v1/data-engine/tables=60").unwrap().scope, Scope::User);
+        assert!(CacheRule::parse("/tables").is_err());
+        assert!(CacheRule::parse("/tables=0").is_err());
+        assert!(CacheRule::parse("/tables=60:team").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cache() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let rules = vec![CacheRule::parse("/tables=60:roles").unwrap(), CacheRule::parse("/tables/private=60:public").unwrap()];
+        let app = axum::Router::new()
+            .route(
+                "/tables",
+                get(move || async move { format!("tables {}", counter.fetch_add(1, Ordering::SeqCst)) }),
+            )
+            .route("/tables/private", get(|| async { ([(header::CACHE_CONTROL, "private")], "secret") }))
+            .layer(axum::middleware::from_fn_with_state(Arc::new(ResponseCache::new(rules, 16)), cache));
+        let request = |uri: &str, roles: &[&str], headers: &[(&'static str, &str)]| {
+            let mut request = Request::get(uri).body(Body::empty()).unwrap();
+            let roles = roles.iter().map(|role| role.to_string()).collect();
+            request.extensions_mut().insert(Claims { sub: "alice".to_string(), exp: 0, roles });
+            for (name, value) in headers {
+                request.headers_mut().insert(HeaderName::from_static(name), HeaderValue::from_str(value).unwrap());
+            }
+            request
+        };
+        let body = |response: Response| async move { axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap() };
+
+        let response = app.clone().oneshot(request("/tables?b=2&a=1", &["analyst"], &[])).await.unwrap();
+        assert_eq!(response.headers()["x-cache"], "MISS");
+        let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+        assert_eq!(body(response).await, "tables 0");
+
+        // 查询参数顺序不影响缓存键
+        let response = app.clone().oneshot(request("/tables?a=1&b=2", &["analyst"], &[])).await.unwrap();
+        assert_eq!(response.headers()["x-cache"], "HIT");
+        assert_eq!(body(response).await, "tables 0");
+
+        let response = app.clone().oneshot(request("/tables?a=1&b=2", &["analyst"], &[("if-none-match", &etag)])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        // 不同角色和 no-cache 都不使用已有的缓存
+        let response = app.clone().oneshot(request("/tables?a=1&b=2", &["admin"], &[])).await.unwrap();
+        assert_eq!(body(response).await, "tables 1");
+        let response = app.clone().oneshot(request("/tables?a=1&b=2", &["analyst"], &[("cache-control", "no-cache")])).await.unwrap();
+        assert_eq!(body(response).await, "tables 2");
+        let response = app.clone().oneshot(request("/tables?a=1&b=2", &["analyst"], &[])).await.unwrap();
+        assert_eq!(body(response).await, "tables 2");
+
+        // 共享范围不缓存上游标记为 private 的响应
+        let response = app.clone().oneshot(request("/tables/private", &[], &[])).await.unwrap();
+        assert!(response.headers().get("x-cache").is_none());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,442 @@
+//! 服务发现和负载均衡
+//!
+//! 上游服务的实例由 `--upstream <服务>=<地址>` 声明，同一服务可以声明多次：
+//! - `http://host:port`、`https://host:port`：固定实例
+//! - `dns://host:port`：定期解析域名的全部地址，每个地址一个实例 (如 Kubernetes 的 headless service)
+//! - `consul://agent:8500`：定期从 Consul 查询通过健康检查的同名服务，也可以指定服务名，如 `consul://agent:8500/alpha-data-engine`
+//!
+//! 每个实例定期请求 `--health-check-path`，连续失败 `--unhealthy-threshold` 次后摘除，检查恢复后重新加入；
+//! 转发请求时连接失败同样计入失败次数。请求按 `--balance` (`round-robin` 或 `least-connections`) 分配到健康的实例
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// 单次健康检查的超时
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 实例来源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// 固定地址，不带末尾的 `/`
+    Static(String),
+    Dns { host: String, port: u16 },
+    Consul { agent: String, service: String },
+}
+
+impl Source {
+    /// 解析 `<服务>=<地址>`
+    pub fn parse_upstream(spec: &str) -> anyhow::Result<(String, Self)> {
+        let Some((name, address)) = spec.split_once('=') else {
+            bail!("Invalid upstream {:?}, expected <service>=<url>", spec);
+        };
+        let name = name.trim();
+        if name.is_empty() || name.contains('/') {
+            bail!("Invalid upstream service name {:?}", name);
+        }
+        let url = reqwest::Url::parse(address.trim()).with_context(|| format!("Invalid upstream URL {:?}", address))?;
+        let host = url.host_str().with_context(|| format!("Upstream URL {:?} has no host", address))?;
+        let source = match url.scheme() {
+            "http" | "https" => Self::Static(url.as_str().trim_end_matches('/').to_string()),
+            "dns" => {
+                let port = url.port().with_context(|| format!("DNS upstream {:?} needs a port", address))?;
+                Self::Dns { host: host.to_string(), port }
+            }
+            "consul" => {
+                let service = url.path().trim_matches('/');
+                Self::Consul {
+                    agent: format!("http://{}:{}", host, url.port().unwrap_or(8500)),
+                    service: if service.is_empty() { name.to_string() } else { service.to_string() },
+                }
+            }
+            scheme => bail!("Unsupported upstream scheme {:?} in {:?}", scheme, address),
+        };
+        Ok((name.to_string(), source))
+    }
+
+    /// 当前的实例地址
+    async fn resolve(&self, client: &reqwest::Client) -> anyhow::Result<Vec<String>> {
+        match self {
+            Self::Static(url) => Ok(vec![url.clone()]),
+            Self::Dns { host, port } => {
+                let addresses = tokio::net::lookup_host((host.as_str(), *port))
+                    .await
+                    .with_context(|| format!("Failed to resolve {}", host))?;
+                Ok(addresses.map(|address| format!("http://{}", address)).collect())
+            }
+            Self::Consul { agent, service } => {
+                let url = format!("{}/v1/health/service/{}?passing=true", agent, service);
+                let entries: Vec<ConsulEntry> = client
+                    .get(&url)
+                    .timeout(HEALTH_CHECK_TIMEOUT)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .with_context(|| format!("Failed to query Consul for {}", service))?
+                    .json()
+                    .await
+                    .with_context(|| format!("Invalid Consul response for {}", service))?;
+                Ok(entries.into_iter().map(ConsulEntry::url).collect())
+            }
+        }
+    }
+}
+
+/// Consul `/v1/health/service` 的一项，服务没有地址时使用节点地址
+#[derive(Debug, Deserialize)]
+struct ConsulEntry {
+    #[serde(rename = "Node")]
+    node: ConsulNode,
+    #[serde(rename = "Service")]
+    service: ConsulService,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConsulNode {
+    #[serde(rename = "Address")]
+    address: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConsulService {
+    #[serde(rename = "Address", default)]
+    address: String,
+    #[serde(rename = "Port")]
+    port: u16,
+}
+
+impl ConsulEntry {
+    fn url(self) -> String {
+        let address = if self.service.address.is_empty() { self.node.address } else { self.service.address };
+        match address.parse::<std::net::Ipv6Addr>() {
+            Ok(_) => format!("http://[{}]:{}", address, self.service.port),
+            Err(_) => format!("http://{}:{}", address, self.service.port),
+        }
+    }
+}
+
+/// 负载均衡策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Balance {
+    /// 依次轮询健康的实例
+    RoundRobin,
+    /// 选择进行中请求最少的实例
+    LeastConnections,
+}
+
+/// 一个上游实例
+#[derive(Debug)]
+pub struct Instance {
+    pub url: String,
+    healthy: AtomicBool,
+    failures: AtomicU32,
+    /// 进行中的请求数
+    active: AtomicUsize,
+    /// 最近一次健康检查的耗时
+    latency_ms: AtomicU64,
+}
+
+impl Instance {
+    /// 新实例在第一次检查前视为健康
+    fn new(url: String) -> Self {
+        Self {
+            url,
+            healthy: AtomicBool::new(true),
+            failures: AtomicU32::new(0),
+            active: AtomicUsize::new(0),
+            latency_ms: AtomicU64::new(0),
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+}
+
+/// 分配给一个请求的实例，请求结束时释放
+#[derive(Debug)]
+pub struct Lease {
+    instance: Arc<Instance>,
+}
+
+impl Lease {
+    fn new(instance: Arc<Instance>) -> Self {
+        instance.active.fetch_add(1, Ordering::Relaxed);
+        Self { instance }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.instance.url
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        self.instance.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 无法分配实例的原因
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PickError {
+    #[error("Unknown service {0}")]
+    UnknownService(String),
+    #[error("No healthy instance of {0}")]
+    Unavailable(String),
+}
+
+#[derive(Debug)]
+struct Service {
+    sources: Vec<Source>,
+    instances: RwLock<Vec<Arc<Instance>>>,
+    next: AtomicUsize,
+}
+
+/// `/health` 中一个服务的状态
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceHealth {
+    pub name: String,
+    pub instances: usize,
+    pub healthy_instances: usize,
+    /// 健康实例中最短的检查耗时
+    pub response_time_ms: u64,
+}
+
+/// 全部上游服务
+#[derive(Debug)]
+pub struct Registry {
+    services: BTreeMap<String, Service>,
+    balance: Balance,
+    unhealthy_threshold: u32,
+    client: reqwest::Client,
+}
+
+impl Registry {
+    /// 固定实例立即可用，DNS 和 Consul 的实例在第一次刷新后可用
+    pub fn new(upstreams: Vec<(String, Source)>, balance: Balance, unhealthy_threshold: u32) -> Self {
+        let mut services: BTreeMap<String, Service> = BTreeMap::new();
+        for (name, source) in upstreams {
+            let service = services.entry(name).or_insert_with(|| Service {
+                sources: Vec::new(),
+                instances: RwLock::new(Vec::new()),
+                next: AtomicUsize::new(0),
+            });
+            if let Source::Static(url) = &source {
+                service.instances.write().unwrap().push(Arc::new(Instance::new(url.clone())));
+            }
+            service.sources.push(source);
+        }
+        Self { services, balance, unhealthy_threshold: unhealthy_threshold.max(1), client: reqwest::Client::new() }
+    }
+
+    pub fn services(&self) -> impl Iterator<Item = &str> {
+        self.services.keys().map(String::as_str)
+    }
+
+    /// 为一个请求分配健康的实例，跳过 `tried` 中已经失败的地址
+    pub fn pick(&self, service: &str, tried: &[String]) -> Result<Lease, PickError> {
+        let entry = self.services.get(service).ok_or_else(|| PickError::UnknownService(service.to_string()))?;
+        let instances = entry.instances.read().unwrap();
+        let healthy: Vec<&Arc<Instance>> =
+            instances.iter().filter(|instance| instance.is_healthy() && !tried.contains(&instance.url)).collect();
+        if healthy.is_empty() {
+            return Err(PickError::Unavailable(service.to_string()));
+        }
+        let start = entry.next.fetch_add(1, Ordering::Relaxed);
+        let instance = match self.balance {
+            Balance::RoundRobin => healthy[start % healthy.len()],
+            // 从轮询位置开始找，进行中请求数相同的实例轮流分配
+            Balance::LeastConnections => (0..healthy.len())
+                .map(|offset| healthy[(start + offset) % healthy.len()])
+                .min_by_key(|instance| instance.active.load(Ordering::Relaxed))
+                .expect("healthy instances"),
+        };
+        Ok(Lease::new(instance.clone()))
+    }
+
+    /// 转发请求时连接失败
+    pub fn report_failure(&self, lease: &Lease) {
+        self.record_failure(&lease.instance, "connection failed");
+    }
+
+    pub fn health(&self) -> Vec<ServiceHealth> {
+        self.services
+            .iter()
+            .map(|(name, service)| {
+                let instances = service.instances.read().unwrap();
+                let healthy: Vec<&Arc<Instance>> = instances.iter().filter(|instance| instance.is_healthy()).collect();
+                ServiceHealth {
+                    name: name.clone(),
+                    instances: instances.len(),
+                    healthy_instances: healthy.len(),
+                    response_time_ms: healthy
+                        .iter()
+                        .map(|instance| instance.latency_ms.load(Ordering::Relaxed))
+                        .min()
+                        .unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+
+    /// 重新解析实例来源，保留仍在的实例的状态；有来源解析失败时保留原有实例
+    async fn refresh(&self, name: &str, service: &Service) {
+        let mut urls = Vec::new();
+        for source in &service.sources {
+            match source.resolve(&self.client).await {
+                Ok(resolved) => urls.extend(resolved),
+                Err(e) => {
+                    tracing::warn!("Failed to discover instances of {}: {:#}", name, e);
+                    return;
+                }
+            }
+        }
+        urls.sort();
+        urls.dedup();
+
+        let mut instances = service.instances.write().unwrap();
+        let refreshed: Vec<Arc<Instance>> = urls
+            .into_iter()
+            .map(|url| match instances.iter().find(|instance| instance.url == url) {
+                Some(instance) => instance.clone(),
+                None => {
+                    tracing::info!("Discovered {} instance {}", name, url);
+                    Arc::new(Instance::new(url))
+                }
+            })
+            .collect();
+        for removed in instances.iter().filter(|instance| !refreshed.iter().any(|kept| kept.url == instance.url)) {
+            tracing::info!("Removed {} instance {}", name, removed.url);
+        }
+        *instances = refreshed;
+    }
+
+    /// 请求实例的健康检查地址
+    async fn check(&self, name: &str, instance: &Instance, path: &str) {
+        let started = Instant::now();
+        let result = self
+            .client
+            .get(format!("{}{}", instance.url, path))
+            .timeout(HEALTH_CHECK_TIMEOUT)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        match result {
+            Ok(_) => {
+                instance.latency_ms.store(started.elapsed().as_millis() as u64, Ordering::Relaxed);
+                instance.failures.store(0, Ordering::Relaxed);
+                if !instance.healthy.swap(true, Ordering::Relaxed) {
+                    tracing::info!("{} instance {} is healthy again", name, instance.url);
+                }
+            }
+            Err(e) => self.record_failure(instance, &e.to_string()),
+        }
+    }
+
+    fn record_failure(&self, instance: &Instance, reason: &str) {
+        let failures = instance.failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= self.unhealthy_threshold && instance.healthy.swap(false, Ordering::Relaxed) {
+            tracing::warn!("Removing unhealthy instance {} after {} failures: {}", instance.url, failures, reason);
+        }
+    }
+}
+
+/// 定期刷新实例并做健康检查，启动时立即执行一次
+pub fn spawn(registry: Arc<Registry>, interval: Duration, path: String) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            for (name, service) in &registry.services {
+                registry.refresh(name, service).await;
+                let instances = service.instances.read().unwrap().clone();
+                let checks = instances.iter().map(|instance| registry.check(name, instance, &path));
+                futures::future::join_all(checks).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_upstream() {
+        assert_eq!(
+            Source::parse_upstream("data-engine=http://10.0.0.1:8081/").unwrap(),
+            ("data-engine".to_string(), Source::Static("http://10.0.0.1:8081".to_string()))
+        );
+        assert_eq!(
+            Source::parse_upstream("data-engine=dns://data-engine.alpha.svc:8081").unwrap().1,
+            Source::Dns { host: "data-engine.alpha.svc".to_string(), port: 8081 }
+        );
+        assert_eq!(
+            Source::parse_upstream("data-engine=consul://consul").unwrap().1,
+            Source::Consul { agent: "http://consul:8500".to_string(), service: "data-engine".to_string() }
+        );
+        assert!(Source::parse_upstream("data-engine").is_err());
+        assert!(Source::parse_upstream("data-engine=dns://data-engine").is_err());
+        assert!(Source::parse_upstream("data-engine=ftp://host").is_err());
+    }
+
+    fn three_instances(balance: Balance) -> Registry {
+        let upstreams = ["a=http://a:1", "a=http://b:1", "a=http://c:1"];
+        Registry::new(upstreams.iter().map(|spec| Source::parse_upstream(spec).unwrap()).collect(), balance, 2)
+    }
+
+    #[test]
+    fn test_balance() {
+        let registry = three_instances(Balance::RoundRobin);
+        let urls: Vec<String> = (0..4).map(|_| registry.pick("a", &[]).unwrap().url().to_string()).collect();
+        assert_eq!(urls, vec!["http://a:1", "http://b:1", "http://c:1", "http://a:1"]);
+        assert_eq!(registry.pick("b", &[]).unwrap_err(), PickError::UnknownService("b".to_string()));
+
+        // 连续失败达到阈值后摘除
+        let lease = registry.pick("a", &[]).unwrap();
+        assert_eq!(lease.url(), "http://b:1");
+        registry.report_failure(&lease);
+        registry.report_failure(&lease);
+        drop(lease);
+        let urls: Vec<String> = (0..4).map(|_| registry.pick("a", &[]).unwrap().url().to_string()).collect();
+        assert!(!urls.contains(&"http://b:1".to_string()));
+        assert_eq!(registry.health()[0].healthy_instances, 2);
+        let tried = vec!["http://a:1".to_string(), "http://c:1".to_string()];
+        assert_eq!(registry.pick("a", &tried).unwrap_err(), PickError::Unavailable("a".to_string()));
+
+        let registry = three_instances(Balance::LeastConnections);
+        let first = registry.pick("a", &[]).unwrap();
+        let second = registry.pick("a", &[]).unwrap();
+        assert_eq!(registry.pick("a", &[]).unwrap().url(), "http://c:1");
+        drop(first);
+        assert_eq!(registry.pick("a", &[]).unwrap().url(), "http://a:1");
+        drop(second);
+    }
+
+    #[tokio::test]
+    async fn test_health_check() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let healthy = format!("a=http://{}", listener.local_addr().unwrap());
+        let app = axum::Router::new().route("/health", axum::routing::get(|| async { "ok" }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        // 绑定后立即释放的端口上没有服务
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let unhealthy = format!("a=http://{}", closed);
+
+        let upstreams = [healthy, unhealthy].iter().map(|spec| Source::parse_upstream(spec).unwrap()).collect();
+        let registry = Registry::new(upstreams, Balance::RoundRobin, 1);
+        let service = &registry.services["a"];
+        registry.refresh("a", service).await;
+        let instances = service.instances.read().unwrap().clone();
+        for instance in &instances {
+            registry.check("a", instance, "/health").await;
+        }
+        let health: Vec<bool> = instances.iter().map(|instance| instance.is_healthy()).collect();
+        assert_eq!(health.iter().filter(|healthy| **healthy).count(), 1);
+        assert_eq!(registry.health()[0].healthy_instances, 1);
+    }
+}
@@ -1,23 +1,51 @@
 //! Alpha Finance API Gateway
 //!
-//! 统一的 API 入口点，负责路由、认证、限流和负载均衡
-
+//! 统一的 API 入口点，负责路由、认证、限流和负载均衡。
+//! `/api/v1/<服务>/...` 按服务发现的结果转发到健康的上游实例 (见 [`discovery`]、[`proxy`])，
+//! 上游调用有超时、幂等请求重试和按服务的熔断 (见 [`breaker`])。
+//! 配置 JWKS 后除 `/health` 外的路由都要求 JWT，`/api/v1` 还要求 `analyst` 或 `admin` 角色 (见 [`auth`])。
+//! 请求按 API Key 或客户端 IP 限流 (见 [`ratelimit`])，行情快照和目录查询等 GET 响应在网关缓存 (见 [`cache`])。
+//! `/ws` 的 WebSocket 连接转发到 real-time-feed (见 [`ws`])。
+//! `/openapi.json` 和 `/docs` 提供合并了上游服务接口的 OpenAPI 文档 (见 [`openapi`])
+
+mod auth;
+mod breaker;
+mod cache;
+mod discovery;
+mod openapi;
+mod proxy;
+mod ratelimit;
+mod ws;
+
+use alpha_core::errors::AlphaError;
+use alpha_core::models::MarketData;
+use alpha_core::provider::SymbolMatch;
+use alpha_core::utils::series::parse_timeframe;
+use alpha_providers::{DataProvider, ProviderChain, ProviderSettings};
+use auth::{AuthSettings, Authenticator};
 use axum::{
-    extract::Query,
-    http::{HeaderMap, StatusCode},
+    extract::{Path, Query, Request, State},
+    http::StatusCode,
     middleware,
-    response::Json,
-    routing::{get, post},
+    response::{IntoResponse, Json, Response},
+    routing::{any, get},
     Router,
 };
+use breaker::{BreakerSettings, BreakerState};
+use cache::{CacheRule, ResponseCache};
 use clap::Parser;
+use discovery::{Balance, Registry, Source};
+use proxy::{Proxy, ProxySettings};
+use ratelimit::{RateLimitSettings, RateLimiter, RedisBuckets};
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tower::ServiceBuilder;
 use tower_http::{
     cors::{Any, CorsLayer},
     trace::TraceLayer,
 };
+use utoipa::{IntoParams, ToSchema};
 
 /// API 网关配置
 #[derive(Parser, Debug)]
@@ -27,17 +55,150 @@ struct Args {
     #[arg(short, long, default_value = "0.0.0.0:8080")]
     bind: SocketAddr,
 
-    /// 服务发现地址
-    #[arg(long, default_value = "http://localhost:8081")]
-    discovery_url: String,
+    /// 上游服务实例 `<服务>=<地址>`，地址可以是 `http(s)://`、`dns://host:port` 或 `consul://agent:8500` (见 [`discovery`])
+    #[arg(long = "upstream", value_delimiter = ',', default_value = "data-engine=http://localhost:8081,real-time-feed=http://localhost:8082")]
+    upstreams: Vec<String>,
+
+    /// 负载均衡策略
+    #[arg(long, value_enum, default_value_t = Balance::RoundRobin)]
+    balance: Balance,
+
+    /// 健康检查和实例刷新的间隔 (秒)
+    #[arg(long, default_value_t = 10)]
+    health_check_interval_secs: u64,
+
+    /// 上游实例的健康检查路径
+    #[arg(long, default_value = "/health")]
+    health_check_path: String,
+
+    /// 连续失败多少次后摘除实例
+    #[arg(long, default_value_t = 2)]
+    unhealthy_threshold: u32,
+
+    /// 等待上游响应的超时 (秒)
+    #[arg(long, default_value_t = 30)]
+    upstream_timeout_secs: u64,
+
+    /// 幂等请求超时或上游返回 502/503/504 时的最大重试次数
+    #[arg(long, default_value_t = 2)]
+    max_retries: u32,
+
+    /// 连续失败多少次后熔断一个服务
+    #[arg(long, default_value_t = 5)]
+    breaker_failure_threshold: u32,
+
+    /// 熔断后多久放行探测请求 (秒)
+    #[arg(long, default_value_t = 30)]
+    breaker_open_secs: u64,
+
+    /// JWKS 地址，设置后启用 JWT 认证
+    #[arg(long)]
+    jwks_url: Option<String>,
+
+    /// 要求的令牌签发者 (`iss`)
+    #[arg(long)]
+    jwt_issuer: Option<String>,
+
+    /// 要求的令牌受众 (`aud`)
+    #[arg(long)]
+    jwt_audience: Option<String>,
+
+    /// JWKS 的缓存时间 (秒)
+    #[arg(long, default_value_t = 300)]
+    jwks_cache_secs: u64,
+
+    /// 限流额度和 API Key 的配置文件，未设置时只按 IP 使用默认额度 (见 [`ratelimit`])
+    #[arg(long)]
+    rate_limit_config: Option<std::path::PathBuf>,
+
+    /// 多实例共享令牌桶的 Redis 地址
+    #[arg(long)]
+    rate_limit_redis_url: Option<String>,
+
+    /// 网关前可信的反向代理层数，客户端 IP 取 `X-Forwarded-For` 从右数第 N 个地址；0 表示直接使用连接的对端地址
+    #[arg(long, default_value_t = 0)]
+    trusted_proxies: usize,
+
+    /// 缓存的路由 `<路径前缀>=<秒>[:public|roles|user]`，范围决定缓存在哪些令牌之间共享 (见 [`cache`])
+    #[arg(long = "cache-route", value_delimiter = ',', default_value = "/market/quote/=5:public,/api/v1/data-engine/tables=60:roles")]
+    cache_routes: Vec<String>,
+
+    /// 响应缓存的最大条目数
+    #[arg(long, default_value_t = 10_000)]
+    cache_max_entries: usize,
 
     /// 日志级别
     #[arg(short, long, default_value = "info")]
     log_level: String,
+
+    /// 行情数据源，按顺序回退；API Key 从环境变量 `<NAME>_API_KEY` 读取，如 `ALPHA_VANTAGE_API_KEY`
+    #[arg(long, value_delimiter = ',', default_value = "yahoo")]
+    providers: Vec<String>,
+}
+
+impl Args {
+    fn provider_settings(&self) -> Vec<ProviderSettings> {
+        self.providers.iter()
+            .map(|name| ProviderSettings {
+                api_key: std::env::var(format!("{}_API_KEY", name.to_uppercase())).ok(),
+                ..ProviderSettings::new(name.trim())
+            })
+            .collect()
+    }
+
+    fn registry(&self) -> anyhow::Result<Registry> {
+        let upstreams = self.upstreams.iter().map(|spec| Source::parse_upstream(spec)).collect::<anyhow::Result<_>>()?;
+        Ok(Registry::new(upstreams, self.balance, self.unhealthy_threshold))
+    }
+
+    fn proxy_settings(&self) -> ProxySettings {
+        ProxySettings {
+            timeout: std::time::Duration::from_secs(self.upstream_timeout_secs.max(1)),
+            retry: proxy::retry_policy(self.max_retries),
+            breaker: BreakerSettings {
+                failure_threshold: self.breaker_failure_threshold.max(1),
+                open_duration: std::time::Duration::from_secs(self.breaker_open_secs),
+            },
+        }
+    }
+
+    fn response_cache(&self) -> anyhow::Result<ResponseCache> {
+        let rules = self
+            .cache_routes
+            .iter()
+            .filter(|spec| !spec.trim().is_empty())
+            .map(|spec| CacheRule::parse(spec.trim()))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(ResponseCache::new(rules, self.cache_max_entries))
+    }
+
+    fn auth_settings(&self) -> Option<AuthSettings> {
+        Some(AuthSettings {
+            jwks_url: self.jwks_url.clone()?,
+            issuer: self.jwt_issuer.clone(),
+            audience: self.jwt_audience.clone(),
+            jwks_ttl: std::time::Duration::from_secs(self.jwks_cache_secs),
+        })
+    }
+
+    async fn rate_limiter(&self) -> anyhow::Result<RateLimiter> {
+        let settings = match &self.rate_limit_config {
+            Some(path) => RateLimitSettings::load(path)?,
+            None => RateLimitSettings::default(),
+        };
+        let redis = match &self.rate_limit_redis_url {
+            Some(url) => Some(RedisBuckets::connect(url).await?),
+            None => None,
+        };
+        Ok(RateLimiter::new(settings, redis, self.trusted_proxies))
+    }
 }
 
+/// `/api/v1` 代理要求的角色
+const API_ROLES: &[&str] = &["analyst", "admin"];
+
 /// 健康检查响应
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct HealthResponse {
     status: String,
     version: String,
@@ -45,11 +206,15 @@ struct HealthResponse {
     services: Vec<ServiceStatus>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct ServiceStatus {
     name: String,
+    /// `healthy`、`degraded` 或 `unhealthy`
     status: String,
     response_time_ms: u64,
+    instances: usize,
+    healthy_instances: usize,
+    circuit: BreakerState,
 }
 
 /// API 路由响应
@@ -59,7 +224,7 @@ struct ApiRequest {
     path: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct ApiResponse<T> {
     success: bool,
     data: Option<T>,
@@ -67,6 +232,34 @@ struct ApiResponse<T> {
     timestamp: chrono::DateTime<chrono::Utc>,
 }
 
+/// K 线查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct CandlesQuery {
+    /// K 线周期，如 `1m`、`1h`、`1d`
+    #[serde(default = "default_timeframe")]
+    #[param(default = "1d")]
+    timeframe: String,
+    #[serde(default = "default_limit")]
+    #[param(default = 200)]
+    limit: usize,
+}
+
+fn default_timeframe() -> String {
+    "1d".to_string()
+}
+
+fn default_limit() -> usize {
+    200
+}
+
+/// 代码搜索参数
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct SearchQuery {
+    q: String,
+}
+
 impl<T> ApiResponse<T> {
     fn success(data: T) -> Self {
         Self {
@@ -106,15 +299,32 @@ async fn main() -> anyhow::Result<()> {
 
     tracing::info!("Starting Alpha Finance API Gateway");
 
+    let provider = alpha_providers::default_registry().chain(&args.provider_settings())?;
+    tracing::info!("Market data providers: {:?}", provider);
+
+    let auth = args.auth_settings().map(|settings| Arc::new(Authenticator::new(settings)));
+    if auth.is_none() {
+        tracing::warn!("JWT authentication is disabled, set --jwks-url to enable it");
+    }
+
+    let limiter = Arc::new(args.rate_limiter().await?);
+    let registry = Arc::new(args.registry()?);
+    let interval = std::time::Duration::from_secs(args.health_check_interval_secs.max(1));
+    discovery::spawn(registry.clone(), interval, args.health_check_path.clone());
+    let proxy = Arc::new(Proxy::new(registry.clone(), args.proxy_settings()));
+    let cache = Arc::new(args.response_cache()?);
+    let docs = Arc::new(openapi::Docs::new(registry.clone()));
+
     // 构建路由
     let app = Router::new()
-        // 健康检查
-        .route("/health", get(health_check))
-        // API 代理
-        .route("/api/v1/*path", get(api_proxy))
-        .route("/api/v1/*path", post(api_proxy))
+        // 健康检查和 API 代理
+        .merge(proxy_routes(proxy, cache.clone(), auth.clone()))
+        // API 文档
+        .merge(openapi::routes(docs))
         // WebSocket 代理
-        .route("/ws/*path", get(ws_proxy))
+        .merge(auth::protect(ws_routes(registry), auth.clone(), &[]))
+        // 行情数据
+        .merge(auth::protect(market_routes(provider).layer(middleware::from_fn_with_state(cache, cache::cache)), auth, &[]))
         // 中间件
         .layer(
             ServiceBuilder::new()
@@ -126,39 +336,56 @@ async fn main() -> anyhow::Result<()> {
                         .allow_headers(Any),
                 )
                 .layer(middleware::from_fn(request_logger))
+                .layer(middleware::from_fn_with_state(limiter, ratelimit::rate_limit))
         );
 
     // 启动服务器
     let listener = tokio::net::TcpListener::bind(args.bind).await?;
     tracing::info!("API Gateway listening on {}", args.bind);
 
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
 
     Ok(())
 }
 
-/// 健康检查端点
-async fn health_check() -> Json<HealthResponse> {
-    let services = vec![
-        ServiceStatus {
-            name: "data-engine".to_string(),
-            status: "healthy".to_string(),
-            response_time_ms: 15,
-        },
-        ServiceStatus {
-            name: "real-time-feed".to_string(),
-            status: "healthy".to_string(),
-            response_time_ms: 8,
-        },
-        ServiceStatus {
-            name: "collector".to_string(),
-            status: "healthy".to_string(),
-            response_time_ms: 22,
-        },
-    ];
+/// 健康检查和 API 代理路由，健康检查不要求认证；缓存在认证之后，按令牌区分缓存
+fn proxy_routes(proxy: Arc<Proxy>, cache: Arc<ResponseCache>, auth: Option<Arc<Authenticator>>) -> Router {
+    let api = Router::new()
+        .route("/api/v1/*path", any(api_proxy))
+        .layer(middleware::from_fn_with_state(cache, cache::cache));
+    let api = auth::protect(api, auth, API_ROLES);
+    Router::new()
+        .route("/health", get(health_check))
+        .merge(api)
+        .with_state(proxy)
+}
+
+/// 健康检查端点，上游服务的状态取自最近一次健康检查和熔断器；有服务没有健康实例或熔断打开时为 `degraded`
+#[utoipa::path(get, path = "/health", tag = "gateway", security(()), responses((status = 200, body = HealthResponse)))]
+async fn health_check(State(proxy): State<Arc<Proxy>>) -> Json<HealthResponse> {
+    let services: Vec<ServiceStatus> = proxy
+        .registry()
+        .health()
+        .into_iter()
+        .map(|service| ServiceStatus {
+            status: match (service.healthy_instances, proxy.breaker_state(&service.name)) {
+                (0, _) | (_, BreakerState::Open) => "unhealthy",
+                (_, BreakerState::HalfOpen) => "degraded",
+                (healthy, _) if healthy < service.instances => "degraded",
+                _ => "healthy",
+            }
+            .to_string(),
+            circuit: proxy.breaker_state(&service.name),
+            name: service.name,
+            response_time_ms: service.response_time_ms,
+            instances: service.instances,
+            healthy_instances: service.healthy_instances,
+        })
+        .collect();
+    let degraded = services.iter().any(|service| service.status == "unhealthy");
 
     Json(HealthResponse {
-        status: "ok".to_string(),
+        status: if degraded { "degraded" } else { "ok" }.to_string(),
         version: env!("CARGO_PKG_VERSION").to_string(),
         timestamp: chrono::Utc::now(),
         services,
@@ -167,37 +394,121 @@ async fn health_check() -> Json<HealthResponse> {
 
 /// API 代理端点
 async fn api_proxy(
-    axum::extract::Path(path): axum::extract::Path<String>,
-    headers: HeaderMap,
-    query: Query<std::collections::HashMap<String, String>>,
-) -> Json<ApiResponse<serde_json::Value>> {
-    // 这里应该实现实际的代理逻辑
-    // 包括服务发现、负载均衡、认证等
+    State(proxy): State<Arc<Proxy>>,
+    Path(path): Path<String>,
+    request: Request,
+) -> Response {
+    proxy.forward(&path, request).await
+}
+
+/// 行情路由，直接由数据源回退链提供
+fn market_routes(provider: ProviderChain) -> Router {
+    Router::new()
+        .route("/market/candles/:symbol", get(market_candles))
+        .route("/market/quote/:symbol", get(market_quote))
+        .route("/market/search", get(market_search))
+        .with_state(provider)
+}
 
-    tracing::info!("Proxying request to: {}", path);
+/// K 线，按时间升序
+#[utoipa::path(
+    get,
+    path = "/market/candles/{symbol}",
+    tag = "market",
+    params(("symbol" = String, Path), CandlesQuery),
+    responses(
+        (status = 200, body = ApiResponse<Vec<MarketData>>),
+        (status = 400, description = "周期无效"),
+        (status = 404, description = "代码不存在"),
+        (status = 502, description = "数据源不可用"),
+    )
+)]
+async fn market_candles(
+    State(provider): State<ProviderChain>,
+    Path(symbol): Path<String>,
+    Query(query): Query<CandlesQuery>,
+) -> Response {
+    let result = match parse_timeframe(&query.timeframe) {
+        Ok(timeframe) => provider.candles(&symbol, timeframe, query.limit).await,
+        Err(e) => Err(e),
+    };
+    market_response(result)
+}
 
-    // 模拟代理响应
-    let mock_data = serde_json::json!({
-        "path": path,
-        "query": serde_json::to_value(query.into_inner()).unwrap_or_default(),
-        "timestamp": chrono::Utc::now(),
-    });
+/// 最新行情
+#[utoipa::path(
+    get,
+    path = "/market/quote/{symbol}",
+    tag = "market",
+    params(("symbol" = String, Path)),
+    responses(
+        (status = 200, body = ApiResponse<MarketData>),
+        (status = 404, description = "代码不存在"),
+        (status = 502, description = "数据源不可用"),
+    )
+)]
+async fn market_quote(
+    State(provider): State<ProviderChain>,
+    Path(symbol): Path<String>,
+) -> Response {
+    market_response(provider.quote(&symbol).await)
+}
+
+/// 按代码或名称搜索
+#[utoipa::path(get, path = "/market/search", tag = "market", params(SearchQuery), responses((status = 200, body = ApiResponse<Vec<SymbolMatch>>)))]
+async fn market_search(
+    State(provider): State<ProviderChain>,
+    Query(query): Query<SearchQuery>,
+) -> Response {
+    market_response(provider.search(&query.q).await)
+}
+
+/// 将数据源结果转换为统一响应，错误按类别映射 HTTP 状态码
+fn market_response<T: Serialize>(result: Result<T, AlphaError>) -> Response {
+    match result {
+        Ok(data) => Json(ApiResponse::success(data)).into_response(),
+        Err(err) => {
+            let status = match err.root() {
+                AlphaError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+                AlphaError::DataNotFound(_) => StatusCode::NOT_FOUND,
+                AlphaError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+                AlphaError::NetworkError(_) | AlphaError::ServiceUnavailable(_) => StatusCode::BAD_GATEWAY,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            tracing::warn!("Market data request failed: {}", err.report());
+            (status, Json(ApiResponse::<T>::error(err.to_string()))).into_response()
+        }
+    }
+}
 
-    Json(ApiResponse::success(mock_data))
+/// WebSocket 代理路由
+fn ws_routes(registry: Arc<Registry>) -> Router {
+    Router::new()
+        .route("/ws", get(ws_proxy))
+        .route("/ws/*path", get(ws_proxy))
+        .with_state(registry)
 }
 
 /// WebSocket 代理端点
+#[utoipa::path(
+    get,
+    path = "/ws",
+    tag = "gateway",
+    description = "升级为 WebSocket 并转发到 real-time-feed 的 `/ws`，浏览器可用查询参数 `access_token` 传递令牌",
+    responses(
+        (status = 101, description = "已升级为 WebSocket"),
+        (status = 502, description = "连接 real-time-feed 失败"),
+        (status = 503, description = "没有可用的 real-time-feed 实例"),
+    )
+)]
 async fn ws_proxy(
-    axum::extract::Path(path): axum::extract::Path<String>,
+    State(registry): State<Arc<Registry>>,
+    uri: axum::http::Uri,
+    headers: axum::http::HeaderMap,
     ws: axum::extract::ws::WebSocketUpgrade,
-    ws_state: axum::extract::State<()>,
-) -> axum::response::Response {
-    tracing::info!("WebSocket connection to: {}", path);
-
-    // 这里应该实现 WebSocket 代理逻辑
-    ws.on_upgrade(|_socket| async {
-        // 处理 WebSocket 连接
-    })
+) -> Response {
+    tracing::info!("WebSocket connection to: {}", uri.path());
+    ws::proxy(&registry, ws, &uri, &headers).await
 }
 
 /// 请求日志中间件
@@ -229,11 +540,14 @@ mod tests {
 
     #[tokio::test]
     async fn test_health_check() {
-        let response = health_check().await;
+        let args = Args::parse_from(["alpha-api-gateway"]);
+        let proxy = Proxy::new(Arc::new(args.registry().unwrap()), args.proxy_settings());
+        let response = health_check(State(Arc::new(proxy))).await;
         let health = response.0;
 
         assert_eq!(health.status, "ok");
         assert!(!health.services.is_empty());
+        assert!(health.services.iter().all(|service| service.circuit == BreakerState::Closed));
     }
 
     #[tokio::test]
@@ -245,4 +559,18 @@ mod tests {
         assert!(response.data.is_some());
         assert!(response.error.is_none());
     }
+
+    #[tokio::test]
+    async fn test_market_quote() {
+        let provider = alpha_providers::default_registry()
+            .chain(&[ProviderSettings::new("simulated")])
+            .unwrap();
+
+        let response = market_quote(State(provider.clone()), Path("AAPL".to_string())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let query = CandlesQuery { timeframe: "7x".to_string(), limit: 10 };
+        let response = market_candles(State(provider), Path("AAPL".to_string()), Query(query)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,159 @@
+//! OpenAPI 文档
+//!
+//! `/openapi.json` 返回网关的 OpenAPI 规范：网关自身的路由 (健康检查、行情、WebSocket) 加上各上游服务 `/openapi.json` 中的接口和 schema，
+//! 上游路径加上 `/api/v1/<服务>` 前缀，即经网关调用时的路径。上游的规范缓存一分钟，取不到时跳过该服务。
+//! `/docs` 为 Swagger UI。两者都不要求认证
+
+use crate::discovery::Registry;
+use axum::extract::State;
+use axum::response::Json;
+use axum::routing::get;
+use axum::Router;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::{Config, SwaggerUi};
+
+/// 合并后的规范的缓存时间
+const SPEC_TTL: Duration = Duration::from_secs(60);
+
+/// 获取上游规范的超时
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 网关自身的路由
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Alpha Finance API",
+        description = "API 网关。`/api/v1/<服务>/...` 转发到对应的上游服务，要求 `analyst` 或 `admin` 角色；请求按 API Key 或客户端 IP 限流"
+    ),
+    paths(crate::health_check, crate::market_candles, crate::market_quote, crate::market_search, crate::ws_proxy),
+    modifiers(&SecuritySchemes),
+    security(("bearer_auth" = [])),
+    tags((name = "gateway"), (name = "market", description = "由数据源回退链直接提供的行情"))
+)]
+pub struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()),
+        );
+        components.add_security_scheme("api_key", SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))));
+    }
+}
+
+/// 合并各上游规范的文档
+pub struct Docs {
+    registry: Arc<Registry>,
+    client: reqwest::Client,
+    cached: Mutex<Option<(Instant, utoipa::openapi::OpenApi)>>,
+}
+
+impl Docs {
+    pub fn new(registry: Arc<Registry>) -> Self {
+        Self { registry, client: reqwest::Client::new(), cached: Mutex::new(None) }
+    }
+
+    /// 网关的规范合并上游的规范
+    pub async fn spec(&self) -> utoipa::openapi::OpenApi {
+        if let Some((fetched_at, spec)) = &*self.cached.lock().unwrap() {
+            if fetched_at.elapsed() < SPEC_TTL {
+                return spec.clone();
+            }
+        }
+        let services: Vec<String> = self.registry.services().map(str::to_string).collect();
+        let upstreams = futures::future::join_all(services.iter().map(|service| self.fetch(service))).await;
+        let mut spec = ApiDoc::openapi();
+        for (service, upstream) in services.iter().zip(upstreams) {
+            if let Some(upstream) = upstream {
+                spec.merge(prefixed(service, upstream));
+            }
+        }
+        *self.cached.lock().unwrap() = Some((Instant::now(), spec.clone()));
+        spec
+    }
+
+    async fn fetch(&self, service: &str) -> Option<utoipa::openapi::OpenApi> {
+        let lease = self.registry.pick(service, &[]).ok()?;
+        let url = format!("{}/openapi.json", lease.url());
+        let response = self.client.get(&url).timeout(FETCH_TIMEOUT).send().await.and_then(|response| response.error_for_status());
+        match response {
+            Ok(response) => match response.json().await {
+                Ok(spec) => Some(spec),
+                Err(e) => {
+                    tracing::warn!("Invalid OpenAPI spec from {}: {}", url, e);
+                    None
+                }
+            },
+            Err(e) => {
+                tracing::debug!("No OpenAPI spec from {}: {}", url, e);
+                None
+            }
+        }
+    }
+}
+
+/// 上游路径改为经网关转发的路径
+fn prefixed(service: &str, mut spec: utoipa::openapi::OpenApi) -> utoipa::openapi::OpenApi {
+    let paths = std::mem::take(&mut spec.paths.paths);
+    spec.paths.paths = paths.into_iter().map(|(path, item)| (format!("/api/v1/{}{}", service, path), item)).collect();
+    spec
+}
+
+async fn openapi(State(docs): State<Arc<Docs>>) -> Json<utoipa::openapi::OpenApi> {
+    Json(docs.spec().await)
+}
+
+/// `/openapi.json` 和 `/docs` 路由
+pub fn routes(docs: Arc<Docs>) -> Router {
+    Router::new()
+        .route("/openapi.json", get(openapi))
+        .with_state(docs)
+        .merge(SwaggerUi::new("/docs").config(Config::from("/openapi.json")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::{Balance, Source};
+
+    #[tokio::test]
+    async fn test_spec() {
+        let upstream = serde_json::json!({
+            "openapi": "3.1.0",
+            "info": {"title": "Alpha Finance Data Engine", "version": "0.1.0"},
+            "paths": {"/tables": {"get": {"tags": ["data-engine"], "responses": {"200": {"description": "", "content": {
+                "application/json": {"schema": {"$ref": "#/components/schemas/TableList"}}
+            }}}}}},
+            "components": {"schemas": {"TableList": {"type": "object"}}}
+        });
+        let app = axum::Router::new().route("/openapi.json", get(move || async move { Json(upstream) }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        // real-time-feed 不提供规范
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let upstreams = [format!("data-engine=http://{}", address), format!("real-time-feed=http://{}", closed)]
+            .iter()
+            .map(|spec| Source::parse_upstream(spec).unwrap())
+            .collect();
+        let docs = Docs::new(Arc::new(Registry::new(upstreams, Balance::RoundRobin, 1)));
+
+        let spec = serde_json::to_value(docs.spec().await).unwrap();
+        assert!(spec["paths"]["/health"]["get"].is_object());
+        assert!(spec["paths"]["/market/quote/{symbol}"]["get"].is_object());
+        assert!(spec["paths"]["/api/v1/data-engine/tables"]["get"].is_object());
+        assert!(spec["paths"]["/tables"].is_null());
+        assert!(spec["components"]["schemas"]["TableList"].is_object());
+        assert!(spec["components"]["schemas"]["HealthResponse"].is_object());
+        assert!(spec["components"]["securitySchemes"]["bearer_auth"].is_object());
+        // 健康检查不要求认证
+        assert_eq!(spec["paths"]["/health"]["get"]["security"], serde_json::json!([{}]));
+    }
+}
@@ -0,0 +1,321 @@
+//! API 代理
+//!
+//! `/api/v1/<服务>/<路径>` 转发到该服务的一个健康实例的 `/<路径>` (见 [`crate::discovery`])，
+//! 保留方法、查询参数、请求头和请求体，响应原样流式返回。连接实例失败时换一个实例重试；
+//! 幂等请求 (GET、HEAD、OPTIONS、PUT、DELETE) 在超时或上游返回 502/503/504 时按指数退避最多再重试 `--max-retries` 次，优先换实例。
+//! 服务不存在返回 404，没有可用实例或熔断打开 (见 [`crate::breaker`]) 返回 503，全部实例连接失败返回 502，超时返回 504
+
+use crate::breaker::{BreakerSettings, BreakerState, Breakers, Permit};
+use crate::discovery::{PickError, Registry};
+use crate::ApiResponse;
+use alpha_core::utils::retry::{Backoff, RetryPolicy};
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Json, Response};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 请求体大小上限
+const MAX_BODY_BYTES: usize = 16 << 20;
+
+/// 连接上游实例的超时
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 首次重试前的等待时间，之后按 [`RetryPolicy`] 翻倍并加抖动
+const RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// 单次重试等待上限
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// 幂等请求遇到这些状态码时重试
+const RETRY_STATUS: &[StatusCode] = &[StatusCode::BAD_GATEWAY, StatusCode::SERVICE_UNAVAILABLE, StatusCode::GATEWAY_TIMEOUT];
+
+/// 不转发的逐跳请求头
+const HOP_BY_HOP_HEADERS: &[&str] =
+    &["connection", "keep-alive", "proxy-authenticate", "proxy-authorization", "te", "trailer", "transfer-encoding", "upgrade", "host"];
+
+/// 代理配置
+#[derive(Debug, Clone)]
+pub struct ProxySettings {
+    /// 等待上游响应头的超时，不限制响应体的传输时间
+    pub timeout: Duration,
+    /// 幂等请求的重试策略，见 [`retry_policy`]
+    pub retry: RetryPolicy,
+    pub breaker: BreakerSettings,
+}
+
+/// 首次请求之外最多重试 `max_retries` 次
+pub fn retry_policy(max_retries: u32) -> RetryPolicy {
+    RetryPolicy::new(max_retries.saturating_add(1), RETRY_BACKOFF).with_max_delay(MAX_RETRY_BACKOFF)
+}
+
+/// 转发到上游服务，每个服务一个熔断器
+#[derive(Debug)]
+pub struct Proxy {
+    registry: Arc<Registry>,
+    breakers: Breakers,
+    client: reqwest::Client,
+    settings: ProxySettings,
+}
+
+impl Proxy {
+    pub fn new(registry: Arc<Registry>, settings: ProxySettings) -> Self {
+        let breakers = Breakers::new(registry.services(), settings.breaker);
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT.min(settings.timeout))
+            .build()
+            .expect("Failed to build HTTP client");
+        Self { registry, breakers, client, settings }
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub fn breaker_state(&self, service: &str) -> BreakerState {
+        self.breakers.get(service).map_or(BreakerState::Closed, |breaker| breaker.state())
+    }
+
+    /// 转发一个请求，`path` 为 `/api/v1/` 之后的部分
+    pub async fn forward(&self, path: &str, request: Request) -> Response {
+        let (service, rest) = path.split_once('/').unwrap_or((path, ""));
+        let query = request.uri().query().map(|query| format!("?{}", query)).unwrap_or_default();
+        let method = match reqwest::Method::from_bytes(request.method().as_str().as_bytes()) {
+            Ok(method) => method,
+            Err(e) => return error(StatusCode::METHOD_NOT_ALLOWED, e.to_string()),
+        };
+        let headers = request_headers(request.headers());
+        let body = match axum::body::to_bytes(request.into_body(), MAX_BODY_BYTES).await {
+            Ok(body) => body,
+            Err(e) => return error(StatusCode::PAYLOAD_TOO_LARGE, format!("Failed to read request body: {}", e)),
+        };
+
+        let permit = match self.breakers.get(service).map(|breaker| breaker.acquire(Instant::now())) {
+            Some(Err(retry_after)) => return circuit_open(service, retry_after),
+            Some(Ok(permit)) => Some(permit),
+            None => None,
+        };
+        let idempotent = matches!(
+            method,
+            reqwest::Method::GET | reqwest::Method::HEAD | reqwest::Method::OPTIONS | reqwest::Method::PUT | reqwest::Method::DELETE
+        );
+        // 连接失败的实例不再使用；超时或 5xx 的实例在没有其他实例时仍可重试
+        let mut unreachable = Vec::new();
+        let mut attempted = Vec::new();
+        // 每个请求使用不同的抖动种子，避免并发请求同时重试
+        let mut backoff = self.settings.retry.clone().with_seed(self.settings.retry.seed ^ request_seed()).backoffs();
+        let can_retry = |backoff: &Backoff| idempotent && backoff.retries().saturating_add(1) < self.settings.retry.max_attempts;
+        loop {
+            let lease = match self.registry.pick(service, &attempted).or_else(|_| self.registry.pick(service, &unreachable)) {
+                Ok(lease) => lease,
+                Err(e @ PickError::UnknownService(_)) => return error(StatusCode::NOT_FOUND, e.to_string()),
+                Err(e) if attempted.is_empty() => return error(StatusCode::SERVICE_UNAVAILABLE, e.to_string()),
+                Err(_) => {
+                    record(permit, false);
+                    return error(StatusCode::BAD_GATEWAY, format!("Failed to connect to {}", service));
+                }
+            };
+            let url = format!("{}/{}{}", lease.url(), rest, query);
+            tracing::debug!("Proxying {} {} to {}", method, path, url);
+            attempted.push(lease.url().to_string());
+            let send = self.client.request(method.clone(), &url).headers(headers.clone()).body(body.clone()).send();
+            match tokio::time::timeout(self.settings.timeout, send).await {
+                Ok(Ok(upstream)) => {
+                    let status = StatusCode::from_u16(upstream.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+                    if can_retry(&backoff) && RETRY_STATUS.contains(&status) {
+                        tracing::warn!("Upstream {} returned {}, retrying", url, status);
+                        tokio::time::sleep(backoff.next_delay()).await;
+                        continue;
+                    }
+                    record(permit, !status.is_server_error());
+                    return response(upstream, lease);
+                }
+                Ok(Err(e)) if e.is_connect() => {
+                    tracing::warn!("Failed to connect to {}: {}", lease.url(), e);
+                    self.registry.report_failure(&lease);
+                    unreachable.push(lease.url().to_string());
+                }
+                Ok(Err(e)) => {
+                    tracing::warn!("Proxy request to {} failed: {}", url, e);
+                    record(permit, false);
+                    return error(StatusCode::BAD_GATEWAY, format!("Upstream request failed: {}", e));
+                }
+                Err(_) => {
+                    tracing::warn!("Proxy request to {} timed out after {:?}", url, self.settings.timeout);
+                    if can_retry(&backoff) {
+                        tokio::time::sleep(backoff.next_delay()).await;
+                        continue;
+                    }
+                    record(permit, false);
+                    return error(StatusCode::GATEWAY_TIMEOUT, format!("Upstream request timed out after {:?}", self.settings.timeout));
+                }
+            }
+        }
+    }
+}
+
+fn request_seed() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_nanos() as u64)
+}
+
+/// 记录请求结果，服务没有熔断器时忽略
+fn record(permit: Option<Permit<'_>>, success: bool) {
+    match permit {
+        Some(permit) if success => permit.success(),
+        Some(permit) => permit.failure(),
+        None => {}
+    }
+}
+
+fn circuit_open(service: &str, retry_after: Duration) -> Response {
+    let mut response = error(StatusCode::SERVICE_UNAVAILABLE, format!("Circuit breaker for {} is open", service));
+    let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    response.headers_mut().insert(header::RETRY_AFTER, seconds.into());
+    response
+}
+
+fn request_headers(headers: &HeaderMap) -> reqwest::header::HeaderMap {
+    let mut forwarded = reqwest::header::HeaderMap::new();
+    for (name, value) in headers {
+        if HOP_BY_HOP_HEADERS.contains(&name.as_str()) {
+            continue;
+        }
+        if let (Ok(name), Ok(value)) = (
+            reqwest::header::HeaderName::from_bytes(name.as_str().as_bytes()),
+            reqwest::header::HeaderValue::from_bytes(value.as_bytes()),
+        ) {
+            forwarded.append(name, value);
+        }
+    }
+    forwarded
+}
+
+/// 流式返回上游响应，响应体传完后才释放实例
+fn response(upstream: reqwest::Response, lease: crate::discovery::Lease) -> Response {
+    let status = StatusCode::from_u16(upstream.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+    let mut headers = HeaderMap::new();
+    for (name, value) in upstream.headers() {
+        if HOP_BY_HOP_HEADERS.contains(&name.as_str()) {
+            continue;
+        }
+        if let (Ok(name), Ok(value)) = (
+            axum::http::HeaderName::from_bytes(name.as_str().as_bytes()),
+            axum::http::HeaderValue::from_bytes(value.as_bytes()),
+        ) {
+            headers.append(name, value);
+        }
+    }
+    let body = futures::StreamExt::map(upstream.bytes_stream(), move |chunk| {
+        let _lease = &lease;
+        chunk
+    });
+    (status, headers, Body::from_stream(body)).into_response()
+}
+
+pub fn error(status: StatusCode, message: String) -> Response {
+    (status, Json(ApiResponse::<()>::error(message))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::{Balance, Source};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn settings(failure_threshold: u32) -> ProxySettings {
+        ProxySettings {
+            timeout: Duration::from_millis(200),
+            retry: retry_policy(1),
+            breaker: BreakerSettings { failure_threshold, open_duration: Duration::from_secs(30) },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_forward() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream = format!("data-engine=http://{}", listener.local_addr().unwrap());
+        let app = axum::Router::new().route(
+            "/query",
+            axum::routing::post(|uri: axum::http::Uri, body: String| async move { format!("{} {}", uri, body) }),
+        );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        // 第一个实例无法连接，请求转到第二个实例
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let upstreams = [format!("data-engine=http://{}", closed), upstream]
+            .iter()
+            .map(|spec| Source::parse_upstream(spec).unwrap())
+            .collect();
+        let registry = Arc::new(Registry::new(upstreams, Balance::RoundRobin, 1));
+        let proxy = Proxy::new(registry.clone(), settings(2));
+
+        let request = Request::post("/api/v1/data-engine/query?format=csv").body(Body::from("SELECT 1")).unwrap();
+        let response = proxy.forward("data-engine/query", request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"/query?format=csv SELECT 1");
+        assert_eq!(registry.health()[0].healthy_instances, 1);
+
+        let request = Request::get("/api/v1/collector/status").body(Body::empty()).unwrap();
+        assert_eq!(proxy.forward("collector/status", request).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_retry_and_breaker() {
+        let flaky = Arc::new(AtomicUsize::new(0));
+        let down = Arc::new(AtomicUsize::new(0));
+        let slow = Arc::new(AtomicUsize::new(0));
+        let hits = (flaky.clone(), down.clone(), slow.clone());
+        let app = axum::Router::new()
+            // 第一次返回 503
+            .route(
+                "/flaky",
+                axum::routing::get(move || async move {
+                    match hits.0.fetch_add(1, Ordering::SeqCst) {
+                        0 => StatusCode::SERVICE_UNAVAILABLE,
+                        _ => StatusCode::OK,
+                    }
+                }),
+            )
+            .route(
+                "/down",
+                axum::routing::any(move || async move {
+                    hits.1.fetch_add(1, Ordering::SeqCst);
+                    StatusCode::SERVICE_UNAVAILABLE
+                }),
+            )
+            .route(
+                "/slow",
+                axum::routing::get(move || async move {
+                    hits.2.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    StatusCode::OK
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream = format!("data-engine=http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let registry = Arc::new(Registry::new(vec![Source::parse_upstream(&upstream).unwrap()], Balance::RoundRobin, 1));
+        let proxy = Proxy::new(registry, settings(2));
+        let get = |path: &str| Request::get(path).body(Body::empty()).unwrap();
+
+        assert_eq!(proxy.forward("data-engine/flaky", get("/")).await.status(), StatusCode::OK);
+        assert_eq!(flaky.load(Ordering::SeqCst), 2);
+
+        // 非幂等请求不重试
+        let request = Request::post("/").body(Body::empty()).unwrap();
+        assert_eq!(proxy.forward("data-engine/down", request).await.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(down.load(Ordering::SeqCst), 1);
+        assert_eq!(proxy.breaker_state("data-engine"), BreakerState::Closed);
+
+        assert_eq!(proxy.forward("data-engine/slow", get("/")).await.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(slow.load(Ordering::SeqCst), 2);
+        assert_eq!(proxy.breaker_state("data-engine"), BreakerState::Open);
+
+        // 熔断打开后不再请求上游
+        let response = proxy.forward("data-engine/flaky", get("/")).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+        assert_eq!(flaky.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,379 @@
+//! 限流
+//!
+//! 令牌桶限流：带 `X-API-Key` 的请求按该 Key 所属等级的额度限流，其余请求按客户端 IP 使用 `anonymous` 额度。
+//! 额度和 API Key 在 `--rate-limit-config` 指定的文件中配置：
+//!
+//! ```toml
+//! [anonymous]
+//! requests_per_minute = 60
+//! burst = 30
+//!
+//! [tiers.pro]
+//! requests_per_minute = 1200
+//!
+//! [[api_keys]]
+//! key = "ak_live_..."
+//! name = "desktop-exporter"
+//! tier = "pro"
+//! ```
+//!
+//! `burst` 为桶容量，省略时等于每分钟请求数，`requests_per_minute = 0` 表示不限流。未知的 API Key 返回 401。
+//! 响应带 `X-RateLimit-Limit`、`X-RateLimit-Remaining`、`X-RateLimit-Reset` (桶装满前的秒数)，超限返回 429 和 `Retry-After`。
+//!
+//! 多实例部署时设置 `--rate-limit-redis-url`，令牌桶存在 Redis 中由各实例共享；Redis 不可用时退回本实例内存中的桶。
+//! 网关在反向代理之后时设置 `--trusted-proxies <N>`：每层代理在 `X-Forwarded-For` 末尾追加它看到的对端地址，
+//! 客户端 IP 取从右数第 N 个地址，左侧由客户端自行填写的部分不予采信
+
+use crate::ApiResponse;
+use anyhow::{bail, Context};
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Json, Response};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 内存中的桶超过该数量时清理已装满的桶
+const MAX_IDLE_BUCKETS: usize = 10_000;
+
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// 一个等级的额度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Tier {
+    pub requests_per_minute: u32,
+    pub burst: Option<u32>,
+}
+
+impl Tier {
+    fn capacity(&self) -> u32 {
+        self.burst.unwrap_or(self.requests_per_minute).max(1)
+    }
+
+    /// 每毫秒补充的令牌数
+    fn refill_per_ms(&self) -> f64 {
+        f64::from(self.requests_per_minute) / 60_000.0
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKey {
+    pub key: String,
+    /// 限流和日志中使用的名称，不暴露 Key 本身
+    pub name: String,
+    pub tier: String,
+}
+
+/// 限流配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RateLimitSettings {
+    pub anonymous: Tier,
+    pub tiers: BTreeMap<String, Tier>,
+    pub api_keys: Vec<ApiKey>,
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        Self {
+            anonymous: Tier { requests_per_minute: 60, burst: Some(30) },
+            tiers: BTreeMap::new(),
+            api_keys: Vec::new(),
+        }
+    }
+}
+
+impl RateLimitSettings {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let settings: Self = config::Config::builder()
+            .add_source(config::File::from(path))
+            .build()
+            .and_then(|settings| settings.try_deserialize())
+            .with_context(|| format!("Invalid rate limit configuration {}", path.display()))?;
+        for key in &settings.api_keys {
+            if !settings.tiers.contains_key(&key.tier) {
+                bail!("API key {} uses unknown tier {}", key.name, key.tier);
+            }
+        }
+        Ok(settings)
+    }
+}
+
+/// 一次取令牌的结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// 桶装满前的时间
+    pub reset_after: Duration,
+    /// 被拒绝时下一个令牌可用前的时间
+    pub retry_after: Duration,
+}
+
+impl Decision {
+    fn new(allowed: bool, tier: &Tier, tokens: f64) -> Self {
+        let capacity = tier.capacity();
+        let rate = tier.refill_per_ms();
+        let until = |target: f64| Duration::from_millis(((target - tokens).max(0.0) / rate).ceil() as u64);
+        Self {
+            allowed,
+            limit: capacity,
+            remaining: tokens.max(0.0).floor() as u32,
+            reset_after: until(f64::from(capacity)),
+            retry_after: if allowed { Duration::ZERO } else { until(1.0) },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// 桶装满的时间，此后可以丢弃
+    full_at: Instant,
+}
+
+/// 本实例内存中的令牌桶
+#[derive(Debug, Default)]
+pub struct MemoryBuckets {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl MemoryBuckets {
+    fn acquire(&self, key: &str, tier: &Tier, now: Instant) -> Decision {
+        let capacity = f64::from(tier.capacity());
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() > MAX_IDLE_BUCKETS {
+            buckets.retain(|_, bucket| bucket.full_at > now);
+        }
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket { tokens: capacity, updated: now, full_at: now });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64() * 1000.0;
+        bucket.tokens = (bucket.tokens + elapsed * tier.refill_per_ms()).min(capacity);
+        bucket.updated = now;
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+        let decision = Decision::new(allowed, tier, bucket.tokens);
+        bucket.full_at = now + decision.reset_after;
+        decision
+    }
+}
+
+/// 原子地补充并取一个令牌，返回 {是否允许, 剩余令牌 * 1000}
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local rate = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated')
+local tokens = tonumber(bucket[1]) or capacity
+local updated = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - updated) * rate)
+local allowed = 0
+if tokens >= 1 then
+  tokens = tokens - 1
+  allowed = 1
+end
+redis.call('HSET', KEYS[1], 'tokens', tokens, 'updated', now)
+redis.call('PEXPIRE', KEYS[1], math.ceil((capacity - tokens) / rate) + 1000)
+return {allowed, math.floor(tokens * 1000)}
+"#;
+
+/// Redis 中由各实例共享的令牌桶
+pub struct RedisBuckets {
+    connection: redis::aio::ConnectionManager,
+    script: redis::Script,
+}
+
+impl RedisBuckets {
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let client = redis::Client::open(url).context("Invalid Redis URL")?;
+        let connection = redis::aio::ConnectionManager::new(client).await.context("Failed to connect to Redis")?;
+        Ok(Self { connection, script: redis::Script::new(TOKEN_BUCKET_SCRIPT) })
+    }
+
+    async fn acquire(&self, key: &str, tier: &Tier) -> redis::RedisResult<Decision> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let (allowed, tokens): (i64, i64) = self
+            .script
+            .key(format!("alpha:ratelimit:{}", key))
+            .arg(tier.capacity())
+            .arg(tier.refill_per_ms())
+            .arg(now)
+            .invoke_async(&mut self.connection.clone())
+            .await?;
+        Ok(Decision::new(allowed == 1, tier, tokens as f64 / 1000.0))
+    }
+}
+
+/// 经过 `trusted_proxies` 层可信代理后的客户端地址：`X-Forwarded-For` (多个头按顺序拼接) 从右数第 `trusted_proxies` 个，
+/// 地址数不足或无法解析时返回 None
+fn forwarded_client(headers: &HeaderMap, trusted_proxies: usize) -> Option<IpAddr> {
+    let index = trusted_proxies.checked_sub(1)?;
+    let hops: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect();
+    hops.iter().rev().nth(index)?.trim().parse().ok()
+}
+
+/// 限流器
+pub struct RateLimiter {
+    settings: RateLimitSettings,
+    keys: HashMap<String, ApiKey>,
+    memory: MemoryBuckets,
+    redis: Option<RedisBuckets>,
+    /// 网关前可信的反向代理层数，0 表示不读取 `X-Forwarded-For`
+    trusted_proxies: usize,
+}
+
+impl RateLimiter {
+    pub fn new(settings: RateLimitSettings, redis: Option<RedisBuckets>, trusted_proxies: usize) -> Self {
+        let keys = settings.api_keys.iter().map(|key| (key.key.clone(), key.clone())).collect();
+        Self { settings, keys, memory: MemoryBuckets::default(), redis, trusted_proxies }
+    }
+
+    /// 请求所属的桶和额度，API Key 未知时返回 None
+    fn bucket(&self, headers: &HeaderMap, peer: Option<SocketAddr>) -> Option<(String, Tier)> {
+        if let Some(key) = headers.get(API_KEY_HEADER) {
+            let key = self.keys.get(key.to_str().ok()?)?;
+            return Some((format!("key:{}", key.name), self.settings.tiers[&key.tier]));
+        }
+        let ip = forwarded_client(headers, self.trusted_proxies)
+            .or_else(|| peer.map(|peer| peer.ip()))
+            .map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+        Some((format!("ip:{}", ip), self.settings.anonymous))
+    }
+
+    async fn acquire(&self, key: &str, tier: &Tier) -> Decision {
+        if let Some(redis) = &self.redis {
+            match redis.acquire(key, tier).await {
+                Ok(decision) => return decision,
+                Err(e) => tracing::warn!("Redis rate limiting failed, using local buckets: {}", e),
+            }
+        }
+        self.memory.acquire(key, tier, Instant::now())
+    }
+}
+
+/// 限流中间件，`/health` 不限流
+pub async fn rate_limit(State(limiter): State<Arc<RateLimiter>>, request: Request, next: Next) -> Response {
+    if request.uri().path() == "/health" {
+        return next.run(request).await;
+    }
+    let peer = request.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(peer)| *peer);
+    let Some((key, tier)) = limiter.bucket(request.headers(), peer) else {
+        return (StatusCode::UNAUTHORIZED, Json(ApiResponse::<()>::error("Unknown API key".to_string()))).into_response();
+    };
+    if tier.requests_per_minute == 0 {
+        return next.run(request).await;
+    }
+
+    let decision = limiter.acquire(&key, &tier).await;
+    let mut response = if decision.allowed {
+        next.run(request).await
+    } else {
+        tracing::debug!("Rate limited {}", key);
+        let message = format!("Rate limit exceeded, retry after {}s", seconds(decision.retry_after));
+        let mut response = (StatusCode::TOO_MANY_REQUESTS, Json(ApiResponse::<()>::error(message))).into_response();
+        response.headers_mut().insert(axum::http::header::RETRY_AFTER, HeaderValue::from(seconds(decision.retry_after)));
+        response
+    };
+    let headers = response.headers_mut();
+    headers.insert(HeaderName::from_static("x-ratelimit-limit"), HeaderValue::from(decision.limit));
+    headers.insert(HeaderName::from_static("x-ratelimit-remaining"), HeaderValue::from(decision.remaining));
+    headers.insert(HeaderName::from_static("x-ratelimit-reset"), HeaderValue::from(seconds(decision.reset_after)));
+    response
+}
+
+/// 向上取整的秒数
+fn seconds(duration: Duration) -> u64 {
+    duration.as_millis().div_ceil(1000) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_memory_buckets() {
+        let buckets = MemoryBuckets::default();
+        let tier = Tier { requests_per_minute: 60, burst: Some(2) };
+        let start = Instant::now();
+        assert!(buckets.acquire("ip:1", &tier, start).allowed);
+        let decision = buckets.acquire("ip:1", &tier, start);
+        assert_eq!((decision.allowed, decision.remaining, decision.limit), (true, 0, 2));
+        assert_eq!(decision.reset_after, Duration::from_secs(2));
+
+        let decision = buckets.acquire("ip:1", &tier, start);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after, Duration::from_secs(1));
+        // 其他客户端不受影响，一秒后补充一个令牌
+        assert!(buckets.acquire("ip:2", &tier, start).allowed);
+        assert!(buckets.acquire("ip:1", &tier, start + Duration::from_secs(1)).allowed);
+        assert!(!buckets.acquire("ip:1", &tier, start + Duration::from_secs(1)).allowed);
+    }
+
+    #[test]
+    fn test_forwarded_client() {
+        let mut headers = HeaderMap::new();
+        headers.append("x-forwarded-for", HeaderValue::from_static("1.1.1.1, 2.2.2.2"));
+        headers.append("x-forwarded-for", HeaderValue::from_static("3.3.3.3"));
+        let client = |proxies| forwarded_client(&headers, proxies).map(|ip| ip.to_string());
+        assert_eq!(client(0), None);
+        assert_eq!(client(1).as_deref(), Some("3.3.3.3"));
+        assert_eq!(client(2).as_deref(), Some("2.2.2.2"));
+        assert_eq!(client(4), None);
+
+        let invalid = HeaderMap::from_iter([(HeaderName::from_static("x-forwarded-for"), HeaderValue::from_static("unknown"))]);
+        assert_eq!(forwarded_client(&invalid, 1), None);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let settings = RateLimitSettings {
+            anonymous: Tier { requests_per_minute: 60, burst: Some(1) },
+            tiers: BTreeMap::from([("pro".to_string(), Tier { requests_per_minute: 0, burst: None })]),
+            api_keys: vec![ApiKey { key: "ak_test".to_string(), name: "exporter".to_string(), tier: "pro".to_string() }],
+        };
+        let limiter = Arc::new(RateLimiter::new(settings, None, 1));
+        let app = axum::Router::new()
+            .route("/market/quote/:symbol", axum::routing::get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(limiter, rate_limit));
+        let request = |headers: &[(&str, &str)]| {
+            let mut request = axum::http::Request::get("/market/quote/AAPL");
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+            request.body(axum::body::Body::empty()).unwrap()
+        };
+
+        // 按可信代理追加的最右侧地址计数，客户端伪造的左侧地址不影响限流
+        let client = [("x-forwarded-for", "198.51.100.1, 203.0.113.7")];
+        let response = app.clone().oneshot(request(&client)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-ratelimit-remaining"], "0");
+        let spoofed = [("x-forwarded-for", "198.51.100.2, 203.0.113.7")];
+        let response = app.clone().oneshot(request(&spoofed)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["retry-after"], "1");
+        let response = app.clone().oneshot(request(&[("x-forwarded-for", "203.0.113.8")])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // 不限流的等级，未知的 Key
+        for _ in 0..3 {
+            let response = app.clone().oneshot(request(&[("x-api-key", "ak_test")])).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let response = app.oneshot(request(&[("x-api-key", "ak_unknown")])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,206 @@
+//! WebSocket 代理
+//!
+//! `/ws` 和 `/ws/...` 的连接转发到 `real-time-feed` 的一个健康实例的相同路径和查询参数，浏览器只需连接网关。
+//! 先连接上游再升级客户端连接，上游不可用时直接返回 503/502/504；`Authorization`、`Cookie` 和请求的子协议转发给上游，
+//! 上游选定的子协议返回给客户端。之后双向转发文本、二进制、ping/pong 和关闭帧，一方断开时关闭另一方
+
+use crate::discovery::{Lease, PickError, Registry};
+use crate::proxy;
+use axum::extract::ws::{self, WebSocket, WebSocketUpgrade};
+use axum::http::{HeaderMap, StatusCode, Uri};
+use axum::response::Response;
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::{self, client::IntoClientRequest, protocol::frame::coding::CloseCode};
+
+/// 上游服务名
+pub const SERVICE: &str = "real-time-feed";
+
+/// 连接上游并完成握手的超时
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 转发给上游的握手请求头
+const FORWARDED_HEADERS: &[&str] = &["authorization", "cookie", "sec-websocket-protocol"];
+
+/// 连接上游后升级客户端连接，连接期间占用上游实例
+pub async fn proxy(registry: &Registry, ws: WebSocketUpgrade, uri: &Uri, headers: &HeaderMap) -> Response {
+    let lease = match registry.pick(SERVICE, &[]) {
+        Ok(lease) => lease,
+        Err(e @ PickError::UnknownService(_)) => return proxy::error(StatusCode::NOT_FOUND, e.to_string()),
+        Err(e) => return proxy::error(StatusCode::SERVICE_UNAVAILABLE, e.to_string()),
+    };
+    // http:// -> ws://，https:// -> wss://
+    let path = uri.path_and_query().map_or(uri.path(), |path| path.as_str());
+    let url = format!("{}{}", lease.url().replacen("http", "ws", 1), path);
+    let mut request = match url.as_str().into_client_request() {
+        Ok(request) => request,
+        Err(e) => return proxy::error(StatusCode::BAD_GATEWAY, format!("Invalid upstream URL {}: {}", url, e)),
+    };
+    for name in FORWARDED_HEADERS {
+        for value in headers.get_all(*name) {
+            if let Ok(value) = tungstenite::http::HeaderValue::from_bytes(value.as_bytes()) {
+                request.headers_mut().append(*name, value);
+            }
+        }
+    }
+
+    let (upstream, response) = match tokio::time::timeout(CONNECT_TIMEOUT, tokio_tungstenite::connect_async(request)).await {
+        Ok(Ok(connected)) => connected,
+        Ok(Err(e)) => {
+            tracing::warn!("Failed to connect to {}: {}", url, e);
+            if matches!(e, tungstenite::Error::Io(_)) {
+                registry.report_failure(&lease);
+            }
+            return proxy::error(StatusCode::BAD_GATEWAY, format!("Failed to connect to {}", SERVICE));
+        }
+        Err(_) => {
+            tracing::warn!("Connecting to {} timed out after {:?}", url, CONNECT_TIMEOUT);
+            return proxy::error(StatusCode::GATEWAY_TIMEOUT, format!("Connecting to {} timed out", SERVICE));
+        }
+    };
+    let protocol = response.headers().get("sec-websocket-protocol").and_then(|value| value.to_str().ok()).map(str::to_string);
+    let ws = match protocol {
+        Some(protocol) => ws.protocols([protocol]),
+        None => ws,
+    };
+    tracing::debug!("Proxying WebSocket {} to {}", uri.path(), url);
+    ws.on_upgrade(move |socket| relay(socket, upstream, lease))
+}
+
+async fn relay<S>(client: WebSocket, upstream: S, _lease: Lease)
+where
+    S: Stream<Item = Result<tungstenite::Message, tungstenite::Error>> + Sink<tungstenite::Message> + Send,
+{
+    let (mut client_tx, mut client_rx) = client.split();
+    let (mut upstream_tx, mut upstream_rx) = upstream.split();
+    // 转发关闭帧后继续读，直到对方回复关闭帧或断开
+    let client_to_upstream = async {
+        while let Some(Ok(message)) = client_rx.next().await {
+            if upstream_tx.send(to_upstream(message)).await.is_err() {
+                break;
+            }
+        }
+        let _ = upstream_tx.close().await;
+    };
+    let upstream_to_client = async {
+        while let Some(Ok(message)) = upstream_rx.next().await {
+            let Some(message) = to_client(message) else {
+                continue;
+            };
+            if client_tx.send(message).await.is_err() {
+                break;
+            }
+        }
+        let _ = client_tx.close().await;
+    };
+    tokio::join!(client_to_upstream, upstream_to_client);
+}
+
+fn to_upstream(message: ws::Message) -> tungstenite::Message {
+    match message {
+        ws::Message::Text(text) => tungstenite::Message::Text(text),
+        ws::Message::Binary(data) => tungstenite::Message::Binary(data),
+        ws::Message::Ping(data) => tungstenite::Message::Ping(data),
+        ws::Message::Pong(data) => tungstenite::Message::Pong(data),
+        ws::Message::Close(frame) => tungstenite::Message::Close(frame.map(|frame| tungstenite::protocol::CloseFrame {
+            code: CloseCode::from(frame.code),
+            reason: frame.reason,
+        })),
+    }
+}
+
+/// 原始帧不转发
+fn to_client(message: tungstenite::Message) -> Option<ws::Message> {
+    Some(match message {
+        tungstenite::Message::Text(text) => ws::Message::Text(text),
+        tungstenite::Message::Binary(data) => ws::Message::Binary(data),
+        tungstenite::Message::Ping(data) => ws::Message::Ping(data),
+        tungstenite::Message::Pong(data) => ws::Message::Pong(data),
+        tungstenite::Message::Close(frame) => ws::Message::Close(frame.map(|frame| ws::CloseFrame {
+            code: frame.code.into(),
+            reason: frame.reason,
+        })),
+        tungstenite::Message::Frame(_) => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::{Balance, Source};
+    use axum::extract::State;
+    use axum::routing::get;
+    use std::sync::Arc;
+
+    async fn serve(app: axum::Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        addr.to_string()
+    }
+
+    #[tokio::test]
+    async fn test_proxy() {
+        // 上游先返回收到的路径和 Authorization，之后回显
+        let feed = axum::Router::new().route(
+            "/ws",
+            get(|ws: WebSocketUpgrade, uri: Uri, headers: HeaderMap| async move {
+                let auth = headers.get("authorization").and_then(|value| value.to_str().ok()).unwrap_or_default().to_string();
+                ws.protocols(["alpha.json"]).on_upgrade(move |mut socket| async move {
+                    socket.send(ws::Message::Text(format!("{} {}", uri, auth))).await.unwrap();
+                    while let Some(Ok(message)) = socket.recv().await {
+                        if socket.send(message).await.is_err() {
+                            break;
+                        }
+                    }
+                })
+            }),
+        );
+        let upstream = format!("{}=http://{}", SERVICE, serve(feed).await);
+        let registry = Arc::new(Registry::new(vec![Source::parse_upstream(&upstream).unwrap()], Balance::RoundRobin, 1));
+        let gateway = axum::Router::new()
+            .route(
+                "/ws",
+                get(|State(registry): State<Arc<Registry>>, ws: WebSocketUpgrade, uri: Uri, headers: HeaderMap| async move {
+                    proxy(&registry, ws, &uri, &headers).await
+                }),
+            )
+            .with_state(registry);
+        let gateway = serve(gateway).await;
+
+        let mut request = format!("ws://{}/ws?session=s1", gateway).into_client_request().unwrap();
+        request.headers_mut().insert("authorization", "Bearer t1".parse().unwrap());
+        request.headers_mut().insert("sec-websocket-protocol", "alpha.json".parse().unwrap());
+        let (mut socket, response) = tokio_tungstenite::connect_async(request).await.unwrap();
+        assert_eq!(response.headers()["sec-websocket-protocol"], "alpha.json");
+        let next = |message: Option<Result<tungstenite::Message, tungstenite::Error>>| message.unwrap().unwrap();
+        assert_eq!(next(socket.next().await), tungstenite::Message::Text("/ws?session=s1 Bearer t1".to_string()));
+
+        socket.send(tungstenite::Message::Binary(vec![1, 2, 3])).await.unwrap();
+        assert_eq!(next(socket.next().await), tungstenite::Message::Binary(vec![1, 2, 3]));
+        // 上游回显转发过去的 ping
+        socket.send(tungstenite::Message::Ping(b"p".to_vec())).await.unwrap();
+        loop {
+            match next(socket.next().await) {
+                tungstenite::Message::Pong(_) => continue,
+                message => {
+                    assert_eq!(message, tungstenite::Message::Ping(b"p".to_vec()));
+                    break;
+                }
+            }
+        }
+
+        let frame = tungstenite::protocol::CloseFrame { code: CloseCode::Away, reason: "bye".into() };
+        socket.send(tungstenite::Message::Close(Some(frame))).await.unwrap();
+        loop {
+            match socket.next().await {
+                Some(Ok(tungstenite::Message::Close(frame))) => {
+                    assert_eq!(frame.unwrap().code, CloseCode::Away);
+                    break;
+                }
+                Some(Ok(_)) => continue,
+                other => panic!("expected close frame, got {:?}", other),
+            }
+        }
+    }
+}
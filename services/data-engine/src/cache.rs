@@ -0,0 +1,427 @@
+//! 查询结果缓存
+//!
+//! 看板会反复执行同样的查询。`POST /query` 以规范化后的 SQL 和参数为键缓存 JSON 格式的结果，
+//! `cache_ttl_secs` 内的重复查询不再规划和执行 (设为 0 关闭缓存)。内存中的结果超过 `cache_memory_bytes`
+//! 时最早的条目转存到 `cache_dir` 下的 Arrow IPC 文件 (未配置时直接丢弃)，磁盘上超过 `cache_disk_bytes`
+//! 时删除最早的文件。`POST /ingest` 写入后清空全部缓存，流式格式不经过缓存。命中率见 `/health`
+
+use crate::params::QueryParam;
+use crate::settings::Settings;
+use datafusion::arrow::error::ArrowError;
+use datafusion::arrow::ipc::reader::FileReader;
+use datafusion::arrow::ipc::writer::FileWriter;
+use datafusion::arrow::record_batch::RecordBatch;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// 规范化 SQL：去掉首尾空白和末尾分号，合并连续空白，引号内的内容保持不变
+pub fn key(sql: &str, params: &[QueryParam]) -> String {
+    let mut key = String::with_capacity(sql.len());
+    let mut quote = None;
+    let mut space = false;
+    for c in sql.trim().trim_end_matches(';').trim_end().chars() {
+        match quote {
+            Some(q) => {
+                key.push(c);
+                if c == q {
+                    quote = None;
+                }
+            }
+            None if c.is_whitespace() => space = true,
+            None => {
+                if std::mem::take(&mut space) {
+                    key.push(' ');
+                }
+                if c == '\'' || c == '"' {
+                    quote = Some(c);
+                }
+                key.push(c);
+            }
+        }
+    }
+    key.push('\n');
+    key.push_str(&serde_json::to_string(params).unwrap_or_default());
+    key
+}
+
+/// 查询开始时的缓存版本，期间发生过写入时结果不再缓存
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Generation(u64);
+
+pub enum Lookup {
+    Hit(Vec<RecordBatch>),
+    Miss(Generation),
+}
+
+/// 缓存统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub memory_entries: usize,
+    pub memory_bytes: usize,
+    pub disk_entries: usize,
+    pub disk_bytes: u64,
+}
+
+struct MemoryEntry {
+    batches: Vec<RecordBatch>,
+    bytes: usize,
+    expires: Instant,
+    sequence: u64,
+}
+
+struct DiskEntry {
+    path: PathBuf,
+    bytes: u64,
+    expires: Instant,
+    sequence: u64,
+}
+
+#[derive(Default)]
+struct State {
+    generation: u64,
+    sequence: u64,
+    memory: HashMap<String, MemoryEntry>,
+    memory_bytes: usize,
+    disk: HashMap<String, DiskEntry>,
+    disk_bytes: u64,
+}
+
+impl State {
+    fn remove_memory(&mut self, key: &str) -> Option<MemoryEntry> {
+        let entry = self.memory.remove(key)?;
+        self.memory_bytes -= entry.bytes;
+        Some(entry)
+    }
+
+    fn remove_disk(&mut self, key: &str) -> Option<PathBuf> {
+        let entry = self.disk.remove(key)?;
+        self.disk_bytes -= entry.bytes;
+        Some(entry.path)
+    }
+}
+
+/// 查询结果缓存
+pub struct QueryCache {
+    ttl: Duration,
+    memory_bytes: usize,
+    dir: Option<PathBuf>,
+    disk_bytes: u64,
+    state: Mutex<State>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl QueryCache {
+    /// 按配置创建缓存，清理缓存目录中上次运行留下的文件
+    pub fn new(settings: &Settings) -> std::io::Result<Self> {
+        Self::with_limits(
+            Duration::from_secs(settings.cache_ttl_secs),
+            settings.cache_memory_bytes,
+            settings.cache_dir.clone(),
+            settings.cache_disk_bytes,
+        )
+    }
+
+    fn with_limits(ttl: Duration, memory_bytes: usize, dir: Option<PathBuf>, disk_bytes: u64) -> std::io::Result<Self> {
+        if let Some(dir) = &dir {
+            std::fs::create_dir_all(dir)?;
+            // 只删除缓存文件，目录配置错误时不影响其他数据
+            for entry in std::fs::read_dir(dir)? {
+                let path = entry?.path();
+                if is_cache_file(&path) {
+                    std::fs::remove_file(&path)?;
+                }
+            }
+        }
+        Ok(Self {
+            ttl,
+            memory_bytes,
+            dir,
+            disk_bytes,
+            state: Mutex::default(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        })
+    }
+
+    fn enabled(&self) -> bool {
+        !self.ttl.is_zero()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub async fn get(&self, key: &str) -> Lookup {
+        if !self.enabled() {
+            return Lookup::Miss(Generation(0));
+        }
+
+        let now = Instant::now();
+        let (generation, path) = {
+            let mut state = self.lock();
+            match state.memory.get(key) {
+                Some(entry) if entry.expires > now => {
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    return Lookup::Hit(entry.batches.clone());
+                }
+                Some(_) => {
+                    state.remove_memory(key);
+                }
+                None => {}
+            }
+            let path = match state.disk.get(key) {
+                Some(entry) if entry.expires > now => Some(entry.path.clone()),
+                Some(_) => {
+                    remove_files(state.remove_disk(key));
+                    None
+                }
+                None => None,
+            };
+            (Generation(state.generation), path)
+        };
+
+        if let Some(path) = path {
+            // 文件可能刚被淘汰或清空，读取失败按未命中处理
+            match tokio::task::spawn_blocking(move || read_batches(&path)).await {
+                Ok(Ok(batches)) => {
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    return Lookup::Hit(batches);
+                }
+                Ok(Err(e)) => tracing::debug!("Unable to read cached result: {}", e),
+                Err(e) => tracing::warn!("Cache read task failed: {}", e),
+            }
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        Lookup::Miss(generation)
+    }
+
+    /// 缓存查询结果，超出内存上限的条目在后台转存到磁盘
+    pub fn insert(self: &Arc<Self>, key: String, generation: Generation, batches: Vec<RecordBatch>) {
+        let bytes: usize = batches.iter().map(RecordBatch::get_array_memory_size).sum();
+        if !self.enabled() || bytes > self.memory_bytes {
+            return;
+        }
+
+        let mut state = self.lock();
+        if state.generation != generation.0 {
+            return;
+        }
+        state.sequence += 1;
+        let entry = MemoryEntry { batches, bytes, expires: Instant::now() + self.ttl, sequence: state.sequence };
+        state.remove_memory(&key);
+        state.memory_bytes += bytes;
+        state.memory.insert(key, entry);
+
+        let mut evicted = Vec::new();
+        while state.memory_bytes > self.memory_bytes {
+            let Some(oldest) = state.memory.iter().min_by_key(|(_, entry)| entry.sequence).map(|(key, _)| key.clone()) else {
+                break;
+            };
+            if let Some(entry) = state.remove_memory(&oldest) {
+                evicted.push((oldest, entry));
+            }
+        }
+        drop(state);
+
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let now = Instant::now();
+        for (key, entry) in evicted {
+            if entry.batches.is_empty() || entry.expires <= now {
+                continue;
+            }
+            let cache = self.clone();
+            let path = dir.join(format!("{}.arrow", entry.sequence));
+            tokio::spawn(async move {
+                let written = tokio::task::spawn_blocking({
+                    let path = path.clone();
+                    move || write_batches(&path, &entry.batches)
+                })
+                .await;
+                match written {
+                    Ok(Ok(bytes)) => {
+                        let entry = DiskEntry { path, bytes, expires: entry.expires, sequence: entry.sequence };
+                        cache.insert_disk(key, generation, entry);
+                    }
+                    Ok(Err(e)) => {
+                        tracing::warn!("Unable to spill cached result to {}: {}", path.display(), e);
+                        remove_files([path]);
+                    }
+                    Err(e) => tracing::warn!("Cache spill task failed: {}", e),
+                }
+            });
+        }
+    }
+
+    fn insert_disk(&self, key: String, generation: Generation, entry: DiskEntry) {
+        let mut state = self.lock();
+        if state.generation != generation.0 || entry.bytes > self.disk_bytes {
+            drop(state);
+            remove_files([entry.path]);
+            return;
+        }
+        let mut removed: Vec<PathBuf> = state.remove_disk(&key).into_iter().collect();
+        state.disk_bytes += entry.bytes;
+        state.disk.insert(key, entry);
+        while state.disk_bytes > self.disk_bytes {
+            let Some(oldest) = state.disk.iter().min_by_key(|(_, entry)| entry.sequence).map(|(key, _)| key.clone()) else {
+                break;
+            };
+            removed.extend(state.remove_disk(&oldest));
+        }
+        drop(state);
+        remove_files(removed);
+    }
+
+    /// 数据变化后清空缓存，正在执行的查询结果也不再缓存
+    pub fn invalidate(&self) {
+        let mut state = self.lock();
+        state.generation += 1;
+        state.memory.clear();
+        state.memory_bytes = 0;
+        let removed: Vec<PathBuf> = state.disk.drain().map(|(_, entry)| entry.path).collect();
+        state.disk_bytes = 0;
+        drop(state);
+        remove_files(removed);
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let state = self.lock();
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            memory_entries: state.memory.len(),
+            memory_bytes: state.memory_bytes,
+            disk_entries: state.disk.len(),
+            disk_bytes: state.disk_bytes,
+        }
+    }
+}
+
+fn is_cache_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "arrow")
+}
+
+fn write_batches(path: &Path, batches: &[RecordBatch]) -> Result<u64, ArrowError> {
+    let mut writer = FileWriter::try_new(File::create(path)?, &batches[0].schema())?;
+    for batch in batches {
+        writer.write(batch)?;
+    }
+    writer.finish()?;
+    Ok(std::fs::metadata(path)?.len())
+}
+
+fn read_batches(path: &Path) -> Result<Vec<RecordBatch>, ArrowError> {
+    FileReader::try_new(BufReader::new(File::open(path)?), None)?.collect()
+}
+
+fn remove_files(paths: impl IntoIterator<Item = PathBuf>) {
+    for path in paths {
+        if let Err(e) = std::fs::remove_file(&path) {
+            tracing::debug!("Unable to remove cached result {}: {}", path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::Int64Array;
+
+    fn batches(values: Vec<i64>) -> Vec<RecordBatch> {
+        vec![RecordBatch::try_from_iter([("value", Arc::new(Int64Array::from(values)) as _)]).unwrap()]
+    }
+
+    fn miss(lookup: Lookup) -> Generation {
+        match lookup {
+            Lookup::Miss(generation) => generation,
+            Lookup::Hit(_) => panic!("unexpected cache hit"),
+        }
+    }
+
+    fn hit(lookup: Lookup) -> Vec<RecordBatch> {
+        match lookup {
+            Lookup::Hit(batches) => batches,
+            Lookup::Miss(_) => panic!("unexpected cache miss"),
+        }
+    }
+
+    #[test]
+    fn test_key() {
+        let params: Vec<QueryParam> = serde_json::from_str(r#"["AAPL"]"#).unwrap();
+        assert_eq!(
+            key("  SELECT *\n  FROM   stock_quotes WHERE symbol = $1; ", &params),
+            key("SELECT * FROM stock_quotes WHERE symbol = $1", &params)
+        );
+        assert_ne!(key("SELECT 'a  b'", &[]), key("SELECT 'a b'", &[]));
+        assert_ne!(key("SELECT $1", &params), key("SELECT $1", &[]));
+    }
+
+    #[tokio::test]
+    async fn test_cache() {
+        let cache = Arc::new(QueryCache::with_limits(Duration::from_millis(200), 1 << 20, None, 0).unwrap());
+        let generation = miss(cache.get("a").await);
+        cache.insert("a".to_string(), generation, batches(vec![1, 2]));
+        assert_eq!(hit(cache.get("a").await), batches(vec![1, 2]));
+
+        // 写入后清空，写入前开始的查询结果不再缓存
+        let stale = miss(cache.get("b").await);
+        cache.invalidate();
+        cache.insert("b".to_string(), stale, batches(vec![3]));
+        miss(cache.get("a").await);
+        miss(cache.get("b").await);
+
+        let generation = miss(cache.get("a").await);
+        cache.insert("a".to_string(), generation, batches(vec![1]));
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        miss(cache.get("a").await);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 6));
+        assert_eq!(stats.memory_entries, 0);
+
+        let disabled = Arc::new(QueryCache::with_limits(Duration::ZERO, 1 << 20, None, 0).unwrap());
+        disabled.insert("a".to_string(), miss(disabled.get("a").await), batches(vec![1]));
+        miss(disabled.get("a").await);
+    }
+
+    #[tokio::test]
+    async fn test_spill() {
+        let dir = std::env::temp_dir().join(format!("alpha-data-engine-cache-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("stale.arrow"), b"stale").unwrap();
+        std::fs::write(dir.join("keep.txt"), b"keep").unwrap();
+
+        let size = batches(vec![1]).iter().map(RecordBatch::get_array_memory_size).sum::<usize>();
+        let cache = Arc::new(QueryCache::with_limits(Duration::from_secs(60), size, Some(dir.clone()), 1 << 20).unwrap());
+        assert!(!dir.join("stale.arrow").exists());
+        assert!(dir.join("keep.txt").exists());
+
+        cache.insert("a".to_string(), miss(cache.get("a").await), batches(vec![1]));
+        cache.insert("b".to_string(), miss(cache.get("b").await), batches(vec![2]));
+        for _ in 0..50 {
+            if cache.stats().disk_entries == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let stats = cache.stats();
+        assert_eq!((stats.memory_entries, stats.disk_entries), (1, 1));
+        assert_eq!(hit(cache.get("a").await), batches(vec![1]));
+        assert_eq!(hit(cache.get("b").await), batches(vec![2]));
+
+        cache.invalidate();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().filter(|entry| is_cache_file(&entry.as_ref().unwrap().path())).count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,271 @@
+//! 物化 K 线视图
+//!
+//! 仪表盘按周期查询 K 线时不必扫描逐笔行情：`candles_1m`、`candles_5m`、`candles_1h`、`candles_1d`
+//! 保存按代码和时间桶 (UTC，`timestamp` 为桶的起始时间) 聚合的 OHLCV，逐级汇总 1m → 5m → 1h → 1d。
+//!
+//! 启动时从 `stock_quotes` 重建，之后 `POST /ingest` 写入的行情交给后台任务增量合并，
+//! 合并后重新注册各表并调用 `on_change`，写入后稍有延迟才反映到视图中。
+//! 视图只保存在内存中，保留期删除的分区 (见 [`crate::storage`]) 在重启后才从视图中消失
+
+use arrow::array::{Array, ArrayRef, AsArray, Float64Array, StringArray, TimestampMillisecondArray, UInt64Array};
+use arrow::datatypes::{DataType, Field, Float64Type, Schema, SchemaRef, TimeUnit, TimestampMillisecondType, UInt64Type};
+use arrow::error::ArrowError;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::datasource::MemTable;
+use datafusion::error::Result;
+use datafusion::prelude::SessionContext;
+use futures::StreamExt;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// 视图的周期和时间桶长度 (毫秒)，每一级由上一级汇总
+const INTERVALS: [(&str, i64); 4] = [("1m", 60_000), ("5m", 300_000), ("1h", 3_600_000), ("1d", 86_400_000)];
+
+/// 一根 K 线，`first`/`last` 是其中最早和最晚行情的时间，用于乱序合并开收盘价
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candle {
+    first: i64,
+    last: i64,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: u64,
+    trades: u64,
+}
+
+impl Candle {
+    fn merge(&mut self, other: &Candle) {
+        if other.first < self.first {
+            self.first = other.first;
+            self.open = other.open;
+        }
+        // 时间相同时以后写入的为准
+        if other.last >= self.last {
+            self.last = other.last;
+            self.close = other.close;
+        }
+        self.high = self.high.max(other.high);
+        self.low = self.low.min(other.low);
+        self.volume += other.volume;
+        self.trades += other.trades;
+    }
+}
+
+/// 一级视图：(代码, 桶起始时间) 到 K 线
+type Level = BTreeMap<(String, i64), Candle>;
+
+fn merge_into(level: &mut Level, key: (String, i64), candle: Candle) {
+    match level.get_mut(&key) {
+        Some(existing) => existing.merge(&candle),
+        None => {
+            level.insert(key, candle);
+        }
+    }
+}
+
+/// 各周期的 K 线
+#[derive(Debug, Default)]
+pub struct Rollups {
+    levels: [Level; INTERVALS.len()],
+}
+
+impl Rollups {
+    /// 合并一批 `stock_quotes` 行情。带开高低价的行按 K 线合并，逐笔行情的开高低价取成交价
+    pub fn apply(&mut self, batch: &RecordBatch) -> Result<(), ArrowError> {
+        let column = |name: &str| {
+            batch
+                .column_by_name(name)
+                .ok_or_else(|| ArrowError::SchemaError(format!("Missing column {}", name)))
+        };
+        let symbols = column("symbol")?.as_string::<i32>();
+        let timestamps = column("timestamp")?.as_primitive::<TimestampMillisecondType>();
+        let prices = column("price")?.as_primitive::<Float64Type>();
+        let volumes = column("volume")?.as_primitive::<UInt64Type>();
+        let opens = column("open")?.as_primitive::<Float64Type>();
+        let highs = column("high")?.as_primitive::<Float64Type>();
+        let lows = column("low")?.as_primitive::<Float64Type>();
+        let price_or = |values: &Float64Array, row: usize| if values.is_valid(row) { values.value(row) } else { prices.value(row) };
+
+        // 本批行情的增量逐级汇总后再合并到各级视图
+        let mut delta = Level::new();
+        let (_, width) = INTERVALS[0];
+        for row in 0..batch.num_rows() {
+            let timestamp = timestamps.value(row);
+            let candle = Candle {
+                first: timestamp,
+                last: timestamp,
+                open: price_or(opens, row),
+                high: price_or(highs, row),
+                low: price_or(lows, row),
+                close: prices.value(row),
+                volume: volumes.value(row),
+                trades: 1,
+            };
+            merge_into(&mut delta, (symbols.value(row).to_string(), bucket(timestamp, width)), candle);
+        }
+        for (index, level) in self.levels.iter_mut().enumerate() {
+            if index > 0 {
+                delta = rollup(delta, INTERVALS[index].1);
+            }
+            for (key, candle) in &delta {
+                merge_into(level, key.clone(), *candle);
+            }
+        }
+        Ok(())
+    }
+
+    fn batch(&self, level: usize) -> Result<RecordBatch, ArrowError> {
+        let candles = &self.levels[level];
+        let values = |value: fn(&Candle) -> f64| Arc::new(candles.values().map(value).collect::<Float64Array>()) as ArrayRef;
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from_iter_values(candles.keys().map(|(symbol, _)| symbol.as_str()))),
+            Arc::new(TimestampMillisecondArray::from_iter_values(candles.keys().map(|(_, start)| *start)).with_timezone("UTC")),
+            values(|candle| candle.open),
+            values(|candle| candle.high),
+            values(|candle| candle.low),
+            values(|candle| candle.close),
+            Arc::new(UInt64Array::from_iter_values(candles.values().map(|candle| candle.volume))),
+            Arc::new(UInt64Array::from_iter_values(candles.values().map(|candle| candle.trades))),
+        ];
+        RecordBatch::try_new(candle_schema(), columns)
+    }
+
+    /// 以当前的 K 线重新注册各视图
+    fn register(&self, ctx: &SessionContext) -> Result<()> {
+        for (level, (interval, _)) in INTERVALS.iter().enumerate() {
+            let table = MemTable::try_new(candle_schema(), vec![vec![self.batch(level)?]])?;
+            ctx.register_table(format!("candles_{}", interval).as_str(), Arc::new(table))?;
+        }
+        Ok(())
+    }
+}
+
+/// 把上一级的 K 线汇总到宽度为 `width` 的时间桶，各级桶宽都是上一级的整数倍
+fn rollup(finer: Level, width: i64) -> Level {
+    let mut coarser = Level::new();
+    for ((symbol, start), candle) in finer {
+        merge_into(&mut coarser, (symbol, bucket(start, width)), candle);
+    }
+    coarser
+}
+
+fn bucket(timestamp: i64, width: i64) -> i64 {
+    timestamp.div_euclid(width) * width
+}
+
+fn candle_schema() -> SchemaRef {
+    let price = |name: &str| Field::new(name, DataType::Float64, false);
+    Arc::new(Schema::new(vec![
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("timestamp", DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())), false),
+        price("open"),
+        price("high"),
+        price("low"),
+        price("close"),
+        Field::new("volume", DataType::UInt64, false),
+        Field::new("trades", DataType::UInt64, false),
+    ]))
+}
+
+/// 后台维护的 K 线视图
+pub struct CandleViews {
+    updates: mpsc::UnboundedSender<RecordBatch>,
+}
+
+impl CandleViews {
+    /// 从 `stock_quotes` 重建视图并启动后台合并任务，需在接受写入前调用
+    pub async fn start(ctx: SessionContext, on_change: impl Fn() + Send + 'static) -> Result<Self> {
+        let mut rollups = Rollups::default();
+        let mut results = ctx
+            .sql("SELECT symbol, timestamp, price, volume, open, high, low FROM stock_quotes")
+            .await?
+            .execute_stream()
+            .await?;
+        while let Some(batch) = results.next().await {
+            rollups.apply(&batch?)?;
+        }
+        rollups.register(&ctx)?;
+        tracing::info!("Built candle views with {} 1m candles", rollups.levels[0].len());
+
+        let (updates, mut pending) = mpsc::unbounded_channel::<RecordBatch>();
+        std::thread::Builder::new().name("candle-views".to_string()).spawn(move || {
+            while let Some(batch) = pending.blocking_recv() {
+                let mut applied = rollups.apply(&batch);
+                // 积压的写入合并完再注册一次
+                while let Ok(batch) = pending.try_recv() {
+                    applied = applied.and(rollups.apply(&batch));
+                }
+                match applied.map_err(Into::into).and_then(|()| rollups.register(&ctx)) {
+                    Ok(()) => on_change(),
+                    Err(e) => tracing::error!("Candle view update failed: {}", e),
+                }
+            }
+        })?;
+        Ok(Self { updates })
+    }
+
+    /// 把写入的行情交给后台任务
+    pub fn update(&self, batch: RecordBatch) {
+        if self.updates.send(batch).is_err() {
+            tracing::error!("Candle view updates stopped");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quotes(json: &str) -> RecordBatch {
+        crate::ingest::decode(None, format!(r#"{{"rows": [{}]}}"#, json).as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_rollups() {
+        let mut rollups = Rollups::default();
+        rollups
+            .apply(&quotes(
+                r#"{"symbol": "AAPL", "timestamp": "2024-03-01T14:30:10Z", "price": 181.0, "volume": 10},
+                   {"symbol": "AAPL", "timestamp": "2024-03-01T14:30:50Z", "price": 182.0, "volume": 20},
+                   {"symbol": "AAPL", "timestamp": "2024-03-01T14:34:00Z", "price": 180.0, "volume": 5},
+                   {"symbol": "MSFT", "timestamp": "2024-03-01T14:30:00Z", "price": 410.0, "volume": 1}"#,
+            ))
+            .unwrap();
+        // 乱序到达的更早行情改变开盘价，带开高低价的行按 K 线合并
+        rollups
+            .apply(&quotes(
+                r#"{"symbol": "AAPL", "timestamp": "2024-03-01T14:30:00Z", "price": 180.5, "volume": 1,
+                    "open": 179.0, "high": 183.0, "low": 178.0}"#,
+            ))
+            .unwrap();
+
+        let minute = bucket(1_709_303_400_000, 60_000);
+        assert_eq!(
+            rollups.levels[0][&("AAPL".to_string(), minute)],
+            Candle {
+                first: 1_709_303_400_000,
+                last: 1_709_303_450_000,
+                open: 179.0,
+                high: 183.0,
+                low: 178.0,
+                close: 182.0,
+                volume: 31,
+                trades: 3
+            }
+        );
+        assert_eq!(rollups.levels[0].len(), 3);
+
+        // 14:30 和 14:34 落在同一个 5m 桶，各级视图的成交量一致
+        let five = &rollups.levels[1][&("AAPL".to_string(), bucket(minute, 300_000))];
+        assert_eq!((five.open, five.close, five.low, five.volume, five.trades), (179.0, 180.0, 178.0, 36, 4));
+        for level in &rollups.levels[1..] {
+            assert_eq!(level.len(), 2);
+            assert_eq!(level.values().map(|candle| candle.volume).sum::<u64>(), 37);
+        }
+        let day = rollups.batch(3).unwrap();
+        assert_eq!(day.column(1).as_primitive::<TimestampMillisecondType>().value(0), 1_709_251_200_000);
+        assert_eq!(day.column(7).as_primitive::<UInt64Type>().values().to_vec(), vec![4, 1]);
+    }
+}
@@ -0,0 +1,220 @@
+//! 表目录
+//!
+//! `GET /tables` 列出默认 schema 中注册的表，`GET /tables/{name}/schema` 返回一张表的列、类型和行数，
+//! 客户端和网关据此构建查询界面而不必写死表结构。
+//!
+//! 行数取自执行计划的统计：内存表 (如 K 线视图) 是准确值，Parquet 等文件表在没有统计时为 `null`。
+//! Parquet 目录存储的 `stock_quotes` 的行数由各分区文件的 Parquet 元数据汇总，并附带分区列表 (见 [`crate::ingest::PartitionStats`])
+
+use crate::ingest::{PartitionStats, Quotes};
+use crate::AppState;
+use axum::http::StatusCode;
+use axum::response::ErrorResponse;
+use datafusion::catalog::schema::SchemaProvider;
+use datafusion::common::stats::Precision;
+use datafusion::datasource::{TableProvider, TableType};
+use datafusion::error::{DataFusionError, Result};
+use serde::Serialize;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// `GET /tables` 的响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TableList {
+    pub tables: Vec<TableSummary>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TableSummary {
+    pub name: String,
+    pub table_type: &'static str,
+    pub columns: usize,
+    #[serde(flatten)]
+    pub row_count: RowCount,
+}
+
+/// 行数估计，没有统计时 `row_count` 为 `null`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, ToSchema)]
+pub struct RowCount {
+    pub row_count: Option<usize>,
+    pub row_count_exact: bool,
+}
+
+/// `GET /tables/{name}/schema` 的响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TableSchema {
+    pub name: String,
+    pub table_type: &'static str,
+    pub columns: Vec<ColumnInfo>,
+    #[serde(flatten)]
+    pub row_count: RowCount,
+    /// 只有 Parquet 目录存储的 `stock_quotes` 有分区
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partitions: Option<Vec<PartitionStats>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ColumnInfo {
+    pub name: String,
+    /// Arrow 类型，如 `Float64`、`Timestamp(Millisecond, Some("UTC"))`
+    pub data_type: String,
+    pub nullable: bool,
+}
+
+/// 列出全部表
+#[utoipa::path(get, path = "/tables", tag = "data-engine", responses((status = 200, body = TableList)))]
+pub async fn list_tables(
+    axum::extract::State(state): axum::extract::State<AppState>,
+) -> Result<axum::Json<TableList>, ErrorResponse> {
+    let schema = default_schema(&state).map_err(internal)?;
+    let mut names = schema.table_names();
+    names.sort();
+
+    let mut tables = Vec::with_capacity(names.len());
+    for name in names {
+        // 列出期间被注销的表直接跳过
+        let Some(table) = schema.table(&name).await else {
+            continue;
+        };
+        let (row_count, _) = describe(&state, &name, &table).await.map_err(internal)?;
+        tables.push(TableSummary {
+            table_type: table_type(table.table_type()),
+            columns: table.schema().fields().len(),
+            row_count,
+            name,
+        });
+    }
+    Ok(axum::Json(TableList { tables }))
+}
+
+/// 一张表的列和行数
+#[utoipa::path(
+    get,
+    path = "/tables/{name}/schema",
+    tag = "data-engine",
+    params(("name" = String, Path, description = "表名")),
+    responses((status = 200, body = TableSchema), (status = 404, description = "表不存在"))
+)]
+pub async fn table_schema(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+) -> Result<axum::Json<TableSchema>, ErrorResponse> {
+    let schema = default_schema(&state).map_err(internal)?;
+    let Some(table) = schema.table(&name).await else {
+        return Err(ErrorResponse::from((StatusCode::NOT_FOUND, format!("Table {} not found", name))));
+    };
+    let (row_count, partitions) = describe(&state, &name, &table).await.map_err(internal)?;
+    let columns = table
+        .schema()
+        .fields()
+        .iter()
+        .map(|field| ColumnInfo {
+            name: field.name().clone(),
+            data_type: field.data_type().to_string(),
+            nullable: field.is_nullable(),
+        })
+        .collect();
+    Ok(axum::Json(TableSchema { name, table_type: table_type(table.table_type()), columns, row_count, partitions }))
+}
+
+fn default_schema(state: &AppState) -> Result<Arc<dyn SchemaProvider>> {
+    let session = state.ctx.state();
+    let options = &session.config_options().catalog;
+    state
+        .ctx
+        .catalog(&options.default_catalog)
+        .and_then(|catalog| catalog.schema(&options.default_schema))
+        .ok_or_else(|| {
+            DataFusionError::Internal(format!("Schema {}.{} not found", options.default_catalog, options.default_schema))
+        })
+}
+
+/// 行数和分区，Parquet 目录存储的 `stock_quotes` 读取分区文件的元数据，其他表取扫描计划的统计
+async fn describe(
+    state: &AppState,
+    name: &str,
+    table: &Arc<dyn TableProvider>,
+) -> Result<(RowCount, Option<Vec<PartitionStats>>)> {
+    if let (Quotes::Parquet(quotes), "stock_quotes") = (&state.quotes, name) {
+        let quotes = quotes.clone();
+        let partitions = tokio::task::spawn_blocking(move || quotes.partition_stats())
+            .await
+            .map_err(|e| DataFusionError::External(Box::new(e)))??;
+        let rows = partitions.iter().map(|partition| partition.rows as usize).sum();
+        return Ok((RowCount { row_count: Some(rows), row_count_exact: true }, Some(partitions)));
+    }
+
+    let plan = table.scan(&state.ctx.state(), None, &[], None).await?;
+    let row_count = match plan.statistics()?.num_rows {
+        Precision::Exact(rows) => RowCount { row_count: Some(rows), row_count_exact: true },
+        Precision::Inexact(rows) => RowCount { row_count: Some(rows), row_count_exact: false },
+        Precision::Absent => RowCount::default(),
+    };
+    Ok((row_count, None))
+}
+
+fn table_type(table_type: TableType) -> &'static str {
+    match table_type {
+        TableType::Base => "base",
+        TableType::View => "view",
+        TableType::Temporary => "temporary",
+    }
+}
+
+fn internal(e: DataFusionError) -> ErrorResponse {
+    tracing::error!("Catalog error: {}", e);
+    ErrorResponse::from((StatusCode::INTERNAL_SERVER_ERROR, format!("Catalog error: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::QueryCache;
+    use crate::candles::CandleViews;
+    use crate::ingest::QuoteStore;
+    use crate::limits::Limits;
+    use crate::settings::Settings;
+    use axum::response::IntoResponse;
+    use datafusion::prelude::SessionContext;
+
+    #[tokio::test]
+    async fn test_catalog() {
+        let dir = std::env::temp_dir().join(format!("alpha-data-engine-catalog-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let settings = Settings { jobs_dir: dir.with_extension("jobs"), ..Settings::default() };
+        let ctx = SessionContext::new();
+        let quotes = Arc::new(QuoteStore::open(ctx.clone(), &dir).unwrap());
+        let rows = br#"{"rows": [{"symbol": "AAPL", "timestamp": "2024-03-01T14:30:00Z", "price": 180.5, "volume": 100},
+                                 {"symbol": "MSFT", "timestamp": "2024-03-01T14:30:00Z", "price": 410.0, "volume": 10}]}"#;
+        quotes.append(crate::ingest::decode(None, rows).unwrap()).await.unwrap();
+        let state = AppState {
+            ctx: ctx.clone(),
+            quotes: Quotes::Parquet(quotes),
+            cache: Arc::new(QueryCache::new(&Settings::default()).unwrap()),
+            candles: Arc::new(CandleViews::start(ctx.clone(), || ()).await.unwrap()),
+            limits: Limits::new(&Settings::default()),
+            queries: Arc::new(crate::queries::Queries::new()),
+            jobs: Arc::new(crate::jobs::Jobs::open(&settings).unwrap()),
+        };
+
+        let tables = list_tables(axum::extract::State(state.clone())).await.ok().unwrap();
+        let tables = serde_json::to_value(&tables.0).unwrap();
+        let names: Vec<&str> = tables["tables"].as_array().unwrap().iter().map(|table| table["name"].as_str().unwrap()).collect();
+        assert_eq!(names, vec!["candles_1d", "candles_1h", "candles_1m", "candles_5m", "stock_quotes"]);
+        // K 线视图是内存表，行数准确
+        assert_eq!(tables["tables"][2]["row_count"], 2);
+        assert_eq!(tables["tables"][2]["row_count_exact"], true);
+
+        let schema = table_schema(axum::extract::State(state.clone()), axum::extract::Path("stock_quotes".to_string())).await.ok().unwrap();
+        let schema = serde_json::to_value(&schema.0).unwrap();
+        assert_eq!(schema["columns"][0], serde_json::json!({"name": "symbol", "data_type": "Utf8", "nullable": false}));
+        assert_eq!(schema["row_count"], 2);
+        assert_eq!(schema["partitions"][1]["path"], "date=2024-03-01/symbol=MSFT");
+        assert_eq!(schema["partitions"][1]["files"], 1);
+
+        let missing = table_schema(axum::extract::State(state), axum::extract::Path("missing".to_string())).await;
+        assert_eq!(missing.unwrap_err().into_response().status(), StatusCode::NOT_FOUND);
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::remove_dir_all(&settings.jobs_dir).unwrap();
+    }
+}
@@ -0,0 +1,215 @@
+//! Delta Lake 表
+//!
+//! 配置 `delta_quotes_url` 后 `stock_quotes` 改存为该位置的 Delta 表 (默认按日期和代码分区的 Parquet 目录，见 [`crate::ingest`])：
+//! 每次写入是一次事务提交，并发写入由事务日志检测冲突，查询只读到已提交的版本。存储维护 (见 [`crate::storage`])
+//! 对 Delta 表执行删除过期行、`OPTIMIZE` 合并小文件和 `VACUUM` 清理不再引用的文件 (保留期内的旧版本仍可回溯)。
+//!
+//! 其他 Delta 表在配置文件中声明后注册为同名表，可以指定版本或时间查询历史快照：
+//!
+//! ```toml
+//! [[delta_tables]]
+//! name = "quotes_2024_03_01"
+//! url = "s3://lake/delta/stock_quotes"
+//! timestamp = "2024-03-01T00:00:00Z"   # 或 version = 42
+//! ```
+//!
+//! 写入的列必须与表结构一致，`stock_quotes` 增加列时需要先修改表结构再写入。
+//!
+//! 对象存储上的表使用 `object_stores` 中 URL 相同前缀的配置。S3 没有原子的重命名，
+//! 多个实例写同一张表时需要配置 DynamoDB 锁 (`aws_s3_locking_provider`)，单实例写入可以设置 `aws_s3_allow_unsafe_rename = "true"`
+
+use crate::create_stock_quotes_schema;
+use crate::object_stores::ObjectStoreSettings;
+use crate::storage::{DatasetSettings, MaintenanceReport};
+use chrono::NaiveDate;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::datasource::{MemTable, TableProvider};
+use datafusion::error::{DataFusionError, Result};
+use datafusion::prelude::{col, lit, SessionContext};
+use datafusion::scalar::ScalarValue;
+use deltalake::protocol::SaveMode;
+use deltalake::{DeltaOps, DeltaTable, DeltaTableBuilder, DeltaTableError};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// 注册 `s3://` 和 `gs://` 的 Delta 存储，需在打开对象存储上的表之前调用
+pub fn register_handlers() {
+    deltalake::aws::register_handlers(None);
+    deltalake::gcp::register_handlers(None);
+}
+
+/// 配置中声明的 Delta 表，`version` 和 `timestamp` (RFC 3339) 至多设置一个，都未设置时读取最新版本
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeltaTableSettings {
+    pub name: String,
+    pub url: String,
+    pub version: Option<i64>,
+    pub timestamp: Option<String>,
+}
+
+/// 注册配置中的全部 Delta 表
+pub async fn register(ctx: &SessionContext, tables: &[DeltaTableSettings], stores: &[ObjectStoreSettings]) -> Result<()> {
+    for settings in tables {
+        let mut builder = DeltaTableBuilder::from_uri(&settings.url).with_storage_options(storage_options(&settings.url, stores)?);
+        builder = match (settings.version, &settings.timestamp) {
+            (Some(_), Some(_)) => {
+                return Err(DataFusionError::Configuration(format!(
+                    "Delta table {} sets both version and timestamp",
+                    settings.name
+                )));
+            }
+            (Some(version), None) => builder.with_version(version),
+            (None, Some(timestamp)) => builder.with_datestring(timestamp).map_err(delta_error)?,
+            (None, None) => builder,
+        };
+        let table = builder.load().await.map_err(delta_error)?;
+        let version = table.version();
+        ctx.register_table(settings.name.as_str(), Arc::new(table))?;
+        tracing::info!("Registered Delta table {} at version {} from {}", settings.name, version, settings.url);
+    }
+    Ok(())
+}
+
+/// 取 `object_stores` 中前缀匹配的存储配置，本地路径没有配置
+fn storage_options(url: &str, stores: &[ObjectStoreSettings]) -> Result<HashMap<String, String>> {
+    match stores.iter().find(|store| url.starts_with(store.url.trim_end_matches('/'))) {
+        Some(store) => Ok(store.options()?.into_iter().collect()),
+        None => Ok(HashMap::new()),
+    }
+}
+
+fn delta_error(e: DeltaTableError) -> DataFusionError {
+    DataFusionError::External(Box::new(e))
+}
+
+/// 存为 Delta 表的 `stock_quotes`
+pub struct DeltaQuotes {
+    ctx: SessionContext,
+    /// 最近提交后的表，写入和维护依次进行
+    table: Mutex<DeltaTable>,
+}
+
+impl DeltaQuotes {
+    /// 打开 `url` 处的表并注册 `stock_quotes`，表不存在时在首次写入时创建
+    pub async fn open(ctx: SessionContext, url: &str, stores: &[ObjectStoreSettings]) -> Result<Self> {
+        let mut table = DeltaTableBuilder::from_uri(url)
+            .with_storage_options(storage_options(url, stores)?)
+            .build()
+            .map_err(delta_error)?;
+        match table.load().await {
+            Ok(()) | Err(DeltaTableError::NotATable(_)) => {}
+            Err(e) => return Err(delta_error(e)),
+        }
+        tracing::info!("Opened stock_quotes Delta table at version {} from {}", table.version(), url);
+        let store = Self { ctx, table: Mutex::new(table) };
+        store.register(&*store.table.lock().await)?;
+        Ok(store)
+    }
+
+    /// 以一次提交写入校验过的行情，返回写入的行数
+    pub async fn append(&self, batch: RecordBatch) -> Result<usize> {
+        let row_count = batch.num_rows();
+        let mut table = self.table.lock().await;
+        let updated = DeltaOps(table.clone())
+            .write(vec![batch])
+            .with_save_mode(SaveMode::Append)
+            .await
+            .map_err(delta_error)?;
+        self.register(&updated)?;
+        *table = updated;
+        Ok(row_count)
+    }
+
+    /// 删除早于保留期的行，合并小于 `compaction_target_bytes` 的文件，再清理不再引用的文件
+    pub async fn maintain(&self, settings: &DatasetSettings, today: NaiveDate) -> Result<MaintenanceReport> {
+        let mut report = MaintenanceReport::default();
+        let mut table = self.table.lock().await;
+        if table.version() < 0 {
+            return Ok(report);
+        }
+
+        if let Some(days) = settings.retention_days {
+            let cutoff = (today - chrono::Duration::days(i64::from(days))).and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+            let cutoff = ScalarValue::TimestampMillisecond(Some(cutoff.timestamp_millis()), Some("UTC".into()));
+            let (updated, metrics) = DeltaOps(table.clone())
+                .delete()
+                .with_predicate(col("timestamp").lt(lit(cutoff)))
+                .await
+                .map_err(delta_error)?;
+            report.deleted_rows = metrics.num_deleted_rows.unwrap_or_default();
+            self.register(&updated)?;
+            *table = updated;
+        }
+
+        if settings.compaction_target_bytes > 0 {
+            let (updated, metrics) = DeltaOps(table.clone())
+                .optimize()
+                .with_target_size(settings.compaction_target_bytes as i64)
+                .await
+                .map_err(delta_error)?;
+            report.compacted_partitions = metrics.partitions_optimized as usize;
+            report.merged_files = metrics.num_files_removed as usize;
+            self.register(&updated)?;
+            *table = updated;
+        }
+
+        let (updated, _) = DeltaOps(table.clone()).vacuum().await.map_err(delta_error)?;
+        self.register(&updated)?;
+        *table = updated;
+        Ok(report)
+    }
+
+    /// 以当前版本重新注册 `stock_quotes`，表还没有提交时注册空表
+    fn register(&self, table: &DeltaTable) -> Result<()> {
+        let provider: Arc<dyn TableProvider> = if table.version() < 0 {
+            Arc::new(MemTable::try_new(Arc::new(create_stock_quotes_schema()), vec![vec![]])?)
+        } else {
+            Arc::new(table.clone())
+        };
+        self.ctx.register_table("stock_quotes", provider)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(date: &str) -> RecordBatch {
+        let json = format!(r#"{{"rows": [{{"symbol": "AAPL", "timestamp": "{}T14:30:00Z", "price": 180.5, "volume": 100}}]}}"#, date);
+        crate::ingest::decode(None, json.as_bytes()).unwrap()
+    }
+
+    async fn count(ctx: &SessionContext, table: &str) -> i64 {
+        let results = ctx.sql(&format!("SELECT COUNT(*) FROM {}", table)).await.unwrap().collect().await.unwrap();
+        results[0].column(0).as_any().downcast_ref::<datafusion::arrow::array::Int64Array>().unwrap().value(0)
+    }
+
+    #[tokio::test]
+    async fn test_delta_quotes() {
+        let dir = std::env::temp_dir().join(format!("alpha-data-engine-delta-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let url = dir.display().to_string();
+        let ctx = SessionContext::new();
+        let quotes = DeltaQuotes::open(ctx.clone(), &url, &[]).await.unwrap();
+        assert_eq!(count(&ctx, "stock_quotes").await, 0);
+
+        quotes.append(rows("2024-01-01")).await.unwrap();
+        quotes.append(rows("2024-03-01")).await.unwrap();
+        assert_eq!(count(&ctx, "stock_quotes").await, 2);
+
+        // 删除过期行后仍可读取删除前的版本
+        let settings = DatasetSettings { retention_days: Some(30), ..DatasetSettings::default() };
+        let report = quotes.maintain(&settings, NaiveDate::from_ymd_opt(2024, 3, 10).unwrap()).await.unwrap();
+        assert_eq!(report.deleted_rows, 1);
+        assert_eq!(count(&ctx, "stock_quotes").await, 1);
+
+        let history = DeltaTableSettings { name: "quotes_v1".to_string(), url: url.clone(), version: Some(1), timestamp: None };
+        register(&ctx, &[history], &[]).await.unwrap();
+        assert_eq!(count(&ctx, "quotes_v1").await, 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
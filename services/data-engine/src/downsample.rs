@@ -0,0 +1,260 @@
+//! 图表降采样
+//!
+//! 前端绘制多年行情时不需要每一条数据：`GET /downsample` 取一个代码在时间范围内的一列数值，
+//! 用 LTTB (Largest-Triangle-Three-Buckets) 算法保留至多 `points` 个视觉上有代表性的点，峰值和拐点不会被平均掉：
+//!
+//! ```text
+//! GET /downsample?symbol=AAPL&start=2020-01-01T00:00:00Z&end=2024-01-01T00:00:00Z&points=1000
+//! GET /downsample?symbol=AAPL&table=candles_1d&column=close&points=500
+//! ```
+//!
+//! `table` 默认为 `stock_quotes`，`column` 默认为 `price`，表须有 `symbol` 和 `timestamp` 列，`column` 须为数值列。
+//! `start` (含) 和 `end` (不含) 为 RFC 3339 时间，省略时不限制。`points` 默认 1000，范围 3 至 10000。
+//! 值为 NULL 的行被跳过。降采样在引擎中进行，范围内的全部数据会先读入内存，受查询超时限制 (见 [`crate::limits`])
+
+use crate::limits::LimitError;
+use crate::AppState;
+use axum::http::StatusCode;
+use axum::response::ErrorResponse;
+use chrono::{DateTime, Utc};
+use datafusion::arrow::array::{Array, Float64Array, Int64Array};
+use datafusion::arrow::compute::cast;
+use datafusion::arrow::datatypes::{DataType, TimeUnit};
+use datafusion::error::{DataFusionError, Result};
+use datafusion::prelude::{col, lit, SessionContext};
+use datafusion::scalar::ScalarValue;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// 未指定 `points` 时返回的点数
+pub const DEFAULT_POINTS: usize = 1000;
+/// 单次请求最多返回的点数
+pub const MAX_POINTS: usize = 10_000;
+
+/// `GET /downsample` 的查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DownsampleRequest {
+    pub symbol: String,
+    /// 起始时间 (含)
+    pub start: Option<DateTime<Utc>>,
+    /// 结束时间 (不含)
+    pub end: Option<DateTime<Utc>>,
+    /// 返回的点数，默认 1000，范围 3 至 10000
+    pub points: Option<usize>,
+    #[serde(default = "default_table")]
+    #[param(default = "stock_quotes")]
+    pub table: String,
+    /// 数值列
+    #[serde(default = "default_column")]
+    #[param(default = "price")]
+    pub column: String,
+}
+
+fn default_table() -> String {
+    "stock_quotes".to_string()
+}
+
+fn default_column() -> String {
+    "price".to_string()
+}
+
+/// `GET /downsample` 的响应
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DownsampleResponse {
+    pub symbol: String,
+    pub column: String,
+    /// 降采样前的点数
+    pub total_points: usize,
+    pub points: Vec<Point>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+pub struct Point {
+    pub timestamp: DateTime<Utc>,
+    pub value: f64,
+}
+
+/// 降采样一个代码的数据
+#[utoipa::path(
+    get,
+    path = "/downsample",
+    tag = "data-engine",
+    params(DownsampleRequest),
+    responses((status = 200, body = DownsampleResponse), (status = 400, description = "参数或列类型无效"), (status = 404, description = "表或列不存在"))
+)]
+pub async fn downsample(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::Query(request): axum::extract::Query<DownsampleRequest>,
+) -> Result<axum::Json<DownsampleResponse>, ErrorResponse> {
+    let points = request.points.unwrap_or(DEFAULT_POINTS);
+    if !(3..=MAX_POINTS).contains(&points) {
+        return Err(bad_request(format!("points must be between 3 and {}", MAX_POINTS)));
+    }
+    validate(&state.ctx, &request).await?;
+
+    let loading = series(&state.ctx, &request);
+    let series = match state.limits.timeout {
+        Some(timeout) => tokio::time::timeout(timeout, loading).await.map_err(|_| LimitError::timeout(timeout))?,
+        None => loading.await,
+    }
+    .map_err(|e| {
+        tracing::error!("Downsampling {} failed: {}", request.symbol, e);
+        ErrorResponse::from((StatusCode::INTERNAL_SERVER_ERROR, format!("Downsampling failed: {}", e)))
+    })?;
+
+    let points = lttb(&series, points)
+        .into_iter()
+        .filter_map(|(timestamp, value)| Some(Point { timestamp: DateTime::from_timestamp_millis(timestamp)?, value }))
+        .collect();
+    Ok(axum::Json(DownsampleResponse { symbol: request.symbol, column: request.column, total_points: series.len(), points }))
+}
+
+/// 检查表和列，表不存在时返回 404
+async fn validate(ctx: &SessionContext, request: &DownsampleRequest) -> Result<(), ErrorResponse> {
+    let Ok(table) = ctx.table_provider(request.table.as_str()).await else {
+        return Err(ErrorResponse::from((StatusCode::NOT_FOUND, format!("Table {} not found", request.table))));
+    };
+    let schema = table.schema();
+    for required in ["symbol", "timestamp"] {
+        if schema.field_with_name(required).is_err() {
+            return Err(bad_request(format!("Table {} has no {} column", request.table, required)));
+        }
+    }
+    match schema.field_with_name(&request.column) {
+        Ok(field) if field.data_type().is_numeric() => Ok(()),
+        Ok(field) => Err(bad_request(format!("Column {} is not numeric ({})", request.column, field.data_type()))),
+        Err(_) => Err(bad_request(format!("Table {} has no {} column", request.table, request.column))),
+    }
+}
+
+fn bad_request(message: String) -> ErrorResponse {
+    ErrorResponse::from((StatusCode::BAD_REQUEST, message))
+}
+
+/// 读取按时间排序的 (毫秒时间戳, 值)，跳过值为 NULL 的行
+async fn series(ctx: &SessionContext, request: &DownsampleRequest) -> Result<Vec<(i64, f64)>> {
+    let timestamp = |time: DateTime<Utc>| lit(ScalarValue::TimestampMillisecond(Some(time.timestamp_millis()), Some("UTC".into())));
+    let mut filter = col("symbol").eq(lit(request.symbol.as_str())).and(col(request.column.as_str()).is_not_null());
+    if let Some(start) = request.start {
+        filter = filter.and(col("timestamp").gt_eq(timestamp(start)));
+    }
+    if let Some(end) = request.end {
+        filter = filter.and(col("timestamp").lt(timestamp(end)));
+    }
+    let batches = ctx
+        .table(request.table.as_str())
+        .await?
+        .filter(filter)?
+        .select(vec![col("timestamp"), col(request.column.as_str())])?
+        .sort(vec![col("timestamp").sort(true, false)])?
+        .collect()
+        .await?;
+
+    let mut series = Vec::with_capacity(batches.iter().map(|batch| batch.num_rows()).sum());
+    for batch in &batches {
+        // 时间戳统一为毫秒
+        let timestamps = cast(batch.column(0), &DataType::Timestamp(TimeUnit::Millisecond, None))?;
+        let timestamps = cast(&timestamps, &DataType::Int64)?;
+        let values = cast(batch.column(1), &DataType::Float64)?;
+        let timestamps = timestamps.as_any().downcast_ref::<Int64Array>().ok_or_else(|| {
+            DataFusionError::Internal("Timestamps did not cast to Int64".to_string())
+        })?;
+        let values = values.as_any().downcast_ref::<Float64Array>().ok_or_else(|| {
+            DataFusionError::Internal(format!("Column {} did not cast to Float64", request.column))
+        })?;
+        for row in 0..batch.num_rows() {
+            if timestamps.is_valid(row) && values.is_valid(row) {
+                series.push((timestamps.value(row), values.value(row)));
+            }
+        }
+    }
+    Ok(series)
+}
+
+/// LTTB 降采样：保留首尾两点，其余点均分到 `threshold - 2` 个桶，每个桶取与前一个选中点和下一个桶均值
+/// 构成的三角形面积最大的点。点数不超过 `threshold` 时原样返回
+pub fn lttb(points: &[(i64, f64)], threshold: usize) -> Vec<(i64, f64)> {
+    if threshold >= points.len() || threshold < 3 {
+        return points.to_vec();
+    }
+    let every = (points.len() - 2) as f64 / (threshold - 2) as f64;
+    let mut sampled = Vec::with_capacity(threshold);
+    let mut selected = 0;
+    sampled.push(points[0]);
+
+    for bucket in 0..threshold - 2 {
+        // 下一个桶的均值，最后一个桶以末点为准
+        let next_start = ((bucket + 1) as f64 * every) as usize + 1;
+        let next_end = (((bucket + 2) as f64 * every) as usize + 1).min(points.len());
+        let next = &points[next_start..next_end];
+        let (average_x, average_y) = next
+            .iter()
+            .fold((0.0, 0.0), |(x, y), &(timestamp, value)| (x + timestamp as f64, y + value));
+        let (average_x, average_y) = (average_x / next.len() as f64, average_y / next.len() as f64);
+
+        let start = (bucket as f64 * every) as usize + 1;
+        let end = next_start;
+        let (selected_x, selected_y) = (points[selected].0 as f64, points[selected].1);
+        let mut largest = -1.0;
+        for (index, &(timestamp, value)) in points.iter().enumerate().take(end).skip(start) {
+            // 三角形面积的两倍，时间戳取差值以保持精度
+            let area = ((selected_x - average_x) * (value - selected_y)
+                - (selected_x - timestamp as f64) * (average_y - selected_y))
+                .abs();
+            if area > largest {
+                largest = area;
+                selected = index;
+            }
+        }
+        sampled.push(points[selected]);
+    }
+
+    sampled.push(points[points.len() - 1]);
+    sampled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::{StringArray, TimestampMillisecondArray};
+    use datafusion::arrow::record_batch::RecordBatch;
+    use datafusion::datasource::MemTable;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_lttb() {
+        let points: Vec<(i64, f64)> = (0..100).map(|x| (x, if x == 42 { 100.0 } else { (x % 5) as f64 })).collect();
+        let sampled = lttb(&points, 10);
+        assert_eq!(sampled.len(), 10);
+        assert_eq!((sampled[0], sampled[9]), (points[0], points[99]));
+        // 尖峰被保留
+        assert!(sampled.contains(&(42, 100.0)));
+        assert!(sampled.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(lttb(&points[..5], 10), points[..5].to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_series() {
+        let ctx = SessionContext::new();
+        let batch = RecordBatch::try_from_iter([
+            ("symbol", Arc::new(StringArray::from(vec!["AAPL", "MSFT", "AAPL", "AAPL"])) as _),
+            ("timestamp", Arc::new(TimestampMillisecondArray::from(vec![3_000, 1_000, 1_000, 2_000]).with_timezone("UTC")) as _),
+            ("price", Arc::new(Float64Array::from(vec![Some(3.0), Some(9.0), Some(1.0), None])) as _),
+        ])
+        .unwrap();
+        let table = MemTable::try_new(batch.schema(), vec![vec![batch]]).unwrap();
+        ctx.register_table("stock_quotes", Arc::new(table)).unwrap();
+
+        let request = |query: &str| {
+            let uri: axum::http::Uri = format!("/downsample?{}", query).parse().unwrap();
+            axum::extract::Query::<DownsampleRequest>::try_from_uri(&uri).unwrap().0
+        };
+        assert_eq!(series(&ctx, &request("symbol=AAPL")).await.unwrap(), vec![(1_000, 1.0), (3_000, 3.0)]);
+        let later = request("symbol=AAPL&start=1970-01-01T00:00:02Z");
+        assert_eq!(series(&ctx, &later).await.unwrap(), vec![(3_000, 3.0)]);
+
+        assert!(validate(&ctx, &request("symbol=AAPL&column=symbol")).await.is_err());
+        assert!(validate(&ctx, &request("symbol=AAPL&table=missing")).await.is_err());
+    }
+}
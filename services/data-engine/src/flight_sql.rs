@@ -0,0 +1,178 @@
+//! Arrow Flight SQL 服务
+//!
+//! 分析客户端 (Python ADBC、JDBC/ODBC 驱动、BI 工具) 通过 Flight SQL 执行 SQL，结果以 Arrow 流返回，
+//! 不经过 `/query` 的 JSON 转换，类型和精度不丢失。
+//!
+//! 目前支持语句查询 (`CommandStatementQuery`) 和服务信息 (`CommandGetSqlInfo`)。查询无状态：
+//! `GetFlightInfo` 只规划 SQL 以得到结果的 Schema，SQL 本身放在 Ticket 中，`DoGet` 时再执行
+
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::error::FlightError;
+use arrow_flight::flight_service_server::{FlightService, FlightServiceServer};
+use arrow_flight::sql::metadata::{SqlInfoData, SqlInfoDataBuilder};
+use arrow_flight::sql::server::FlightSqlService;
+use arrow_flight::sql::{CommandGetSqlInfo, CommandStatementQuery, ProstMessageExt, SqlInfo, TicketStatementQuery};
+use arrow_flight::{FlightDescriptor, FlightEndpoint, FlightInfo, Ticket};
+use datafusion::arrow::datatypes::Schema;
+use datafusion::error::DataFusionError;
+use datafusion::prelude::*;
+use futures::TryStreamExt;
+use prost::Message;
+use std::net::SocketAddr;
+use tonic::{Request, Response, Status};
+
+/// Flight SQL 服务
+pub struct FlightSqlServiceImpl {
+    ctx: SessionContext,
+    sql_info: SqlInfoData,
+}
+
+impl FlightSqlServiceImpl {
+    pub fn new(ctx: SessionContext) -> Self {
+        let mut builder = SqlInfoDataBuilder::new();
+        builder.append(SqlInfo::FlightSqlServerName, "Alpha Finance Data Engine");
+        builder.append(SqlInfo::FlightSqlServerVersion, env!("CARGO_PKG_VERSION"));
+        builder.append(SqlInfo::FlightSqlServerReadOnly, false);
+        let sql_info = builder.build().expect("static sql info is valid");
+        Self { ctx, sql_info }
+    }
+
+    /// 规划 SQL，出错时区分请求错误和服务端错误
+    async fn plan(&self, query: &str) -> Result<DataFrame, Status> {
+        self.ctx.sql(query).await.map_err(|e| {
+            tracing::error!("Flight SQL planning error: {}", e);
+            to_status(e)
+        })
+    }
+}
+
+/// 在 `addr` 上提供 Flight SQL 服务，直到服务出错
+pub async fn serve(ctx: SessionContext, addr: SocketAddr) -> Result<(), tonic::transport::Error> {
+    tonic::transport::Server::builder()
+        .add_service(FlightServiceServer::new(FlightSqlServiceImpl::new(ctx)))
+        .serve(addr)
+        .await
+}
+
+#[tonic::async_trait]
+impl FlightSqlService for FlightSqlServiceImpl {
+    type FlightService = FlightSqlServiceImpl;
+
+    async fn get_flight_info_statement(
+        &self,
+        query: CommandStatementQuery,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let df = self.plan(&query.query).await?;
+        let schema: Schema = df.schema().into();
+
+        let ticket = TicketStatementQuery { statement_handle: query.query.into() };
+        let endpoint = FlightEndpoint::new().with_ticket(Ticket::new(ticket.as_any().encode_to_vec()));
+        let info = FlightInfo::new()
+            .try_with_schema(&schema)
+            .map_err(|e| Status::internal(format!("Unable to encode schema: {}", e)))?
+            .with_endpoint(endpoint)
+            .with_descriptor(request.into_inner());
+        Ok(Response::new(info))
+    }
+
+    async fn do_get_statement(
+        &self,
+        ticket: TicketStatementQuery,
+        _request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        let query = String::from_utf8(ticket.statement_handle.to_vec())
+            .map_err(|_| Status::invalid_argument("Statement handle is not valid UTF-8"))?;
+        let results = self.plan(&query).await?.execute_stream().await.map_err(|e| {
+            tracing::error!("Flight SQL execution error: {}", e);
+            to_status(e)
+        })?;
+
+        // 先发送 Schema，结果为空时客户端也能得到列信息
+        let schema = results.schema();
+        let batches = results.map_err(|e| FlightError::ExternalError(Box::new(e)));
+        let stream = FlightDataEncoderBuilder::new()
+            .with_schema(schema)
+            .build(batches)
+            .map_err(Status::from);
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn get_flight_info_sql_info(
+        &self,
+        query: CommandGetSqlInfo,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let endpoint = FlightEndpoint::new().with_ticket(Ticket::new(query.as_any().encode_to_vec()));
+        let info = FlightInfo::new()
+            .try_with_schema(query.into_builder(&self.sql_info).schema().as_ref())
+            .map_err(|e| Status::internal(format!("Unable to encode schema: {}", e)))?
+            .with_endpoint(endpoint)
+            .with_descriptor(request.into_inner());
+        Ok(Response::new(info))
+    }
+
+    async fn do_get_sql_info(
+        &self,
+        query: CommandGetSqlInfo,
+        _request: Request<Ticket>,
+    ) -> Result<Response<<Self as FlightService>::DoGetStream>, Status> {
+        let builder = query.into_builder(&self.sql_info);
+        let schema = builder.schema();
+        let batch = builder.build().map_err(FlightError::from);
+        let stream = FlightDataEncoderBuilder::new()
+            .with_schema(schema)
+            .build(futures::stream::once(async { batch }))
+            .map_err(Status::from);
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn register_sql_info(&self, _id: i32, _result: &SqlInfo) {}
+}
+
+/// SQL 本身的错误属于请求错误，其余为服务端错误
+fn to_status(error: DataFusionError) -> Status {
+    match error {
+        DataFusionError::SQL(..) | DataFusionError::Plan(_) | DataFusionError::SchemaError(..) | DataFusionError::NotImplemented(_) => {
+            Status::invalid_argument(error.to_string())
+        }
+        _ => Status::internal(error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_flight::decode::FlightRecordBatchStream;
+    use arrow_flight::sql::Any;
+    use datafusion::arrow::record_batch::RecordBatch;
+
+    #[tokio::test]
+    async fn test_statement_query() {
+        let service = FlightSqlServiceImpl::new(SessionContext::new());
+        let query = CommandStatementQuery { query: "SELECT 1 AS x, 'AAPL' AS symbol".to_string(), transaction_id: None };
+        let info = service
+            .get_flight_info_statement(query, Request::new(FlightDescriptor::default()))
+            .await
+            .unwrap()
+            .into_inner();
+        let schema = info.clone().try_decode_schema().unwrap();
+        assert_eq!(schema.fields().iter().map(|field| field.name().as_str()).collect::<Vec<_>>(), vec!["x", "symbol"]);
+
+        let ticket = info.endpoint[0].ticket.clone().unwrap();
+        let statement = Any::decode(&*ticket.ticket).unwrap().unpack::<TicketStatementQuery>().unwrap().unwrap();
+        let stream = service.do_get_statement(statement, Request::new(ticket)).await.unwrap().into_inner();
+        let batches: Vec<RecordBatch> = FlightRecordBatchStream::new_from_flight_data(stream.map_err(FlightError::Tonic))
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(batches.iter().map(|batch| batch.num_rows()).sum::<usize>(), 1);
+
+        let invalid = CommandStatementQuery { query: "SELECT * FROM missing".to_string(), transaction_id: None };
+        let status = service
+            .get_flight_info_statement(invalid, Request::new(FlightDescriptor::default()))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
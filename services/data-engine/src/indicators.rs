@@ -0,0 +1,218 @@
+//! 技术指标窗口函数
+//!
+//! 把 alpha-core 指标注册表中只依赖收盘价的指标注册为 SQL 窗口函数，分析时不必先导出数据：
+//!
+//! ```sql
+//! SELECT symbol, timestamp, rsi(price, 14) OVER (PARTITION BY symbol ORDER BY timestamp) AS rsi
+//! FROM stock_quotes
+//! ```
+//!
+//! 第一个参数为价格列，其后按注册顺序传入指标参数 (须为常量)，省略的参数取默认值。
+//! 多输出指标按输出拆成多个函数，如 `bollinger_upper`、`macd_signal`。每个分区按 `ORDER BY`
+//! 排序后整体计算，窗口帧被忽略；结果与其他平台一致，包括预热期的 0 值。价格为 NULL 的行不参与计算，结果为 NULL
+
+use alpha_core::indicators::registry::{IndicatorInput, IndicatorParams, IndicatorRegistry, IndicatorSpec};
+use alpha_core::TechnicalIndicators;
+use datafusion::arrow::array::{Array, ArrayRef, Float64Array};
+use datafusion::arrow::compute::cast;
+use datafusion::arrow::datatypes::DataType;
+use datafusion::common::cast::as_float64_array;
+use datafusion::error::{DataFusionError, Result};
+use datafusion::logical_expr::{PartitionEvaluator, Signature, Volatility, WindowUDF, WindowUDFImpl};
+use datafusion::prelude::SessionContext;
+use std::any::Any;
+use std::sync::Arc;
+
+/// 注册全部指标窗口函数
+pub fn register(ctx: &SessionContext) {
+    let registry = Arc::new(IndicatorRegistry::new());
+    let specs: Vec<IndicatorSpec> = registry.specs().filter(|spec| !spec.requires_ohlcv).cloned().collect();
+    for spec in specs {
+        for output in spec.outputs {
+            let indicator = Indicator::new(registry.clone(), spec.clone(), *output);
+            tracing::debug!("Registering indicator function {}", indicator.function);
+            ctx.register_udwf(WindowUDF::new_from_impl(indicator));
+        }
+    }
+}
+
+/// 一个指标输出对应的窗口函数
+#[derive(Debug)]
+struct Indicator {
+    registry: Arc<IndicatorRegistry>,
+    spec: IndicatorSpec,
+    output: &'static str,
+    function: String,
+    signature: Signature,
+}
+
+impl Indicator {
+    fn new(registry: Arc<IndicatorRegistry>, spec: IndicatorSpec, output: &'static str) -> Self {
+        // 单输出或与指标同名的输出直接使用指标名
+        let function = if spec.outputs.len() == 1 || output == spec.name {
+            spec.name.to_string()
+        } else {
+            format!("{}_{}", spec.name, output)
+        };
+        // 参数类型在 return_type 中检查，计算时统一转换为 Float64
+        let signature = Signature::variadic_any(Volatility::Immutable);
+        Self { registry, spec, output, function, signature }
+    }
+}
+
+impl WindowUDFImpl for Indicator {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        &self.function
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        if arg_types.is_empty() || arg_types.len() > self.spec.params.len() + 1 {
+            let params: Vec<&str> = self.spec.params.iter().map(|(name, _)| *name).collect();
+            return Err(DataFusionError::Plan(format!(
+                "{} expects (price{}), got {} arguments",
+                self.function,
+                params.iter().map(|name| format!(", [{}]", name)).collect::<String>(),
+                arg_types.len()
+            )));
+        }
+        if let Some(arg_type) = arg_types.iter().find(|arg_type| !arg_type.is_numeric() && **arg_type != DataType::Null) {
+            return Err(DataFusionError::Plan(format!("{} expects numeric arguments, got {}", self.function, arg_type)));
+        }
+        Ok(DataType::Float64)
+    }
+
+    fn partition_evaluator(&self) -> Result<Box<dyn PartitionEvaluator>> {
+        Ok(Box::new(IndicatorEvaluator {
+            registry: self.registry.clone(),
+            spec: self.spec.clone(),
+            output: self.output,
+            function: self.function.clone(),
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct IndicatorEvaluator {
+    registry: Arc<IndicatorRegistry>,
+    spec: IndicatorSpec,
+    output: &'static str,
+    function: String,
+}
+
+impl IndicatorEvaluator {
+    /// 指标参数必须在分区内保持不变
+    fn params(&self, values: &[ArrayRef]) -> Result<IndicatorParams> {
+        let mut params = IndicatorParams::new();
+        for ((name, _), value) in self.spec.params.iter().zip(values) {
+            let value = cast(value, &DataType::Float64)?;
+            let value = as_float64_array(&value)?;
+            let first = value.iter().next().flatten();
+            match first {
+                Some(first) if value.iter().all(|v| v == Some(first)) => {
+                    params.insert(name.to_string(), first);
+                }
+                _ => {
+                    return Err(DataFusionError::Execution(format!(
+                        "Parameter {} of {} must be a non-null constant",
+                        name, self.function
+                    )))
+                }
+            }
+        }
+        Ok(params)
+    }
+}
+
+impl PartitionEvaluator for IndicatorEvaluator {
+    fn evaluate_all(&mut self, values: &[ArrayRef], num_rows: usize) -> Result<ArrayRef> {
+        if num_rows == 0 {
+            return Ok(Arc::new(Float64Array::from(Vec::<f64>::new())));
+        }
+        let prices = cast(&values[0], &DataType::Float64)?;
+        let prices = as_float64_array(&prices)?;
+        let params = self.params(&values[1..])?;
+
+        let close: Vec<f64> = prices.iter().flatten().collect();
+        let outputs = self
+            .registry
+            .calculate(&TechnicalIndicators::new(), self.spec.name, &IndicatorInput::close(&close), &params)
+            .map_err(|e| DataFusionError::Execution(format!("{}: {}", self.function, e)))?;
+        let result = outputs
+            .into_iter()
+            .find(|(name, _)| name == self.output)
+            .map(|(_, values)| values)
+            .ok_or_else(|| DataFusionError::Internal(format!("{} has no output {}", self.spec.name, self.output)))?;
+
+        // 把结果放回非 NULL 价格所在的行
+        let mut result = result.into_iter();
+        let array: Float64Array = prices.iter().map(|price| price.and_then(|_| result.next())).collect();
+        Ok(Arc::new(array))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::record_batch::RecordBatch;
+
+    async fn query(ctx: &SessionContext, sql: &str) -> Result<Vec<Option<f64>>> {
+        let batches: Vec<RecordBatch> = ctx.sql(sql).await?.collect().await?;
+        Ok(batches
+            .iter()
+            .flat_map(|batch| as_float64_array(batch.column(0)).unwrap().iter().collect::<Vec<_>>())
+            .collect())
+    }
+
+    #[tokio::test]
+    async fn test_indicator_functions() {
+        let ctx = SessionContext::new();
+        register(&ctx);
+        let prices: Vec<f64> = (0..30).map(|i| 100.0 + (i as f64 * 0.4).sin() * 5.0).collect();
+        let rows: Vec<String> = prices
+            .iter()
+            .enumerate()
+            .flat_map(|(i, price)| [format!("('AAPL', {}, {})", i, price), format!("('MSFT', {}, {})", i, price * 2.0)])
+            .collect();
+        let quotes = format!("(VALUES {}) AS t(symbol, ts, price)", rows.join(", "));
+
+        let indicators = TechnicalIndicators::new();
+        let expected = |values: Vec<f64>| values.into_iter().map(Some).collect::<Vec<_>>();
+        let rsi = query(&ctx, &format!("SELECT rsi(price, 10) OVER (PARTITION BY symbol ORDER BY ts) FROM {} WHERE symbol = 'AAPL' ORDER BY ts", quotes))
+            .await
+            .unwrap();
+        assert_eq!(rsi, expected(indicators.calculate_rsi(&prices, 10)));
+
+        // 省略的参数取默认值，整数价格同样可用
+        let signal = query(&ctx, &format!("SELECT macd_signal(CAST(price AS BIGINT)) OVER (ORDER BY ts) FROM {} WHERE symbol = 'MSFT' ORDER BY ts", quotes))
+            .await
+            .unwrap();
+        let doubled: Vec<f64> = prices.iter().map(|price| (price * 2.0).trunc()).collect();
+        assert_eq!(signal, expected(indicators.calculate_macd(&doubled, 12, 26, 9).1));
+
+        assert!(query(&ctx, &format!("SELECT sma(price, 5, 1) OVER (ORDER BY ts) FROM {}", quotes)).await.is_err());
+        assert!(query(&ctx, &format!("SELECT sma(symbol) OVER (ORDER BY ts) FROM {}", quotes)).await.is_err());
+        // 周期须为常量
+        assert!(query(&ctx, &format!("SELECT sma(price, ts + 1) OVER (ORDER BY ts) FROM {}", quotes)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_null_prices() {
+        let ctx = SessionContext::new();
+        register(&ctx);
+        let sma = query(
+            &ctx,
+            "SELECT sma(price, 2) OVER (ORDER BY ts) FROM (VALUES (1, 1.0), (2, NULL), (3, 3.0), (4, 5.0)) AS t(ts, price) ORDER BY ts",
+        )
+        .await
+        .unwrap();
+        assert_eq!(sma, vec![Some(0.0), None, Some(2.0), Some(4.0)]);
+    }
+}
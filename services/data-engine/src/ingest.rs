@@ -0,0 +1,467 @@
+//! 行情写入
+//!
+//! `POST /ingest` 接受 JSON (`{"rows": [...]}`) 或 Arrow IPC 流 (`Content-Type: application/vnd.apache.arrow.stream`)，
+//! 按 `stock_quotes` 的 Schema 校验后按日期和代码分区写入 Parquet：
+//! `<root>/date=2024-03-01/symbol=AAPL/part-<纳秒>-<序号>.parquet`。
+//!
+//! 每次写入在涉及的分区各生成一个新文件，先全部写成临时文件再改名，查询不会读到写了一半的数据。
+//! `stock_quotes` 是这些分区目录上的 ListingTable，查询时重新列出文件；新出现的分区随即注册到表中，写入后即可查询
+
+use crate::create_stock_quotes_schema;
+use crate::delta::DeltaQuotes;
+use arrow::array::{new_null_array, ArrayRef, AsArray, Float64Array, StringArray, TimestampMillisecondArray, UInt32Array, UInt64Array};
+use arrow::compute::{cast, concat_batches, take_record_batch};
+use arrow::datatypes::{DataType, Float64Type, TimestampMillisecondType};
+use arrow::error::ArrowError;
+use arrow::ipc::reader::StreamReader;
+use chrono::NaiveDate;
+use datafusion::datasource::file_format::parquet::ParquetFormat;
+use datafusion::datasource::listing::{ListingOptions, ListingTable, ListingTableConfig, ListingTableUrl};
+use datafusion::datasource::{MemTable, TableProvider};
+use datafusion::error::DataFusionError;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::prelude::SessionContext;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// 单次写入的最多行数
+pub const MAX_INGEST_ROWS: usize = 10_000;
+
+/// Arrow IPC 流格式的 Content-Type
+pub const ARROW_STREAM_CONTENT_TYPE: &str = "application/vnd.apache.arrow.stream";
+
+/// 写入请求
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct IngestRequest {
+    rows: Vec<IngestRow>,
+}
+
+/// 一条行情，逐笔行情没有开高低价
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct IngestRow {
+    symbol: String,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    price: f64,
+    volume: u64,
+    #[serde(default)]
+    open: Option<f64>,
+    #[serde(default)]
+    high: Option<f64>,
+    #[serde(default)]
+    low: Option<f64>,
+}
+
+/// 写入响应
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct IngestResponse {
+    pub success: bool,
+    pub row_count: usize,
+}
+
+/// 按 Content-Type 解析请求体，返回按 `stock_quotes` 列布局校验过的 RecordBatch
+pub fn decode(content_type: Option<&str>, body: &[u8]) -> Result<RecordBatch, ArrowError> {
+    let batch = match content_type.map(|value| value.split(';').next().unwrap_or_default().trim()) {
+        Some(ARROW_STREAM_CONTENT_TYPE) => conform(&read_ipc(body)?)?,
+        _ => {
+            let request: IngestRequest = serde_json::from_slice(body)
+                .map_err(|e| ArrowError::ParseError(format!("Invalid JSON body: {}", e)))?;
+            check_row_count(request.rows.len())?;
+            ingest_batch(&request.rows)?
+        }
+    };
+    validate_rows(&batch)?;
+    Ok(batch)
+}
+
+fn read_ipc(body: &[u8]) -> Result<RecordBatch, ArrowError> {
+    let reader = StreamReader::try_new(body, None)?;
+    let schema = reader.schema();
+    let batches = reader.collect::<Result<Vec<_>, _>>()?;
+    check_row_count(batches.iter().map(|batch| batch.num_rows()).sum())?;
+    concat_batches(&schema, &batches)
+}
+
+fn check_row_count(rows: usize) -> Result<(), ArrowError> {
+    if rows > MAX_INGEST_ROWS {
+        return Err(invalid(format!("At most {} rows can be ingested at once", MAX_INGEST_ROWS)));
+    }
+    Ok(())
+}
+
+/// 按 `stock_quotes` 的列布局构建 RecordBatch
+fn ingest_batch(rows: &[IngestRow]) -> Result<RecordBatch, ArrowError> {
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(rows.iter().map(|row| row.symbol.as_str()))),
+        Arc::new(
+            TimestampMillisecondArray::from_iter_values(rows.iter().map(|row| row.timestamp.timestamp_millis()))
+                .with_timezone("UTC"),
+        ),
+        Arc::new(Float64Array::from_iter_values(rows.iter().map(|row| row.price))),
+        Arc::new(UInt64Array::from_iter_values(rows.iter().map(|row| row.volume))),
+        Arc::new(rows.iter().map(|row| row.open).collect::<Float64Array>()),
+        Arc::new(rows.iter().map(|row| row.high).collect::<Float64Array>()),
+        Arc::new(rows.iter().map(|row| row.low).collect::<Float64Array>()),
+    ];
+    RecordBatch::try_new(Arc::new(create_stock_quotes_schema()), columns)
+}
+
+/// 把 IPC 传入的列对齐到 `stock_quotes`：按列名匹配，缺少的可空列补空值，时间戳统一为毫秒 UTC，
+/// 其余类型必须一致
+fn conform(batch: &RecordBatch) -> Result<RecordBatch, ArrowError> {
+    let schema = Arc::new(create_stock_quotes_schema());
+    let incoming = batch.schema();
+    if let Some(field) = incoming.fields().iter().find(|field| schema.field_with_name(field.name()).is_err()) {
+        return Err(invalid(format!("Unknown column {}", field.name())));
+    }
+
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let Ok(index) = incoming.index_of(field.name()) else {
+                return if field.is_nullable() {
+                    Ok(new_null_array(field.data_type(), batch.num_rows()))
+                } else {
+                    Err(invalid(format!("Missing column {}", field.name())))
+                };
+            };
+            let column = batch.column(index);
+            let column = match (column.data_type(), field.data_type()) {
+                (actual, expected) if actual == expected => column.clone(),
+                (DataType::Timestamp(..), DataType::Timestamp(..)) => cast(column, field.data_type())?,
+                (actual, expected) => {
+                    return Err(invalid(format!("Column {} must be {}, got {}", field.name(), expected, actual)));
+                }
+            };
+            if !field.is_nullable() && column.null_count() > 0 {
+                return Err(invalid(format!("Column {} must not contain nulls", field.name())));
+            }
+            Ok(column)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    RecordBatch::try_new(schema, columns)
+}
+
+/// 代码用作分区目录名，只允许字母、数字和 `-_.^`，且不能以 `.` 开头
+pub fn is_valid_symbol(symbol: &str) -> bool {
+    !symbol.is_empty()
+        && !symbol.starts_with('.')
+        && symbol.chars().all(|c| c.is_ascii_alphanumeric() || "-_.^".contains(c))
+}
+
+/// 单行能否写入：代码可用作分区目录名，价格为有限值
+pub fn is_valid_row(symbol: &str, price: f64) -> bool {
+    is_valid_symbol(symbol) && price.is_finite()
+}
+
+fn validate_rows(batch: &RecordBatch) -> Result<(), ArrowError> {
+    let symbols = batch.column(0).as_string::<i32>();
+    let prices = batch.column(2).as_primitive::<Float64Type>();
+    for row in 0..batch.num_rows() {
+        let symbol = symbols.value(row);
+        if !is_valid_row(symbol, prices.value(row)) {
+            return Err(invalid(format!("Invalid row for symbol {:?}", symbol)));
+        }
+    }
+    Ok(())
+}
+
+/// 按日期 (UTC) 和代码拆分，返回分区相对路径和各自的行
+fn partitions(batch: &RecordBatch) -> Result<Vec<(PathBuf, RecordBatch)>, ArrowError> {
+    let symbols = batch.column(0).as_string::<i32>();
+    let timestamps = batch.column(1).as_primitive::<TimestampMillisecondType>();
+    let mut rows: BTreeMap<(NaiveDate, &str), Vec<u32>> = BTreeMap::new();
+    for row in 0..batch.num_rows() {
+        let timestamp = timestamps.value(row);
+        let date = chrono::DateTime::from_timestamp_millis(timestamp)
+            .ok_or_else(|| invalid(format!("Invalid timestamp {}", timestamp)))?
+            .date_naive();
+        rows.entry((date, symbols.value(row))).or_default().push(row as u32);
+    }
+    rows.into_iter()
+        .map(|((date, symbol), indices)| {
+            let partition = PathBuf::from(format!("date={}", date)).join(format!("symbol={}", symbol));
+            Ok((partition, take_record_batch(batch, &UInt32Array::from(indices))?))
+        })
+        .collect()
+}
+
+fn invalid(message: String) -> ArrowError {
+    ArrowError::InvalidArgumentError(message)
+}
+
+/// `stock_quotes` 的存储：分区 Parquet 目录或 Delta 表 (见 [`crate::delta`])
+#[derive(Clone)]
+pub enum Quotes {
+    Parquet(Arc<QuoteStore>),
+    Delta(Arc<DeltaQuotes>),
+}
+
+impl Quotes {
+    /// 写入校验过的行情，返回写入的行数
+    pub async fn append(&self, batch: RecordBatch) -> datafusion::error::Result<usize> {
+        match self {
+            Self::Parquet(store) => store.append(batch).await,
+            Self::Delta(table) => table.append(batch).await,
+        }
+    }
+}
+
+/// 一个分区的文件统计
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, utoipa::ToSchema)]
+pub struct PartitionStats {
+    /// 相对根目录的分区路径，如 `date=2024-03-01/symbol=AAPL`
+    pub path: String,
+    pub files: usize,
+    /// 各文件 Parquet 元数据中的行数之和
+    pub rows: u64,
+    pub bytes: u64,
+}
+
+/// `stock_quotes` 的 Parquet 分区存储
+pub struct QuoteStore {
+    ctx: SessionContext,
+    root: PathBuf,
+    /// 已注册的分区 (相对 `root`)
+    partitions: Mutex<BTreeSet<PathBuf>>,
+    sequence: AtomicU64,
+}
+
+impl QuoteStore {
+    /// 扫描 `root` 下已有的分区并注册 `stock_quotes`
+    pub fn open(ctx: SessionContext, root: impl AsRef<Path>) -> datafusion::error::Result<Self> {
+        std::fs::create_dir_all(root.as_ref())?;
+        let root = root.as_ref().canonicalize()?;
+        let partitions = scan_partitions(&root)?;
+        let store = Self { ctx, root, partitions: Mutex::new(BTreeSet::new()), sequence: AtomicU64::new(0) };
+        store.register(&partitions)?;
+        tracing::info!("Registered {} stock_quotes partitions under {}", partitions.len(), store.root.display());
+        *store.partitions.lock().unwrap() = partitions;
+        Ok(store)
+    }
+
+    /// 写入校验过的行情，返回写入的行数
+    pub async fn append(&self, batch: RecordBatch) -> datafusion::error::Result<usize> {
+        let row_count = batch.num_rows();
+        let parts = partitions(&batch)?;
+        let name = format!(
+            "part-{}-{}",
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+            self.sequence.fetch_add(1, Ordering::Relaxed)
+        );
+        let root = self.root.clone();
+        let written = tokio::task::spawn_blocking(move || write_partitions(&root, &name, &parts))
+            .await
+            .map_err(|e| DataFusionError::External(Box::new(e)))??;
+
+        let mut partitions = self.partitions.lock().unwrap();
+        if written.iter().any(|partition| !partitions.contains(partition)) {
+            let mut updated = partitions.clone();
+            updated.extend(written);
+            self.register(&updated)?;
+            tracing::info!("Registered {} new stock_quotes partitions", updated.len() - partitions.len());
+            *partitions = updated;
+        }
+        Ok(row_count)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 已注册的分区 (相对 `root`)
+    pub fn partitions(&self) -> BTreeSet<PathBuf> {
+        self.partitions.lock().unwrap().clone()
+    }
+
+    /// 读取各分区文件的 Parquet 元数据统计行数，只读文件尾部，不扫描数据
+    pub fn partition_stats(&self) -> datafusion::error::Result<Vec<PartitionStats>> {
+        self.partitions()
+            .iter()
+            .map(|partition| {
+                let mut stats = PartitionStats { path: partition.display().to_string(), files: 0, rows: 0, bytes: 0 };
+                for entry in std::fs::read_dir(self.root.join(partition))? {
+                    let entry = entry?;
+                    let path = entry.path();
+                    if path.extension().is_some_and(|extension| extension == "parquet") {
+                        let reader = SerializedFileReader::new(std::fs::File::open(&path)?)?;
+                        stats.files += 1;
+                        stats.rows += reader.metadata().file_metadata().num_rows() as u64;
+                        stats.bytes += entry.metadata()?.len();
+                    }
+                }
+                Ok(stats)
+            })
+            .collect()
+    }
+
+    /// 先从 `stock_quotes` 中移除分区再删除目录，日期目录空了一并删除
+    pub fn drop_partitions(&self, dropped: &BTreeSet<PathBuf>) -> datafusion::error::Result<()> {
+        {
+            let mut partitions = self.partitions.lock().unwrap();
+            let remaining: BTreeSet<PathBuf> = partitions.difference(dropped).cloned().collect();
+            if remaining.len() == partitions.len() {
+                return Ok(());
+            }
+            self.register(&remaining)?;
+            *partitions = remaining;
+        }
+        for partition in dropped {
+            std::fs::remove_dir_all(self.root.join(partition))?;
+            if let Some(date) = partition.parent() {
+                // 同一日期下还有其他代码时目录非空，删除失败是预期的
+                let _ = std::fs::remove_dir(self.root.join(date));
+            }
+        }
+        Ok(())
+    }
+
+    /// 以分区目录重新注册 `stock_quotes`，没有分区时注册空表
+    fn register(&self, partitions: &BTreeSet<PathBuf>) -> datafusion::error::Result<()> {
+        let schema = Arc::new(create_stock_quotes_schema());
+        let table: Arc<dyn TableProvider> = if partitions.is_empty() {
+            Arc::new(MemTable::try_new(schema, vec![vec![]])?)
+        } else {
+            let urls = partitions
+                .iter()
+                .map(|partition| ListingTableUrl::parse(format!("{}/", self.root.join(partition).display())))
+                .collect::<datafusion::error::Result<Vec<_>>>()?;
+            let options = ListingOptions::new(Arc::new(ParquetFormat::default())).with_file_extension(".parquet");
+            let config = ListingTableConfig::new_with_multi_paths(urls)
+                .with_listing_options(options)
+                .with_schema(schema);
+            Arc::new(ListingTable::try_new(config)?)
+        };
+        self.ctx.register_table("stock_quotes", table)?;
+        Ok(())
+    }
+}
+
+/// 列出 `date=*/symbol=*` 分区目录
+fn scan_partitions(root: &Path) -> std::io::Result<BTreeSet<PathBuf>> {
+    let subdirectories = |dir: &Path, prefix: &str| -> std::io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type()?.is_dir() && name.starts_with(prefix) {
+                names.push(name);
+            }
+        }
+        Ok(names)
+    };
+
+    let mut partitions = BTreeSet::new();
+    for date in subdirectories(root, "date=")? {
+        for symbol in subdirectories(&root.join(&date), "symbol=")? {
+            partitions.insert(PathBuf::from(&date).join(symbol));
+        }
+    }
+    Ok(partitions)
+}
+
+/// 各分区先写临时文件，全部成功后再改名为 `<name>.parquet`；失败时删除已写的临时文件
+fn write_partitions(root: &Path, name: &str, parts: &[(PathBuf, RecordBatch)]) -> datafusion::error::Result<Vec<PathBuf>> {
+    let mut temporary = Vec::with_capacity(parts.len());
+    for (partition, batch) in parts {
+        let dir = root.join(partition);
+        let path = dir.join(format!("{}.parquet.tmp", name));
+        let result = std::fs::create_dir_all(&dir).map_err(DataFusionError::from).and_then(|()| write_parquet(&path, batch));
+        if let Err(e) = result {
+            for path in temporary.iter().chain([&path]) {
+                let _ = std::fs::remove_file(path);
+            }
+            return Err(e);
+        }
+        temporary.push(path);
+    }
+    for path in &temporary {
+        std::fs::rename(path, path.with_extension(""))?;
+    }
+    Ok(parts.iter().map(|(partition, _)| partition.clone()).collect())
+}
+
+pub fn write_parquet(path: &Path, batch: &RecordBatch) -> datafusion::error::Result<()> {
+    let file = std::fs::File::create(path)?;
+    let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+    let mut writer = ArrowWriter::try_new(file, batch.schema(), Some(properties))?;
+    writer.write(batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::Int64Array;
+    use arrow::datatypes::{Field, Schema, TimeUnit};
+    use arrow::ipc::writer::StreamWriter;
+
+    fn ipc(batch: &RecordBatch) -> Vec<u8> {
+        let mut writer = StreamWriter::try_new(Vec::new(), &batch.schema()).unwrap();
+        writer.write(batch).unwrap();
+        writer.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_decode() {
+        let json = br#"{"rows": [{"symbol": "AAPL", "timestamp": "2024-03-01T14:30:00Z", "price": 180.5, "volume": 100}]}"#;
+        let batch = decode(Some("application/json"), json).unwrap();
+        assert_eq!((batch.num_rows(), batch.column(4).null_count()), (1, 1));
+
+        // IPC 缺少可空列时补空值，时间戳按秒传入时转换为毫秒
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("symbol", DataType::Utf8, false),
+            Field::new("timestamp", DataType::Timestamp(TimeUnit::Second, None), false),
+            Field::new("price", DataType::Float64, false),
+            Field::new("volume", DataType::UInt64, false),
+        ]));
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from(vec!["MSFT"])),
+            Arc::new(arrow::array::TimestampSecondArray::from(vec![1_709_303_400])),
+            Arc::new(Float64Array::from(vec![410.0])),
+            Arc::new(UInt64Array::from(vec![10])),
+        ];
+        let batch = RecordBatch::try_new(schema.clone(), columns).unwrap();
+        let decoded = decode(Some("application/vnd.apache.arrow.stream; charset=binary"), &ipc(&batch)).unwrap();
+        assert_eq!(decoded.schema(), Arc::new(create_stock_quotes_schema()));
+        assert_eq!(decoded.column(1).as_primitive::<TimestampMillisecondType>().value(0), 1_709_303_400_000);
+        assert_eq!(decoded.column(6).null_count(), 1);
+
+        // 类型不符、未知列、非法代码都拒绝
+        let wrong = Schema::new(vec![Field::new("volume", DataType::Int64, false)]);
+        let batch = RecordBatch::try_new(Arc::new(wrong), vec![Arc::new(Int64Array::from(vec![1]))]).unwrap();
+        assert!(decode(Some(ARROW_STREAM_CONTENT_TYPE), &ipc(&batch)).is_err());
+        let extra = Schema::new(vec![Field::new("exchange", DataType::Utf8, false)]);
+        let batch = RecordBatch::try_new(Arc::new(extra), vec![Arc::new(StringArray::from(vec!["XNAS"]))]).unwrap();
+        assert!(decode(Some(ARROW_STREAM_CONTENT_TYPE), &ipc(&batch)).is_err());
+        let json = br#"{"rows": [{"symbol": "../etc", "timestamp": "2024-03-01T14:30:00Z", "price": 1.0, "volume": 1}]}"#;
+        assert!(decode(None, json).is_err());
+    }
+
+    #[test]
+    fn test_partitions() {
+        let json = br#"{"rows": [
+            {"symbol": "AAPL", "timestamp": "2024-03-01T14:30:00Z", "price": 180.5, "volume": 100},
+            {"symbol": "MSFT", "timestamp": "2024-03-01T14:30:00Z", "price": 410.0, "volume": 10},
+            {"symbol": "AAPL", "timestamp": "2024-03-02T00:00:00Z", "price": 181.0, "volume": 5},
+            {"symbol": "AAPL", "timestamp": "2024-03-01T23:59:59Z", "price": 180.9, "volume": 7}]}"#;
+        let batch = decode(None, json).unwrap();
+        let parts = partitions(&batch).unwrap();
+        let layout: Vec<_> = parts.iter().map(|(path, batch)| (path.display().to_string(), batch.num_rows())).collect();
+        assert_eq!(
+            layout,
+            vec![
+                ("date=2024-03-01/symbol=AAPL".to_string(), 2),
+                ("date=2024-03-01/symbol=MSFT".to_string(), 1),
+                ("date=2024-03-02/symbol=AAPL".to_string(), 1),
+            ]
+        );
+    }
+}
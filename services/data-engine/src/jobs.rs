@@ -0,0 +1,514 @@
+//! 异步查询任务
+//!
+//! 导出报表等耗时的查询不适合同步等待：`POST /jobs` 提交查询后立即返回任务 ID，查询在后台执行，
+//! 结果写入 `jobs_dir` 下的 Parquet 或 Arrow IPC 文件 (`format` 为 `parquet` 或 `arrow`，默认 Parquet)。
+//! - `GET /jobs/{id}` 返回任务状态 (`queued`、`running`、`succeeded`、`failed`、`cancelled`)
+//! - `GET /jobs/{id}/result` 下载成功任务的结果文件
+//! - `DELETE /jobs/{id}` 取消执行中的任务，已结束的任务删除其结果
+//!
+//! 同时最多执行 `max_running_jobs` 个任务，其余排队。任务不受 `query_timeout_secs` 限制，
+//! 执行中的任务也可以用 `DELETE /query/{id}` 取消 (见 [`crate::queries`])。
+//! 任务状态保存为结果旁的 `<id>.json`，重启后仍可查询，重启时未完成的任务记为失败；
+//! 结束超过 `job_retention_secs` 的任务在提交新任务时删除
+
+use crate::limits::LimitError;
+use crate::params::{self, QueryParam};
+use crate::queries::{QueryHandle, Queries};
+use crate::settings::Settings;
+use crate::AppState;
+use axum::http::StatusCode;
+use axum::response::{ErrorResponse, IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::ipc::writer::FileWriter;
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::error::{DataFusionError, Result};
+use datafusion::prelude::SessionContext;
+use futures::StreamExt;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::sync::{mpsc, Semaphore};
+use utoipa::ToSchema;
+
+/// 结果文件的格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobFormat {
+    #[default]
+    Parquet,
+    /// Arrow IPC 文件格式
+    Arrow,
+}
+
+impl JobFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Parquet => "parquet",
+            Self::Arrow => "arrow",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Parquet => "application/vnd.apache.parquet",
+            Self::Arrow => "application/vnd.apache.arrow.file",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    fn is_finished(self) -> bool {
+        matches!(self, Self::Succeeded | Self::Failed | Self::Cancelled)
+    }
+}
+
+/// 一个任务的状态，也是 `GET /jobs/{id}` 的响应
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Job {
+    pub id: String,
+    pub query: String,
+    pub format: JobFormat,
+    pub status: JobStatus,
+    pub submitted_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub row_count: Option<usize>,
+    /// 结果文件的大小
+    pub bytes: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// `POST /jobs` 的请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct JobRequest {
+    pub query: String,
+    #[serde(default)]
+    pub params: Vec<QueryParam>,
+    #[serde(default)]
+    pub format: JobFormat,
+}
+
+/// 全部任务
+#[derive(Debug)]
+pub struct Jobs {
+    dir: PathBuf,
+    jobs: Mutex<HashMap<String, Job>>,
+    permits: Arc<Semaphore>,
+    retention: Duration,
+}
+
+impl Jobs {
+    /// 读取 `jobs_dir` 中已有的任务，删除未写完的结果
+    pub fn open(settings: &Settings) -> std::io::Result<Self> {
+        let dir = settings.jobs_dir.clone();
+        std::fs::create_dir_all(&dir)?;
+        let mut jobs = HashMap::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            match path.extension().and_then(|extension| extension.to_str()) {
+                Some("tmp") => std::fs::remove_file(&path)?,
+                Some("json") => {
+                    let mut job: Job = match serde_json::from_slice(&std::fs::read(&path)?) {
+                        Ok(job) => job,
+                        Err(e) => {
+                            tracing::warn!("Ignoring unreadable job {}: {}", path.display(), e);
+                            continue;
+                        }
+                    };
+                    if !job.status.is_finished() {
+                        job.status = JobStatus::Failed;
+                        job.finished_at = Some(Utc::now());
+                        job.error = Some("Engine restarted before the job finished".to_string());
+                        persist(&dir, &job)?;
+                    }
+                    jobs.insert(job.id.clone(), job);
+                }
+                _ => {}
+            }
+        }
+        tracing::info!("Loaded {} jobs from {}", jobs.len(), dir.display());
+        Ok(Self {
+            dir,
+            jobs: Mutex::new(jobs),
+            permits: Arc::new(Semaphore::new(settings.max_running_jobs.max(1))),
+            retention: Duration::from_secs(settings.job_retention_secs),
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.jobs.lock().unwrap().get(id).cloned()
+    }
+
+    /// 在后台执行查询，任务 ID 同时是查询 ID
+    pub fn submit(self: &Arc<Self>, ctx: SessionContext, queries: &Arc<Queries>, request: JobRequest) -> Result<Job, LimitError> {
+        self.expire();
+        let query = queries.register(None)?;
+        let job = Job {
+            id: query.id().to_string(),
+            query: request.query.clone(),
+            format: request.format,
+            status: JobStatus::Queued,
+            submitted_at: Utc::now(),
+            finished_at: None,
+            row_count: None,
+            bytes: None,
+            error: None,
+        };
+        self.update(job.clone());
+        tokio::spawn(self.clone().run(ctx, request, query));
+        Ok(job)
+    }
+
+    async fn run(self: Arc<Self>, ctx: SessionContext, request: JobRequest, query: QueryHandle) {
+        let id = query.id().to_string();
+        let cancelled = query.cancelled();
+        let result = tokio::select! {
+            result = async {
+                let _permit = self.permits.acquire().await.map_err(|e| DataFusionError::External(Box::new(e)))?;
+                self.modify(&id, |job| job.status = JobStatus::Running);
+                self.execute(&ctx, &id, &request).await
+            } => Some(result),
+            () = cancelled => None,
+        };
+        // 取消或失败时删除未写完的结果
+        let _ = std::fs::remove_file(self.temporary_path(&id, request.format));
+        self.modify(&id, |job| {
+            job.finished_at = Some(Utc::now());
+            match result {
+                Some(Ok((row_count, bytes))) => {
+                    job.status = JobStatus::Succeeded;
+                    job.row_count = Some(row_count);
+                    job.bytes = Some(bytes);
+                }
+                Some(Err(e)) => {
+                    tracing::warn!("Job {} failed: {}", job.id, e);
+                    job.status = JobStatus::Failed;
+                    job.error = Some(e.to_string());
+                }
+                None => job.status = JobStatus::Cancelled,
+            }
+        });
+        drop(query);
+    }
+
+    /// 执行查询并写入结果文件，返回行数和文件大小
+    async fn execute(&self, ctx: &SessionContext, id: &str, request: &JobRequest) -> Result<(usize, u64)> {
+        let df = params::bind(ctx.sql(&request.query).await?, &request.params)?;
+        let mut results = df.execute_stream().await?;
+
+        // 写文件在阻塞线程中进行，结果流经有界通道传入
+        let temporary = self.temporary_path(id, request.format);
+        let (sender, mut receiver) = mpsc::channel::<RecordBatch>(2);
+        let (schema, format) = (results.schema(), request.format);
+        let writing = tokio::task::spawn_blocking({
+            let temporary = temporary.clone();
+            move || -> Result<usize> {
+                let mut writer = ResultWriter::try_new(&temporary, schema, format)?;
+                let mut row_count = 0;
+                while let Some(batch) = receiver.blocking_recv() {
+                    row_count += batch.num_rows();
+                    writer.write(&batch)?;
+                }
+                writer.finish()?;
+                Ok(row_count)
+            }
+        });
+        while let Some(batch) = results.next().await {
+            // 写入线程出错时通道关闭，停止执行并取回写入错误
+            if sender.send(batch?).await.is_err() {
+                break;
+            }
+        }
+        drop(sender);
+        let row_count = writing.await.map_err(|e| DataFusionError::External(Box::new(e)))??;
+
+        let path = self.result_path(id, request.format);
+        std::fs::rename(&temporary, &path)?;
+        Ok((row_count, std::fs::metadata(&path)?.len()))
+    }
+
+    /// 取消执行中的任务，已结束的任务连同结果一起删除；任务不存在时返回 false
+    pub fn remove(&self, id: &str, queries: &Queries) -> std::io::Result<bool> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let Some(job) = jobs.get(id) else {
+            return Ok(false);
+        };
+        if !job.status.is_finished() {
+            queries.cancel(id);
+            return Ok(true);
+        }
+        let format = job.format;
+        jobs.remove(id);
+        drop(jobs);
+        self.delete_files(id, format)?;
+        Ok(true)
+    }
+
+    fn delete_files(&self, id: &str, format: JobFormat) -> std::io::Result<()> {
+        for path in [self.result_path(id, format), self.dir.join(format!("{}.json", id))] {
+            match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// 删除结束超过保留时间的任务
+    fn expire(&self) {
+        let Ok(retention) = chrono::Duration::from_std(self.retention) else {
+            return;
+        };
+        let cutoff = Utc::now() - retention;
+        let mut expired = Vec::new();
+        self.jobs.lock().unwrap().retain(|id, job| match job.finished_at {
+            Some(finished_at) if finished_at < cutoff => {
+                expired.push((id.clone(), job.format));
+                false
+            }
+            _ => true,
+        });
+        for (id, format) in expired {
+            if let Err(e) = self.delete_files(&id, format) {
+                tracing::warn!("Failed to remove expired job {}: {}", id, e);
+            }
+        }
+    }
+
+    fn update(&self, job: Job) {
+        if let Err(e) = persist(&self.dir, &job) {
+            tracing::warn!("Failed to save job {}: {}", job.id, e);
+        }
+        self.jobs.lock().unwrap().insert(job.id.clone(), job);
+    }
+
+    fn modify(&self, id: &str, change: impl FnOnce(&mut Job)) {
+        let Some(mut job) = self.get(id) else {
+            return;
+        };
+        change(&mut job);
+        self.update(job);
+    }
+
+    fn result_path(&self, id: &str, format: JobFormat) -> PathBuf {
+        self.dir.join(format!("{}.{}", id, format.extension()))
+    }
+
+    fn temporary_path(&self, id: &str, format: JobFormat) -> PathBuf {
+        self.dir.join(format!("{}.{}.tmp", id, format.extension()))
+    }
+}
+
+/// 保存任务状态，先写临时文件再改名
+fn persist(dir: &Path, job: &Job) -> std::io::Result<()> {
+    let path = dir.join(format!("{}.json", job.id));
+    let temporary = dir.join(format!("{}.json.tmp", job.id));
+    std::fs::write(&temporary, serde_json::to_vec(job)?)?;
+    std::fs::rename(temporary, path)
+}
+
+enum ResultWriter {
+    Parquet(ArrowWriter<File>),
+    Arrow(FileWriter<File>),
+}
+
+impl ResultWriter {
+    fn try_new(path: &Path, schema: SchemaRef, format: JobFormat) -> Result<Self> {
+        let file = File::create(path)?;
+        Ok(match format {
+            JobFormat::Parquet => {
+                let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+                Self::Parquet(ArrowWriter::try_new(file, schema, Some(properties))?)
+            }
+            JobFormat::Arrow => Self::Arrow(FileWriter::try_new(file, &schema)?),
+        })
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        match self {
+            Self::Parquet(writer) => writer.write(batch)?,
+            Self::Arrow(writer) => writer.write(batch)?,
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            Self::Parquet(writer) => {
+                writer.close()?;
+            }
+            Self::Arrow(mut writer) => writer.finish()?,
+        }
+        Ok(())
+    }
+}
+
+fn not_found(id: &str) -> ErrorResponse {
+    ErrorResponse::from((StatusCode::NOT_FOUND, format!("Job {} not found", id)))
+}
+
+/// 提交任务
+#[utoipa::path(post, path = "/jobs", tag = "data-engine", request_body = JobRequest, responses((status = 202, description = "任务已排队", body = Job)))]
+pub async fn submit_job(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::Json(request): axum::Json<JobRequest>,
+) -> Result<(StatusCode, axum::Json<Job>), ErrorResponse> {
+    let job = state.jobs.submit(state.ctx.clone(), &state.queries, request)?;
+    tracing::info!("Submitted job {}", job.id);
+    Ok((StatusCode::ACCEPTED, axum::Json(job)))
+}
+
+/// 任务状态
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}",
+    tag = "data-engine",
+    params(("id" = String, Path)),
+    responses((status = 200, body = Job), (status = 404, description = "任务不存在"))
+)]
+pub async fn job_status(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<axum::Json<Job>, ErrorResponse> {
+    state.jobs.get(&id).map(axum::Json).ok_or_else(|| not_found(&id))
+}
+
+/// 下载任务结果，任务未成功时返回 409
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}/result",
+    tag = "data-engine",
+    params(("id" = String, Path)),
+    responses(
+        (status = 200, description = "Parquet 或 Arrow IPC 文件", content(
+            (crate::openapi::Binary = "application/vnd.apache.parquet"),
+            (crate::openapi::Binary = "application/vnd.apache.arrow.file"),
+        )),
+        (status = 404, description = "任务不存在"),
+        (status = 409, description = "任务未成功"),
+    )
+)]
+pub async fn job_result(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Response, ErrorResponse> {
+    let job = state.jobs.get(&id).ok_or_else(|| not_found(&id))?;
+    if job.status != JobStatus::Succeeded {
+        return Err(ErrorResponse::from((StatusCode::CONFLICT, format!("Job {} has no result", id))));
+    }
+    let file = tokio::fs::File::open(state.jobs.result_path(&id, job.format)).await.map_err(|e| {
+        tracing::error!("Failed to open result of job {}: {}", id, e);
+        ErrorResponse::from((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read result: {}", e)))
+    })?;
+    let chunks = futures::stream::unfold(Some(file), |file| async move {
+        let mut file = file?;
+        let mut chunk = vec![0; 64 * 1024];
+        match file.read(&mut chunk).await {
+            Ok(0) => None,
+            Ok(read) => {
+                chunk.truncate(read);
+                Some((Ok(chunk), Some(file)))
+            }
+            Err(e) => Some((Err(e), None)),
+        }
+    });
+    let disposition = format!("attachment; filename=\"{}.{}\"", id, job.format.extension());
+    Ok((
+        [
+            (axum::http::header::CONTENT_TYPE, job.format.content_type().to_string()),
+            (axum::http::header::CONTENT_DISPOSITION, disposition),
+        ],
+        axum::body::Body::from_stream(chunks),
+    )
+        .into_response())
+}
+
+/// 取消或删除任务
+#[utoipa::path(
+    delete,
+    path = "/jobs/{id}",
+    tag = "data-engine",
+    params(("id" = String, Path)),
+    responses((status = 200, description = "已取消或删除"), (status = 404, description = "任务不存在"))
+)]
+pub async fn delete_job(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<axum::Json<serde_json::Value>, ErrorResponse> {
+    match state.jobs.remove(&id, &state.queries) {
+        Ok(true) => Ok(axum::Json(serde_json::json!({ "success": true, "job_id": id }))),
+        Ok(false) => Err(not_found(&id)),
+        Err(e) => {
+            tracing::error!("Failed to delete job {}: {}", id, e);
+            Err(ErrorResponse::from((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete job: {}", e))))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    async fn wait(jobs: &Jobs, id: &str) -> Job {
+        for _ in 0..500 {
+            let job = jobs.get(id).unwrap();
+            if job.status.is_finished() {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("Job {} did not finish", id);
+    }
+
+    #[tokio::test]
+    async fn test_jobs() {
+        let dir = std::env::temp_dir().join(format!("alpha-data-engine-jobs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let settings = Settings { jobs_dir: dir.clone(), ..Settings::default() };
+        let jobs = Arc::new(Jobs::open(&settings).unwrap());
+        let queries = Arc::new(Queries::new());
+        let ctx = SessionContext::new();
+
+        let query = "SELECT x FROM (VALUES (1), (2), (3)) AS t(x)".to_string();
+        let request = JobRequest { query, params: Vec::new(), format: JobFormat::Parquet };
+        let job = jobs.submit(ctx.clone(), &queries, request).ok().unwrap();
+        let job = wait(&jobs, &job.id).await;
+        assert_eq!((job.status, job.row_count), (JobStatus::Succeeded, Some(3)));
+        let file = File::open(dir.join(format!("{}.parquet", job.id))).unwrap();
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap().build().unwrap();
+        let rows: usize = reader.map(|batch| batch.unwrap().num_rows()).sum();
+        assert_eq!(rows, 3);
+
+        let request = JobRequest { query: "SELECT * FROM missing".to_string(), params: Vec::new(), format: JobFormat::Arrow };
+        let failed = jobs.submit(ctx, &queries, request).ok().unwrap();
+        let failed = wait(&jobs, &failed.id).await;
+        assert_eq!(failed.status, JobStatus::Failed);
+        assert!(failed.error.unwrap().contains("missing"));
+
+        // 重启后仍可查询已结束的任务
+        let reopened = Jobs::open(&settings).unwrap();
+        assert_eq!(reopened.get(&job.id).unwrap().status, JobStatus::Succeeded);
+        assert!(reopened.remove(&job.id, &queries).unwrap());
+        assert!(!dir.join(format!("{}.parquet", job.id)).exists());
+        assert!(reopened.get(&job.id).is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
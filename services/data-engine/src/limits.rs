@@ -0,0 +1,249 @@
+//! 查询资源限制
+//!
+//! 单个查询不能拖垮引擎：
+//! - 规划和执行超过 `query_timeout_secs` 的查询被取消，流式结果在超时后中断
+//! - 全部查询共享大小为 `query_memory_bytes` 的 DataFusion 内存池，排序、聚合等算子申请不到内存时查询失败
+//! - JSON 结果分页返回，每页最多 `max_page_rows` 行 (请求中的 `page_size` 可以更小)，
+//!   响应中的 `next_cursor` 作为下一次请求的 `cursor` 获取下一页。分页按偏移量实现，查询需带 `ORDER BY` 才稳定
+//!
+//! 超出限制时返回带错误码的 JSON：`{"success": false, "error": "query_timeout", "message": "..."}`。
+//! 以上限制设为 0 表示不限制
+
+use crate::settings::Settings;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::runtime_env::{RuntimeConfig, RuntimeEnv};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::SendableRecordBatchStream;
+use futures::StreamExt;
+use serde::Serialize;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// 查询限制
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub timeout: Option<Duration>,
+    pub max_page_rows: Option<usize>,
+}
+
+impl Limits {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            timeout: (settings.query_timeout_secs > 0).then(|| Duration::from_secs(settings.query_timeout_secs)),
+            max_page_rows: (settings.max_page_rows > 0).then_some(settings.max_page_rows),
+        }
+    }
+}
+
+/// 带内存池的运行时环境
+pub fn runtime(settings: &Settings) -> Result<Arc<RuntimeEnv>> {
+    let mut config = RuntimeConfig::new();
+    if settings.query_memory_bytes > 0 {
+        config = config.with_memory_limit(settings.query_memory_bytes, 1.0);
+    }
+    Ok(Arc::new(RuntimeEnv::new(config)?))
+}
+
+/// 限制错误的错误码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitCode {
+    QueryTimeout,
+    MemoryLimitExceeded,
+    PageSizeExceeded,
+    InvalidCursor,
+    /// 被 `DELETE /query/{id}` 取消 (见 [`crate::queries`])
+    QueryCancelled,
+    InvalidQueryId,
+}
+
+impl LimitCode {
+    fn status(self) -> StatusCode {
+        match self {
+            Self::QueryTimeout => StatusCode::REQUEST_TIMEOUT,
+            Self::MemoryLimitExceeded => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::PageSizeExceeded | Self::InvalidCursor | Self::InvalidQueryId => StatusCode::BAD_REQUEST,
+            // 与 nginx 记录客户端取消请求的状态码一致
+            Self::QueryCancelled => StatusCode::from_u16(499).expect("valid status code"),
+        }
+    }
+}
+
+/// 超出限制的错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitError {
+    pub code: LimitCode,
+    pub message: String,
+}
+
+impl LimitError {
+    fn new(code: LimitCode, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+
+    pub fn timeout(timeout: Duration) -> Self {
+        Self::new(LimitCode::QueryTimeout, format!("Query exceeded the {}s time limit", timeout.as_secs()))
+    }
+
+    pub fn cancelled(id: &str) -> Self {
+        Self::new(LimitCode::QueryCancelled, format!("Query {} was cancelled", id))
+    }
+
+    pub fn invalid_query_id(message: impl Into<String>) -> Self {
+        Self::new(LimitCode::InvalidQueryId, message)
+    }
+
+    /// 内存池耗尽导致的执行错误
+    pub fn from_datafusion(error: &DataFusionError) -> Option<Self> {
+        match error.find_root() {
+            DataFusionError::ResourcesExhausted(message) => {
+                Some(Self::new(LimitCode::MemoryLimitExceeded, format!("Query exceeded the memory limit: {}", message)))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl IntoResponse for LimitError {
+    fn into_response(self) -> Response {
+        tracing::warn!("Query limit exceeded: {}", self.message);
+        let body = serde_json::json!({ "success": false, "error": self.code, "message": self.message });
+        (self.code.status(), axum::Json(body)).into_response()
+    }
+}
+
+/// 一页结果的范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    pub offset: usize,
+    pub size: Option<usize>,
+}
+
+impl Page {
+    pub fn new(cursor: Option<&str>, page_size: Option<usize>, limits: &Limits) -> Result<Self, LimitError> {
+        let offset = match cursor {
+            Some(cursor) => cursor
+                .parse()
+                .map_err(|_| LimitError::new(LimitCode::InvalidCursor, format!("Invalid cursor {:?}", cursor)))?,
+            None => 0,
+        };
+        let size = match (page_size, limits.max_page_rows) {
+            (Some(0), _) => return Err(LimitError::new(LimitCode::PageSizeExceeded, "page_size must be positive")),
+            (Some(size), Some(max)) if size > max => {
+                return Err(LimitError::new(LimitCode::PageSizeExceeded, format!("page_size exceeds the limit of {} rows", max)));
+            }
+            (Some(size), _) => Some(size),
+            (None, max) => max,
+        };
+        Ok(Self { offset, size })
+    }
+
+    /// 需要读取的行数，多读一行用于判断是否还有下一页
+    pub fn fetch(&self) -> Option<usize> {
+        self.size.map(|size| size + 1)
+    }
+
+    /// 截取本页的行，还有下一页时返回下一页的游标
+    pub fn split(&self, results: Vec<RecordBatch>) -> (Vec<RecordBatch>, Option<String>) {
+        let Some(size) = self.size else {
+            return (results, None);
+        };
+        let mut remaining = size;
+        let mut page = Vec::with_capacity(results.len());
+        let mut more = false;
+        for batch in results {
+            if batch.num_rows() > remaining {
+                more = true;
+                if remaining > 0 {
+                    page.push(batch.slice(0, remaining));
+                }
+                break;
+            }
+            remaining -= batch.num_rows();
+            page.push(batch);
+        }
+        (page, more.then(|| (self.offset + size).to_string()))
+    }
+}
+
+/// 缓存键中的分页部分
+impl fmt::Display for Page {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.size {
+            Some(size) => write!(f, "{}+{}", self.offset, size),
+            None => write!(f, "{}+", self.offset),
+        }
+    }
+}
+
+/// 超过截止时间后以错误结束结果流
+pub fn with_deadline(results: SendableRecordBatchStream, deadline: Instant, timeout: Duration) -> SendableRecordBatchStream {
+    let schema = results.schema();
+    let results = futures::stream::unfold(Some(results), move |results| async move {
+        let mut results = results?;
+        match tokio::time::timeout_at(deadline, results.next()).await {
+            Ok(Some(batch)) => Some((batch, Some(results))),
+            Ok(None) => None,
+            Err(_) => Some((Err(DataFusionError::Execution(LimitError::timeout(timeout).message)), None)),
+        }
+    });
+    Box::pin(RecordBatchStreamAdapter::new(schema, results))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::Int64Array;
+
+    fn batch(values: Vec<i64>) -> RecordBatch {
+        RecordBatch::try_from_iter([("value", Arc::new(Int64Array::from(values)) as _)]).unwrap()
+    }
+
+    #[test]
+    fn test_page() {
+        let limits = Limits { timeout: None, max_page_rows: Some(100) };
+        assert_eq!(Page::new(None, None, &limits).unwrap(), Page { offset: 0, size: Some(100) });
+        assert_eq!(Page::new(Some("200"), Some(3), &limits).unwrap(), Page { offset: 200, size: Some(3) });
+        assert_eq!(Page::new(None, Some(101), &limits).unwrap_err().code, LimitCode::PageSizeExceeded);
+        assert_eq!(Page::new(Some("next"), None, &limits).unwrap_err().code, LimitCode::InvalidCursor);
+        let unlimited = Limits { timeout: None, max_page_rows: None };
+        assert_eq!(Page::new(None, None, &unlimited).unwrap().fetch(), None);
+
+        let page = Page { offset: 200, size: Some(3) };
+        assert_eq!(page.fetch(), Some(4));
+        let (rows, cursor) = page.split(vec![batch(vec![1, 2]), batch(vec![3, 4])]);
+        assert_eq!(rows, vec![batch(vec![1, 2]), batch(vec![3])]);
+        assert_eq!(cursor.as_deref(), Some("203"));
+        let (rows, cursor) = page.split(vec![batch(vec![1, 2, 3])]);
+        assert_eq!(rows, vec![batch(vec![1, 2, 3])]);
+        assert_eq!(cursor, None);
+    }
+
+    #[test]
+    fn test_limit_error() {
+        let exhausted = DataFusionError::Context(
+            "sort".to_string(),
+            Box::new(DataFusionError::ResourcesExhausted("Failed to allocate".to_string())),
+        );
+        assert_eq!(LimitError::from_datafusion(&exhausted).unwrap().code, LimitCode::MemoryLimitExceeded);
+        assert_eq!(LimitError::from_datafusion(&DataFusionError::Plan("bad".to_string())), None);
+        assert_eq!(LimitError::timeout(Duration::from_secs(30)).into_response().status(), StatusCode::REQUEST_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn test_deadline() {
+        let schema = batch(vec![1]).schema();
+        let pending = futures::stream::iter(vec![Ok(batch(vec![1]))]).chain(futures::stream::pending());
+        let results = Box::pin(RecordBatchStreamAdapter::new(schema, pending));
+        let timeout = Duration::from_millis(100);
+        let items: Vec<_> = with_deadline(results, Instant::now() + timeout, timeout).collect().await;
+        assert_eq!(items.len(), 2);
+        assert!(items[0].is_ok());
+        assert!(items[1].is_err());
+    }
+}
@@ -0,0 +1,251 @@
+//! 实时行情接入
+//!
+//! 配置 `[live_feed]` 后，引擎订阅 real-time-feed 的 Redis 总线频道 (默认 `alpha:feed:ticks`)，把其中的 ticker 行情
+//! 攒批后经与 `POST /ingest` 相同的路径写入 `stock_quotes`，实时行情随即可以和历史数据一起查询：
+//!
+//! ```toml
+//! [live_feed]
+//! redis_url = "redis://redis:6379"
+//! batch_size = 500
+//! flush_interval_secs = 5
+//! ```
+//!
+//! 攒满 `batch_size` 条或每隔 `flush_interval_secs` 秒写入一次，写入失败的行情留待下次重试，
+//! 积压超过 [`MAX_PENDING`] 条时丢弃最早的。断线后按指数退避重连，断线期间发布的行情不会补写。
+//! feed 开启归档 (`FEED_ARCHIVE=on`) 时同一行情流会由 feed 推送到 `POST /ingest`，两者只开启一个
+
+use crate::ingest::{self, MAX_INGEST_ROWS};
+use crate::AppState;
+use alpha_core::utils::buffer::BatchBuffer;
+use alpha_core::utils::retry::RetryPolicy;
+use arrow::error::ArrowError;
+use chrono::{DateTime, Utc};
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::error::{DataFusionError, Result};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+
+/// 追加 `stock_quotes` 失败期间最多保留的实时行情数
+pub const MAX_PENDING: usize = 100_000;
+/// 断线后重连的最长等待
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// 实时行情接入配置
+#[derive(Clone, Deserialize)]
+pub struct LiveFeedSettings {
+    pub redis_url: String,
+    #[serde(default = "default_channel")]
+    pub channel: String,
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    #[serde(default = "default_flush_interval_secs")]
+    pub flush_interval_secs: u64,
+}
+
+fn default_channel() -> String {
+    "alpha:feed:ticks".to_string()
+}
+
+fn default_batch_size() -> usize {
+    500
+}
+
+fn default_flush_interval_secs() -> u64 {
+    5
+}
+
+// 不输出连接信息 (可能含密码)
+impl fmt::Debug for LiveFeedSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LiveFeedSettings")
+            .field("channel", &self.channel)
+            .field("batch_size", &self.batch_size)
+            .field("flush_interval_secs", &self.flush_interval_secs)
+            .finish()
+    }
+}
+
+/// 总线上的一条 ticker 行情，只取写入 `stock_quotes` 的字段
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LiveTick {
+    symbol: String,
+    timestamp: DateTime<Utc>,
+    price: f64,
+    volume: u64,
+}
+
+/// 解析频道中的消息，只保留 ticker 行情 (没有 `topic` 字段的旧版本消息视为 ticker)，
+/// 其他主题和无法写入的行情返回 None
+fn parse_tick(payload: &str) -> Option<LiveTick> {
+    let message: serde_json::Value = serde_json::from_str(payload).ok()?;
+    if message.get("topic").is_some_and(|topic| topic != "ticker") {
+        return None;
+    }
+    let tick: LiveTick = serde_json::from_value(message).ok()?;
+    ingest::is_valid_row(&tick.symbol, tick.price).then_some(tick)
+}
+
+/// 经写入路径解析和校验一批行情
+fn ingest_batch(ticks: &[LiveTick]) -> Result<RecordBatch, ArrowError> {
+    let body = serde_json::to_vec(&serde_json::json!({ "rows": ticks }))
+        .map_err(|e| ArrowError::JsonError(e.to_string()))?;
+    ingest::decode(None, &body)
+}
+
+/// 转换一批行情，失败时逐条检查，只丢弃自身无法写入的行情；全部丢弃时返回 None
+fn build_batch(ticks: &mut Vec<LiveTick>) -> Option<RecordBatch> {
+    if let Ok(batch) = ingest_batch(ticks) {
+        return Some(batch);
+    }
+    let before = ticks.len();
+    ticks.retain(|tick| ingest_batch(std::slice::from_ref(tick)).is_ok());
+    tracing::warn!("Dropping {} invalid live ticks", before - ticks.len());
+    if ticks.is_empty() {
+        return None;
+    }
+    ingest_batch(ticks)
+        .map_err(|e| tracing::warn!("Dropping {} live ticks: {}", ticks.len(), e))
+        .ok()
+}
+
+/// 校验配置并在后台接入实时行情
+pub fn spawn(state: AppState, settings: LiveFeedSettings) -> Result<()> {
+    if settings.batch_size == 0 || settings.batch_size > MAX_INGEST_ROWS {
+        return Err(DataFusionError::Configuration(format!(
+            "live_feed.batch_size must be between 1 and {}",
+            MAX_INGEST_ROWS
+        )));
+    }
+    if settings.flush_interval_secs == 0 {
+        return Err(DataFusionError::Configuration("live_feed.flush_interval_secs must be positive".to_string()));
+    }
+    let client = redis::Client::open(settings.redis_url.as_str())
+        .map_err(|e| DataFusionError::Configuration(format!("Invalid live_feed.redis_url: {}", e)))?;
+    tracing::info!("Ingesting live ticks from Redis channel {}", settings.channel);
+    tokio::spawn(consume(client, settings, state));
+    Ok(())
+}
+
+async fn consume(client: redis::Client, settings: LiveFeedSettings, state: AppState) {
+    let mut pending = BatchBuffer::new(MAX_PENDING);
+    let mut flush = tokio::time::interval(Duration::from_secs(settings.flush_interval_secs));
+    flush.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    // 上次写入失败后只在定时器触发时重试，不因攒满而反复写入
+    let mut failing = false;
+    let mut backoff = RetryPolicy::reconnect(MAX_RECONNECT_DELAY).backoffs();
+    loop {
+        let mut pubsub = match subscribe(&client, &settings.channel).await {
+            Ok(pubsub) => pubsub,
+            Err(e) => {
+                tracing::warn!("Live feed subscription to {} failed: {}", settings.channel, e);
+                tokio::time::sleep(backoff.next_delay()).await;
+                continue;
+            }
+        };
+        backoff.reset();
+
+        let mut messages = pubsub.on_message();
+        loop {
+            tokio::select! {
+                message = messages.next() => {
+                    let Some(message) = message else {
+                        tracing::warn!("Live feed subscription to {} ended, reconnecting", settings.channel);
+                        break;
+                    };
+                    let Some(tick) = message.get_payload::<String>().ok().as_deref().and_then(parse_tick) else {
+                        continue;
+                    };
+                    let dropped = pending.push(tick);
+                    if dropped > 0 {
+                        tracing::warn!("Live feed backlog is full, dropped {} ticks", dropped);
+                    }
+                    if failing || pending.len() < settings.batch_size {
+                        continue;
+                    }
+                }
+                _ = flush.tick() => {}
+            }
+            failing = !write_pending(&state, &mut pending, settings.batch_size).await;
+        }
+        tokio::time::sleep(backoff.next_delay()).await;
+    }
+}
+
+async fn subscribe(client: &redis::Client, channel: &str) -> redis::RedisResult<redis::aio::PubSub> {
+    let mut pubsub = client.get_async_connection().await?.into_pubsub();
+    pubsub.subscribe(channel).await?;
+    tracing::info!("Subscribed to Redis channel {}", channel);
+    Ok(pubsub)
+}
+
+/// 按批追加到 `stock_quotes`，追加失败时返回 false
+async fn write_pending(state: &AppState, pending: &mut BatchBuffer<LiveTick>, batch_size: usize) -> bool {
+    while !pending.is_empty() {
+        let mut ticks = pending.take(batch_size);
+        let Some(batch) = build_batch(&mut ticks) else {
+            continue;
+        };
+        if let Err(e) = state.append_quotes(batch).await {
+            tracing::warn!("Failed to ingest {} live ticks, {} pending: {}", ticks.len(), pending.len() + ticks.len(), e);
+            let dropped = pending.restore(ticks);
+            if dropped > 0 {
+                tracing::warn!("Live feed backlog is full, dropped {} ticks", dropped);
+            }
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(symbol: &str, second: u32) -> LiveTick {
+        LiveTick {
+            symbol: symbol.to_string(),
+            timestamp: format!("2024-03-01T14:30:{:02}Z", second).parse().unwrap(),
+            price: 180.5,
+            volume: 100,
+        }
+    }
+
+    #[test]
+    fn test_parse_tick() {
+        let ticker = r#"{"topic": "ticker", "symbol": "AAPL", "seq": 7, "price": 180.5, "volume": 100, "change": 0.5,
+                         "change_percent": 0.28, "timestamp": "2024-03-01T14:30:00Z"}"#;
+        assert_eq!(parse_tick(ticker), Some(tick("AAPL", 0)));
+        let legacy = r#"{"symbol": "AAPL", "price": 180.5, "volume": 100, "timestamp": "2024-03-01T14:30:00Z"}"#;
+        assert_eq!(parse_tick(legacy), Some(tick("AAPL", 0)));
+
+        let depth = r#"{"topic": "depth", "symbol": "AAPL", "timestamp": "2024-03-01T14:30:00Z", "bids": [], "asks": []}"#;
+        assert_eq!(parse_tick(depth), None);
+        let invalid = r#"{"symbol": "../etc", "price": 1.0, "volume": 1, "timestamp": "2024-03-01T14:30:00Z"}"#;
+        assert_eq!(parse_tick(invalid), None);
+        assert_eq!(parse_tick("not json"), None);
+    }
+
+    #[test]
+    fn test_ingest_batch() {
+        let batch = ingest_batch(&[tick("AAPL", 0), tick("MSFT", 1)]).unwrap();
+        assert_eq!(batch.schema(), std::sync::Arc::new(crate::create_stock_quotes_schema()));
+        assert_eq!(batch.num_rows(), 2);
+    }
+
+    #[test]
+    fn test_build_batch_drops_only_invalid_ticks() {
+        let mut ticks = vec![tick("AAPL", 0), tick("../etc", 1), tick("MSFT", 2)];
+        ticks[2].price = f64::NAN;
+        ticks.push(tick("GOOG", 3));
+        let batch = build_batch(&mut ticks).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(ticks, vec![tick("AAPL", 0), tick("GOOG", 3)]);
+
+        let mut invalid = vec![tick("../etc", 0)];
+        assert!(build_batch(&mut invalid).is_none());
+        assert!(invalid.is_empty());
+    }
+}
@@ -1,10 +1,58 @@
 //! Alpha Finance Data Engine
 //!
 //! 基于 DataFusion 的高性能数据处理引擎
-
+//!
+//! `POST /query` 执行 SQL 查询，可按 `Accept` 流式返回 NDJSON、CSV 或 Arrow IPC (见 [`streaming`])，重复的 JSON 查询从缓存返回 (见 [`cache`])；
+//! `POST /ingest` 把行情按日期和代码分区写入 Parquet 并追加到 `stock_quotes` 表，写入后即可查询 (见 [`ingest`])，也可以存为 Delta 表 (见 [`delta`])；
+//! 也可以直接从 real-time-feed 的总线接入实时行情 (见 [`live_feed`])。
+//! `GET /tables` 和 `GET /tables/{name}/schema` 列出已注册的表和表结构 (见 [`catalog`])。
+//! 耗时的查询可以用 `POST /jobs` 提交为后台任务，结果写入文件后下载 (见 [`jobs`])。
+//! `GET /downsample` 把长时间范围的行情降采样为适合绘图的点数 (见 [`downsample`])。
+//! 端口 50051 提供 Arrow Flight SQL，分析客户端直接以 Arrow 流获取查询结果 (见 [`flight_sql`])。
+//! 写入的分区定期合并小文件并按保留期清理 (见 [`storage`])。历史数据可以放在 S3/GCS/MinIO 等对象存储中 (见 [`object_stores`])。
+//! `candles_1m`/`5m`/`1h`/`1d` 是写入时增量维护的 K 线视图 (见 [`candles`])。
+//! 查询中可以直接使用 `rsi`、`sma` 等技术指标窗口函数 (见 [`indicators`])。
+//! `GET /openapi.json` 返回 HTTP 接口的 OpenAPI 规范 (见 [`openapi`])
+
+use alpha_core::utils::checksum;
 use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::error::DataFusionError;
+use axum::response::IntoResponse;
+use cache::{Generation, Lookup, QueryCache};
+use candles::CandleViews;
+use ingest::{IngestResponse, QuoteStore, Quotes};
+use limits::{LimitError, Limits, Page};
+use jobs::Jobs;
+use queries::{QueryHandle, Queries};
+use settings::Settings;
+use streaming::ResultFormat;
 use datafusion::prelude::*;
+use datafusion::physical_plan::display::DisplayableExecutionPlan;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Instant;
+
+mod cache;
+mod candles;
+mod catalog;
+mod delta;
+mod downsample;
+mod flight_sql;
+mod indicators;
+mod ingest;
+mod jobs;
+mod live_feed;
+mod limits;
+mod object_stores;
+mod openapi;
+mod params;
+mod queries;
+mod settings;
+mod storage;
+mod streaming;
+
+/// Flight SQL 监听地址
+const FLIGHT_SQL_ADDR: &str = "0.0.0.0:50051";
 
 #[tokio::main]
 async fn main() -> datafusion::error::Result<()> {
@@ -14,42 +62,107 @@ async fn main() -> datafusion::error::Result<()> {
     tracing::info!("Starting Alpha Finance Data Engine");
 
     // 创建 DataFusion 上下文
-    let ctx = SessionContext::new();
+    let settings = Settings::load()?;
+
+    let ctx = SessionContext::new_with_config_rt(SessionConfig::new(), limits::runtime(&settings)?);
+    indicators::register(&ctx);
+    object_stores::register(&ctx, &settings.object_stores)?;
+    delta::register_handlers();
 
     // 注册数据源
-    register_data_sources(&ctx).await?;
+    register_data_sources(&ctx, &settings).await?;
+    let quotes = match &settings.delta_quotes_url {
+        Some(url) => Quotes::Delta(Arc::new(delta::DeltaQuotes::open(ctx.clone(), url, &settings.object_stores).await?)),
+        None => Quotes::Parquet(Arc::new(QuoteStore::open(ctx.clone(), QUOTES_DIR)?)),
+    };
+    let cache = Arc::new(QueryCache::new(&settings)?);
+    start_maintenance(&settings, quotes.clone(), cache.clone())?;
+    let candles = {
+        let cache = cache.clone();
+        Arc::new(CandleViews::start(ctx.clone(), move || cache.invalidate()).await?)
+    };
 
     // 启动 HTTP 服务
-    start_http_server(ctx.clone()).await?;
+    let limits = Limits::new(&settings);
+    let jobs = Arc::new(Jobs::open(&settings)?);
+    let state = AppState { ctx: ctx.clone(), quotes, cache, candles, limits, queries: Arc::new(Queries::new()), jobs };
+    if let Some(live_feed) = &settings.live_feed {
+        live_feed::spawn(state.clone(), live_feed.clone())?;
+    }
+    start_http_server(state).await?;
 
-    // 启动 gRPC 服务
+    // 启动 gRPC 服务，运行到服务退出
     start_grpc_server(ctx.clone()).await?;
 
     Ok(())
 }
 
 /// 注册数据源
-async fn register_data_sources(ctx: &SessionContext) -> datafusion::error::Result<()> {
-    // 注册 TimescaleDB 作为外部表
-    ctx.register_table(
-        "stock_quotes",
-        Arc::new(datafusion::datasource::MemTable::try_new(
-            Arc::new(create_stock_quotes_schema()),
-            vec![vec![]], // 初始为空
-        )?),
-    )?;
-
-    // 注册 Parquet 数据文件
+async fn register_data_sources(ctx: &SessionContext, settings: &Settings) -> datafusion::error::Result<()> {
+    // 注册 Parquet 数据文件 (本地文件存在摘要旁路文件时先校验，避免分析截断或损坏的数据)
+    let historical_path = settings.historical_path.as_str();
+    if historical_path.contains("://") {
+        tracing::info!("Reading {} from object store, skipping integrity check", historical_path);
+    } else {
+        match checksum::verify_sidecar(Path::new(historical_path)) {
+            Ok(true) => tracing::info!("Checksum verified for {}", historical_path),
+            Ok(false) => tracing::warn!("No checksum found for {}, skipping integrity check", historical_path),
+            Err(e) => return Err(DataFusionError::External(Box::new(e))),
+        }
+    }
+
     ctx.register_parquet(
         "historical_data",
-        "/data/historical/stock_quotes.parquet",
+        historical_path,
         ParquetReadOptions::default(),
     ).await?;
 
+    delta::register(ctx, &settings.delta_tables, &settings.object_stores).await?;
+
     tracing::info!("Data sources registered successfully");
     Ok(())
 }
 
+/// 按数据集策略定期维护存储，删除过期数据后清空查询缓存
+fn start_maintenance(settings: &Settings, quotes: Quotes, cache: Arc<QueryCache>) -> datafusion::error::Result<()> {
+    if let Some(name) = settings.datasets.keys().find(|name| name.as_str() != "stock_quotes") {
+        return Err(DataFusionError::Configuration(format!("Unknown dataset {}", name)));
+    }
+    if settings.maintenance_interval_secs == 0 {
+        return Ok(());
+    }
+    let policy = settings.datasets.get("stock_quotes").cloned().unwrap_or_default();
+    tracing::info!("Maintaining stock_quotes every {}s with {:?}", settings.maintenance_interval_secs, policy);
+    let interval = std::time::Duration::from_secs(settings.maintenance_interval_secs);
+    storage::spawn(quotes, policy, interval, move || cache.invalidate());
+    Ok(())
+}
+
+/// `POST /ingest` 写入的分区根目录，未配置 `delta_quotes_url` 时使用
+const QUOTES_DIR: &str = "/data/quotes/stock_quotes";
+
+/// HTTP 服务共享的状态
+#[derive(Clone)]
+struct AppState {
+    ctx: SessionContext,
+    quotes: Quotes,
+    cache: Arc<QueryCache>,
+    candles: Arc<CandleViews>,
+    limits: Limits,
+    queries: Arc<Queries>,
+    jobs: Arc<Jobs>,
+}
+
+impl AppState {
+    /// 追加校验过的行情到 `stock_quotes`，随后清空查询缓存并更新 K 线视图
+    async fn append_quotes(&self, batch: RecordBatch) -> datafusion::error::Result<usize> {
+        let row_count = self.quotes.append(batch.clone()).await?;
+        self.cache.invalidate();
+        self.candles.update(batch);
+        Ok(row_count)
+    }
+}
+
 /// 创建股票行情数据的 Arrow Schema
 fn create_stock_quotes_schema() -> arrow::datatypes::Schema {
     arrow::datatypes::Schema::new(vec![
@@ -62,7 +175,7 @@ fn create_stock_quotes_schema() -> arrow::datatypes::Schema {
             "timestamp",
             arrow::datatypes::DataType::Timestamp(
                 arrow::datatypes::TimeUnit::Millisecond,
-                Some(Box::new(arrow::datatypes::DataType::Utf8)),
+                Some("UTC".into()),
             ),
             false,
         ),
@@ -95,11 +208,20 @@ fn create_stock_quotes_schema() -> arrow::datatypes::Schema {
 }
 
 /// 启动 HTTP 服务
-async fn start_http_server(ctx: SessionContext) -> datafusion::error::Result<()> {
+async fn start_http_server(state: AppState) -> datafusion::error::Result<()> {
     let app = axum::Router::new()
         .route("/health", axum::routing::get(health_check))
         .route("/query", axum::routing::post(execute_query))
-        .with_state(ctx);
+        .route("/query/:id", axum::routing::delete(cancel_query))
+        .route("/ingest", axum::routing::post(ingest))
+        .route("/tables", axum::routing::get(catalog::list_tables))
+        .route("/tables/:name/schema", axum::routing::get(catalog::table_schema))
+        .route("/downsample", axum::routing::get(downsample::downsample))
+        .route("/jobs", axum::routing::post(jobs::submit_job))
+        .route("/jobs/:id", axum::routing::get(jobs::job_status).delete(jobs::delete_job))
+        .route("/jobs/:id/result", axum::routing::get(jobs::job_result))
+        .route("/openapi.json", axum::routing::get(openapi::openapi))
+        .with_state(state);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:8081").await?;
     tracing::info!("Data Engine HTTP server listening on 0.0.0.0:8081");
@@ -113,28 +235,127 @@ async fn start_http_server(ctx: SessionContext) -> datafusion::error::Result<()>
     Ok(())
 }
 
-/// 启动 gRPC 服务
-async fn start_grpc_server(_ctx: SessionContext) -> datafusion::error::Result<()> {
-    // gRPC 服务实现将在后续添加
-    tracing::info!("gRPC service not yet implemented");
-    Ok(())
+/// 启动 gRPC 服务 (Arrow Flight SQL)
+async fn start_grpc_server(ctx: SessionContext) -> datafusion::error::Result<()> {
+    let addr = FLIGHT_SQL_ADDR.parse().expect("valid Flight SQL address");
+    tracing::info!("Data Engine Flight SQL server listening on {}", FLIGHT_SQL_ADDR);
+    flight_sql::serve(ctx, addr).await.map_err(|e| DataFusionError::External(Box::new(e)))
 }
 
 /// 健康检查
-async fn health_check() -> axum::Json<serde_json::Value> {
+#[utoipa::path(get, path = "/health", tag = "data-engine", responses((status = 200, description = "服务状态和查询缓存统计")))]
+async fn health_check(axum::extract::State(state): axum::extract::State<AppState>) -> axum::Json<serde_json::Value> {
     axum::Json(serde_json::json!({
         "status": "healthy",
         "service": "data-engine",
         "timestamp": chrono::Utc::now(),
+        "cache": state.cache.stats(),
     }))
 }
 
-/// 执行 SQL 查询
+/// 执行 SQL 查询，`Accept` 指定 NDJSON、CSV 或 Arrow IPC 流时流式返回 (见 [`streaming`])，
+/// JSON 格式的结果分页返回并会被缓存 (见 [`limits`]、[`cache`])
+#[utoipa::path(
+    post,
+    path = "/query",
+    tag = "data-engine",
+    request_body = QueryRequest,
+    responses(
+        (status = 200, description = "查询结果；`Accept` 为 NDJSON、CSV 或 Arrow IPC 流时流式返回对应格式", body = QueryResponse),
+        (status = 400, description = "SQL 错误、参数或游标无效"),
+        (status = 408, description = "查询超时"),
+        (status = 413, description = "超出内存限制"),
+        (status = 499, description = "查询被取消"),
+    )
+)]
 async fn execute_query(
-    axum::extract::State(ctx): axum::extract::State<SessionContext>,
+    axum::extract::State(state): axum::extract::State<AppState>,
+    headers: axum::http::HeaderMap,
     axum::Json(request): axum::Json<QueryRequest>,
-) -> Result<axum::Json<QueryResponse>, axum::response::ErrorResponse> {
-    let df = match ctx.sql(&request.query).await {
+) -> Result<axum::response::Response, axum::response::ErrorResponse> {
+    let format = ResultFormat::from_accept(headers.get(axum::http::header::ACCEPT).and_then(|value| value.to_str().ok()));
+    let page = Page::new(request.cursor.as_deref(), request.page_size, &state.limits)?;
+    // 需要执行计划时不读写缓存
+    let cached = if format == ResultFormat::Json && !request.explain {
+        let key = format!("{}\n{}", cache::key(&request.query, &request.params), page);
+        match state.cache.get(&key).await {
+            Lookup::Hit(results) => {
+                let (results, next_cursor) = page.split(results);
+                let mut response = QueryResponse::new(&results)?;
+                response.cached = true;
+                response.next_cursor = next_cursor;
+                return Ok(axum::Json(response).into_response());
+            }
+            Lookup::Miss(generation) => Some((key, generation)),
+        }
+    } else {
+        None
+    };
+
+    let query = state.queries.register(request.query_id.as_deref())?;
+    let id = query.id().to_string();
+    let cancelled = query.cancelled();
+    // 超时或取消后丢弃查询的 future，DataFusion 随之取消正在执行的任务
+    let run = async {
+        let Some(timeout) = state.limits.timeout else {
+            return run_query(&state, &request, format, page, cached, None, query).await;
+        };
+        let deadline = tokio::time::Instant::now() + timeout;
+        match tokio::time::timeout_at(deadline, run_query(&state, &request, format, page, cached, Some(deadline), query)).await {
+            Ok(response) => response,
+            Err(_) => Err(LimitError::timeout(timeout).into()),
+        }
+    };
+    let mut response = tokio::select! {
+        response = run => response?,
+        () = cancelled => return Err(LimitError::cancelled(&id).into()),
+    };
+    if let Ok(value) = axum::http::HeaderValue::from_str(&id) {
+        response.headers_mut().insert(queries::QUERY_ID_HEADER, value);
+    }
+    Ok(response)
+}
+
+/// 取消执行中的查询 (见 [`queries`])
+#[utoipa::path(
+    delete,
+    path = "/query/{id}",
+    tag = "data-engine",
+    params(("id" = String, Path, description = "查询 ID，即响应头 `X-Query-Id`")),
+    responses((status = 200, description = "已取消"), (status = 404, description = "查询不存在或已结束"))
+)]
+async fn cancel_query(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<axum::Json<serde_json::Value>, axum::response::ErrorResponse> {
+    if !state.queries.cancel(&id) {
+        return Err(axum::response::ErrorResponse::from((
+            axum::http::StatusCode::NOT_FOUND,
+            format!("Query {} not found", id),
+        )));
+    }
+    tracing::info!("Cancelled query {}", id);
+    Ok(axum::Json(serde_json::json!({ "success": true, "query_id": id })))
+}
+
+async fn run_query(
+    state: &AppState,
+    request: &QueryRequest,
+    format: ResultFormat,
+    page: Page,
+    cached: Option<(String, Generation)>,
+    deadline: Option<tokio::time::Instant>,
+    query: QueryHandle,
+) -> Result<axum::response::Response, axum::response::ErrorResponse> {
+    let started = Instant::now();
+    let planned = async {
+        let df = params::bind(state.ctx.sql(&request.query).await?, &request.params)?;
+        match format {
+            ResultFormat::Json if page.offset > 0 || page.size.is_some() => df.limit(page.offset, page.fetch()),
+            _ => Ok(df),
+        }
+    };
+    let df = match planned.await {
         Ok(df) => df,
         Err(e) => {
             tracing::error!("SQL execution error: {}", e);
@@ -145,101 +366,152 @@ async fn execute_query(
         }
     };
 
-    let results = match df.collect().await {
-        Ok(results) => results,
-        Err(e) => {
-            tracing::error!("Result collection error: {}", e);
-            return Err(axum::response::ErrorResponse::from((
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Execution error: {}", e)
-            )));
+    let execution_error = |e: DataFusionError| {
+        if let Some(error) = LimitError::from_datafusion(&e) {
+            return axum::response::ErrorResponse::from(error);
         }
+        tracing::error!("Query execution error: {}", e);
+        axum::response::ErrorResponse::from((
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Execution error: {}", e)
+        ))
     };
-
-    let response = QueryResponse {
-        success: true,
-        row_count: results.iter().map(|batch| batch.num_rows()).sum(),
-        data: results_to_json(&results)?,
-        execution_time_ms: 0, // TODO: 添加执行时间测量
+    let task_ctx = Arc::new(df.task_ctx());
+    let logical_plan = match request.explain {
+        true => Some(df.clone().into_optimized_plan().map_err(execution_error)?.display_indent().to_string()),
+        false => None,
     };
+    let physical_plan = df.create_physical_plan().await.map_err(execution_error)?;
+    let planning_time = started.elapsed();
+
+    if format != ResultFormat::Json {
+        tracing::debug!("Planned streaming query in {:?}", planning_time);
+        let mut results = datafusion::physical_plan::execute_stream(physical_plan, task_ctx).map_err(execution_error)?;
+        if let (Some(deadline), Some(timeout)) = (deadline, state.limits.timeout) {
+            results = limits::with_deadline(results, deadline, timeout);
+        }
+        let results = queries::cancellable(results, query);
+        return Ok(streaming::response(format, results));
+    }
 
-    Ok(axum::Json(response))
+    let started = Instant::now();
+    let results = datafusion::physical_plan::collect(physical_plan.clone(), task_ctx).await.map_err(execution_error)?;
+    let execution_time = started.elapsed();
+
+    let (rows, next_cursor) = page.split(results.clone());
+    let mut response = QueryResponse::new(&rows)?;
+    response.next_cursor = next_cursor;
+    response.planning_time_ms = planning_time.as_millis() as u64;
+    response.execution_time_ms = execution_time.as_millis() as u64;
+    // 执行后输出物理计划，附带各算子的行数和耗时
+    response.plan = logical_plan.map(|logical| QueryPlan {
+        logical,
+        physical: DisplayableExecutionPlan::with_metrics(physical_plan.as_ref()).indent(true).to_string(),
+    });
+    if let Some((key, generation)) = cached {
+        state.cache.insert(key, generation, results);
+    }
+    Ok(axum::Json(response).into_response())
 }
 
-/// 查询请求
-#[derive(serde::Deserialize)]
+/// 追加行情到 `stock_quotes`，请求体为 JSON 或 Arrow IPC 流
+#[utoipa::path(
+    post,
+    path = "/ingest",
+    tag = "data-engine",
+    request_body(content(
+        (ingest::IngestRequest = "application/json"),
+        (openapi::Binary = "application/vnd.apache.arrow.stream"),
+    )),
+    responses((status = 200, description = "写入的行数", body = IngestResponse), (status = 400, description = "行数据无效"))
+)]
+async fn ingest(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> Result<axum::Json<IngestResponse>, axum::response::ErrorResponse> {
+    let content_type = headers.get(axum::http::header::CONTENT_TYPE).and_then(|value| value.to_str().ok());
+    let batch = ingest::decode(content_type, &body).map_err(|e| {
+        axum::response::ErrorResponse::from((axum::http::StatusCode::BAD_REQUEST, format!("Invalid rows: {}", e)))
+    })?;
+    let row_count = batch.num_rows();
+    if row_count == 0 {
+        return Ok(axum::Json(IngestResponse { success: true, row_count }));
+    }
+
+    if let Err(e) = state.append_quotes(batch).await {
+        tracing::error!("Ingest error: {}", e);
+        return Err(axum::response::ErrorResponse::from((
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Ingest error: {}", e)
+        )));
+    }
+
+    tracing::debug!("Ingested {} rows into stock_quotes", row_count);
+    Ok(axum::Json(IngestResponse { success: true, row_count }))
+}
+
+/// 查询请求，`params` 依次绑定 `$1`、`$2` ... 占位符 (见 [`params`])
+#[derive(serde::Deserialize, utoipa::ToSchema)]
 struct QueryRequest {
     query: String,
+    #[serde(default)]
+    params: Vec<params::QueryParam>,
+    /// 在响应中附带优化后的逻辑计划和带执行指标的物理计划
+    #[serde(default)]
+    explain: bool,
+    /// 上一页响应中的 `next_cursor`
+    cursor: Option<String>,
+    /// 每页行数，不超过 `max_page_rows`
+    page_size: Option<usize>,
+    /// 客户端指定的查询 ID，用于取消执行中的查询 (见 [`queries`])
+    query_id: Option<String>,
 }
 
 /// 查询响应
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, utoipa::ToSchema)]
 struct QueryResponse {
     success: bool,
     row_count: usize,
+    /// 结果行，每行为列名到值的对象
     data: serde_json::Value,
+    /// 结果是否来自缓存，来自缓存时耗时为 0
+    cached: bool,
+    planning_time_ms: u64,
     execution_time_ms: u64,
+    /// 还有下一页时为下一页的游标
+    next_cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plan: Option<QueryPlan>,
 }
 
-/// 将 Arrow RecordBatch 转换为 JSON
-fn results_to_json(results: &[RecordBatch]) -> Result<serde_json::Value, anyhow::Error> {
-    let mut rows = Vec::new();
-
-    for batch in results {
-        let num_rows = batch.num_rows();
-        for row_idx in 0..num_rows {
-            let mut row = serde_json::Map::new();
-
-            for (col_idx, field) in batch.schema().fields().iter().enumerate() {
-                let column = batch.column(col_idx);
-                let value = arrow_to_json_value(column, row_idx)?;
-                row.insert(field.name().clone(), value);
-            }
-
-            rows.push(serde_json::Value::Object(row));
-        }
+impl QueryResponse {
+    fn new(results: &[RecordBatch]) -> Result<Self, axum::response::ErrorResponse> {
+        let data = streaming::json_rows(results).map_err(|e| {
+            tracing::error!("Result conversion error: {}", e);
+            axum::response::ErrorResponse::from((
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Result conversion error: {}", e)
+            ))
+        })?;
+        Ok(Self {
+            success: true,
+            row_count: results.iter().map(|batch| batch.num_rows()).sum(),
+            data,
+            cached: false,
+            planning_time_ms: 0,
+            execution_time_ms: 0,
+            next_cursor: None,
+            plan: None,
+        })
     }
-
-    Ok(serde_json::Value::Array(rows))
 }
 
-/// 将 Arrow Array 转换为 JSON Value
-fn arrow_to_json_value(
-    array: &arrow::array::ArrayRef,
-    row_idx: usize,
-) -> Result<serde_json::Value, anyhow::Error> {
-    use arrow::array::*;
-
-    if array.is_null(row_idx) {
-        return Ok(serde_json::Value::Null);
-    }
-
-    match array.data_type() {
-        arrow::datatypes::DataType::Utf8 => {
-            let string_array = array.as_any().downcast_ref::<StringArray>().unwrap();
-            Ok(serde_json::Value::String(string_array.value(row_idx).to_string()))
-        }
-        arrow::datatypes::DataType::Float64 => {
-            let float_array = array.as_any().downcast_ref::<Float64Array>().unwrap();
-            Ok(serde_json::Value::Number(
-                serde_json::Number::from_f64(float_array.value(row_idx)).unwrap()
-            ))
-        }
-        arrow::datatypes::DataType::UInt64 => {
-            let uint_array = array.as_any().downcast_ref::<UInt64Array>().unwrap();
-            Ok(serde_json::Value::Number(
-                serde_json::Number::from(uint_array.value(row_idx))
-            ))
-        }
-        arrow::datatypes::DataType::Timestamp(arrow::datatypes::TimeUnit::Millisecond, _) => {
-            let timestamp_array = array.as_any().downcast_ref::<TimestampMillisecondArray>().unwrap();
-            let timestamp = timestamp_array.value(row_idx);
-            let datetime = chrono::DateTime::from_timestamp_millis(timestamp)
-                .ok_or_else(|| anyhow::anyhow!("Invalid timestamp"))?;
-            Ok(serde_json::Value::String(datetime.to_rfc3339()))
-        }
-        _ => Ok(serde_json::Value::String(format!("{:?}", array.data_type()))),
-    }
+/// 查询的执行计划
+#[derive(serde::Serialize, utoipa::ToSchema)]
+struct QueryPlan {
+    logical: String,
+    physical: String,
 }
 
 #[cfg(test)]
@@ -257,4 +529,110 @@ mod tests {
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].num_rows(), 1);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_execute_query() {
+        let dir = std::env::temp_dir().join(format!("alpha-data-engine-query-{}", std::process::id()));
+        let settings = Settings { jobs_dir: dir.with_extension("jobs"), ..Settings::default() };
+        let ctx = SessionContext::new();
+        let state = AppState {
+            ctx: ctx.clone(),
+            quotes: Quotes::Parquet(Arc::new(QuoteStore::open(ctx.clone(), &dir).unwrap())),
+            cache: Arc::new(QueryCache::new(&Settings::default()).unwrap()),
+            candles: Arc::new(CandleViews::start(ctx.clone(), || ()).await.unwrap()),
+            limits: Limits::new(&Settings::default()),
+            queries: Arc::new(Queries::new()),
+            jobs: Arc::new(Jobs::open(&settings).unwrap()),
+        };
+        let query = |request: serde_json::Value| {
+            let state = state.clone();
+            async move {
+                let request = serde_json::from_value(request).unwrap();
+                let response = match execute_query(axum::extract::State(state), axum::http::HeaderMap::new(), axum::Json(request)).await {
+                    Ok(response) => response,
+                    Err(e) => e.into_response(),
+                };
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap_or_default())
+            }
+        };
+
+        let (_, response) = query(serde_json::json!({"query": "SELECT 1 + 1 AS two"})).await;
+        assert_eq!(response["data"], serde_json::json!([{"two": 2}]));
+        assert_eq!(response["cached"], false);
+        assert!(response.get("plan").is_none());
+        assert_eq!(query(serde_json::json!({"query": "SELECT 1 + 1 AS two"})).await.1["cached"], true);
+
+        // 需要执行计划时重新执行
+        let (_, response) = query(serde_json::json!({"query": "SELECT 1 + 1 AS two", "explain": true})).await;
+        assert_eq!(response["cached"], false);
+        assert!(response["plan"]["logical"].as_str().unwrap().contains("Projection"));
+        assert!(response["plan"]["physical"].as_str().unwrap().contains("ProjectionExec"));
+
+        // 分页
+        let sql = "SELECT x FROM (VALUES (1), (2), (3)) AS t(x) ORDER BY x";
+        let (_, response) = query(serde_json::json!({"query": sql, "page_size": 2})).await;
+        assert_eq!(response["data"], serde_json::json!([{"x": 1}, {"x": 2}]));
+        assert_eq!(response["next_cursor"], "2");
+        let (_, response) = query(serde_json::json!({"query": sql, "page_size": 2, "cursor": "2"})).await;
+        assert_eq!(response["data"], serde_json::json!([{"x": 3}]));
+        assert_eq!(response["next_cursor"], serde_json::Value::Null);
+        let (status, response) = query(serde_json::json!({"query": sql, "page_size": 1_000_000})).await;
+        assert_eq!(status, axum::http::StatusCode::BAD_REQUEST);
+        assert_eq!(response["error"], "page_size_exceeded");
+
+        // 响应头带查询 ID，已结束的查询无法取消
+        let request = serde_json::from_value(serde_json::json!({"query": "SELECT 1 AS one", "query_id": "report-1"})).unwrap();
+        let response = execute_query(axum::extract::State(state.clone()), axum::http::HeaderMap::new(), axum::Json(request)).await.ok().unwrap();
+        assert_eq!(response.headers()[queries::QUERY_ID_HEADER], "report-1");
+        let cancelled = cancel_query(axum::extract::State(state.clone()), axum::extract::Path("report-1".to_string())).await;
+        assert_eq!(cancelled.unwrap_err().into_response().status(), axum::http::StatusCode::NOT_FOUND);
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::remove_dir_all(&settings.jobs_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ingest() {
+        let dir = std::env::temp_dir().join(format!("alpha-data-engine-ingest-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let settings = Settings { jobs_dir: dir.with_extension("jobs"), ..Settings::default() };
+        let ctx = SessionContext::new();
+        let quotes = Quotes::Parquet(Arc::new(QuoteStore::open(ctx.clone(), &dir).unwrap()));
+        let (changed, candles_changed) = std::sync::mpsc::channel();
+        let candles = CandleViews::start(ctx.clone(), move || changed.send(()).unwrap()).await.unwrap();
+        let state = AppState {
+            ctx: ctx.clone(),
+            quotes,
+            cache: Arc::new(QueryCache::new(&Settings::default()).unwrap()),
+            candles: Arc::new(candles),
+            limits: Limits::new(&Settings::default()),
+            queries: Arc::new(Queries::new()),
+            jobs: Arc::new(Jobs::open(&settings).unwrap()),
+        };
+
+        let body = r#"{"rows": [{"symbol": "AAPL", "timestamp": "2024-03-01T14:30:00Z", "price": 180.5, "volume": 100},
+                                {"symbol": "AAPL", "timestamp": "2024-03-01T14:30:01Z", "price": 180.6, "volume": 50}]}"#;
+        let response = ingest(axum::extract::State(state.clone()), axum::http::HeaderMap::new(), body.into()).await.ok().unwrap();
+        assert_eq!(response.row_count, 2);
+
+        let df = ctx.sql("SELECT SUM(volume) AS volume FROM stock_quotes WHERE symbol = 'AAPL'").await.unwrap();
+        let results = df.collect().await.unwrap();
+        assert_eq!(streaming::json_rows(&results).unwrap(), serde_json::json!([{"volume": 150}]));
+        assert!(dir.join("date=2024-03-01/symbol=AAPL").is_dir());
+
+        // K 线视图在后台更新
+        candles_changed.recv_timeout(std::time::Duration::from_secs(5)).unwrap();
+        let df = ctx.sql("SELECT open, close, volume FROM candles_1m WHERE symbol = 'AAPL'").await.unwrap();
+        let results = df.collect().await.unwrap();
+        assert_eq!(streaming::json_rows(&results).unwrap(), serde_json::json!([{"open": 180.5, "close": 180.6, "volume": 150}]));
+
+        // 重启后从已有分区恢复
+        let ctx = SessionContext::new();
+        QuoteStore::open(ctx.clone(), &dir).unwrap();
+        let results = ctx.sql("SELECT COUNT(*) AS count FROM stock_quotes").await.unwrap().collect().await.unwrap();
+        assert_eq!(results[0].column(0).as_any().downcast_ref::<arrow::array::Int64Array>().unwrap().value(0), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::remove_dir_all(&settings.jobs_dir).unwrap();
+    }
+}
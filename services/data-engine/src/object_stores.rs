@@ -0,0 +1,115 @@
+//! 对象存储
+//!
+//! 配置文件中的每个 `[[object_stores]]` 把一个桶 (`s3://bucket`、`gs://bucket`) 注册到 DataFusion，
+//! 之后 `historical_path` 等数据源可以直接写成该桶中的 URL，引擎读取数据湖而不依赖本地挂载：
+//!
+//! ```toml
+//! [[object_stores]]
+//! url = "s3://lake"
+//! endpoint = "http://minio:9000"   # MinIO 等 S3 兼容存储
+//! region = "us-east-1"
+//! allow_http = true
+//! ```
+//!
+//! `url` 以外的键原样交给 object_store 的构建器，可用的键见 `AmazonS3ConfigKey` 和 `GoogleConfigKey`。
+//! 配置中未给出的凭证取标准环境变量 (`AWS_ACCESS_KEY_ID`、`AWS_SECRET_ACCESS_KEY`、`GOOGLE_SERVICE_ACCOUNT` 等)，
+//! 建议密钥只放在环境变量中
+
+use datafusion::error::{DataFusionError, Result};
+use datafusion::prelude::SessionContext;
+use object_store::aws::{AmazonS3Builder, AmazonS3ConfigKey};
+use object_store::gcp::{GoogleCloudStorageBuilder, GoogleConfigKey};
+use object_store::ObjectStore;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use url::Url;
+
+/// 一个对象存储桶
+#[derive(Debug, Clone, Deserialize)]
+pub struct ObjectStoreSettings {
+    pub url: String,
+    /// 构建器选项，值可以是字符串、数字或布尔
+    #[serde(flatten)]
+    options: BTreeMap<String, config::Value>,
+}
+
+impl ObjectStoreSettings {
+    pub fn options(&self) -> Result<BTreeMap<String, String>> {
+        self.options
+            .iter()
+            .map(|(key, value)| {
+                let value = value.clone().into_string().map_err(|e| configuration(&self.url, format!("{}: {}", key, e)))?;
+                Ok((key.clone(), value))
+            })
+            .collect()
+    }
+}
+
+/// 注册配置中的全部对象存储
+pub fn register(ctx: &SessionContext, stores: &[ObjectStoreSettings]) -> Result<()> {
+    for settings in stores {
+        let url = Url::parse(&settings.url).map_err(|e| configuration(&settings.url, e))?;
+        let store = build(&url, &settings.options()?)?;
+        ctx.runtime_env().register_object_store(&url, store);
+        tracing::info!("Registered object store {}", url);
+    }
+    Ok(())
+}
+
+fn build(url: &Url, options: &BTreeMap<String, String>) -> Result<Arc<dyn ObjectStore>> {
+    if url.host_str().is_none() {
+        return Err(configuration(url, "missing bucket"));
+    }
+    let store: Arc<dyn ObjectStore> = match url.scheme() {
+        "s3" => {
+            let mut builder = AmazonS3Builder::from_env().with_url(url.as_str());
+            for (key, value) in options {
+                let key: AmazonS3ConfigKey = key.parse().map_err(|e| configuration(url, e))?;
+                builder = builder.with_config(key, value);
+            }
+            Arc::new(builder.build().map_err(|e| configuration(url, e))?)
+        }
+        "gs" => {
+            let mut builder = GoogleCloudStorageBuilder::from_env().with_url(url.as_str());
+            for (key, value) in options {
+                let key: GoogleConfigKey = key.parse().map_err(|e| configuration(url, e))?;
+                builder = builder.with_config(key, value);
+            }
+            Arc::new(builder.build().map_err(|e| configuration(url, e))?)
+        }
+        scheme => return Err(configuration(url, format!("unsupported scheme {}", scheme))),
+    };
+    Ok(store)
+}
+
+fn configuration(url: impl std::fmt::Display, error: impl std::fmt::Display) -> DataFusionError {
+    DataFusionError::Configuration(format!("Invalid object store {}: {}", url, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::datasource::object_store::ObjectStoreUrl;
+
+    fn store(url: &str, options: &[(&str, &str)]) -> ObjectStoreSettings {
+        let options = options.iter().map(|(key, value)| (key.to_string(), config::Value::from(*value))).collect();
+        ObjectStoreSettings { url: url.to_string(), options }
+    }
+
+    #[test]
+    fn test_register() {
+        let ctx = SessionContext::new();
+        let minio = store(
+            "s3://lake",
+            &[("endpoint", "http://127.0.0.1:9000"), ("region", "us-east-1"), ("access_key_id", "minio"), ("secret_access_key", "secret"), ("allow_http", "true")],
+        );
+        register(&ctx, &[minio]).unwrap();
+        assert!(ctx.runtime_env().object_store(ObjectStoreUrl::parse("s3://lake").unwrap()).is_ok());
+        assert!(ctx.runtime_env().object_store(ObjectStoreUrl::parse("s3://other").unwrap()).is_err());
+
+        assert!(register(&ctx, &[store("s3://lake", &[("endpiont", "http://127.0.0.1:9000")])]).is_err());
+        assert!(register(&ctx, &[store("ftp://lake", &[])]).is_err());
+        assert!(register(&ctx, &[store("lake", &[])]).is_err());
+    }
+}
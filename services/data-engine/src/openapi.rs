@@ -0,0 +1,49 @@
+//! OpenAPI 文档
+//!
+//! `GET /openapi.json` 返回 HTTP 接口的 OpenAPI 规范。API 网关合并各服务的规范并加上 `/api/v1/data-engine` 前缀后统一提供文档
+
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Alpha Finance Data Engine", description = "SQL 查询、行情写入、表目录、降采样和后台任务"),
+    paths(
+        crate::health_check,
+        crate::execute_query,
+        crate::cancel_query,
+        crate::ingest,
+        crate::catalog::list_tables,
+        crate::catalog::table_schema,
+        crate::downsample::downsample,
+        crate::jobs::submit_job,
+        crate::jobs::job_status,
+        crate::jobs::job_result,
+        crate::jobs::delete_job,
+    )
+)]
+pub struct ApiDoc;
+
+/// 二进制文件或流
+#[derive(utoipa::ToSchema)]
+#[schema(value_type = String, format = Binary)]
+pub struct Binary(Vec<u8>);
+
+/// OpenAPI 规范
+pub async fn openapi() -> axum::Json<utoipa::openapi::OpenApi> {
+    axum::Json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi() {
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        assert!(spec["paths"]["/query"]["post"].is_object());
+        assert!(spec["paths"]["/jobs/{id}/result"]["get"].is_object());
+        for schema in ["QueryRequest", "QueryResponse", "QueryParam", "TableSchema", "DownsampleResponse", "Job"] {
+            assert!(spec["components"]["schemas"][schema].is_object(), "missing schema {}", schema);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! 查询参数
+//!
+//! `QueryRequest.params` 依次绑定 SQL 中的 `$1`、`$2` ... 占位符，参数值不拼接进 SQL 文本。
+//! 参数可以是 JSON 值 (`"AAPL"`、`100`、`1.5`、`true`、`null`)，也可以是带类型的对象
+//! `{"type": "timestamp", "value": "2024-03-01T00:00:00Z"}`。能从上下文推断出占位符类型时
+//! (如 `price > $1`)，参数转换为该类型；无法推断时 (如出现在 SELECT 列表中) 按参数自身的类型绑定
+
+use chrono::{DateTime, NaiveDate};
+use datafusion::arrow::datatypes::{DataType, TimeUnit};
+use datafusion::error::{DataFusionError, Result};
+use datafusion::prelude::DataFrame;
+use datafusion::scalar::ScalarValue;
+use serde::{Deserialize, Serialize};
+
+/// 参数类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamType {
+    Boolean,
+    Int64,
+    Uint64,
+    Float64,
+    #[serde(alias = "string")]
+    Utf8,
+    /// RFC 3339 时间或毫秒时间戳，按 UTC 毫秒绑定
+    Timestamp,
+    /// `YYYY-MM-DD`
+    Date,
+}
+
+impl ParamType {
+    fn data_type(self) -> DataType {
+        match self {
+            Self::Boolean => DataType::Boolean,
+            Self::Int64 => DataType::Int64,
+            Self::Uint64 => DataType::UInt64,
+            Self::Float64 => DataType::Float64,
+            Self::Utf8 => DataType::Utf8,
+            Self::Timestamp => DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+            Self::Date => DataType::Date32,
+        }
+    }
+}
+
+/// 一个查询参数
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(untagged)]
+pub enum QueryParam {
+    Typed {
+        #[serde(rename = "type")]
+        param_type: ParamType,
+        value: serde_json::Value,
+    },
+    Value(serde_json::Value),
+}
+
+impl QueryParam {
+    fn to_scalar(&self) -> std::result::Result<ScalarValue, String> {
+        use serde_json::Value;
+
+        let (param_type, value) = match self {
+            Self::Value(Value::Null) => return Ok(ScalarValue::Null),
+            Self::Value(Value::Bool(value)) => return Ok(ScalarValue::Boolean(Some(*value))),
+            Self::Value(Value::String(value)) => return Ok(ScalarValue::Utf8(Some(value.clone()))),
+            Self::Value(Value::Number(number)) => {
+                return Ok(match (number.as_i64(), number.as_u64(), number.as_f64()) {
+                    (Some(value), _, _) => ScalarValue::Int64(Some(value)),
+                    (None, Some(value), _) => ScalarValue::UInt64(Some(value)),
+                    (None, None, value) => ScalarValue::Float64(value),
+                });
+            }
+            Self::Value(value) => return Err(format!("Unsupported parameter {}", value)),
+            Self::Typed { param_type, value } => (*param_type, value),
+        };
+
+        if value.is_null() {
+            return ScalarValue::try_from(&param_type.data_type()).map_err(|e| e.to_string());
+        }
+        let invalid = || format!("Invalid {:?} parameter {}", param_type, value);
+        let scalar = match param_type {
+            ParamType::Boolean => ScalarValue::Boolean(Some(value.as_bool().ok_or_else(invalid)?)),
+            ParamType::Int64 => ScalarValue::Int64(Some(value.as_i64().ok_or_else(invalid)?)),
+            ParamType::Uint64 => ScalarValue::UInt64(Some(value.as_u64().ok_or_else(invalid)?)),
+            ParamType::Float64 => ScalarValue::Float64(Some(value.as_f64().ok_or_else(invalid)?)),
+            ParamType::Utf8 => ScalarValue::Utf8(Some(value.as_str().ok_or_else(invalid)?.to_string())),
+            ParamType::Timestamp => {
+                let millis = match value.as_str() {
+                    Some(text) => DateTime::parse_from_rfc3339(text).map_err(|_| invalid())?.timestamp_millis(),
+                    None => value.as_i64().ok_or_else(invalid)?,
+                };
+                ScalarValue::TimestampMillisecond(Some(millis), Some("UTC".into()))
+            }
+            ParamType::Date => {
+                let date = NaiveDate::parse_from_str(value.as_str().ok_or_else(invalid)?, "%Y-%m-%d").map_err(|_| invalid())?;
+                let days = date.signed_duration_since(NaiveDate::default()).num_days();
+                ScalarValue::Date32(Some(i32::try_from(days).map_err(|_| invalid())?))
+            }
+        };
+        Ok(scalar)
+    }
+}
+
+/// 绑定参数，参数个数必须与占位符一致
+pub fn bind(df: DataFrame, params: &[QueryParam]) -> Result<DataFrame> {
+    let types = df.logical_plan().get_parameter_types()?;
+    if params.len() != types.len() {
+        return Err(DataFusionError::Plan(format!("Expected {} parameters, got {}", types.len(), params.len())));
+    }
+    if params.is_empty() {
+        return Ok(df);
+    }
+
+    let values = params
+        .iter()
+        .enumerate()
+        .map(|(index, param)| {
+            let value = param
+                .to_scalar()
+                .map_err(|e| DataFusionError::Plan(format!("Parameter ${}: {}", index + 1, e)))?;
+            match types.get(&format!("${}", index + 1)) {
+                Some(Some(expected)) if value.is_null() => ScalarValue::try_from(expected),
+                Some(Some(expected)) if value.data_type() != *expected => value.cast_to(expected).map_err(|e| {
+                    DataFusionError::Plan(format!("Parameter ${} cannot be used as {}: {}", index + 1, expected, e))
+                }),
+                Some(_) => Ok(value),
+                None => Err(DataFusionError::Plan(format!("Parameters must be numbered $1 to ${}", params.len()))),
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    df.with_param_values(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::prelude::SessionContext;
+
+    fn params(json: &str) -> Vec<QueryParam> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_to_scalar() {
+        let values: Vec<_> = params(
+            r#"["AAPL", 100, 1.5, true, null,
+                {"type": "timestamp", "value": "2024-03-01T14:30:00+08:00"},
+                {"type": "date", "value": "2024-03-01"},
+                {"type": "float64", "value": null}]"#,
+        )
+        .iter()
+        .map(|param| param.to_scalar().unwrap())
+        .collect();
+        assert_eq!(
+            values,
+            vec![
+                ScalarValue::Utf8(Some("AAPL".to_string())),
+                ScalarValue::Int64(Some(100)),
+                ScalarValue::Float64(Some(1.5)),
+                ScalarValue::Boolean(Some(true)),
+                ScalarValue::Null,
+                ScalarValue::TimestampMillisecond(Some(1_709_274_600_000), Some("UTC".into())),
+                ScalarValue::Date32(Some(19_783)),
+                ScalarValue::Float64(None),
+            ]
+        );
+
+        assert!(params(r#"[{"type": "int64", "value": "100"}]"#)[0].to_scalar().is_err());
+        assert!(params(r#"[{"type": "timestamp", "value": "yesterday"}]"#)[0].to_scalar().is_err());
+        assert!(params(r#"[[1, 2]]"#)[0].to_scalar().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bind() {
+        let ctx = SessionContext::new();
+        let sql = "SELECT symbol FROM (VALUES ('AAPL', 180.5), ('MSFT', 410.0)) AS t(symbol, price) \
+                   WHERE symbol = $1 AND price > $2";
+        let count = |params: Vec<QueryParam>| {
+            let ctx = ctx.clone();
+            async move {
+                let df = bind(ctx.sql(sql).await?, &params)?;
+                Ok::<_, DataFusionError>(df.collect().await?.iter().map(|batch| batch.num_rows()).sum::<usize>())
+            }
+        };
+
+        // 整数参数按 price 的类型转换为浮点数
+        assert_eq!(count(params(r#"["AAPL", 100]"#)).await.unwrap(), 1);
+        // 参数值不会被当作 SQL 解析
+        assert_eq!(count(params(r#"["AAPL' OR '1'='1", 0]"#)).await.unwrap(), 0);
+        assert!(count(params(r#"["AAPL"]"#)).await.is_err());
+        assert!(count(params(r#"["AAPL", "cheap"]"#)).await.is_err());
+    }
+}
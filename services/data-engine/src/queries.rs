@@ -0,0 +1,153 @@
+//! 查询取消
+//!
+//! 每个 `POST /query` 分配一个查询 ID，在响应头 `X-Query-Id` 中返回；流式格式在开始输出结果前就返回响应头，
+//! 客户端可以随即 `DELETE /query/{id}` 取消。JSON 格式等到结果收集完才返回，需要取消时在请求中用 `query_id`
+//! 指定 ID (字母、数字和 `-_`，不超过 64 个字符，不能与执行中的查询重复)。
+//!
+//! 取消后丢弃查询的 future，DataFusion 随之停止执行：JSON 请求返回错误码 `query_cancelled`，
+//! 流式结果以错误结束
+
+use crate::limits::LimitError;
+use datafusion::error::DataFusionError;
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::SendableRecordBatchStream;
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// 响应头中的查询 ID
+pub const QUERY_ID_HEADER: &str = "x-query-id";
+
+/// 执行中的查询
+#[derive(Debug, Default)]
+pub struct Queries {
+    running: Mutex<HashMap<String, watch::Sender<bool>>>,
+}
+
+impl Queries {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记一个查询，未指定 ID 时生成
+    pub fn register(self: &Arc<Self>, id: Option<&str>) -> Result<QueryHandle, LimitError> {
+        let id = match id {
+            Some(id) if !is_valid_id(id) => return Err(LimitError::invalid_query_id(format!("Invalid query_id {:?}", id))),
+            Some(id) => id.to_string(),
+            None => uuid::Uuid::new_v4().to_string(),
+        };
+        let mut running = self.running.lock().unwrap();
+        if running.contains_key(&id) {
+            return Err(LimitError::invalid_query_id(format!("Query {} is already running", id)));
+        }
+        let (sender, cancelled) = watch::channel(false);
+        running.insert(id.clone(), sender);
+        Ok(QueryHandle { id, queries: self.clone(), cancelled })
+    }
+
+    /// 取消执行中的查询，查询不存在或已结束时返回 false
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.running.lock().unwrap().get(id) {
+            Some(sender) => {
+                sender.send_replace(true);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 64 && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+}
+
+/// 一个执行中的查询，释放时注销
+#[derive(Debug)]
+pub struct QueryHandle {
+    id: String,
+    queries: Arc<Queries>,
+    cancelled: watch::Receiver<bool>,
+}
+
+impl QueryHandle {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// 等待查询被取消，查询结束前一直等待
+    pub fn cancelled(&self) -> impl std::future::Future<Output = ()> + Send + 'static {
+        let mut cancelled = self.cancelled.clone();
+        async move {
+            // 查询结束后发送端随之释放，此时不再会被取消
+            if cancelled.wait_for(|cancelled| *cancelled).await.is_err() {
+                std::future::pending::<()>().await;
+            }
+        }
+    }
+}
+
+impl Drop for QueryHandle {
+    fn drop(&mut self) {
+        self.queries.running.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// 查询被取消后以错误结束结果流，结果流结束时注销查询
+pub fn cancellable(results: SendableRecordBatchStream, query: QueryHandle) -> SendableRecordBatchStream {
+    let schema = results.schema();
+    let results = futures::stream::unfold(Some((results, query)), |state| async move {
+        let (mut results, query) = state?;
+        tokio::select! {
+            batch = results.next() => batch.map(|batch| (batch, Some((results, query)))),
+            () = query.cancelled() => {
+                let message = LimitError::cancelled(query.id()).message;
+                Some((Err(DataFusionError::Execution(message)), None))
+            }
+        }
+    });
+    Box::pin(RecordBatchStreamAdapter::new(schema, results))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::LimitCode;
+    use datafusion::arrow::array::Int64Array;
+    use datafusion::arrow::record_batch::RecordBatch;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_cancel() {
+        let queries = Arc::new(Queries::new());
+        let query = queries.register(Some("report-1")).unwrap();
+        assert_eq!(queries.register(Some("report-1")).unwrap_err().code, LimitCode::InvalidQueryId);
+        assert_eq!(queries.register(Some("../etc")).unwrap_err().code, LimitCode::InvalidQueryId);
+        assert_eq!(queries.register(None).unwrap().id().len(), 36);
+
+        let cancelled = query.cancelled();
+        assert!(tokio::time::timeout(Duration::from_millis(10), query.cancelled()).await.is_err());
+        assert!(queries.cancel("report-1"));
+        tokio::time::timeout(Duration::from_secs(1), cancelled).await.unwrap();
+
+        // 结束后注销，ID 可以再次使用
+        drop(query);
+        assert!(!queries.cancel("report-1"));
+        assert!(queries.register(Some("report-1")).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_cancellable() {
+        let queries = Arc::new(Queries::new());
+        let query = queries.register(Some("stream")).unwrap();
+        let batch = RecordBatch::try_from_iter([("value", Arc::new(Int64Array::from(vec![1])) as _)]).unwrap();
+        let pending = futures::stream::iter(vec![Ok(batch.clone())]).chain(futures::stream::pending());
+        let mut results = cancellable(Box::pin(RecordBatchStreamAdapter::new(batch.schema(), pending)), query);
+
+        assert!(results.next().await.unwrap().is_ok());
+        queries.cancel("stream");
+        assert!(results.next().await.unwrap().is_err());
+        assert!(results.next().await.is_none());
+        assert!(!queries.cancel("stream"));
+    }
+}
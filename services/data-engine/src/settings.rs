@@ -0,0 +1,141 @@
+//! 服务配置
+//!
+//! 配置取自 `ENGINE_CONFIG` 指定的配置文件 (TOML/YAML/JSON) 和 `ENGINE_` 前缀的环境变量，环境变量优先。
+//! 对象存储只能在配置文件中声明 (见 [`crate::object_stores`])
+
+use crate::delta::DeltaTableSettings;
+use crate::live_feed::LiveFeedSettings;
+use crate::object_stores::ObjectStoreSettings;
+use crate::storage::DatasetSettings;
+use datafusion::error::{DataFusionError, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+const DEFAULT_HISTORICAL_PATH: &str = "/data/historical/stock_quotes.parquet";
+
+/// 数据引擎配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// 历史行情 Parquet，本地路径或已注册对象存储中的 URL (如 `s3://lake/historical/stock_quotes.parquet`)
+    pub historical_path: String,
+    pub object_stores: Vec<ObjectStoreSettings>,
+    /// 设置后 `stock_quotes` 存为该位置的 Delta 表，否则为本地的分区 Parquet 目录 (见 [`crate::delta`])
+    pub delta_quotes_url: Option<String>,
+    /// 额外注册的 Delta 表
+    pub delta_tables: Vec<DeltaTableSettings>,
+    /// 查询结果缓存的有效期 (秒)，0 表示不缓存
+    pub cache_ttl_secs: u64,
+    /// 内存中缓存结果的总大小上限
+    pub cache_memory_bytes: usize,
+    /// 超出内存上限的结果转存的目录，未设置时直接丢弃
+    pub cache_dir: Option<PathBuf>,
+    /// 磁盘上缓存结果的总大小上限
+    pub cache_disk_bytes: u64,
+    /// 单个查询的最长时间 (秒)，0 表示不限制
+    pub query_timeout_secs: u64,
+    /// 全部查询共享的内存上限，0 表示不限制
+    pub query_memory_bytes: usize,
+    /// JSON 结果每页的最大行数，0 表示不限制
+    pub max_page_rows: usize,
+    /// 各数据集的保留和合并策略，键为表名 (见 [`crate::storage`])
+    pub datasets: BTreeMap<String, DatasetSettings>,
+    /// 存储维护的间隔 (秒)，0 表示不维护
+    pub maintenance_interval_secs: u64,
+    /// 从 real-time-feed 的总线接入实时行情，未设置时不接入 (见 [`crate::live_feed`])
+    pub live_feed: Option<LiveFeedSettings>,
+    /// 异步任务的状态和结果文件所在目录 (见 [`crate::jobs`])
+    pub jobs_dir: PathBuf,
+    /// 同时执行的任务数上限，其余任务排队
+    pub max_running_jobs: usize,
+    /// 任务结束后保留结果的时间 (秒)
+    pub job_retention_secs: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            historical_path: DEFAULT_HISTORICAL_PATH.to_string(),
+            object_stores: Vec::new(),
+            delta_quotes_url: None,
+            delta_tables: Vec::new(),
+            cache_ttl_secs: 60,
+            cache_memory_bytes: 256 << 20,
+            cache_dir: None,
+            cache_disk_bytes: 1 << 30,
+            query_timeout_secs: 30,
+            query_memory_bytes: 4 << 30,
+            max_page_rows: 10_000,
+            datasets: BTreeMap::new(),
+            maintenance_interval_secs: 3600,
+            live_feed: None,
+            jobs_dir: PathBuf::from("/data/jobs"),
+            max_running_jobs: 4,
+            job_retention_secs: 86400,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Result<Self> {
+        let mut builder = config::Config::builder();
+        if let Ok(path) = std::env::var("ENGINE_CONFIG") {
+            builder = builder.add_source(config::File::with_name(&path));
+        }
+        Self::build(builder.add_source(config::Environment::with_prefix("ENGINE")))
+    }
+
+    fn build(builder: config::ConfigBuilder<config::builder::DefaultState>) -> Result<Self> {
+        builder
+            .build()
+            .and_then(|settings| settings.try_deserialize())
+            .map_err(|e| DataFusionError::Configuration(format!("Invalid configuration: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings() {
+        let toml = r#"
+            historical_path = "s3://lake/historical/stock_quotes.parquet"
+            cache_ttl_secs = "30"
+
+            [datasets.stock_quotes]
+            retention_days = 30
+
+            [live_feed]
+            redis_url = "redis://redis:6379"
+
+            [[delta_tables]]
+            name = "quotes_v1"
+            url = "s3://lake/delta/stock_quotes"
+            version = 1
+
+            [[object_stores]]
+            url = "s3://lake"
+            endpoint = "http://minio:9000"
+            allow_http = true
+        "#;
+        let builder = config::Config::builder().add_source(config::File::from_str(toml, config::FileFormat::Toml));
+        let settings = Settings::build(builder).unwrap();
+        assert_eq!(settings.historical_path, "s3://lake/historical/stock_quotes.parquet");
+        assert_eq!(settings.cache_ttl_secs, 30);
+        assert_eq!(settings.datasets["stock_quotes"], DatasetSettings { retention_days: Some(30), ..DatasetSettings::default() });
+        let live_feed = settings.live_feed.as_ref().unwrap();
+        assert_eq!((live_feed.channel.as_str(), live_feed.batch_size), ("alpha:feed:ticks", 500));
+        assert_eq!((settings.delta_tables[0].name.as_str(), settings.delta_tables[0].version), ("quotes_v1", Some(1)));
+        assert_eq!(settings.object_stores.len(), 1);
+        assert_eq!(settings.object_stores[0].url, "s3://lake");
+        assert_eq!(settings.object_stores[0].options().unwrap().get("allow_http").map(String::as_str), Some("true"));
+
+        let settings = Settings::build(config::Config::builder()).unwrap();
+        assert_eq!(settings.historical_path, DEFAULT_HISTORICAL_PATH);
+        assert!(settings.object_stores.is_empty());
+        assert!(settings.live_feed.is_none());
+        assert!(settings.delta_quotes_url.is_none());
+    }
+}
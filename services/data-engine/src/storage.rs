@@ -0,0 +1,229 @@
+//! 分区存储维护
+//!
+//! 写入按 `date=*/symbol=*` 分区，每次写入生成一个小文件。后台每隔 `maintenance_interval_secs` 对每个数据集：
+//! - 删除超过保留期的日期分区 (`retention_days`，未设置时永久保留)，先从表中移除再删除目录
+//! - 把分区中小于 `compaction_target_bytes` 的文件合并成一个按时间排序的文件，减少查询时打开的文件数
+//!
+//! 各数据集的策略在配置文件中按表名设置：
+//!
+//! ```toml
+//! [datasets.stock_quotes]
+//! retention_days = 30
+//! compaction_target_bytes = 67108864
+//! ```
+//!
+//! 合并时先写入新文件再删除旧文件，删除前开始的查询可能短暂读到重复的行或找不到旧文件
+
+use crate::ingest::{write_parquet, QuoteStore, Quotes};
+use arrow::compute::{concat_batches, sort_to_indices, take_record_batch};
+use chrono::{Duration as DateDuration, NaiveDate};
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::error::{DataFusionError, Result};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 一个数据集的存储策略
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct DatasetSettings {
+    /// 保留的天数，不含当天
+    pub retention_days: Option<u32>,
+    /// 小于该大小的文件参与合并，0 表示不合并
+    pub compaction_target_bytes: u64,
+}
+
+impl Default for DatasetSettings {
+    fn default() -> Self {
+        Self { retention_days: None, compaction_target_bytes: 64 << 20 }
+    }
+}
+
+/// 一次维护的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    pub expired_partitions: usize,
+    pub compacted_partitions: usize,
+    pub merged_files: usize,
+    /// Delta 表按保留期删除的行数
+    pub deleted_rows: usize,
+}
+
+/// 定期维护 `stock_quotes`，删除了过期数据时调用 `on_change`；Delta 表的维护见 [`crate::delta::DeltaQuotes::maintain`]
+pub fn spawn(quotes: Quotes, settings: DatasetSettings, interval: Duration, on_change: impl Fn() + Send + 'static) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let today = chrono::Utc::now().date_naive();
+            let report = match &quotes {
+                Quotes::Parquet(store) => maintain(store.clone(), &settings, today).await,
+                Quotes::Delta(table) => table.maintain(&settings, today).await,
+            };
+            match report {
+                Ok(report) => {
+                    if report.expired_partitions > 0 || report.deleted_rows > 0 {
+                        on_change();
+                    }
+                    if report != MaintenanceReport::default() {
+                        tracing::info!("stock_quotes maintenance: {:?}", report);
+                    }
+                }
+                Err(e) => tracing::error!("stock_quotes maintenance failed: {}", e),
+            }
+        }
+    });
+}
+
+/// 按策略删除过期分区并合并小文件
+pub async fn maintain(store: Arc<QuoteStore>, settings: &DatasetSettings, today: NaiveDate) -> Result<MaintenanceReport> {
+    let mut report = MaintenanceReport::default();
+    let mut partitions = store.partitions();
+
+    if let Some(days) = settings.retention_days {
+        let cutoff = today - DateDuration::days(i64::from(days));
+        let expired: BTreeSet<PathBuf> =
+            partitions.iter().filter(|partition| partition_date(partition).is_some_and(|date| date < cutoff)).cloned().collect();
+        if !expired.is_empty() {
+            let dropping = store.clone();
+            let dropped = expired.clone();
+            tokio::task::spawn_blocking(move || dropping.drop_partitions(&dropped))
+                .await
+                .map_err(|e| DataFusionError::External(Box::new(e)))??;
+            report.expired_partitions = expired.len();
+            partitions.retain(|partition| !expired.contains(partition));
+        }
+    }
+
+    if settings.compaction_target_bytes > 0 {
+        let root = store.root().to_path_buf();
+        let target = settings.compaction_target_bytes;
+        let merged = tokio::task::spawn_blocking(move || {
+            partitions
+                .iter()
+                .map(|partition| compact_partition(&root.join(partition), target))
+                .collect::<Result<Vec<_>>>()
+        })
+        .await
+        .map_err(|e| DataFusionError::External(Box::new(e)))??;
+        for files in merged.into_iter().filter(|files| *files > 0) {
+            report.compacted_partitions += 1;
+            report.merged_files += files;
+        }
+    }
+    Ok(report)
+}
+
+/// `date=2024-03-01/symbol=AAPL` 中的日期
+fn partition_date(partition: &Path) -> Option<NaiveDate> {
+    let date = partition.components().next()?.as_os_str().to_str()?.strip_prefix("date=")?;
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// 合并分区中的小文件，返回合并的文件数；少于两个小文件时不处理
+fn compact_partition(dir: &Path, target_bytes: u64) -> Result<usize> {
+    let mut small = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_some_and(|extension| extension == "parquet") && entry.metadata()?.len() < target_bytes {
+            small.push(path);
+        }
+    }
+    if small.len() < 2 {
+        return Ok(0);
+    }
+    small.sort();
+
+    let mut batches = Vec::new();
+    for path in &small {
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?.build()?;
+        for batch in reader {
+            batches.push(batch?);
+        }
+    }
+    let Some(schema) = batches.first().map(RecordBatch::schema) else {
+        return Ok(0);
+    };
+    let merged = concat_batches(&schema, &batches)?;
+    let merged = match merged.column_by_name("timestamp") {
+        Some(timestamp) => take_record_batch(&merged, &sort_to_indices(timestamp, None, None)?)?,
+        None => merged,
+    };
+
+    let name = format!("part-{}-compacted.parquet", chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default());
+    let temporary = dir.join(format!("{}.tmp", name));
+    let written = write_parquet(&temporary, &merged)
+        .and_then(|()| std::fs::rename(&temporary, dir.join(&name)).map_err(DataFusionError::from));
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&temporary);
+        return Err(e);
+    }
+    for path in &small {
+        std::fs::remove_file(path)?;
+    }
+    Ok(small.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::prelude::SessionContext;
+
+    fn rows(date: &str, symbol: &str, seconds: &[u32]) -> RecordBatch {
+        let rows: Vec<String> = seconds
+            .iter()
+            .map(|second| {
+                format!(r#"{{"symbol": "{}", "timestamp": "{}T14:30:{:02}Z", "price": 1.0, "volume": 1}}"#, symbol, date, second)
+            })
+            .collect();
+        crate::ingest::decode(None, format!(r#"{{"rows": [{}]}}"#, rows.join(",")).as_bytes()).unwrap()
+    }
+
+    fn files(dir: &Path) -> usize {
+        std::fs::read_dir(dir).unwrap().filter(|entry| entry.as_ref().unwrap().path().extension().unwrap() == "parquet").count()
+    }
+
+    #[test]
+    fn test_partition_date() {
+        assert_eq!(partition_date(Path::new("date=2024-03-01/symbol=AAPL")), NaiveDate::from_ymd_opt(2024, 3, 1));
+        assert_eq!(partition_date(Path::new("symbol=AAPL")), None);
+    }
+
+    #[tokio::test]
+    async fn test_maintain() {
+        let dir = std::env::temp_dir().join(format!("alpha-data-engine-storage-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let ctx = SessionContext::new();
+        let store = Arc::new(QuoteStore::open(ctx.clone(), &dir).unwrap());
+        store.append(rows("2024-01-01", "AAPL", &[0])).await.unwrap();
+        store.append(rows("2024-03-01", "AAPL", &[2, 3])).await.unwrap();
+        store.append(rows("2024-03-01", "AAPL", &[1])).await.unwrap();
+        store.append(rows("2024-03-01", "MSFT", &[0])).await.unwrap();
+
+        let settings = DatasetSettings { retention_days: Some(30), ..DatasetSettings::default() };
+        let today = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        let report = maintain(store.clone(), &settings, today).await.unwrap();
+        assert_eq!(report, MaintenanceReport { expired_partitions: 1, compacted_partitions: 1, merged_files: 2, deleted_rows: 0 });
+        assert!(!dir.join("date=2024-01-01").exists());
+        assert_eq!(files(&dir.join("date=2024-03-01/symbol=AAPL")), 1);
+        assert_eq!(files(&dir.join("date=2024-03-01/symbol=MSFT")), 1);
+
+        // 合并后的数据按时间排序，过期数据不再可查
+        let results = ctx.sql("SELECT CAST(timestamp AS BIGINT) AS ts FROM stock_quotes").await.unwrap().collect().await.unwrap();
+        assert_eq!(results.iter().map(RecordBatch::num_rows).sum::<usize>(), 4);
+        let partition = dir.join("date=2024-03-01/symbol=AAPL");
+        let merged = std::fs::read_dir(&partition).unwrap().next().unwrap().unwrap().path();
+        let batch = ParquetRecordBatchReaderBuilder::try_new(File::open(merged).unwrap()).unwrap().build().unwrap().next().unwrap().unwrap();
+        let timestamps = batch.column_by_name("timestamp").unwrap();
+        assert_eq!(sort_to_indices(timestamps, None, None).unwrap().values().to_vec(), vec![0, 1, 2]);
+
+        // 再次维护没有变化
+        assert_eq!(maintain(store, &settings, today).await.unwrap(), MaintenanceReport::default());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,230 @@
+//! 查询结果格式
+//!
+//! `POST /query` 默认收集全部结果后返回一个 JSON 对象。请求头 `Accept` 为 `application/x-ndjson`、
+//! `text/csv` 或 `application/vnd.apache.arrow.stream` 时改为边执行边返回：每个 RecordBatch 编码后立即发出，
+//! 内存占用与结果大小无关。执行中途出错时中断响应，客户端会看到未正常结束的 chunked 响应。
+//! 各格式都由 arrow 的编码器生成，支持全部 Arrow 类型
+
+use crate::ingest::ARROW_STREAM_CONTENT_TYPE;
+use arrow::csv::WriterBuilder as CsvWriterBuilder;
+use arrow::datatypes::SchemaRef;
+use arrow::error::ArrowError;
+use arrow::ipc::writer::StreamWriter;
+use arrow::json::writer::{JsonArray, LineDelimited};
+use arrow::json::WriterBuilder as JsonWriterBuilder;
+use axum::body::{Body, Bytes};
+use axum::response::{IntoResponse, Response};
+use datafusion::arrow::record_batch::RecordBatch;
+use datafusion::error::DataFusionError;
+use datafusion::physical_plan::SendableRecordBatchStream;
+use futures::{Stream, StreamExt, TryStreamExt};
+
+/// 换行分隔 JSON 的 Content-Type
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+pub const CSV_CONTENT_TYPE: &str = "text/csv";
+
+/// 查询结果的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultFormat {
+    /// 收集后一次返回
+    Json,
+    /// 每行一条记录
+    NdJson,
+    /// 首行为列名
+    Csv,
+    /// Arrow IPC 流
+    ArrowStream,
+}
+
+impl ResultFormat {
+    /// 按 Accept 头中第一个支持的类型选择格式，都不支持时为 JSON
+    pub fn from_accept(accept: Option<&str>) -> Self {
+        accept
+            .into_iter()
+            .flat_map(|value| value.split(','))
+            .map(|media_type| media_type.split(';').next().unwrap_or_default().trim())
+            .find_map(|media_type| match media_type {
+                "application/json" => Some(Self::Json),
+                NDJSON_CONTENT_TYPE => Some(Self::NdJson),
+                CSV_CONTENT_TYPE => Some(Self::Csv),
+                ARROW_STREAM_CONTENT_TYPE => Some(Self::ArrowStream),
+                _ => None,
+            })
+            .unwrap_or(Self::Json)
+    }
+}
+
+/// 以流式格式返回查询结果，`format` 为 JSON 时按 NDJSON 处理
+pub fn response(format: ResultFormat, results: SendableRecordBatchStream) -> Response {
+    let schema = results.schema();
+    let (content_type, body) = match format {
+        ResultFormat::ArrowStream => (ARROW_STREAM_CONTENT_TYPE, Body::from_stream(logged(arrow_stream(schema, results)))),
+        ResultFormat::Csv => (CSV_CONTENT_TYPE, Body::from_stream(logged(csv(results)))),
+        ResultFormat::Json | ResultFormat::NdJson => (NDJSON_CONTENT_TYPE, Body::from_stream(logged(ndjson(results)))),
+    };
+    ([(axum::http::header::CONTENT_TYPE, content_type)], body).into_response()
+}
+
+fn logged<S>(stream: S) -> impl Stream<Item = Result<Bytes, DataFusionError>>
+where
+    S: Stream<Item = Result<Bytes, DataFusionError>>,
+{
+    stream.inspect_err(|e| tracing::error!("Streaming query error: {}", e))
+}
+
+/// 收集后的结果转换为 JSON 数组，每行一个对象，NULL 值保留为 `null`
+pub fn json_rows(batches: &[RecordBatch]) -> Result<serde_json::Value, ArrowError> {
+    let mut writer = JsonWriterBuilder::new().with_explicit_nulls(true).build::<_, JsonArray>(Vec::new());
+    for batch in batches {
+        writer.write(batch)?;
+    }
+    writer.finish()?;
+    serde_json::from_slice(&writer.into_inner()).map_err(|e| ArrowError::JsonError(e.to_string()))
+}
+
+/// 每个 RecordBatch 编码为若干行 JSON
+fn ndjson<S>(batches: S) -> impl Stream<Item = Result<Bytes, DataFusionError>>
+where
+    S: Stream<Item = Result<RecordBatch, DataFusionError>>,
+{
+    batches.map(|batch| {
+        let mut writer = JsonWriterBuilder::new().build::<_, LineDelimited>(Vec::new());
+        writer.write(&batch?)?;
+        writer.finish()?;
+        Ok(Bytes::from(writer.into_inner()))
+    })
+}
+
+/// 第一个 RecordBatch 前写入列名，结果为空时不输出任何内容
+fn csv<S>(batches: S) -> impl Stream<Item = Result<Bytes, DataFusionError>>
+where
+    S: Stream<Item = Result<RecordBatch, DataFusionError>>,
+{
+    batches.enumerate().map(|(index, batch)| {
+        let mut writer = CsvWriterBuilder::new().with_header(index == 0).build(Vec::new());
+        writer.write(&batch?)?;
+        Ok(Bytes::from(writer.into_inner()))
+    })
+}
+
+/// 先发送 Schema，随后每个 RecordBatch 一条消息，最后是结束标记
+fn arrow_stream<S>(schema: SchemaRef, batches: S) -> impl Stream<Item = Result<Bytes, DataFusionError>>
+where
+    S: Stream<Item = Result<RecordBatch, DataFusionError>> + Unpin,
+{
+    let writer = StreamWriter::try_new(Vec::new(), &schema).map_err(DataFusionError::from);
+    futures::stream::try_unfold((Some(writer), batches), |(writer, mut batches)| async move {
+        let Some(writer) = writer else {
+            return Ok(None);
+        };
+        let mut writer = writer?;
+        match batches.try_next().await? {
+            Some(batch) => writer.write(&batch)?,
+            None => {
+                writer.finish()?;
+                let bytes = Bytes::from(std::mem::take(writer.get_mut()));
+                return Ok(Some((bytes, (None, batches))));
+            }
+        }
+        let bytes = Bytes::from(std::mem::take(writer.get_mut()));
+        Ok(Some((bytes, (Some(Ok(writer)), batches))))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Float64Array, Int64Array, StringArray, TimestampMillisecondArray};
+    use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+    use arrow::ipc::reader::StreamReader;
+    use std::sync::Arc;
+
+    fn batches() -> (SchemaRef, Vec<RecordBatch>) {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("symbol", DataType::Utf8, false),
+            Field::new("price", DataType::Float64, true),
+        ]));
+        let batch = |symbols: Vec<&str>, prices: Vec<Option<f64>>| {
+            RecordBatch::try_new(schema.clone(), vec![Arc::new(StringArray::from(symbols)), Arc::new(Float64Array::from(prices))])
+                .unwrap()
+        };
+        let batches = vec![batch(vec!["AAPL", "MSFT"], vec![Some(180.5), None]), batch(vec!["TSLA"], vec![Some(200.0)])];
+        (schema, batches)
+    }
+
+    #[test]
+    fn test_from_accept() {
+        assert_eq!(ResultFormat::from_accept(None), ResultFormat::Json);
+        assert_eq!(ResultFormat::from_accept(Some("*/*")), ResultFormat::Json);
+        assert_eq!(ResultFormat::from_accept(Some("text/html, application/x-ndjson;q=0.9")), ResultFormat::NdJson);
+        assert_eq!(ResultFormat::from_accept(Some(ARROW_STREAM_CONTENT_TYPE)), ResultFormat::ArrowStream);
+        assert_eq!(ResultFormat::from_accept(Some("text/csv; charset=utf-8")), ResultFormat::Csv);
+    }
+
+    #[tokio::test]
+    async fn test_ndjson() {
+        let (_, batches) = batches();
+        let chunks: Vec<Bytes> = ndjson(futures::stream::iter(batches.into_iter().map(Ok))).try_collect().await.unwrap();
+        assert_eq!(chunks.len(), 2);
+        let text: String = chunks.iter().map(|chunk| std::str::from_utf8(chunk).unwrap()).collect();
+        assert_eq!(
+            text,
+            "{\"symbol\":\"AAPL\",\"price\":180.5}\n{\"symbol\":\"MSFT\"}\n{\"symbol\":\"TSLA\",\"price\":200.0}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_csv() {
+        let (_, batches) = batches();
+        let chunks: Vec<Bytes> = csv(futures::stream::iter(batches.into_iter().map(Ok))).try_collect().await.unwrap();
+        let text: String = chunks.iter().map(|chunk| std::str::from_utf8(chunk).unwrap()).collect();
+        assert_eq!(text, "symbol,price\nAAPL,180.5\nMSFT,\nTSLA,200.0\n");
+    }
+
+    #[test]
+    fn test_json_rows() {
+        let (_, batches) = batches();
+        assert_eq!(
+            json_rows(&batches).unwrap(),
+            serde_json::json!([{"symbol": "AAPL", "price": 180.5}, {"symbol": "MSFT", "price": null}, {"symbol": "TSLA", "price": 200.0}])
+        );
+        assert_eq!(json_rows(&[]).unwrap(), serde_json::json!([]));
+
+        // 时间戳输出为 RFC 3339 字符串
+        let timestamps = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![
+                Field::new("timestamp", DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())), false),
+                Field::new("volume", DataType::Int64, false),
+            ])),
+            vec![
+                Arc::new(TimestampMillisecondArray::from(vec![1_709_303_400_000]).with_timezone("UTC")),
+                Arc::new(Int64Array::from(vec![100])),
+            ],
+        )
+        .unwrap();
+        assert_eq!(json_rows(&[timestamps]).unwrap(), serde_json::json!([{"timestamp": "2024-03-01T14:30:00Z", "volume": 100}]));
+    }
+
+    #[tokio::test]
+    async fn test_arrow_stream() {
+        let (schema, batches) = batches();
+        let chunks: Vec<Bytes> = arrow_stream(schema.clone(), futures::stream::iter(batches.clone().into_iter().map(Ok)))
+            .try_collect()
+            .await
+            .unwrap();
+        // 两个 RecordBatch 各一块，结束标记一块
+        assert_eq!(chunks.len(), 3);
+        let body: Vec<u8> = chunks.concat();
+        let reader = StreamReader::try_new(body.as_slice(), None).unwrap();
+        assert_eq!(reader.schema(), schema);
+        assert_eq!(reader.collect::<Result<Vec<_>, _>>().unwrap(), batches);
+
+        // 结果为空时也发送 Schema
+        let chunks: Vec<Bytes> = arrow_stream(schema.clone(), futures::stream::iter(Vec::new())).try_collect().await.unwrap();
+        let body: Vec<u8> = chunks.concat();
+        let reader = StreamReader::try_new(body.as_slice(), None).unwrap();
+        assert_eq!(reader.schema(), schema);
+        assert_eq!(reader.count(), 0);
+    }
+}
@@ -0,0 +1,180 @@
+//! 行情归档
+//!
+//! 配置 `archive = "on"` (环境变量 `FEED_ARCHIVE=on`) 后，实例把总线上的 ticker 行情攒批写入 data-engine 的 `stock_quotes` 表 (`POST /ingest`)，
+//! 实时行情随之成为可以查询的历史。攒满 `archive_batch_size` 条 (默认 500) 或每隔
+//! `archive_flush_secs` 秒 (默认 5) 写入一次；写入失败的行情留待下次重试，积压超过 [`MAX_PENDING`]
+//! 条时丢弃最早的。各实例收到的是同一行情流，多实例部署时只在一个实例上开启
+
+use crate::bus::MessageBus;
+use crate::history::DataEngineClient;
+use crate::settings::{positive, Settings};
+use crate::topic::FeedMessage;
+use alpha_core::utils::buffer::BatchBuffer;
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::MissedTickBehavior;
+
+/// data-engine 不可用期间最多积压的归档行数
+pub const MAX_PENDING: usize = 100_000;
+
+const DEFAULT_BATCH_SIZE: usize = 500;
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+pub struct ArchiveConfig {
+    pub batch_size: usize,
+    #[serde(rename = "flush_interval_secs", serialize_with = "as_secs")]
+    pub flush_interval: Duration,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self { batch_size: DEFAULT_BATCH_SIZE, flush_interval: DEFAULT_FLUSH_INTERVAL }
+    }
+}
+
+impl ArchiveConfig {
+    /// 未开启归档时返回 None
+    pub fn from_settings(settings: &Settings) -> anyhow::Result<Option<Self>> {
+        Self::from_vars(
+            settings.archive.as_deref(),
+            settings.archive_batch_size.as_deref(),
+            settings.archive_flush_secs.as_deref(),
+        )
+    }
+
+    fn from_vars(enabled: Option<&str>, batch_size: Option<&str>, flush_secs: Option<&str>) -> anyhow::Result<Option<Self>> {
+        match enabled.map(|value| value.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("" | "off" | "false" | "0") => return Ok(None),
+            Some("on" | "true" | "1") => {}
+            Some(value) => return Err(anyhow!("Unknown archive setting: {}", value)),
+        }
+        Ok(Some(Self {
+            batch_size: positive(batch_size, "archive_batch_size", DEFAULT_BATCH_SIZE)?,
+            flush_interval: Duration::from_secs(positive(flush_secs, "archive_flush_secs", DEFAULT_FLUSH_INTERVAL.as_secs())?),
+        }))
+    }
+}
+
+fn as_secs<S: serde::Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_secs())
+}
+
+/// 写入 `stock_quotes` 的一行
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ArchiveRow {
+    pub symbol: String,
+    pub timestamp: DateTime<Utc>,
+    pub price: f64,
+    pub volume: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct IngestRequest<'a> {
+    rows: &'a [ArchiveRow],
+}
+
+#[derive(Debug, Deserialize)]
+struct IngestResponse {
+    row_count: usize,
+}
+
+/// 归档统计
+#[derive(Debug, Default)]
+pub struct ArchiveStats {
+    archived_rows: AtomicU64,
+    failed_flushes: AtomicU64,
+    dropped_rows: AtomicU64,
+}
+
+impl ArchiveStats {
+    pub fn to_json(&self, config: ArchiveConfig) -> serde_json::Value {
+        serde_json::json!({
+            "config": config,
+            "archived_rows": self.archived_rows.load(Ordering::Relaxed),
+            "failed_flushes": self.failed_flushes.load(Ordering::Relaxed),
+            "dropped_rows": self.dropped_rows.load(Ordering::Relaxed),
+        })
+    }
+}
+
+/// 在后台归档总线上的 ticker 行情
+pub fn spawn(bus: Arc<dyn MessageBus>, client: DataEngineClient, config: ArchiveConfig, stats: Arc<ArchiveStats>) {
+    let mut receiver = bus.subscribe();
+    tokio::spawn(async move {
+        let mut pending = BatchBuffer::new(MAX_PENDING);
+        let mut flush = tokio::time::interval(config.flush_interval);
+        flush.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // 上次写入失败后只在定时器触发时重试，不因攒满而反复请求
+        let mut failing = false;
+        loop {
+            tokio::select! {
+                message = receiver.recv() => match message {
+                    Ok(FeedMessage::Ticker(data)) => {
+                        let row = ArchiveRow { symbol: data.symbol, timestamp: data.timestamp, price: data.price, volume: data.volume };
+                        let dropped = pending.push(row);
+                        stats.dropped_rows.fetch_add(dropped as u64, Ordering::Relaxed);
+                        if failing || pending.len() < config.batch_size {
+                            continue;
+                        }
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Archiver lagged, {} messages will not be archived", skipped);
+                        stats.dropped_rows.fetch_add(skipped, Ordering::Relaxed);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                _ = flush.tick() => {}
+            }
+            failing = !write_pending(&client, config, &mut pending, &stats).await;
+        }
+    });
+}
+
+/// 按批推送到 `POST /ingest`，请求失败时记入 `failed_flushes` 并返回 false
+async fn write_pending(client: &DataEngineClient, config: ArchiveConfig, pending: &mut BatchBuffer<ArchiveRow>, stats: &ArchiveStats) -> bool {
+    while !pending.is_empty() {
+        let rows = pending.take(config.batch_size);
+        match client.post::<_, IngestResponse>("/ingest", &IngestRequest { rows: &rows }).await {
+            Ok(response) => {
+                tracing::debug!("Archived {} rows", response.row_count);
+                stats.archived_rows.fetch_add(rows.len() as u64, Ordering::Relaxed);
+            }
+            Err(e) => {
+                tracing::warn!("Failed to archive {} rows, {} pending: {:#}", rows.len(), pending.len() + rows.len(), e);
+                stats.failed_flushes.fetch_add(1, Ordering::Relaxed);
+                let dropped = pending.restore(rows);
+                stats.dropped_rows.fetch_add(dropped as u64, Ordering::Relaxed);
+                return false;
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_vars() {
+        assert_eq!(ArchiveConfig::from_vars(None, Some("10"), None).unwrap(), None);
+        assert_eq!(ArchiveConfig::from_vars(Some("off"), None, None).unwrap(), None);
+        assert_eq!(ArchiveConfig::from_vars(Some("on"), None, None).unwrap(), Some(ArchiveConfig::default()));
+
+        let config = ArchiveConfig::from_vars(Some(" TRUE "), Some("100"), Some("2")).unwrap().unwrap();
+        assert_eq!((config.batch_size, config.flush_interval), (100, Duration::from_secs(2)));
+        assert_eq!(serde_json::to_value(config).unwrap(), serde_json::json!({"batch_size": 100, "flush_interval_secs": 2}));
+
+        assert!(ArchiveConfig::from_vars(Some("yes please"), None, None).is_err());
+        assert!(ArchiveConfig::from_vars(Some("on"), Some("0"), None).is_err());
+        assert!(ArchiveConfig::from_vars(Some("on"), None, Some("abc")).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+//! 连接认证
+//!
+//! 客户端在 `/ws` 升级请求的 `token` 查询参数或 `Authorization: Bearer` 头中携带凭证，
+//! 也可以在连接后 [`AUTH_TIMEOUT`] 内发送 `{"type": "auth", "token": "..."}` 作为第一条消息。
+//! 凭证为 API Key 或 HS256 JWT，认证结果决定连接可订阅的代码；失败时以 44xx 关闭码断开连接。
+//!
+//! API Key 列表从 `FEED_API_KEYS` 指向的 JSON 文件读取，只保存 Key 的 SHA-256 摘要 (其他摘要算法在加载时拒绝)：
+//! `[{"name": "desktop", "key": "sha256:…", "symbols": ["AAPL", "MSFT"]}]`，`symbols` 缺省或含 `*` 时不限代码。
+//! JWT 密钥取 `FEED_JWT_SECRET`，可订阅的代码取 `symbols` 声明。两者都未配置时不做认证
+
+use alpha_core::utils::checksum::{Checksum, ChecksumAlgorithm};
+use alpha_core::utils::symbol::Symbol;
+use anyhow::Context;
+use chrono::{DateTime, TimeZone, Utc};
+use jsonwebtoken::{errors::ErrorKind, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::time::Duration;
+
+/// 未在升级请求中携带凭证时，等待认证消息的时间
+pub const AUTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 可订阅的代码
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Entitlements {
+    #[default]
+    All,
+    Symbols(BTreeSet<String>),
+}
+
+impl Entitlements {
+    /// `*` 表示全部代码，其余按大写代码精确匹配
+    pub fn from_symbols(symbols: &[String]) -> Self {
+        if symbols.iter().any(|symbol| symbol.trim() == "*") {
+            return Self::All;
+        }
+        // 与订阅时的代码一样统一为规范写法，无法解析的代码只转为大写
+        Self::Symbols(symbols.iter()
+            .map(|symbol| Symbol::parse(symbol).map_or_else(|_| symbol.trim().to_ascii_uppercase(), |symbol| symbol.canonical()))
+            .collect())
+    }
+
+    pub fn allows(&self, symbol: &str) -> bool {
+        match self {
+            Self::All => true,
+            Self::Symbols(symbols) => symbols.contains(symbol),
+        }
+    }
+}
+
+/// 认证通过的客户端
+#[derive(Debug, Clone, PartialEq)]
+pub struct Principal {
+    /// API Key 名称或 JWT 的 `sub`
+    pub name: String,
+    pub entitlements: Entitlements,
+    /// JWT 过期时间，到期后断开连接
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl Principal {
+    /// 未启用认证时的客户端
+    pub fn anonymous() -> Self {
+        Self { name: "anonymous".to_string(), entitlements: Entitlements::All, expires_at: None }
+    }
+}
+
+/// 认证失败的原因，决定关闭码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthFailure {
+    Missing,
+    Invalid,
+    Expired,
+    Timeout,
+}
+
+impl AuthFailure {
+    /// WebSocket 关闭码 (4000-4999 为应用自定义)
+    pub fn close_code(self) -> u16 {
+        match self {
+            Self::Missing | Self::Invalid | Self::Expired => 4401,
+            Self::Timeout => 4408,
+        }
+    }
+
+    /// 关闭原因，供客户端区分处理
+    pub fn reason(self) -> &'static str {
+        match self {
+            Self::Missing => "missing_credentials",
+            Self::Invalid => "invalid_credentials",
+            Self::Expired => "token_expired",
+            Self::Timeout => "auth_timeout",
+        }
+    }
+}
+
+/// 连接后发送的认证消息
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuthMessage {
+    Auth { token: String },
+}
+
+/// 配置文件中的一个 API Key
+#[derive(Debug, Clone, Deserialize)]
+struct ApiKey {
+    name: String,
+    /// Key 的摘要，如 `sha256:…`
+    key: Checksum,
+    #[serde(default)]
+    symbols: Option<Vec<String>>,
+}
+
+/// JWT 声明
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub exp: i64,
+    /// 可订阅的代码，缺省时不限
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbols: Option<Vec<String>>,
+}
+
+pub struct Authenticator {
+    api_keys: Vec<ApiKey>,
+    jwt_key: Option<DecodingKey>,
+}
+
+// 不输出密钥
+impl fmt::Debug for Authenticator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Authenticator")
+            .field("api_keys", &self.api_keys.len())
+            .field("jwt", &self.jwt_key.is_some())
+            .finish()
+    }
+}
+
+impl Authenticator {
+    /// `api_keys` 为 API Key 配置文件内容
+    pub fn new(api_keys: Option<&str>, jwt_secret: Option<&[u8]>) -> anyhow::Result<Self> {
+        let api_keys: Vec<ApiKey> = match api_keys {
+            Some(content) => serde_json::from_str(content).context("Malformed API key file")?,
+            None => Vec::new(),
+        };
+        // xxh3 等非密码学摘要不能用来保存密钥
+        if let Some(api_key) = api_keys.iter().find(|api_key| api_key.key.algorithm != ChecksumAlgorithm::Sha256) {
+            anyhow::bail!("API key {} must be stored as a sha256 digest, got {}", api_key.name, api_key.key.algorithm.name());
+        }
+        Ok(Self {
+            api_keys,
+            jwt_key: jwt_secret.filter(|secret| !secret.is_empty()).map(DecodingKey::from_secret),
+        })
+    }
+
+    /// 从 `FEED_API_KEYS` 和 `FEED_JWT_SECRET` 读取配置
+    pub fn from_env() -> anyhow::Result<Self> {
+        let api_keys = match std::env::var("FEED_API_KEYS") {
+            Ok(path) => Some(std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path))?),
+            Err(_) => None,
+        };
+        let jwt_secret = std::env::var("FEED_JWT_SECRET").ok();
+        Self::new(api_keys.as_deref(), jwt_secret.as_deref().map(str::as_bytes))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.api_keys.is_empty() || self.jwt_key.is_some()
+    }
+
+    /// 校验凭证：三段式的按 JWT 校验，其余按 API Key 校验
+    pub fn authenticate(&self, token: &str) -> Result<Principal, AuthFailure> {
+        let token = token.trim();
+        if token.is_empty() {
+            return Err(AuthFailure::Missing);
+        }
+
+        if let (Some(key), 2) = (&self.jwt_key, token.matches('.').count()) {
+            let claims = jsonwebtoken::decode::<Claims>(token, key, &Validation::new(Algorithm::HS256))
+                .map_err(|e| match e.kind() {
+                    ErrorKind::ExpiredSignature => AuthFailure::Expired,
+                    _ => AuthFailure::Invalid,
+                })?
+                .claims;
+            return Ok(Principal {
+                name: claims.sub,
+                entitlements: claims.symbols.as_deref().map_or(Entitlements::All, Entitlements::from_symbols),
+                expires_at: Utc.timestamp_opt(claims.exp, 0).single(),
+            });
+        }
+
+        self.api_keys.iter()
+            .find(|api_key| api_key.key.verify_bytes(token.as_bytes()).is_ok())
+            .map(|api_key| Principal {
+                name: api_key.name.clone(),
+                entitlements: api_key.symbols.as_deref().map_or(Entitlements::All, Entitlements::from_symbols),
+                expires_at: None,
+            })
+            .ok_or(AuthFailure::Invalid)
+    }
+}
+
+/// 从 `Authorization: Bearer <token>` 中取出凭证
+pub fn bearer_token(header: &str) -> Option<&str> {
+    let (scheme, token) = header.trim().split_once(' ')?;
+    scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{EncodingKey, Header};
+
+    const SECRET: &[u8] = b"test-secret";
+
+    fn authenticator() -> Authenticator {
+        let key = |raw: &str| Checksum::of_bytes(ChecksumAlgorithm::Sha256, raw.as_bytes()).to_string();
+        let keys = serde_json::json!([
+            { "name": "desktop", "key": key("desktop-key") },
+            { "name": "partner", "key": key("partner-key"), "symbols": ["aapl", "MSFT"] },
+        ]);
+        Authenticator::new(Some(&keys.to_string()), Some(SECRET)).unwrap()
+    }
+
+    fn jwt(exp: i64, symbols: Option<Vec<String>>) -> String {
+        let claims = Claims { sub: "web".to_string(), exp, symbols };
+        jsonwebtoken::encode(&Header::default(), &claims, &EncodingKey::from_secret(SECRET)).unwrap()
+    }
+
+    #[test]
+    fn test_api_keys() {
+        let auth = authenticator();
+        assert!(auth.is_enabled());
+        assert_eq!(auth.authenticate("desktop-key").unwrap().entitlements, Entitlements::All);
+
+        let partner = auth.authenticate(" partner-key ").unwrap();
+        assert_eq!(partner.name, "partner");
+        assert!(partner.entitlements.allows("AAPL"));
+        assert!(!partner.entitlements.allows("TSLA"));
+
+        assert_eq!(auth.authenticate("wrong-key"), Err(AuthFailure::Invalid));
+        assert_eq!(auth.authenticate(""), Err(AuthFailure::Missing));
+        assert!(!Authenticator::new(None, None).unwrap().is_enabled());
+        assert!(Authenticator::new(Some("{"), None).is_err());
+    }
+
+    #[test]
+    fn test_rejects_non_sha256_keys() {
+        let key = Checksum::of_bytes(ChecksumAlgorithm::Xxh3, b"desktop-key").to_string();
+        let keys = serde_json::json!([{ "name": "desktop", "key": key }]);
+        let err = Authenticator::new(Some(&keys.to_string()), None).unwrap_err();
+        assert!(err.to_string().contains("sha256"), "{}", err);
+    }
+
+    #[test]
+    fn test_jwt() {
+        let auth = authenticator();
+        let exp = Utc::now().timestamp() + 3600;
+        let principal = auth.authenticate(&jwt(exp, Some(vec!["tsla".to_string()]))).unwrap();
+        assert_eq!(principal.name, "web");
+        assert_eq!(principal.expires_at.map(|t| t.timestamp()), Some(exp));
+        assert!(principal.entitlements.allows("TSLA"));
+        assert!(!principal.entitlements.allows("AAPL"));
+
+        let expired = jwt(Utc::now().timestamp() - 3600, None);
+        assert_eq!(auth.authenticate(&expired), Err(AuthFailure::Expired));
+        let forged = jsonwebtoken::encode(
+            &Header::default(),
+            &Claims { sub: "web".to_string(), exp, symbols: None },
+            &EncodingKey::from_secret(b"other"),
+        ).unwrap();
+        assert_eq!(auth.authenticate(&forged), Err(AuthFailure::Invalid));
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        assert_eq!(bearer_token("Bearer abc"), Some("abc"));
+        assert_eq!(bearer_token("bearer  abc "), Some("abc"));
+        assert_eq!(bearer_token("Basic abc"), None);
+        assert_eq!(bearer_token("abc"), None);
+    }
+}
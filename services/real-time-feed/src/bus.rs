@@ -0,0 +1,294 @@
+//! 消息总线
+//!
+//! 各主题的消息 (见 [`crate::topic`]) 经总线发布，每个实例从总线接收后推送给本实例的连接。单实例使用进程内广播；
+//! 配置 `redis_url` (环境变量 `FEED_REDIS_URL`) 后改用 Redis pub/sub，网关后的多个实例共享同一行情流。
+//!
+//! 客户端连接时可带 `session` 参数，订阅集合按会话保存在总线中 ([`SESSION_TTL`] 内有效)，
+//! 重连到任一实例后恢复
+
+use crate::settings::Settings;
+use crate::topic::FeedMessage;
+use crate::RealTimeData;
+use alpha_core::utils::retry::RetryPolicy;
+use anyhow::Context;
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// 每个实例本地广播通道的默认容量
+const DEFAULT_LOCAL_CAPACITY: usize = 1000;
+/// 默认的 Redis 频道
+const DEFAULT_CHANNEL: &str = "alpha:feed:ticks";
+/// 会话订阅的保存时间
+pub const SESSION_TTL: Duration = Duration::from_secs(24 * 3600);
+/// Redis 断线后重连的最长等待
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+#[async_trait]
+pub trait MessageBus: Send + Sync + fmt::Debug {
+    /// 后端名称，用于统计
+    fn name(&self) -> &'static str;
+
+    /// 发布消息，所有实例都会收到
+    async fn publish(&self, message: &FeedMessage) -> anyhow::Result<()>;
+
+    /// 接收本实例收到的消息
+    fn subscribe(&self) -> broadcast::Receiver<FeedMessage>;
+
+    /// 保存会话的订阅集合，集合为空时删除会话
+    async fn save_session(&self, key: &str, symbols: &[String]) -> anyhow::Result<()>;
+
+    /// 读取会话的订阅集合，会话不存在或已过期时返回空集合
+    async fn load_session(&self, key: &str) -> anyhow::Result<Vec<String>>;
+}
+
+/// 按配置选择后端：设置了 `redis_url` 时使用 Redis (频道取 `redis_channel`)，否则使用进程内广播；
+/// 两者的本地广播容量都取 `bus_capacity`
+pub async fn from_settings(settings: &Settings) -> anyhow::Result<Box<dyn MessageBus>> {
+    let capacity = crate::settings::positive(settings.bus_capacity.as_deref(), "bus_capacity", DEFAULT_LOCAL_CAPACITY)?;
+    match &settings.redis_url {
+        Some(url) => {
+            let channel = settings.redis_channel.as_deref().unwrap_or(DEFAULT_CHANNEL);
+            Ok(Box::new(RedisBus::connect(url, channel, capacity).await?))
+        }
+        None => Ok(Box::new(LocalBus::new(capacity))),
+    }
+}
+
+/// 会话键，会话 ID 只允许字母数字和 `-_`，不合法时返回 None
+pub fn session_key(principal: &str, session: &str) -> Option<String> {
+    let valid = !session.is_empty()
+        && session.len() <= 64
+        && session.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+    valid.then(|| format!("{}:{}", principal, session))
+}
+
+/// 进程内广播，只在单实例内有效
+#[derive(Debug)]
+pub struct LocalBus {
+    sender: broadcast::Sender<FeedMessage>,
+    sessions: Mutex<HashMap<String, (Vec<String>, Instant)>>,
+}
+
+impl LocalBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender, sessions: Mutex::new(HashMap::new()) }
+    }
+}
+
+#[async_trait]
+impl MessageBus for LocalBus {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn publish(&self, message: &FeedMessage) -> anyhow::Result<()> {
+        // 没有连接时发送失败，不算错误
+        let _ = self.sender.send(message.clone());
+        Ok(())
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<FeedMessage> {
+        self.sender.subscribe()
+    }
+
+    async fn save_session(&self, key: &str, symbols: &[String]) -> anyhow::Result<()> {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        sessions.retain(|_, (_, saved_at)| now.duration_since(*saved_at) < SESSION_TTL);
+        if symbols.is_empty() {
+            sessions.remove(key);
+        } else {
+            sessions.insert(key.to_string(), (symbols.to_vec(), now));
+        }
+        Ok(())
+    }
+
+    async fn load_session(&self, key: &str) -> anyhow::Result<Vec<String>> {
+        let sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(sessions.get(key)
+            .filter(|(_, saved_at)| saved_at.elapsed() < SESSION_TTL)
+            .map(|(symbols, _)| symbols.clone())
+            .unwrap_or_default())
+    }
+}
+
+/// Redis pub/sub，消息以 JSON 发布到同一频道，会话订阅保存为带过期时间的键
+pub struct RedisBus {
+    connection: redis::aio::ConnectionManager,
+    channel: String,
+    sender: broadcast::Sender<FeedMessage>,
+}
+
+// 不输出连接信息 (可能含密码)
+impl fmt::Debug for RedisBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedisBus").field("channel", &self.channel).finish()
+    }
+}
+
+impl RedisBus {
+    /// 连接 Redis 并在后台订阅频道，断线后自动重连
+    pub async fn connect(url: &str, channel: &str, capacity: usize) -> anyhow::Result<Self> {
+        let client = redis::Client::open(url).context("Invalid Redis URL")?;
+        let connection = redis::aio::ConnectionManager::new(client.clone())
+            .await
+            .context("Failed to connect to Redis")?;
+        let (sender, _) = broadcast::channel(capacity);
+
+        tokio::spawn(relay(client, channel.to_string(), sender.clone()));
+        tracing::info!("Using Redis message bus on channel {}", channel);
+        Ok(Self { connection, channel: channel.to_string(), sender })
+    }
+
+    fn session_key(key: &str) -> String {
+        format!("alpha:feed:session:{}", key)
+    }
+}
+
+/// 把频道中的消息转发到本地广播，连接断开时按指数退避重连
+async fn relay(client: redis::Client, channel: String, sender: broadcast::Sender<FeedMessage>) {
+    let mut backoff = RetryPolicy::reconnect(MAX_RECONNECT_DELAY).backoffs();
+    loop {
+        match subscribe_channel(&client, &channel, &sender).await {
+            Ok(()) => {
+                tracing::warn!("Redis subscription to {} ended, reconnecting", channel);
+                backoff.reset();
+            }
+            Err(e) => tracing::warn!("Redis subscription to {} failed: {:#}", channel, e),
+        }
+        tokio::time::sleep(backoff.next_delay()).await;
+    }
+}
+
+async fn subscribe_channel(
+    client: &redis::Client,
+    channel: &str,
+    sender: &broadcast::Sender<FeedMessage>,
+) -> anyhow::Result<()> {
+    let mut pubsub = client.get_async_connection().await?.into_pubsub();
+    pubsub.subscribe(channel).await?;
+    tracing::info!("Subscribed to Redis channel {}", channel);
+
+    let mut messages = pubsub.on_message();
+    while let Some(message) = messages.next().await {
+        let payload: String = match message.get_payload() {
+            Ok(payload) => payload,
+            Err(e) => {
+                tracing::debug!("Ignoring non-text Redis message: {}", e);
+                continue;
+            }
+        };
+        match parse_payload(&payload) {
+            Ok(message) => {
+                let _ = sender.send(message);
+            }
+            Err(e) => tracing::debug!("Ignoring malformed Redis message: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// 解析频道中的消息，没有 `topic` 字段的旧版本消息视为 ticker
+fn parse_payload(payload: &str) -> serde_json::Result<FeedMessage> {
+    serde_json::from_str::<FeedMessage>(payload)
+        .or_else(|e| serde_json::from_str::<RealTimeData>(payload).map(FeedMessage::Ticker).map_err(|_| e))
+}
+
+#[async_trait]
+impl MessageBus for RedisBus {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn publish(&self, message: &FeedMessage) -> anyhow::Result<()> {
+        let json = serde_json::to_string(message)?;
+        redis::cmd("PUBLISH")
+            .arg(&self.channel)
+            .arg(json)
+            .query_async::<_, ()>(&mut self.connection.clone())
+            .await?;
+        Ok(())
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<FeedMessage> {
+        self.sender.subscribe()
+    }
+
+    async fn save_session(&self, key: &str, symbols: &[String]) -> anyhow::Result<()> {
+        let key = Self::session_key(key);
+        let mut connection = self.connection.clone();
+        if symbols.is_empty() {
+            redis::cmd("DEL").arg(key).query_async::<_, ()>(&mut connection).await?;
+        } else {
+            redis::cmd("SET")
+                .arg(key)
+                .arg(serde_json::to_string(symbols)?)
+                .arg("EX")
+                .arg(SESSION_TTL.as_secs())
+                .query_async::<_, ()>(&mut connection)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn load_session(&self, key: &str) -> anyhow::Result<Vec<String>> {
+        let saved: Option<String> = redis::cmd("GET")
+            .arg(Self::session_key(key))
+            .query_async(&mut self.connection.clone())
+            .await?;
+        match saved {
+            Some(json) => Ok(serde_json::from_str(&json).context("Malformed session in Redis")?),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_bus() {
+        let bus = LocalBus::new(16);
+        let mut receiver = bus.subscribe();
+        let data = RealTimeData {
+            symbol: "AAPL".to_string(),
+            seq: 1,
+            price: 150.0,
+            volume: 100,
+            change: 0.5,
+            change_percent: 0.3,
+            bid: None,
+            ask: None,
+            timestamp: chrono::Utc::now(),
+        };
+        bus.publish(&FeedMessage::Ticker(data.clone())).await.unwrap();
+        assert_eq!(receiver.recv().await.unwrap().symbol(), "AAPL");
+
+        // 旧版本实例发布的消息没有 topic 字段
+        let legacy = parse_payload(&serde_json::to_string(&data).unwrap()).unwrap();
+        assert!(matches!(legacy, FeedMessage::Ticker(tick) if tick.seq == 1));
+        assert!(parse_payload(r#"{"topic":"ticker"}"#).is_err());
+
+        let symbols = vec!["AAPL".to_string(), "MSFT".to_string()];
+        bus.save_session("desktop:abc", &symbols).await.unwrap();
+        assert_eq!(bus.load_session("desktop:abc").await.unwrap(), symbols);
+        assert!(bus.load_session("web:abc").await.unwrap().is_empty());
+        bus.save_session("desktop:abc", &[]).await.unwrap();
+        assert!(bus.load_session("desktop:abc").await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_session_key() {
+        assert_eq!(session_key("desktop", "tab-1_a"), Some("desktop:tab-1_a".to_string()));
+        assert_eq!(session_key("desktop", ""), None);
+        assert_eq!(session_key("desktop", "a:b"), None);
+        assert_eq!(session_key("desktop", &"a".repeat(65)), None);
+    }
+}
@@ -0,0 +1,188 @@
+//! 推送编码
+//!
+//! 默认以 JSON 文本帧推送。客户端可以在升级请求的 `Sec-WebSocket-Protocol` 中声明
+//! [`MSGPACK_PROTOCOL`]，或在连接后发送 `{"type": "format", "format": "msgpack"}`，改为 MessagePack 二进制帧
+//! (字段按名称编码，结构与 JSON 相同)。客户端发给服务端的认证和订阅消息始终是 JSON 文本帧
+//!
+//! 格式消息还可以开启：
+//! - `"compression": "deflate"`：每帧编码后再做 raw deflate 压缩，以二进制帧发送
+//! - `"batch": true`：积压的多条行情合并为一帧 `{"type": "batch", "ticks": [...]}`，没有积压时仍逐条发送
+//! - `"max_rate": n`：每个代码每秒最多推送 n 次 (上限 [`MAX_RATE`])，间隔内只保留最新一条；0 表示不限。
+//!   客户端处理不及导致积压时，服务端自动限制为 [`SLOW_CLIENT_RATE`] 并以 format 消息告知
+
+use axum::extract::ws::Message;
+use flate2::write::DeflateEncoder;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::time::Duration;
+
+/// JSON 文本帧子协议
+pub const JSON_PROTOCOL: &str = "alpha.json.v1";
+/// MessagePack 二进制帧子协议
+pub const MSGPACK_PROTOCOL: &str = "alpha.msgpack.v1";
+/// 服务端支持的子协议，按优先顺序
+pub const PROTOCOLS: [&str; 2] = [MSGPACK_PROTOCOL, JSON_PROTOCOL];
+/// 客户端可以设置的最大推送频率 (每个代码每秒)
+pub const MAX_RATE: u32 = 50;
+/// 客户端积压时自动设置的推送频率
+pub const SLOW_CLIENT_RATE: u32 = 5;
+
+/// 推送编码
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WireFormat {
+    #[default]
+    Json,
+    #[serde(alias = "messagepack")]
+    Msgpack,
+}
+
+impl WireFormat {
+    /// 协商出的子协议对应的编码
+    pub fn from_protocol(protocol: &str) -> Option<Self> {
+        match protocol {
+            JSON_PROTOCOL => Some(Self::Json),
+            MSGPACK_PROTOCOL => Some(Self::Msgpack),
+            _ => None,
+        }
+    }
+
+    /// 编码为对应类型的帧
+    pub fn encode<T: Serialize>(self, value: &T) -> anyhow::Result<Message> {
+        Ok(match self {
+            Self::Json => Message::Text(serde_json::to_string(value)?),
+            Self::Msgpack => Message::Binary(self.to_bytes(value)?),
+        })
+    }
+
+    fn to_bytes<T: Serialize>(self, value: &T) -> anyhow::Result<Vec<u8>> {
+        Ok(match self {
+            Self::Json => serde_json::to_vec(value)?,
+            Self::Msgpack => rmp_serde::to_vec_named(value)?,
+        })
+    }
+}
+
+/// 帧压缩
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    #[default]
+    None,
+    Deflate,
+}
+
+/// 连接的推送选项
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+pub struct WireOptions {
+    pub format: WireFormat,
+    pub compression: Compression,
+    /// 积压时合并多条行情
+    pub batch: bool,
+    /// 每个代码每秒最多推送的次数，None 为不限
+    pub max_rate: Option<u32>,
+}
+
+impl WireOptions {
+    pub fn encode<T: Serialize>(self, value: &T) -> anyhow::Result<Message> {
+        match self.compression {
+            Compression::None => self.format.encode(value),
+            Compression::Deflate => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::fast());
+                encoder.write_all(&self.format.to_bytes(value)?)?;
+                Ok(Message::Binary(encoder.finish()?))
+            }
+        }
+    }
+
+    /// 应用格式消息，未给出的选项保持不变
+    pub fn apply(&mut self, message: FormatMessage) {
+        let FormatMessage::Format { format, compression, batch, max_rate } = message;
+        self.format = format.unwrap_or(self.format);
+        self.compression = compression.unwrap_or(self.compression);
+        self.batch = batch.unwrap_or(self.batch);
+        if let Some(rate) = max_rate {
+            self.max_rate = (rate > 0).then(|| rate.min(MAX_RATE));
+        }
+    }
+
+    /// 限频时合并行情的间隔
+    pub fn flush_period(self) -> Option<Duration> {
+        self.max_rate.map(|rate| Duration::from_secs(1) / rate)
+    }
+}
+
+/// 客户端修改推送选项的消息
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FormatMessage {
+    Format {
+        #[serde(default)]
+        format: Option<WireFormat>,
+        #[serde(default)]
+        compression: Option<Compression>,
+        #[serde(default)]
+        batch: Option<bool>,
+        #[serde(default)]
+        max_rate: Option<u32>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_encode() {
+        let value = BTreeMap::from([("symbol", "AAPL")]);
+        match WireFormat::Json.encode(&value).unwrap() {
+            Message::Text(text) => assert_eq!(text.as_str(), r#"{"symbol":"AAPL"}"#),
+            other => panic!("unexpected frame {:?}", other),
+        }
+        match WireFormat::Msgpack.encode(&value).unwrap() {
+            Message::Binary(bytes) => {
+                let decoded: BTreeMap<String, String> = rmp_serde::from_slice(&bytes).unwrap();
+                assert_eq!(decoded["symbol"], "AAPL");
+            }
+            other => panic!("unexpected frame {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_deflate() {
+        let value: Vec<u32> = (0..1000).map(|i| i % 10).collect();
+        let options = WireOptions { compression: Compression::Deflate, ..WireOptions::default() };
+        let Message::Binary(bytes) = options.encode(&value).unwrap() else {
+            panic!("deflate frames must be binary");
+        };
+        let mut json = String::new();
+        std::io::Read::read_to_string(&mut flate2::read::DeflateDecoder::new(&bytes[..]), &mut json).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<u32>>(&json).unwrap(), value);
+        assert!(bytes.len() < json.len() / 4);
+    }
+
+    #[test]
+    fn test_negotiation() {
+        assert_eq!(WireFormat::from_protocol(MSGPACK_PROTOCOL), Some(WireFormat::Msgpack));
+        assert_eq!(WireFormat::from_protocol("chat"), None);
+
+        let mut options = WireOptions::default();
+        options.apply(serde_json::from_str(r#"{"type": "format", "format": "messagepack"}"#).unwrap());
+        options.apply(serde_json::from_str(r#"{"type": "format", "batch": true, "compression": "deflate"}"#).unwrap());
+        assert_eq!(
+            options,
+            WireOptions { format: WireFormat::Msgpack, compression: Compression::Deflate, batch: true, max_rate: None }
+        );
+
+        options.apply(serde_json::from_str(r#"{"type": "format", "max_rate": 1000}"#).unwrap());
+        assert_eq!(options.max_rate, Some(MAX_RATE));
+        assert_eq!(options.flush_period(), Some(Duration::from_millis(20)));
+        options.apply(serde_json::from_str(r#"{"type": "format", "batch": false}"#).unwrap());
+        assert_eq!(options.max_rate, Some(MAX_RATE));
+        options.apply(serde_json::from_str(r#"{"type": "format", "max_rate": 0}"#).unwrap());
+        assert_eq!(options.flush_period(), None);
+        assert!(serde_json::from_str::<FormatMessage>(r#"{"symbols": ["AAPL"]}"#).is_err());
+        assert!(serde_json::from_str::<FormatMessage>(r#"{"type": "format", "compression": "gzip"}"#).is_err());
+    }
+}
@@ -0,0 +1,73 @@
+//! 心跳与空闲超时
+//!
+//! 服务端每隔 `ping_interval_secs` (默认 30 秒) 向客户端发送 ping，客户端超过 `idle_timeout_secs`
+//! (默认 90 秒) 没有任何消息 (包括 pong) 时关闭连接。连接任务每次心跳时刷新自己在连接表中的时间，
+//! 后台定期清理长时间没有刷新的条目 (任务已异常退出)
+
+use crate::settings::{positive, Settings};
+use anyhow::anyhow;
+use serde::Serialize;
+use std::time::Duration;
+
+const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(30);
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+pub struct HeartbeatConfig {
+    #[serde(rename = "ping_interval_secs", serialize_with = "as_secs")]
+    pub ping_interval: Duration,
+    #[serde(rename = "idle_timeout_secs", serialize_with = "as_secs")]
+    pub idle_timeout: Duration,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self { ping_interval: DEFAULT_PING_INTERVAL, idle_timeout: DEFAULT_IDLE_TIMEOUT }
+    }
+}
+
+impl HeartbeatConfig {
+    pub fn from_settings(settings: &Settings) -> anyhow::Result<Self> {
+        Self::from_vars(settings.ping_interval_secs.as_deref(), settings.idle_timeout_secs.as_deref())
+    }
+
+    fn from_vars(ping_interval: Option<&str>, idle_timeout: Option<&str>) -> anyhow::Result<Self> {
+        let config = Self {
+            ping_interval: Duration::from_secs(positive(ping_interval, "ping_interval_secs", DEFAULT_PING_INTERVAL.as_secs())?),
+            idle_timeout: Duration::from_secs(positive(idle_timeout, "idle_timeout_secs", DEFAULT_IDLE_TIMEOUT.as_secs())?),
+        };
+        // 至少给客户端一次回复 pong 的机会
+        if config.idle_timeout <= config.ping_interval {
+            return Err(anyhow!("idle_timeout_secs must be greater than ping_interval_secs"));
+        }
+        Ok(config)
+    }
+
+    /// 连接表中的条目超过该时间没有刷新即视为失效；存活的任务最迟每个心跳间隔刷新一次
+    pub fn stale_after(&self) -> Duration {
+        (self.ping_interval + self.idle_timeout) * 2
+    }
+}
+
+fn as_secs<S: serde::Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_vars() {
+        assert_eq!(HeartbeatConfig::from_vars(None, None).unwrap(), HeartbeatConfig::default());
+
+        let config = HeartbeatConfig::from_vars(Some("5"), Some(" 12 ")).unwrap();
+        assert_eq!((config.ping_interval, config.idle_timeout), (Duration::from_secs(5), Duration::from_secs(12)));
+        assert_eq!(config.stale_after(), Duration::from_secs(34));
+        assert_eq!(serde_json::to_value(config).unwrap(), serde_json::json!({"ping_interval_secs": 5, "idle_timeout_secs": 12}));
+
+        assert!(HeartbeatConfig::from_vars(Some("0"), None).is_err());
+        assert!(HeartbeatConfig::from_vars(Some("abc"), None).is_err());
+        assert!(HeartbeatConfig::from_vars(Some("60"), Some("60")).is_err());
+    }
+}
@@ -0,0 +1,359 @@
+//! 历史回放
+//!
+//! 客户端发送 `{"type":"replay","symbol":"AAPL","from":"...","to":"...","speed":10}`，服务端从 data-engine
+//! 分页查询该代码在 `[from, to)` 内的历史行情，按数据中相邻行情的时间间隔除以倍速推送；
+//! 超过 [`MAX_IDLE_GAP`] 的间隔 (如休市) 压缩为该值。回放的行情以 `replay_tick` 消息推送，与实时行情区分。
+//! 每个连接同时只有一个回放，新的回放替换旧的，`{"type":"stop_replay"}` 停止回放。
+//! data-engine 的地址由 `DATA_ENGINE_URL` 配置 (默认 `http://localhost:8081`)
+
+use crate::auth::Entitlements;
+use crate::RealTimeData;
+use alpha_core::utils::symbol::Symbol;
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// 最大倍速
+pub const MAX_SPEED: f64 = 1000.0;
+/// 回放中相邻两条行情的最长等待
+pub const MAX_IDLE_GAP: Duration = Duration::from_secs(5);
+/// 每次查询的行数
+const PAGE_SIZE: usize = 5000;
+/// 回放任务到连接之间的缓冲，连接发送不及时回放随之放慢
+const EVENT_BUFFER: usize = 256;
+
+const DEFAULT_URL: &str = "http://localhost:8081";
+/// data-engine 单次请求的最长时间
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 客户端发送的回放消息
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReplayMessage {
+    Replay(ReplayRequest),
+    StopReplay {
+        #[serde(default)]
+        id: Option<u64>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReplayRequest {
+    /// 客户端请求编号，原样带回回放消息
+    #[serde(default)]
+    pub id: Option<u64>,
+    pub symbol: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// 倍速，缺省为 1
+    #[serde(default = "default_speed")]
+    pub speed: f64,
+}
+
+fn default_speed() -> f64 {
+    1.0
+}
+
+impl ReplayRequest {
+    /// 校验请求，代码统一为规范写法
+    pub fn validate(mut self, entitlements: &Entitlements) -> Result<Self, String> {
+        self.symbol = Symbol::parse(&self.symbol).map_err(|e| e.report())?.canonical();
+        if !entitlements.allows(&self.symbol) {
+            return Err(format!("Not entitled to {}", self.symbol));
+        }
+        if self.from >= self.to {
+            return Err("Replay range is empty".to_string());
+        }
+        if !(self.speed > 0.0 && self.speed <= MAX_SPEED) {
+            return Err(format!("Replay speed must be in (0, {}]", MAX_SPEED));
+        }
+        Ok(self)
+    }
+}
+
+/// 回放推送的消息
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type")]
+pub enum ReplayEvent {
+    #[serde(rename = "replay_started")]
+    Started(ReplayRequest),
+    /// 一条历史行情，序号为该次回放内的序号
+    #[serde(rename = "replay_tick")]
+    Tick {
+        id: Option<u64>,
+        #[serde(flatten)]
+        tick: RealTimeData,
+    },
+    /// 回放结束，`count` 为推送的行情数
+    #[serde(rename = "replay_finished")]
+    Finished { id: Option<u64>, symbol: String, count: u64 },
+    #[serde(rename = "replay_failed")]
+    Failed { id: Option<u64>, symbol: String, message: String },
+    #[serde(rename = "replay_stopped")]
+    Stopped { id: Option<u64> },
+}
+
+/// data-engine 查询客户端
+#[derive(Debug, Clone)]
+pub struct DataEngineClient {
+    http: reqwest::Client,
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryResponse {
+    data: Vec<HistoryRow>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryRow {
+    timestamp: DateTime<Utc>,
+    price: f64,
+    #[serde(default)]
+    volume: Option<u64>,
+}
+
+impl DataEngineClient {
+    pub fn from_env() -> Self {
+        let url = std::env::var("DATA_ENGINE_URL").unwrap_or_else(|_| DEFAULT_URL.to_string());
+        Self { http: reqwest::Client::new(), url: url.trim_end_matches('/').to_string() }
+    }
+
+    /// 向 data-engine 的接口发送 JSON 请求，非 2xx 的响应作为错误返回
+    pub async fn post<B: Serialize, R: DeserializeOwned>(&self, path: &str, body: &B) -> anyhow::Result<R> {
+        let response = self.http
+            .post(format!("{}{}", self.url, path))
+            .timeout(REQUEST_TIMEOUT)
+            .json(body)
+            .send()
+            .await
+            .context("Failed to reach data-engine")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("data-engine returned {}: {}", status, body));
+        }
+        response.json::<R>().await.context("Invalid data-engine response")
+    }
+
+    /// 按时间顺序查询从 `offset` 起的一页历史行情
+    async fn page(&self, request: &ReplayRequest, offset: usize) -> anyhow::Result<Vec<HistoryRow>> {
+        Ok(self.post::<_, QueryResponse>("/query", &page_query(request, offset)).await?.data)
+    }
+}
+
+/// 分页查询请求，代码和时间范围作为参数绑定
+fn page_query(request: &ReplayRequest, offset: usize) -> serde_json::Value {
+    let timestamp = |time: DateTime<Utc>| {
+        serde_json::json!({ "type": "timestamp", "value": time.to_rfc3339_opts(SecondsFormat::Millis, true) })
+    };
+    serde_json::json!({
+        "query": format!(
+            "SELECT timestamp, price, volume FROM historical_data \
+             WHERE symbol = $1 AND timestamp >= $2 AND timestamp < $3 \
+             ORDER BY timestamp LIMIT {} OFFSET {}",
+            PAGE_SIZE, offset,
+        ),
+        "params": [request.symbol, timestamp(request.from), timestamp(request.to)],
+    })
+}
+
+/// 按倍速计算每条行情相对回放开始的发送时间
+#[derive(Debug)]
+struct Pacer {
+    speed: f64,
+    previous: Option<DateTime<Utc>>,
+    elapsed: Duration,
+}
+
+impl Pacer {
+    fn new(speed: f64) -> Self {
+        Self { speed, previous: None, elapsed: Duration::ZERO }
+    }
+
+    fn next(&mut self, at: DateTime<Utc>) -> Duration {
+        if let Some(previous) = self.previous.replace(at) {
+            let gap = (at - previous).to_std().unwrap_or_default();
+            self.elapsed += gap.div_f64(self.speed).min(MAX_IDLE_GAP);
+        }
+        self.elapsed
+    }
+}
+
+/// 一个连接正在进行的回放，丢弃时停止
+#[derive(Debug)]
+pub struct ReplayHandle {
+    receiver: mpsc::Receiver<ReplayEvent>,
+    task: JoinHandle<()>,
+}
+
+impl ReplayHandle {
+    /// 下一条回放消息，回放结束后返回 None
+    pub async fn recv(&mut self) -> Option<ReplayEvent> {
+        self.receiver.recv().await
+    }
+}
+
+impl Drop for ReplayHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// 在后台开始回放
+pub fn spawn(client: DataEngineClient, request: ReplayRequest) -> ReplayHandle {
+    let (sender, receiver) = mpsc::channel(EVENT_BUFFER);
+    let task = tokio::spawn(async move {
+        let (id, symbol) = (request.id, request.symbol.clone());
+        let event = match run(&client, request, &sender).await {
+            Ok(count) => ReplayEvent::Finished { id, symbol, count },
+            Err(e) => {
+                tracing::warn!("Replay of {} failed: {:#}", symbol, e);
+                ReplayEvent::Failed { id, symbol, message: format!("{:#}", e) }
+            }
+        };
+        let _ = sender.send(event).await;
+    });
+    ReplayHandle { receiver, task }
+}
+
+/// 逐页查询并按节奏推送，返回推送的行情数；连接已不再接收时提前结束
+async fn run(client: &DataEngineClient, request: ReplayRequest, sender: &mpsc::Sender<ReplayEvent>) -> anyhow::Result<u64> {
+    let (id, symbol) = (request.id, request.symbol.clone());
+    let mut pacer = Pacer::new(request.speed);
+    let mut previous_price = None;
+    let mut count = 0;
+    if sender.send(ReplayEvent::Started(request.clone())).await.is_err() {
+        return Ok(count);
+    }
+
+    let start = Instant::now();
+    loop {
+        let rows = client.page(&request, count as usize).await?;
+        let last_page = rows.len() < PAGE_SIZE;
+        for row in rows {
+            tokio::time::sleep_until(start + pacer.next(row.timestamp)).await;
+            count += 1;
+            let previous = previous_price.replace(row.price).unwrap_or(row.price);
+            let change = row.price - previous;
+            let tick = RealTimeData {
+                symbol: symbol.clone(),
+                seq: count,
+                price: row.price,
+                volume: row.volume.unwrap_or(0),
+                change,
+                change_percent: if previous == 0.0 { 0.0 } else { change / previous * 100.0 },
+                bid: None,
+                ask: None,
+                timestamp: row.timestamp,
+            };
+            if sender.send(ReplayEvent::Tick { id, tick }).await.is_err() {
+                return Ok(count);
+            }
+        }
+        if last_page {
+            return Ok(count);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn request(symbol: &str, speed: f64) -> ReplayRequest {
+        ReplayRequest {
+            id: Some(1),
+            symbol: symbol.to_string(),
+            from: Utc.with_ymd_and_hms(2024, 3, 1, 14, 30, 0).unwrap(),
+            to: Utc.with_ymd_and_hms(2024, 3, 1, 21, 0, 0).unwrap(),
+            speed,
+        }
+    }
+
+    #[test]
+    fn test_parse_message() {
+        let message: ReplayMessage = serde_json::from_str(
+            r#"{"type":"replay","symbol":"aapl","from":"2024-03-01T14:30:00Z","to":"2024-03-01T21:00:00Z"}"#,
+        )
+        .unwrap();
+        let ReplayMessage::Replay(parsed) = message else {
+            panic!("expected a replay request");
+        };
+        assert_eq!(parsed.speed, 1.0);
+        assert_eq!(parsed.validate(&Entitlements::All).unwrap().symbol, "AAPL");
+        assert!(matches!(serde_json::from_str(r#"{"type":"stop_replay"}"#), Ok(ReplayMessage::StopReplay { id: None })));
+        assert!(serde_json::from_str::<ReplayMessage>(r#"{"symbols":["AAPL"]}"#).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(request("AAPL", 10.0).validate(&Entitlements::All).is_ok());
+        assert!(request("AAPL", 0.0).validate(&Entitlements::All).is_err());
+        assert!(request("AAPL", MAX_SPEED * 2.0).validate(&Entitlements::All).is_err());
+        assert!(request("AAPL", f64::NAN).validate(&Entitlements::All).is_err());
+        assert!(request("NOT A SYMBOL", 1.0).validate(&Entitlements::All).is_err());
+
+        let entitlements = Entitlements::from_symbols(&["MSFT".to_string()]);
+        assert_eq!(request("AAPL", 1.0).validate(&entitlements).unwrap_err(), "Not entitled to AAPL");
+
+        let mut empty = request("AAPL", 1.0);
+        empty.to = empty.from;
+        assert!(empty.validate(&Entitlements::All).is_err());
+    }
+
+    #[test]
+    fn test_page_query() {
+        let query = page_query(&request("BRK.B", 1.0), 5000);
+        assert_eq!(
+            query["query"],
+            "SELECT timestamp, price, volume FROM historical_data \
+             WHERE symbol = $1 AND timestamp >= $2 AND timestamp < $3 \
+             ORDER BY timestamp LIMIT 5000 OFFSET 5000"
+        );
+        assert_eq!(
+            query["params"],
+            serde_json::json!([
+                "BRK.B",
+                {"type": "timestamp", "value": "2024-03-01T14:30:00.000Z"},
+                {"type": "timestamp", "value": "2024-03-01T21:00:00.000Z"},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_pacer() {
+        let start = Utc.with_ymd_and_hms(2024, 3, 1, 14, 30, 0).unwrap();
+        let mut pacer = Pacer::new(10.0);
+        assert_eq!(pacer.next(start), Duration::ZERO);
+        assert_eq!(pacer.next(start + chrono::Duration::seconds(20)), Duration::from_secs(2));
+        // 休市的间隔被压缩
+        assert_eq!(pacer.next(start + chrono::Duration::hours(18)), Duration::from_secs(2) + MAX_IDLE_GAP);
+    }
+
+    #[test]
+    fn test_event_serialization() {
+        let tick = RealTimeData {
+            symbol: "AAPL".to_string(),
+            seq: 3,
+            price: 180.0,
+            volume: 100,
+            change: 0.5,
+            change_percent: 0.28,
+            bid: None,
+            ask: None,
+            timestamp: Utc::now(),
+        };
+        let json = serde_json::to_value(ReplayEvent::Tick { id: Some(7), tick }).unwrap();
+        assert_eq!((json["type"].as_str(), json["id"].as_u64(), json["seq"].as_u64()), (Some("replay_tick"), Some(7), Some(3)));
+
+        let json = serde_json::to_value(ReplayEvent::Started(request("AAPL", 2.0))).unwrap();
+        assert_eq!((json["type"].as_str(), json["speed"].as_f64()), (Some("replay_started"), Some(2.0)));
+    }
+}
@@ -1,54 +1,133 @@
 //! Alpha Finance Real-Time Feed Service
 //!
-//! 实时数据流推送服务，支持 WebSocket 连接和广播
-
+//! 实时数据流推送服务，支持 WebSocket 连接和广播；行情由 [`upstream`] 适配器接入，经 [`bus::MessageBus`] 分发，可水平扩展。
+//! 配置来自命令行参数、环境变量和配置文件，见 [`settings`]
+
+use archive::{ArchiveConfig, ArchiveStats};
+use auth::{AuthFailure, AuthMessage, Authenticator, Principal, AUTH_TIMEOUT};
+use bus::MessageBus;
+use heartbeat::HeartbeatConfig;
+use history::{DataEngineClient, ReplayEvent, ReplayMessage};
+use outbox::{FrameKind, Outbox, OutboxConfig, QueueStats};
+use replay::{ReplayBuffer, REPLAY_CAPACITY};
+use settings::{Args, Settings};
+use shutdown::Shutdown;
+use snapshot::SnapshotStore;
+use codec::{FormatMessage, WireFormat, WireOptions, SLOW_CLIENT_RATE};
 use axum::{
     extract::{
-        ws::{Message, WebSocket, WebSocketUpgrade},
-        State,
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Query, State,
     },
-    response::Response,
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     routing::get,
     Router,
 };
-use futures_util::{SinkExt, StreamExt};
+use clap::Parser;
+use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
-    sync::{Arc, Mutex},
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
     time::{Duration, Instant},
 };
+use subscription::{ControlMessage, SubscriptionAction, Subscriptions};
 use tokio::{
-    sync::broadcast,
-    time::{interval, MissedTickBehavior},
+    sync::broadcast::error::{RecvError, TryRecvError},
+    time::{interval, Interval, MissedTickBehavior},
 };
+use topic::{FeedMessage, Topic};
+
+mod archive;
+mod auth;
+mod bus;
+mod codec;
+mod heartbeat;
+mod history;
+mod outbox;
+mod replay;
+mod settings;
+mod shutdown;
+mod snapshot;
+mod subscription;
+mod topic;
+mod upstream;
 
 /// 实时数据消息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct RealTimeData {
     symbol: String,
+    /// 该代码行情的序号，从 1 递增
+    #[serde(default)]
+    seq: u64,
     price: f64,
     volume: u64,
     change: f64,
     change_percent: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bid: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ask: Option<f64>,
     timestamp: chrono::DateTime<chrono::Utc>,
 }
 
-/// WebSocket 连接管理器
+/// 积压时合并为一帧的消息，各条仍带 `topic`
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename = "batch")]
+struct TickBatch {
+    ticks: Vec<FeedMessage>,
+}
+
+/// 每帧最多合并的行情数
+const MAX_BATCH_TICKS: usize = 200;
+
+/// 连接表中的一个连接
+#[derive(Debug)]
+struct Connection {
+    symbols: BTreeSet<String>,
+    /// 连接任务最近一次心跳
+    last_heartbeat: Instant,
+}
+
+/// WebSocket 连接管理器，记录每个连接订阅的代码用于统计
 #[derive(Debug)]
 struct ConnectionManager {
-    connections: Arc<Mutex<HashMap<String, broadcast::Sender<RealTimeData>>>>,
+    connections: Arc<Mutex<HashMap<String, Connection>>>,
+    /// 因空闲超时关闭的连接数
+    idle_timeouts: AtomicU64,
+    /// 被清理的失效条目数
+    reaped: AtomicU64,
 }
 
 impl ConnectionManager {
     fn new() -> Self {
         Self {
             connections: Arc::new(Mutex::new(HashMap::new())),
+            idle_timeouts: AtomicU64::new(0),
+            reaped: AtomicU64::new(0),
         }
     }
 
-    fn add_connection(&self, id: String, sender: broadcast::Sender<RealTimeData>) {
-        self.connections.lock().unwrap().insert(id, sender);
+    fn add_connection(&self, id: String) {
+        let connection = Connection { symbols: BTreeSet::new(), last_heartbeat: Instant::now() };
+        self.connections.lock().unwrap().insert(id, connection);
+    }
+
+    fn set_subscriptions(&self, id: &str, symbols: &[String]) {
+        if let Some(connection) = self.connections.lock().unwrap().get_mut(id) {
+            connection.symbols = symbols.iter().cloned().collect();
+        }
+    }
+
+    /// 刷新连接的心跳时间
+    fn touch(&self, id: &str) {
+        if let Some(connection) = self.connections.lock().unwrap().get_mut(id) {
+            connection.last_heartbeat = Instant::now();
+        }
     }
 
     fn remove_connection(&self, id: &str) {
@@ -58,191 +137,657 @@ impl ConnectionManager {
     fn get_connection_count(&self) -> usize {
         self.connections.lock().unwrap().len()
     }
+
+    fn record_idle_timeout(&self) {
+        self.idle_timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 移除超过 `stale_after` 没有心跳的条目，返回被移除的连接 ID
+    fn reap(&self, stale_after: Duration) -> Vec<String> {
+        let mut connections = self.connections.lock().unwrap();
+        let stale: Vec<String> = connections.iter()
+            .filter(|(_, connection)| connection.last_heartbeat.elapsed() >= stale_after)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &stale {
+            connections.remove(id);
+        }
+        self.reaped.fetch_add(stale.len() as u64, Ordering::Relaxed);
+        stale
+    }
+
+    /// 每个代码的订阅连接数
+    fn subscriber_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for symbol in self.connections.lock().unwrap().values().flat_map(|connection| &connection.symbols) {
+            *counts.entry(symbol.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+/// 连接任务结束 (包括 panic) 时从连接表中移除
+struct ConnectionGuard<'a> {
+    manager: &'a ConnectionManager,
+    id: String,
+}
+
+impl Drop for ConnectionGuard<'_> {
+    fn drop(&mut self) {
+        self.manager.remove_connection(&self.id);
+    }
 }
 
 /// 应用状态
 #[derive(Debug)]
 struct AppState {
     connection_manager: ConnectionManager,
-    data_sender: broadcast::Sender<RealTimeData>,
+    bus: Arc<dyn MessageBus>,
+    replay: Arc<ReplayBuffer>,
+    snapshots: Arc<SnapshotStore>,
+    upstreams: Vec<Arc<upstream::HealthTracker>>,
+    authenticator: Authenticator,
+    heartbeat: HeartbeatConfig,
+    data_engine: DataEngineClient,
+    outbox: OutboxConfig,
+    queue_stats: Arc<QueueStats>,
+    /// 未开启归档时为 None
+    archive: Option<(ArchiveConfig, Arc<ArchiveStats>)>,
+    shutdown: Shutdown,
+}
+
+/// `/ws` 升级请求的查询参数
+#[derive(Debug, Deserialize)]
+struct WsParams {
+    /// API Key 或 JWT
+    token: Option<String>,
+    /// 会话 ID，重连后恢复该会话的订阅
+    session: Option<String>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let settings = Settings::load(&args)?;
+
     // 初始化日志
-    tracing_subscriber::fmt::init();
+    tracing_subscriber::fmt().with_max_level(settings.log_level()).init();
 
     tracing::info!("Starting Alpha Finance Real-Time Feed Service");
 
-    // 创建广播通道
-    let (data_sender, _data_receiver) = broadcast::channel(1000);
+    // 读取认证配置
+    let authenticator = Authenticator::from_env()?;
+    if !authenticator.is_enabled() {
+        tracing::warn!("FEED_API_KEYS and FEED_JWT_SECRET are not set, WebSocket authentication is disabled");
+    }
+
+    let heartbeat = HeartbeatConfig::from_settings(&settings)?;
+    let outbox = OutboxConfig::from_settings(&settings)?;
+    let bind = settings.bind()?;
+    let drain_timeout = settings::positive(settings.drain_timeout_secs.as_deref(), "drain_timeout_secs", shutdown::DEFAULT_DRAIN_TIMEOUT_SECS)?;
+
+    // 连接消息总线
+    let bus: Arc<dyn MessageBus> = bus::from_settings(&settings).await?.into();
+    let replay_capacity = settings::positive(settings.replay_capacity.as_deref(), "replay_capacity", REPLAY_CAPACITY)?;
+    let replay = Arc::new(ReplayBuffer::new(replay_capacity));
+    replay::spawn_recorder(bus.clone(), replay.clone());
+    let snapshots = Arc::new(SnapshotStore::new());
+    snapshot::spawn_recorder(bus.clone(), snapshots.clone());
+
+    // 接入上游行情，多实例部署时只在一个实例上接入
+    let upstreams = upstream::spawn(upstream::from_settings(&settings)?, bus.clone());
+
+    // 把实时行情归档到 data-engine，同样只在一个实例上开启
+    let data_engine = DataEngineClient::from_env();
+    let archive = ArchiveConfig::from_settings(&settings)?.map(|config| {
+        let stats = Arc::new(ArchiveStats::default());
+        tracing::info!("Archiving real-time data to data-engine with {:?}", config);
+        archive::spawn(bus.clone(), data_engine.clone(), config, stats.clone());
+        (config, stats)
+    });
 
     // 创建应用状态
     let app_state = Arc::new(AppState {
         connection_manager: ConnectionManager::new(),
-        data_sender,
+        bus,
+        replay,
+        snapshots,
+        upstreams,
+        authenticator,
+        heartbeat,
+        data_engine,
+        outbox,
+        queue_stats: Arc::default(),
+        archive,
+        shutdown: Shutdown::new(),
     });
-
-    // 启动数据生成器
-    start_data_generator(app_state.clone());
+    spawn_reaper(app_state.clone());
 
     // 构建 HTTP 路由
     let app = Router::new()
         .route("/ws", get(websocket_handler))
         .route("/health", get(health_check))
         .route("/stats", get(get_stats))
-        .with_state(app_state);
+        .with_state(app_state.clone());
 
     // 启动服务器
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:8082").await?;
-    tracing::info!("Real-Time Feed service listening on 0.0.0.0:8082");
-
-    axum::serve(listener, app).await?;
+    let listener = tokio::net::TcpListener::bind(bind).await?;
+    tracing::info!("Real-Time Feed service listening on {}", bind);
+
+    // 收到停机信号后停止接受连接并通知已有连接关闭；升级后的 WebSocket 不在 serve 的等待范围内，单独等待
+    let draining = app_state.clone();
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            shutdown::signal().await;
+            tracing::info!("Draining {} connections", draining.connection_manager.get_connection_count());
+            draining.shutdown.trigger();
+        })
+        .await?;
+    let remaining = shutdown::drain(
+        || app_state.connection_manager.get_connection_count(),
+        Duration::from_secs(drain_timeout),
+    )
+    .await;
+    if remaining > 0 {
+        tracing::warn!("Shutting down with {} connections still open", remaining);
+    }
+    tracing::info!("Real-Time Feed service stopped");
 
     Ok(())
 }
 
-/// 启动数据生成器（模拟实时数据）
-fn start_data_generator(app_state: Arc<AppState>) {
-    let sender = app_state.data_sender.clone();
+/// WebSocket 连接处理器
+async fn websocket_handler(
+    ws: WebSocketUpgrade,
+    Query(params): Query<WsParams>,
+    headers: HeaderMap,
+    State(app_state): State<Arc<AppState>>,
+) -> Response {
+    if app_state.shutdown.is_draining() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Server is shutting down").into_response();
+    }
+    // 查询参数优先，其次 Authorization 头
+    let token = params.token.or_else(|| {
+        headers.get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(auth::bearer_token)
+            .map(str::to_string)
+    });
+    ws.protocols(codec::PROTOCOLS)
+        .on_upgrade(move |socket| handle_websocket(socket, app_state, token, params.session))
+}
 
-    tokio::spawn(async move {
-        let mut interval = interval(Duration::from_millis(100)); // 每100ms发送一次数据
-        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+/// 认证连接，升级请求未携带凭证时等待第一条认证消息
+async fn authenticate(
+    socket: &mut WebSocket,
+    authenticator: &Authenticator,
+    token: Option<String>,
+) -> Result<Principal, AuthFailure> {
+    if !authenticator.is_enabled() {
+        return Ok(Principal::anonymous());
+    }
+    if let Some(token) = token {
+        return authenticator.authenticate(&token);
+    }
 
-        let symbols = vec!["AAPL", "GOOGL", "MSFT", "AMZN", "TSLA"];
-        let mut last_prices: HashMap<String, f64> = symbols.iter()
-            .map(|s| (s.to_string(), 100.0 + rand::random::<f64>() * 900.0))
-            .collect();
+    let first_text = async {
+        loop {
+            match socket.recv().await {
+                Some(Ok(Message::Text(text))) => return Some(text),
+                Some(Ok(Message::Ping(_) | Message::Pong(_))) => continue,
+                _ => return None,
+            }
+        }
+    };
+    match tokio::time::timeout(AUTH_TIMEOUT, first_text).await {
+        Err(_) => Err(AuthFailure::Timeout),
+        Ok(Some(text)) => match serde_json::from_str::<AuthMessage>(&text) {
+            Ok(AuthMessage::Auth { token }) => authenticator.authenticate(&token),
+            Err(_) => Err(AuthFailure::Missing),
+        },
+        Ok(None) => Err(AuthFailure::Missing),
+    }
+}
 
+/// 定期清理连接表中任务已退出的条目
+fn spawn_reaper(app_state: Arc<AppState>) {
+    let stale_after = app_state.heartbeat.stale_after();
+    tokio::spawn(async move {
+        let mut timer = interval(app_state.heartbeat.idle_timeout);
+        timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
         loop {
-            interval.tick().await;
-
-            for symbol in &symbols {
-                let last_price = *last_prices.get(symbol).unwrap_or(&100.0);
-                let change = (rand::random::<f64>() - 0.5) * 10.0;
-                let new_price = (last_price + change).max(1.0);
-                let change_percent = ((new_price - last_price) / last_price) * 100.0;
-
-                let data = RealTimeData {
-                    symbol: symbol.clone(),
-                    price: new_price,
-                    volume: (1000 + rand::random::<u64>() % 90000) as u64,
-                    change,
-                    change_percent,
-                    timestamp: chrono::Utc::now(),
-                };
-
-                // 更新最新价格
-                last_prices.insert(symbol.clone(), new_price);
-
-                // 广播数据
-                if let Err(e) = sender.send(data.clone()) {
-                    tracing::debug!("Failed to send real-time data: {}", e);
-                }
+            timer.tick().await;
+            for id in app_state.connection_manager.reap(stale_after) {
+                tracing::warn!("Reaped stale connection {}", id);
             }
         }
     });
 }
 
-/// WebSocket 连接处理器
-async fn websocket_handler(
-    ws: WebSocketUpgrade,
-    State(app_state): State<Arc<AppState>>,
-) -> Response {
-    ws.on_upgrade(|socket| handle_websocket(socket, app_state))
+fn close_frame(code: u16, reason: &'static str) -> Message {
+    Message::Close(Some(CloseFrame { code, reason: reason.into() }))
+}
+
+/// 以认证失败对应的关闭码关闭尚未认证的连接
+async fn close_with(socket: &mut WebSocket, failure: AuthFailure) {
+    if let Err(e) = socket.send(close_frame(failure.close_code(), failure.reason())).await {
+        tracing::debug!("Failed to send close frame: {}", e);
+    }
+}
+
+/// 按连接的推送选项把一条消息放入发送队列，连接已关闭时返回 false
+fn send_encoded<T: Serialize>(outbox: &Outbox, options: WireOptions, value: &T, kind: FrameKind) -> bool {
+    match options.encode(value) {
+        Ok(message) => outbox.push(message, kind),
+        Err(e) => {
+            tracing::error!("Failed to encode message: {:#}", e);
+            true
+        }
+    }
+}
+
+/// 消息是否未补发过，只有 ticker 行情会补发
+fn is_fresh(replayed: &mut HashMap<String, u64>, message: &FeedMessage) -> bool {
+    let FeedMessage::Ticker(data) = message else {
+        return true;
+    };
+    match replayed.get(&data.symbol) {
+        Some(&last) if data.seq <= last => false,
+        Some(_) => {
+            replayed.remove(&data.symbol);
+            true
+        }
+        None => true,
+    }
+}
+
+/// 补发 `resume_from` 之后的行情，缺失的部分先发送 gap 消息；连接断开时返回 false
+fn send_replay(
+    outbox: &Outbox,
+    options: WireOptions,
+    buffer: &ReplayBuffer,
+    resume_from: &BTreeMap<String, u64>,
+    replayed: &mut HashMap<String, u64>,
+) -> bool {
+    for (symbol, &last_seen) in resume_from {
+        let replay = buffer.since(symbol, last_seen);
+        if let Some(next) = replay.gap {
+            let gap = ControlMessage::Gap { symbol: symbol.clone(), last_seen, next };
+            if !send_encoded(outbox, options, &gap, FrameKind::Control) {
+                return false;
+            }
+        }
+        if let Some(last) = replay.ticks.last() {
+            replayed.insert(symbol.clone(), last.seq);
+        }
+        let ticks: Vec<FeedMessage> = replay.ticks.into_iter().map(FeedMessage::Ticker).collect();
+        if !send_ticks(outbox, options, &ticks) {
+            return false;
+        }
+    }
+    true
+}
+
+/// 发送代码的快照，没有快照的代码跳过；连接断开时返回 false
+fn send_snapshots(
+    outbox: &Outbox,
+    options: WireOptions,
+    store: &SnapshotStore,
+    symbols: &[String],
+    replayed: &mut HashMap<String, u64>,
+) -> bool {
+    for symbol in symbols {
+        let Some(snapshot) = store.get(symbol) else {
+            continue;
+        };
+        replayed.insert(symbol.clone(), snapshot.tick.seq);
+        if !send_encoded(outbox, options, &snapshot, FrameKind::Control) {
+            return false;
+        }
+    }
+    true
+}
+
+/// 发送多条消息，开启合并时按 [`MAX_BATCH_TICKS`] 分帧；连接断开时返回 false
+fn send_ticks(outbox: &Outbox, options: WireOptions, ticks: &[FeedMessage]) -> bool {
+    if options.batch && ticks.len() > 1 {
+        for chunk in ticks.chunks(MAX_BATCH_TICKS) {
+            if !send_encoded(outbox, options, &TickBatch { ticks: chunk.to_vec() }, FrameKind::Data) {
+                return false;
+            }
+        }
+        return true;
+    }
+    for tick in ticks {
+        if !send_encoded(outbox, options, tick, FrameKind::Data) {
+            return false;
+        }
+    }
+    true
+}
+
+/// 限频合并的计时器，未限频时不使用
+fn flush_timer(options: WireOptions) -> Interval {
+    let mut timer = interval(options.flush_period().unwrap_or(Duration::from_secs(1)));
+    timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    timer
+}
+
+/// 恢复会话保存的订阅，返回告知客户端的确认；会话为空或已无权订阅时返回 None
+async fn restore_session(bus: &dyn MessageBus, key: &str, subscriptions: &mut Subscriptions) -> Option<ControlMessage> {
+    let keys = match bus.load_session(key).await {
+        Ok(keys) if !keys.is_empty() => keys,
+        Ok(_) => return None,
+        Err(e) => {
+            tracing::warn!("Failed to load session {}: {:#}", key, e);
+            return None;
+        }
+    };
+    if let Err(e) = subscriptions.restore(&keys) {
+        tracing::info!("Discarding session {}: {}", key, e);
+        return None;
+    }
+    Some(subscriptions.ack(None, SubscriptionAction::Replace))
 }
 
 /// 处理 WebSocket 连接
-async fn handle_websocket(socket: WebSocket, app_state: Arc<AppState>) {
+///
+/// 认证后在同一个循环中转发行情和处理客户端消息：只转发该连接订阅的代码和主题，订阅消息回复确认或错误；
+/// JWT 到期或客户端空闲超时时关闭连接。新订阅 ticker 的代码先推送快照。带会话 ID 时先恢复会话的订阅，每次订阅变化后保存；
+/// 限频时按代码只保留最新行情，定时发送。历史回放的消息与实时行情在同一循环中转发。
+/// 认证后发送的帧都经过连接的 [`Outbox`]，由写任务发送
+async fn handle_websocket(
+    mut socket: WebSocket,
+    app_state: Arc<AppState>,
+    token: Option<String>,
+    session: Option<String>,
+) {
     let connection_id = uuid::Uuid::new_v4().to_string();
     tracing::info!("New WebSocket connection: {}", connection_id);
 
-    // 为这个连接创建数据接收器
-    let mut data_receiver = app_state.data_sender.subscribe();
-
-    // 将连接添加到管理器
-    app_state.connection_manager.add_connection(
-        connection_id.clone(),
-        app_state.data_sender.clone(),
-    );
-
-    // 处理连接
-    let (mut sender, mut receiver) = socket.split();
-
-    // 发送数据的任务
-    let send_task = tokio::spawn(async move {
-        while let Ok(data) = data_receiver.recv().await {
-            let message = match serde_json::to_string(&data) {
-                Ok(json) => Message::Text(json),
-                Err(e) => {
-                    tracing::error!("Failed to serialize real-time data: {}", e);
-                    continue;
-                }
-            };
+    let format = socket.protocol()
+        .and_then(|protocol| protocol.to_str().ok())
+        .and_then(WireFormat::from_protocol)
+        .unwrap_or_default();
+    let mut options = WireOptions { format, ..WireOptions::default() };
+
+    let principal = match authenticate(&mut socket, &app_state.authenticator, token).await {
+        Ok(principal) => principal,
+        Err(failure) => {
+            tracing::info!("Rejected WebSocket connection {}: {}", connection_id, failure.reason());
+            close_with(&mut socket, failure).await;
+            return;
+        }
+    };
 
-            if sender.send(message).await.is_err() {
-                break;
-            }
+    let (sink, mut stream) = socket.split();
+    let outbox = Arc::new(Outbox::new(app_state.outbox, app_state.queue_stats.clone()));
+    let writer = outbox::spawn_writer(sink, outbox.clone());
+
+    if app_state.authenticator.is_enabled() {
+        tracing::info!("Connection {} authenticated as {}", connection_id, principal.name);
+        let reply = ControlMessage::Authenticated { client: principal.name.clone() };
+        send_encoded(&outbox, options, &reply, FrameKind::Control);
+    }
+
+    let mut data_receiver = app_state.bus.subscribe();
+    let mut subscriptions = Subscriptions::new(principal.entitlements.clone());
+    app_state.connection_manager.add_connection(connection_id.clone());
+    let _guard = ConnectionGuard { manager: &app_state.connection_manager, id: connection_id.clone() };
+
+    let session_key = session.as_deref().and_then(|session| {
+        let key = bus::session_key(&principal.name, session);
+        if key.is_none() {
+            tracing::debug!("Ignoring invalid session id from {}", connection_id);
         }
+        key
     });
+    // 快照或补发过的代码及其最后序号，实时行情追上之前跳过重复的部分
+    let mut replayed: HashMap<String, u64> = HashMap::new();
+    if let Some(key) = &session_key {
+        match restore_session(app_state.bus.as_ref(), key, &mut subscriptions).await {
+            Some(reply) => {
+                app_state.connection_manager.set_subscriptions(&connection_id, &subscriptions.symbols());
+                send_encoded(&outbox, options, &reply, FrameKind::Control);
+                let tickers: Vec<String> = subscriptions.symbols_for(Topic::Ticker).into_iter().collect();
+                send_snapshots(&outbox, options, &app_state.snapshots, &tickers, &mut replayed);
+            }
+            None => tracing::debug!("No subscriptions to restore for {}", connection_id),
+        }
+    }
 
-    // 接收消息的任务（处理心跳等）
-    let receive_task = tokio::spawn(async move {
-        while let Some(msg) = receiver.next().await {
-            match msg {
-                Ok(Message::Text(text)) => {
-                    tracing::debug!("Received text message from {}: {}", connection_id, text);
+    let expiry = async {
+        match principal.expires_at {
+            Some(at) => tokio::time::sleep((at - chrono::Utc::now()).to_std().unwrap_or_default()).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(expiry);
+
+    // 限频时每个代码每个主题待发送的最新消息
+    let mut conflated: BTreeMap<(Topic, String), FeedMessage> = BTreeMap::new();
+    let mut flush = flush_timer(options);
+    let heartbeat = app_state.heartbeat;
+    let mut ping = tokio::time::interval_at(tokio::time::Instant::now() + heartbeat.ping_interval, heartbeat.ping_interval);
+    ping.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    // 最近一次收到客户端消息
+    let mut last_seen = Instant::now();
+    // 正在进行的历史回放
+    let mut history: Option<history::ReplayHandle> = None;
+    let mut draining = app_state.shutdown.subscribe();
+
+    loop {
+        // 发送队列丢过帧时告知客户端
+        if let Some(dropped) = outbox.take_lag_notice() {
+            tracing::debug!("Connection {} dropped {} frames", connection_id, dropped);
+            send_encoded(&outbox, options, &ControlMessage::Lagged { dropped }, FrameKind::LagNotice);
+        }
 
-                    // 处理订阅请求
-                    if let Ok(subscribe_msg) = serde_json::from_str::<SubscribeMessage>(&text) {
-                        tracing::info!("Client {} subscribed to: {:?}", connection_id, subscribe_msg.symbols);
+        let (kind, outgoing) = tokio::select! {
+            () = shutdown::draining(&mut draining) => {
+                outbox.push(close_frame(close_code::AWAY, "Server shutting down"), FrameKind::Control);
+                break;
+            }
+            () = &mut expiry => {
+                let failure = AuthFailure::Expired;
+                outbox.push(close_frame(failure.close_code(), failure.reason()), FrameKind::Control);
+                break;
+            }
+            data = data_receiver.recv() => match data {
+                Ok(data) => {
+                    let wanted = |data: &FeedMessage, replayed: &mut HashMap<String, u64>| {
+                        subscriptions.contains(data.symbol(), data.topic()) && is_fresh(replayed, data)
+                    };
+                    let mut ticks = Vec::new();
+                    if wanted(&data, &mut replayed) {
+                        ticks.push(data);
+                    }
+                    // 合并已经积压的消息
+                    while (options.batch || options.max_rate.is_some()) && ticks.len() < MAX_BATCH_TICKS {
+                        match data_receiver.try_recv() {
+                            Ok(data) if wanted(&data, &mut replayed) => {
+                                ticks.push(data);
+                            }
+                            Ok(_) => {}
+                            Err(TryRecvError::Lagged(skipped)) => {
+                                tracing::debug!("Connection {} lagged, skipped {} messages", connection_id, skipped);
+                            }
+                            Err(_) => break,
+                        }
+                    }
+                    if options.max_rate.is_some() {
+                        for tick in ticks {
+                            conflated.insert((tick.topic(), tick.symbol().to_string()), tick);
+                        }
+                        continue;
+                    }
+                    match ticks.len() {
+                        0 => continue,
+                        1 => (FrameKind::Data, options.encode(&ticks[0])),
+                        _ => (FrameKind::Data, options.encode(&TickBatch { ticks })),
                     }
                 }
-                Ok(Message::Ping(payload)) => {
-                    // 响应 ping
-                    if let Err(e) = sender.send(Message::Pong(payload)).await {
-                        tracing::debug!("Failed to send pong: {}", e);
-                        break;
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::debug!("Connection {} lagged, skipped {} messages", connection_id, skipped);
+                    if options.max_rate.is_some() {
+                        continue;
                     }
+                    // 客户端处理不及，改为限频合并
+                    tracing::warn!("Connection {} is too slow, throttling to {}/s", connection_id, SLOW_CLIENT_RATE);
+                    options.max_rate = Some(SLOW_CLIENT_RATE);
+                    flush = flush_timer(options);
+                    (FrameKind::Control, options.encode(&ControlMessage::Format(options)))
                 }
-                Ok(Message::Close(_)) => {
+                Err(RecvError::Closed) => break,
+            },
+            _ = ping.tick() => {
+                app_state.connection_manager.touch(&connection_id);
+                if last_seen.elapsed() > heartbeat.idle_timeout {
+                    tracing::info!("Closing idle connection {}", connection_id);
+                    app_state.connection_manager.record_idle_timeout();
+                    outbox.push(close_frame(close_code::AWAY, "Idle timeout"), FrameKind::Control);
                     break;
                 }
-                Err(e) => {
+                (FrameKind::Control, Ok(Message::Ping(Default::default())))
+            }
+            // 回放按发送队列的空位推进，保持节奏
+            event = async { outbox.ready().await; history.as_mut()?.recv().await }, if history.is_some() => match event {
+                Some(event @ ReplayEvent::Tick { .. }) => (FrameKind::Data, options.encode(&event)),
+                Some(event) => (FrameKind::Control, options.encode(&event)),
+                None => {
+                    history = None;
+                    continue;
+                }
+            },
+            _ = flush.tick(), if options.max_rate.is_some() => {
+                let ticks: Vec<FeedMessage> = std::mem::take(&mut conflated)
+                    .into_values()
+                    .filter(|tick| subscriptions.contains(tick.symbol(), tick.topic()))
+                    .collect();
+                if !send_ticks(&outbox, options, &ticks) {
+                    break;
+                }
+                continue;
+            }
+            msg = stream.next() => match msg.inspect(|msg| if msg.is_ok() { last_seen = Instant::now() }) {
+                Some(Ok(Message::Text(text))) => {
+                    tracing::debug!("Received text message from {}: {}", connection_id, text);
+                    if let Ok(message) = serde_json::from_str::<FormatMessage>(&text) {
+                        let previous_rate = options.max_rate;
+                        options.apply(message);
+                        tracing::info!("Client {} switched to {:?}", connection_id, options);
+                        if options.max_rate != previous_rate {
+                            flush = flush_timer(options);
+                        }
+                        let reply = ControlMessage::Format(options);
+                        if options.max_rate.is_none() && !conflated.is_empty() {
+                            // 取消限频时发出已合并的行情
+                            let ticks: Vec<FeedMessage> = std::mem::take(&mut conflated).into_values().collect();
+                            if !send_encoded(&outbox, options, &reply, FrameKind::Control) || !send_ticks(&outbox, options, &ticks) {
+                                break;
+                            }
+                            continue;
+                        }
+                        (FrameKind::Control, options.encode(&reply))
+                    } else if let Ok(message) = serde_json::from_str::<ReplayMessage>(&text) {
+                        match message {
+                            ReplayMessage::Replay(request) => {
+                                let id = request.id;
+                                match request.validate(&principal.entitlements) {
+                                    Ok(request) => {
+                                        tracing::info!("Client {} started replay {:?}", connection_id, request);
+                                        // 替换正在进行的回放
+                                        history = Some(history::spawn(app_state.data_engine.clone(), request));
+                                        continue;
+                                    }
+                                    Err(message) => (FrameKind::Control, options.encode(&ControlMessage::Error { id, message })),
+                                }
+                            }
+                            ReplayMessage::StopReplay { id } => {
+                                history = None;
+                                (FrameKind::Control, options.encode(&ReplayEvent::Stopped { id }))
+                            }
+                        }
+                    } else {
+                        let before = subscriptions.symbols_for(Topic::Ticker);
+                        let (reply, resume_from) = subscriptions.handle_message(&text);
+                        // 新订阅 ticker 且不需要补发的代码推送快照
+                        let mut added = Vec::new();
+                        if let ControlMessage::Ack { action, symbols, channels, .. } = &reply {
+                            tracing::info!("Client {} {:?}, now subscribed to: {:?}", connection_id, action, channels);
+                            app_state.connection_manager.set_subscriptions(&connection_id, symbols);
+                            if let Some(key) = &session_key {
+                                if let Err(e) = app_state.bus.save_session(key, &subscriptions.session_keys()).await {
+                                    tracing::warn!("Failed to save session of {}: {:#}", connection_id, e);
+                                }
+                            }
+                            added = subscriptions.symbols_for(Topic::Ticker)
+                                .into_iter()
+                                .filter(|symbol| !before.contains(symbol) && !resume_from.contains_key(symbol))
+                                .collect();
+                        }
+                        if resume_from.is_empty() && added.is_empty() {
+                            (FrameKind::Control, options.encode(&reply))
+                        } else {
+                            // 先确认订阅，再推送快照和补发
+                            if !send_encoded(&outbox, options, &reply, FrameKind::Control)
+                                || !send_snapshots(&outbox, options, &app_state.snapshots, &added, &mut replayed)
+                                || !send_replay(&outbox, options, &app_state.replay, &resume_from, &mut replayed)
+                            {
+                                break;
+                            }
+                            continue;
+                        }
+                    }
+                }
+                // 响应 ping
+                Some(Ok(Message::Ping(payload))) => (FrameKind::Control, Ok(Message::Pong(payload))),
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Err(e)) => {
                     tracing::debug!("WebSocket error for {}: {}", connection_id, e);
                     break;
                 }
-                _ => {}
+                Some(Ok(_)) => continue,
+            },
+        };
+
+        let message = match outgoing {
+            Ok(message) => message,
+            Err(e) => {
+                tracing::error!("Failed to encode message: {:#}", e);
+                continue;
             }
+        };
+        if !outbox.push(message, kind) {
+            break;
         }
-    });
-
-    // 等待任一任务完成
-    tokio::select! {
-        _ = send_task => {},
-        _ = receive_task => {},
     }
 
-    // 清理连接
-    app_state.connection_manager.remove_connection(&connection_id);
+    // 写任务发送完已排队的帧 (如关闭帧) 后退出，等待其完成后才从连接表移除，停机时据此判断是否排空
+    outbox.close();
+    if tokio::time::timeout(outbox::WRITE_TIMEOUT, writer).await.is_err() {
+        tracing::debug!("Connection {} did not flush within {:?}", connection_id, outbox::WRITE_TIMEOUT);
+    }
     tracing::info!("WebSocket connection closed: {}", connection_id);
 }
 
-/// 订阅消息
-#[derive(Debug, Deserialize)]
-struct SubscribeMessage {
-    symbols: Vec<String>,
-    action: Option<String>, // "subscribe" or "unsubscribe"
-}
-
-/// 健康检查
-async fn health_check() -> axum::Json<serde_json::Value> {
-    axum::Json(serde_json::json!({
-        "status": "healthy",
+/// 健康检查，有上游断开或长时间没有行情时为 degraded；停机过程中为 draining 并返回 503，负载均衡据此摘除实例
+async fn health_check(State(app_state): State<Arc<AppState>>) -> (StatusCode, axum::Json<serde_json::Value>) {
+    let now = chrono::Utc::now();
+    let upstreams: Vec<_> = app_state.upstreams.iter().map(|tracker| tracker.snapshot()).collect();
+    let healthy = upstreams.iter().all(|upstream| upstream.is_healthy(now));
+    let (code, status) = match (app_state.shutdown.is_draining(), healthy) {
+        (true, _) => (StatusCode::SERVICE_UNAVAILABLE, "draining"),
+        (false, true) => (StatusCode::OK, "healthy"),
+        (false, false) => (StatusCode::OK, "degraded"),
+    };
+    let body = axum::Json(serde_json::json!({
+        "status": status,
+        "upstreams": upstreams,
         "service": "real-time-feed",
         "timestamp": chrono::Utc::now(),
-    }))
+    }));
+    (code, body)
 }
 
 /// 获取服务统计信息
@@ -251,6 +796,14 @@ async fn get_stats(State(app_state): State<Arc<AppState>>) -> axum::Json<serde_j
 
     axum::Json(serde_json::json!({
         "active_connections": connection_count,
+        "subscribers": app_state.connection_manager.subscriber_counts(),
+        "idle_timeouts": app_state.connection_manager.idle_timeouts.load(Ordering::Relaxed),
+        "reaped_connections": app_state.connection_manager.reaped.load(Ordering::Relaxed),
+        "heartbeat": app_state.heartbeat,
+        "send_queues": app_state.queue_stats.to_json(),
+        "archive": app_state.archive.as_ref().map(|(config, stats)| stats.to_json(*config)),
+        "bus": app_state.bus.name(),
+        "upstreams": app_state.upstreams.iter().map(|tracker| tracker.snapshot()).collect::<Vec<_>>(),
         "service": "real-time-feed",
         "timestamp": chrono::Utc::now(),
     }))
@@ -263,12 +816,44 @@ mod tests {
     #[tokio::test]
     async fn test_connection_manager() {
         let manager = ConnectionManager::new();
-        let (tx, _rx) = broadcast::channel(10);
 
-        manager.add_connection("test".to_string(), tx);
-        assert_eq!(manager.get_connection_count(), 1);
+        manager.add_connection("a".to_string());
+        manager.add_connection("b".to_string());
+        assert_eq!(manager.get_connection_count(), 2);
+
+        manager.set_subscriptions("a", &["AAPL".to_string(), "MSFT".to_string()]);
+        manager.set_subscriptions("b", &["AAPL".to_string()]);
+        manager.set_subscriptions("missing", &["TSLA".to_string()]);
+        let counts = manager.subscriber_counts();
+        assert_eq!(counts.get("AAPL"), Some(&2));
+        assert_eq!(counts.get("MSFT"), Some(&1));
+        assert_eq!(counts.get("TSLA"), None);
 
-        manager.remove_connection("test");
+        manager.remove_connection("a");
+        manager.remove_connection("b");
+        assert_eq!(manager.get_connection_count(), 0);
+    }
+
+    #[test]
+    fn test_reap() {
+        let manager = ConnectionManager::new();
+        manager.add_connection("a".to_string());
+        manager.add_connection("b".to_string());
+        assert!(manager.reap(Duration::from_secs(60)).is_empty());
+
+        let mut reaped = manager.reap(Duration::ZERO);
+        reaped.sort();
+        assert_eq!(reaped, vec!["a", "b"]);
+        assert_eq!(manager.get_connection_count(), 0);
+        assert_eq!(manager.reaped.load(Ordering::Relaxed), 2);
+
+        // 任务 panic 时由 guard 移除
+        manager.add_connection("c".to_string());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = ConnectionGuard { manager: &manager, id: "c".to_string() };
+            panic!("connection task failed");
+        }));
+        assert!(result.is_err());
         assert_eq!(manager.get_connection_count(), 0);
     }
 
@@ -276,10 +861,13 @@ mod tests {
     async fn test_real_time_data_serialization() {
         let data = RealTimeData {
             symbol: "AAPL".to_string(),
+            seq: 1,
             price: 150.0,
             volume: 1000,
             change: 1.5,
             change_percent: 1.0,
+            bid: None,
+            ask: None,
             timestamp: chrono::Utc::now(),
         };
 
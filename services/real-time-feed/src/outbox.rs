@@ -0,0 +1,317 @@
+//! 连接的发送队列
+//!
+//! 每个连接的帧先放入有界队列，由单独的写任务发送，转发循环不会因为某个连接的 socket 缓慢而阻塞。
+//! 队列满时按 `overflow_policy` 处理：`drop_oldest` (默认) 丢弃最早的行情帧，并以 `lagged` 消息告知客户端
+//! 丢弃的帧数；`disconnect` 以 1008 关闭连接。控制消息 (确认、快照等) 不会被丢弃。
+//! 队列容量由 `send_queue_capacity` 配置 (默认 1024 帧)，单帧写入超过 [`WRITE_TIMEOUT`] 视为连接已失效
+
+use crate::settings::{positive, Settings};
+use anyhow::anyhow;
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket};
+use futures_util::stream::SplitSink;
+use futures_util::SinkExt;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+/// 单帧写入的最长时间
+pub const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// 队列满时的处理方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    #[default]
+    DropOldest,
+    Disconnect,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+pub struct OutboxConfig {
+    pub capacity: usize,
+    pub policy: OverflowPolicy,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self { capacity: DEFAULT_CAPACITY, policy: OverflowPolicy::default() }
+    }
+}
+
+impl OutboxConfig {
+    pub fn from_settings(settings: &Settings) -> anyhow::Result<Self> {
+        Self::from_vars(settings.send_queue_capacity.as_deref(), settings.overflow_policy.as_deref())
+    }
+
+    fn from_vars(capacity: Option<&str>, policy: Option<&str>) -> anyhow::Result<Self> {
+        let capacity = positive(capacity, "send_queue_capacity", DEFAULT_CAPACITY)?;
+        let policy = match policy.map(|value| value.trim().to_ascii_lowercase()) {
+            Some(value) => serde_json::from_value(serde_json::Value::String(value.clone()))
+                .map_err(|_| anyhow!("Unknown overflow_policy: {}", value))?,
+            None => OverflowPolicy::default(),
+        };
+        Ok(Self { capacity, policy })
+    }
+}
+
+/// 帧的类别，队列满时只丢弃行情帧
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    Data,
+    Control,
+    /// 丢帧通知，发出后才会产生下一条通知
+    LagNotice,
+}
+
+/// 全部连接的队列统计
+#[derive(Debug, Default)]
+pub struct QueueStats {
+    dropped_frames: AtomicU64,
+    overflow_disconnects: AtomicU64,
+    write_timeouts: AtomicU64,
+}
+
+impl QueueStats {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "dropped_frames": self.dropped_frames.load(Ordering::Relaxed),
+            "overflow_disconnects": self.overflow_disconnects.load(Ordering::Relaxed),
+            "write_timeouts": self.write_timeouts.load(Ordering::Relaxed),
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    frames: VecDeque<(Message, FrameKind)>,
+    /// 尚未通知客户端的丢帧数
+    dropped: u64,
+    notice_queued: bool,
+    closed: bool,
+}
+
+/// 一个连接的发送队列
+#[derive(Debug)]
+pub struct Outbox {
+    config: OutboxConfig,
+    queue: Mutex<Queue>,
+    /// 有新帧或队列关闭
+    pushed: Notify,
+    /// 写任务取走了帧
+    popped: Notify,
+    stats: Arc<QueueStats>,
+}
+
+impl Outbox {
+    pub fn new(config: OutboxConfig, stats: Arc<QueueStats>) -> Self {
+        Self {
+            config,
+            queue: Mutex::new(Queue::default()),
+            pushed: Notify::new(),
+            popped: Notify::new(),
+            stats,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 放入一帧，返回 false 表示连接已关闭 (写任务已退出或按策略断开)
+    pub fn push(&self, message: Message, kind: FrameKind) -> bool {
+        let mut queue = self.lock();
+        if queue.closed {
+            return false;
+        }
+        if kind == FrameKind::Data && queue.frames.len() >= self.config.capacity {
+            match self.config.policy {
+                OverflowPolicy::DropOldest => {
+                    // 没有可丢弃的行情帧时 (全是控制消息) 丢弃本帧
+                    match queue.frames.iter().position(|(_, kind)| *kind == FrameKind::Data) {
+                        Some(index) => {
+                            queue.frames.remove(index);
+                        }
+                        None => {
+                            queue.dropped += 1;
+                            self.stats.dropped_frames.fetch_add(1, Ordering::Relaxed);
+                            return true;
+                        }
+                    }
+                    queue.dropped += 1;
+                    self.stats.dropped_frames.fetch_add(1, Ordering::Relaxed);
+                }
+                OverflowPolicy::Disconnect => {
+                    self.stats.overflow_disconnects.fetch_add(1, Ordering::Relaxed);
+                    queue.frames.clear();
+                    let frame = CloseFrame { code: close_code::POLICY, reason: "Send queue overflow".into() };
+                    queue.frames.push_back((Message::Close(Some(frame)), FrameKind::Control));
+                    queue.closed = true;
+                    drop(queue);
+                    self.pushed.notify_one();
+                    return false;
+                }
+            }
+        }
+        if kind == FrameKind::LagNotice {
+            queue.notice_queued = true;
+        }
+        queue.frames.push_back((message, kind));
+        drop(queue);
+        self.pushed.notify_one();
+        true
+    }
+
+    /// 需要通知客户端的丢帧数；已有通知在排队时返回 None，之后的丢帧计入下一条通知
+    pub fn take_lag_notice(&self) -> Option<u64> {
+        let mut queue = self.lock();
+        if queue.dropped == 0 || queue.notice_queued {
+            return None;
+        }
+        Some(std::mem::take(&mut queue.dropped))
+    }
+
+    /// 等待队列有空位，用于需要保持节奏的发送方 (如历史回放)
+    pub async fn ready(&self) {
+        loop {
+            let popped = self.popped.notified();
+            tokio::pin!(popped);
+            popped.as_mut().enable();
+            {
+                let queue = self.lock();
+                if queue.closed || queue.frames.len() < self.config.capacity {
+                    return;
+                }
+            }
+            popped.await;
+        }
+    }
+
+    /// 不再接收新帧，写任务发送完已排队的帧后退出
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.pushed.notify_one();
+    }
+
+    async fn pop(&self) -> Option<Message> {
+        loop {
+            let next = {
+                let mut queue = self.lock();
+                match queue.frames.pop_front() {
+                    Some((message, kind)) => {
+                        if kind == FrameKind::LagNotice {
+                            queue.notice_queued = false;
+                        }
+                        Some(Some(message))
+                    }
+                    None if queue.closed => Some(None),
+                    None => None,
+                }
+            };
+            if let Some(next) = next {
+                self.popped.notify_waiters();
+                return next;
+            }
+            self.pushed.notified().await;
+        }
+    }
+}
+
+/// 启动写任务，发送关闭帧、写入失败或超时后退出并关闭队列；队列关闭后发送完已排队的帧再退出
+pub fn spawn_writer(mut sink: SplitSink<WebSocket, Message>, outbox: Arc<Outbox>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        while let Some(message) = outbox.pop().await {
+            let is_close = matches!(message, Message::Close(_));
+            match tokio::time::timeout(WRITE_TIMEOUT, sink.send(message)).await {
+                Ok(Ok(())) if !is_close => {}
+                Ok(Ok(())) => break,
+                Ok(Err(e)) => {
+                    tracing::debug!("WebSocket write failed: {}", e);
+                    break;
+                }
+                Err(_) => {
+                    outbox.stats.write_timeouts.fetch_add(1, Ordering::Relaxed);
+                    tracing::warn!("WebSocket write timed out after {:?}", WRITE_TIMEOUT);
+                    break;
+                }
+            }
+        }
+        outbox.close();
+        outbox.popped.notify_waiters();
+        let _ = sink.close().await;
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> Message {
+        Message::Text(value.into())
+    }
+
+    fn drain(outbox: &Outbox) -> Vec<Message> {
+        outbox.lock().frames.drain(..).map(|(message, _)| message).collect()
+    }
+
+    #[test]
+    fn test_from_vars() {
+        assert_eq!(OutboxConfig::from_vars(None, None).unwrap(), OutboxConfig::default());
+        let config = OutboxConfig::from_vars(Some("16"), Some(" Disconnect ")).unwrap();
+        assert_eq!((config.capacity, config.policy), (16, OverflowPolicy::Disconnect));
+        assert!(OutboxConfig::from_vars(Some("0"), None).is_err());
+        assert!(OutboxConfig::from_vars(None, Some("drop_newest")).is_err());
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let stats = Arc::new(QueueStats::default());
+        let outbox = Outbox::new(OutboxConfig { capacity: 2, policy: OverflowPolicy::DropOldest }, stats.clone());
+        assert!(outbox.push(text("ack"), FrameKind::Control));
+        assert!(outbox.push(text("1"), FrameKind::Data));
+        assert!(outbox.push(text("2"), FrameKind::Data));
+        assert!(outbox.push(text("3"), FrameKind::Data));
+        assert_eq!(outbox.take_lag_notice(), Some(2));
+        assert!(outbox.push(text("lagged"), FrameKind::LagNotice));
+
+        // 通知发出前的丢帧计入下一条通知
+        assert!(outbox.push(text("4"), FrameKind::Data));
+        assert_eq!(outbox.take_lag_notice(), None);
+        assert_eq!(drain(&outbox), vec![text("ack"), text("lagged"), text("4")]);
+        assert_eq!(stats.dropped_frames.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn test_disconnect() {
+        let stats = Arc::new(QueueStats::default());
+        let outbox = Outbox::new(OutboxConfig { capacity: 1, policy: OverflowPolicy::Disconnect }, stats.clone());
+        assert!(outbox.push(text("1"), FrameKind::Data));
+        // 控制消息不受容量限制
+        assert!(outbox.push(text("ack"), FrameKind::Control));
+        assert!(!outbox.push(text("2"), FrameKind::Data));
+        assert!(!outbox.push(text("ack"), FrameKind::Control));
+        assert!(matches!(drain(&outbox).as_slice(), [Message::Close(Some(frame))] if frame.code == close_code::POLICY));
+        assert_eq!(stats.overflow_disconnects.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pop() {
+        let outbox = Arc::new(Outbox::new(OutboxConfig { capacity: 1, ..OutboxConfig::default() }, Arc::default()));
+        assert!(outbox.push(text("1"), FrameKind::Data));
+        let waiter = tokio::spawn({
+            let outbox = outbox.clone();
+            async move { outbox.ready().await }
+        });
+        assert_eq!(outbox.pop().await, Some(text("1")));
+        waiter.await.unwrap();
+
+        outbox.close();
+        assert!(!outbox.push(text("2"), FrameKind::Data));
+        assert_eq!(outbox.pop().await, None);
+    }
+}
@@ -0,0 +1,131 @@
+//! 断线续传
+//!
+//! 发布方为每个代码的行情分配从 1 递增的序号 (`seq`)，每个实例按代码保留最近 `replay_capacity` 条行情 (默认 [`REPLAY_CAPACITY`])。
+//! 客户端重连后在订阅消息中带上 `resume_from` (代码到最后收到的序号)，服务端补发之后的行情；
+//! 缓冲区中已没有的部分以 `gap` 消息告知，而不是静默丢失
+
+use crate::bus::MessageBus;
+use crate::topic::FeedMessage;
+use crate::RealTimeData;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::error::RecvError;
+
+/// 每个代码默认保留的行情条数
+pub const REPLAY_CAPACITY: usize = 1000;
+
+/// 补发结果
+#[derive(Debug, Default)]
+pub struct Replay {
+    /// 需要补发的行情，按序号排列
+    pub ticks: Vec<RealTimeData>,
+    /// 有行情无法补发时，客户端接下来收到的第一个序号；小于客户端的序号表示行情流已重新编号
+    pub gap: Option<u64>,
+}
+
+/// 按代码保存最近行情的环形缓冲区
+#[derive(Debug)]
+pub struct ReplayBuffer {
+    capacity: usize,
+    streams: Mutex<HashMap<String, VecDeque<RealTimeData>>>,
+}
+
+impl ReplayBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity: capacity.max(1), streams: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn record(&self, data: &RealTimeData) {
+        let mut streams = self.streams.lock().unwrap_or_else(|e| e.into_inner());
+        let stream = streams.entry(data.symbol.clone()).or_default();
+        if stream.len() == self.capacity {
+            stream.pop_front();
+        }
+        stream.push_back(data.clone());
+    }
+
+    /// 序号 `after` 之后的行情
+    pub fn since(&self, symbol: &str, after: u64) -> Replay {
+        let streams = self.streams.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(stream) = streams.get(symbol) else {
+            return Replay::default();
+        };
+        let (Some(oldest), Some(newest)) = (stream.front(), stream.back()) else {
+            return Replay::default();
+        };
+
+        // 客户端的序号比最新的还大，说明发布方重启后重新编号，补发缓冲区中的全部行情
+        if after > newest.seq {
+            return Replay { ticks: stream.iter().cloned().collect(), gap: Some(oldest.seq) };
+        }
+        Replay {
+            ticks: stream.iter().filter(|tick| tick.seq > after).cloned().collect(),
+            gap: (oldest.seq > after + 1).then_some(oldest.seq),
+        }
+    }
+}
+
+/// 在后台把总线上的 ticker 行情记录到缓冲区
+pub fn spawn_recorder(bus: Arc<dyn MessageBus>, buffer: Arc<ReplayBuffer>) {
+    let mut receiver = bus.subscribe();
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(FeedMessage::Ticker(data)) => buffer.record(&data),
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Replay recorder lagged, {} messages will not be replayable", skipped);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(symbol: &str, seq: u64) -> RealTimeData {
+        RealTimeData {
+            symbol: symbol.to_string(),
+            seq,
+            price: 100.0,
+            volume: 1,
+            change: 0.0,
+            change_percent: 0.0,
+            bid: None,
+            ask: None,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    fn seqs(replay: &Replay) -> Vec<u64> {
+        replay.ticks.iter().map(|tick| tick.seq).collect()
+    }
+
+    #[test]
+    fn test_replay() {
+        let buffer = ReplayBuffer::new(3);
+        for seq in 1..=5 {
+            buffer.record(&tick("AAPL", seq));
+        }
+        buffer.record(&tick("MSFT", 1));
+
+        let replay = buffer.since("AAPL", 3);
+        assert_eq!((seqs(&replay), replay.gap), (vec![4, 5], None));
+        let replay = buffer.since("AAPL", 5);
+        assert_eq!((seqs(&replay), replay.gap), (vec![], None));
+
+        // 序号 2 已被挤出缓冲区
+        let replay = buffer.since("AAPL", 1);
+        assert_eq!((seqs(&replay), replay.gap), (vec![3, 4, 5], Some(3)));
+
+        // 发布方重新编号
+        let replay = buffer.since("AAPL", 90);
+        assert_eq!((seqs(&replay), replay.gap), (vec![3, 4, 5], Some(3)));
+
+        assert!(buffer.since("TSLA", 1).ticks.is_empty());
+        assert_eq!(seqs(&buffer.since("MSFT", 0)), vec![1]);
+    }
+}
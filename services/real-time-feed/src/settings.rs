@@ -0,0 +1,264 @@
+//! 服务配置
+//!
+//! 配置按优先级从高到低取自命令行参数、`FEED_` 前缀的环境变量和 `--config` 指定的配置文件 (TOML/YAML/JSON)。
+//! 三者使用同一组键：配置文件中的 `ping_interval_secs` 对应环境变量 `FEED_PING_INTERVAL_SECS`
+//! 和命令行参数 `--ping-interval-secs`，命令行只提供常用的项 (见 [`Args`])。
+//! 代码列表既可以写成逗号分隔的字符串，也可以在配置文件中写成数组。
+//! 认证密钥 (`FEED_API_KEYS`、`FEED_JWT_SECRET`) 和第三方凭证只从环境变量读取
+
+use anyhow::{anyhow, Context};
+use clap::Parser;
+use serde::{Deserialize, Deserializer};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+const DEFAULT_BIND: &str = "0.0.0.0:8082";
+
+/// 实时行情服务配置，未指定的项取环境变量、配置文件或默认值
+#[derive(Parser, Debug, Default)]
+#[command(author, version, about, long_about = None)]
+pub struct Args {
+    /// 配置文件
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// 服务器监听地址，默认 0.0.0.0:8082
+    #[arg(short, long)]
+    pub bind: Option<SocketAddr>,
+
+    /// 日志级别，默认 info
+    #[arg(short, long)]
+    pub log_level: Option<String>,
+
+    /// 上游适配器，逗号分隔；off 表示不接入
+    #[arg(long)]
+    pub upstream: Option<String>,
+
+    /// 模拟行情的代码
+    #[arg(long)]
+    pub simulated_symbols: Option<String>,
+
+    /// Binance 行情的代码
+    #[arg(long)]
+    pub binance_symbols: Option<String>,
+
+    /// Polygon 行情的代码
+    #[arg(long)]
+    pub polygon_symbols: Option<String>,
+
+    /// 模拟行情的步长 (毫秒)
+    #[arg(long)]
+    pub tick_interval_ms: Option<u64>,
+
+    /// 每个代码保留用于断线续传的行情数
+    #[arg(long)]
+    pub replay_capacity: Option<usize>,
+
+    /// 进程内总线的广播容量
+    #[arg(long)]
+    pub bus_capacity: Option<usize>,
+
+    /// 每个连接发送队列的容量
+    #[arg(long)]
+    pub send_queue_capacity: Option<usize>,
+
+    /// 发送队列满时的处理方式：drop_oldest 或 disconnect
+    #[arg(long)]
+    pub overflow_policy: Option<String>,
+
+    /// 心跳间隔 (秒)
+    #[arg(long)]
+    pub ping_interval_secs: Option<u64>,
+
+    /// 空闲超时 (秒)
+    #[arg(long)]
+    pub idle_timeout_secs: Option<u64>,
+
+    /// 停机时等待连接关闭的最长时间 (秒)
+    #[arg(long)]
+    pub drain_timeout_secs: Option<u64>,
+}
+
+impl Args {
+    /// 命令行中指定了的配置项
+    fn overrides(&self) -> Vec<(&'static str, String)> {
+        let Args {
+            config: _,
+            bind,
+            log_level,
+            upstream,
+            simulated_symbols,
+            binance_symbols,
+            polygon_symbols,
+            tick_interval_ms,
+            replay_capacity,
+            bus_capacity,
+            send_queue_capacity,
+            overflow_policy,
+            ping_interval_secs,
+            idle_timeout_secs,
+            drain_timeout_secs,
+        } = self;
+        [
+            ("bind", bind.map(|bind| bind.to_string())),
+            ("log_level", log_level.clone()),
+            ("upstream", upstream.clone()),
+            ("simulated_symbols", simulated_symbols.clone()),
+            ("binance_symbols", binance_symbols.clone()),
+            ("polygon_symbols", polygon_symbols.clone()),
+            ("tick_interval_ms", tick_interval_ms.map(|value| value.to_string())),
+            ("replay_capacity", replay_capacity.map(|value| value.to_string())),
+            ("bus_capacity", bus_capacity.map(|value| value.to_string())),
+            ("send_queue_capacity", send_queue_capacity.map(|value| value.to_string())),
+            ("overflow_policy", overflow_policy.clone()),
+            ("ping_interval_secs", ping_interval_secs.map(|value| value.to_string())),
+            ("idle_timeout_secs", idle_timeout_secs.map(|value| value.to_string())),
+            ("drain_timeout_secs", drain_timeout_secs.map(|value| value.to_string())),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect()
+    }
+}
+
+/// 合并后的配置，保留原始文本，由各模块解析和校验
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    #[serde(deserialize_with = "text")]
+    pub bind: Option<String>,
+    #[serde(deserialize_with = "text")]
+    pub log_level: Option<String>,
+    #[serde(deserialize_with = "text")]
+    pub upstream: Option<String>,
+    #[serde(deserialize_with = "text")]
+    pub simulated_symbols: Option<String>,
+    #[serde(deserialize_with = "text")]
+    pub binance_symbols: Option<String>,
+    #[serde(deserialize_with = "text")]
+    pub polygon_symbols: Option<String>,
+    #[serde(deserialize_with = "text")]
+    pub tick_interval_ms: Option<String>,
+    #[serde(deserialize_with = "text")]
+    pub replay_capacity: Option<String>,
+    #[serde(deserialize_with = "text")]
+    pub bus_capacity: Option<String>,
+    #[serde(deserialize_with = "text")]
+    pub redis_url: Option<String>,
+    #[serde(deserialize_with = "text")]
+    pub redis_channel: Option<String>,
+    #[serde(deserialize_with = "text")]
+    pub send_queue_capacity: Option<String>,
+    #[serde(deserialize_with = "text")]
+    pub overflow_policy: Option<String>,
+    #[serde(deserialize_with = "text")]
+    pub ping_interval_secs: Option<String>,
+    #[serde(deserialize_with = "text")]
+    pub idle_timeout_secs: Option<String>,
+    #[serde(deserialize_with = "text")]
+    pub drain_timeout_secs: Option<String>,
+    #[serde(deserialize_with = "text")]
+    pub archive: Option<String>,
+    #[serde(deserialize_with = "text")]
+    pub archive_batch_size: Option<String>,
+    #[serde(deserialize_with = "text")]
+    pub archive_flush_secs: Option<String>,
+}
+
+impl Settings {
+    /// 按优先级合并命令行参数、环境变量和配置文件
+    pub fn load(args: &Args) -> anyhow::Result<Self> {
+        let mut builder = config::Config::builder();
+        if let Some(path) = &args.config {
+            builder = builder.add_source(config::File::from(path.as_path()));
+        }
+        builder = builder.add_source(config::Environment::with_prefix("FEED"));
+        for (key, value) in args.overrides() {
+            builder = builder.set_override(key, value)?;
+        }
+        let settings = builder.build().context("Failed to load configuration")?;
+        settings.try_deserialize().context("Invalid configuration")
+    }
+
+    pub fn bind(&self) -> anyhow::Result<SocketAddr> {
+        let bind = self.bind.as_deref().unwrap_or(DEFAULT_BIND);
+        bind.trim().parse().with_context(|| format!("Invalid bind address {:?}", bind))
+    }
+
+    pub fn log_level(&self) -> tracing::Level {
+        match self.log_level.as_deref().map(|level| level.trim().to_lowercase()).as_deref() {
+            Some("debug") => tracing::Level::DEBUG,
+            Some("warn") => tracing::Level::WARN,
+            Some("error") => tracing::Level::ERROR,
+            _ => tracing::Level::INFO,
+        }
+    }
+
+    /// 适配器的代码列表
+    pub fn symbols(&self, adapter: &str) -> Option<&str> {
+        match adapter {
+            "simulated" => self.simulated_symbols.as_deref(),
+            "binance" => self.binance_symbols.as_deref(),
+            "polygon" => self.polygon_symbols.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// 解析正整数配置项，未设置时取默认值
+pub fn positive<T>(value: Option<&str>, key: &str, default: T) -> anyhow::Result<T>
+where
+    T: FromStr + PartialOrd + Default,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    let Some(value) = value else {
+        return Ok(default);
+    };
+    let parsed: T = value.trim().parse().with_context(|| format!("Invalid {}", key))?;
+    if parsed <= T::default() {
+        return Err(anyhow!("{} must be positive", key));
+    }
+    Ok(parsed)
+}
+
+/// 配置值统一为文本：数字和布尔转换为字符串，数组以逗号连接
+fn text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    let Some(value) = Option::<config::Value>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    let text = match value.clone().into_array() {
+        Ok(items) => items.into_iter()
+            .map(|item| item.into_string())
+            .collect::<Result<Vec<_>, _>>()
+            .map(|items| items.join(",")),
+        Err(_) => value.into_string(),
+    };
+    text.map(Some).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positive() {
+        assert_eq!(positive::<usize>(None, "replay_capacity", 1000).unwrap(), 1000);
+        assert_eq!(positive::<usize>(Some(" 64 "), "replay_capacity", 1000).unwrap(), 64);
+        assert!(positive::<usize>(Some("0"), "replay_capacity", 1000).is_err());
+        assert!(positive::<u64>(Some("-1"), "tick_interval_ms", 100).is_err());
+    }
+
+    #[test]
+    fn test_settings() {
+        let settings = Settings { bind: Some("127.0.0.1:9000".to_string()), ..Settings::default() };
+        assert_eq!(settings.bind().unwrap(), "127.0.0.1:9000".parse::<SocketAddr>().unwrap());
+        assert_eq!(Settings::default().bind().unwrap().port(), 8082);
+        assert!(Settings { bind: Some("localhost".to_string()), ..Settings::default() }.bind().is_err());
+
+        let args = Args { tick_interval_ms: Some(50), upstream: Some("binance".to_string()), ..Args::default() };
+        let mut overrides = args.overrides();
+        overrides.sort();
+        assert_eq!(overrides, vec![("tick_interval_ms", "50".to_string()), ("upstream", "binance".to_string())]);
+    }
+}
@@ -0,0 +1,134 @@
+//! 优雅停机
+//!
+//! 收到 SIGTERM 或 Ctrl-C 后停止接受新连接，`/ws` 的升级请求返回 503，`/health` 报告 draining；
+//! 已有连接发出已排队的帧后以 1001 和原因关闭。在 `drain_timeout_secs` (默认 30 秒) 内等待连接全部关闭，
+//! 超时后直接退出
+
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+pub const DEFAULT_DRAIN_TIMEOUT_SECS: u64 = 30;
+
+/// 检查连接是否全部关闭的间隔
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 停机状态，开始停机后不可撤销
+#[derive(Debug)]
+pub struct Shutdown {
+    sender: watch::Sender<bool>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self { sender: watch::channel(false).0 }
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 开始停机，通知全部连接
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.sender.subscribe()
+    }
+}
+
+/// 等待开始停机
+pub async fn draining(receiver: &mut watch::Receiver<bool>) {
+    // 发送端随进程存在，出错只可能是正在退出
+    let _ = receiver.wait_for(|draining| *draining).await;
+}
+
+/// 等待 SIGTERM 或 Ctrl-C
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => tracing::info!("Received Ctrl-C"),
+        () = terminate => tracing::info!("Received SIGTERM"),
+    }
+}
+
+/// 等待连接数归零，返回超时时仍未关闭的连接数
+pub async fn drain(connections: impl Fn() -> usize, timeout: Duration) -> usize {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = connections();
+        if remaining == 0 || Instant::now() >= deadline {
+            return remaining;
+        }
+        tokio::time::sleep(DRAIN_POLL_INTERVAL.min(deadline - Instant::now())).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let shutdown = Shutdown::new();
+        let mut receiver = shutdown.subscribe();
+        assert!(!shutdown.is_draining());
+
+        let waiter = tokio::spawn(async move { draining(&mut receiver).await });
+        shutdown.trigger();
+        waiter.await.unwrap();
+        assert!(shutdown.is_draining());
+
+        // 停机后才订阅的连接立即收到通知
+        draining(&mut shutdown.subscribe()).await;
+    }
+
+    #[tokio::test]
+    async fn test_drain() {
+        let connections = Arc::new(AtomicUsize::new(2));
+        let closing = connections.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            closing.store(0, Ordering::Relaxed);
+        });
+        let started = Instant::now();
+        assert_eq!(drain(|| connections.load(Ordering::Relaxed), Duration::from_secs(5)).await, 0);
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        // 超时后返回仍未关闭的连接数
+        connections.store(1, Ordering::Relaxed);
+        let started = Instant::now();
+        assert_eq!(drain(|| connections.load(Ordering::Relaxed), Duration::from_millis(300)).await, 1);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+    }
+}
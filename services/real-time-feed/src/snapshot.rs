@@ -0,0 +1,157 @@
+//! 订阅快照
+//!
+//! 每个实例按代码记录最新行情和当日 (UTC) K 线。客户端订阅代码后先收到该代码的快照，再接收后续行情，
+//! 界面不必等到下一条行情才有数据。快照中的行情带序号，之后序号不大于它的行情不再推送
+
+use crate::bus::MessageBus;
+use crate::topic::FeedMessage;
+use crate::RealTimeData;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::error::RecvError;
+
+/// 当日 K 线
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Candle {
+    /// 当日 0 点 (UTC)
+    pub start: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: u64,
+}
+
+impl Candle {
+    fn open(tick: &RealTimeData) -> Self {
+        Self {
+            start: day_start(tick.timestamp),
+            open: tick.price,
+            high: tick.price,
+            low: tick.price,
+            close: tick.price,
+            volume: tick.volume,
+        }
+    }
+
+    fn update(&mut self, tick: &RealTimeData) {
+        self.high = self.high.max(tick.price);
+        self.low = self.low.min(tick.price);
+        self.close = tick.price;
+        self.volume += tick.volume;
+    }
+}
+
+/// 订阅后先推送的快照
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename = "snapshot")]
+pub struct Snapshot {
+    pub symbol: String,
+    /// 最新行情
+    pub tick: RealTimeData,
+    pub candle: Candle,
+}
+
+/// 按代码保存的最新快照
+#[derive(Debug, Default)]
+pub struct SnapshotStore {
+    snapshots: Mutex<HashMap<String, Snapshot>>,
+}
+
+impl SnapshotStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一条行情，跨日时重新开始 K 线；早于当前 K 线的行情不计入
+    pub fn record(&self, data: &RealTimeData) {
+        let mut snapshots = self.snapshots.lock().unwrap_or_else(|e| e.into_inner());
+        match snapshots.get_mut(&data.symbol) {
+            Some(snapshot) => {
+                let start = day_start(data.timestamp);
+                if start > snapshot.candle.start {
+                    snapshot.candle = Candle::open(data);
+                } else if start == snapshot.candle.start {
+                    snapshot.candle.update(data);
+                }
+                snapshot.tick = data.clone();
+            }
+            None => {
+                let snapshot = Snapshot { symbol: data.symbol.clone(), tick: data.clone(), candle: Candle::open(data) };
+                snapshots.insert(data.symbol.clone(), snapshot);
+            }
+        }
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<Snapshot> {
+        self.snapshots.lock().unwrap_or_else(|e| e.into_inner()).get(symbol).cloned()
+    }
+}
+
+fn day_start(at: DateTime<Utc>) -> DateTime<Utc> {
+    at.date_naive().and_time(chrono::NaiveTime::MIN).and_utc()
+}
+
+/// 在后台把总线上的 ticker 行情记录为快照
+pub fn spawn_recorder(bus: Arc<dyn MessageBus>, store: Arc<SnapshotStore>) {
+    let mut receiver = bus.subscribe();
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(FeedMessage::Ticker(data)) => store.record(&data),
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Snapshot recorder lagged, skipped {} messages", skipped);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn tick(seq: u64, price: f64, at: DateTime<Utc>) -> RealTimeData {
+        RealTimeData {
+            symbol: "AAPL".to_string(),
+            seq,
+            price,
+            volume: 10,
+            change: 0.0,
+            change_percent: 0.0,
+            bid: None,
+            ask: None,
+            timestamp: at,
+        }
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let store = SnapshotStore::new();
+        let day = Utc.with_ymd_and_hms(2024, 3, 1, 14, 30, 0).unwrap();
+        assert!(store.get("AAPL").is_none());
+
+        store.record(&tick(1, 100.0, day));
+        store.record(&tick(2, 103.0, day + chrono::Duration::minutes(1)));
+        store.record(&tick(3, 98.0, day + chrono::Duration::minutes(2)));
+        let snapshot = store.get("AAPL").unwrap();
+        assert_eq!((snapshot.tick.seq, snapshot.tick.price), (3, 98.0));
+        let candle = &snapshot.candle;
+        assert_eq!(candle.start, Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap());
+        assert_eq!((candle.open, candle.high, candle.low, candle.close, candle.volume), (100.0, 103.0, 98.0, 98.0, 30));
+
+        // 跨日重新开始
+        store.record(&tick(4, 99.0, day + chrono::Duration::days(1)));
+        let candle = store.get("AAPL").unwrap().candle;
+        assert_eq!((candle.open, candle.high, candle.low, candle.volume), (99.0, 99.0, 99.0, 10));
+
+        let json = serde_json::to_value(store.get("AAPL").unwrap()).unwrap();
+        assert_eq!(json["type"], "snapshot");
+        assert_eq!(json["tick"]["seq"], 4);
+    }
+}
@@ -0,0 +1,296 @@
+//! 客户端订阅
+//!
+//! 每个连接维护自己订阅的代码及每个代码的主题 (见 [`crate::topic`])，只推送订阅了的消息。
+//! 客户端通过订阅消息增加、删除或整体替换订阅，每条订阅消息都回复确认 (携带生效后的完整订阅) 或错误；
+//! 新连接在订阅前不接收任何消息。订阅消息的 `topics` 缺省为 `ticker`，取消订阅时缺省为全部主题。
+//! 只能订阅认证结果允许的代码。新订阅 `ticker` 的代码先推送快照 (见 [`crate::snapshot`])；
+//! 订阅消息可以带 `resume_from` 请求补发断线期间的 `ticker` 行情 (见 [`crate::replay`])，这些代码不再推送快照
+
+use crate::auth::Entitlements;
+use crate::codec::WireOptions;
+use crate::topic::Topic;
+use alpha_core::utils::symbol::Symbol;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// 单个连接最多订阅的代码数
+pub const MAX_SYMBOLS_PER_CLIENT: usize = 500;
+
+/// 订阅操作
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SubscriptionAction {
+    #[default]
+    Subscribe,
+    Unsubscribe,
+    /// 用消息中的代码替换全部订阅
+    Replace,
+}
+
+/// 客户端发送的订阅消息，`action` 缺省为 subscribe
+#[derive(Debug, Deserialize)]
+pub struct SubscribeMessage {
+    pub symbols: Vec<String>,
+    /// 对 `symbols` 中每个代码生效的主题
+    #[serde(default)]
+    pub topics: Vec<Topic>,
+    #[serde(default)]
+    pub action: SubscriptionAction,
+    /// 客户端请求编号，原样带回回复
+    #[serde(default)]
+    pub id: Option<u64>,
+    /// 代码到客户端最后收到的序号，订阅生效后补发之后的行情
+    #[serde(default)]
+    pub resume_from: BTreeMap<String, u64>,
+}
+
+/// 服务端对订阅消息的回复，以 `type` 区分于行情消息
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlMessage {
+    /// 订阅已生效，`symbols` 为生效后订阅了任一主题的代码，`channels` 为每个代码订阅的主题
+    Ack {
+        id: Option<u64>,
+        action: SubscriptionAction,
+        symbols: Vec<String>,
+        channels: BTreeMap<String, Vec<Topic>>,
+    },
+    /// 消息无效，订阅保持不变
+    Error { id: Option<u64>, message: String },
+    /// 认证通过，`client` 为 API Key 名称或 JWT 的 `sub`
+    Authenticated { client: String },
+    /// 推送选项已修改，本消息起按新选项发送
+    Format(WireOptions),
+    /// 序号 `last_seen` 之后有行情无法补发，接下来从 `next` 开始；`next` 不大于 `last_seen` 表示行情流已重新编号
+    Gap { symbol: String, last_seen: u64, next: u64 },
+    /// 客户端接收不及，发送队列丢弃了 `dropped` 帧行情
+    Lagged { dropped: u64 },
+}
+
+/// 一个连接的订阅，代码统一为大写
+#[derive(Debug, Clone, Default)]
+pub struct Subscriptions {
+    channels: BTreeMap<String, BTreeSet<Topic>>,
+    entitlements: Entitlements,
+}
+
+impl Subscriptions {
+    pub fn new(entitlements: Entitlements) -> Self {
+        Self { channels: BTreeMap::new(), entitlements }
+    }
+
+    pub fn contains(&self, symbol: &str, topic: Topic) -> bool {
+        self.channels.get(symbol).is_some_and(|topics| topics.contains(&topic))
+    }
+
+    /// 订阅了任一主题的代码
+    pub fn symbols(&self) -> Vec<String> {
+        self.channels.keys().cloned().collect()
+    }
+
+    /// 订阅了某个主题的代码
+    pub fn symbols_for(&self, topic: Topic) -> BTreeSet<String> {
+        self.channels.iter()
+            .filter(|(_, topics)| topics.contains(&topic))
+            .map(|(symbol, _)| symbol.clone())
+            .collect()
+    }
+
+    /// 告知客户端当前订阅的确认
+    pub fn ack(&self, id: Option<u64>, action: SubscriptionAction) -> ControlMessage {
+        let channels = self.channels.iter()
+            .map(|(symbol, topics)| (symbol.clone(), topics.iter().copied().collect()))
+            .collect();
+        ControlMessage::Ack { id, action, symbols: self.symbols(), channels }
+    }
+
+    /// 应用订阅操作，`topics` 为空时订阅 `ticker`、取消全部主题；有任一代码无效、无权订阅或超过数量上限时整体不生效
+    pub fn apply(&mut self, action: SubscriptionAction, symbols: &[String], topics: &[Topic]) -> Result<(), String> {
+        let topics: BTreeSet<Topic> = match (topics.is_empty(), action) {
+            (true, SubscriptionAction::Unsubscribe) => Topic::ALL.into_iter().collect(),
+            (true, _) => BTreeSet::from([Topic::Ticker]),
+            (false, _) => topics.iter().copied().collect(),
+        };
+        let mut requested = BTreeMap::new();
+        for symbol in symbols {
+            requested.insert(normalize(symbol)?, topics.clone());
+        }
+        self.apply_channels(action, requested)
+    }
+
+    fn apply_channels(&mut self, action: SubscriptionAction, requested: BTreeMap<String, BTreeSet<Topic>>) -> Result<(), String> {
+        if action != SubscriptionAction::Unsubscribe {
+            if let Some(symbol) = requested.keys().find(|symbol| !self.entitlements.allows(symbol)) {
+                return Err(format!("Not entitled to {}", symbol));
+            }
+        }
+
+        let mut updated = match action {
+            SubscriptionAction::Replace => BTreeMap::new(),
+            _ => self.channels.clone(),
+        };
+        for (symbol, topics) in requested {
+            let entry = updated.entry(symbol).or_default();
+            match action {
+                SubscriptionAction::Unsubscribe => entry.retain(|topic| !topics.contains(topic)),
+                _ => entry.extend(topics),
+            }
+        }
+        updated.retain(|_, topics| !topics.is_empty());
+        if updated.len() > MAX_SYMBOLS_PER_CLIENT {
+            return Err(format!("At most {} symbols can be subscribed", MAX_SYMBOLS_PER_CLIENT));
+        }
+        self.channels = updated;
+        Ok(())
+    }
+
+    /// 保存到会话的订阅：`ticker` 为代码本身，其他主题为 `代码@主题`
+    pub fn session_keys(&self) -> Vec<String> {
+        self.channels.iter()
+            .flat_map(|(symbol, topics)| topics.iter().map(move |topic| match topic {
+                Topic::Ticker => symbol.clone(),
+                topic => format!("{}@{}", symbol, topic.as_str()),
+            }))
+            .collect()
+    }
+
+    /// 以会话保存的订阅替换当前订阅
+    pub fn restore(&mut self, keys: &[String]) -> Result<(), String> {
+        let mut requested: BTreeMap<String, BTreeSet<Topic>> = BTreeMap::new();
+        for key in keys {
+            let (symbol, topic) = match key.split_once('@') {
+                Some((symbol, topic)) => (symbol, Topic::parse(topic).ok_or_else(|| format!("Unknown topic in {}", key))?),
+                None => (key.as_str(), Topic::Ticker),
+            };
+            requested.entry(normalize(symbol)?).or_default().insert(topic);
+        }
+        self.apply_channels(SubscriptionAction::Replace, requested)
+    }
+
+    /// 处理客户端的一条文本消息，返回需要回复的消息和需要补发的起点 (只含已订阅 `ticker` 的代码)
+    pub fn handle_message(&mut self, text: &str) -> (ControlMessage, BTreeMap<String, u64>) {
+        let message = match serde_json::from_str::<SubscribeMessage>(text) {
+            Ok(message) => message,
+            Err(e) => {
+                let error = ControlMessage::Error { id: None, message: format!("Invalid subscription message: {}", e) };
+                return (error, BTreeMap::new());
+            }
+        };
+
+        match self.apply(message.action, &message.symbols, &message.topics) {
+            Ok(()) => {
+                let resume_from = message.resume_from.into_iter()
+                    .filter_map(|(symbol, seq)| Some((normalize(&symbol).ok()?, seq)))
+                    .filter(|(symbol, _)| self.contains(symbol, Topic::Ticker))
+                    .collect();
+                (self.ack(message.id, message.action), resume_from)
+            }
+            Err(error) => (ControlMessage::Error { id: message.id, message: error }, BTreeMap::new()),
+        }
+    }
+}
+
+/// 校验代码并统一为规范写法 (与上游发布行情时使用的代码一致)
+fn normalize(symbol: &str) -> Result<String, String> {
+    Symbol::parse(symbol).map(|symbol| symbol.canonical()).map_err(|e| e.report())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbols(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn ticker_ack(id: Option<u64>, action: SubscriptionAction, list: &[&str]) -> ControlMessage {
+        let channels = list.iter().map(|s| (s.to_string(), vec![Topic::Ticker])).collect();
+        ControlMessage::Ack { id, action, symbols: symbols(list), channels }
+    }
+
+    #[test]
+    fn test_subscribe_unsubscribe_replace() {
+        let mut subscriptions = Subscriptions::default();
+        assert!(!subscriptions.contains("AAPL", Topic::Ticker));
+
+        let (ack, resume_from) = subscriptions.handle_message(r#"{"symbols": ["aapl", " MSFT "]}"#);
+        assert!(resume_from.is_empty());
+        assert_eq!(ack, ticker_ack(None, SubscriptionAction::Subscribe, &["AAPL", "MSFT"]));
+        assert!(subscriptions.contains("AAPL", Topic::Ticker) && !subscriptions.contains("AAPL", Topic::Trades));
+
+        let (ack, _) = subscriptions.handle_message(r#"{"symbols": ["AAPL", "TSLA"], "action": "unsubscribe", "id": 7}"#);
+        assert_eq!(ack, ticker_ack(Some(7), SubscriptionAction::Unsubscribe, &["MSFT"]));
+
+        let (_, resume_from) = subscriptions.handle_message(
+            r#"{"symbols": ["GOOGL", "AMZN"], "action": "replace", "resume_from": {"googl": 42, "TSLA": 7}}"#,
+        );
+        assert_eq!(subscriptions.symbols(), symbols(&["AMZN", "GOOGL"]));
+        assert_eq!(resume_from, BTreeMap::from([("GOOGL".to_string(), 42)]));
+    }
+
+    #[test]
+    fn test_canonical_symbols() {
+        // 上游按规范写法发布行情，任意写法的订阅都落到规范代码上
+        let mut subscriptions = Subscriptions::new(Entitlements::from_symbols(&symbols(&["btc/usdt", "BRK-B"])));
+        let (ack, resume_from) = subscriptions.handle_message(
+            r#"{"symbols": ["BTC/USDT", "brk-b"], "resume_from": {"btc_usdt": 9, "BRK/B": 3}}"#,
+        );
+        assert_eq!(ack, ticker_ack(None, SubscriptionAction::Subscribe, &["BRK.B", "BTC-USDT"]));
+        assert!(subscriptions.contains("BTC-USDT", Topic::Ticker) && subscriptions.contains("BRK.B", Topic::Ticker));
+        assert_eq!(resume_from, BTreeMap::from([("BRK.B".to_string(), 3), ("BTC-USDT".to_string(), 9)]));
+
+        subscriptions.apply(SubscriptionAction::Unsubscribe, &symbols(&["BTC-USDT"]), &[]).unwrap();
+        assert_eq!(subscriptions.symbols(), symbols(&["BRK.B"]));
+    }
+
+    #[test]
+    fn test_rejects_invalid_messages() {
+        let mut subscriptions = Subscriptions::default();
+        subscriptions.apply(SubscriptionAction::Subscribe, &symbols(&["AAPL"]), &[]).unwrap();
+
+        // 任一代码无效时整条消息不生效
+        let (reply, _) = subscriptions.handle_message(r#"{"symbols": ["MSFT", ""], "id": 3}"#);
+        assert!(matches!(reply, ControlMessage::Error { id: Some(3), .. }));
+        assert!(matches!(subscriptions.handle_message(r#"{"symbols": ["MSFT"], "action": "watch"}"#).0, ControlMessage::Error { .. }));
+        assert!(matches!(subscriptions.handle_message("ping").0, ControlMessage::Error { id: None, .. }));
+
+        let mut limited = Subscriptions::new(Entitlements::from_symbols(&symbols(&["AAPL"])));
+        assert!(limited.apply(SubscriptionAction::Replace, &symbols(&["aapl", "MSFT"]), &[]).is_err());
+        limited.apply(SubscriptionAction::Subscribe, &symbols(&["aapl"]), &[]).unwrap();
+        limited.apply(SubscriptionAction::Unsubscribe, &symbols(&["AAPL", "MSFT"]), &[]).unwrap();
+        assert!(limited.symbols().is_empty());
+
+        let many: Vec<String> = (0..=MAX_SYMBOLS_PER_CLIENT).map(|i| format!("S{}", i)).collect();
+        assert!(subscriptions.apply(SubscriptionAction::Replace, &many, &[]).is_err());
+        assert_eq!(subscriptions.symbols(), symbols(&["AAPL"]));
+    }
+
+    #[test]
+    fn test_topics() {
+        let mut subscriptions = Subscriptions::default();
+        let (ack, _) = subscriptions.handle_message(r#"{"symbols": ["AAPL", "MSFT"], "topics": ["trades", "quotes"]}"#);
+        let ControlMessage::Ack { channels, .. } = ack else { panic!("expected ack") };
+        assert_eq!(channels["AAPL"], vec![Topic::Trades, Topic::Quotes]);
+        assert!(subscriptions.symbols_for(Topic::Ticker).is_empty());
+
+        subscriptions.apply(SubscriptionAction::Subscribe, &symbols(&["AAPL"]), &[]).unwrap();
+        subscriptions.apply(SubscriptionAction::Unsubscribe, &symbols(&["MSFT"]), &[Topic::Quotes]).unwrap();
+        assert!(subscriptions.contains("AAPL", Topic::Ticker) && subscriptions.contains("MSFT", Topic::Trades));
+        assert!(!subscriptions.contains("MSFT", Topic::Quotes));
+
+        // 未指定主题时取消该代码的全部主题
+        subscriptions.apply(SubscriptionAction::Unsubscribe, &symbols(&["MSFT"]), &[]).unwrap();
+        assert_eq!(subscriptions.symbols(), symbols(&["AAPL"]));
+        assert!(matches!(subscriptions.handle_message(r#"{"symbols": ["AAPL"], "topics": ["book"]}"#).0, ControlMessage::Error { .. }));
+
+        // 会话保存的订阅可以还原，旧格式的代码视为 ticker
+        let keys = subscriptions.session_keys();
+        assert_eq!(keys, symbols(&["AAPL", "AAPL@trades", "AAPL@quotes"]));
+        let mut restored = Subscriptions::default();
+        restored.restore(&keys).unwrap();
+        assert_eq!(restored.session_keys(), keys);
+        restored.restore(&symbols(&["msft"])).unwrap();
+        assert!(restored.contains("MSFT", Topic::Ticker));
+        assert!(restored.restore(&symbols(&["MSFT@book"])).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+//! 消息主题
+//!
+//! 总线上和推送给客户端的消息按主题区分，每帧以 `topic` 字段标明：
+//! - `ticker`：聚合行情，带序号，支持快照和断线补发 (订阅时未指定主题即为该主题)
+//! - `trades`：逐笔成交
+//! - `quotes`：最优买卖报价
+//! - `depth`：盘口深度 (前若干档的快照)
+//! - `signals`：分析信号，由分析服务发布到总线
+//!
+//! 客户端按代码和主题订阅，只收到订阅了的主题
+
+use crate::RealTimeData;
+use alpha_core::models::{Quote, Signal, Trade};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Topic {
+    Ticker,
+    Trades,
+    Quotes,
+    Depth,
+    Signals,
+}
+
+impl Topic {
+    pub const ALL: [Topic; 5] = [Topic::Ticker, Topic::Trades, Topic::Quotes, Topic::Depth, Topic::Signals];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ticker => "ticker",
+            Self::Trades => "trades",
+            Self::Quotes => "quotes",
+            Self::Depth => "depth",
+            Self::Signals => "signals",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|topic| topic.as_str() == name)
+    }
+}
+
+/// 一档盘口
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct DepthLevel {
+    pub price: f64,
+    pub size: f64,
+}
+
+/// 盘口深度快照，买盘价格从高到低，卖盘从低到高
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DepthUpdate {
+    pub symbol: String,
+    pub timestamp: DateTime<Utc>,
+    pub bids: Vec<DepthLevel>,
+    pub asks: Vec<DepthLevel>,
+}
+
+/// 某个代码的分析信号
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SignalUpdate {
+    pub symbol: String,
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub signal: Signal,
+}
+
+/// 总线上的消息，也是推送给客户端的帧
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "topic", rename_all = "snake_case")]
+pub enum FeedMessage {
+    Ticker(RealTimeData),
+    Trades(Trade),
+    Quotes(Quote),
+    Depth(DepthUpdate),
+    Signals(SignalUpdate),
+}
+
+impl FeedMessage {
+    pub fn topic(&self) -> Topic {
+        match self {
+            Self::Ticker(_) => Topic::Ticker,
+            Self::Trades(_) => Topic::Trades,
+            Self::Quotes(_) => Topic::Quotes,
+            Self::Depth(_) => Topic::Depth,
+            Self::Signals(_) => Topic::Signals,
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        match self {
+            Self::Ticker(data) => &data.symbol,
+            Self::Trades(trade) => &trade.symbol,
+            Self::Quotes(quote) => &quote.symbol,
+            Self::Depth(depth) => &depth.symbol,
+            Self::Signals(signal) => &signal.symbol,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alpha_core::models::{SignalHorizon, SignalType};
+
+    #[test]
+    fn test_topic() {
+        for topic in Topic::ALL {
+            assert_eq!(Topic::parse(topic.as_str()), Some(topic));
+            assert_eq!(serde_json::to_value(topic).unwrap(), topic.as_str());
+        }
+        assert_eq!(Topic::parse("book"), None);
+    }
+
+    #[test]
+    fn test_envelope() {
+        let tick = RealTimeData {
+            symbol: "AAPL".to_string(),
+            seq: 1,
+            price: 150.0,
+            volume: 100,
+            change: 0.0,
+            change_percent: 0.0,
+            bid: None,
+            ask: None,
+            timestamp: Utc::now(),
+        };
+        let json = serde_json::to_value(FeedMessage::Ticker(tick)).unwrap();
+        assert_eq!((json["topic"].as_str(), json["symbol"].as_str(), json["seq"].as_u64()), (Some("ticker"), Some("AAPL"), Some(1)));
+
+        let signal = FeedMessage::Signals(SignalUpdate {
+            symbol: "MSFT".to_string(),
+            timestamp: Utc::now(),
+            signal: Signal::new(SignalType::Buy, 0.8, "RSI", "Oversold", SignalHorizon::ShortTerm),
+        });
+        let json = serde_json::to_string(&signal).unwrap();
+        let parsed: FeedMessage = serde_json::from_str(&json).unwrap();
+        assert_eq!((parsed.topic(), parsed.symbol()), (Topic::Signals, "MSFT"));
+        assert!(json.contains(r#""topic":"signals""#) && json.contains(r#""source_indicator":"RSI""#));
+
+        let depth: FeedMessage = serde_json::from_str(
+            r#"{"topic":"depth","symbol":"BTC-USDT","timestamp":"2024-03-01T00:00:00Z",
+                "bids":[{"price":100.0,"size":2.0}],"asks":[{"price":100.5,"size":1.0}]}"#,
+        )
+        .unwrap();
+        assert_eq!(depth.topic(), Topic::Depth);
+    }
+}
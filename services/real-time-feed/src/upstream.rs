@@ -0,0 +1,392 @@
+//! 上游行情接入
+//!
+//! 每个上游适配器连接一个行情源，把成交和报价统一为核心的 [`Trade`] / [`Quote`] (以及盘口深度)，
+//! 原样发布到 `trades` / `quotes` / `depth` 主题，同时由 [`Normalizer`] 编号并转换为 `ticker` 行情发布到消息总线。适配器断开后按指数退避重连，
+//! 各自的连接状态和最近事件时间由 [`HealthTracker`] 记录，供 `/health` 和 `/stats` 查询。
+//!
+//! 配置 (见 [`crate::settings`])：
+//! - `upstream`：逗号分隔的适配器 `simulated` (默认)、`binance`、`polygon`；`off` 表示不接入，
+//!   多实例部署时只在一个实例上接入
+//! - `<适配器>_symbols`：该适配器订阅的代码，如 `FEED_BINANCE_SYMBOLS=BTC-USDT,ETH-USDT`；
+//!   行情以规范形式的代码发布，适配器负责转换为上游的写法
+//! - `tick_interval_ms`：模拟行情的步长
+//! - `POLYGON_API_KEY` (环境变量)：Polygon 的 API Key
+
+pub mod binance;
+pub mod polygon;
+pub mod simulated;
+
+use crate::bus::MessageBus;
+use crate::settings::Settings;
+use crate::topic::{DepthUpdate, FeedMessage};
+use crate::RealTimeData;
+use alpha_core::models::{Quote, Trade};
+use alpha_core::utils::retry::RetryPolicy;
+use alpha_core::utils::symbol::Symbol;
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// 适配器到发布之间的事件缓冲
+const EVENT_BUFFER: usize = 10_000;
+/// 重连的最长等待
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+/// 超过该时间没有事件视为不健康
+const STALE_AFTER: chrono::Duration = chrono::Duration::seconds(60);
+
+/// 上游事件
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarketEvent {
+    Trade(Trade),
+    Quote(Quote),
+    Depth(DepthUpdate),
+}
+
+impl MarketEvent {
+    /// 价格无效的成交、买卖价倒挂的报价和空盘口不发布
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Self::Trade(trade) => trade.price.is_finite() && trade.price > 0.0 && trade.size >= 0.0,
+            Self::Quote(quote) => quote.is_valid(),
+            Self::Depth(depth) => !(depth.bids.is_empty() && depth.asks.is_empty()),
+        }
+    }
+
+    /// 事件本身对应的主题消息
+    pub fn to_message(&self) -> FeedMessage {
+        match self {
+            Self::Trade(trade) => FeedMessage::Trades(trade.clone()),
+            Self::Quote(quote) => FeedMessage::Quotes(quote.clone()),
+            Self::Depth(depth) => FeedMessage::Depth(depth.clone()),
+        }
+    }
+}
+
+#[async_trait]
+pub trait UpstreamAdapter: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// 连接上游并持续写入事件，直到连接断开 (返回 Ok) 或出错
+    async fn run(&self, symbols: &[String], sink: &EventSink) -> anyhow::Result<()>;
+}
+
+/// 适配器写入事件的出口，同时更新健康状态
+pub struct EventSink {
+    sender: mpsc::Sender<MarketEvent>,
+    health: Arc<HealthTracker>,
+}
+
+impl EventSink {
+    /// 连接 (及认证、订阅) 完成
+    pub fn connected(&self) {
+        self.health.update(|health| {
+            health.status = UpstreamStatus::Connected;
+            health.connected_since = Some(Utc::now());
+        });
+    }
+
+    pub async fn send(&self, event: MarketEvent) -> anyhow::Result<()> {
+        self.health.update(|health| health.last_event_at = Some(Utc::now()));
+        self.sender.send(event).await.map_err(|_| anyhow!("Ingestion pipeline closed"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UpstreamStatus {
+    Connecting,
+    Connected,
+    Disconnected,
+}
+
+/// 一个适配器的健康状态
+#[derive(Debug, Clone, Serialize)]
+pub struct UpstreamHealth {
+    pub adapter: String,
+    pub symbols: Vec<String>,
+    pub status: UpstreamStatus,
+    pub connected_since: Option<DateTime<Utc>>,
+    pub last_event_at: Option<DateTime<Utc>>,
+    /// 累计重连次数
+    pub reconnects: u64,
+    pub last_error: Option<String>,
+}
+
+impl UpstreamHealth {
+    /// 已连接且最近有事件 (刚连接时给出同样长的宽限)
+    pub fn is_healthy(&self, now: DateTime<Utc>) -> bool {
+        let latest = self.last_event_at.max(self.connected_since);
+        self.status == UpstreamStatus::Connected && latest.is_some_and(|at| now - at < STALE_AFTER)
+    }
+}
+
+#[derive(Debug)]
+pub struct HealthTracker(Mutex<UpstreamHealth>);
+
+impl HealthTracker {
+    fn new(adapter: &str, symbols: &[String]) -> Self {
+        Self(Mutex::new(UpstreamHealth {
+            adapter: adapter.to_string(),
+            symbols: symbols.to_vec(),
+            status: UpstreamStatus::Connecting,
+            connected_since: None,
+            last_event_at: None,
+            reconnects: 0,
+            last_error: None,
+        }))
+    }
+
+    pub fn snapshot(&self) -> UpstreamHealth {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut UpstreamHealth)) {
+        f(&mut self.0.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+}
+
+/// 一个适配器及其订阅的代码
+pub struct UpstreamConfig {
+    pub adapter: Box<dyn UpstreamAdapter>,
+    pub symbols: Vec<String>,
+}
+
+/// 按配置创建适配器及其代码
+pub fn from_settings(settings: &Settings) -> anyhow::Result<Vec<UpstreamConfig>> {
+    let names = settings.upstream.as_deref().unwrap_or("simulated");
+    if names.trim().eq_ignore_ascii_case("off") {
+        return Ok(Vec::new());
+    }
+
+    let mut configs = Vec::new();
+    for name in names.split(',').map(|name| name.trim().to_ascii_lowercase()).filter(|name| !name.is_empty()) {
+        let (adapter, defaults): (Box<dyn UpstreamAdapter>, &[&str]) = match name.as_str() {
+            "simulated" => {
+                let step = crate::settings::positive(settings.tick_interval_ms.as_deref(), "tick_interval_ms", simulated::DEFAULT_STEP_MS)?;
+                (Box::new(simulated::SimulatedAdapter::new(Duration::from_millis(step))), simulated::DEFAULT_SYMBOLS)
+            }
+            "binance" => (Box::new(binance::BinanceAdapter::default()), binance::DEFAULT_SYMBOLS),
+            "polygon" => {
+                let api_key = std::env::var("POLYGON_API_KEY").context("POLYGON_API_KEY is required for the polygon upstream")?;
+                (Box::new(polygon::PolygonAdapter::new(api_key)), polygon::DEFAULT_SYMBOLS)
+            }
+            other => return Err(anyhow!("Unknown upstream adapter: {}", other)),
+        };
+        let symbols = match settings.symbols(&name) {
+            Some(list) => parse_symbols(list).with_context(|| format!("Invalid {}_symbols", name))?,
+            None => defaults.iter().map(|symbol| symbol.to_string()).collect(),
+        };
+        configs.push(UpstreamConfig { adapter, symbols });
+    }
+    Ok(configs)
+}
+
+/// 解析逗号分隔的代码，统一为规范形式 (如 `BTC-USDT`)，与客户端订阅的代码一致
+fn parse_symbols(list: &str) -> anyhow::Result<Vec<String>> {
+    let mut symbols = Vec::new();
+    for symbol in list.split(',').map(str::trim).filter(|symbol| !symbol.is_empty()) {
+        symbols.push(Symbol::parse(symbol).map_err(|e| anyhow!(e.report()))?.canonical());
+    }
+    if symbols.is_empty() {
+        return Err(anyhow!("No symbols configured"));
+    }
+    Ok(symbols)
+}
+
+/// 启动全部适配器和发布任务，返回各适配器的健康状态
+pub fn spawn(configs: Vec<UpstreamConfig>, bus: Arc<dyn MessageBus>) -> Vec<Arc<HealthTracker>> {
+    let (sender, mut receiver) = mpsc::channel(EVENT_BUFFER);
+
+    let trackers = configs.into_iter()
+        .map(|UpstreamConfig { adapter, symbols }| {
+            let health = Arc::new(HealthTracker::new(adapter.name(), &symbols));
+            let sink = EventSink { sender: sender.clone(), health: health.clone() };
+            tokio::spawn(supervise(adapter, symbols, sink));
+            health
+        })
+        .collect();
+
+    tokio::spawn(async move {
+        let mut normalizer = Normalizer::default();
+        while let Some(event) = receiver.recv().await {
+            if !event.is_valid() {
+                continue;
+            }
+            let raw = event.to_message();
+            let ticker = normalizer.apply(event).map(FeedMessage::Ticker);
+            for message in std::iter::once(raw).chain(ticker) {
+                if let Err(e) = bus.publish(&message).await {
+                    tracing::warn!("Failed to publish {} message: {:#}", message.topic().as_str(), e);
+                }
+            }
+        }
+    });
+    trackers
+}
+
+/// 运行适配器，断开后按指数退避重连；连上过的断开从 1 秒重新开始退避
+async fn supervise(adapter: Box<dyn UpstreamAdapter>, symbols: Vec<String>, sink: EventSink) {
+    let mut backoff = RetryPolicy::reconnect(MAX_RECONNECT_DELAY).backoffs();
+    loop {
+        sink.health.update(|health| health.status = UpstreamStatus::Connecting);
+        tracing::info!("Connecting to upstream {} for {:?}", adapter.name(), symbols);
+
+        let result = adapter.run(&symbols, &sink).await;
+        let was_connected = sink.health.snapshot().status == UpstreamStatus::Connected;
+        match &result {
+            Ok(()) => tracing::warn!("Upstream {} disconnected", adapter.name()),
+            Err(e) => tracing::warn!("Upstream {} failed: {:#}", adapter.name(), e),
+        }
+        sink.health.update(|health| {
+            health.status = UpstreamStatus::Disconnected;
+            health.reconnects += 1;
+            if let Err(e) = &result {
+                health.last_error = Some(format!("{:#}", e));
+            }
+        });
+
+        if was_connected {
+            backoff.reset();
+        }
+        tokio::time::sleep(backoff.next_delay()).await;
+    }
+}
+
+/// 把上游事件转换为推送的行情
+///
+/// 成交产生行情，报价只更新买卖价，随下一笔成交推送；从未有过成交的代码 (如外汇) 以报价中间价推送，盘口不产生行情。
+/// 涨跌为与该代码上一条行情的差值，序号从 1 递增；小数成交量四舍五入
+#[derive(Debug, Default)]
+pub struct Normalizer {
+    last_price: HashMap<String, f64>,
+    quotes: HashMap<String, Quote>,
+    traded: HashSet<String>,
+    sequences: HashMap<String, u64>,
+}
+
+impl Normalizer {
+    pub fn apply(&mut self, event: MarketEvent) -> Option<RealTimeData> {
+        if !event.is_valid() {
+            return None;
+        }
+        let (symbol, timestamp, price, volume) = match event {
+            MarketEvent::Trade(trade) => {
+                self.traded.insert(trade.symbol.clone());
+                (trade.symbol, trade.timestamp, trade.price, trade.size.round() as u64)
+            }
+            MarketEvent::Depth(_) => return None,
+            MarketEvent::Quote(quote) => {
+                let emit = !self.traded.contains(&quote.symbol);
+                let fields = (quote.symbol.clone(), quote.timestamp, quote.mid(), 0);
+                self.quotes.insert(quote.symbol.clone(), quote);
+                if !emit {
+                    return None;
+                }
+                fields
+            }
+        };
+
+        let previous = self.last_price.insert(symbol.clone(), price).unwrap_or(price);
+        let change = price - previous;
+        let seq = self.sequences.entry(symbol.clone()).or_insert(0);
+        *seq += 1;
+        let quote = self.quotes.get(&symbol);
+        Some(RealTimeData {
+            seq: *seq,
+            price,
+            volume,
+            change,
+            change_percent: change / previous * 100.0,
+            bid: quote.map(|quote| quote.bid),
+            ask: quote.map(|quote| quote.ask),
+            timestamp,
+            symbol,
+        })
+    }
+}
+
+/// 毫秒时间戳转换为时间，超出范围时取当前时间
+pub(crate) fn timestamp_millis(millis: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp_millis(millis).unwrap_or_else(Utc::now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(symbol: &str, price: f64) -> MarketEvent {
+        MarketEvent::Trade(Trade {
+            symbol: symbol.to_string(),
+            timestamp: Utc::now(),
+            price,
+            size: 1.6,
+            trade_id: None,
+        })
+    }
+
+    fn quote(symbol: &str, bid: f64, ask: f64) -> MarketEvent {
+        MarketEvent::Quote(Quote {
+            symbol: symbol.to_string(),
+            timestamp: Utc::now(),
+            bid,
+            bid_size: 1.0,
+            ask,
+            ask_size: 1.0,
+        })
+    }
+
+    #[test]
+    fn test_normalizer() {
+        let mut normalizer = Normalizer::default();
+        let first = normalizer.apply(trade("AAPL", 100.0)).unwrap();
+        assert_eq!((first.seq, first.change, first.volume, first.bid), (1, 0.0, 2, None));
+
+        // 有成交的代码报价只更新买卖价
+        assert!(normalizer.apply(quote("AAPL", 100.5, 101.5)).is_none());
+        let second = normalizer.apply(trade("AAPL", 101.0)).unwrap();
+        assert_eq!((second.seq, second.change, second.bid, second.ask), (2, 1.0, Some(100.5), Some(101.5)));
+        assert!((second.change_percent - 1.0).abs() < 1e-9);
+
+        // 只有报价的代码以中间价推送
+        let fx = normalizer.apply(quote("EURUSD", 1.0, 1.2)).unwrap();
+        assert_eq!((fx.seq, fx.price), (1, 1.1));
+
+        assert!(normalizer.apply(trade("AAPL", f64::NAN)).is_none());
+        assert!(normalizer.apply(quote("EURUSD", 1.2, 1.0)).is_none());
+
+        let depth = MarketEvent::Depth(DepthUpdate {
+            symbol: "AAPL".to_string(),
+            timestamp: Utc::now(),
+            bids: vec![crate::topic::DepthLevel { price: 100.0, size: 5.0 }],
+            asks: Vec::new(),
+        });
+        assert_eq!(depth.to_message().topic(), crate::topic::Topic::Depth);
+        assert!(normalizer.apply(depth).is_none());
+    }
+
+    #[test]
+    fn test_parse_symbols() {
+        assert_eq!(parse_symbols(" btc/usdt, ETH-USDT ,brk-b").unwrap(), vec!["BTC-USDT", "ETH-USDT", "BRK.B"]);
+        assert!(parse_symbols(" , ").is_err());
+        assert!(parse_symbols("AAPL,BTCUSDT").is_err());
+    }
+
+    #[test]
+    fn test_health() {
+        let tracker = HealthTracker::new("binance", &["BTC-USDT".to_string()]);
+        let now = Utc::now();
+        assert!(!tracker.snapshot().is_healthy(now));
+        tracker.update(|health| {
+            health.status = UpstreamStatus::Connected;
+            health.connected_since = Some(now - chrono::Duration::seconds(120));
+        });
+        assert!(!tracker.snapshot().is_healthy(now));
+        tracker.update(|health| health.last_event_at = Some(now));
+        assert!(tracker.snapshot().is_healthy(now));
+    }
+}
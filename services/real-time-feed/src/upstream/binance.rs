@@ -0,0 +1,215 @@
+//! Binance 现货行情
+//!
+//! 通过组合流订阅每个代码的 `@trade` (逐笔成交)、`@bookTicker` (最优报价) 和 `@depth10@100ms` (前 10 档盘口)，无需认证。
+//! Binance 的交易对不带分隔符 (`BTCUSDT`)，收到的行情转换回规范形式 (`BTC-USDT`)。
+//! Binance 每 24 小时断开一次连接，由上层重连
+
+use super::{timestamp_millis, EventSink, MarketEvent, UpstreamAdapter};
+use crate::topic::{DepthLevel, DepthUpdate};
+use alpha_core::models::{Quote, Trade};
+use alpha_core::utils::symbol::Symbol;
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::collections::HashMap;
+use tokio_tungstenite::tungstenite::Message;
+
+pub const DEFAULT_SYMBOLS: &[&str] = &["BTC-USDT", "ETH-USDT"];
+
+const DEFAULT_URL: &str = "wss://stream.binance.com:9443/stream";
+
+#[derive(Debug, Clone)]
+pub struct BinanceAdapter {
+    url: String,
+}
+
+impl Default for BinanceAdapter {
+    fn default() -> Self {
+        Self { url: std::env::var("BINANCE_WS_URL").unwrap_or_else(|_| DEFAULT_URL.to_string()) }
+    }
+}
+
+/// 组合流消息
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    stream: String,
+    data: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct TradeEvent {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "t")]
+    trade_id: u64,
+    #[serde(rename = "p")]
+    price: String,
+    #[serde(rename = "q")]
+    quantity: String,
+    #[serde(rename = "T")]
+    trade_time: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct BookTicker {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "b")]
+    bid: String,
+    #[serde(rename = "B")]
+    bid_size: String,
+    #[serde(rename = "a")]
+    ask: String,
+    #[serde(rename = "A")]
+    ask_size: String,
+}
+
+/// 部分盘口快照，不带代码，需从流名称中取得
+#[derive(Debug, Deserialize)]
+struct PartialDepth {
+    bids: Vec<(String, String)>,
+    asks: Vec<(String, String)>,
+}
+
+/// Binance 的价格和数量以字符串表示
+fn number(value: &str) -> anyhow::Result<f64> {
+    value.parse().with_context(|| format!("Invalid number {:?}", value))
+}
+
+/// Binance 交易对到规范代码的映射
+fn symbol_map(symbols: &[String]) -> anyhow::Result<HashMap<String, String>> {
+    symbols.iter()
+        .map(|symbol| {
+            let parsed = Symbol::parse(symbol).map_err(|e| anyhow!(e.report()))?;
+            let quote = parsed.quote.as_deref().ok_or_else(|| anyhow!("{} is not a crypto pair", symbol))?;
+            Ok((format!("{}{}", parsed.ticker, quote), parsed.canonical()))
+        })
+        .collect()
+}
+
+fn levels(levels: &[(String, String)]) -> anyhow::Result<Vec<DepthLevel>> {
+    levels.iter()
+        .map(|(price, size)| Ok(DepthLevel { price: number(price)?, size: number(size)? }))
+        .collect()
+}
+
+/// 解析一条组合流消息，非成交、报价和盘口的流返回 None；不在映射中的交易对保留原写法
+fn parse_message(text: &str, symbols: &HashMap<String, String>) -> anyhow::Result<Option<MarketEvent>> {
+    let canonical = |symbol: String| {
+        let symbol = symbol.to_ascii_uppercase();
+        symbols.get(&symbol).cloned().unwrap_or(symbol)
+    };
+    let envelope: Envelope = serde_json::from_str(text)?;
+    if envelope.stream.ends_with("@trade") {
+        let trade: TradeEvent = serde_json::from_value(envelope.data)?;
+        return Ok(Some(MarketEvent::Trade(Trade {
+            symbol: canonical(trade.symbol),
+            timestamp: timestamp_millis(trade.trade_time),
+            price: number(&trade.price)?,
+            size: number(&trade.quantity)?,
+            trade_id: Some(trade.trade_id.to_string()),
+        })));
+    }
+    if envelope.stream.ends_with("@bookTicker") {
+        let ticker: BookTicker = serde_json::from_value(envelope.data)?;
+        return Ok(Some(MarketEvent::Quote(Quote {
+            symbol: canonical(ticker.symbol),
+            // bookTicker 不带时间
+            timestamp: chrono::Utc::now(),
+            bid: number(&ticker.bid)?,
+            bid_size: number(&ticker.bid_size)?,
+            ask: number(&ticker.ask)?,
+            ask_size: number(&ticker.ask_size)?,
+        })));
+    }
+    if let Some((symbol, _)) = envelope.stream.split_once("@depth") {
+        let depth: PartialDepth = serde_json::from_value(envelope.data)?;
+        return Ok(Some(MarketEvent::Depth(DepthUpdate {
+            symbol: canonical(symbol.to_string()),
+            // 部分盘口快照不带时间
+            timestamp: chrono::Utc::now(),
+            bids: levels(&depth.bids)?,
+            asks: levels(&depth.asks)?,
+        })));
+    }
+    Ok(None)
+}
+
+#[async_trait]
+impl UpstreamAdapter for BinanceAdapter {
+    fn name(&self) -> &'static str {
+        "binance"
+    }
+
+    async fn run(&self, symbols: &[String], sink: &EventSink) -> anyhow::Result<()> {
+        let symbols = symbol_map(symbols)?;
+        let streams: Vec<String> = symbols.keys()
+            .map(|symbol| symbol.to_ascii_lowercase())
+            .flat_map(|symbol| {
+                [format!("{}@trade", symbol), format!("{}@bookTicker", symbol), format!("{}@depth10@100ms", symbol)]
+            })
+            .collect();
+        let url = format!("{}?streams={}", self.url, streams.join("/"));
+        let (mut socket, _) = tokio_tungstenite::connect_async(url.as_str())
+            .await
+            .context("Failed to connect to Binance")?;
+        sink.connected();
+
+        while let Some(message) = socket.next().await {
+            match message? {
+                Message::Text(text) => match parse_message(&text, &symbols) {
+                    Ok(Some(event)) => sink.send(event).await?,
+                    Ok(None) => {}
+                    Err(e) => tracing::debug!("Ignoring Binance message: {:#}", e),
+                },
+                Message::Ping(payload) => socket.send(Message::Pong(payload)).await?,
+                Message::Close(frame) => {
+                    tracing::info!("Binance closed the connection: {:?}", frame);
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_message() {
+        let symbols = symbol_map(&["BTC-USDT".to_string(), "ETH-USDT".to_string()]).unwrap();
+        assert_eq!(symbols.get("BTCUSDT").map(String::as_str), Some("BTC-USDT"));
+        assert!(symbol_map(&["AAPL".to_string()]).is_err());
+
+        let trade = r#"{"stream":"btcusdt@trade","data":{"e":"trade","E":1700000000001,"s":"BTCUSDT","t":12345,
+            "p":"37000.50","q":"0.0150","T":1700000000000,"m":true,"M":true}}"#;
+        let Some(MarketEvent::Trade(trade)) = parse_message(trade, &symbols).unwrap() else {
+            panic!("expected a trade");
+        };
+        assert_eq!((trade.symbol.as_str(), trade.price, trade.size), ("BTC-USDT", 37000.5, 0.015));
+        assert_eq!(trade.timestamp.timestamp_millis(), 1_700_000_000_000);
+        assert_eq!(trade.trade_id.as_deref(), Some("12345"));
+
+        let ticker = r#"{"stream":"ethusdt@bookTicker","data":{"u":400900217,"s":"ETHUSDT",
+            "b":"2000.10","B":"3.5","a":"2000.20","A":"1.25"}}"#;
+        let Some(MarketEvent::Quote(quote)) = parse_message(ticker, &symbols).unwrap() else {
+            panic!("expected a quote");
+        };
+        assert_eq!((quote.symbol.as_str(), quote.bid, quote.ask, quote.ask_size), ("ETH-USDT", 2000.1, 2000.2, 1.25));
+
+        let depth = r#"{"stream":"btcusdt@depth10@100ms","data":{"lastUpdateId":160,
+            "bids":[["37000.00","1.5"],["36999.50","2"]],"asks":[["37000.50","0.25"]]}}"#;
+        let Some(MarketEvent::Depth(depth)) = parse_message(depth, &symbols).unwrap() else {
+            panic!("expected depth");
+        };
+        assert_eq!((depth.symbol.as_str(), depth.bids.len()), ("BTC-USDT", 2));
+        assert_eq!(depth.asks, vec![DepthLevel { price: 37000.5, size: 0.25 }]);
+
+        assert!(parse_message(r#"{"stream":"btcusdt@aggTrade","data":{}}"#, &symbols).unwrap().is_none());
+        assert!(parse_message(r#"{"stream":"btcusdt@trade","data":{"s":"BTCUSDT"}}"#, &symbols).is_err());
+    }
+}
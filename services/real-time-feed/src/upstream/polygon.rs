@@ -0,0 +1,181 @@
+//! Polygon.io 美股行情
+//!
+//! 连接后先发送 API Key 认证，认证成功再订阅 `T.<代码>` (逐笔成交) 和 `Q.<代码>` (报价)。
+//! 每条消息是事件数组，以 `ev` 区分类型
+
+use super::{timestamp_millis, EventSink, MarketEvent, UpstreamAdapter};
+use alpha_core::models::{Quote, Trade};
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::fmt;
+use tokio_tungstenite::tungstenite::Message;
+
+pub const DEFAULT_SYMBOLS: &[&str] = &["AAPL", "GOOGL", "MSFT", "AMZN", "TSLA"];
+
+const DEFAULT_URL: &str = "wss://socket.polygon.io/stocks";
+
+#[derive(Clone)]
+pub struct PolygonAdapter {
+    url: String,
+    api_key: String,
+}
+
+// 不输出 API Key
+impl fmt::Debug for PolygonAdapter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PolygonAdapter").field("url", &self.url).finish()
+    }
+}
+
+impl PolygonAdapter {
+    pub fn new(api_key: String) -> Self {
+        Self {
+            url: std::env::var("POLYGON_WS_URL").unwrap_or_else(|_| DEFAULT_URL.to_string()),
+            api_key,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "ev")]
+enum PolygonEvent {
+    #[serde(rename = "status")]
+    Status {
+        status: String,
+        #[serde(default)]
+        message: String,
+    },
+    #[serde(rename = "T")]
+    Trade {
+        sym: String,
+        p: f64,
+        s: f64,
+        /// 毫秒时间戳
+        t: i64,
+        #[serde(default)]
+        i: Option<String>,
+    },
+    #[serde(rename = "Q")]
+    Quote {
+        sym: String,
+        bp: f64,
+        bs: f64,
+        ap: f64,
+        #[serde(rename = "as")]
+        ask_size: f64,
+        t: i64,
+    },
+    #[serde(other)]
+    Other,
+}
+
+impl PolygonEvent {
+    fn into_market_event(self) -> Option<MarketEvent> {
+        match self {
+            Self::Trade { sym, p, s, t, i } => Some(MarketEvent::Trade(Trade {
+                symbol: sym,
+                timestamp: timestamp_millis(t),
+                price: p,
+                size: s,
+                trade_id: i,
+            })),
+            Self::Quote { sym, bp, bs, ap, ask_size, t } => Some(MarketEvent::Quote(Quote {
+                symbol: sym,
+                timestamp: timestamp_millis(t),
+                bid: bp,
+                bid_size: bs,
+                ask: ap,
+                ask_size,
+            })),
+            Self::Status { .. } | Self::Other => None,
+        }
+    }
+}
+
+#[async_trait]
+impl UpstreamAdapter for PolygonAdapter {
+    fn name(&self) -> &'static str {
+        "polygon"
+    }
+
+    async fn run(&self, symbols: &[String], sink: &EventSink) -> anyhow::Result<()> {
+        let (mut socket, _) = tokio_tungstenite::connect_async(self.url.as_str())
+            .await
+            .context("Failed to connect to Polygon")?;
+        let auth = serde_json::json!({ "action": "auth", "params": self.api_key });
+        socket.send(Message::Text(auth.to_string())).await?;
+
+        while let Some(message) = socket.next().await {
+            let text = match message? {
+                Message::Text(text) => text,
+                Message::Ping(payload) => {
+                    socket.send(Message::Pong(payload)).await?;
+                    continue;
+                }
+                Message::Close(frame) => {
+                    tracing::info!("Polygon closed the connection: {:?}", frame);
+                    return Ok(());
+                }
+                _ => continue,
+            };
+            let events: Vec<PolygonEvent> = match serde_json::from_str(&text) {
+                Ok(events) => events,
+                Err(e) => {
+                    tracing::debug!("Ignoring Polygon message: {}", e);
+                    continue;
+                }
+            };
+
+            for event in events {
+                if let PolygonEvent::Status { status, message } = &event {
+                    match status.as_str() {
+                        "auth_success" => {
+                            let params: Vec<String> = symbols.iter()
+                                .flat_map(|symbol| [format!("T.{}", symbol), format!("Q.{}", symbol)])
+                                .collect();
+                            let subscribe = serde_json::json!({ "action": "subscribe", "params": params.join(",") });
+                            socket.send(Message::Text(subscribe.to_string())).await?;
+                            sink.connected();
+                        }
+                        "auth_failed" => return Err(anyhow!("Polygon authentication failed: {}", message)),
+                        _ => tracing::debug!("Polygon status {}: {}", status, message),
+                    }
+                    continue;
+                }
+                if let Some(event) = event.into_market_event() {
+                    sink.send(event).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_events() {
+        let text = r#"[
+            {"ev":"T","sym":"AAPL","x":4,"i":"52983525029461","z":3,"p":189.5,"s":100,"c":[0],"t":1700000000000,"q":1},
+            {"ev":"Q","sym":"MSFT","bx":4,"ax":7,"bp":370.1,"ap":370.2,"bs":2,"as":5,"t":1700000000500,"q":7},
+            {"ev":"status","status":"auth_success","message":"authenticated"},
+            {"ev":"A","sym":"AAPL"}
+        ]"#;
+        let events: Vec<PolygonEvent> = serde_json::from_str(text).unwrap();
+        assert_eq!(events.len(), 4);
+        assert!(matches!(&events[2], PolygonEvent::Status { status, .. } if status == "auth_success"));
+
+        let events: Vec<MarketEvent> = events.into_iter().filter_map(PolygonEvent::into_market_event).collect();
+        let [MarketEvent::Trade(trade), MarketEvent::Quote(quote)] = events.as_slice() else {
+            panic!("unexpected events {:?}", events);
+        };
+        assert_eq!((trade.symbol.as_str(), trade.price, trade.size), ("AAPL", 189.5, 100.0));
+        assert_eq!(trade.trade_id.as_deref(), Some("52983525029461"));
+        assert_eq!((quote.bid, quote.ask, quote.ask_size), (370.1, 370.2, 5.0));
+        assert_eq!(quote.timestamp.timestamp_millis(), 1_700_000_000_500);
+    }
+}
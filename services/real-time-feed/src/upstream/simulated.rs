@@ -0,0 +1,78 @@
+//! 模拟行情源，用于开发和演示
+//!
+//! 每个代码一条几何布朗运动价格路径，每个步长 (默认 100ms) 产生一组报价和成交
+
+use super::{EventSink, MarketEvent, UpstreamAdapter};
+use alpha_core::models::{Quote, Trade};
+use alpha_core::simulate::{MarketSimulator, PriceProcess, SimulationConfig, TRADING_DAYS_PER_YEAR};
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::time::{interval, MissedTickBehavior};
+
+pub const DEFAULT_SYMBOLS: &[&str] = &["AAPL", "GOOGL", "MSFT", "AMZN", "TSLA"];
+
+/// 默认步长 (毫秒)
+pub const DEFAULT_STEP_MS: u64 = 100;
+
+#[derive(Debug)]
+pub struct SimulatedAdapter {
+    step: Duration,
+}
+
+impl SimulatedAdapter {
+    pub fn new(step: Duration) -> Self {
+        Self { step }
+    }
+}
+
+#[async_trait]
+impl UpstreamAdapter for SimulatedAdapter {
+    fn name(&self) -> &'static str {
+        "simulated"
+    }
+
+    async fn run(&self, symbols: &[String], sink: &EventSink) -> anyhow::Result<()> {
+        let mut simulators = symbols.iter()
+            .enumerate()
+            .map(|(i, symbol)| {
+                let config = SimulationConfig::new(
+                    PriceProcess::Gbm { drift: 0.05, volatility: 0.4 },
+                    100.0 + i as f64 * 150.0,
+                    i as u64,
+                )
+                // 每个步长按一年 252 个 6.5 小时交易日折算
+                .with_dt(self.step.as_secs_f64() / (TRADING_DAYS_PER_YEAR * 6.5 * 3600.0));
+                Ok((symbol.clone(), MarketSimulator::new(config).map_err(|e| anyhow::anyhow!(e.report()))?))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        sink.connected();
+
+        let mut timer = interval(self.step);
+        timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            timer.tick().await;
+            for (symbol, simulator) in &mut simulators {
+                let tick = simulator.next_tick(symbol, chrono::Utc::now());
+                if let (Some(bid), Some(ask)) = (tick.bid, tick.ask) {
+                    let quote = Quote {
+                        symbol: symbol.clone(),
+                        timestamp: tick.timestamp,
+                        bid,
+                        bid_size: 100.0,
+                        ask,
+                        ask_size: 100.0,
+                    };
+                    sink.send(MarketEvent::Quote(quote)).await?;
+                }
+                let trade = Trade {
+                    symbol: symbol.clone(),
+                    timestamp: tick.timestamp,
+                    price: tick.price,
+                    size: tick.volume as f64,
+                    trade_id: None,
+                };
+                sink.send(MarketEvent::Trade(trade)).await?;
+            }
+        }
+    }
+}
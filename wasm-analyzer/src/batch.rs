@@ -0,0 +1,160 @@
+//! 多标的批量分析
+//!
+//! 一次跨越 JS/WASM 边界完成所有标的的分析，输入和输出各只做一次整体转换
+
+use alpha_core::analytics::AnalysisEngine;
+use alpha_core::models::{AnalysisResult, MarketData};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::convert::{from_js, set, to_js};
+use crate::error::{invalid_input, js_error, ErrorInfo};
+use crate::jobs::CancelToken;
+use crate::options::PriceSource;
+use crate::types::{BatchDataJs, BatchOptionsJs, BatchResultJs};
+use crate::{analyze_with, WasmAnalyzer};
+
+/// 每个标的的行情数据：与 `symbols` 顺序对应的数组，或以代码为键的对象
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BatchData {
+    Aligned(Vec<Vec<MarketData>>),
+    Keyed(BTreeMap<String, Vec<MarketData>>),
+}
+
+/// 批量分析选项
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct BatchOptions {
+    /// 遇到第一个失败的标的即终止并抛出错误 (默认记录错误后继续)
+    pub fail_fast: bool,
+    /// 每完成一个标的调用一次的进度回调，参数为 `{ completed, total, percent, symbol }`
+    #[serde(skip)]
+    pub on_progress: Option<js_sys::Function>,
+}
+
+impl BatchOptions {
+    /// 报告进度，回调抛出的异常不影响分析本身
+    fn report_progress(&self, completed: usize, total: usize, symbol: &str) {
+        let Some(callback) = &self.on_progress else {
+            return;
+        };
+
+        let percent = if total == 0 { 100.0 } else { completed as f64 * 100.0 / total as f64 };
+        let progress = js_sys::Object::new();
+        let _ = set(&progress, "completed", JsValue::from(completed as u32))
+            .and_then(|_| set(&progress, "total", JsValue::from(total as u32)))
+            .and_then(|_| set(&progress, "percent", JsValue::from(percent)))
+            .and_then(|_| set(&progress, "symbol", JsValue::from_str(symbol)))
+            .and_then(|_| callback.call1(&JsValue::NULL, &progress));
+    }
+}
+
+/// 批量分析结果，成功和失败的标的分开记录
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct BatchResult {
+    pub results: BTreeMap<String, AnalysisResult>,
+    pub errors: BTreeMap<String, ErrorInfo>,
+}
+
+/// 待分析的 (代码, 行情) 列表
+pub(crate) type BatchJobs = Vec<(String, Vec<MarketData>)>;
+
+impl BatchData {
+    fn into_jobs(self, symbols: Vec<String>) -> Result<BatchJobs, JsValue> {
+        match self {
+            BatchData::Aligned(data) => {
+                if data.len() != symbols.len() {
+                    return Err(invalid_input(format!(
+                        "标的数量 ({}) 与数据组数 ({}) 不一致",
+                        symbols.len(),
+                        data.len()
+                    )));
+                }
+                Ok(symbols.into_iter().zip(data).collect())
+            }
+            BatchData::Keyed(mut data) => Ok(symbols
+                .into_iter()
+                .map(|symbol| {
+                    let series = data.remove(&symbol).unwrap_or_default();
+                    (symbol, series)
+                })
+                .collect()),
+        }
+    }
+}
+
+/// 解析批量分析参数，并按分析器的价格来源处理行情
+pub(crate) fn parse_batch(
+    symbols: Vec<String>,
+    data_per_symbol: &JsValue,
+    options: &JsValue,
+    price_source: PriceSource,
+) -> Result<(BatchJobs, BatchOptions), JsValue> {
+    let options = if options.is_undefined() || options.is_null() {
+        BatchOptions::default()
+    } else {
+        // 函数无法经 serde 反序列化，单独读取回调
+        let on_progress = js_sys::Reflect::get(options, &JsValue::from_str("on_progress"))?
+            .dyn_into::<js_sys::Function>()
+            .ok();
+        BatchOptions { on_progress, ..from_js(options)? }
+    };
+    let mut jobs = from_js::<BatchData>(data_per_symbol)?.into_jobs(symbols)?;
+    for (_, market_data) in &mut jobs {
+        price_source.apply(market_data);
+    }
+    Ok((jobs, options))
+}
+
+/// 依次分析各标的；传入取消令牌时每个标的之间为安全点，可在此让出事件循环并响应取消
+pub(crate) async fn run_batch(
+    engine: &AnalysisEngine,
+    jobs: BatchJobs,
+    options: &BatchOptions,
+    cancel: Option<&CancelToken>,
+) -> Result<BatchResult, JsValue> {
+    let mut batch = BatchResult::default();
+    let total = jobs.len();
+
+    for (index, (symbol, market_data)) in jobs.into_iter().enumerate() {
+        if let Some(token) = cancel {
+            token.checkpoint().await?;
+        }
+
+        match analyze_with(engine, &market_data).await {
+            Ok(result) => {
+                batch.results.insert(symbol.clone(), result);
+            }
+            Err(err) if options.fail_fast => {
+                return Err(js_error(err.context(symbol)));
+            }
+            Err(err) => {
+                batch.errors.insert(symbol.clone(), ErrorInfo::from(&err));
+            }
+        }
+
+        options.report_progress(index + 1, total, &symbol);
+    }
+
+    Ok(batch)
+}
+
+#[wasm_bindgen]
+impl WasmAnalyzer {
+    /// 批量分析多个标的，返回 `{ results, errors }`，键为股票代码
+    #[wasm_bindgen(js_name = analyzeBatch)]
+    pub async fn analyze_batch(
+        &self,
+        symbols: Vec<String>,
+        data_per_symbol: &BatchDataJs,
+        options: &BatchOptionsJs,
+    ) -> Result<BatchResultJs, JsValue> {
+        let (jobs, options) = parse_batch(symbols, data_per_symbol, options, self.options.price_source)?;
+        let batch = run_batch(&self.engine, jobs, &options, None).await?;
+
+        to_js(&batch).map(JsCast::unchecked_into)
+    }
+}
@@ -0,0 +1,126 @@
+//! 复用缓冲区的指标计算
+//!
+//! 仪表盘每个 tick 都会重新计算指标，默认接口每次都要分配新的 `Vec` 和 `Float64Array`。
+//! `*Into` 系列方法把输入拷入分析器内部复用的缓冲区，计算后写回调用方提供的 `Float64Array`，
+//! 稳定运行时 JS 堆和 WASM 堆都不再产生新的分配
+
+use js_sys::Float64Array;
+use wasm_bindgen::prelude::*;
+
+use crate::error::invalid_input;
+use crate::WasmAnalyzer;
+
+/// 分析器内部复用的缓冲区，容量只增不减，直到调用 `releaseBuffers`
+#[derive(Debug, Default)]
+pub(crate) struct BufferPool {
+    input: Vec<f64>,
+    outputs: [Vec<f64>; 3],
+}
+
+impl BufferPool {
+    /// 把 JS 输入拷入输入缓冲区，返回输入和输出缓冲区
+    fn prepare(&mut self, prices: &Float64Array) -> (&[f64], &mut [Vec<f64>; 3]) {
+        self.input.resize(prices.length() as usize, 0.0);
+        prices.copy_to(&mut self.input);
+        (&self.input, &mut self.outputs)
+    }
+}
+
+/// 输出数组长度必须与输入一致
+fn check_len(prices: &Float64Array, outputs: &[&Float64Array]) -> Result<(), JsValue> {
+    if outputs.iter().any(|out| out.length() != prices.length()) {
+        return Err(invalid_input(format!("输出数组长度必须与输入一致 ({})", prices.length())));
+    }
+    Ok(())
+}
+
+#[wasm_bindgen]
+impl WasmAnalyzer {
+    /// 计算 RSI 并写入 `out`
+    #[wasm_bindgen(js_name = calculateRSIInto)]
+    pub fn calculate_rsi_into(&self, prices: &Float64Array, period: usize, out: &Float64Array) -> Result<(), JsValue> {
+        check_len(prices, &[out])?;
+        let mut pool = self.buffers.borrow_mut();
+        let (input, outputs) = pool.prepare(prices);
+
+        self.indicators.calculate_rsi_into(input, period, &mut outputs[0]);
+        out.copy_from(&outputs[0]);
+        Ok(())
+    }
+
+    /// 计算 SMA 并写入 `out`
+    #[wasm_bindgen(js_name = calculateSMAInto)]
+    pub fn calculate_sma_into(&self, prices: &Float64Array, period: usize, out: &Float64Array) -> Result<(), JsValue> {
+        check_len(prices, &[out])?;
+        let mut pool = self.buffers.borrow_mut();
+        let (input, outputs) = pool.prepare(prices);
+
+        self.indicators.calculate_sma_into(input, period, &mut outputs[0]);
+        out.copy_from(&outputs[0]);
+        Ok(())
+    }
+
+    /// 计算 EMA 并写入 `out`
+    #[wasm_bindgen(js_name = calculateEMAInto)]
+    pub fn calculate_ema_into(&self, prices: &Float64Array, period: usize, out: &Float64Array) -> Result<(), JsValue> {
+        check_len(prices, &[out])?;
+        let mut pool = self.buffers.borrow_mut();
+        let (input, outputs) = pool.prepare(prices);
+
+        self.indicators.calculate_ema_into(input, period, &mut outputs[0]);
+        out.copy_from(&outputs[0]);
+        Ok(())
+    }
+
+    /// 计算布林带并写入 `upper` / `middle` / `lower`
+    #[wasm_bindgen(js_name = calculateBollingerBandsInto)]
+    pub fn calculate_bollinger_bands_into(
+        &self,
+        prices: &Float64Array,
+        period: usize,
+        std_dev: f64,
+        upper: &Float64Array,
+        middle: &Float64Array,
+        lower: &Float64Array,
+    ) -> Result<(), JsValue> {
+        check_len(prices, &[upper, middle, lower])?;
+        let mut pool = self.buffers.borrow_mut();
+        let (input, [upper_buf, middle_buf, lower_buf]) = pool.prepare(prices);
+
+        self.indicators.calculate_bollinger_bands_into(input, period, std_dev, upper_buf, middle_buf, lower_buf);
+        upper.copy_from(upper_buf);
+        middle.copy_from(middle_buf);
+        lower.copy_from(lower_buf);
+        Ok(())
+    }
+
+    /// 计算 MACD 并写入 `macd` / `signal` / `histogram`
+    #[wasm_bindgen(js_name = calculateMACDInto)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn calculate_macd_into(
+        &self,
+        prices: &Float64Array,
+        fast_period: usize,
+        slow_period: usize,
+        signal_period: usize,
+        macd: &Float64Array,
+        signal: &Float64Array,
+        histogram: &Float64Array,
+    ) -> Result<(), JsValue> {
+        check_len(prices, &[macd, signal, histogram])?;
+        let mut pool = self.buffers.borrow_mut();
+        let (input, [macd_buf, signal_buf, histogram_buf]) = pool.prepare(prices);
+
+        self.indicators.calculate_macd_into(input, fast_period, slow_period, signal_period, macd_buf, signal_buf, histogram_buf);
+        macd.copy_from(macd_buf);
+        signal.copy_from(signal_buf);
+        histogram.copy_from(histogram_buf);
+        Ok(())
+    }
+
+    /// 释放内部缓冲区占用的内存 (如切换到更短的序列后)
+    #[wasm_bindgen(js_name = releaseBuffers)]
+    pub fn release_buffers(&self) {
+        *self.buffers.borrow_mut() = BufferPool::default();
+    }
+}
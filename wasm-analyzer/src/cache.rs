@@ -0,0 +1,163 @@
+//! 基于 IndexedDB 的行情缓存
+//!
+//! 每个标的一条记录，值为按时间排序的行情数组。新下载的数据按时间戳与缓存合并 (新数据覆盖旧数据)，
+//! 再次访问同一标的时只需下载增量部分。缓存为可选项，需显式 `attachCache` 到分析器
+
+use alpha_core::errors::AlphaError;
+use alpha_core::models::MarketData;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{IdbDatabase, IdbObjectStore, IdbRequest, IdbTransactionMode};
+
+use crate::convert::{from_js, to_js};
+use crate::error::js_error;
+use crate::types::MarketDataArray;
+use crate::WasmAnalyzer;
+
+const DEFAULT_DB_NAME: &str = "alpha-market-data";
+const DB_VERSION: u32 = 1;
+const STORE_NAME: &str = "candles";
+
+/// 每个标的最多保留的记录数，超出时丢弃最早的数据
+const MAX_RECORDS_PER_SYMBOL: usize = 20_000;
+
+/// IndexedDB 行情缓存 (在窗口和 Worker 中均可使用)
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct MarketDataCache {
+    db: IdbDatabase,
+}
+
+#[wasm_bindgen]
+impl MarketDataCache {
+    /// 打开 (必要时创建) 缓存数据库，`dbName` 默认为 `alpha-market-data`
+    pub async fn open(db_name: Option<String>) -> Result<MarketDataCache, JsValue> {
+        let factory = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("indexedDB"))?
+            .dyn_into::<web_sys::IdbFactory>()
+            .map_err(|_| js_error(AlphaError::PlatformError("当前环境不支持 IndexedDB".to_string())))?;
+
+        let request = factory.open_with_u32(db_name.as_deref().unwrap_or(DEFAULT_DB_NAME), DB_VERSION)?;
+        let upgrade_request = request.clone();
+        let on_upgrade = Closure::<dyn FnMut()>::new(move || {
+            if let Some(db) = upgrade_request.result().ok().and_then(|db| db.dyn_into::<IdbDatabase>().ok()) {
+                let _ = db.create_object_store(STORE_NAME);
+            }
+        });
+        request.set_onupgradeneeded(Some(on_upgrade.as_ref().unchecked_ref()));
+
+        // 闭包需存活到打开完成
+        let result = await_request(&request).await;
+        request.set_onupgradeneeded(None);
+        drop(on_upgrade);
+
+        Ok(MarketDataCache { db: result?.dyn_into()? })
+    }
+
+    /// 读取缓存的行情，未缓存时返回空数组
+    pub async fn load(&self, symbol: &str) -> Result<MarketDataArray, JsValue> {
+        to_js(&self.read(symbol).await?).map(JsCast::unchecked_into)
+    }
+
+    /// 将行情合并写入缓存，返回合并后的记录数
+    pub async fn store(&self, symbol: &str, data: &MarketDataArray) -> Result<usize, JsValue> {
+        let incoming: Vec<MarketData> = from_js(data)?;
+        Ok(self.merge(symbol, incoming).await?.len())
+    }
+
+    /// 删除单个标的的缓存
+    pub async fn remove(&self, symbol: &str) -> Result<(), JsValue> {
+        let request = self.object_store(IdbTransactionMode::Readwrite)?.delete(&JsValue::from_str(symbol))?;
+        await_request(&request).await.map(|_| ())
+    }
+
+    /// 清空全部缓存
+    pub async fn clear(&self) -> Result<(), JsValue> {
+        let request = self.object_store(IdbTransactionMode::Readwrite)?.clear()?;
+        await_request(&request).await.map(|_| ())
+    }
+
+    /// 已缓存的标的列表
+    pub async fn symbols(&self) -> Result<Vec<String>, JsValue> {
+        let request = self.object_store(IdbTransactionMode::Readonly)?.get_all_keys()?;
+        from_js(&await_request(&request).await?)
+    }
+}
+
+impl MarketDataCache {
+    async fn read(&self, symbol: &str) -> Result<Vec<MarketData>, JsValue> {
+        let request = self.object_store(IdbTransactionMode::Readonly)?.get(&JsValue::from_str(symbol))?;
+        let value = await_request(&request).await?;
+        if value.is_undefined() {
+            Ok(Vec::new())
+        } else {
+            from_js(&value)
+        }
+    }
+
+    /// 合并缓存与新数据并写回，返回按时间排序的完整序列
+    pub(crate) async fn merge(&self, symbol: &str, incoming: Vec<MarketData>) -> Result<Vec<MarketData>, JsValue> {
+        let cached = self.read(symbol).await?;
+        if incoming.is_empty() {
+            return Ok(cached);
+        }
+
+        let mut by_time: BTreeMap<DateTime<Utc>, MarketData> = cached
+            .into_iter()
+            .map(|data| (data.timestamp, data))
+            .collect();
+        for data in incoming {
+            by_time.insert(data.timestamp, data);
+        }
+
+        let skip = by_time.len().saturating_sub(MAX_RECORDS_PER_SYMBOL);
+        let merged: Vec<MarketData> = by_time.into_values().skip(skip).collect();
+
+        let request = self.object_store(IdbTransactionMode::Readwrite)?
+            .put_with_key(&to_js(&merged)?, &JsValue::from_str(symbol))?;
+        await_request(&request).await?;
+
+        Ok(merged)
+    }
+
+    fn object_store(&self, mode: IdbTransactionMode) -> Result<IdbObjectStore, JsValue> {
+        self.db.transaction_with_str_and_mode(STORE_NAME, mode)?.object_store(STORE_NAME)
+    }
+}
+
+/// 等待 IndexedDB 请求完成并返回结果
+async fn await_request(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+    let outcome = JsFuture::from(promise).await;
+    request.set_onsuccess(None);
+    request.set_onerror(None);
+
+    outcome.map_err(|_| {
+        let message = match request.error() {
+            Ok(Some(err)) => format!("IndexedDB 请求失败: {}", err.message()),
+            _ => "IndexedDB 请求失败".to_string(),
+        };
+        js_error(AlphaError::StorageError(message))
+    })?;
+    request.result()
+}
+
+#[wasm_bindgen]
+impl WasmAnalyzer {
+    /// 挂载行情缓存，之后 `analyzeSymbol` 会先与缓存合并再分析
+    #[wasm_bindgen(js_name = attachCache)]
+    pub fn attach_cache(&mut self, cache: &MarketDataCache) {
+        self.cache = Some(cache.clone());
+    }
+
+    /// 卸载行情缓存
+    #[wasm_bindgen(js_name = detachCache)]
+    pub fn detach_cache(&mut self) {
+        self.cache = None;
+    }
+}
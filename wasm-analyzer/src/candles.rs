@@ -0,0 +1,24 @@
+//! 逐笔数据聚合为 K 线
+//!
+//! 直接复用 core 的聚合逻辑，保证浏览器端生成的 K 线与服务端的区间划分一致
+
+use alpha_core::models::MarketData;
+use alpha_core::utils::series::{aggregate_candles as aggregate, parse_timeframe};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::convert::{from_js, to_js};
+use crate::error::js_error;
+use crate::types::MarketDataArray;
+
+/// 将按时间升序排列的逐笔数据聚合为 K 线，`timeframe` 如 `1m`、`5m`、`1h`、`1d`
+///
+/// 返回的每根 K 线时间戳为区间起点 (按周期对齐到 UTC 纪元)，`price` 为收盘价
+#[wasm_bindgen(js_name = aggregateCandles)]
+pub fn aggregate_candles(ticks: &MarketDataArray, timeframe: &str) -> Result<MarketDataArray, JsValue> {
+    let step = parse_timeframe(timeframe).map_err(js_error)?;
+    let ticks: Vec<MarketData> = from_js(ticks)?;
+    let candles = aggregate(&ticks, step).map_err(js_error)?;
+
+    to_js(&candles).map(JsCast::unchecked_into)
+}
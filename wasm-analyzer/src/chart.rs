@@ -0,0 +1,121 @@
+//! 图表库数据格式转换
+//!
+//! 把分析结果中的指标序列 (以及可选的 K 线) 整理成常见图表库可直接使用的格式，
+//! 省去各前端重复编写相同的转换代码。预热期等非有限值会被跳过
+
+use alpha_core::models::{AnalysisResult, MarketData};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::convert::{from_js, to_js};
+use crate::error::invalid_input;
+use crate::types::{AnalysisResultJs, ChartSeriesJs, MarketDataArrayOpt};
+
+/// 目标图表库
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChartFormat {
+    /// TradingView lightweight-charts：`{ time, value }`，时间为 UTC 秒
+    Lightweight,
+    /// ECharts 时间轴：`[time, value]`，时间为毫秒
+    ECharts,
+}
+
+impl FromStr for ChartFormat {
+    type Err = JsValue;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "lightweight" | "lightweight-charts" => Ok(ChartFormat::Lightweight),
+            "echarts" => Ok(ChartFormat::ECharts),
+            other => Err(invalid_input(format!("不支持的图表格式: {}", other))),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct LinePoint {
+    time: i64,
+    value: f64,
+}
+
+#[derive(Serialize)]
+struct CandlePoint {
+    time: i64,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+}
+
+/// 指标线以名称为键，K 线未提供时为空数组
+#[derive(Serialize)]
+struct ChartSeries<L, C> {
+    indicators: BTreeMap<String, Vec<L>>,
+    candles: Vec<C>,
+}
+
+/// 缺失的开高低价以成交价补齐
+fn ohlc(data: &MarketData) -> (f64, f64, f64, f64) {
+    (
+        data.open.unwrap_or(data.price),
+        data.high.unwrap_or(data.price),
+        data.low.unwrap_or(data.price),
+        data.price,
+    )
+}
+
+fn lines<L>(result: &AnalysisResult, point: impl Fn(i64, f64) -> L) -> BTreeMap<String, Vec<L>> {
+    result.indicators.iter()
+        .map(|indicator| {
+            let points = indicator.timestamps.iter()
+                .zip(&indicator.values)
+                .filter(|(_, value)| value.is_finite())
+                .map(|(ts, &value)| point(ts.timestamp_millis(), value))
+                .collect();
+            (indicator.name.clone(), points)
+        })
+        .collect()
+}
+
+/// 将分析结果转换为图表序列，`format` 取 `lightweight-charts` 或 `echarts`，`candles` 可选
+#[wasm_bindgen(js_name = toChartSeries)]
+pub fn to_chart_series(
+    result: &AnalysisResultJs,
+    format: &str,
+    candles: &MarketDataArrayOpt,
+) -> Result<ChartSeriesJs, JsValue> {
+    let format: ChartFormat = format.parse()?;
+    let result: AnalysisResult = from_js(result)?;
+    let candles: Vec<MarketData> = if candles.is_undefined() || candles.is_null() {
+        Vec::new()
+    } else {
+        from_js(candles)?
+    };
+
+    let series = match format {
+        ChartFormat::Lightweight => to_js(&ChartSeries {
+            indicators: lines(&result, |ms, value| LinePoint { time: ms / 1000, value }),
+            candles: candles.iter()
+                .map(|data| {
+                    let (open, high, low, close) = ohlc(data);
+                    CandlePoint { time: data.timestamp.timestamp(), open, high, low, close }
+                })
+                .collect(),
+        }),
+        // ECharts K 线数据顺序为 [open, close, low, high]
+        ChartFormat::ECharts => to_js(&ChartSeries {
+            indicators: lines(&result, |ms, value| (ms, value)),
+            candles: candles.iter()
+                .map(|data| {
+                    let (open, high, low, close) = ohlc(data);
+                    (data.timestamp.timestamp_millis(), open, close, low, high)
+                })
+                .collect(),
+        }),
+    }?;
+
+    Ok(series.unchecked_into())
+}
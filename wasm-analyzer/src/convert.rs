@@ -0,0 +1,73 @@
+//! JS 与 Rust 之间的数据转换
+//!
+//! 结构化结果走 serde-wasm-bindgen 直接构造 JS 对象，不经过 JSON 字符串；
+//! 数值列统一使用 `Float64Array`，只在 JS 堆与 WASM 线性内存之间做一次整块拷贝
+
+use alpha_core::errors::AlphaError;
+use alpha_core::models::MarketData;
+use chrono::DateTime;
+use serde::{de::DeserializeOwned, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::error::{invalid_input, js_error};
+
+/// 序列化为 JS 值，map 输出为普通对象以便前端按字段访问
+pub fn to_js<T: Serialize + ?Sized>(value: &T) -> Result<JsValue, JsValue> {
+    let serializer = serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
+    value
+        .serialize(&serializer)
+        .map_err(|e| js_error(AlphaError::SerializationError(format!("结果序列化错误: {}", e))))
+}
+
+/// 从 JS 值反序列化
+pub fn from_js<T: DeserializeOwned>(value: &JsValue) -> Result<T, JsValue> {
+    serde_wasm_bindgen::from_value(value.clone())
+        .map_err(|e| invalid_input(format!("数据转换错误: {}", e)))
+}
+
+/// 由若干数值列构造 JS 对象，每列为独立的 `Float64Array`
+pub fn columns(fields: &[(&str, &[f64])]) -> Result<js_sys::Object, JsValue> {
+    let object = js_sys::Object::new();
+    for (name, values) in fields {
+        set(&object, name, js_sys::Float64Array::from(*values))?;
+    }
+    Ok(object)
+}
+
+/// 设置 JS 对象属性
+pub fn set(object: &js_sys::Object, key: &str, value: impl AsRef<JsValue>) -> Result<(), JsValue> {
+    js_sys::Reflect::set(object, &JsValue::from_str(key), value.as_ref()).map(|_| ())
+}
+
+/// 由列式数据 (毫秒时间戳、价格、成交量) 构造行情序列，省去逐条对象的反序列化
+pub fn market_data_from_columns(
+    symbol: &str,
+    timestamps: &[f64],
+    prices: &[f64],
+    volumes: &[f64],
+) -> Result<Vec<MarketData>, JsValue> {
+    if timestamps.len() != prices.len() || prices.len() != volumes.len() {
+        return Err(invalid_input("时间戳、价格和成交量长度必须一致"));
+    }
+
+    timestamps.iter()
+        .zip(prices)
+        .zip(volumes)
+        .map(|((&ts, &price), &volume)| {
+            let timestamp = DateTime::from_timestamp_millis(ts as i64)
+                .ok_or_else(|| invalid_input(format!("无效时间戳: {}", ts)))?;
+
+            Ok(MarketData {
+                symbol: symbol.to_string(),
+                timestamp,
+                price,
+                volume: volume.max(0.0) as u64,
+                bid: None,
+                ask: None,
+                open: None,
+                high: None,
+                low: None,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,26 @@
+//! 拖拽上传的 CSV 行情文件解析
+//!
+//! 直接在 WASM 内解析原始字节，省去 JS 端逐行拆分和构造对象的开销，结果可直接传给 `analyzeSymbol`
+
+use alpha_core::utils::csv::{parse_csv as parse, CsvMapping};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::convert::{from_js, to_js};
+use crate::error::js_error;
+use crate::types::{CsvMappingJs, MarketDataArray};
+
+/// 将 CSV 文件内容 (如 `new Uint8Array(await file.arrayBuffer())`) 解析为按时间升序的行情数据
+///
+/// `mapping` 省略时按表头自动识别 date/open/high/low/close/volume/symbol 列
+#[wasm_bindgen(js_name = parseCsv)]
+pub fn parse_csv(bytes: &[u8], mapping: &CsvMappingJs) -> Result<MarketDataArray, JsValue> {
+    let mapping: CsvMapping = if mapping.is_undefined() || mapping.is_null() {
+        CsvMapping::default()
+    } else {
+        from_js(mapping)?
+    };
+    let data = parse(bytes, &mapping).map_err(js_error)?;
+
+    to_js(&data).map(JsCast::unchecked_into)
+}
@@ -0,0 +1,115 @@
+//! 结构化错误
+//!
+//! 所有导出函数抛出的错误都是 `AlphaJsError` 实例，带有稳定的 `code` 和 `category`，
+//! 前端可以据此分支处理 (如 `network` 类错误提示重试)，而不必解析错误文本
+
+use alpha_core::errors::AlphaError;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// 可序列化的错误信息，用于批量结果和 Worker 响应等需要结构化克隆的场景
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorInfo {
+    pub code: String,
+    pub category: String,
+    pub message: String,
+    pub retryable: bool,
+}
+
+impl From<&AlphaError> for ErrorInfo {
+    fn from(err: &AlphaError) -> Self {
+        ErrorInfo {
+            code: err.code().to_string(),
+            category: err.category().to_string(),
+            message: err.report(),
+            retryable: err.is_retryable(),
+        }
+    }
+}
+
+impl ErrorInfo {
+    /// 从任意抛出值提取错误信息，非 `AlphaJsError` 的值 (如 JS 异常) 归为 `WASM_ERROR`
+    pub fn from_js(value: &JsValue) -> Self {
+        let field = |key: &str| {
+            js_sys::Reflect::get(value, &JsValue::from_str(key))
+                .ok()
+                .filter(|_| value.is_object())
+        };
+
+        match field("code").and_then(|code| code.as_string()) {
+            Some(code) => ErrorInfo {
+                code,
+                category: field("category").and_then(|v| v.as_string()).unwrap_or_default(),
+                message: field("message").and_then(|v| v.as_string()).unwrap_or_default(),
+                retryable: field("retryable").and_then(|v| v.as_bool()).unwrap_or(false),
+            },
+            None => {
+                let message = value.as_string()
+                    .or_else(|| field("message").and_then(|v| v.as_string()))
+                    .unwrap_or_else(|| format!("{:?}", value));
+                ErrorInfo::from(&AlphaError::WasmError(message))
+            }
+        }
+    }
+}
+
+/// 导出给 JS 的错误类型
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct AlphaJsError {
+    info: ErrorInfo,
+}
+
+#[wasm_bindgen]
+impl AlphaJsError {
+    /// 错误码，如 `INVALID_INPUT`
+    #[wasm_bindgen(getter)]
+    pub fn code(&self) -> String {
+        self.info.code.clone()
+    }
+
+    /// 错误大类，如 `input`、`network`、`cancelled`
+    #[wasm_bindgen(getter)]
+    pub fn category(&self) -> String {
+        self.info.category.clone()
+    }
+
+    /// 包含完整上下文链的错误描述
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.info.message.clone()
+    }
+
+    /// 是否为可重试的暂时性错误
+    #[wasm_bindgen(getter)]
+    pub fn retryable(&self) -> bool {
+        self.info.retryable
+    }
+
+    /// 与内置 `Error` 一致，便于日志和 `console.error` 识别
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        "AlphaJsError".to_string()
+    }
+
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        format!("AlphaJsError [{}]: {}", self.info.code, self.info.message)
+    }
+}
+
+impl From<AlphaError> for AlphaJsError {
+    fn from(err: AlphaError) -> Self {
+        AlphaJsError { info: ErrorInfo::from(&err) }
+    }
+}
+
+/// 转换为抛给 JS 的错误对象
+pub fn js_error(err: AlphaError) -> JsValue {
+    AlphaJsError::from(err).into()
+}
+
+/// 无效输入错误
+pub fn invalid_input(msg: impl Into<String>) -> JsValue {
+    js_error(AlphaError::invalid_input(msg))
+}
@@ -0,0 +1,19 @@
+//! 跨平台结果一致性指纹
+//!
+//! 与桌面端、服务端使用同一个 core 实现 (`Checksum::of_analysis`)，集成测试中比对各平台对同一输入的指纹即可
+//! 验证计算结果一致
+
+use alpha_core::models::AnalysisResult;
+use alpha_core::utils::checksum::{Checksum, ChecksumAlgorithm, FINGERPRINT_DECIMALS};
+use wasm_bindgen::prelude::*;
+
+use crate::convert::from_js;
+use crate::types::AnalysisResultJs;
+
+/// 计算分析结果的规范指纹 (`sha256:<hex>`)，数值先按 `decimals` 位小数取整 (默认 8)
+#[wasm_bindgen]
+pub fn fingerprint(result: &AnalysisResultJs, decimals: Option<u32>) -> Result<String, JsValue> {
+    let result: AnalysisResult = from_js(result)?;
+    let checksum = Checksum::of_analysis(ChecksumAlgorithm::Sha256, &result, decimals.unwrap_or(FINGERPRINT_DECIMALS));
+    Ok(checksum.to_string())
+}
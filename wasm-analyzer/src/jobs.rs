@@ -0,0 +1,160 @@
+//! 可取消的后台分析任务
+//!
+//! WASM 在浏览器主线程上单线程运行，取消请求只能在任务让出事件循环时被处理。
+//! 任务在每个安全点检查取消标记，并在连续运行超过时间片后通过 `setTimeout(0)` 让出，
+//! 让用户操作 (如切换标的后调用 `cancel`) 有机会执行
+
+use alpha_core::errors::AlphaError;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::batch::{parse_batch, run_batch};
+use crate::convert::{set, to_js};
+use crate::error::js_error;
+use crate::types::{AnalysisJobJs, BatchDataJs, BatchOptionsJs};
+use crate::WasmAnalyzer;
+
+/// 连续运行超过该时长 (毫秒) 后在下一个安全点让出事件循环
+const TIME_SLICE_MS: f64 = 10.0;
+
+/// 任务被取消时 Promise 的拒绝原因
+pub(crate) const CANCELLED: &str = "分析任务已取消";
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(handler: &js_sys::Function, timeout: i32) -> JsValue;
+}
+
+/// 取消令牌，由任务和注册表共享
+#[derive(Debug, Clone)]
+pub(crate) struct CancelToken {
+    cancelled: Rc<Cell<bool>>,
+    last_yield: Rc<Cell<f64>>,
+}
+
+impl CancelToken {
+    fn new() -> Self {
+        Self {
+            cancelled: Rc::new(Cell::new(false)),
+            last_yield: Rc::new(Cell::new(js_sys::Date::now())),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.set(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.get()
+    }
+
+    /// 安全点：必要时让出事件循环，已取消则返回错误
+    pub async fn checkpoint(&self) -> Result<(), JsValue> {
+        if js_sys::Date::now() - self.last_yield.get() >= TIME_SLICE_MS {
+            yield_now().await?;
+            self.last_yield.set(js_sys::Date::now());
+        }
+
+        if self.is_cancelled() {
+            Err(js_error(AlphaError::Cancelled(CANCELLED.to_string())))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// 通过宏任务让出事件循环 (微任务不会让出给用户事件)
+async fn yield_now() -> Result<(), JsValue> {
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        set_timeout(&resolve, 0);
+    });
+    JsFuture::from(promise).await.map(|_| ())
+}
+
+/// 运行中任务的注册表
+#[derive(Debug, Clone, Default)]
+pub(crate) struct JobRegistry {
+    next_id: Rc<Cell<u32>>,
+    running: Rc<RefCell<BTreeMap<u32, CancelToken>>>,
+}
+
+impl JobRegistry {
+    fn register(&self) -> (u32, CancelToken) {
+        let id = self.next_id.get().wrapping_add(1);
+        self.next_id.set(id);
+
+        let token = CancelToken::new();
+        self.running.borrow_mut().insert(id, token.clone());
+        (id, token)
+    }
+
+    fn finish(&self, id: u32) {
+        self.running.borrow_mut().remove(&id);
+    }
+
+    fn cancel(&self, id: u32) -> bool {
+        match self.running.borrow_mut().remove(&id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn cancel_all(&self) -> usize {
+        let running = std::mem::take(&mut *self.running.borrow_mut());
+        running.values().for_each(CancelToken::cancel);
+        running.len()
+    }
+}
+
+#[wasm_bindgen]
+impl WasmAnalyzer {
+    /// 启动可取消的批量分析，立即返回 `{ jobId, result }`，`result` 为分析完成后兑现的 Promise
+    #[wasm_bindgen(js_name = startAnalysis)]
+    pub fn start_analysis(
+        &self,
+        symbols: Vec<String>,
+        data_per_symbol: &BatchDataJs,
+        options: &BatchOptionsJs,
+    ) -> Result<AnalysisJobJs, JsValue> {
+        let (jobs, options) = parse_batch(symbols, data_per_symbol, options, self.options.price_source)?;
+        let (id, token) = self.jobs.register();
+        let registry = self.jobs.clone();
+        let engine = self.engine.clone();
+
+        let promise = wasm_bindgen_futures::future_to_promise(async move {
+            let result = run_batch(&engine, jobs, &options, Some(&token)).await;
+            registry.finish(id);
+            to_js(&result?)
+        });
+
+        let job = js_sys::Object::new();
+        set(&job, "jobId", JsValue::from(id))?;
+        set(&job, "result", &promise)?;
+        Ok(job.unchecked_into())
+    }
+
+    /// 取消任务，任务会在下一个安全点停止并以取消错误拒绝；任务不存在或已结束时返回 false
+    pub fn cancel(&self, job_id: u32) -> bool {
+        self.jobs.cancel(job_id)
+    }
+
+    /// 取消全部运行中的任务，返回取消的数量
+    #[wasm_bindgen(js_name = cancelAll)]
+    pub fn cancel_all(&self) -> usize {
+        self.jobs.cancel_all()
+    }
+
+    /// 运行中的任务数量
+    #[wasm_bindgen(js_name = runningJobs, getter)]
+    pub fn running_jobs(&self) -> usize {
+        self.jobs.running.borrow().len()
+    }
+}
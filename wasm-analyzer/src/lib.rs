@@ -1,10 +1,51 @@
 //! Alpha Finance WASM 分析引擎
 //!
-//! 在浏览器中运行的高性能数据分析引擎
+//! 在浏览器、Web Worker 和 Node.js 中运行的高性能数据分析引擎
 
 use wasm_bindgen::prelude::*;
-use alpha_core::{models::*, analytics::AnalysisEngine, indicators::TechnicalIndicators};
+use alpha_core::{models::*, analytics::AnalysisEngine, errors::{AlphaError, AlphaResult, ErrorContext}, indicators::TechnicalIndicators};
+use alpha_core::indicators::registry::IndicatorRegistry;
+use alpha_core::utils::symbol::Symbol;
 use chrono::Utc;
+use std::cell::RefCell;
+
+mod batch;
+mod buffers;
+mod cache;
+mod candles;
+mod chart;
+mod convert;
+mod csv;
+mod error;
+mod fingerprint;
+mod jobs;
+mod ohlcv;
+mod options;
+mod patterns;
+mod portfolio;
+mod registry;
+mod runtime;
+mod screener;
+mod stream;
+mod types;
+mod views;
+mod worker;
+
+use convert::{columns, from_js, market_data_from_columns, set, to_js};
+use error::js_error;
+use options::AnalyzerOptions;
+use types::*;
+use wasm_bindgen::JsCast;
+pub use cache::MarketDataCache;
+pub use candles::aggregate_candles;
+pub use chart::to_chart_series;
+pub use csv::parse_csv;
+pub use error::AlphaJsError;
+pub use fingerprint::fingerprint;
+pub use patterns::detect_patterns;
+pub use stream::IndicatorStream;
+pub use views::ResultBuffers;
+pub use worker::WorkerHost;
 
 // 在浏览器控制台中显示 panic 信息
 #[wasm_bindgen(start)]
@@ -18,73 +59,93 @@ pub fn main() {
 pub struct WasmAnalyzer {
     engine: AnalysisEngine,
     indicators: TechnicalIndicators,
+    registry: IndicatorRegistry,
+    options: AnalyzerOptions,
+    jobs: jobs::JobRegistry,
+    cache: Option<MarketDataCache>,
+    buffers: RefCell<buffers::BufferPool>,
 }
 
 #[wasm_bindgen]
 impl WasmAnalyzer {
-    /// 创建新的 WASM 分析器
+    /// 创建分析器，`options` 可省略，如 `new WasmAnalyzer({ precision: 2, priceSource: "typical" })`
     #[wasm_bindgen(constructor)]
-    pub fn new() -> WasmAnalyzer {
-        WasmAnalyzer {
-            engine: AnalysisEngine::new(),
-            indicators: TechnicalIndicators::new(),
-        }
+    pub fn new(options: &AnalyzerOptionsJs) -> Result<WasmAnalyzer, JsValue> {
+        AnalyzerOptions::from_js_value(options).map(WasmAnalyzer::with_options)
     }
 
-    /// 创建带精度的分析器
-    #[wasm_bindgen(constructor)]
-    pub fn with_precision(precision: usize) -> WasmAnalyzer {
-        WasmAnalyzer {
-            engine: AnalysisEngine::with_precision(precision),
-            indicators: TechnicalIndicators::with_precision(precision),
-        }
+    /// 构造时指定的小数位数
+    #[wasm_bindgen(getter)]
+    pub fn precision(&self) -> usize {
+        self.options.precision
     }
 
     /// 分析股票数据
+    ///
+    /// 挂载了缓存时先与缓存合并 (传入空数组即直接使用缓存数据)，合并结果写回缓存
     #[wasm_bindgen(js_name = analyzeSymbol)]
-    pub async fn analyze_symbol(&self, symbol: &str, data_js: &JsValue) -> Result<JsValue, JsValue> {
+    pub async fn analyze_symbol(&self, symbol: &str, data_js: &MarketDataArray) -> Result<AnalysisResultJs, JsValue> {
         // 转换 JavaScript 数据到 Rust 结构
-        let market_data: Vec<MarketData> = data_js
-            .into_serde()
-            .map_err(|e| JsValue::from_str(&format!("数据转换错误: {}", e)))?;
-
-        if market_data.is_empty() {
-            return Err(JsValue::from_str("市场数据不能为空"));
+        let mut market_data: Vec<MarketData> = from_js(data_js)?;
+        if let Some(cache) = &self.cache {
+            market_data = cache.merge(symbol, market_data).await?;
         }
+        self.options.price_source.apply(&mut market_data);
+        self.analyze(market_data).await.map(JsCast::unchecked_into)
+    }
 
-        // 执行分析
-        let analysis_result = self.engine.analyze_symbol(&market_data, None)
+    /// 分析列式行情数据 (毫秒时间戳、价格、成交量均为 `Float64Array`)
+    ///
+    /// 大数据量时优先使用，避免为每个数据点构造和解析 JS 对象
+    #[wasm_bindgen(js_name = analyzeColumns)]
+    pub async fn analyze_columns(
+        &self,
+        symbol: &str,
+        timestamps: &[f64],
+        prices: &[f64],
+        volumes: &[f64],
+    ) -> Result<AnalysisResultJs, JsValue> {
+        let market_data = market_data_from_columns(symbol, timestamps, prices, volumes)?;
+        self.analyze(market_data).await.map(JsCast::unchecked_into)
+    }
+
+    async fn analyze(&self, market_data: Vec<MarketData>) -> Result<JsValue, JsValue> {
+        let analysis_result = self.run_analysis(&market_data)
             .await
-            .map_err(|e| JsValue::from_str(&format!("分析失败: {}", e)))?;
+            .map_err(js_error)?;
 
         // 转换结果为 JavaScript 对象
-        let result_js = JsValue::from_serde(&analysis_result)
-            .map_err(|e| JsValue::from_str(&format!("结果序列化错误: {}", e)))?;
+        to_js(&analysis_result)
+    }
 
-        Ok(result_js)
+    async fn run_analysis(&self, market_data: &[MarketData]) -> AlphaResult<AnalysisResult> {
+        analyze_with(&self.engine, market_data).await
+    }
+
+    /// 创建增量指标流，`config` 可省略或只提供部分字段 (如 `{ rsi_period: 6 }`)
+    #[wasm_bindgen(js_name = createStream)]
+    pub fn create_stream(&self, symbol: &str, config: &StreamConfigJs) -> Result<IndicatorStream, JsValue> {
+        IndicatorStream::new(symbol, config, self.options.effective_precision())
     }
 
     /// 计算 RSI 指标
     #[wasm_bindgen(js_name = calculateRSI)]
-    pub fn calculate_rsi(&self, prices_js: &js_sys::Float64Array, period: usize) -> js_sys::Float64Array {
-        let prices: Vec<f64> = prices_js.to_vec();
-        let rsi = self.indicators.calculate_rsi(&prices, period);
+    pub fn calculate_rsi(&self, prices: &[f64], period: usize) -> js_sys::Float64Array {
+        let rsi = self.indicators.calculate_rsi(prices, period);
         js_sys::Float64Array::from(&rsi[..])
     }
 
     /// 计算移动平均线
     #[wasm_bindgen(js_name = calculateSMA)]
-    pub fn calculate_sma(&self, prices_js: &js_sys::Float64Array, period: usize) -> js_sys::Float64Array {
-        let prices: Vec<f64> = prices_js.to_vec();
-        let sma = self.indicators.calculate_sma(&prices, period);
+    pub fn calculate_sma(&self, prices: &[f64], period: usize) -> js_sys::Float64Array {
+        let sma = self.indicators.calculate_sma(prices, period);
         js_sys::Float64Array::from(&sma[..])
     }
 
     /// 计算指数移动平均线
     #[wasm_bindgen(js_name = calculateEMA)]
-    pub fn calculate_ema(&self, prices_js: &js_sys::Float64Array, period: usize) -> js_sys::Float64Array {
-        let prices: Vec<f64> = prices_js.to_vec();
-        let ema = self.indicators.calculate_ema(&prices, period);
+    pub fn calculate_ema(&self, prices: &[f64], period: usize) -> js_sys::Float64Array {
+        let ema = self.indicators.calculate_ema(prices, period);
         js_sys::Float64Array::from(&ema[..])
     }
 
@@ -92,116 +153,109 @@ impl WasmAnalyzer {
     #[wasm_bindgen(js_name = calculateBollingerBands)]
     pub fn calculate_bollinger_bands(
         &self,
-        prices_js: &js_sys::Float64Array,
+        prices: &[f64],
         period: usize,
         std_dev: f64,
-    ) -> JsValue {
-        let prices: Vec<f64> = prices_js.to_vec();
-        let (upper, middle, lower) = self.indicators.calculate_bollinger_bands(&prices, period, std_dev);
-
-        let result = serde_json::json!({
-            "upper": upper,
-            "middle": middle,
-            "lower": lower
-        });
+    ) -> Result<BollingerBandsJs, JsValue> {
+        let (upper, middle, lower) = self.indicators.calculate_bollinger_bands(prices, period, std_dev);
 
-        JsValue::from_serde(&result).unwrap_or(JsValue::NULL)
+        Ok(columns(&[("upper", &upper), ("middle", &middle), ("lower", &lower)])?.unchecked_into())
     }
 
     /// 计算 MACD
     #[wasm_bindgen(js_name = calculateMACD)]
     pub fn calculate_macd(
         &self,
-        prices_js: &js_sys::Float64Array,
+        prices: &[f64],
         fast_period: usize,
         slow_period: usize,
         signal_period: usize,
-    ) -> JsValue {
-        let prices: Vec<f64> = prices_js.to_vec();
-        let (macd_line, signal_line, histogram) = self.indicators.calculate_macd(&prices, fast_period, slow_period, signal_period);
-
-        let result = serde_json::json!({
-            "macd": macd_line,
-            "signal": signal_line,
-            "histogram": histogram
-        });
+    ) -> Result<MacdResultJs, JsValue> {
+        let (macd_line, signal_line, histogram) = self.indicators.calculate_macd(prices, fast_period, slow_period, signal_period);
 
-        JsValue::from_serde(&result).unwrap_or(JsValue::NULL)
+        Ok(columns(&[("macd", &macd_line), ("signal", &signal_line), ("histogram", &histogram)])?.unchecked_into())
     }
 
     /// 批量计算多个指标
     #[wasm_bindgen(js_name = calculateAllIndicators)]
     pub fn calculate_all_indicators(
         &self,
-        prices_js: &js_sys::Float64Array,
+        prices: &[f64],
         rsi_period: usize,
         sma_short: usize,
         sma_long: usize,
         macd_fast: usize,
         macd_slow: usize,
         macd_signal: usize,
-    ) -> JsValue {
-        let prices: Vec<f64> = prices_js.to_vec();
-
+    ) -> Result<AllIndicatorsJs, JsValue> {
         // 并行计算多个指标
-        let rsi = self.indicators.calculate_rsi(&prices, rsi_period);
-        let sma_short_values = self.indicators.calculate_sma(&prices, sma_short);
-        let sma_long_values = self.indicators.calculate_sma(&prices, sma_long);
-        let (macd_line, signal_line, histogram) = self.indicators.calculate_macd(&prices, macd_fast, macd_slow, macd_signal);
-        let (upper, middle, lower) = self.indicators.calculate_bollinger_bands(&prices, 20, 2.0);
-
-        let result = serde_json::json!({
-            "rsi": rsi,
-            "sma_short": sma_short_values,
-            "sma_long": sma_long_values,
-            "macd": {
-                "line": macd_line,
-                "signal": signal_line,
-                "histogram": histogram
-            },
-            "bollinger": {
-                "upper": upper,
-                "middle": middle,
-                "lower": lower
-            }
-        });
+        let rsi = self.indicators.calculate_rsi(prices, rsi_period);
+        let sma_short_values = self.indicators.calculate_sma(prices, sma_short);
+        let sma_long_values = self.indicators.calculate_sma(prices, sma_long);
+        let (macd_line, signal_line, histogram) = self.indicators.calculate_macd(prices, macd_fast, macd_slow, macd_signal);
+        let (upper, middle, lower) = self.indicators.calculate_bollinger_bands(prices, 20, 2.0);
+
+        let result = columns(&[("rsi", &rsi), ("sma_short", &sma_short_values), ("sma_long", &sma_long_values)])?;
+        set(&result, "macd", &columns(&[("line", &macd_line), ("signal", &signal_line), ("histogram", &histogram)])?)?;
+        set(&result, "bollinger", &columns(&[("upper", &upper), ("middle", &middle), ("lower", &lower)])?)?;
 
-        JsValue::from_serde(&result).unwrap_or(JsValue::NULL)
+        Ok(result.unchecked_into())
     }
 
     /// 获取性能指标
     #[wasm_bindgen(js_name = getPerformanceMetrics)]
-    pub fn get_performance_metrics(&self) -> JsValue {
-        let window = web_sys::window().unwrap();
-        let performance = window.performance().unwrap();
+    pub fn get_performance_metrics(&self) -> PerformanceMetricsJs {
+        let memory = runtime::heap_usage();
 
         let metrics = serde_json::json!({
             "timestamp": Utc::now().to_rfc3339(),
             "memory": {
-                "used": performance.memory().map(|m| m.used_js_heap_size()).unwrap_or(0),
-                "total": performance.memory().map(|m| m.total_js_heap_size()).unwrap_or(0),
-                "limit": performance.memory().map(|m| m.js_heap_size_limit()).unwrap_or(0)
+                "used": memory.used,
+                "total": memory.total,
+                "limit": memory.limit
             },
             "timing": {
-                "now": performance.now()
+                "now": runtime::now()
             }
         });
 
-        JsValue::from_serde(&metrics).unwrap_or(JsValue::NULL)
+        to_js(&metrics).unwrap_or(JsValue::NULL).unchecked_into()
     }
 
     /// 强制垃圾回收（如果支持）
     #[wasm_bindgen(js_name = forceGC)]
     pub fn force_gc() {
-        let window = web_sys::window().unwrap();
-        if let Some(gc) = js_sys::Reflect::get(&window, &JsValue::from_str("gc")).ok() {
-            if gc.is_function() {
-                js_sys::Function::from(gc).call0(&window).unwrap();
-            }
+        runtime::collect_garbage();
+    }
+}
+
+impl WasmAnalyzer {
+    pub(crate) fn with_options(options: AnalyzerOptions) -> WasmAnalyzer {
+        let precision = options.effective_precision();
+        WasmAnalyzer {
+            engine: AnalysisEngine::with_precision(precision),
+            indicators: TechnicalIndicators::with_precision(precision),
+            registry: IndicatorRegistry::new(),
+            options,
+            jobs: jobs::JobRegistry::default(),
+            cache: None,
+            buffers: RefCell::default(),
         }
     }
 }
 
+/// 使用给定引擎分析单个标的
+pub(crate) async fn analyze_with(engine: &AnalysisEngine, market_data: &[MarketData]) -> AlphaResult<AnalysisResult> {
+    if market_data.is_empty() {
+        return Err(AlphaError::invalid_input("市场数据不能为空"));
+    }
+
+    // 执行分析
+    engine.analyze_symbol(market_data, None)
+        .await
+        .context("分析失败")
+}
+
 /// 工具函数
 #[wasm_bindgen]
 pub struct Utils;
@@ -224,10 +278,10 @@ impl Utils {
         ((new_value - old_value) / old_value) * 100.0
     }
 
-    /// 验证股票代码
+    /// 验证证券代码，规则与 core 的 `Symbol::parse` 一致 (支持 `0700.HK`、`BRK.B`、`BTC-USDT` 等写法)
     #[wasm_bindgen(js_name = validateSymbol)]
     pub fn validate_symbol(symbol: &str) -> bool {
-        !symbol.is_empty() && symbol.len() <= 10 && symbol.chars().all(|c| c.is_alphanumeric() || c == '.')
+        Symbol::parse(symbol).is_ok()
     }
 
     /// 获取当前时间戳
@@ -278,14 +332,18 @@ mod tests {
         assert!(Utils::validate_symbol("AAPL"));
         assert!(!Utils::validate_symbol(""));
         assert!(!Utils::validate_symbol("TOO_LONG_SYMBOL_12345"));
+        assert!(Utils::validate_symbol("0700.HK"));
+        assert!(Utils::validate_symbol("BTC-USDT"));
+        assert!(Utils::validate_symbol("NASDAQ:AAPL"));
+        assert!(!Utils::validate_symbol("AAPL$"));
     }
 
     #[wasm_bindgen_test]
     fn test_analyzer_creation() {
-        let analyzer = WasmAnalyzer::new();
-        let analyzer_with_precision = WasmAnalyzer::with_precision(4);
+        let analyzer = WasmAnalyzer::new(&JsValue::UNDEFINED.unchecked_into()).unwrap();
+        assert_eq!(analyzer.precision(), 4);
 
-        // 这些应该能成功创建
-        assert!(true);
+        let legacy = WasmAnalyzer::new(&JsValue::from(2).unchecked_into()).unwrap();
+        assert_eq!(legacy.precision(), 2);
     }
 }
\ No newline at end of file
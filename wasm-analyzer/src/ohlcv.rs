@@ -0,0 +1,110 @@
+//! 需要完整 OHLCV 输入的指标
+//!
+//! 各列分别以 `Float64Array` 传入，或使用 `[open, high, low, close, volume]` 逐行交错的单个缓冲区
+//! (便于直接从二进制行情接口转交，只跨越一次边界)
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::convert::{columns, set};
+use crate::error::invalid_input;
+use crate::types::{OhlcvIndicatorsJs, StochasticResultJs};
+use crate::WasmAnalyzer;
+
+/// 交错缓冲区每行的字段数
+const FIELDS: usize = 5;
+
+/// 校验各列长度一致
+fn check_columns(columns: &[&[f64]]) -> Result<(), JsValue> {
+    let len = columns.first().map_or(0, |c| c.len());
+    if columns.iter().any(|c| c.len() != len) {
+        return Err(invalid_input("OHLCV 各列长度必须一致"));
+    }
+    Ok(())
+}
+
+/// 拆分交错缓冲区为 (open, high, low, close, volume) 五列
+fn split_interleaved(data: &[f64]) -> Result<[Vec<f64>; FIELDS], JsValue> {
+    if data.len() % FIELDS != 0 {
+        return Err(invalid_input(format!("交错缓冲区长度 ({}) 不是 {} 的倍数", data.len(), FIELDS)));
+    }
+
+    let rows = data.len() / FIELDS;
+    let mut split: [Vec<f64>; FIELDS] = Default::default();
+    for (field, column) in split.iter_mut().enumerate() {
+        column.reserve_exact(rows);
+        column.extend(data.iter().skip(field).step_by(FIELDS));
+    }
+    Ok(split)
+}
+
+#[wasm_bindgen]
+impl WasmAnalyzer {
+    /// 计算平均真实波幅 (ATR)
+    #[wasm_bindgen(js_name = calculateATR)]
+    pub fn calculate_atr(&self, high: &[f64], low: &[f64], close: &[f64], period: usize) -> Result<js_sys::Float64Array, JsValue> {
+        check_columns(&[high, low, close])?;
+        let atr = self.indicators.calculate_atr(high, low, close, period);
+        Ok(js_sys::Float64Array::from(&atr[..]))
+    }
+
+    /// 计算随机指标，返回 `{ k, d }`
+    #[wasm_bindgen(js_name = calculateStochastic)]
+    pub fn calculate_stochastic(
+        &self,
+        high: &[f64],
+        low: &[f64],
+        close: &[f64],
+        k_period: usize,
+        d_period: usize,
+    ) -> Result<StochasticResultJs, JsValue> {
+        check_columns(&[high, low, close])?;
+        let (k, d) = self.indicators.calculate_stochastic(high, low, close, k_period, d_period);
+        Ok(columns(&[("k", &k), ("d", &d)])?.unchecked_into())
+    }
+
+    /// 计算累计 VWAP
+    #[wasm_bindgen(js_name = calculateVWAP)]
+    pub fn calculate_vwap(&self, high: &[f64], low: &[f64], close: &[f64], volume: &[f64]) -> Result<js_sys::Float64Array, JsValue> {
+        check_columns(&[high, low, close, volume])?;
+        let vwap = self.indicators.calculate_vwap(high, low, close, volume);
+        Ok(js_sys::Float64Array::from(&vwap[..]))
+    }
+
+    /// 计算资金流量指标 (MFI)
+    #[wasm_bindgen(js_name = calculateMFI)]
+    pub fn calculate_mfi(
+        &self,
+        high: &[f64],
+        low: &[f64],
+        close: &[f64],
+        volume: &[f64],
+        period: usize,
+    ) -> Result<js_sys::Float64Array, JsValue> {
+        check_columns(&[high, low, close, volume])?;
+        let mfi = self.indicators.calculate_mfi(high, low, close, volume, period);
+        Ok(js_sys::Float64Array::from(&mfi[..]))
+    }
+
+    /// 从交错的 OHLCV 缓冲区一次计算 ATR、随机指标、VWAP 和 MFI
+    #[wasm_bindgen(js_name = calculateOhlcvIndicators)]
+    pub fn calculate_ohlcv_indicators(
+        &self,
+        interleaved: &[f64],
+        atr_period: usize,
+        stoch_k: usize,
+        stoch_d: usize,
+        mfi_period: usize,
+    ) -> Result<OhlcvIndicatorsJs, JsValue> {
+        let [_open, high, low, close, volume] = split_interleaved(interleaved)?;
+
+        let atr = self.indicators.calculate_atr(&high, &low, &close, atr_period);
+        let (k, d) = self.indicators.calculate_stochastic(&high, &low, &close, stoch_k, stoch_d);
+        let vwap = self.indicators.calculate_vwap(&high, &low, &close, &volume);
+        let mfi = self.indicators.calculate_mfi(&high, &low, &close, &volume, mfi_period);
+
+        let result = columns(&[("atr", &atr), ("vwap", &vwap), ("mfi", &mfi)])?;
+        set(&result, "stochastic", &columns(&[("k", &k), ("d", &d)])?)?;
+        Ok(result.unchecked_into())
+    }
+}
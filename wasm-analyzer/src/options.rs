@@ -0,0 +1,109 @@
+//! 分析器构造选项
+//!
+//! `new WasmAnalyzer({ precision, priceSource, decimalMode })`，所有字段均可省略；
+//! 新增配置项时在此扩展，不必再增加构造函数
+
+use alpha_core::indicators::TechnicalIndicators;
+use alpha_core::models::MarketData;
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+
+use crate::convert::from_js;
+
+/// 默认保留的小数位数
+const DEFAULT_PRECISION: usize = 4;
+
+/// 分析使用的价格
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceSource {
+    /// 成交价 / 收盘价 (`price` 字段)
+    #[default]
+    Close,
+    Open,
+    High,
+    Low,
+    /// (H + L) / 2
+    Hl2,
+    /// (H + L + C) / 3
+    Typical,
+}
+
+impl PriceSource {
+    /// 把选定的价格写入 `price` 字段，缺少开高低价的数据点保持原价
+    pub fn apply(&self, data: &mut [MarketData]) {
+        if *self == PriceSource::Close {
+            return;
+        }
+
+        for point in data {
+            let close = point.price;
+            let (open, high, low) = (point.open, point.high, point.low);
+            point.price = match self {
+                PriceSource::Close => close,
+                PriceSource::Open => open.unwrap_or(close),
+                PriceSource::High => high.unwrap_or(close),
+                PriceSource::Low => low.unwrap_or(close),
+                PriceSource::Hl2 => match (high, low) {
+                    (Some(h), Some(l)) => (h + l) / 2.0,
+                    _ => close,
+                },
+                PriceSource::Typical => match (high, low) {
+                    (Some(h), Some(l)) => (h + l + close) / 3.0,
+                    _ => close,
+                },
+            };
+        }
+    }
+}
+
+/// 指标结果的小数处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DecimalMode {
+    /// 按 `precision` 取整 (与其他平台结果逐位一致)
+    #[default]
+    Rounded,
+    /// 保留完整 f64 精度，忽略 `precision`
+    Full,
+}
+
+/// 分析器选项
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AnalyzerOptions {
+    pub precision: usize,
+    pub price_source: PriceSource,
+    pub decimal_mode: DecimalMode,
+}
+
+impl Default for AnalyzerOptions {
+    fn default() -> Self {
+        Self {
+            precision: DEFAULT_PRECISION,
+            price_source: PriceSource::default(),
+            decimal_mode: DecimalMode::default(),
+        }
+    }
+}
+
+impl AnalyzerOptions {
+    /// 解析 JS 选项：省略时取默认值，兼容旧用法直接传入精度数字
+    pub fn from_js_value(value: &JsValue) -> Result<Self, JsValue> {
+        if value.is_undefined() || value.is_null() {
+            Ok(Self::default())
+        } else if let Some(precision) = value.as_f64() {
+            Ok(Self { precision: precision as usize, ..Self::default() })
+        } else {
+            from_js(value)
+        }
+    }
+
+    /// 传给 core 的实际精度
+    pub fn effective_precision(&self) -> usize {
+        match self.decimal_mode {
+            DecimalMode::Rounded => self.precision,
+            DecimalMode::Full => TechnicalIndicators::FULL_PRECISION,
+        }
+    }
+}
@@ -0,0 +1,24 @@
+//! K 线形态识别，供前端在图表上标注
+
+use alpha_core::indicators::patterns::{detect_patterns as detect, CandlePattern};
+use alpha_core::models::MarketData;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::convert::{from_js, to_js};
+use crate::types::{CandlePatternListJs, MarketDataArray, PatternHitArray};
+
+/// 在 OHLC 数据中识别 K 线形态，`patternList` 省略或为空数组时识别全部形态
+///
+/// 缺少 `open` / `high` / `low` 的数据点不参与识别
+#[wasm_bindgen(js_name = detectPatterns)]
+pub fn detect_patterns(ohlc_data: &MarketDataArray, pattern_list: &CandlePatternListJs) -> Result<PatternHitArray, JsValue> {
+    let data: Vec<MarketData> = from_js(ohlc_data)?;
+    let patterns: Vec<CandlePattern> = if pattern_list.is_undefined() || pattern_list.is_null() {
+        Vec::new()
+    } else {
+        from_js(pattern_list)?
+    };
+
+    to_js(&detect(&data, &patterns)).map(JsCast::unchecked_into)
+}
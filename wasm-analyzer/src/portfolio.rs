@@ -0,0 +1,37 @@
+//! 组合风险分析，供仪表盘在浏览器内直接展示组合风险
+
+use alpha_core::models::MarketData;
+use alpha_core::portfolio::{analyze_portfolio as analyze, PortfolioRiskConfig, Position};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::convert::{from_js, to_js};
+use crate::error::js_error;
+use crate::types::{PortfolioRiskConfigJs, PortfolioRiskJs, PositionArray, PriceHistoriesJs};
+use crate::WasmAnalyzer;
+
+#[wasm_bindgen]
+impl WasmAnalyzer {
+    /// 计算组合波动率、VaR / CVaR、最大回撤和各资产风险贡献
+    ///
+    /// `histories` 以股票代码为键，只使用所有标的共同的时间点
+    #[wasm_bindgen(js_name = analyzePortfolio)]
+    pub fn analyze_portfolio(
+        &self,
+        positions: &PositionArray,
+        histories: &PriceHistoriesJs,
+        config: &PortfolioRiskConfigJs,
+    ) -> Result<PortfolioRiskJs, JsValue> {
+        let positions: Vec<Position> = from_js(positions)?;
+        let histories: BTreeMap<String, Vec<MarketData>> = from_js(histories)?;
+        let config: PortfolioRiskConfig = if config.is_undefined() || config.is_null() {
+            PortfolioRiskConfig::default()
+        } else {
+            from_js(config)?
+        };
+
+        let risk = analyze(&positions, &histories, &config).map_err(js_error)?;
+        to_js(&risk).map(JsCast::unchecked_into)
+    }
+}
@@ -0,0 +1,91 @@
+//! 按名称调用指标
+//!
+//! 通过 core 的指标注册表分发，新增指标只需在 core 注册，前端无需等待新的导出方法
+
+use alpha_core::indicators::registry::{IndicatorInput, IndicatorOutput, IndicatorParams};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::convert::{from_js, set, to_js};
+use crate::error::{invalid_input, js_error};
+use crate::types::{IndicatorColumnsJs, IndicatorInputJs, IndicatorParamsJs, IndicatorSpecArray};
+use crate::WasmAnalyzer;
+
+/// 读取数值列，支持 `Float64Array` 和普通数组
+fn f64_field(input: &JsValue, key: &str) -> Result<Option<Vec<f64>>, JsValue> {
+    let value = js_sys::Reflect::get(input, &JsValue::from_str(key))?;
+    if value.is_undefined() || value.is_null() {
+        Ok(None)
+    } else if let Some(array) = value.dyn_ref::<js_sys::Float64Array>() {
+        Ok(Some(array.to_vec()))
+    } else {
+        from_js(&value).map(Some)
+    }
+}
+
+#[wasm_bindgen]
+impl WasmAnalyzer {
+    /// 按名称计算指标 (不区分大小写)，返回以输出列名为键的 `Float64Array` 对象
+    ///
+    /// `input` 可直接传收盘价数组，或 `{ close, high?, low?, volume? }`；`params` 缺省的参数取默认值
+    #[wasm_bindgen(js_name = calculateIndicator)]
+    pub fn calculate_indicator(
+        &self,
+        name: &str,
+        input: &IndicatorInputJs,
+        params: &IndicatorParamsJs,
+    ) -> Result<IndicatorColumnsJs, JsValue> {
+        let output = self.run_indicator(name, input, params)?;
+
+        let result = js_sys::Object::new();
+        for (column, values) in &output {
+            set(&result, column, js_sys::Float64Array::from(&values[..]))?;
+        }
+        Ok(result.unchecked_into())
+    }
+
+    /// 列出可按名称调用的指标及其参数默认值
+    #[wasm_bindgen(js_name = listIndicators)]
+    pub fn list_indicators(&self) -> Result<IndicatorSpecArray, JsValue> {
+        let specs: Vec<_> = self.registry.specs().collect();
+        to_js(&specs).map(JsCast::unchecked_into)
+    }
+}
+
+impl WasmAnalyzer {
+    /// 解析 JS 输入与参数并通过注册表计算
+    pub(crate) fn run_indicator(
+        &self,
+        name: &str,
+        input: &IndicatorInputJs,
+        params: &IndicatorParamsJs,
+    ) -> Result<IndicatorOutput, JsValue> {
+        let (close, high, low, volume) = if let Some(close) = input.dyn_ref::<js_sys::Float64Array>() {
+            (close.to_vec(), None, None, None)
+        } else if js_sys::Array::is_array(input) {
+            (from_js(input)?, None, None, None)
+        } else {
+            (
+                f64_field(input, "close")?.ok_or_else(|| invalid_input("输入缺少 close 列"))?,
+                f64_field(input, "high")?,
+                f64_field(input, "low")?,
+                f64_field(input, "volume")?,
+            )
+        };
+        let params: IndicatorParams = if params.is_undefined() || params.is_null() {
+            IndicatorParams::new()
+        } else {
+            from_js(params)?
+        };
+
+        let input = IndicatorInput {
+            high: high.as_deref(),
+            low: low.as_deref(),
+            close: &close,
+            volume: volume.as_deref(),
+        };
+        self.registry
+            .calculate(&self.indicators, name, &input, &params)
+            .map_err(js_error)
+    }
+}
@@ -0,0 +1,74 @@
+//! 宿主环境探测
+//!
+//! 同一份构建需要同时运行在浏览器、Web Worker 和 Node.js 中，因此不直接依赖 `window`，
+//! 统一从 `globalThis` 上按需读取能力，缺失时返回默认值而不是 panic
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+/// JS 堆内存使用情况 (字节)，无法获取的字段为 0
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeapUsage {
+    pub used: f64,
+    pub total: f64,
+    pub limit: f64,
+}
+
+/// 读取 `globalThis` 上的属性，不存在时返回 `None`
+fn global(key: &str) -> Option<JsValue> {
+    property(&js_sys::global(), key)
+}
+
+fn property(target: &JsValue, key: &str) -> Option<JsValue> {
+    js_sys::Reflect::get(target, &JsValue::from_str(key))
+        .ok()
+        .filter(|value| !value.is_undefined() && !value.is_null())
+}
+
+fn number(target: &JsValue, key: &str) -> f64 {
+    property(target, key).and_then(|value| value.as_f64()).unwrap_or(0.0)
+}
+
+/// 调用对象上的无参方法
+fn call_method(target: &JsValue, key: &str) -> Option<JsValue> {
+    property(target, key)?
+        .dyn_into::<js_sys::Function>()
+        .ok()?
+        .call0(target)
+        .ok()
+}
+
+/// 高精度时间 (毫秒)，`performance` 不可用时退化为 `Date.now()`
+pub fn now() -> f64 {
+    global("performance")
+        .and_then(|performance| call_method(&performance, "now"))
+        .and_then(|value| value.as_f64())
+        .unwrap_or_else(js_sys::Date::now)
+}
+
+/// 浏览器读取 `performance.memory` (仅 Chromium)，Node.js 读取 `process.memoryUsage()`
+pub fn heap_usage() -> HeapUsage {
+    if let Some(memory) = global("performance").and_then(|performance| property(&performance, "memory")) {
+        return HeapUsage {
+            used: number(&memory, "usedJSHeapSize"),
+            total: number(&memory, "totalJSHeapSize"),
+            limit: number(&memory, "jsHeapSizeLimit"),
+        };
+    }
+
+    match global("process").and_then(|process| call_method(&process, "memoryUsage")) {
+        Some(usage) => HeapUsage {
+            used: number(&usage, "heapUsed"),
+            total: number(&usage, "heapTotal"),
+            limit: 0.0,
+        },
+        None => HeapUsage::default(),
+    }
+}
+
+/// 调用全局 `gc()` (需浏览器开启相应标志或 Node.js 以 `--expose-gc` 启动)，不可用时忽略
+pub fn collect_garbage() {
+    if let Some(gc) = global("gc").and_then(|gc| gc.dyn_into::<js_sys::Function>().ok()) {
+        let _ = gc.call0(&JsValue::UNDEFINED);
+    }
+}
@@ -0,0 +1,42 @@
+//! 浏览器内多标的选股
+//!
+//! 条件解析与指标计算都在 core 中完成，数百个标的的扫描无需往返 JS
+
+use alpha_core::models::MarketData;
+use alpha_core::screener::{screen, ScreenCriteria};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::convert::{from_js, to_js};
+use crate::error::{invalid_input, js_error};
+use crate::types::{PriceHistoriesJs, ScreenCriteriaJs, ScreenResultJs};
+use crate::WasmAnalyzer;
+
+#[wasm_bindgen]
+impl WasmAnalyzer {
+    /// 按条件扫描多个标的，返回按得分排序的命中结果
+    ///
+    /// `criteria` 可为条件表达式 (如 `"RSI(14) < 30 AND price > SMA(200)"`)、其 JSON 字符串或对象，
+    /// 数据不足或计算失败的标的列入 `skipped` 而不是中断扫描
+    #[wasm_bindgen]
+    pub fn screen(&self, universe: &PriceHistoriesJs, criteria: &ScreenCriteriaJs) -> Result<ScreenResultJs, JsValue> {
+        let criteria = parse_criteria(criteria)?;
+        let mut universe: BTreeMap<String, Vec<MarketData>> = from_js(universe)?;
+        for data in universe.values_mut() {
+            self.options.price_source.apply(data);
+        }
+
+        let result = screen(&self.registry, &self.indicators, &universe, &criteria).map_err(js_error)?;
+        to_js(&result).map(JsCast::unchecked_into)
+    }
+}
+
+fn parse_criteria(criteria: &JsValue) -> Result<ScreenCriteria, JsValue> {
+    match criteria.as_string() {
+        Some(text) if text.trim_start().starts_with('{') => serde_json::from_str(&text)
+            .map_err(|e| invalid_input(format!("选股条件 JSON 无效: {}", e))),
+        Some(text) => Ok(ScreenCriteria::new(text)),
+        None => from_js(criteria),
+    }
+}
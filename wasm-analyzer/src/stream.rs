@@ -0,0 +1,98 @@
+//! 逐笔行情的增量指标流
+//!
+//! 仪表盘每次只推送最新一笔，避免每秒重传全部历史价格
+
+use alpha_core::indicators::streaming::{IndicatorSnapshot, StreamConfig, StreamingIndicators};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::convert::{from_js, to_js};
+use crate::error::{invalid_input, js_error};
+use crate::types::{IndicatorSnapshotJs, StreamConfigJs, TickUpdateJs};
+use wasm_bindgen::JsCast;
+
+/// 单笔更新结果
+#[derive(Serialize)]
+struct TickUpdate<'a> {
+    symbol: &'a str,
+    timestamp: f64,
+    volume: f64,
+    #[serde(flatten)]
+    indicators: IndicatorSnapshot,
+}
+
+/// 增量指标流句柄
+#[wasm_bindgen]
+pub struct IndicatorStream {
+    symbol: String,
+    indicators: StreamingIndicators,
+}
+
+impl IndicatorStream {
+    pub(crate) fn new(symbol: &str, config_js: &StreamConfigJs, precision: usize) -> Result<IndicatorStream, JsValue> {
+        let config: StreamConfig = if config_js.is_undefined() || config_js.is_null() {
+            StreamConfig::default()
+        } else {
+            from_js(config_js)?
+        };
+        let indicators = StreamingIndicators::with_precision(config, precision)
+            .map_err(|e| js_error(e.context("指标参数错误")))?;
+
+        Ok(IndicatorStream {
+            symbol: symbol.to_string(),
+            indicators,
+        })
+    }
+}
+
+#[wasm_bindgen]
+impl IndicatorStream {
+    /// 推入一笔行情 (毫秒时间戳)，返回最新的 RSI/EMA/MACD/布林带值
+    #[wasm_bindgen(js_name = pushTick)]
+    pub fn push_tick(&mut self, price: f64, volume: f64, ts: f64) -> Result<TickUpdateJs, JsValue> {
+        if !price.is_finite() {
+            return Err(invalid_input(format!("无效价格: {}", price)));
+        }
+
+        let indicators = self.indicators.push(price);
+        to_js(&TickUpdate {
+            symbol: &self.symbol,
+            timestamp: ts,
+            volume,
+            indicators,
+        })
+        .map(JsCast::unchecked_into)
+    }
+
+    /// 批量推入历史价格用于预热，只返回最后一笔的指标值
+    #[wasm_bindgen(js_name = pushPrices)]
+    pub fn push_prices(&mut self, prices: &[f64]) -> Result<IndicatorSnapshotJs, JsValue> {
+        if let Some(bad) = prices.iter().find(|p| !p.is_finite()) {
+            return Err(invalid_input(format!("无效价格: {}", bad)));
+        }
+
+        let latest = prices.iter().fold(None, |_, &p| Some(self.indicators.push(p)));
+        to_js(&latest).map(JsCast::unchecked_into)
+    }
+
+    /// 最新指标值，尚未推入数据时为 `undefined`
+    pub fn latest(&self) -> Result<IndicatorSnapshotJs, JsValue> {
+        to_js(&self.indicators.latest()).map(JsCast::unchecked_into)
+    }
+
+    /// 已推入的数据点数量
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.indicators.len()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn symbol(&self) -> String {
+        self.symbol.clone()
+    }
+
+    /// 清空状态 (如切换周期后重新预热)
+    pub fn reset(&mut self) {
+        self.indicators.reset();
+    }
+}
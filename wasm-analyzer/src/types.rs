@@ -0,0 +1,442 @@
+//! 导出给 JS 的结果类型的 TypeScript 定义
+//!
+//! alpha-core 需要保持 no_std 且不依赖 wasm-bindgen，因此这里手工维护与其 serde 输出一致的 TS 声明，
+//! 再通过 `typescript_type` 把导出函数的 `any` 替换为具体类型。修改 core 模型字段时需同步更新
+
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &'static str = r#"
+export type SignalType = "Buy" | "Sell" | "Hold" | "None";
+export type SignalHorizon = "Intraday" | "ShortTerm" | "MediumTerm" | "LongTerm";
+
+/** 行情数据，时间戳为 RFC 3339 字符串 */
+export interface MarketData {
+  symbol: string;
+  timestamp: string;
+  price: number;
+  volume: number;
+  bid?: number | null;
+  ask?: number | null;
+  open?: number | null;
+  high?: number | null;
+  low?: number | null;
+}
+
+export interface IndicatorResult {
+  name: string;
+  timestamps: string[];
+  values: number[];
+  signals: SignalType[];
+  /** 预热期长度，前 warmup 个值不是有效数据 */
+  warmup: number;
+}
+
+export interface Signal {
+  direction: SignalType;
+  /** 0.0 - 1.0 */
+  strength: number;
+  source_indicator: string;
+  rationale: string;
+  horizon: SignalHorizon;
+}
+
+export interface RiskMetrics {
+  volatility: number;
+  sharpe_ratio?: number | null;
+  max_drawdown: number;
+  beta?: number | null;
+}
+
+export interface AnalysisResult {
+  symbol: string;
+  analyzed_at: string;
+  indicators: IndicatorResult[];
+  risk_metrics: RiskMetrics;
+  recommendation: SignalType;
+  signals: Signal[];
+  confidence: number;
+}
+
+export interface BollingerBands {
+  upper: Float64Array;
+  middle: Float64Array;
+  lower: Float64Array;
+}
+
+export interface MacdResult {
+  macd: Float64Array;
+  signal: Float64Array;
+  histogram: Float64Array;
+}
+
+export interface AllIndicators {
+  rsi: Float64Array;
+  sma_short: Float64Array;
+  sma_long: Float64Array;
+  macd: { line: Float64Array; signal: Float64Array; histogram: Float64Array };
+  bollinger: BollingerBands;
+}
+
+export interface StochasticResult {
+  k: Float64Array;
+  d: Float64Array;
+}
+
+export interface OhlcvIndicators {
+  atr: Float64Array;
+  vwap: Float64Array;
+  mfi: Float64Array;
+  stochastic: StochasticResult;
+}
+
+/** 收盘价数组，或带高低价/成交量的列对象 (OHLCV 指标需要) */
+export type IndicatorInput =
+  | Float64Array
+  | number[]
+  | { close: Float64Array | number[]; high?: Float64Array | number[]; low?: Float64Array | number[]; volume?: Float64Array | number[] };
+
+export interface IndicatorSpec {
+  name: string;
+  description: string;
+  /** [参数名, 默认值] */
+  params: [string, number][];
+  outputs: string[];
+  requires_ohlcv: boolean;
+}
+
+export interface AnalyzerOptions {
+  /** 小数位数，默认 4 */
+  precision?: number;
+  /** 分析使用的价格，默认 "close" */
+  priceSource?: "close" | "open" | "high" | "low" | "hl2" | "typical";
+  /** "rounded" 按 precision 取整 (默认)，"full" 保留完整精度 */
+  decimalMode?: "rounded" | "full";
+}
+
+export interface StreamConfig {
+  rsi_period?: number;
+  ema_period?: number;
+  macd_fast?: number;
+  macd_slow?: number;
+  macd_signal?: number;
+  bollinger_period?: number;
+  bollinger_std_dev?: number;
+}
+
+/** 预热期内尚无值的指标为 undefined */
+export interface IndicatorSnapshot {
+  price: number;
+  rsi?: number;
+  ema?: number;
+  macd?: number;
+  macd_signal?: number;
+  macd_histogram?: number;
+  bollinger_upper?: number;
+  bollinger_middle?: number;
+  bollinger_lower?: number;
+}
+
+export interface TickUpdate extends IndicatorSnapshot {
+  symbol: string;
+  /** 毫秒时间戳 */
+  timestamp: number;
+  volume: number;
+}
+
+export interface PerformanceMetrics {
+  timestamp: string;
+  /** 字节；浏览器取 performance.memory (仅 Chromium)，Node.js 取 process.memoryUsage()，无法获取时为 0 */
+  memory: { used: number; total: number; limit: number };
+  timing: { now: number };
+}
+
+/** 与 symbols 顺序对应的数组，或以股票代码为键的对象 */
+export type BatchData = MarketData[][] | Record<string, MarketData[]>;
+
+export interface BatchOptions {
+  /** 遇到第一个失败的标的即终止 (默认记录错误后继续) */
+  fail_fast?: boolean;
+  /** 每完成一个标的调用一次，回调中抛出的异常会被忽略 */
+  on_progress?: (progress: BatchProgress) => void;
+}
+
+export interface BatchProgress {
+  completed: number;
+  total: number;
+  /** 0 - 100 */
+  percent: number;
+  /** 刚完成 (成功或失败) 的标的 */
+  symbol: string;
+}
+
+export type ErrorCategory =
+  | "input"
+  | "data"
+  | "calculation"
+  | "network"
+  | "auth"
+  | "config"
+  | "platform"
+  | "cancelled"
+  | "internal";
+
+/** AlphaJsError 的可克隆形式，用于批量结果和 Worker 响应 */
+export interface ErrorInfo {
+  /** 如 "INVALID_INPUT"、"NETWORK_ERROR"、"CANCELLED" */
+  code: string;
+  category: ErrorCategory;
+  message: string;
+  retryable: boolean;
+}
+
+export interface BatchResult {
+  results: Record<string, AnalysisResult>;
+  errors: Record<string, ErrorInfo>;
+}
+
+export interface AnalysisJob {
+  jobId: number;
+  /** 被取消时以取消错误拒绝 */
+  result: Promise<BatchResult>;
+}
+
+export interface Position {
+  symbol: string;
+  /** 负数表示空头 */
+  quantity: number;
+}
+
+export interface PortfolioRiskConfig {
+  /** 默认 0.95 */
+  confidence?: number;
+  /** 默认 252 */
+  periods_per_year?: number;
+}
+
+export interface AssetContribution {
+  symbol: string;
+  market_value: number;
+  weight: number;
+  volatility: number;
+  /** 占组合方差的比例，各资产之和为 1 */
+  risk_contribution: number;
+}
+
+/** VaR / CVaR 为单周期损失比例，*_value 为对应金额 */
+export interface PortfolioRisk {
+  total_value: number;
+  volatility: number;
+  value_at_risk: number;
+  value_at_risk_value: number;
+  conditional_var: number;
+  conditional_var_value: number;
+  max_drawdown: number;
+  observations: number;
+  contributions: AssetContribution[];
+}
+
+export interface ScreenCriteria {
+  /** 如 "RSI(14) < 30 AND price > SMA(200)"，支持 AND / OR / NOT、括号，多输出指标用 MACD(12,26,9).histogram */
+  condition: string;
+  /** 排序依据的操作数，如 "RSI(14)"；省略时按各条件超出阈值的相对幅度之和排序 */
+  rank_by?: string;
+  /** 默认按得分降序 */
+  ascending?: boolean;
+  limit?: number;
+}
+
+export interface ScreenMatch {
+  symbol: string;
+  score: number;
+  /** 条件中各操作数的最新值，键如 "rsi(14)"、"price" */
+  values: Record<string, number>;
+}
+
+export interface ScreenResult {
+  matches: ScreenMatch[];
+  scanned: number;
+  /** 数据不足或计算失败的标的 */
+  skipped: string[];
+}
+
+export type ChartFormat = "lightweight-charts" | "echarts";
+
+/** lightweight-charts 格式，time 为 UTC 秒 */
+export interface LightweightSeries {
+  indicators: Record<string, { time: number; value: number }[]>;
+  candles: { time: number; open: number; high: number; low: number; close: number }[];
+}
+
+/** ECharts 时间轴格式，time 为毫秒，K 线为 [time, open, close, low, high] */
+export interface EChartsSeries {
+  indicators: Record<string, [number, number][]>;
+  candles: [number, number, number, number, number][];
+}
+
+export type ChartSeries = LightweightSeries | EChartsSeries;
+
+export type CandlePattern =
+  | "doji"
+  | "hammer"
+  | "shooting_star"
+  | "bullish_engulfing"
+  | "bearish_engulfing"
+  | "morning_star"
+  | "evening_star";
+
+/** index 与 timestamp 指形态的最后一根 K 线 */
+export interface PatternHit {
+  pattern: CandlePattern;
+  index: number;
+  timestamp: string;
+  direction: SignalType;
+  /** 0.0 - 1.0 */
+  confidence: number;
+}
+
+/** 表头名称 (不区分大小写) 或从 0 开始的列号 */
+export type CsvColumn = string | number;
+
+export interface CsvMapping {
+  /** 默认 "," */
+  delimiter?: string;
+  /** 默认 true；无表头且未配置 columns 时按 date,open,high,low,close,volume 顺序读取 */
+  has_header?: boolean;
+  /** 文件不含代码列时使用的股票代码 */
+  symbol?: string;
+  /** 未指定的列按常见表头名 (Date、Close、收盘价等) 自动识别 */
+  columns?: {
+    timestamp?: CsvColumn;
+    open?: CsvColumn;
+    high?: CsvColumn;
+    low?: CsvColumn;
+    close?: CsvColumn;
+    volume?: CsvColumn;
+    symbol?: CsvColumn;
+  };
+  /** strftime 格式，如 "%Y/%m/%d"；省略时自动识别 RFC 3339、常见日期格式及 Unix 秒/毫秒 */
+  date_format?: string;
+  /** 不带时区的时间所在时区，如 "Asia/Shanghai"，默认 UTC */
+  timezone?: string;
+  /** 跳过无法解析的行而不是报错 */
+  skip_invalid?: boolean;
+}
+
+export type NumericColumn = ArrayBuffer | Float64Array;
+
+export type WorkerRequest =
+  | { id?: unknown; type: "init"; payload?: AnalyzerOptions }
+  | {
+      id?: unknown;
+      type: "analyze";
+      payload: { symbol: string; timestamps: NumericColumn; prices: NumericColumn; volumes: NumericColumn };
+    }
+  | { id?: unknown; type: "dispose"; payload?: undefined };
+
+export type WorkerResponse =
+  | { id: unknown; ok: true; result: unknown }
+  | { id: unknown; ok: false; error: ErrorInfo };
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "MarketData[]")]
+    pub type MarketDataArray;
+
+    #[wasm_bindgen(typescript_type = "MarketData[] | undefined")]
+    pub type MarketDataArrayOpt;
+
+    #[wasm_bindgen(typescript_type = "CsvMapping | undefined")]
+    pub type CsvMappingJs;
+
+    #[wasm_bindgen(typescript_type = "AnalyzerOptions | number | undefined")]
+    pub type AnalyzerOptionsJs;
+
+    #[wasm_bindgen(typescript_type = "AnalysisResult")]
+    pub type AnalysisResultJs;
+
+    #[wasm_bindgen(typescript_type = "CandlePattern[] | undefined")]
+    pub type CandlePatternListJs;
+
+    #[wasm_bindgen(typescript_type = "PatternHit[]")]
+    pub type PatternHitArray;
+
+    #[wasm_bindgen(typescript_type = "AnalysisJob")]
+    pub type AnalysisJobJs;
+
+    #[wasm_bindgen(typescript_type = "BatchData")]
+    pub type BatchDataJs;
+
+    #[wasm_bindgen(typescript_type = "BatchOptions | undefined")]
+    pub type BatchOptionsJs;
+
+    #[wasm_bindgen(typescript_type = "BatchResult")]
+    pub type BatchResultJs;
+
+    #[wasm_bindgen(typescript_type = "ChartSeries")]
+    pub type ChartSeriesJs;
+
+    #[wasm_bindgen(typescript_type = "Position[]")]
+    pub type PositionArray;
+
+    #[wasm_bindgen(typescript_type = "Record<string, MarketData[]>")]
+    pub type PriceHistoriesJs;
+
+    #[wasm_bindgen(typescript_type = "PortfolioRiskConfig | undefined")]
+    pub type PortfolioRiskConfigJs;
+
+    #[wasm_bindgen(typescript_type = "PortfolioRisk")]
+    pub type PortfolioRiskJs;
+
+    #[wasm_bindgen(typescript_type = "ScreenCriteria | string")]
+    pub type ScreenCriteriaJs;
+
+    #[wasm_bindgen(typescript_type = "ScreenResult")]
+    pub type ScreenResultJs;
+
+    #[wasm_bindgen(typescript_type = "BollingerBands")]
+    pub type BollingerBandsJs;
+
+    #[wasm_bindgen(typescript_type = "MacdResult")]
+    pub type MacdResultJs;
+
+    #[wasm_bindgen(typescript_type = "AllIndicators")]
+    pub type AllIndicatorsJs;
+
+    #[wasm_bindgen(typescript_type = "StochasticResult")]
+    pub type StochasticResultJs;
+
+    #[wasm_bindgen(typescript_type = "OhlcvIndicators")]
+    pub type OhlcvIndicatorsJs;
+
+    #[wasm_bindgen(typescript_type = "IndicatorInput")]
+    pub type IndicatorInputJs;
+
+    #[wasm_bindgen(typescript_type = "Record<string, number> | undefined")]
+    pub type IndicatorParamsJs;
+
+    #[wasm_bindgen(typescript_type = "Record<string, Float64Array>")]
+    pub type IndicatorColumnsJs;
+
+    #[wasm_bindgen(typescript_type = "IndicatorSpec[]")]
+    pub type IndicatorSpecArray;
+
+    #[wasm_bindgen(typescript_type = "StreamConfig | undefined")]
+    pub type StreamConfigJs;
+
+    #[wasm_bindgen(typescript_type = "TickUpdate")]
+    pub type TickUpdateJs;
+
+    #[wasm_bindgen(typescript_type = "IndicatorSnapshot | undefined")]
+    pub type IndicatorSnapshotJs;
+
+    #[wasm_bindgen(typescript_type = "PerformanceMetrics")]
+    pub type PerformanceMetricsJs;
+
+    #[wasm_bindgen(typescript_type = "WorkerRequest")]
+    pub type WorkerRequestJs;
+
+    #[wasm_bindgen(typescript_type = "WorkerResponse")]
+    pub type WorkerResponseJs;
+}
@@ -0,0 +1,84 @@
+//! WASM 线性内存上的零拷贝结果视图
+//!
+//! 结果保存在 WASM 堆中，`view()` 直接返回覆盖这段内存的 `Float64Array`，省去整块拷贝。
+//! 生命周期约定：
+//! - 视图在 `free()` 之后失效，必须先复制出需要保留的数据
+//! - 任何可能分配内存的 WASM 调用都可能使线性内存增长并使已有视图失效 (长度变为 0)，
+//!   因此应在下一次调用分析器之前用完视图，或改用 `ptr()` + `wasmMemory()` 每次重新构造
+
+use alpha_core::indicators::registry::IndicatorOutput;
+use wasm_bindgen::prelude::*;
+
+use crate::error::invalid_input;
+use crate::types::{IndicatorInputJs, IndicatorParamsJs};
+use crate::WasmAnalyzer;
+
+/// 保存在 WASM 内存中的多列结果，需显式调用 `free()` 释放
+#[wasm_bindgen]
+pub struct ResultBuffers {
+    columns: IndicatorOutput,
+}
+
+impl ResultBuffers {
+    fn column(&self, name: &str) -> Result<&[f64], JsValue> {
+        self.columns.iter()
+            .find(|(column, _)| column == name)
+            .map(|(_, values)| values.as_slice())
+            .ok_or_else(|| invalid_input(format!("结果中没有列: {}", name)))
+    }
+}
+
+#[wasm_bindgen]
+impl ResultBuffers {
+    /// 输出列名
+    #[wasm_bindgen(getter)]
+    pub fn columns(&self) -> Vec<String> {
+        self.columns.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// 每列的元素个数
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.columns.first().map_or(0, |(_, values)| values.len())
+    }
+
+    /// 覆盖指定列的零拷贝视图，失效条件见模块说明
+    pub fn view(&self, column: &str) -> Result<js_sys::Float64Array, JsValue> {
+        let values = self.column(column)?;
+        // SAFETY: 视图只在 JS 侧使用；本对象存活且线性内存未增长期间底层数据不会移动或释放，
+        // 由调用方遵守模块说明中的约定
+        Ok(unsafe { js_sys::Float64Array::view(values) })
+    }
+
+    /// 指定列在线性内存中的字节偏移，可配合 `wasmMemory().buffer` 自行构造视图
+    pub fn ptr(&self, column: &str) -> Result<usize, JsValue> {
+        self.column(column).map(|values| values.as_ptr() as usize)
+    }
+
+    /// 复制指定列到独立的 `Float64Array`，不受生命周期约束
+    #[wasm_bindgen(js_name = copyColumn)]
+    pub fn copy_column(&self, column: &str) -> Result<js_sys::Float64Array, JsValue> {
+        self.column(column).map(js_sys::Float64Array::from)
+    }
+}
+
+/// WASM 线性内存对象，配合 `ResultBuffers.ptr()` 使用
+#[wasm_bindgen(js_name = wasmMemory)]
+pub fn wasm_memory() -> JsValue {
+    wasm_bindgen::memory()
+}
+
+#[wasm_bindgen]
+impl WasmAnalyzer {
+    /// 按名称计算指标，结果保留在 WASM 内存中供零拷贝读取，用完后需调用 `free()`
+    #[wasm_bindgen(js_name = calculateIndicatorBuffers)]
+    pub fn calculate_indicator_buffers(
+        &self,
+        name: &str,
+        input: &IndicatorInputJs,
+        params: &IndicatorParamsJs,
+    ) -> Result<ResultBuffers, JsValue> {
+        let columns = self.run_indicator(name, input, params)?;
+        Ok(ResultBuffers { columns })
+    }
+}
@@ -0,0 +1,107 @@
+//! Web Worker 消息入口
+//!
+//! 消息格式为 `{ id, type, payload }`，`type` 取 `init` / `analyze` / `dispose`；
+//! 数值列可直接传 `ArrayBuffer` (配合 `postMessage` 的 transfer 列表零拷贝移交给 Worker) 或 `Float64Array`。
+//! 响应格式为 `{ id, ok: true, result }` 或 `{ id, ok: false, error }`，配套脚本见 `worker.js`
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::convert::{market_data_from_columns, set, to_js};
+use crate::error::{invalid_input, ErrorInfo};
+use crate::types::{WorkerRequestJs, WorkerResponseJs};
+use crate::options::AnalyzerOptions;
+use crate::WasmAnalyzer;
+
+/// Worker 内的分析宿主，负责解析消息并分发到分析器
+#[wasm_bindgen]
+pub struct WorkerHost {
+    analyzer: Option<WasmAnalyzer>,
+}
+
+#[wasm_bindgen]
+impl WorkerHost {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WorkerHost {
+        WorkerHost { analyzer: None }
+    }
+
+    /// 处理一条 Worker 消息，总是返回响应对象而不是抛出异常
+    #[wasm_bindgen(js_name = handleMessage)]
+    pub async fn handle_message(&mut self, message: WorkerRequestJs) -> WorkerResponseJs {
+        let message: JsValue = message.into();
+        let id = get(&message, "id").unwrap_or(JsValue::NULL);
+        let result = self.dispatch(&message).await;
+
+        let response = js_sys::Object::new();
+        let _ = set(&response, "id", &id);
+        let _ = match result {
+            Ok(value) => set(&response, "ok", JsValue::TRUE).and_then(|_| set(&response, "result", &value)),
+            // 错误需可被 postMessage 结构化克隆，转换为普通对象
+            Err(err) => set(&response, "ok", JsValue::FALSE)
+                .and_then(|_| to_js(&ErrorInfo::from_js(&err)))
+                .and_then(|info| set(&response, "error", &info)),
+        };
+        response.unchecked_into()
+    }
+
+    async fn dispatch(&mut self, message: &JsValue) -> Result<JsValue, JsValue> {
+        let kind = get(message, "type")?
+            .as_string()
+            .ok_or_else(|| invalid_input("消息缺少 type 字段"))?;
+        let payload = get(message, "payload")?;
+
+        match kind.as_str() {
+            "init" => {
+                self.analyzer = Some(WasmAnalyzer::with_options(AnalyzerOptions::from_js_value(&payload)?));
+                to_js(&true)
+            }
+            "analyze" => {
+                let analyzer = self.analyzer.as_ref()
+                    .ok_or_else(|| invalid_input("分析器尚未初始化，请先发送 init 消息"))?;
+                let symbol = get(&payload, "symbol")?.as_string().unwrap_or_default();
+                let market_data = market_data_from_columns(
+                    &symbol,
+                    &f64_column(&payload, "timestamps")?,
+                    &f64_column(&payload, "prices")?,
+                    &f64_column(&payload, "volumes")?,
+                )?;
+                analyzer.analyze(market_data).await
+            }
+            "dispose" => {
+                self.analyzer = None;
+                to_js(&true)
+            }
+            other => Err(invalid_input(format!("未知消息类型: {}", other))),
+        }
+    }
+}
+
+impl Default for WorkerHost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn get(target: &JsValue, key: &str) -> Result<JsValue, JsValue> {
+    if target.is_undefined() || target.is_null() {
+        return Ok(JsValue::UNDEFINED);
+    }
+    js_sys::Reflect::get(target, &JsValue::from_str(key))
+}
+
+/// 读取数值列，支持 `ArrayBuffer` 和 `Float64Array`
+fn f64_column(payload: &JsValue, key: &str) -> Result<Vec<f64>, JsValue> {
+    let value = get(payload, key)?;
+
+    if let Some(array) = value.dyn_ref::<js_sys::Float64Array>() {
+        Ok(array.to_vec())
+    } else if let Some(buffer) = value.dyn_ref::<js_sys::ArrayBuffer>() {
+        if buffer.byte_length() % 8 != 0 {
+            return Err(invalid_input(format!("{} 的字节长度不是 8 的倍数", key)));
+        }
+        Ok(js_sys::Float64Array::new(buffer).to_vec())
+    } else {
+        Err(invalid_input(format!("{} 必须是 ArrayBuffer 或 Float64Array", key)))
+    }
+}
@@ -0,0 +1,19 @@
+// Alpha Finance 分析 Worker
+//
+// 用法:
+//   const worker = new Worker(new URL('./worker.js', import.meta.url), { type: 'module' });
+//   worker.postMessage({ id: 1, type: 'init', payload: { precision: 4 } });
+//   worker.postMessage(
+//     { id: 2, type: 'analyze', payload: { symbol: 'AAPL', timestamps, prices, volumes } },
+//     [timestamps.buffer, prices.buffer, volumes.buffer],
+//   );
+//   worker.onmessage = ({ data }) => data.ok ? resolve(data.result) : reject(data.error);
+
+import init, { WorkerHost } from './pkg/alpha_wasm_analyzer.js';
+
+const ready = init().then(() => new WorkerHost());
+
+self.onmessage = async (event) => {
+  const host = await ready;
+  self.postMessage(await host.handleMessage(event.data));
+};